@@ -5,10 +5,41 @@
 //!
 //! On web, tokio-with-wasm runs async tasks on the JavaScript event loop
 //! and can spawn blocking tasks to web workers.
+//!
+//! Applications should spawn through here rather than calling tokio
+//! directly. On native, tasks go to the runtime of the caller's context
+//! (the one the entry points enter) or, when there is none, e.g. in unit
+//! tests, to a runtime created on first use.
+
+// Re-export sync primitives and channels - these work on both platforms since
+// they're just async primitives that work with any executor
+pub use tokio::sync::{Mutex, RwLock, Semaphore, mpsc, oneshot};
+
+/// Runtime used when spawning outside of any runtime context (native only)
+#[cfg(not(target_arch = "wasm32"))]
+static FALLBACK_RUNTIME: once_cell::sync::OnceCell<tokio::runtime::Runtime> =
+    once_cell::sync::OnceCell::new();
 
-// Re-export sync primitives - these work on both platforms since they're
-// just async primitives that work with any executor
-pub use tokio::sync::{RwLock, Semaphore};
+/// Handle of the runtime tasks are spawned on (native only)
+///
+/// This is the runtime of the current context if there is one, otherwise a
+/// multi-threaded runtime created the first time it is needed and kept for
+/// the rest of the process.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn handle() -> tokio::runtime::Handle {
+    tokio::runtime::Handle::try_current().unwrap_or_else(|_| {
+        FALLBACK_RUNTIME
+            .get_or_init(|| {
+                tracing::debug!("No tokio runtime in context, creating one");
+                tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .expect("failed to create a tokio runtime")
+            })
+            .handle()
+            .clone()
+    })
+}
 
 /// Spawn an async task.
 ///
@@ -22,9 +53,10 @@ where
 {
     // Wrap the provided future in a profiling scope so spawned tasks are easier
     // to identify in profiling traces. When profiling is disabled this is a no-op.
+    let handle = handle();
     #[cfg(feature = "profiling")]
     {
-        tokio::spawn(async move {
+        handle.spawn(async move {
             // Attach a tag describing the spawned future type so profiler traces
             // can be filtered by task kind without emitting separate events.
             profiling::scope!(
@@ -36,7 +68,7 @@ where
     }
     #[cfg(not(feature = "profiling"))]
     {
-        tokio::spawn(future)
+        handle.spawn(future)
     }
 }
 
@@ -65,6 +97,50 @@ where
     }
 }
 
+/// Run a blocking closure without stalling async tasks.
+///
+/// On native: Uses tokio's blocking thread pool
+/// On web: Uses tokio-with-wasm, which runs the closure in a web worker
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    handle().spawn_blocking(f)
+}
+
+/// Run a blocking closure without stalling async tasks.
+///
+/// On native: Uses tokio's blocking thread pool
+/// On web: Uses tokio-with-wasm, which runs the closure in a web worker
+#[cfg(target_arch = "wasm32")]
+pub fn spawn_blocking<F, R>(f: F) -> tokio_with_wasm::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    tokio_with_wasm::task::spawn_blocking(f)
+}
+
+/// Wait for `duration` without blocking the thread.
+///
+/// On native: Uses the timer of tokio's runtime the task runs on
+/// On web: Uses tokio-with-wasm's timer on top of `setTimeout`
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn sleep(duration: std::time::Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Wait for `duration` without blocking the thread.
+///
+/// On native: Uses the timer of tokio's runtime the task runs on
+/// On web: Uses tokio-with-wasm's timer on top of `setTimeout`
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: std::time::Duration) {
+    tokio_with_wasm::time::sleep(duration).await
+}
+
 /// Yield execution to allow other tasks to run.
 ///
 /// On native: Uses tokio's yield_now
@@ -89,6 +165,7 @@ pub async fn yield_now() {
 }
 
 /// Check if we're running inside a tokio runtime context (native only).
+/// Spawning doesn't need one (see [`handle`]); blocking on tokio types does.
 /// On web, this always returns true since tasks run on the JS event loop.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn in_runtime_context() -> bool {
@@ -22,6 +22,17 @@ type QueueEntry = (String, Vec<u8>);
 type Queue = Vec<QueueEntry>;
 static QUEUE: Lazy<Mutex<Queue>> = Lazy::new(|| Mutex::new(Vec::new()));
 
+/// How a save started by [`save_native_file`] ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveOutcome {
+    /// Written where the user chose
+    Saved,
+    /// The dialog was closed without choosing a place
+    Cancelled,
+    /// Writing failed, with the reason
+    Failed(String),
+}
+
 #[cfg(target_os = "android")]
 pub static ANDROID_APP: Lazy<Mutex<Option<AndroidApp>>> = Lazy::new(|| Mutex::new(None));
 
@@ -30,7 +41,8 @@ pub static PERMISSION_REQUESTED: std::sync::atomic::AtomicBool =
     std::sync::atomic::AtomicBool::new(false);
 
 mod rfd {
-    use super::QUEUE;
+    use super::{QUEUE, SaveOutcome};
+    use crate::async_runtime::oneshot;
 
     /// Async implementation that uses rfd's AsyncFileDialog.
     /// Works on both native (desktop) and wasm (web) targets.
@@ -82,6 +94,36 @@ mod rfd {
         std::mem::drop(crate::async_runtime::spawn(fut));
         Ok(())
     }
+
+    /// Ask the user where to save `bytes` (suggesting `file_name`) and write them there.
+    /// On web the browser downloads the file instead.
+    ///
+    /// The dialog runs in the background; the returned receiver gets how it
+    /// ended once it has. Failures are logged too, so it can be dropped.
+    pub fn save_file(file_name: &str, bytes: Vec<u8>) -> oneshot::Receiver<SaveOutcome> {
+        let file_name = file_name.to_string();
+        let (sender, receiver) = oneshot::channel();
+        let fut = async move {
+            let outcome = match rfd::AsyncFileDialog::new()
+                .set_file_name(&file_name)
+                .save_file()
+                .await
+            {
+                None => SaveOutcome::Cancelled,
+                Some(handle) => match handle.write(&bytes).await {
+                    Ok(()) => SaveOutcome::Saved,
+                    Err(e) => {
+                        tracing::error!("Failed to save {}: {}", file_name, e);
+                        SaveOutcome::Failed(e.to_string())
+                    }
+                },
+            };
+            let _ = sender.send(outcome);
+        };
+
+        std::mem::drop(crate::async_runtime::spawn(fut));
+        receiver
+    }
 }
 
 pub(crate) mod rust {
@@ -262,6 +304,7 @@ pub(crate) mod rust {
 }
 
 pub use rfd::open_file_picker as open_native_file_picker;
+pub use rfd::save_file as save_native_file;
 pub use rust::open_file_picker as open_rust_file_picker;
 pub use rust::render_file_dialog as render_rust_file_dialog;
 
@@ -128,12 +128,21 @@ macro_rules! eframe_app_lib {
     };
 }
 
+/// Defines `main` for native desktop builds and the web entry point.
+///
+/// An optional `before_start` function runs first, before logging is set up
+/// and before any thread is spawned, for work that must happen while the
+/// process is single-threaded.
 #[macro_export]
 macro_rules! eframe_app_main {
     ($app_name:expr, $app_creator:expr) => {
+        $crate::eframe_app_main!($app_name, $app_creator, before_start: || {});
+    };
+    ($app_name:expr, $app_creator:expr, before_start: $before_start:expr) => {
         fn main() {
             #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
             {
+                ($before_start)();
                 $crate::run::desktop_main($app_name, $app_creator);
             };
         }
@@ -141,6 +150,7 @@ macro_rules! eframe_app_main {
         #[cfg(target_arch = "wasm32")]
         #[wasm_bindgen::prelude::wasm_bindgen(start)]
         fn web_main() {
+            ($before_start)();
             $crate::web::set_app_creator($app_creator);
         }
     };
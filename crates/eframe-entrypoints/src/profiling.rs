@@ -13,9 +13,11 @@ profiling feature is compiled in. There are two implementations:
 
 Top-level API (always available):
 - `setup_logging_and_profiling()`
+- `set_autostart(bool)`
 - `start_profiling()`
 - `stop_profiling()`
 - `is_profiling_enabled() -> bool`
+- `is_available() -> bool`
 - `profiling_ui(&mut egui::Ui)`
 */
 
@@ -66,6 +68,21 @@ mod inner {
     #[cfg(feature = "profiling")]
     static PROFILING_STATE: Mutex<Option<ProfilingState>> = Mutex::new(None);
 
+    #[cfg(feature = "profiling")]
+    /// Whether `ENABLE_PROFILING` starts a session, see [`set_autostart`]
+    static AUTOSTART: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+
+    /// Let `ENABLE_PROFILING` start a session at startup (the default) or not,
+    /// e.g. for a launch that should avoid anything optional.
+    ///
+    /// Takes effect in [`setup_logging_and_profiling`], so call it before that.
+    pub fn set_autostart(enabled: bool) {
+        #[cfg(feature = "profiling")]
+        AUTOSTART.store(enabled, std::sync::atomic::Ordering::Relaxed);
+        #[cfg(not(feature = "profiling"))]
+        let _ = enabled;
+    }
+
     #[cfg(feature = "profiling")]
     fn profiling_state() -> &'static Mutex<Option<ProfilingState>> {
         &PROFILING_STATE
@@ -121,10 +138,14 @@ mod inner {
 
             registry.init();
 
-            // Optional auto-start if environment variable set
+            // Optional auto-start if environment variable set, unless the app turned it off
             if std::env::var("ENABLE_PROFILING").is_ok() {
-                tracing::info!("ENABLE_PROFILING set - starting profiling session at startup");
-                start_profiling();
+                if !AUTOSTART.load(std::sync::atomic::Ordering::Relaxed) {
+                    tracing::info!("ENABLE_PROFILING set but auto-start is turned off");
+                } else {
+                    tracing::info!("ENABLE_PROFILING set - starting profiling session at startup");
+                    start_profiling();
+                }
             }
 
             tracing::info!("Tracing initialized with reloadable chrome profiling layer (debug)");
@@ -407,6 +428,11 @@ mod inner {
         }
     }
 
+    /// Whether this build can profile at all, i.e. has the `profiling` feature
+    pub fn is_available() -> bool {
+        cfg!(feature = "profiling")
+    }
+
     pub fn is_profiling_enabled() -> bool {
         #[cfg(feature = "profiling")]
         {
@@ -468,6 +494,6 @@ mod inner {
 
 // Re-export a stable API surface regardless of which `inner` module was compiled.
 pub use inner::{
-    is_profiling_enabled, profiling_ui, setup_logging_and_profiling, start_profiling,
-    stop_profiling,
+    is_available, is_profiling_enabled, profiling_ui, set_autostart, setup_logging_and_profiling,
+    start_profiling, stop_profiling,
 };
@@ -48,12 +48,9 @@ pub fn desktop_main<F>(app_name: &str, app_creator: F)
 where
     F: FnOnce(&eframe::CreationContext<'_>) -> Box<dyn eframe::App> + Send + 'static,
 {
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap();
-
-    let _guard = rt.enter();
+    // Spawns from any thread land on this runtime too, even outside its context
+    let runtime = crate::async_runtime::handle();
+    let _guard = runtime.enter();
     native_main(app_name, app_creator);
 }
 
@@ -75,11 +72,8 @@ pub fn android_main(
     }
 
     // Ensure a tokio runtime is available for async tasks.
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap();
-    let _guard = rt.enter();
+    let runtime = crate::async_runtime::handle();
+    let _guard = runtime.enter();
 
     #[cfg(target_os = "android")]
     {
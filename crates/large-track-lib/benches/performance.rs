@@ -4,7 +4,7 @@
 //!
 //! Reduced benchmark suite for faster iteration during optimization.
 
-use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
 use geo::{Coord, Point, Rect};
 use gpx::{Gpx, Track, TrackSegment, Waypoint};
 use large_track_lib::{Config, RouteCollection};
@@ -124,6 +124,45 @@ fn bench_query_performance(c: &mut Criterion) {
     group.finish();
 }
 
+/// The first frame after zooming in from an overview, with the cache cold at the new level
+///
+/// `lazy` simplifies every visible segment within the frame; `progressive`
+/// reuses the overview's level and refines in the background, so the gap
+/// between the two is the worst-frame time saved right after a zoom.
+fn bench_zoom_in_first_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("zoom_in_first_frame");
+    group.sample_size(20);
+
+    let gpx = generate_gpx_track(200_000, 51.5, -0.1);
+    let overview = create_viewport(50.0, -2.0, 53.0, 1.0);
+    let zoomed = create_viewport(51.50, -0.12, 51.55, -0.05);
+
+    for (name, progressive_refinement) in [("lazy", false), ("progressive", true)] {
+        let config = Config {
+            progressive_refinement,
+            ..Config::default()
+        };
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut collection = RouteCollection::new(config.clone());
+                    collection.add_route(gpx.clone()).unwrap();
+                    collection.query_visible(overview, SCREEN_SIZE);
+                    collection
+                },
+                |collection| {
+                    let segments = collection.query_visible(zoomed, SCREEN_SIZE);
+                    // Dropped outside the measurement, with any refinement still running
+                    (segments, collection)
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_many_routes(c: &mut Criterion) {
     let mut group = c.benchmark_group("many_routes");
     group.sample_size(20);
@@ -162,6 +201,17 @@ fn bench_construction(c: &mut Criterion) {
         });
     });
 
+    // One large file: conversion to Web Mercator dominates before insertion
+    let large = generate_gpx_track(1_000_000, 51.5, -0.1);
+    group.throughput(Throughput::Elements(1_000_000));
+    group.bench_function("single_1m", |b| {
+        let config = Config::default();
+        b.iter(|| {
+            let mut collection = RouteCollection::new(config.clone());
+            collection.add_route(large.clone()).unwrap();
+        });
+    });
+
     group.finish();
 }
 
@@ -191,6 +241,7 @@ fn bench_collection_info(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_query_performance,
+    bench_zoom_in_first_frame,
     bench_many_routes,
     bench_construction,
     bench_collection_info,
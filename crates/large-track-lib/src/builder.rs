@@ -0,0 +1,298 @@
+//! Building routes from plain coordinates
+//!
+//! Callers that don't read GPX files (e.g. a server pulling points from a
+//! database) can assemble a [`Route`] here instead of constructing a
+//! [`gpx::Gpx`] by hand. [`Route::new`] feeds parsed files through the same
+//! builder, so queries, statistics and export don't tell the two apart.
+
+use crate::{DataError, Result, Route, utils};
+use geo::Rect;
+use std::sync::Arc;
+
+/// One recorded position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackPoint {
+    /// Latitude in degrees (WGS84)
+    pub lat: f64,
+    /// Longitude in degrees (WGS84)
+    pub lon: f64,
+    /// Elevation in meters
+    pub ele: Option<f64>,
+    /// When the position was recorded
+    pub time: Option<time::OffsetDateTime>,
+}
+
+impl TrackPoint {
+    /// A position without elevation or time
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self {
+            lat,
+            lon,
+            ele: None,
+            time: None,
+        }
+    }
+
+    fn to_waypoint(self) -> gpx::Waypoint {
+        let mut waypoint = gpx::Waypoint::new(geo::Point::new(self.lon, self.lat));
+        waypoint.elevation = self.ele;
+        waypoint.time = self.time.map(gpx::Time::from);
+        waypoint
+    }
+}
+
+/// What a [`Route`] caches about its points, computed once by [`RouteBuilder::build`]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RouteMetadata {
+    /// Bounding box in Web Mercator meters
+    pub bounding_box: Rect<f64>,
+    pub total_points: usize,
+    /// Total distance in meters
+    pub total_distance: f64,
+    /// (min, max) elevation in meters, `None` if no point has elevation data
+    pub elevation_range: Option<(f64, f64)>,
+}
+
+/// Assembles a route segment by segment
+///
+/// ```
+/// use large_track_lib::{RouteBuilder, TrackPoint};
+///
+/// let route = RouteBuilder::new("Morning run")
+///     .add_segment([TrackPoint::new(45.0, 7.0), TrackPoint::new(45.001, 7.001)])
+///     .build()
+///     .unwrap();
+/// assert_eq!(route.total_points(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RouteBuilder {
+    gpx: gpx::Gpx,
+}
+
+impl RouteBuilder {
+    /// Start a route whose only track is called `name`
+    pub fn new(name: impl Into<String>) -> Self {
+        let mut track = gpx::Track::new();
+        track.name = Some(name.into());
+        Self::from_gpx(gpx::Gpx {
+            version: gpx::GpxVersion::Gpx11,
+            tracks: vec![track],
+            ..Default::default()
+        })
+    }
+
+    /// Start from a whole GPX document, e.g. one read from a file
+    ///
+    /// Segments added afterwards go to its last track.
+    pub fn from_gpx(gpx: gpx::Gpx) -> Self {
+        Self { gpx }
+    }
+
+    /// Append a segment; track lines never join points of different segments
+    pub fn add_segment(mut self, points: impl IntoIterator<Item = TrackPoint>) -> Self {
+        let mut segment = gpx::TrackSegment::new();
+        segment.points = points.into_iter().map(TrackPoint::to_waypoint).collect();
+        if self.gpx.tracks.is_empty() {
+            self.gpx.tracks.push(gpx::Track::new());
+        }
+        if let Some(track) = self.gpx.tracks.last_mut() {
+            track.segments.push(segment);
+        }
+        self
+    }
+
+    /// Validate the points and compute the route metadata
+    pub fn build(self) -> Result<Arc<Route>> {
+        let metadata = Self::compute_metadata(&self.gpx)?;
+        Ok(Route::with_metadata(self.gpx, metadata))
+    }
+
+    /// Compute all metadata in a single pass over the data
+    fn compute_metadata(gpx: &gpx::Gpx) -> Result<RouteMetadata> {
+        // Profiling scope for metadata computation (bounding box, counts, distance).
+        // This is useful to separate parsing time from metadata computation in traces.
+        #[cfg(feature = "profiling")]
+        profiling::scope!("route::compute_metadata");
+        let mut min_x = f64::INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+
+        let mut total_points: usize = 0;
+        let mut total_distance: f64 = 0.0;
+        let mut elevation_range: Option<(f64, f64)> = None;
+        let mut found_valid_point = false;
+
+        for (track_idx, track) in gpx.tracks.iter().enumerate() {
+            for (segment_idx, segment) in track.segments.iter().enumerate() {
+                let points = &segment.points;
+                let segment_len = points.len();
+                total_points += segment_len;
+
+                let mut prev_waypoint: Option<&gpx::Waypoint> = None;
+
+                for (point_idx, waypoint) in points.iter().enumerate() {
+                    // GPX parsing accepts "NaN" and "inf", which would poison every
+                    // bounding box and simplification run the segment takes part in
+                    let coordinate = waypoint.point();
+                    if !coordinate.x().is_finite() || !coordinate.y().is_finite() {
+                        return Err(DataError::InvalidGeometry(format!(
+                            "Non-finite coordinate at track {} segment {} point {}",
+                            track_idx, segment_idx, point_idx
+                        )));
+                    }
+
+                    let point = utils::waypoint_to_mercator(waypoint);
+
+                    if !utils::is_valid_mercator(&point) {
+                        tracing::warn!(
+                            "Skipping point outside Web Mercator bounds: ({}, {})",
+                            waypoint.point().y(),
+                            waypoint.point().x()
+                        );
+                        prev_waypoint = None; // Break distance chain
+                        continue;
+                    }
+
+                    // Update bounding box
+                    min_x = min_x.min(point.x());
+                    min_y = min_y.min(point.y());
+                    max_x = max_x.max(point.x());
+                    max_y = max_y.max(point.y());
+                    found_valid_point = true;
+
+                    // Update elevation range
+                    if let Some(elevation) = waypoint.elevation.filter(|e| e.is_finite()) {
+                        elevation_range = Some(match elevation_range {
+                            Some((min, max)) => (min.min(elevation), max.max(elevation)),
+                            None => (elevation, elevation),
+                        });
+                    }
+
+                    // Compute distance from previous point
+                    if let Some(prev) = prev_waypoint {
+                        total_distance += Route::haversine_distance(prev, waypoint);
+                    }
+                    prev_waypoint = Some(waypoint);
+                }
+            }
+        }
+
+        if total_points == 0 {
+            return Err(DataError::EmptyRoute);
+        }
+        if !found_valid_point {
+            return Err(DataError::InvalidGeometry(
+                "No valid points in route".to_string(),
+            ));
+        }
+
+        Ok(RouteMetadata {
+            bounding_box: Rect::new(
+                geo::Coord { x: min_x, y: min_y },
+                geo::Coord { x: max_x, y: max_y },
+            ),
+            total_points,
+            total_distance,
+            elevation_range,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, DataError, RouteCollection, utils};
+
+    fn points() -> Vec<TrackPoint> {
+        let start = time::OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        (0..50)
+            .map(|i| TrackPoint {
+                lat: 45.0 + (i as f64 * 0.3).sin() * 0.01,
+                lon: 7.0 + i as f64 * 0.001,
+                ele: Some(200.0 + i as f64),
+                time: Some(start + time::Duration::seconds(i)),
+            })
+            .collect()
+    }
+
+    /// The same points as a GPX document, as if read from a file
+    fn gpx_from(points: &[TrackPoint]) -> gpx::Gpx {
+        let mut segment = gpx::TrackSegment::new();
+        for point in points {
+            let mut waypoint = gpx::Waypoint::new(geo::Point::new(point.lon, point.lat));
+            waypoint.elevation = point.ele;
+            waypoint.time = point.time.map(gpx::Time::from);
+            segment.points.push(waypoint);
+        }
+        let mut track = gpx::Track::new();
+        track.name = Some("Run".to_string());
+        track.segments.push(segment);
+        gpx::Gpx {
+            version: gpx::GpxVersion::Gpx11,
+            tracks: vec![track],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn built_route_matches_the_gpx_route() {
+        let built = RouteBuilder::new("Run")
+            .add_segment(points())
+            .build()
+            .unwrap();
+        let parsed = Route::new(gpx_from(&points())).unwrap();
+
+        // Export: the stored document is the same one a file would give
+        assert_eq!(built.gpx_data(), parsed.gpx_data());
+        let export = |route: &Route| {
+            let mut bytes = Vec::new();
+            gpx::write(route.gpx_data(), &mut bytes).unwrap();
+            bytes
+        };
+        assert_eq!(export(&built), export(&parsed));
+
+        // Stats
+        assert_eq!(built.total_points(), parsed.total_points());
+        assert_eq!(built.total_distance(), parsed.total_distance());
+        assert_eq!(built.elevation_range(), parsed.elevation_range());
+
+        // Queries
+        let mut from_builder = RouteCollection::new(Config::default());
+        from_builder.add_built_route(built).unwrap();
+        let mut from_gpx = RouteCollection::new(Config::default());
+        from_gpx.add_route(gpx_from(&points())).unwrap();
+
+        let min = utils::wgs84_to_mercator(44.9, 6.9);
+        let max = utils::wgs84_to_mercator(45.1, 7.1);
+        let viewport = geo::Rect::new(min.0, max.0);
+        let indices = |collection: &RouteCollection| -> Vec<Vec<usize>> {
+            collection
+                .query_visible(viewport, (800.0, 600.0))
+                .iter()
+                .flat_map(|segment| &segment.parts)
+                .map(|part| part.simplified_indices.clone())
+                .collect()
+        };
+        assert!(!indices(&from_builder).is_empty());
+        assert_eq!(indices(&from_builder), indices(&from_gpx));
+        let (built_info, parsed_info) = (from_builder.get_info(), from_gpx.get_info());
+        assert_eq!(built_info.total_points, parsed_info.total_points);
+        assert_eq!(
+            built_info.total_distance_meters,
+            parsed_info.total_distance_meters
+        );
+        assert_eq!(built_info.max_elevation, parsed_info.max_elevation);
+    }
+
+    #[test]
+    fn builder_rejects_what_route_new_rejects() {
+        let empty = RouteBuilder::new("Empty").add_segment([]).build();
+        assert!(matches!(empty, Err(DataError::EmptyRoute)));
+
+        let invalid = RouteBuilder::new("NaN")
+            .add_segment([TrackPoint::new(f64::NAN, 7.0)])
+            .build();
+        assert!(matches!(invalid, Err(DataError::InvalidGeometry(_))));
+    }
+}
@@ -3,15 +3,45 @@
 //! This module provides the high-level API for managing multiple GPX routes,
 //! building spatial indices, and executing viewport queries.
 
-use crate::{Quadtree, Result, Route, SimplifiedSegment, utils};
+use crate::{
+    Climb, CorridorReport, DEFAULT_CHUNKING_AGGRESSIVENESS, DEFAULT_MAX_SEGMENT_POINTS, DataError,
+    Quadtree, RawPointRef, Result, Route, RouteDiff, SegmentPart, SimplifiedSegment, utils,
+};
 
-use geo::Rect;
+use geo::{Coord, LineString, Rect, SimplifyIdx};
 use rayon::prelude::*;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Approximate in-memory bytes per byte of GPX text once a file is loaded
+///
+/// Calibrated against [`RouteCollection::memory_estimate`] for tracks with
+/// elevation and timestamps (see the calibration test). Parsed waypoints are
+/// much larger than their XML, so this is well above 1. Useful for warning
+/// before loading files that would not fit in memory.
+pub const ESTIMATED_MEMORY_PER_GPX_BYTE: f64 = 5.0;
+
+/// Estimated memory needed to load GPX files totalling `file_bytes`
+pub fn estimate_memory_for_gpx_bytes(file_bytes: u64) -> u64 {
+    (file_bytes as f64 * ESTIMATED_MEMORY_PER_GPX_BYTE) as u64
+}
+
+/// Parse the contents of a GPX file
+///
+/// Files with nothing but whitespace (and maybe a byte order mark) fail with
+/// [`DataError::EmptyFile`] rather than the parser's less helpful complaint
+/// about missing XML.
+pub fn parse_gpx(bytes: &[u8]) -> Result<gpx::Gpx> {
+    let content = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if content.iter().all(u8::is_ascii_whitespace) {
+        return Err(DataError::EmptyFile);
+    }
+    Ok(gpx::read(bytes)?)
+}
+
 /// Configuration for the route collection
 ///
 /// The LOD (Level of Detail) system automatically adjusts simplification based on the
@@ -32,6 +62,83 @@ pub struct Config {
     pub bias: f64,
     /// Subdivision threshold for quadtree nodes (currently unused, reserved for future use)
     pub max_points_per_node: usize,
+    /// Simplify every segment at every LOD level as routes are added (default false).
+    /// Queries then skip simplification entirely, at the cost of slower loading
+    /// and keeping index lists for all levels in memory. Intended for static
+    /// datasets; see [`Quadtree::precompute_lod`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub precompute_lod: bool,
+    /// How eagerly segments are split at quadtree node boundaries, in `[0, 1]`
+    /// (default [`DEFAULT_CHUNKING_AGGRESSIVENESS`]).
+    /// Higher values create more, smaller chunks: panning at high zoom touches
+    /// fewer off-screen points, but every query handles more segments. Lower
+    /// values keep segments whole, which suits zoomed-out overviews.
+    #[cfg_attr(feature = "serde", serde(default = "default_chunking_aggressiveness"))]
+    pub chunking_aggressiveness: f64,
+    /// Extend each clipped run to its neighboring point outside the viewport
+    /// (default true), so lines reach the viewport edge instead of stopping at
+    /// the last visible point. Lines that cross the viewport without a point
+    /// inside it are only drawn with this on. Applies at query time; see
+    /// [`RouteCollection::set_boundary_context`].
+    #[cfg_attr(feature = "serde", serde(default = "default_boundary_context"))]
+    pub boundary_context: bool,
+    /// Answer queries with the nearest coarser cached level while the exact
+    /// one is simplified in the background (default false), which keeps the
+    /// first frames after a zoom in cheap. Needs a rayon pool that runs spawned
+    /// jobs, so leave it off where rayon has no threads (e.g. plain wasm). See
+    /// [`Quadtree::query_progressive`]; applies at query time like
+    /// [`Config::boundary_context`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub progressive_refinement: bool,
+    /// Put back points where tracks turn by at least this many degrees after
+    /// simplifying (default `None`), so switchbacks keep their shape instead
+    /// of being cut straight across. [`crate::DEFAULT_SHARP_TURN_DEGREES`] suits most
+    /// tracks. See [`Quadtree::set_sharp_turn_threshold`];
+    /// [`RouteCollection::set_sharp_turn_threshold`] changes it without a rebuild.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sharp_turn_threshold: Option<f64>,
+    /// Keep elevation peaks and valleys standing out by at least this many
+    /// meters through simplification (default `None`), so summits and valley
+    /// floors stay where they are at every zoom. Found once per route while
+    /// indexing, so changing it needs a rebuild.
+    /// [`crate::DEFAULT_EXTREMA_PROMINENCE_METERS`] suits most tracks. See
+    /// [`Quadtree::anchor_elevation_extrema`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub elevation_extrema_prominence: Option<f64>,
+    /// Longest segment, in points, indexed as one piece (default
+    /// [`DEFAULT_MAX_SEGMENT_POINTS`]). Longer segments are split into
+    /// consecutive pieces sharing their boundary point, so they still draw
+    /// as one line and map back to their original point indices; see
+    /// [`Config::segments_to_split`].
+    #[cfg_attr(feature = "serde", serde(default = "default_max_segment_points"))]
+    pub max_segment_points: usize,
+}
+
+impl Config {
+    /// Number of track segments in `gpx` that indexing splits at
+    /// [`Config::max_segment_points`], for telling the user why
+    pub fn segments_to_split(&self, gpx: &gpx::Gpx) -> usize {
+        gpx.tracks
+            .iter()
+            .flat_map(|track| &track.segments)
+            .filter(|segment| segment.points.len() > self.max_segment_points.max(2))
+            .count()
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_chunking_aggressiveness() -> f64 {
+    DEFAULT_CHUNKING_AGGRESSIVENESS
+}
+
+#[cfg(feature = "serde")]
+fn default_boundary_context() -> bool {
+    true
+}
+
+#[cfg(feature = "serde")]
+fn default_max_segment_points() -> usize {
+    DEFAULT_MAX_SEGMENT_POINTS
 }
 
 #[cfg_attr(feature = "profiling", profiling::all_functions)]
@@ -47,6 +154,13 @@ impl Default for Config {
             ),
             bias: 1.0,
             max_points_per_node: 100,
+            precompute_lod: false,
+            chunking_aggressiveness: DEFAULT_CHUNKING_AGGRESSIVENESS,
+            boundary_context: true,
+            progressive_refinement: false,
+            sharp_turn_threshold: None,
+            elevation_extrema_prominence: None,
+            max_segment_points: DEFAULT_MAX_SEGMENT_POINTS,
         }
     }
 }
@@ -61,6 +175,10 @@ pub struct CollectionInfo {
     pub total_points: usize,
     /// Total distance in meters
     pub total_distance_meters: f64,
+    /// Lowest elevation in meters (None if no point has elevation data)
+    pub min_elevation: Option<f64>,
+    /// Highest elevation in meters (None if no point has elevation data)
+    pub max_elevation: Option<f64>,
 }
 
 /// Cached statistics for the collection
@@ -75,6 +193,8 @@ struct CachedStats {
     total_distance: f64,
     /// Cached bounding box in Web Mercator (None if empty)
     bounding_box_mercator: Option<Rect<f64>>,
+    /// Cached (min, max) elevation in meters (None if no elevation data)
+    elevation_range: Option<(f64, f64)>,
 }
 
 /// Top-level manager for all routes and queries
@@ -92,6 +212,17 @@ pub struct RouteCollection {
     cached_stats: CachedStats,
 }
 
+// The viewer shares a collection behind `Arc<RwLock<_>>` and hands routes and
+// query results to rayon and tokio workers. Fail the build, rather than some
+// distant caller, if a dependency change makes any of these lose `Send` or `Sync`.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RouteCollection>();
+    assert_send_sync::<Quadtree>();
+    assert_send_sync::<Route>();
+    assert_send_sync::<SimplifiedSegment>();
+};
+
 #[cfg_attr(feature = "profiling", profiling::all_functions)]
 impl RouteCollection {
     /// Create a new route collection with the given configuration
@@ -100,7 +231,8 @@ impl RouteCollection {
         #[cfg(feature = "profiling")]
         profiling::scope!("collection::new");
 
-        let quadtree = Quadtree::new(config.reference_pixel_viewport, config.bias);
+        let mut quadtree = Quadtree::new(config.reference_pixel_viewport, config.bias);
+        quadtree.set_sharp_turn_threshold(config.sharp_turn_threshold);
         Self {
             routes: Vec::new(),
             quadtree,
@@ -118,16 +250,21 @@ impl RouteCollection {
         #[cfg(feature = "profiling")]
         profiling::scope!("collection::add_route");
 
-        let route = Route::new(gpx_data)?;
+        self.add_built_route(Route::new(gpx_data)?)
+    }
+
+    /// Add a route that was already built, e.g. by a [`crate::RouteBuilder`]
+    ///
+    /// [`Self::add_route`] goes through here too, so a built route is indexed
+    /// exactly like one parsed from the same points.
+    pub fn add_built_route(&mut self, route: Arc<Route>) -> Result<()> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::add_built_route");
+
         let route_index = self.routes.len();
 
         // Build quadtree for this route
-        let route_quadtree = Quadtree::new_with_route(
-            route.clone(),
-            route_index,
-            self.config.reference_pixel_viewport,
-            self.config.bias,
-        )?;
+        let route_quadtree = Self::route_quadtree(&self.config, route.clone(), route_index)?;
 
         // Merge into main quadtree
         self.quadtree.merge(route_quadtree)?;
@@ -141,6 +278,105 @@ impl RouteCollection {
         Ok(())
     }
 
+    /// Index one route on its own, ready to be merged into the collection's quadtree
+    fn route_quadtree(config: &Config, route: Arc<Route>, route_index: usize) -> Result<Quadtree> {
+        let mut quadtree = Quadtree::new_with_route_split(
+            route,
+            route_index,
+            config.reference_pixel_viewport,
+            config.bias,
+            config.chunking_aggressiveness,
+            config.max_segment_points,
+        )?;
+        quadtree.set_sharp_turn_threshold(config.sharp_turn_threshold);
+        if let Some(prominence) = config.elevation_extrema_prominence {
+            quadtree.anchor_elevation_extrema(prominence);
+        }
+        if config.precompute_lod {
+            quadtree.precompute_lod();
+        }
+        Ok(quadtree)
+    }
+
+    /// Replace the route at `index` with new GPX data, keeping its index
+    ///
+    /// The old route's segments are removed from the spatial index and the new
+    /// route is indexed in their place, so other routes keep their indices and
+    /// colors. This costs one pass over the stored segments plus building the
+    /// new route, which is far cheaper than rebuilding the whole collection
+    /// (e.g. for a track that keeps growing while it is being recorded).
+    pub fn replace_route(&mut self, index: usize, gpx_data: gpx::Gpx) -> Result<()> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::replace_route");
+
+        if index >= self.routes.len() {
+            return Err(DataError::RouteNotFound(index));
+        }
+        self.replace_route_with(index, vec![Route::new(gpx_data)?])
+            .map(|_| ())
+    }
+
+    /// Replace the route at `index` with one or more built routes, returning their indices
+    ///
+    /// The first replacement takes `index` as in [`Self::replace_route`] and
+    /// the others are appended after the last route, so no other route
+    /// changes index (e.g. when a route is split into parts with
+    /// [`Route::split_at`]).
+    pub fn replace_route_with(
+        &mut self,
+        index: usize,
+        routes: Vec<Arc<Route>>,
+    ) -> Result<Vec<usize>> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::replace_route_with");
+
+        if index >= self.routes.len() {
+            return Err(DataError::RouteNotFound(index));
+        }
+        let mut routes = routes.into_iter();
+        let Some(first) = routes.next() else {
+            return Err(DataError::InvalidGeometry(
+                "no route to replace the route with".to_string(),
+            ));
+        };
+
+        let route_quadtree = Self::route_quadtree(&self.config, first.clone(), index)?;
+        self.quadtree.remove_route(index);
+        self.quadtree.merge(route_quadtree)?;
+        self.routes[index] = first;
+
+        // Totals would need the old route's contribution subtracted (and the
+        // bounding box can shrink), so recompute from the per-route caches.
+        self.rebuild_cached_stats();
+
+        let mut indices = vec![index];
+        for route in routes {
+            indices.push(self.routes.len());
+            self.add_built_route(route)?;
+        }
+        Ok(indices)
+    }
+
+    /// Remove the route at `index` and return it; later routes move down one index
+    ///
+    /// Only the removed route's segments are touched and the cached totals are
+    /// updated by subtracting the route's own, so removing one file from a
+    /// large collection is far cheaper than rebuilding it.
+    pub fn remove_route(&mut self, index: usize) -> Result<Arc<Route>> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::remove_route");
+
+        if index >= self.routes.len() {
+            return Err(DataError::RouteNotFound(index));
+        }
+
+        self.quadtree.remove_route(index);
+        self.quadtree.close_route_gap(index);
+        let route = self.routes.remove(index);
+        self.update_stats_for_removed_route(&route);
+        Ok(route)
+    }
+
     /// Add multiple routes in parallel
     ///
     /// This is more efficient than adding routes one by one as it parallelizes
@@ -162,13 +398,7 @@ impl RouteCollection {
             .enumerate()
             .map(|(i, gpx_data)| {
                 let route = Route::new(gpx_data)?;
-                let route_index = start_index + i;
-                let quadtree = Quadtree::new_with_route(
-                    route.clone(),
-                    route_index,
-                    self.config.reference_pixel_viewport,
-                    self.config.bias,
-                )?;
+                let quadtree = Self::route_quadtree(&self.config, route.clone(), start_index + i)?;
                 Ok((route, quadtree))
             })
             .collect();
@@ -198,11 +428,7 @@ impl RouteCollection {
 
         let gpx_data_vec: Result<Vec<gpx::Gpx>> = paths
             .into_par_iter()
-            .map(|path| {
-                let file = std::fs::File::open(path.as_ref())?;
-                let reader = std::io::BufReader::new(file);
-                Ok(gpx::read(reader)?)
-            })
+            .map(|path| parse_gpx(&std::fs::read(path.as_ref())?))
             .collect();
 
         self.add_routes_parallel(gpx_data_vec?)
@@ -213,6 +439,8 @@ impl RouteCollection {
     /// The viewport should be in Web Mercator coordinates (EPSG:3857).
     /// Returns segments at the appropriate LOD level for the viewport size.
     /// Simplification is performed lazily and cached for efficiency.
+    /// Results are meant for drawing only; use [`RouteCollection::simplify_global`]
+    /// for anything saved or exported.
     ///
     /// # Arguments
     /// * `geo_viewport` - The geographic viewport in Web Mercator coordinates
@@ -229,6 +457,14 @@ impl RouteCollection {
     /// // For 4K displays, more detail will be preserved automatically
     /// let segments = collection.query_visible(viewport, (3840.0, 2160.0));
     /// ```
+    ///
+    /// # Concurrency
+    ///
+    /// Safe to call from any number of threads at once through a shared
+    /// reference (such as a read guard): the simplification caches it fills
+    /// are concurrent maps, so concurrent queries never block each other for
+    /// long and always see complete entries. Adding or removing routes needs
+    /// `&mut self`, e.g. a write guard, so it can't overlap a query.
     #[inline]
     pub fn query_visible(
         &self,
@@ -239,7 +475,174 @@ impl RouteCollection {
         #[cfg(feature = "profiling")]
         profiling::scope!("collection::query_visible");
 
-        self.quadtree.query(geo_viewport, screen_size)
+        if self.config.progressive_refinement {
+            self.quadtree
+                .query_progressive(geo_viewport, screen_size, self.config.boundary_context)
+        } else {
+            self.quadtree.query_with_boundary_context(
+                geo_viewport,
+                screen_size,
+                self.config.boundary_context,
+            )
+        }
+    }
+
+    /// Like [`RouteCollection::query_visible`], `lod_reduction` levels coarser
+    ///
+    /// Every level doubles the simplification tolerance, for keeping frames
+    /// fast at the cost of detail; 0 is the same as `query_visible`. See
+    /// [`Quadtree::query_with_lod_reduction`].
+    pub fn query_visible_coarser(
+        &self,
+        geo_viewport: Rect<f64>,
+        screen_size: (f64, f64),
+        lod_reduction: u32,
+    ) -> Vec<SimplifiedSegment> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::query_visible_coarser");
+
+        self.quadtree.query_with_lod_reduction(
+            geo_viewport,
+            screen_size,
+            self.config.boundary_context,
+            self.config.progressive_refinement,
+            lod_reduction,
+        )
+    }
+
+    /// Background simplifications still running for [`Config::progressive_refinement`]
+    ///
+    /// While this is non-zero some segments were last drawn coarser than
+    /// requested; querying again once it drops shows them at full detail.
+    pub fn pending_refinements(&self) -> usize {
+        self.quadtree.pending_refinements()
+    }
+
+    /// Turn boundary context on or off for later queries (see [`Config::boundary_context`])
+    ///
+    /// Unlike the other config values this needs no rebuild, as nothing
+    /// stored depends on it.
+    pub fn set_boundary_context(&mut self, enabled: bool) {
+        self.config.boundary_context = enabled;
+    }
+
+    /// Change the sharp-turn threshold for later queries (see [`Config::sharp_turn_threshold`])
+    ///
+    /// Like boundary context this needs no rebuild: the threshold is part of
+    /// the simplification cache key.
+    pub fn set_sharp_turn_threshold(&mut self, degrees: Option<f64>) {
+        self.config.sharp_turn_threshold = degrees;
+        self.quadtree.set_sharp_turn_threshold(degrees);
+    }
+
+    /// Drop every cached simplification (see [`Quadtree::clear_cache`])
+    ///
+    /// Config changes applied without a rebuild call this when the cache key
+    /// doesn't cover them. Also frees the indices cached for other settings,
+    /// e.g. every sharp-turn threshold tried so far.
+    pub fn clear_cache(&mut self) {
+        self.quadtree.clear_cache();
+    }
+
+    /// Check the structure of the spatial index (see [`Quadtree::check_invariants`])
+    pub fn check_invariants(&self) -> Result<()> {
+        self.quadtree.check_invariants()
+    }
+
+    /// Simplify every route with one fixed tolerance, for export
+    ///
+    /// This is the counterpart of [`RouteCollection::query_visible`] for output
+    /// that outlives the current view. Display simplification depends on the
+    /// viewport, the screen size and the LOD bias, so its tolerance is only
+    /// meaningful in pixels on the current screen; reusing it would make an
+    /// exported file depend on how the map happened to be zoomed. Here every
+    /// full segment is simplified with Ramer-Douglas-Peucker: no kept point is
+    /// dropped and every dropped point lies within `tolerance_meters` of the
+    /// simplified line, wherever the map is and whatever the config says.
+    ///
+    /// Distances are measured in a local equirectangular projection per
+    /// segment, which is accurate for anything short of continent-sized
+    /// segments. Results use full segments (never chunks or viewport clips),
+    /// one [`SimplifiedSegment`] per route.
+    pub fn simplify_global(&self, tolerance_meters: f64) -> Vec<SimplifiedSegment> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("collection::simplify_global");
+
+        self.routes
+            .par_iter()
+            .enumerate()
+            .map(|(route_index, route)| {
+                let mut parts = Vec::new();
+                for (track_index, track) in route.tracks().iter().enumerate() {
+                    for (segment_index, segment) in track.segments.iter().enumerate() {
+                        let point_count = segment.points.len();
+                        if point_count == 0 {
+                            continue;
+                        }
+                        parts.push(SegmentPart::new(
+                            track_index,
+                            segment_index,
+                            0..point_count,
+                            simplify_segment_metric(&segment.points, tolerance_meters),
+                        ));
+                    }
+                }
+                SimplifiedSegment::new(route.clone(), route_index, parts)
+            })
+            .collect()
+    }
+
+    /// Iterate over every original point inside a Web Mercator viewport
+    ///
+    /// Unlike [`RouteCollection::query_visible`] this applies no simplification,
+    /// which makes it suitable for picking, measuring or exporting the exact
+    /// recorded points in an area. The cost is linear in the points of the
+    /// segments whose bounding boxes touch the viewport, so prefer small
+    /// viewports. See [`Quadtree::query_raw`] for details.
+    pub fn query_raw(&self, geo_viewport: Rect<f64>) -> impl Iterator<Item = RawPointRef> + '_ {
+        self.quadtree.query_raw(geo_viewport)
+    }
+
+    /// Up to `n` routes passing within `radius` of `point`, nearest first
+    ///
+    /// `point` is in Web Mercator like the viewports of the other queries, and
+    /// `radius` in Web Mercator meters. Each route appears once, at the
+    /// distance of its nearest line (not just its nearest point), so a click
+    /// where several tracks overlap can offer all of them to choose from.
+    pub fn nearest_n_tracks(
+        &self,
+        point: geo::Point<f64>,
+        radius: f64,
+        n: usize,
+    ) -> Vec<(usize, f64)> {
+        let mut routes = self.quadtree.nearest_routes(point, radius);
+        routes.truncate(n);
+        routes
+    }
+
+    /// Number of stored segments a query of `geo_viewport` would consider
+    ///
+    /// A cheap estimate of how much [`RouteCollection::query_visible`] would
+    /// render; see [`Quadtree::query_count`].
+    pub fn query_count(&self, geo_viewport: Rect<f64>) -> usize {
+        self.quadtree.query_count(geo_viewport)
+    }
+
+    /// Rough number of bytes held by the parsed routes and the spatial index
+    ///
+    /// Waypoints are counted at their struct size; strings inside them (names,
+    /// descriptions, links) are not. Walks the whole index, so avoid calling it
+    /// every frame.
+    pub fn memory_estimate(&self) -> usize {
+        let waypoint_bytes = self.total_points() * std::mem::size_of::<gpx::Waypoint>();
+        waypoint_bytes + self.index_memory_estimate()
+    }
+
+    /// Rough number of bytes held by the spatial index alone
+    ///
+    /// See [`Quadtree::memory_estimate`]; walks the whole index as well.
+    pub fn index_memory_estimate(&self) -> usize {
+        self.quadtree.memory_estimate()
     }
 
     /// Get total number of routes
@@ -264,6 +667,17 @@ impl RouteCollection {
         self.cached_stats.total_distance
     }
 
+    /// Total ascent and descent of all routes, ignoring elevation changes
+    /// below `threshold_m` (see [`Route::climb`])
+    ///
+    /// Returns `None` if no route has elevation data.
+    pub fn climb(&self, threshold_m: f64) -> Option<Climb> {
+        self.routes
+            .iter()
+            .filter_map(|route| route.climb(threshold_m))
+            .reduce(|total, climb| total + climb)
+    }
+
     /// Get collection information
     ///
     /// This is O(1) as all values are cached.
@@ -273,6 +687,8 @@ impl RouteCollection {
             route_count: self.routes.len(),
             total_points: self.cached_stats.total_points,
             total_distance_meters: self.cached_stats.total_distance,
+            min_elevation: self.cached_stats.elevation_range.map(|(min, _)| min),
+            max_elevation: self.cached_stats.elevation_range.map(|(_, max)| max),
         }
     }
 
@@ -288,6 +704,33 @@ impl RouteCollection {
         self.routes.get(index)
     }
 
+    /// Compare the route at `recorded` against the route at `reference` (see [`crate::compare_routes`])
+    pub fn compare_routes(
+        &self,
+        reference: usize,
+        recorded: usize,
+        corridor_m: f64,
+    ) -> Result<CorridorReport> {
+        let reference = self
+            .get_route(reference)
+            .ok_or(DataError::RouteNotFound(reference))?;
+        let recorded = self
+            .get_route(recorded)
+            .ok_or(DataError::RouteNotFound(recorded))?;
+        crate::compare_routes(reference, recorded, corridor_m)
+    }
+
+    /// Summary differences between the routes at `first` and `second` (see [`crate::diff_routes`])
+    pub fn diff_routes(&self, first: usize, second: usize, buffer_m: f64) -> Result<RouteDiff> {
+        let first = self
+            .get_route(first)
+            .ok_or(DataError::RouteNotFound(first))?;
+        let second = self
+            .get_route(second)
+            .ok_or(DataError::RouteNotFound(second))?;
+        crate::diff_routes(first, second, buffer_m)
+    }
+
     /// Get all routes
     #[inline]
     pub fn routes(&self) -> &[Arc<Route>] {
@@ -304,9 +747,57 @@ impl RouteCollection {
     pub fn clear(&mut self) {
         self.routes.clear();
         self.quadtree = Quadtree::new(self.config.reference_pixel_viewport, self.config.bias);
+        self.quadtree
+            .set_sharp_turn_threshold(self.config.sharp_turn_threshold);
         self.cached_stats = CachedStats::default();
     }
 
+    /// Write one CSV row of statistics per route to `path`, after a header
+    ///
+    /// See [`RouteCollection::write_stats_csv`] for the columns.
+    pub fn export_stats_csv(&self, path: &Path) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        self.write_stats_csv(std::io::BufWriter::new(file))
+    }
+
+    /// Write one CSV row of statistics per route to `writer`, after a header
+    ///
+    /// Columns: name, points, distance (km), elevation gain and loss (m) and
+    /// the WGS84 bounding box. Routes without a name get "Route N" (1-based,
+    /// in load order); elevation cells stay empty for routes without
+    /// elevation data.
+    pub fn write_stats_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(
+            writer,
+            "name,points,distance_km,elevation_gain_m,elevation_loss_m,min_lat,min_lon,max_lat,max_lon"
+        )?;
+        let elevation = |value: Option<f64>| value.map(|m| format!("{:.1}", m)).unwrap_or_default();
+        for (index, route) in self.routes.iter().enumerate() {
+            let name = match route.name() {
+                Some(name) => name.to_string(),
+                None => format!("Route {}", index + 1),
+            };
+            let bbox = route.bounding_box();
+            let (min_lat, min_lon) = utils::mercator_to_wgs84(bbox.min().x, bbox.min().y);
+            let (max_lat, max_lon) = utils::mercator_to_wgs84(bbox.max().x, bbox.max().y);
+            writeln!(
+                writer,
+                "{},{},{:.3},{},{},{:.6},{:.6},{:.6},{:.6}",
+                csv_field(&name),
+                route.total_points(),
+                route.total_distance() / 1000.0,
+                elevation(route.elevation_gain()),
+                elevation(route.elevation_loss()),
+                min_lat,
+                min_lon,
+                max_lat,
+                max_lon
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
     /// Get the combined bounding box of all routes in WGS84 coordinates (lat/lon)
     ///
     /// This is O(1) as the bounding box is cached and updated incrementally.
@@ -343,6 +834,10 @@ impl RouteCollection {
         // Update total distance
         self.cached_stats.total_distance += route.total_distance();
 
+        // Update elevation range
+        self.cached_stats.elevation_range =
+            merge_elevation_ranges(self.cached_stats.elevation_range, route.elevation_range());
+
         // Update bounding box
         let route_bbox = route.bounding_box();
         match &mut self.cached_stats.bounding_box_mercator {
@@ -370,16 +865,60 @@ impl RouteCollection {
         }
     }
 
+    /// Update cached statistics when a route is removed
+    fn update_stats_for_removed_route(&mut self, route: &Route) {
+        let stats = &mut self.cached_stats;
+        // Saturating, so a bug removing a route twice can't wrap the totals around
+        stats.total_points = stats.total_points.saturating_sub(route.total_points());
+        stats.total_distance = if self.routes.is_empty() {
+            // No floating-point residue once everything is gone
+            0.0
+        } else {
+            (stats.total_distance - route.total_distance()).max(0.0)
+        };
+
+        // The bounding box and elevation range may shrink, which can't be
+        // subtracted; fold them again from the per-route caches
+        stats.bounding_box_mercator = None;
+        stats.elevation_range = None;
+        for route in &self.routes {
+            stats.elevation_range =
+                merge_elevation_ranges(stats.elevation_range, route.elevation_range());
+            stats.bounding_box_mercator = Some(match stats.bounding_box_mercator {
+                Some(bbox) => merge_rects(bbox, route.bounding_box()),
+                None => route.bounding_box(),
+            });
+        }
+
+        #[cfg(debug_assertions)]
+        self.debug_assert_totals_match_recount();
+    }
+
+    /// Panic if the cached totals drifted from a full recount (debug builds only)
+    #[cfg(debug_assertions)]
+    fn debug_assert_totals_match_recount(&self) {
+        let points: usize = self.routes.iter().map(|route| route.total_points()).sum();
+        let distance: f64 = self.routes.iter().map(|route| route.total_distance()).sum();
+        debug_assert_eq!(self.cached_stats.total_points, points);
+        debug_assert!(
+            (self.cached_stats.total_distance - distance).abs() <= 1e-6 * distance.max(1.0),
+            "cached distance {} != recount {}",
+            self.cached_stats.total_distance,
+            distance
+        );
+    }
+
     /// Rebuild cached statistics from scratch
     ///
     /// This is useful after deserialization or if the cache becomes invalid.
-    #[allow(dead_code)]
     fn rebuild_cached_stats(&mut self) {
         self.cached_stats = CachedStats::default();
 
         for route in &self.routes {
             self.cached_stats.total_points += route.total_points();
             self.cached_stats.total_distance += route.total_distance();
+            self.cached_stats.elevation_range =
+                merge_elevation_ranges(self.cached_stats.elevation_range, route.elevation_range());
 
             let route_bbox = route.bounding_box();
             match &mut self.cached_stats.bounding_box_mercator {
@@ -407,6 +946,59 @@ impl RouteCollection {
     }
 }
 
+/// Smallest rectangle containing both
+fn merge_rects(a: Rect<f64>, b: Rect<f64>) -> Rect<f64> {
+    Rect::new(
+        geo::Coord {
+            x: a.min().x.min(b.min().x),
+            y: a.min().y.min(b.min().y),
+        },
+        geo::Coord {
+            x: a.max().x.max(b.max().x),
+            y: a.max().y.max(b.max().y),
+        },
+    )
+}
+
+/// Combine two optional (min, max) elevation ranges
+fn merge_elevation_ranges(a: Option<(f64, f64)>, b: Option<(f64, f64)>) -> Option<(f64, f64)> {
+    match (a, b) {
+        (Some((a_min, a_max)), Some((b_min, b_max))) => Some((a_min.min(b_min), a_max.max(b_max))),
+        (range, None) | (None, range) => range,
+    }
+}
+
+/// Ramer-Douglas-Peucker indices for a segment, with the tolerance in meters
+fn simplify_segment_metric(points: &[gpx::Waypoint], tolerance_meters: f64) -> Vec<usize> {
+    if points.len() <= 2 {
+        return (0..points.len()).collect();
+    }
+
+    // Project around the segment's mean latitude so both axes are in meters
+    let mean_lat = points.iter().map(|p| p.point().y()).sum::<f64>() / points.len() as f64;
+    // Web Mercator x is meters at the equator, so this is the length of one degree there
+    let meters_per_degree = utils::EARTH_MERCATOR_MAX / 180.0;
+    let x_scale = meters_per_degree * mean_lat.to_radians().cos();
+    let coords: Vec<Coord<f64>> = points
+        .iter()
+        .map(|p| Coord {
+            x: p.point().x() * x_scale,
+            y: p.point().y() * meters_per_degree,
+        })
+        .collect();
+
+    LineString::from(coords).simplify_idx(tolerance_meters)
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -434,6 +1026,184 @@ mod tests {
         gpx
     }
 
+    /// A long winding route with two segments, spread wide enough to be chunked
+    fn create_winding_gpx() -> Gpx {
+        let mut gpx = Gpx::default();
+        let mut track = Track::default();
+        for offset in [0.0, 0.5] {
+            let mut segment = TrackSegment::default();
+            for i in 0..2000 {
+                let t = i as f64 * 0.001;
+                segment.points.push(create_test_waypoint(
+                    51.0 + offset + t,
+                    -1.0 + (t * 7.0).sin(),
+                ));
+            }
+            track.segments.push(segment);
+        }
+        gpx.tracks.push(track);
+        gpx
+    }
+
+    #[test]
+    fn test_simplify_global_is_viewport_independent() {
+        let count = |segments: &[SimplifiedSegment]| -> usize {
+            segments
+                .iter()
+                .flat_map(|s| s.parts.iter())
+                .map(|p| p.simplified_indices.len())
+                .sum()
+        };
+
+        // Configs that change display simplification must not change exports
+        let mut outputs = Vec::new();
+        for (bias, width) in [(1.0, 1024.0), (4.0, 4096.0)] {
+            let config = Config {
+                bias,
+                reference_pixel_viewport: Rect::new(
+                    geo::Coord { x: 0.0, y: 0.0 },
+                    geo::Coord { x: width, y: 768.0 },
+                ),
+                ..Config::default()
+            };
+            let mut collection = RouteCollection::new(config);
+            collection.add_route(create_winding_gpx()).unwrap();
+
+            let global = collection.simplify_global(25.0);
+            let indices: Vec<Vec<usize>> = global[0]
+                .parts
+                .iter()
+                .map(|p| p.simplified_indices.clone())
+                .collect();
+            outputs.push(indices);
+
+            // Display simplification of the same data does vary with the viewport
+            let viewports = test_viewports();
+            let screen = (1920.0, 1080.0);
+            let zoomed_in = count(&collection.query_visible(viewports[1], screen));
+            let zoomed_out = count(&collection.query_visible(viewports[2], screen));
+            assert_ne!(zoomed_in, zoomed_out);
+        }
+        assert_eq!(outputs[0], outputs[1]);
+
+        // Full segments, simplified, with endpoints kept
+        let segment_points = outputs[0][0].len();
+        assert!(segment_points > 2 && segment_points < 2000);
+        assert_eq!(outputs[0][0][0], 0);
+        assert_eq!(*outputs[0][0].last().unwrap(), 1999);
+
+        // A coarser tolerance keeps fewer points
+        let mut collection = RouteCollection::new(Config::default());
+        collection.add_route(create_winding_gpx()).unwrap();
+        assert!(
+            count(&collection.simplify_global(500.0)) < count(&collection.simplify_global(5.0))
+        );
+    }
+
+    #[test]
+    fn test_index_memory_estimate_follows_the_point_count() {
+        use std::mem::size_of;
+
+        let mut collection = RouteCollection::new(Config::default());
+        for _ in 0..10 {
+            collection.add_route(create_test_gpx()).unwrap();
+        }
+        // 1000 points, each kept projected in the index, plus nodes and bookkeeping
+        let index = collection.index_memory_estimate();
+        let points = 1000 * size_of::<geo::Point<f64>>();
+        assert!(
+            index >= points && index < points * 10,
+            "{} bytes of index for {} bytes of points",
+            index,
+            points
+        );
+        assert_eq!(
+            collection.memory_estimate(),
+            1000 * size_of::<Waypoint>() + index
+        );
+
+        for _ in 0..10 {
+            collection.add_route(create_test_gpx()).unwrap();
+        }
+        let doubled = collection.index_memory_estimate();
+        assert!(doubled >= index + points, "{} after {}", doubled, index);
+    }
+
+    #[test]
+    fn test_memory_per_gpx_byte_calibration() {
+        let mut gpx = create_winding_gpx();
+        let start = time::OffsetDateTime::from_unix_timestamp(1_714_557_600).unwrap();
+        let points = gpx.tracks[0]
+            .segments
+            .iter_mut()
+            .flat_map(|s| s.points.iter_mut());
+        for (i, point) in points.enumerate() {
+            point.elevation = Some(100.0 + i as f64 * 0.1);
+            point.time = Some((start + time::Duration::seconds(i as i64)).into());
+        }
+        gpx.version = gpx::GpxVersion::Gpx11;
+        let mut file = Vec::new();
+        gpx::write(&gpx, &mut file).unwrap();
+
+        let mut collection = RouteCollection::new(Config::default());
+        collection.add_route(gpx).unwrap();
+        // Render once so the estimate includes a populated simplification cache
+        let _ = collection.query_visible(test_viewports()[1], (1920.0, 1080.0));
+
+        let ratio = collection.memory_estimate() as f64 / file.len() as f64;
+        assert!(
+            ratio > ESTIMATED_MEMORY_PER_GPX_BYTE / 3.0
+                && ratio < ESTIMATED_MEMORY_PER_GPX_BYTE * 3.0,
+            "measured {:.2} bytes of memory per GPX byte, constant says {}",
+            ratio,
+            ESTIMATED_MEMORY_PER_GPX_BYTE
+        );
+        assert_eq!(
+            estimate_memory_for_gpx_bytes(1000),
+            (1000.0 * ESTIMATED_MEMORY_PER_GPX_BYTE) as u64
+        );
+    }
+
+    #[test]
+    fn test_parse_gpx_reports_empty_files() {
+        for empty in [&b""[..], &b"  \n\t\r\n"[..], &b"\xEF\xBB\xBF\n"[..]] {
+            assert!(matches!(parse_gpx(empty), Err(DataError::EmptyFile)));
+        }
+        assert_eq!(DataError::EmptyFile.to_string(), "File is empty");
+        // Malformed content still gets the parser's error
+        assert!(matches!(
+            parse_gpx(b"<gpx version=\"1.1\""),
+            Err(DataError::GpxParse(_))
+        ));
+    }
+
+    /// Viewports covering a small area, a partial overlap, the whole world and nothing
+    fn test_viewports() -> Vec<Rect<f64>> {
+        use crate::utils::wgs84_to_mercator;
+
+        let viewport = |lat: (f64, f64), lon: (f64, f64)| {
+            let min = wgs84_to_mercator(lat.0, lon.0);
+            let max = wgs84_to_mercator(lat.1, lon.1);
+            Rect::new(
+                geo::Coord {
+                    x: min.x(),
+                    y: min.y(),
+                },
+                geo::Coord {
+                    x: max.x(),
+                    y: max.y(),
+                },
+            )
+        };
+
+        vec![
+            viewport((51.5, 51.6), (-0.2, 0.0)),
+            viewport((51.2, 52.0), (-1.5, -0.5)),
+            viewport((-80.0, 80.0), (-170.0, 170.0)),
+            viewport((10.0, 11.0), (10.0, 11.0)),
+        ]
+    }
+
     #[test]
     fn test_collection_creation() {
         let config = Config::default();
@@ -462,6 +1232,47 @@ mod tests {
         assert!(!collection.is_empty());
     }
 
+    #[test]
+    fn test_nearest_n_tracks_orders_overlapping_routes_by_distance() {
+        use crate::utils::wgs84_to_mercator;
+        use crate::{RouteBuilder, TrackPoint};
+
+        /// A route of east-west segments, one at each latitude
+        fn parallel_lines(lats: &[f64]) -> Arc<Route> {
+            lats.iter()
+                .fold(RouteBuilder::new("Parallel"), |builder, &lat| {
+                    builder.add_segment(
+                        (0..=20).map(|i| TrackPoint::new(lat, -0.11 + i as f64 * 0.001)),
+                    )
+                })
+                .build()
+                .unwrap()
+        }
+
+        let mut collection = RouteCollection::new(Config::default());
+        // Added farthest first; the second route passes twice, both times close
+        for lats in [
+            &[51.5004][..],
+            &[51.5001, 51.49985][..],
+            &[51.5002][..],
+            &[51.6][..],
+        ] {
+            collection.add_built_route(parallel_lines(lats)).unwrap();
+        }
+
+        let point = wgs84_to_mercator(51.5, -0.1);
+        let nearest = collection.nearest_n_tracks(point, 200.0, 10);
+        let routes: Vec<usize> = nearest.iter().map(|&(route, _)| route).collect();
+        // Once per route, nearest first, and the far route is out of reach
+        assert_eq!(routes, vec![1, 2, 0]);
+        assert!(nearest.windows(2).all(|pair| pair[0].1 < pair[1].1));
+        let expected = wgs84_to_mercator(51.5001, -0.1).y() - point.y();
+        assert!((nearest[0].1 - expected).abs() < 1e-6);
+
+        assert_eq!(collection.nearest_n_tracks(point, 200.0, 2).len(), 2);
+        assert!(collection.nearest_n_tracks(point, 1.0, 10).is_empty());
+    }
+
     #[test]
     fn test_query_visible() {
         let config = Config::default();
@@ -645,6 +1456,37 @@ mod tests {
         assert_eq!(info.route_count, 0);
         assert_eq!(info.total_points, 0);
         assert_eq!(info.total_distance_meters, 0.0);
+        assert!(info.min_elevation.is_none());
+        assert!(info.max_elevation.is_none());
+    }
+
+    #[test]
+    fn test_get_info_elevation_range() {
+        let config = Config::default();
+        let mut collection = RouteCollection::new(config);
+
+        // No elevation data at all
+        collection.add_route(create_test_gpx()).unwrap();
+        let info = collection.get_info();
+        assert!(info.min_elevation.is_none());
+        assert!(info.max_elevation.is_none());
+
+        // Two routes with elevations spanning -10..=249 m overall
+        for offset in [-10.0, 150.0] {
+            let mut gpx = create_test_gpx();
+            let points = gpx.tracks[0]
+                .segments
+                .iter_mut()
+                .flat_map(|s| s.points.iter_mut());
+            for (i, point) in points.enumerate() {
+                point.elevation = Some(offset + i as f64);
+            }
+            collection.add_route(gpx).unwrap();
+        }
+
+        let info = collection.get_info();
+        assert_eq!(info.min_elevation, Some(-10.0));
+        assert_eq!(info.max_elevation, Some(249.0));
     }
 
     #[test]
@@ -813,6 +1655,62 @@ mod tests {
         assert!(!results.is_empty());
     }
 
+    #[test]
+    fn test_long_segment_split_stays_continuous() {
+        let config = Config {
+            max_segment_points: 100,
+            ..Config::default()
+        };
+        let mut collection = RouteCollection::new(config);
+
+        let mut gpx = Gpx::default();
+        let mut track = Track::default();
+        let mut segment = TrackSegment::default();
+        for i in 0..1000 {
+            segment.points.push(create_test_waypoint(
+                51.5074 + i as f64 * 0.00005,
+                -0.1278 + (i % 7) as f64 * 0.00003,
+            ));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+
+        assert_eq!(collection.config().segments_to_split(&gpx), 1);
+        collection.add_route(gpx).unwrap();
+
+        use crate::utils::wgs84_to_mercator;
+        let min = wgs84_to_mercator(51.5, -0.2);
+        let max = wgs84_to_mercator(51.6, -0.0);
+        let viewport = Rect::new(
+            geo::Coord {
+                x: min.x(),
+                y: min.y(),
+            },
+            geo::Coord {
+                x: max.x(),
+                y: max.y(),
+            },
+        );
+        let results = collection.query_visible(viewport, (1920.0, 1080.0));
+
+        // Every drawn run is in original GPX indices, and consecutive runs
+        // meet at a shared point, so the line has no gap at the splits
+        let mut runs: Vec<&[usize]> = results
+            .iter()
+            .flat_map(|segment| &segment.parts)
+            .map(|part| part.simplified_indices.as_slice())
+            .collect();
+        assert!(runs.len() > 1, "expected the segment to be split");
+        runs.sort_by_key(|run| run[0]);
+        let mut reached = 0;
+        for run in runs {
+            assert!(run.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(run[0] <= reached, "gap before point {}", run[0]);
+            reached = reached.max(*run.last().unwrap());
+        }
+        assert_eq!(reached, 999);
+    }
+
     #[test]
     fn test_cached_stats_consistency() {
         let config = Config::default();
@@ -866,4 +1764,401 @@ mod tests {
             bbox2.0 <= bbox1.0 || bbox2.1 <= bbox1.1 || bbox2.2 >= bbox1.2 || bbox2.3 >= bbox1.3
         );
     }
+    /// Positions logged several times while standing still, each repeat
+    /// under a millimeter from the first, then a segment that never moves
+    fn create_stationary_gpx() -> Gpx {
+        let mut track = Track::default();
+        let mut segment = TrackSegment::default();
+        for i in 0..60 {
+            let lon = -0.15 + (i / 4) as f64 * 0.001 + (i % 4) as f64 * 2e-9;
+            segment.points.push(create_test_waypoint(51.55, lon));
+        }
+        track.segments.push(segment);
+        let mut segment = TrackSegment::default();
+        for _ in 0..5 {
+            segment.points.push(create_test_waypoint(51.56, -0.15));
+        }
+        track.segments.push(segment);
+        let mut gpx = Gpx::default();
+        gpx.tracks.push(track);
+        gpx
+    }
+
+    #[test]
+    fn test_query_raw_matches_brute_force() {
+        use crate::utils::wgs84_to_mercator;
+
+        // Whole segments, and segments split into chunks that share their ends
+        for max_segment_points in [DEFAULT_MAX_SEGMENT_POINTS, 10] {
+            check_query_raw_matches_brute_force(Config {
+                max_segment_points,
+                ..Config::default()
+            });
+        }
+
+        fn check_query_raw_matches_brute_force(config: Config) {
+            let mut collection = RouteCollection::new(config);
+            collection.add_route(create_test_gpx()).unwrap();
+            collection.add_route(create_winding_gpx()).unwrap();
+            collection.add_route(create_stationary_gpx()).unwrap();
+            assert!(collection.quadtree.duplicate_points_removed() > 0);
+
+            // Left edge between a stored point and the repeats left out after it
+            let stored = wgs84_to_mercator(51.55, -0.15 + 7.0 * 0.001);
+            let repeat = wgs84_to_mercator(51.55, -0.15 + 7.0 * 0.001 + 2e-9);
+            let edge = (stored.x() + repeat.x()) / 2.0;
+            let split = Rect::new(
+                geo::Coord {
+                    x: edge,
+                    y: stored.y() - 1.0,
+                },
+                geo::Coord {
+                    x: edge + 2_000.0,
+                    y: stored.y() + 1.0,
+                },
+            );
+
+            let in_split: Vec<_> = collection
+                .query_raw(split)
+                .filter(|p| p.route_index == 2 && p.segment_index == 0)
+                .map(|p| p.point_index)
+                .collect();
+            assert!(in_split.contains(&29) && !in_split.contains(&28));
+
+            for rect in test_viewports().into_iter().chain([split]) {
+                let mut expected = Vec::new();
+                for (route_index, route) in collection.routes().iter().enumerate() {
+                    for (track_index, track) in route.tracks().iter().enumerate() {
+                        for (segment_index, segment) in track.segments.iter().enumerate() {
+                            for (point_index, waypoint) in segment.points.iter().enumerate() {
+                                let p =
+                                    wgs84_to_mercator(waypoint.point().y(), waypoint.point().x());
+                                if p.x() >= rect.min().x
+                                    && p.x() <= rect.max().x
+                                    && p.y() >= rect.min().y
+                                    && p.y() <= rect.max().y
+                                {
+                                    expected.push((
+                                        route_index,
+                                        track_index,
+                                        segment_index,
+                                        point_index,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let mut actual: Vec<_> = collection
+                    .query_raw(rect)
+                    .map(|p| {
+                        let waypoint = collection.routes()[p.route_index]
+                            .get_waypoint(p.track_index, p.segment_index, p.point_index)
+                            .unwrap();
+                        assert_eq!(p.wgs84, waypoint.point());
+                        (p.route_index, p.track_index, p.segment_index, p.point_index)
+                    })
+                    .collect();
+
+                expected.sort_unstable();
+                actual.sort_unstable();
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_precompute_lod_matches_lazy_queries() {
+        let mut lazy = RouteCollection::new(Config::default());
+        let mut precomputed = RouteCollection::new(Config {
+            precompute_lod: true,
+            ..Config::default()
+        });
+        for collection in [&mut lazy, &mut precomputed] {
+            collection.add_route(create_test_gpx()).unwrap();
+            collection
+                .add_routes_parallel(vec![create_winding_gpx()])
+                .unwrap();
+        }
+
+        // Precomputed levels use the reference viewport, so query at that size
+        let reference = Config::default().reference_pixel_viewport;
+        let screen_size = (reference.width(), reference.height());
+
+        let summarize = |segments: Vec<SimplifiedSegment>| -> Vec<_> {
+            segments
+                .into_iter()
+                .map(|segment| {
+                    let parts: Vec<_> = segment
+                        .parts
+                        .into_iter()
+                        .map(|part| {
+                            (
+                                part.track_index,
+                                part.segment_index,
+                                part.simplified_indices,
+                            )
+                        })
+                        .collect();
+                    (segment.route_index, parts)
+                })
+                .collect()
+        };
+
+        for viewport in test_viewports() {
+            let expected = summarize(lazy.query_visible(viewport, screen_size));
+            let actual = summarize(precomputed.query_visible(viewport, screen_size));
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_replace_route() {
+        use crate::utils::wgs84_to_mercator;
+
+        let mut collection = RouteCollection::new(Config::default());
+        collection.add_route(create_test_gpx()).unwrap();
+        collection.add_route(create_winding_gpx()).unwrap();
+        let winding_points = collection.get_route(1).unwrap().total_points();
+
+        // Replace the London route with a short one near Madrid
+        let mut gpx = Gpx::default();
+        let mut track = Track::default();
+        let mut segment = TrackSegment::default();
+        for i in 0..10 {
+            segment
+                .points
+                .push(create_test_waypoint(40.4 + i as f64 * 0.001, -3.7));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+        collection.replace_route(0, gpx).unwrap();
+
+        assert_eq!(collection.route_count(), 2);
+        assert_eq!(collection.total_points(), 10 + winding_points);
+
+        let viewport = |lat: (f64, f64), lon: (f64, f64)| {
+            let min = wgs84_to_mercator(lat.0, lon.0);
+            let max = wgs84_to_mercator(lat.1, lon.1);
+            Rect::new(
+                geo::Coord {
+                    x: min.x(),
+                    y: min.y(),
+                },
+                geo::Coord {
+                    x: max.x(),
+                    y: max.y(),
+                },
+            )
+        };
+        let screen_size = (1920.0, 1080.0);
+
+        // The old London points are gone, the new ones are found under index 0
+        let london = collection.query_visible(viewport((51.5, 51.7), (-0.2, 0.0)), screen_size);
+        assert!(london.iter().all(|s| s.route_index != 0));
+        let madrid = collection.query_visible(viewport((40.3, 40.5), (-3.8, -3.6)), screen_size);
+        assert!(!madrid.is_empty());
+        assert!(madrid.iter().all(|s| s.route_index == 0));
+
+        // The cached bounding box follows the replacement
+        let (min_lat, _, _, _) = collection.bounding_box_wgs84().unwrap();
+        assert!(min_lat < 41.0);
+
+        assert!(matches!(
+            collection.replace_route(5, create_test_gpx()),
+            Err(DataError::RouteNotFound(5))
+        ));
+    }
+
+    #[test]
+    fn test_replace_route_with_several() {
+        let mut collection = RouteCollection::new(Config::default());
+        collection.add_route(create_winding_gpx()).unwrap();
+        collection.add_route(create_test_gpx()).unwrap();
+        let winding = collection.get_route(0).unwrap().clone();
+        let total_points = collection.total_points();
+        let total_distance = collection.total_distance();
+
+        let middle = winding.tracks()[0].segments[0].points.len() / 2;
+        let (first, second) = winding.split_at(0, 0, middle).unwrap();
+        let indices = collection
+            .replace_route_with(0, vec![first.clone(), second.clone()])
+            .unwrap();
+
+        // The first part keeps the index, the second goes last; the London route stays put
+        assert_eq!(indices, vec![0, 2]);
+        assert_eq!(collection.route_count(), 3);
+        assert!(Arc::ptr_eq(collection.get_route(0).unwrap(), &first));
+        assert!(Arc::ptr_eq(collection.get_route(2).unwrap(), &second));
+        assert_eq!(collection.total_points(), total_points + 1);
+        assert!((collection.total_distance() - total_distance).abs() < 1e-6);
+        collection.check_invariants().unwrap();
+
+        let full = Rect::new(
+            geo::Coord {
+                x: -20_037_508.0,
+                y: -20_037_508.0,
+            },
+            geo::Coord {
+                x: 20_037_508.0,
+                y: 20_037_508.0,
+            },
+        );
+        let found: std::collections::BTreeSet<usize> = collection
+            .query_raw(full)
+            .map(|point| point.route_index)
+            .collect();
+        assert_eq!(found, [0, 1, 2].into());
+
+        // Putting the whole route back is a plain replacement plus a removal
+        collection.replace_route_with(0, vec![winding]).unwrap();
+        collection.remove_route(2).unwrap();
+        assert_eq!(collection.total_points(), total_points);
+
+        assert!(matches!(
+            collection.replace_route_with(0, Vec::new()),
+            Err(DataError::InvalidGeometry(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_route_updates_stats_incrementally() {
+        let routes = || [create_test_gpx(), create_winding_gpx(), create_test_gpx()];
+        let check = |collection: &RouteCollection| {
+            let mut fresh = RouteCollection::new(Config::default());
+            for route in collection.routes() {
+                fresh.add_built_route(route.clone()).unwrap();
+            }
+            let (info, recount) = (collection.get_info(), fresh.get_info());
+            assert_eq!(info.route_count, recount.route_count);
+            assert_eq!(info.total_points, recount.total_points);
+            assert!((info.total_distance_meters - recount.total_distance_meters).abs() < 1e-6);
+            assert_eq!(collection.bounding_box_wgs84(), fresh.bounding_box_wgs84());
+            collection.check_invariants().unwrap();
+        };
+
+        let max = utils::EARTH_MERCATOR_MAX;
+        let world = Rect::new(
+            geo::Coord { x: -max, y: -max },
+            geo::Coord { x: max, y: max },
+        );
+
+        for order in [[0, 0, 0], [2, 1, 0], [1, 1, 0], [2, 0, 0]] {
+            let mut collection = RouteCollection::new(Config::default());
+            for gpx in routes() {
+                collection.add_route(gpx).unwrap();
+            }
+            for index in order {
+                collection.remove_route(index).unwrap();
+                check(&collection);
+                // Later routes moved down, so indices stay contiguous
+                let indices: std::collections::BTreeSet<usize> = collection
+                    .query_visible(world, (1920.0, 1080.0))
+                    .iter()
+                    .map(|segment| segment.route_index)
+                    .collect();
+                assert!(indices.iter().all(|&i| i < collection.route_count()));
+            }
+            assert_eq!(collection.total_points(), 0);
+            assert_eq!(collection.total_distance(), 0.0);
+            assert!(collection.bounding_box_wgs84().is_none());
+            assert!(matches!(
+                collection.remove_route(0),
+                Err(DataError::RouteNotFound(0))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stats_csv_has_one_row_per_route() {
+        let mut collection = RouteCollection::new(Config::default());
+        let mut named = create_test_gpx();
+        named.tracks[0].name = Some("Thames, east".to_string());
+        for (i, point) in named.tracks[0].segments[0].points.iter_mut().enumerate() {
+            point.elevation = Some(if i < 50 { i as f64 } else { 100.0 - i as f64 });
+        }
+        collection.add_route(named).unwrap();
+        collection.add_route(create_test_gpx()).unwrap();
+
+        let path = std::env::temp_dir().join(format!("ltv-stats-{}.csv", std::process::id()));
+        collection.export_stats_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + collection.route_count());
+        assert!(lines[0].starts_with("name,points,distance_km,"));
+        for (line, route) in lines[1..].iter().zip(collection.routes()) {
+            let distance: f64 = line.rsplit(',').nth(6).unwrap().parse().unwrap();
+            assert!((distance - route.total_distance() / 1000.0).abs() < 1e-3);
+        }
+        assert!(
+            lines[1].starts_with("\"Thames, east\",100,"),
+            "{}",
+            lines[1]
+        );
+        assert!(lines[1].contains(",50.0,49.0,"), "{}", lines[1]);
+        // No name and no elevation
+        assert!(lines[2].starts_with("Route 2,100,"), "{}", lines[2]);
+        assert!(lines[2].contains(",,,"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_concurrent_queries_while_routes_are_added() {
+        use std::sync::RwLock;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        for progressive in [false, true] {
+            let collection = Arc::new(RwLock::new(RouteCollection::new(Config {
+                progressive_refinement: progressive,
+                ..Config::default()
+            })));
+            collection
+                .write()
+                .unwrap()
+                .add_route(create_winding_gpx())
+                .unwrap();
+            let viewport = collection.read().unwrap().routes()[0].bounding_box();
+            let done = Arc::new(AtomicBool::new(false));
+
+            let readers: Vec<_> = (0..4)
+                .map(|i| {
+                    let collection = collection.clone();
+                    let done = done.clone();
+                    std::thread::spawn(move || {
+                        let screen = (800.0 + 100.0 * i as f64, 600.0);
+                        let mut last_count = 0;
+                        let mut queries = 0;
+                        while !done.load(Ordering::SeqCst) || queries == 0 {
+                            let collection = collection.read().unwrap();
+                            let count = collection.route_count();
+                            assert!(count >= last_count, "routes disappeared");
+                            last_count = count;
+                            for segment in collection.query_visible(viewport, screen) {
+                                assert!(segment.route_index < count);
+                                assert!(!segment.parts.is_empty());
+                            }
+                            queries += 1;
+                        }
+                        queries
+                    })
+                })
+                .collect();
+
+            for _ in 0..20 {
+                collection
+                    .write()
+                    .unwrap()
+                    .add_route(create_winding_gpx())
+                    .unwrap();
+                std::thread::yield_now();
+            }
+            done.store(true, Ordering::SeqCst);
+            for reader in readers {
+                assert!(reader.join().expect("reader panicked") > 0);
+            }
+            assert_eq!(collection.read().unwrap().route_count(), 21);
+        }
+    }
 }
@@ -0,0 +1,311 @@
+//! Comparing a recorded route against a reference route
+//!
+//! The reference route gets its own quadtree, so each recorded point only
+//! measures the reference lines stored near it. Distances are measured in Web
+//! Mercator and scaled back to ground meters at the recorded point's latitude,
+//! which is accurate for corridor-sized distances.
+
+use crate::quadtree::Quadtree;
+use crate::{Config, DEFAULT_CHUNKING_AGGRESSIVENESS, Result, Route, utils};
+use rayon::prelude::*;
+use std::sync::Arc;
+
+/// How much of a recorded route stayed near a reference route
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CorridorReport {
+    /// Corridor half-width the report was computed with, in meters
+    pub corridor_m: f64,
+    /// Length of the recorded route, in meters
+    pub total_length_m: f64,
+    /// Length of the recorded route within the corridor, in meters
+    pub inside_length_m: f64,
+    /// Distance of the recorded point farthest from the reference, in meters
+    pub max_excursion_m: f64,
+    /// Stretches of the recorded route outside the corridor, in track order
+    pub off_corridor: Vec<OffCorridorSection>,
+}
+
+impl CorridorReport {
+    /// Fraction (`0.0..=1.0`) of the recorded length within the corridor
+    ///
+    /// A route without length (a single point) counts as fully inside if its
+    /// point is, so the result is always defined.
+    pub fn fraction_inside(&self) -> f64 {
+        if self.total_length_m > 0.0 {
+            self.inside_length_m / self.total_length_m
+        } else if self.off_corridor.is_empty() {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Summary differences between two routes, e.g. two runs of the same course
+///
+/// Deltas are `second - first`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteDiff {
+    /// Difference in total length, in meters
+    pub distance_delta_m: f64,
+    /// Fraction (`0.0..=1.0`) of both routes within the buffer of the other,
+    /// see [`overlap_fraction`]
+    pub overlap: f64,
+    /// Difference in elevation gain, in meters; `None` unless both routes
+    /// carry elevation
+    pub elevation_gain_delta_m: Option<f64>,
+}
+
+/// Consecutive recorded points outside the corridor
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffCorridorSection {
+    pub track_index: usize,
+    pub segment_index: usize,
+    /// First point outside the corridor
+    pub start_point: usize,
+    /// Last point outside the corridor (inclusive)
+    pub end_point: usize,
+    /// Length of the route attributed to this section, in meters
+    pub length_m: f64,
+    /// Farthest distance from the reference within the section, in meters
+    pub max_distance_m: f64,
+}
+
+/// Compare `recorded` against `reference` with a corridor of `corridor_m` meters on each side
+///
+/// Each recorded point is classified as inside or outside the corridor. A
+/// line between two inside points counts as inside, between two outside
+/// points as outside, and a line crossing the corridor edge counts half each.
+pub fn compare_routes(
+    reference: &Arc<Route>,
+    recorded: &Route,
+    corridor_m: f64,
+) -> Result<CorridorReport> {
+    #[cfg(feature = "profiling")]
+    profiling::scope!("compare::compare_routes");
+
+    let defaults = Config::default();
+    let tree = Quadtree::new_with_route(
+        reference.clone(),
+        0,
+        defaults.reference_pixel_viewport,
+        defaults.bias,
+        DEFAULT_CHUNKING_AGGRESSIVENESS,
+    )?;
+    let corridor_m = corridor_m.max(0.0);
+
+    let mut report = CorridorReport {
+        corridor_m,
+        ..Default::default()
+    };
+    for (track_index, track) in recorded.tracks().iter().enumerate() {
+        for (segment_index, segment) in track.segments.iter().enumerate() {
+            let distances: Vec<f64> = segment
+                .points
+                .par_iter()
+                .map(|waypoint| distance_to_reference(&tree, waypoint, corridor_m))
+                .collect();
+
+            let mut section: Option<OffCorridorSection> = None;
+            for (i, &distance) in distances.iter().enumerate() {
+                report.max_excursion_m = report.max_excursion_m.max(distance);
+                let outside = distance > corridor_m;
+
+                // Share of the line from the previous point that lies outside
+                let mut off_length = 0.0;
+                if i > 0 {
+                    let length =
+                        Route::haversine_distance(&segment.points[i - 1], &segment.points[i]);
+                    let previous_outside = distances[i - 1] > corridor_m;
+                    let outside_share = match (previous_outside, outside) {
+                        (false, false) => 0.0,
+                        (true, true) => 1.0,
+                        _ => 0.5,
+                    };
+                    report.total_length_m += length;
+                    report.inside_length_m += length * (1.0 - outside_share);
+                    off_length = length * outside_share;
+                }
+
+                if outside {
+                    let current = section.get_or_insert(OffCorridorSection {
+                        track_index,
+                        segment_index,
+                        start_point: i,
+                        end_point: i,
+                        length_m: 0.0,
+                        max_distance_m: 0.0,
+                    });
+                    current.end_point = i;
+                    current.length_m += off_length;
+                    current.max_distance_m = current.max_distance_m.max(distance);
+                } else if let Some(mut finished) = section.take() {
+                    // The half line back into the corridor belongs to the section it leaves
+                    finished.length_m += off_length;
+                    report.off_corridor.push(finished);
+                }
+            }
+            report.off_corridor.extend(section);
+        }
+    }
+    Ok(report)
+}
+
+/// Fraction (`0.0..=1.0`) of the two routes that overlap, buffering each by `buffer_m` meters
+///
+/// Each route is measured against the other with [`compare_routes`]; the
+/// result is the length of both routes found inside the other's buffer over
+/// their combined length, so it is symmetric and a short route lying along a
+/// long one doesn't count as a full overlap.
+pub fn overlap_fraction(first: &Arc<Route>, second: &Arc<Route>, buffer_m: f64) -> Result<f64> {
+    let first_in_second = compare_routes(second, first, buffer_m)?;
+    let second_in_first = compare_routes(first, second, buffer_m)?;
+    let total = first_in_second.total_length_m + second_in_first.total_length_m;
+    if total > 0.0 {
+        Ok((first_in_second.inside_length_m + second_in_first.inside_length_m) / total)
+    } else {
+        Ok((first_in_second.fraction_inside() + second_in_first.fraction_inside()) / 2.0)
+    }
+}
+
+/// Distance, overlap and elevation gain differences between two routes
+pub fn diff_routes(first: &Arc<Route>, second: &Arc<Route>, buffer_m: f64) -> Result<RouteDiff> {
+    #[cfg(feature = "profiling")]
+    profiling::scope!("compare::diff_routes");
+
+    let elevation_gain_delta_m = match (first.elevation_gain(), second.elevation_gain()) {
+        (Some(first), Some(second)) => Some(second - first),
+        _ => None,
+    };
+    Ok(RouteDiff {
+        distance_delta_m: second.total_distance() - first.total_distance(),
+        overlap: overlap_fraction(first, second, buffer_m)?,
+        elevation_gain_delta_m,
+    })
+}
+
+/// Ground distance (meters) from a waypoint to the nearest reference line
+///
+/// Searches the corridor first, as most points of a followed route are
+/// found there, then widens the search until something is found.
+fn distance_to_reference(tree: &Quadtree, waypoint: &gpx::Waypoint, corridor_m: f64) -> f64 {
+    let point = waypoint.point();
+    let mercator = utils::wgs84_to_mercator(point.y(), point.x());
+    // Mercator stretches distances by 1 / cos(latitude)
+    let mercator_per_meter = 1.0 / point.y().to_radians().cos().max(1e-6);
+
+    let mut radius = (corridor_m * mercator_per_meter).max(1.0);
+    loop {
+        if let Some(distance) = tree.nearest_line_distance(mercator, radius) {
+            return distance / mercator_per_meter;
+        }
+        if radius > utils::EARTH_SIZE_METERS {
+            return f64::INFINITY;
+        }
+        radius *= 4.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RouteBuilder, TrackPoint};
+
+    /// One segment through the given (lat, lon) points
+    fn route_through(points: &[(f64, f64)]) -> Arc<Route> {
+        RouteBuilder::new("Test")
+            .add_segment(points.iter().map(|&(lat, lon)| TrackPoint::new(lat, lon)))
+            .build()
+            .unwrap()
+    }
+
+    /// A straight east-west reference with long lines, so nearest-line (not nearest-point)
+    /// distance matters
+    fn reference() -> Arc<Route> {
+        route_through(&[(0.0, 0.0), (0.0, 0.05), (0.0, 0.1)])
+    }
+
+    #[test]
+    fn followed_route_is_fully_inside() {
+        // ~11 m north of the reference, far from any reference point
+        let recorded: Vec<(f64, f64)> = (0..=20).map(|i| (0.0001, i as f64 * 0.005)).collect();
+        let recorded = route_through(&recorded);
+
+        let report = compare_routes(&reference(), &recorded, 50.0).unwrap();
+        assert!(report.off_corridor.is_empty());
+        assert!((report.fraction_inside() - 1.0).abs() < 1e-9);
+        assert!((report.max_excursion_m - 11.1).abs() < 0.5);
+    }
+
+    #[test]
+    fn detour_is_reported_as_an_off_corridor_section() {
+        // Points every ~550 m; points 4..=6 leave the reference by ~1.1 km
+        let mut recorded: Vec<(f64, f64)> = (0..=10).map(|i| (0.0, i as f64 * 0.005)).collect();
+        for point in &mut recorded[4..=6] {
+            point.0 = 0.01;
+        }
+        let recorded = route_through(&recorded);
+
+        let report = compare_routes(&reference(), &recorded, 50.0).unwrap();
+        assert_eq!(report.off_corridor.len(), 1);
+        let section = &report.off_corridor[0];
+        assert_eq!((section.start_point, section.end_point), (4, 6));
+        assert!((section.max_distance_m - 1112.0).abs() < 5.0);
+        assert_eq!(report.max_excursion_m, section.max_distance_m);
+
+        // Two lines fully outside, two crossing the corridor edge (half each)
+        let outside = report.total_length_m - report.inside_length_m;
+        assert!((section.length_m - outside).abs() < 1e-6);
+        assert!(report.fraction_inside() > 0.5 && report.fraction_inside() < 0.9);
+    }
+
+    #[test]
+    fn identical_routes_overlap_fully() {
+        let route = reference();
+        let same = Route::new(route.gpx_data().clone()).unwrap();
+        assert!((overlap_fraction(&route, &same, 10.0).unwrap() - 1.0).abs() < 1e-9);
+
+        let diff = diff_routes(&route, &same, 10.0).unwrap();
+        assert_eq!(diff.distance_delta_m, 0.0);
+        assert_eq!(diff.elevation_gain_delta_m, None);
+    }
+
+    #[test]
+    fn disjoint_routes_do_not_overlap() {
+        let other = route_through(&[(1.0, 0.0), (1.0, 0.05), (1.0, 0.1)]);
+        assert_eq!(overlap_fraction(&reference(), &other, 50.0).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn diff_reports_elevation_gain_delta() {
+        let with_elevation = |elevations: &[f64]| {
+            let points = [0.0, 0.01, 0.02]
+                .into_iter()
+                .zip(elevations)
+                .map(|(lon, &ele)| TrackPoint {
+                    ele: Some(ele),
+                    ..TrackPoint::new(0.0, lon)
+                });
+            RouteBuilder::new("Test")
+                .add_segment(points)
+                .build()
+                .unwrap()
+        };
+        let first = with_elevation(&[100.0, 150.0, 120.0]);
+        let second = with_elevation(&[100.0, 180.0, 200.0]);
+        assert_eq!(first.elevation_gain(), Some(50.0));
+
+        let diff = diff_routes(&first, &second, 10.0).unwrap();
+        assert_eq!(diff.elevation_gain_delta_m, Some(50.0));
+        assert!((diff.overlap - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn far_away_route_finds_the_distance_by_widening() {
+        let recorded = route_through(&[(1.0, 0.05), (1.0, 0.06)]);
+        let report = compare_routes(&reference(), &recorded, 50.0).unwrap();
+        assert_eq!(report.fraction_inside(), 0.0);
+        // One degree of latitude
+        assert!((report.max_excursion_m - 111_195.0).abs() < 500.0);
+    }
+}
@@ -7,9 +7,28 @@
 //! # Architecture
 //!
 //! - **[`Route`]**: Immutable storage for parsed GPX data
+//! - **[`RouteBuilder`]**: Builds a [`Route`] from plain coordinates, without GPX
 //! - **[`Quadtree`]**: Spatial index with Earth-rooted structure and LOD support
 //! - **[`SimplifiedSegment`]**: External index references with LOD simplification
+//! - **[`TrackProfile`]**: Cumulative distance and time along a [`Route`]
 //! - **[`RouteCollection`]**: High-level manager for routes and queries
+//! - **[`compare_routes`]**: How closely one route follows another
+//! - **[`diff_routes`]**: Distance, overlap and climb differences between two routes
+//!
+//! # Display vs. Export Simplification
+//!
+//! [`RouteCollection::query_visible`] simplifies for the screen: its tolerance
+//! follows the viewport, screen size and LOD bias, and results are clipped to
+//! the viewport. [`RouteCollection::simplify_global`] simplifies for export
+//! with one tolerance in meters, independent of any view. Don't feed one's
+//! results where the other is expected.
+//!
+//! # Thread Safety
+//!
+//! [`RouteCollection`], [`Route`], [`Quadtree`] and [`SimplifiedSegment`] are
+//! `Send + Sync`, checked at compile time. Queries take `&self` and may run
+//! concurrently; mutations take `&mut self`, so sharing a collection that
+//! changes means a lock such as `RwLock`.
 //!
 //! # Performance Characteristics
 //!
@@ -17,17 +36,31 @@
 //! - **Query Time**: O(log D + K) where D=depth, K=results
 //! - **Memory**: O(N) for raw data + O(S×I) for index (S=segments, I=indices per segment)
 
+mod builder;
 mod collection;
+mod compare;
+mod profile;
 mod quadtree;
 mod route;
 mod segment;
 pub mod utils;
 
 // Public API exports
-pub use collection::{CollectionInfo, Config, RouteCollection};
-pub use quadtree::Quadtree;
-pub use route::Route;
-pub use segment::{SegmentPart, SimplifiedSegment};
+pub use builder::{RouteBuilder, TrackPoint};
+pub use collection::{
+    CollectionInfo, Config, ESTIMATED_MEMORY_PER_GPX_BYTE, RouteCollection,
+    estimate_memory_for_gpx_bytes, parse_gpx,
+};
+pub use compare::{
+    CorridorReport, OffCorridorSection, RouteDiff, compare_routes, diff_routes, overlap_fraction,
+};
+pub use profile::TrackProfile;
+pub use quadtree::{
+    DEFAULT_CHUNKING_AGGRESSIVENESS, DEFAULT_EXTREMA_PROMINENCE_METERS, DEFAULT_MAX_SEGMENT_POINTS,
+    DEFAULT_SHARP_TURN_DEGREES, Quadtree,
+};
+pub use route::{Climb, DEFAULT_CLIMB_THRESHOLD_M, Route};
+pub use segment::{RawPointRef, SegmentPart, SimplifiedSegment};
 
 /// Error types for the data module
 #[derive(Debug, thiserror::Error)]
@@ -49,6 +82,15 @@ pub enum DataError {
 
     #[error("Empty route")]
     EmptyRoute,
+
+    #[error("File is empty")]
+    EmptyFile,
+
+    #[error("No route at index {0}")]
+    RouteNotFound(usize),
+
+    #[error("Index invariant violated: {0}")]
+    InvariantViolated(String),
 }
 
 pub type Result<T> = std::result::Result<T, DataError>;
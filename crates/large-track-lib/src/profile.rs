@@ -0,0 +1,281 @@
+//! Cumulative distance, time and speed along a route
+//!
+//! Elevation plots, distance markers, playback and hover readouts all need to
+//! know how far along a route a point is. [`Route::profile`](crate::Route::profile)
+//! computes that once per route and caches it, so they all agree.
+
+use crate::{Route, utils};
+
+/// Per-point cumulative distance, elapsed time and speed of a route
+///
+/// Points are numbered in route order: every point of the first segment of
+/// the first track, then the next segment, and so on. Use
+/// [`TrackProfile::location`] to turn such an index back into track, segment
+/// and point indices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TrackProfile {
+    /// Meters from the start of the route, never decreasing
+    distances: Vec<f64>,
+    /// Seconds since the first timestamp, never decreasing; `None` for points without a time
+    times: Vec<Option<f64>>,
+    /// Meters per second over the line from the previous point; `None`
+    /// unless both carry a time in the same segment and the time advances
+    speeds: Vec<Option<f64>>,
+    /// Indices of the points with a time, ascending
+    timed: Vec<usize>,
+    /// (track, segment, index of its first point) for every non-empty segment
+    segment_starts: Vec<(usize, usize, usize)>,
+    /// Time of the first timestamped point
+    start_time: Option<time::OffsetDateTime>,
+}
+
+impl TrackProfile {
+    /// Walk every point of `route` once
+    ///
+    /// Distance follows the same rules as [`Route::total_distance`]: nothing is
+    /// added between segments or across points outside Web Mercator bounds, so
+    /// the last distance equals the route's total. A timestamp earlier than
+    /// one before it is held at the latest time seen, keeping times sorted.
+    pub(crate) fn new(route: &Route) -> Self {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("profile::new");
+
+        let mut profile = Self {
+            distances: Vec::with_capacity(route.total_points()),
+            times: Vec::with_capacity(route.total_points()),
+            speeds: Vec::with_capacity(route.total_points()),
+            ..Default::default()
+        };
+        let mut distance = 0.0;
+        let mut latest_time: Option<f64> = None;
+        for (track_index, track) in route.tracks().iter().enumerate() {
+            for (segment_index, segment) in track.segments.iter().enumerate() {
+                if segment.points.is_empty() {
+                    continue;
+                }
+                profile
+                    .segment_starts
+                    .push((track_index, segment_index, profile.distances.len()));
+
+                let mut previous: Option<&gpx::Waypoint> = None;
+                // Distance and time of the previous point in this segment
+                let mut previous_step: Option<(f64, Option<f64>)> = None;
+                for waypoint in &segment.points {
+                    if utils::is_valid_mercator(&utils::waypoint_to_mercator(waypoint)) {
+                        if let Some(previous) = previous {
+                            distance += Route::haversine_distance(previous, waypoint);
+                        }
+                        previous = Some(waypoint);
+                    } else {
+                        previous = None;
+                    }
+
+                    let time = waypoint.time.map(|time| {
+                        let time = time::OffsetDateTime::from(time);
+                        let start = *profile.start_time.get_or_insert(time);
+                        let elapsed = (time - start).as_seconds_f64();
+                        let elapsed = latest_time.map_or(elapsed, |latest| elapsed.max(latest));
+                        latest_time = Some(elapsed);
+                        elapsed
+                    });
+                    let speed = match (previous_step, time) {
+                        (Some((from_distance, Some(from_time))), Some(to_time))
+                            if to_time > from_time =>
+                        {
+                            Some((distance - from_distance) / (to_time - from_time))
+                        }
+                        _ => None,
+                    };
+                    previous_step = Some((distance, time));
+
+                    if time.is_some() {
+                        profile.timed.push(profile.distances.len());
+                    }
+                    profile.distances.push(distance);
+                    profile.times.push(time);
+                    profile.speeds.push(speed);
+                }
+            }
+        }
+        profile
+    }
+
+    /// Number of points, the length of every per-point slice
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    /// Whether the profile has no points (never the case for a [`Route`])
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    /// Meters from the start of the route to each point
+    pub fn distances(&self) -> &[f64] {
+        &self.distances
+    }
+
+    /// Seconds from the first timestamp to each point, `None` where a point has no time
+    pub fn times(&self) -> &[Option<f64>] {
+        &self.times
+    }
+
+    /// Speed in meters per second arriving at each point, without smoothing
+    pub fn speeds(&self) -> &[Option<f64>] {
+        &self.speeds
+    }
+
+    /// Time of the first timestamped point; `times` count from here
+    pub fn start_time(&self) -> Option<time::OffsetDateTime> {
+        self.start_time
+    }
+
+    /// Seconds from the first to the last timestamp, `None` without timestamps
+    pub fn duration(&self) -> Option<f64> {
+        self.timed.last().and_then(|&last| self.times[last])
+    }
+
+    /// Last point at most `meters` from the start, or the first point if `meters` is before it
+    pub fn index_at_distance(&self, meters: f64) -> usize {
+        self.distances
+            .partition_point(|&distance| distance <= meters)
+            .saturating_sub(1)
+    }
+
+    /// Last timestamped point at most `seconds` after the start
+    ///
+    /// Points without a time are never returned. A time before the start gives
+    /// the first timestamped point; `None` only if no point has a time.
+    pub fn index_at_time(&self, seconds: f64) -> Option<usize> {
+        let position = self
+            .timed
+            .partition_point(|&index| self.times[index].is_some_and(|time| time <= seconds));
+        self.timed.get(position.saturating_sub(1)).copied()
+    }
+
+    /// Track, segment and point indices of the point at `index`
+    pub fn location(&self, index: usize) -> Option<(usize, usize, usize)> {
+        if index >= self.len() {
+            return None;
+        }
+        let segment = self
+            .segment_starts
+            .partition_point(|&(_, _, start)| start <= index)
+            - 1;
+        let (track_index, segment_index, start) = self.segment_starts[segment];
+        Some((track_index, segment_index, index - start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lon: f64, seconds: Option<i64>) -> gpx::Waypoint {
+        let mut waypoint = gpx::Waypoint::new(geo::Point::new(lon, 0.0));
+        waypoint.time = seconds.map(|seconds| {
+            time::OffsetDateTime::from_unix_timestamp(1_700_000_000 + seconds)
+                .unwrap()
+                .into()
+        });
+        waypoint
+    }
+
+    fn route(segments: Vec<Vec<gpx::Waypoint>>) -> std::sync::Arc<Route> {
+        let mut track = gpx::Track::new();
+        for points in segments {
+            let mut segment = gpx::TrackSegment::new();
+            segment.points = points;
+            track.segments.push(segment);
+        }
+        let mut gpx = gpx::Gpx::default();
+        gpx.tracks.push(track);
+        Route::new(gpx).unwrap()
+    }
+
+    #[test]
+    fn distances_are_monotonic_and_end_at_the_total() {
+        // ~11 m steps, with the second segment starting far away
+        let route = route(vec![
+            (0..5).map(|i| point(i as f64 * 0.0001, None)).collect(),
+            (0..5)
+                .map(|i| point(1.0 + i as f64 * 0.0001, None))
+                .collect(),
+        ]);
+        let profile = route.profile();
+        assert_eq!(profile.len(), 10);
+        assert!(profile.distances().windows(2).all(|w| w[0] <= w[1]));
+        assert!((profile.distances()[9] - route.total_distance()).abs() < 1e-6);
+        // No distance is added for the jump between segments
+        assert_eq!(profile.distances()[4], profile.distances()[5]);
+        assert!(profile.duration().is_none());
+        assert!(profile.index_at_time(0.0).is_none());
+
+        assert_eq!(profile.index_at_distance(-1.0), 0);
+        assert_eq!(profile.index_at_distance(profile.distances()[2]), 2);
+        assert_eq!(profile.index_at_distance(profile.distances()[2] + 1.0), 2);
+        // Both segment ends share a distance; the later point wins
+        assert_eq!(profile.index_at_distance(profile.distances()[4]), 5);
+        assert_eq!(profile.index_at_distance(1e9), 9);
+
+        assert_eq!(profile.location(0), Some((0, 0, 0)));
+        assert_eq!(profile.location(6), Some((0, 1, 1)));
+        assert_eq!(profile.location(10), None);
+        // The profile is computed once
+        assert!(std::ptr::eq(profile, route.profile()));
+    }
+
+    #[test]
+    fn missing_timestamps_are_skipped() {
+        let route = route(vec![
+            vec![
+                point(0.0, Some(0)),
+                point(0.0001, None),
+                point(0.0002, Some(10)),
+            ],
+            vec![point(0.0003, None), point(0.0004, None)],
+            vec![
+                point(0.0005, Some(60)),
+                point(0.0006, Some(50)),
+                point(0.0007, Some(70)),
+            ],
+        ]);
+        let profile = route.profile();
+        assert_eq!(
+            profile.times(),
+            &[
+                Some(0.0),
+                None,
+                Some(10.0),
+                None,
+                None,
+                Some(60.0),
+                // Time going backwards is held, keeping times sorted
+                Some(60.0),
+                Some(70.0)
+            ]
+        );
+        assert_eq!(profile.duration(), Some(70.0));
+        assert_eq!(
+            profile.start_time().unwrap().unix_timestamp(),
+            1_700_000_000
+        );
+
+        assert_eq!(profile.index_at_time(-5.0), Some(0));
+        assert_eq!(profile.index_at_time(5.0), Some(0));
+        assert_eq!(profile.index_at_time(10.0), Some(2));
+        assert_eq!(profile.index_at_time(30.0), Some(2));
+        assert_eq!(profile.index_at_time(60.0), Some(6));
+        assert_eq!(profile.index_at_time(1e9), Some(7));
+
+        // Speed needs a time on both ends within one segment
+        let speeds = profile.speeds();
+        assert_eq!(speeds[0], None);
+        assert_eq!(speeds[1], None);
+        assert_eq!(speeds[2], None);
+        assert_eq!(speeds[5], None);
+        assert_eq!(speeds[6], None);
+        let step = profile.distances()[7] - profile.distances()[6];
+        assert!((speeds[7].unwrap() - step / 10.0).abs() < 1e-9);
+    }
+}
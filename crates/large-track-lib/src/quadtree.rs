@@ -4,11 +4,13 @@
 //! queries with level-of-detail support. The tree stores segments at their appropriate
 //! level and generates simplified versions lazily on-demand.
 
-use crate::{DataError, Result, Route, SegmentPart, SimplifiedSegment, utils};
-use dashmap::DashMap;
+use crate::{DataError, RawPointRef, Result, Route, SegmentPart, SimplifiedSegment, utils};
+use dashmap::{DashMap, DashSet};
 use geo::{Coord, LineString, Point, Rect, SimplifyVwIdx};
 use rayon::prelude::*;
 use smallvec::SmallVec;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
 #[cfg(feature = "serde")]
@@ -17,9 +19,94 @@ use serde::{Deserialize, Serialize};
 /// Maximum depth of the quadtree to prevent infinite recursion
 const MAX_DEPTH: u32 = 20;
 
-/// Minimum number of points required to recurse into children
+/// Viewports narrower than this (in meters) are queried as if they were this wide
+///
+/// At extreme zoom the map can hand us sub-meter or even degenerate (zero or
+/// NaN width) viewports; none of them can show more detail than the finest
+/// stored level, so they all resolve to the same LOD.
+const MIN_VIEWPORT_METERS: f64 = 1.0;
+
+/// Smallest simplification tolerance (in meters) a query will use
+///
+/// Well below GPS precision; anything smaller only costs simplification time.
+const MIN_TOLERANCE_METERS: f64 = 0.01;
+
+/// Consecutive points closer than this (in meters, on each axis) are one point
+///
+/// Far below GPS precision: only repeated fixes of a device standing still
+/// fall within it.
+const DUPLICATE_POINT_EPSILON_METERS: f64 = 1e-3;
+
+/// Minimum number of points required to recurse into children (at the default aggressiveness)
 const MIN_POINTS_FOR_RECURSION: usize = 8;
 
+/// Most nodes [`Quadtree::check_invariants`] queries its own bounding box at
+const INVARIANT_QUERY_SAMPLES: usize = 64;
+
+/// Default chunking aggressiveness, matching the original fixed subdivision rules
+pub const DEFAULT_CHUNKING_AGGRESSIVENESS: f64 = 0.5;
+
+/// Default [`crate::Config::max_segment_points`]: segments with more points
+/// are split into pieces when indexed
+///
+/// Far above any single recorded activity, but stops a single multi-day
+/// segment from becoming one huge chunk that every query has to simplify.
+pub const DEFAULT_MAX_SEGMENT_POINTS: usize = 250_000;
+
+/// Suggested turn angle, in degrees, for [`Quadtree::set_sharp_turn_threshold`]
+///
+/// Hairpins and switchbacks turn by well over this, while the gentle bends
+/// of a road or trail stay below it.
+pub const DEFAULT_SHARP_TURN_DEGREES: f64 = 120.0;
+
+/// Suggested prominence, in meters, for [`Quadtree::anchor_elevation_extrema`]
+///
+/// Summits and valley floors of real climbs stand out by well over this,
+/// while barometric drift and GPS elevation noise stay below it.
+pub const DEFAULT_EXTREMA_PROMINENCE_METERS: f64 = 20.0;
+
+/// Subdivision rules used while inserting segments
+///
+/// Derived from a single aggressiveness value in `[0, 1]`. Aggressive chunking
+/// splits segments into more, smaller chunks stored deeper in the tree, so a
+/// query touching a small viewport scans fewer off-screen points (faster pans
+/// at high zoom). The price is more `RawSegment`s overall: more per-segment
+/// overhead when querying large viewports, more cache entries and more
+/// duplicated boundary points. Conservative chunking does the opposite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ChunkingParams {
+    /// Segments with fewer points are never split further
+    min_points_for_recursion: usize,
+    /// Segments with more points keep descending even when they fit in one
+    /// child, until they are split (`None` = only split segments that span children)
+    max_chunk_points: Option<usize>,
+}
+
+impl ChunkingParams {
+    /// Map an aggressiveness in `[0, 1]` to subdivision rules
+    ///
+    /// - `0.0`: only segments with 64+ points spanning several children are split
+    /// - `0.5` (default): the original rules (8+ points spanning several children)
+    /// - `1.0`: split anything with 2+ points, and push chunks above 64 points
+    ///   down the tree until they split
+    fn from_aggressiveness(aggressiveness: f64) -> Self {
+        let a = aggressiveness.clamp(0.0, 1.0);
+        // Geometric interpolation: 64 at a=0, 8 at a=0.5, 1 (clamped to 2) at a=1
+        let min_points_for_recursion = (MIN_POINTS_FOR_RECURSION as f64 * 8f64.powf(1.0 - 2.0 * a))
+            .round()
+            .max(2.0) as usize;
+        // Above the default, cap chunk size from 4096 points down to 64 at a=1
+        let max_chunk_points = (a > DEFAULT_CHUNKING_AGGRESSIVENESS).then(|| {
+            2f64.powf(12.0 - 12.0 * (a - DEFAULT_CHUNKING_AGGRESSIVENESS))
+                .round() as usize
+        });
+        Self {
+            min_points_for_recursion,
+            max_chunk_points,
+        }
+    }
+}
+
 /// A raw segment stored in the quadtree (before simplification)
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -39,20 +126,56 @@ struct RawSegment {
     /// Optional mapping from chunk indices to original segment indices
     /// (used when this is a chunked portion of a larger segment)
     original_indices: Option<Arc<Vec<usize>>>,
+    /// Indices into `mercator_points` of the points shared with chunks stored
+    /// elsewhere, which simplification must keep (see [`chunk_anchor_indices`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    chunk_anchors: Option<Arc<Vec<usize>>>,
+    /// Indices into the original segment of elevation peaks and valleys that
+    /// simplification must keep (see [`Quadtree::anchor_elevation_extrema`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    elevation_anchors: Option<Arc<Vec<usize>>>,
 }
 
 /// Cache key for simplified segments
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 struct SimplificationCacheKey {
-    /// Pointer to the route (using Arc's address as part of key)
-    route_ptr: usize,
+    /// [`Route::content_id`] of the route, so the same file loaded again hits its entries
+    route_id: u64,
     track_index: usize,
     segment_index: usize,
     /// Tolerance level (discretized to avoid floating point issues)
     tolerance_level: u32,
     /// Hash of chunk bounds (first_idx, last_idx, len) for chunked segments
     chunk_hash: Option<(usize, usize, usize)>,
+    /// Sharp-turn threshold in tenths of a degree, if sharp turns are kept
+    sharp_turn_tenths: Option<u32>,
+}
+
+impl SimplificationCacheKey {
+    /// Build the cache key for a stored segment at the given LOD level
+    ///
+    /// `sharp_turn_degrees` is the [`Quadtree::set_sharp_turn_threshold`] in
+    /// effect, so toggling it never returns indices simplified the other way.
+    fn for_segment(raw: &RawSegment, level: u32, sharp_turn_degrees: Option<f64>) -> Self {
+        // For chunked segments, we need a unique cache key that includes the chunk identity
+        let chunk_hash = raw.original_indices.as_ref().map(|indices| {
+            // Use first and last original index as part of key
+            let first = indices.first().copied().unwrap_or(0);
+            let last = indices.last().copied().unwrap_or(0);
+            (first, last, indices.len())
+        });
+
+        // The quadtree level doubles as the discretized tolerance level
+        Self {
+            route_id: raw.route.content_id(),
+            track_index: raw.track_index,
+            segment_index: raw.segment_index,
+            tolerance_level: level,
+            chunk_hash,
+            sharp_turn_tenths: sharp_turn_degrees.map(|degrees| (degrees * 10.0).round() as u32),
+        }
+    }
 }
 
 /// Root container for the quadtree spatial index
@@ -65,6 +188,12 @@ pub struct Quadtree {
     reference_pixel_viewport: Rect<f64>,
     /// LOD bias factor (higher = more detail retained)
     bias: f64,
+    /// Minimum turn angle, in degrees, of dropped points put back after simplification
+    #[cfg_attr(feature = "serde", serde(default))]
+    sharp_turn_degrees: Option<f64>,
+    /// Consecutive duplicate points left out of the segments built into this tree
+    #[cfg_attr(feature = "serde", serde(default))]
+    duplicate_points_removed: usize,
     /// Cache for simplified segments (shared across all queries)
     /// Uses DashMap for lock-free concurrent access
     /// This is rebuilt at runtime, not serialized
@@ -73,6 +202,12 @@ pub struct Quadtree {
         serde(skip, default = "default_simplification_cache")
     )]
     simplification_cache: Arc<DashMap<SimplificationCacheKey, Arc<Vec<usize>>>>,
+    /// Cache keys being simplified in the background by progressive queries
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pending_refinements: Arc<DashSet<SimplificationCacheKey>>,
+    /// Background simplifications not yet moved into the cache
+    #[cfg_attr(feature = "serde", serde(skip))]
+    refinements: Arc<DashMap<SimplificationCacheKey, Arc<Vec<usize>>>>,
 }
 
 #[cfg(feature = "serde")]
@@ -122,7 +257,11 @@ impl Quadtree {
             root: QuadtreeNode::new_root(reference_pixel_viewport, bias),
             reference_pixel_viewport,
             bias,
+            sharp_turn_degrees: None,
+            duplicate_points_removed: 0,
             simplification_cache: Arc::new(DashMap::new()),
+            pending_refinements: Arc::new(DashSet::new()),
+            refinements: Arc::new(DashMap::new()),
         }
     }
 
@@ -130,11 +269,42 @@ impl Quadtree {
     ///
     /// This can be called in parallel for multiple routes and the results merged.
     /// The `route_index` is used for per-route coloring in the viewer.
+    /// `chunking_aggressiveness` (in `[0, 1]`, default
+    /// [`DEFAULT_CHUNKING_AGGRESSIVENESS`]) controls how eagerly segments are
+    /// split into smaller chunks at node boundaries. Runs of consecutive
+    /// identical points are stored as their first point, see
+    /// [`Quadtree::duplicate_points_removed`].
     pub fn new_with_route(
         route: Arc<Route>,
         route_index: usize,
         pixel_viewport: Rect<f64>,
         bias: f64,
+        chunking_aggressiveness: f64,
+    ) -> Result<Self> {
+        Self::new_with_route_split(
+            route,
+            route_index,
+            pixel_viewport,
+            bias,
+            chunking_aggressiveness,
+            DEFAULT_MAX_SEGMENT_POINTS,
+        )
+    }
+
+    /// Build a quadtree for a single route, splitting overly long segments
+    ///
+    /// Like [`Self::new_with_route`], but segments with more than
+    /// `max_segment_points` points are stored as consecutive pieces of at most
+    /// that many points. Neighbouring pieces share their boundary point, so
+    /// the drawn line has no gap, and every piece still maps back to the
+    /// original GPX point indices.
+    pub fn new_with_route_split(
+        route: Arc<Route>,
+        route_index: usize,
+        pixel_viewport: Rect<f64>,
+        bias: f64,
+        chunking_aggressiveness: f64,
+        max_segment_points: usize,
     ) -> Result<Self> {
         // Profile per-route quadtree construction and mark the phase where segments are inserted.
         #[cfg(feature = "profiling")]
@@ -158,24 +328,45 @@ impl Quadtree {
         }
 
         let mut quadtree = Self::new(pixel_viewport, bias);
+        let chunking = ChunkingParams::from_aggressiveness(chunking_aggressiveness);
 
-        // Insert all track segments from the route
-        for (track_idx, track) in route.tracks().iter().enumerate() {
-            for (segment_idx, segment) in track.segments.iter().enumerate() {
-                if segment.points.is_empty() {
-                    continue;
-                }
-
-                // Convert points to Web Mercator (do this once, cache it)
-                let mercator_points: Vec<Point<f64>> = segment
-                    .points
+        // Convert all segments to Web Mercator in parallel (once, then cached),
+        // keeping track order so the tree comes out the same as a serial build
+        let segments: Vec<(usize, usize, &[gpx::Waypoint])> = route
+            .tracks()
+            .iter()
+            .enumerate()
+            .flat_map(|(track_idx, track)| {
+                track
+                    .segments
                     .iter()
-                    .map(|wp| utils::wgs84_to_mercator(wp.point().y(), wp.point().x()))
-                    .collect();
-
-                // Compute bounding box once
-                let bounding_box = compute_segment_bbox(&mercator_points);
+                    .enumerate()
+                    .filter(|(_, segment)| !segment.points.is_empty())
+                    .map(move |(segment_idx, segment)| {
+                        (track_idx, segment_idx, segment.points.as_slice())
+                    })
+            })
+            .collect();
+        // Stationary runs are dropped here, so simplification never sees their zero-area triangles
+        type Converted = (usize, usize, Vec<Point<f64>>, Rect<f64>, Option<Vec<usize>>);
+        let converted: Vec<Converted> = segments
+            .into_par_iter()
+            .map(|(track_idx, segment_idx, points)| {
+                let (mercator_points, bounding_box) = segment_to_mercator(points);
+                // The bounding box still covers the left-out duplicates, which raw queries return
+                let (mercator_points, kept) = dedup_consecutive_points(mercator_points);
+                (track_idx, segment_idx, mercator_points, bounding_box, kept)
+            })
+            .collect();
 
+        // Insert all track segments from the route
+        let max_segment_points = max_segment_points.max(2);
+        for (track_idx, segment_idx, mercator_points, bounding_box, kept) in converted {
+            if let Some(kept) = &kept {
+                let original_len = route.tracks()[track_idx].segments[segment_idx].points.len();
+                quadtree.duplicate_points_removed += original_len - kept.len();
+            }
+            if mercator_points.len() <= max_segment_points {
                 let raw_segment = RawSegment {
                     route: route.clone(),
                     route_index,
@@ -183,19 +374,155 @@ impl Quadtree {
                     segment_index: segment_idx,
                     mercator_points: Arc::new(mercator_points),
                     bounding_box,
-                    original_indices: None, // Full segment, no remapping needed
+                    // Full segment: no remapping needed unless duplicates were left out
+                    original_indices: kept.map(Arc::new),
+                    chunk_anchors: None,
+                    elevation_anchors: None,
                 };
 
                 // Insert into quadtree at appropriate level
                 quadtree
                     .root
-                    .insert_segment(raw_segment, pixel_viewport, bias);
+                    .insert_segment(raw_segment, pixel_viewport, bias, chunking);
+                continue;
+            }
+
+            let pieces = split_segment_ranges(mercator_points.len(), max_segment_points);
+            tracing::warn!(
+                "Splitting track {} segment {} ({} points) into {} pieces of at most {} points",
+                track_idx,
+                segment_idx,
+                mercator_points.len(),
+                pieces.len(),
+                max_segment_points
+            );
+            for range in pieces {
+                let chunk_anchors = vec![0, range.end() - range.start()];
+                let piece_points = mercator_points[range.clone()].to_vec();
+                let original_indices = match &kept {
+                    Some(kept) => range.map(|k| kept[k]).collect(),
+                    None => range.collect(),
+                };
+                let mut bounding_box = compute_segment_bbox(&piece_points);
+                if kept.is_some() {
+                    // Left-out duplicates lie within the epsilon of a kept point
+                    let margin = Coord {
+                        x: DUPLICATE_POINT_EPSILON_METERS,
+                        y: DUPLICATE_POINT_EPSILON_METERS,
+                    };
+                    bounding_box =
+                        Rect::new(bounding_box.min() - margin, bounding_box.max() + margin);
+                }
+                let raw_segment = RawSegment {
+                    route: route.clone(),
+                    route_index,
+                    track_index: track_idx,
+                    segment_index: segment_idx,
+                    bounding_box,
+                    mercator_points: Arc::new(piece_points),
+                    original_indices: Some(Arc::new(original_indices)),
+                    chunk_anchors: Some(Arc::new(chunk_anchors)),
+                    elevation_anchors: None,
+                };
+                quadtree
+                    .root
+                    .insert_segment(raw_segment, pixel_viewport, bias, chunking);
             }
         }
 
         Ok(quadtree)
     }
 
+    /// Put back dropped points that turn by at least `degrees` when simplifying
+    ///
+    /// Visvalingam-Whyatt measures the area a point adds, so the apex of a
+    /// narrow switchback can go long before the map is zoomed out enough for
+    /// the hairpin to be invisible, and the line then cuts straight across.
+    /// With a threshold, simplification is followed by a pass that puts back
+    /// the apex of every turn of at least `degrees` (0 is straight on, 180 a
+    /// full reversal). Turns are measured over about a pixel's worth of track
+    /// on either side, so GPS jitter below that scale stays dropped. `None`
+    /// (the default) is plain VW.
+    ///
+    /// The threshold is part of the simplification cache key: changing it
+    /// needs no rebuild, and switching back reuses the indices that were
+    /// already simplified. LOD levels precomputed under another threshold are
+    /// not used for the new one.
+    pub fn set_sharp_turn_threshold(&mut self, degrees: Option<f64>) {
+        self.sharp_turn_degrees = degrees;
+    }
+
+    /// Minimum turn angle of re-inserted points, see [`Quadtree::set_sharp_turn_threshold`]
+    pub fn sharp_turn_threshold(&self) -> Option<f64> {
+        self.sharp_turn_degrees
+    }
+
+    /// Consecutive duplicate points left out of the routes built into this tree
+    ///
+    /// Devices standing still keep logging the same position. Each such run
+    /// is indexed as its first point, within [`DUPLICATE_POINT_EPSILON_METERS`],
+    /// and every segment keeps at least two points. Queries still return
+    /// indices into the original GPX points.
+    pub fn duplicate_points_removed(&self) -> usize {
+        self.duplicate_points_removed
+    }
+
+    /// Keep the elevation peaks and valleys of stored segments at every LOD level
+    ///
+    /// Simplification only looks at the track's shape on the map, so the
+    /// summit of a climb along a straight road is as likely to go as any
+    /// other point, and with it whatever is drawn or measured there. This
+    /// finds, once per segment, the points where the elevation turns after
+    /// climbing and then dropping (or dropping and then climbing) by at least
+    /// `min_prominence` meters, and every later simplification of the segment
+    /// keeps them. Segments without elevations get no anchors.
+    ///
+    /// Best called right after building: the anchors change what every level
+    /// simplifies to, so indices cached so far are dropped (see
+    /// [`Quadtree::clear_cache`]).
+    pub fn anchor_elevation_extrema(&mut self, min_prominence: f64) {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::anchor_elevation_extrema");
+
+        // Chunks of one segment share the extrema of the whole segment
+        let mut extrema = HashMap::new();
+        self.root.for_each_segment_mut(&mut |raw| {
+            let key = (
+                Arc::as_ptr(&raw.route) as usize,
+                raw.track_index,
+                raw.segment_index,
+            );
+            raw.elevation_anchors = extrema
+                .entry(key)
+                .or_insert_with(|| {
+                    let points = &raw.route.gpx_data().tracks[raw.track_index].segments
+                        [raw.segment_index]
+                        .points;
+                    let found = elevation_extrema(points, min_prominence);
+                    (!found.is_empty()).then(|| Arc::new(found))
+                })
+                .clone();
+        });
+        self.clear_cache();
+    }
+
+    /// Drop every cached simplification, so later queries simplify again
+    ///
+    /// Cached indices are keyed by segment, LOD level and sharp-turn
+    /// threshold only. Anything else that changes what a segment simplifies
+    /// to must clear them, or queries keep returning the old result. Background
+    /// simplifications still running are discarded when they finish.
+    pub fn clear_cache(&mut self) {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::clear_cache");
+
+        // Fresh maps rather than clearing the shared ones: running jobs hold
+        // the old ones and would put stale results back in
+        self.simplification_cache = Arc::new(DashMap::new());
+        self.pending_refinements = Arc::new(DashSet::new());
+        self.refinements = Arc::new(DashMap::new());
+    }
+
     /// Merge another quadtree into this one
     ///
     /// Both quadtrees must have the same configuration (viewport and bias).
@@ -215,9 +542,52 @@ impl Quadtree {
 
         // Merge root nodes recursively
         self.root.merge_with(other.root)?;
+        self.duplicate_points_removed += other.duplicate_points_removed;
+
+        // Keep any simplifications the other tree already computed (e.g. precomputed LODs).
+        // Keys include the route's content id, so entries from different routes never collide.
+        for (key, indices) in Arc::unwrap_or_clone(other.simplification_cache) {
+            self.simplification_cache.insert(key, indices);
+        }
         Ok(())
     }
 
+    /// Remove every segment belonging to the route at `route_index`
+    ///
+    /// Cached simplifications of the removed segments are dropped as well, so
+    /// the cache doesn't keep growing as files come and go. They are kept while
+    /// another stored route has the same points, since it shares them.
+    /// Emptied nodes are kept; they cost nothing at query time.
+    pub fn remove_route(&mut self, route_index: usize) {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::remove_route");
+
+        let mut removed_routes = HashSet::new();
+        self.root.remove_route(route_index, &mut removed_routes);
+        if removed_routes.is_empty() {
+            return;
+        }
+        let mut remaining = Vec::new();
+        self.root.collect_segments(&mut remaining);
+        for raw in remaining {
+            removed_routes.remove(&raw.route.content_id());
+        }
+        if !removed_routes.is_empty() {
+            self.simplification_cache
+                .retain(|key, _| !removed_routes.contains(&key.route_id));
+            // Before the refinements, so a job finishing in between drops its own result
+            self.pending_refinements
+                .retain(|key| !removed_routes.contains(&key.route_id));
+            self.refinements
+                .retain(|key, _| !removed_routes.contains(&key.route_id));
+        }
+    }
+
+    /// Renumber the routes after `removed_index` one down, once its segments are removed
+    pub fn close_route_gap(&mut self, removed_index: usize) {
+        self.root.close_route_gap(removed_index);
+    }
+
     /// Query for segments intersecting the viewport
     ///
     /// Returns segments at the appropriate LOD level for the given viewport size.
@@ -229,11 +599,87 @@ impl Quadtree {
     ///   The LOD tolerance is adjusted based on the ratio of current screen
     ///   to the reference viewport, ensuring consistent visual quality across screen sizes.
     ///   A bias of 1.0 will produce similar visual results regardless of screen resolution.
+    ///
+    /// Clipped runs include their boundary context; see
+    /// [`Quadtree::query_with_boundary_context`].
     #[inline]
     pub fn query(
         &self,
         geo_viewport: Rect<f64>,
         screen_size: (f64, f64),
+    ) -> Vec<SimplifiedSegment> {
+        self.query_with_boundary_context(geo_viewport, screen_size, true)
+    }
+
+    /// Like [`Quadtree::query`], choosing whether clipped runs get boundary context
+    ///
+    /// With `boundary_context`, each run starts and ends at the nearest
+    /// simplified point outside the viewport, so the drawn line reaches the
+    /// viewport edge. Without it runs only hold points inside the viewport.
+    pub fn query_with_boundary_context(
+        &self,
+        geo_viewport: Rect<f64>,
+        screen_size: (f64, f64),
+        boundary_context: bool,
+    ) -> Vec<SimplifiedSegment> {
+        self.query_inner(geo_viewport, screen_size, boundary_context, false, 0)
+    }
+
+    /// Like [`Quadtree::query_with_boundary_context`], without simplifying on cache misses
+    ///
+    /// Right after a zoom in nothing is cached at the new, finer level, and a
+    /// normal query simplifies every visible segment within the frame. Here a
+    /// segment missing its level is returned at the finest coarser level that
+    /// is cached, and its exact level is simplified on a background rayon task;
+    /// a later query picks the result up. Segments with no cached level at all
+    /// are still simplified right away. Each missing level gets one background
+    /// job however often it is queried; [`Quadtree::pending_refinements`] tells
+    /// when to query again.
+    pub fn query_progressive(
+        &self,
+        geo_viewport: Rect<f64>,
+        screen_size: (f64, f64),
+        boundary_context: bool,
+    ) -> Vec<SimplifiedSegment> {
+        self.query_inner(geo_viewport, screen_size, boundary_context, true, 0)
+    }
+
+    /// Like [`Quadtree::query_with_boundary_context`] (or [`Quadtree::query_progressive`]
+    /// with `progressive`), simplified `lod_reduction` levels coarser than the viewport asks for
+    ///
+    /// Each level doubles the tolerance. Results are cached under the coarser
+    /// level, so switching the reduction on and off doesn't leave coarse
+    /// geometry behind at the finer level.
+    pub fn query_with_lod_reduction(
+        &self,
+        geo_viewport: Rect<f64>,
+        screen_size: (f64, f64),
+        boundary_context: bool,
+        progressive: bool,
+        lod_reduction: u32,
+    ) -> Vec<SimplifiedSegment> {
+        self.query_inner(
+            geo_viewport,
+            screen_size,
+            boundary_context,
+            progressive,
+            lod_reduction,
+        )
+    }
+
+    /// Number of background simplifications started by [`Quadtree::query_progressive`]
+    /// that haven't finished yet
+    pub fn pending_refinements(&self) -> usize {
+        self.pending_refinements.len()
+    }
+
+    fn query_inner(
+        &self,
+        geo_viewport: Rect<f64>,
+        screen_size: (f64, f64),
+        boundary_context: bool,
+        progressive: bool,
+        lod_reduction: u32,
     ) -> Vec<SimplifiedSegment> {
         // Annotate queries with viewport and screen size so traces show the spatial
         // context that caused a heavy query.
@@ -249,7 +695,9 @@ impl Quadtree {
             )
             .as_str()
         );
-        let target_level = self.calculate_target_level(geo_viewport);
+        let target_level = self
+            .calculate_target_level(geo_viewport)
+            .saturating_sub(lod_reduction);
 
         // Calculate base tolerance using reference viewport
         let base_tolerance = QuadtreeNode::calculate_pixel_tolerance(
@@ -266,8 +714,12 @@ impl Quadtree {
         let current_area = screen_width * screen_height;
         // Scale tolerance: larger screens need lower tolerance (more detail)
         // Use sqrt because tolerance is linear while area is quadratic
-        let scale = (reference_area / current_area).sqrt();
-        let target_tolerance = base_tolerance * scale;
+        let scale = if current_area > 0.0 {
+            (reference_area / current_area).sqrt()
+        } else {
+            1.0 // Degenerate screen (e.g. minimized window): keep the reference tolerance
+        };
+        let target_tolerance = (base_tolerance * scale).max(MIN_TOLERANCE_METERS);
 
         let mut raw_results = Vec::new();
         self.root.query_segments(geo_viewport, &mut raw_results);
@@ -286,6 +738,8 @@ impl Quadtree {
                         target_tolerance,
                         target_level,
                         geo_viewport,
+                        boundary_context,
+                        progressive,
                     )
                 })
                 .collect()
@@ -298,6 +752,8 @@ impl Quadtree {
                     target_tolerance,
                     target_level,
                     geo_viewport,
+                    boundary_context,
+                    progressive,
                 ) {
                     results.push(simplified);
                 }
@@ -306,10 +762,61 @@ impl Quadtree {
         }
     }
 
+    /// Simplify every stored segment once at every LOD level and keep the results
+    ///
+    /// After this, [`Quadtree::query`] only looks up cached index lists and clips
+    /// them to the viewport, trading memory for zero per-frame simplification.
+    /// Levels are simplified with the reference viewport's tolerance, so queries
+    /// made at the reference screen size return exactly what lazy mode would.
+    /// Consecutive levels that produce identical indices share one allocation.
+    ///
+    /// Returns the number of indices held by the new cache entries, which is a
+    /// rough measure of the extra memory used (one `usize` per index).
+    pub fn precompute_lod(&self) -> usize {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::precompute_lod");
+
+        let mut segments = Vec::new();
+        self.root.collect_segments(&mut segments);
+
+        segments
+            .par_iter()
+            .map(|raw| {
+                let mut stored = 0;
+                let mut previous: Option<Arc<Vec<usize>>> = None;
+                for level in 0..=MAX_DEPTH {
+                    let key = self.cache_key(raw, level);
+                    if let Some(cached) = self.simplification_cache.get(&key) {
+                        previous = Some(cached.clone());
+                        continue;
+                    }
+                    let tolerance = QuadtreeNode::calculate_pixel_tolerance(
+                        level,
+                        self.reference_pixel_viewport,
+                        self.bias,
+                    );
+                    let indices = simplify_raw_segment(raw, tolerance, self.sharp_turn_degrees);
+                    let arc = match previous.take() {
+                        Some(prev) if *prev == indices => prev,
+                        _ => {
+                            stored += indices.len();
+                            Arc::new(indices)
+                        }
+                    };
+                    self.simplification_cache.insert(key, arc.clone());
+                    previous = Some(arc);
+                }
+                stored
+            })
+            .sum()
+    }
+
     /// Get or create a simplified version of a segment at the given tolerance,
     /// clipped to the viewport to only include visible points.
     ///
     /// Returns None if the segment has no visible points after clipping.
+    /// With `progressive`, a missing level may be answered with a coarser one
+    /// (see [`Quadtree::query_progressive`]).
     #[inline]
     fn get_or_create_simplified_clipped(
         &self,
@@ -317,6 +824,8 @@ impl Quadtree {
         tolerance: f64,
         level: u32,
         viewport: Rect<f64>,
+        boundary_context: bool,
+        progressive: bool,
     ) -> Option<SimplifiedSegment> {
         // Scope to observe per-segment simplification + clipping costs in traces.
         // Attach route/track/segment indices and the tolerance value for richer filtering.
@@ -329,37 +838,7 @@ impl Quadtree {
             )
             .as_str()
         );
-        // For chunked segments, we need a unique cache key that includes the chunk identity
-        let chunk_hash = raw.original_indices.as_ref().map(|indices| {
-            // Use first and last original index as part of key
-            let first = indices.first().copied().unwrap_or(0);
-            let last = indices.last().copied().unwrap_or(0);
-            (first, last, indices.len())
-        });
-
-        // The original implementation used a `tolerance_level` variable here.
-        // We now derive the tolerance level from the provided `level` parameter
-        // (which represents the quadtree level / discretized tolerance).
-        let cache_key = SimplificationCacheKey {
-            route_ptr: Arc::as_ptr(&raw.route) as usize,
-            track_index: raw.track_index,
-            segment_index: raw.segment_index,
-            tolerance_level: level,
-            chunk_hash,
-        };
-
-        // Try to get simplified indices from cache first using DashMap (lock-free)
-        // Use get() first as it's faster for cache hits (no entry creation overhead)
-        let simplified_indices_arc = if let Some(cached) = self.simplification_cache.get(&cache_key)
-        {
-            cached.clone()
-        } else {
-            // Not in cache, compute and insert
-            let indices = simplify_vw_indices_fast(&raw.mercator_points, tolerance);
-            let arc = Arc::new(indices);
-            self.simplification_cache.insert(cache_key, arc.clone());
-            arc
-        };
+        let simplified_indices_arc = self.simplified_indices(raw, tolerance, level, progressive);
 
         // Fast path: check if segment bounding box is entirely within viewport
         // In this case, we can skip clipping entirely (uses cached bbox)
@@ -375,7 +854,12 @@ impl Quadtree {
             runs
         } else {
             // Need to clip to viewport
-            clip_indices_to_viewport_runs(&simplified_indices_arc, &raw.mercator_points, viewport)
+            clip_indices_to_viewport_runs(
+                &simplified_indices_arc,
+                &raw.mercator_points,
+                viewport,
+                boundary_context,
+            )
         };
 
         // Early return if no visible runs
@@ -412,9 +896,313 @@ impl Quadtree {
         ))
     }
 
+    /// Cache key of a segment at `level` under the current sharp-turn threshold
+    fn cache_key(&self, raw: &RawSegment, level: u32) -> SimplificationCacheKey {
+        SimplificationCacheKey::for_segment(raw, level, self.sharp_turn_degrees)
+    }
+
+    /// Simplified indices of a segment at `level`, from the cache or computed and cached
+    ///
+    /// With `progressive`, a miss returns the finest cached coarser level when
+    /// there is one and simplifies `level` in the background instead.
+    fn simplified_indices(
+        &self,
+        raw: &RawSegment,
+        tolerance: f64,
+        level: u32,
+        progressive: bool,
+    ) -> Arc<Vec<usize>> {
+        let cache_key = self.cache_key(raw, level);
+
+        // Try to get simplified indices from cache first using DashMap (lock-free)
+        // Use get() first as it's faster for cache hits (no entry creation overhead)
+        if let Some(cached) = self.simplification_cache.get(&cache_key) {
+            return cached.clone();
+        }
+
+        if progressive {
+            // A background job finished since the last query: the level is cached from now on
+            if let Some((key, refined)) = self.refinements.remove(&cache_key) {
+                self.simplification_cache.insert(key, refined.clone());
+                return refined;
+            }
+            if let Some(coarse) = self.best_cached_level(raw, level) {
+                self.refine_in_background(raw, tolerance, cache_key);
+                return coarse;
+            }
+        }
+
+        // Not in cache, compute and insert
+        let arc = Arc::new(simplify_raw_segment(
+            raw,
+            tolerance,
+            self.sharp_turn_degrees,
+        ));
+        self.simplification_cache.insert(cache_key, arc.clone());
+        arc
+    }
+
+    /// Finest cached simplification of a segment at a level below (coarser than) `level`
+    fn best_cached_level(&self, raw: &RawSegment, level: u32) -> Option<Arc<Vec<usize>>> {
+        (0..level).rev().find_map(|coarser| {
+            self.simplification_cache
+                .get(&self.cache_key(raw, coarser))
+                .map(|cached| cached.clone())
+        })
+    }
+
+    /// Simplify a segment on the rayon pool unless a job for `key` is already running
+    fn refine_in_background(&self, raw: &RawSegment, tolerance: f64, key: SimplificationCacheKey) {
+        if !self.pending_refinements.insert(key.clone()) {
+            return;
+        }
+        let raw = raw.clone();
+        let pending = self.pending_refinements.clone();
+        let refinements = self.refinements.clone();
+        let sharp_turn_degrees = self.sharp_turn_degrees;
+        rayon::spawn(move || {
+            let indices = simplify_raw_segment(&raw, tolerance, sharp_turn_degrees);
+            refinements.insert(key.clone(), Arc::new(indices));
+            // No longer pending: the route was removed while this job ran
+            if pending.remove(&key).is_none() {
+                refinements.remove(&key);
+            }
+        });
+    }
+
+    /// Number of stored segments a query of `viewport` would consider
+    ///
+    /// Uses the same node and bounding-box culling as [`Quadtree::query`] but
+    /// neither collects nor simplifies anything, so it is cheap enough to call
+    /// before deciding whether a full query is worth running.
+    pub fn query_count(&self, viewport: Rect<f64>) -> usize {
+        self.root.count_segments(viewport)
+    }
+
+    /// Iterate over every original point whose Web Mercator position lies in the viewport
+    ///
+    /// Bounds are inclusive. Points are yielded grouped by stored segment, in
+    /// no particular order across segments, and each original point is yielded
+    /// exactly once even though chunks share their boundary points. Duplicate
+    /// points left out of the index (see [`Quadtree::duplicate_points_removed`])
+    /// are yielded too, tested at their own position.
+    ///
+    /// # Performance
+    /// Candidate segments are found with the same node and bounding-box culling
+    /// as [`Quadtree::query`], then their cached mercator points are scanned, so
+    /// no simplification or coordinate conversion happens. Segments whose
+    /// bounding box lies fully inside the viewport skip the per-point test.
+    /// Chunk points are stored in track order rather than spatially sorted, so
+    /// there is nothing to binary search within a chunk; the scan is linear in
+    /// the points of the candidate segments. Left-out duplicates are converted
+    /// from their waypoints as they are reached. De-duplicating chunk
+    /// boundaries costs one hash-set entry per yielded chunk point.
+    pub fn query_raw(&self, viewport: Rect<f64>) -> impl Iterator<Item = RawPointRef> + '_ {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::query_raw");
+
+        let mut raw_segments = Vec::new();
+        self.root.query_segments(viewport, &mut raw_segments);
+
+        let mut seen = HashSet::new();
+        raw_segments
+            .into_iter()
+            .flat_map(move |raw| {
+                let fully_inside = rect_contains_rect(viewport, &raw.bounding_box);
+                (0..raw.mercator_points.len())
+                    .flat_map(move |local_index| {
+                        // The stored point, then any duplicates left out after it
+                        let run = original_run(raw, local_index);
+                        let first = run.start;
+                        run.map(move |point_index| (local_index, point_index, point_index == first))
+                    })
+                    .filter_map(move |(local_index, point_index, stored)| {
+                        let waypoint = raw.route.get_waypoint(
+                            raw.track_index,
+                            raw.segment_index,
+                            point_index,
+                        )?;
+                        let mercator = if stored {
+                            raw.mercator_points[local_index]
+                        } else {
+                            utils::waypoint_to_mercator(waypoint)
+                        };
+                        if !fully_inside && !rect_contains_point(viewport, &mercator) {
+                            return None;
+                        }
+                        let point = RawPointRef {
+                            route_index: raw.route_index,
+                            track_index: raw.track_index,
+                            segment_index: raw.segment_index,
+                            point_index,
+                            wgs84: waypoint.point(),
+                            mercator,
+                        };
+                        Some((point, raw.original_indices.is_some()))
+                    })
+            })
+            .filter_map(move |(point, chunked)| {
+                // Only chunks overlap each other; whole segments are stored once
+                let first_time = !chunked
+                    || seen.insert((
+                        point.route_index,
+                        point.track_index,
+                        point.segment_index,
+                        point.point_index,
+                    ));
+                first_time.then_some(point)
+            })
+    }
+
+    /// Distance (mercator meters) from `point` to the nearest stored line, if one is within `radius`
+    ///
+    /// Only segments whose bounding box touches the square of half-width
+    /// `radius` around the point are measured, so a result is exact whenever
+    /// it is returned; `None` means nothing is that close.
+    pub(crate) fn nearest_line_distance(&self, point: Point<f64>, radius: f64) -> Option<f64> {
+        self.segments_near(point, radius)
+            .iter()
+            .filter_map(|raw| segment_line_distance(raw, point))
+            .min_by(f64::total_cmp)
+            .filter(|&distance| distance <= radius)
+    }
+
+    /// Routes with a line within `radius` (mercator meters) of `point`, each
+    /// with the distance to its nearest line, nearest first
+    ///
+    /// Distances are exact, as in [`Quadtree::nearest_line_distance`]; equally
+    /// near routes are ordered by index.
+    pub fn nearest_routes(&self, point: Point<f64>, radius: f64) -> Vec<(usize, f64)> {
+        let mut nearest: HashMap<usize, f64> = HashMap::new();
+        for raw in self.segments_near(point, radius) {
+            if let Some(distance) = segment_line_distance(raw, point).filter(|&d| d <= radius) {
+                nearest
+                    .entry(raw.route_index)
+                    .and_modify(|best| *best = best.min(distance))
+                    .or_insert(distance);
+            }
+        }
+        let mut routes: Vec<(usize, f64)> = nearest.into_iter().collect();
+        routes.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        routes
+    }
+
+    /// Stored segments whose bounding box touches the square of half-width
+    /// `radius` around `point`
+    fn segments_near(&self, point: Point<f64>, radius: f64) -> Vec<&RawSegment> {
+        let search = Rect::new(
+            Coord {
+                x: point.x() - radius,
+                y: point.y() - radius,
+            },
+            Coord {
+                x: point.x() + radius,
+                y: point.y() + radius,
+            },
+        );
+        let mut raw_segments = Vec::new();
+        self.root.query_segments(search, &mut raw_segments);
+        raw_segments
+    }
+
+    /// Rough number of bytes held by the index (nodes, stored segments and cache)
+    ///
+    /// Counts the fixed-size parts and the point/index buffers; allocator
+    /// overhead and map bookkeeping are not included. Buffers shared between
+    /// cache entries are counted once.
+    pub fn memory_estimate(&self) -> usize {
+        use std::mem::size_of;
+
+        let mut segments = Vec::new();
+        self.root.collect_segments(&mut segments);
+        let segment_bytes: usize = segments
+            .iter()
+            .map(|raw| {
+                size_of::<RawSegment>()
+                    + raw.mercator_points.len() * size_of::<Point<f64>>()
+                    + raw
+                        .original_indices
+                        .as_ref()
+                        .map_or(0, |indices| indices.len() * size_of::<usize>())
+            })
+            .sum();
+
+        let mut seen = HashSet::new();
+        let cache_bytes: usize = self
+            .simplification_cache
+            .iter()
+            .map(|entry| {
+                let indices = entry.value();
+                let buffer = if seen.insert(Arc::as_ptr(indices)) {
+                    indices.len() * size_of::<usize>()
+                } else {
+                    0
+                };
+                size_of::<SimplificationCacheKey>() + size_of::<Arc<Vec<usize>>>() + buffer
+            })
+            .sum();
+
+        self.root.node_count() * size_of::<QuadtreeNode>() + segment_bytes + cache_bytes
+    }
+
+    /// Check the structure of the index, for diagnostics and tests
+    ///
+    /// Verifies that:
+    /// - children are one level deeper than their parent and tile it exactly,
+    ///   as its NW, NE, SW and SE quarters
+    /// - every stored segment lies within or crosses its node, and its cached
+    ///   bounding box holds all of its points
+    /// - every stored segment maps to points of its route: one to one for
+    ///   whole segments, through increasing in-range `original_indices` for
+    ///   chunks and pieces
+    /// - a query without boundary context returns no point outside the
+    ///   queried box, tried on the boxes of up to 64 nodes holding segments
+    ///
+    /// The queries go through the simplification cache like any other.
+    /// Returns the first violation found as [`DataError::InvariantViolated`].
+    pub fn check_invariants(&self) -> Result<()> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("quadtree::check_invariants");
+
+        self.root.check_invariants()?;
+
+        let mut boxes = Vec::new();
+        self.root
+            .collect_occupied_boxes(&mut boxes, INVARIANT_QUERY_SAMPLES);
+        let screen_size = (
+            self.reference_pixel_viewport.width(),
+            self.reference_pixel_viewport.height(),
+        );
+        for viewport in boxes {
+            // Mercator positions are recomputed from the GPX points; allow for rounding
+            let slack = 1e-6 * viewport.width().max(viewport.height()).max(1.0);
+            let (min, max) = (viewport.min(), viewport.max());
+            for segment in self.query_with_boundary_context(viewport, screen_size, false) {
+                for part in &segment.parts {
+                    for waypoint in part.iter_simplified_points(&segment.route) {
+                        let point = waypoint.point();
+                        let mercator = utils::wgs84_to_mercator(point.y(), point.x());
+                        let inside = mercator.x() >= min.x - slack
+                            && mercator.x() <= max.x + slack
+                            && mercator.y() >= min.y - slack
+                            && mercator.y() <= max.y + slack;
+                        if !inside {
+                            return Err(DataError::InvariantViolated(format!(
+                                "query of {:?} returned point {:?} of route {} outside it",
+                                viewport, mercator, segment.route_index
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Calculate the appropriate LOD level for the given viewport
     fn calculate_target_level(&self, geo_viewport: Rect<f64>) -> u32 {
-        let viewport_width_meters = geo_viewport.width();
+        // `max` also maps a NaN width to the minimum, i.e. the finest level
+        let viewport_width_meters = geo_viewport.width().max(MIN_VIEWPORT_METERS);
 
         // Find level where nodes are approximately 1-2 node widths visible
         let mut level = 0;
@@ -531,7 +1319,13 @@ impl QuadtreeNode {
     ///
     /// Segments are chunked at node boundaries so each node only stores
     /// the portion of the segment that falls within its bounds.
-    fn insert_segment(&mut self, segment: RawSegment, pixel_viewport: Rect<f64>, bias: f64) {
+    fn insert_segment(
+        &mut self,
+        segment: RawSegment,
+        pixel_viewport: Rect<f64>,
+        bias: f64,
+        chunking: ChunkingParams,
+    ) {
         // Attribute insertion work to a profiling scope so heavy insertions are visible.
         #[cfg(feature = "profiling")]
         profiling::scope!("quadtree::node::insert_segment");
@@ -542,9 +1336,13 @@ impl QuadtreeNode {
         }
 
         // Determine if we should recurse deeper
+        let point_count = segment.mercator_points.len();
         let should_recurse = self.level < MAX_DEPTH
-            && segment.mercator_points.len() >= MIN_POINTS_FOR_RECURSION
-            && self.segment_spans_multiple_children(&segment.mercator_points);
+            && point_count >= chunking.min_points_for_recursion
+            && (chunking
+                .max_chunk_points
+                .is_some_and(|max| point_count > max)
+                || self.segment_spans_multiple_children(&segment.mercator_points));
 
         if should_recurse {
             // Ensure children exist
@@ -557,7 +1355,7 @@ impl QuadtreeNode {
                 for child in children.iter_mut() {
                     // Extract the portion of the segment that intersects this child
                     if let Some(chunk) = child.extract_segment_chunk(&segment) {
-                        child.insert_segment(chunk, pixel_viewport, bias);
+                        child.insert_segment(chunk, pixel_viewport, bias, chunking);
                     }
                 }
             }
@@ -637,7 +1435,7 @@ impl QuadtreeNode {
         let mut deduped_points: Vec<Point<f64>> = Vec::with_capacity(chunk_points.len());
         let mut deduped_indices: Vec<usize> = Vec::with_capacity(chunk_indices.len());
 
-        for (point, idx) in chunk_points.into_iter().zip(chunk_indices.into_iter()) {
+        for (point, idx) in chunk_points.into_iter().zip(chunk_indices) {
             if deduped_indices.last() != Some(&idx) {
                 deduped_points.push(point);
                 deduped_indices.push(idx);
@@ -651,15 +1449,36 @@ impl QuadtreeNode {
         // Compute bounding box for the chunk
         let chunk_bbox = compute_segment_bbox(&deduped_points);
 
-        Some(RawSegment {
+        // Run ends are found on indices into the parent, before they are
+        // remapped, so points left out as duplicates are not taken for gaps
+        let mut chunk_anchors = chunk_anchor_indices(&deduped_indices);
+        if let Some(parent) = &segment.chunk_anchors {
+            chunk_anchors.extend(
+                deduped_indices
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, index)| parent.binary_search(index).is_ok())
+                    .map(|(k, _)| k),
+            );
+            chunk_anchors.sort_unstable();
+            chunk_anchors.dedup();
+        }
+
+        Some(RawSegment {
             route: segment.route.clone(),
             route_index: segment.route_index,
             track_index: segment.track_index,
             segment_index: segment.segment_index,
             mercator_points: Arc::new(deduped_points),
             bounding_box: chunk_bbox,
-            // Store the original indices so we can map back for rendering
-            original_indices: Some(Arc::new(deduped_indices)),
+            // Store the original indices so we can map back for rendering,
+            // through the parent's own mapping if it is itself a piece
+            original_indices: Some(Arc::new(match &segment.original_indices {
+                Some(parent) => deduped_indices.iter().map(|&i| parent[i]).collect(),
+                None => deduped_indices,
+            })),
+            chunk_anchors: Some(Arc::new(chunk_anchors)),
+            elevation_anchors: segment.elevation_anchors.clone(),
         })
     }
 
@@ -719,6 +1538,36 @@ impl QuadtreeNode {
         false
     }
 
+    /// Remove this route's segments from the subtree, collecting their route pointers
+    fn remove_route(&mut self, route_index: usize, removed_routes: &mut HashSet<u64>) {
+        self.raw_segments.retain(|segment| {
+            let keep = segment.route_index != route_index;
+            if !keep {
+                removed_routes.insert(segment.route.content_id());
+            }
+            keep
+        });
+        if let Some(children) = &mut self.children {
+            for child in children.iter_mut() {
+                child.remove_route(route_index, removed_routes);
+            }
+        }
+    }
+
+    /// Renumber the routes after `removed_index` one down in the subtree
+    fn close_route_gap(&mut self, removed_index: usize) {
+        for segment in &mut self.raw_segments {
+            if segment.route_index > removed_index {
+                segment.route_index -= 1;
+            }
+        }
+        if let Some(children) = &mut self.children {
+            for child in children.iter_mut() {
+                child.close_route_gap(removed_index);
+            }
+        }
+    }
+
     /// Merge another node into this one
     fn merge_with(&mut self, other: QuadtreeNode) -> Result<()> {
         // Verify nodes are compatible
@@ -760,9 +1609,7 @@ impl QuadtreeNode {
             }
             (Some(self_children), Some(other_children)) => {
                 // Both have children, merge recursively
-                for (self_child, other_child) in
-                    self_children.iter_mut().zip(other_children.into_iter())
-                {
+                for (self_child, other_child) in self_children.iter_mut().zip(*other_children) {
                     self_child.merge_with(other_child)?;
                 }
             }
@@ -797,6 +1644,169 @@ impl QuadtreeNode {
         }
     }
 
+    /// Count the raw segments [`QuadtreeNode::query_segments`] would return
+    fn count_segments(&self, viewport: Rect<f64>) -> usize {
+        if !self.intersects_viewport(viewport) {
+            return 0;
+        }
+        let own = self
+            .raw_segments
+            .iter()
+            .filter(|segment| segment_bbox_intersects_viewport(&segment.bounding_box, viewport))
+            .count();
+        own + self.children.as_ref().map_or(0, |children| {
+            children
+                .iter()
+                .map(|child| child.count_segments(viewport))
+                .sum()
+        })
+    }
+
+    /// Number of nodes in this subtree, this one included
+    fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .as_ref()
+            .map_or(0, |children| children.iter().map(|c| c.node_count()).sum())
+    }
+
+    /// Structural part of [`Quadtree::check_invariants`] for this subtree
+    fn check_invariants(&self) -> Result<()> {
+        let violation = |what: String| {
+            Err(DataError::InvariantViolated(format!(
+                "node at level {} {:?}: {}",
+                self.level, self.bounding_box, what
+            )))
+        };
+
+        for (i, segment) in self.raw_segments.iter().enumerate() {
+            if !self.segment_intersects_bounds(&segment.mercator_points) {
+                return violation(format!("segment {} lies outside the node", i));
+            }
+            if let Some(point) = segment
+                .mercator_points
+                .iter()
+                .find(|point| !rect_contains_point(segment.bounding_box, point))
+            {
+                return violation(format!(
+                    "segment {} has point {:?} outside its bounding box",
+                    i, point
+                ));
+            }
+
+            let Some(source) = segment
+                .route
+                .tracks()
+                .get(segment.track_index)
+                .and_then(|track| track.segments.get(segment.segment_index))
+            else {
+                return violation(format!(
+                    "segment {} refers to missing track {} segment {}",
+                    i, segment.track_index, segment.segment_index
+                ));
+            };
+            let source_len = source.points.len();
+            match &segment.original_indices {
+                Some(indices) => {
+                    if indices.len() != segment.mercator_points.len() {
+                        return violation(format!(
+                            "segment {} has {} points but {} original indices",
+                            i,
+                            segment.mercator_points.len(),
+                            indices.len()
+                        ));
+                    }
+                    if indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+                        return violation(format!(
+                            "segment {} has original indices out of order",
+                            i
+                        ));
+                    }
+                    if let Some(&last) = indices.last()
+                        && last >= source_len
+                    {
+                        return violation(format!(
+                            "segment {} maps to point {} of a {}-point segment",
+                            i, last, source_len
+                        ));
+                    }
+                }
+                None if segment.mercator_points.len() != source_len => {
+                    return violation(format!(
+                        "whole segment {} has {} points instead of {}",
+                        i,
+                        segment.mercator_points.len(),
+                        source_len
+                    ));
+                }
+                None => {}
+            }
+        }
+
+        if let Some(children) = &self.children {
+            let (min, max) = (self.bounding_box.min(), self.bounding_box.max());
+            let mid_x = (min.x + max.x) / 2.0;
+            let mid_y = (min.y + max.y) / 2.0;
+            // Same quarters, in the same order, as `subdivide`
+            let quarters = [
+                Rect::new(Coord { x: min.x, y: mid_y }, Coord { x: mid_x, y: max.y }),
+                Rect::new(Coord { x: mid_x, y: mid_y }, Coord { x: max.x, y: max.y }),
+                Rect::new(Coord { x: min.x, y: min.y }, Coord { x: mid_x, y: mid_y }),
+                Rect::new(Coord { x: mid_x, y: min.y }, Coord { x: max.x, y: mid_y }),
+            ];
+            for (child, quarter) in children.iter().zip(quarters) {
+                if child.level != self.level + 1 {
+                    return violation(format!("child at level {}", child.level));
+                }
+                if child.bounding_box != quarter {
+                    return violation(format!(
+                        "child {:?} is not the quarter {:?}",
+                        child.bounding_box, quarter
+                    ));
+                }
+                child.check_invariants()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bounding boxes of up to `limit` nodes that store segments, shallowest first
+    fn collect_occupied_boxes(&self, boxes: &mut Vec<Rect<f64>>, limit: usize) {
+        let mut level = vec![self];
+        while !level.is_empty() && boxes.len() < limit {
+            let mut next = Vec::new();
+            for node in level {
+                if !node.raw_segments.is_empty() && boxes.len() < limit {
+                    boxes.push(node.bounding_box);
+                }
+                if let Some(children) = &node.children {
+                    next.extend(children.iter());
+                }
+            }
+            level = next;
+        }
+    }
+
+    /// Call `f` on every raw segment stored in this node and its descendants
+    fn for_each_segment_mut(&mut self, f: &mut impl FnMut(&mut RawSegment)) {
+        self.raw_segments.iter_mut().for_each(&mut *f);
+        if let Some(children) = &mut self.children {
+            for child in children.iter_mut() {
+                child.for_each_segment_mut(f);
+            }
+        }
+    }
+
+    /// Collect every raw segment stored in this node and its descendants
+    fn collect_segments<'a>(&'a self, results: &mut Vec<&'a RawSegment>) {
+        results.extend(self.raw_segments.iter());
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.collect_segments(results);
+            }
+        }
+    }
+
     /// Check if this node intersects the viewport
     #[inline(always)]
     fn intersects_viewport(&self, viewport: Rect<f64>) -> bool {
@@ -895,6 +1905,31 @@ fn line_intersects_rect(p1: Point<f64>, p2: Point<f64>, rect: Rect<f64>) -> bool
     false
 }
 
+/// Euclidean distance from `point` to the nearest line of `raw`, `None` if it has no points
+fn segment_line_distance(raw: &RawSegment, point: Point<f64>) -> Option<f64> {
+    match raw.mercator_points.as_slice() {
+        [] => None,
+        [single] => Some(distance_to_line(point, *single, *single)),
+        points => points
+            .windows(2)
+            .map(|pair| distance_to_line(point, pair[0], pair[1]))
+            .min_by(f64::total_cmp),
+    }
+}
+
+/// Euclidean distance from `point` to the line from `a` to `b`
+fn distance_to_line(point: Point<f64>, a: Point<f64>, b: Point<f64>) -> f64 {
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((point.x() - a.x()) * dx + (point.y() - a.y()) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.x() + t * dx, a.y() + t * dy);
+    ((point.x() - cx).powi(2) + (point.y() - cy).powi(2)).sqrt()
+}
+
 /// Check if a segment's bounding box intersects a viewport rectangle
 /// This is a fast approximation - may return true for segments that don't actually intersect,
 /// but never returns false for segments that do intersect.
@@ -909,6 +1944,20 @@ fn segment_bbox_intersects_viewport(bbox: &Rect<f64>, viewport: Rect<f64>) -> bo
     !(bmax.x < vmin.x || bmin.x > vmax.x || bmax.y < vmin.y || bmin.y > vmax.y)
 }
 
+/// Inclusive point-in-rectangle test
+#[inline(always)]
+fn rect_contains_point(rect: Rect<f64>, point: &Point<f64>) -> bool {
+    let (min, max) = (rect.min(), rect.max());
+    point.x() >= min.x && point.x() <= max.x && point.y() >= min.y && point.y() <= max.y
+}
+
+/// Whether `inner` lies entirely inside `outer` (inclusive)
+#[inline(always)]
+fn rect_contains_rect(outer: Rect<f64>, inner: &Rect<f64>) -> bool {
+    rect_contains_point(outer, &inner.min().into())
+        && rect_contains_point(outer, &inner.max().into())
+}
+
 /// Fast O(n) simplification using Visvalingam-Whyatt that directly returns indices
 ///
 /// This uses the geo crate's SimplifyVwIdx trait which returns indices directly,
@@ -935,6 +1984,322 @@ fn simplify_vw_indices_fast(points: &[Point<f64>], tolerance: f64) -> Vec<usize>
     linestring.simplify_vw_idx(tolerance)
 }
 
+/// Simplify a stored segment, force-keeping the points where chunks meet
+///
+/// A chunk's first and last points (and both ends of every run, when the
+/// original segment leaves the node and re-enters it) are shared with the
+/// chunks stored in neighbouring nodes. Each chunk is simplified on its own, so
+/// dropping one of them would leave a hairline gap at the node boundary.
+/// With `sharp_turn_degrees`, sharp turns are restored afterwards (see
+/// [`restore_sharp_turns`]), and elevation extrema are always kept (see
+/// [`Quadtree::anchor_elevation_extrema`]).
+#[inline]
+fn simplify_raw_segment(
+    raw: &RawSegment,
+    tolerance: f64,
+    sharp_turn_degrees: Option<f64>,
+) -> Vec<usize> {
+    let mut indices = simplify_vw_indices_fast(&raw.mercator_points, tolerance);
+    if let Some(degrees) = sharp_turn_degrees {
+        indices = restore_sharp_turns(&raw.mercator_points, &indices, tolerance, degrees);
+    }
+    if let Some(extrema) = &raw.elevation_anchors {
+        let anchors = match &raw.original_indices {
+            Some(original) => original
+                .iter()
+                .enumerate()
+                .filter(|&(_, index)| extrema.binary_search(index).is_ok())
+                .map(|(k, _)| k)
+                .collect(),
+            None => extrema.to_vec(),
+        };
+        seed_indices(&mut indices, anchors);
+    }
+    if let Some(anchors) = &raw.chunk_anchors {
+        seed_indices(&mut indices, anchors.to_vec());
+    }
+    indices
+}
+
+/// Add `anchors` to the sorted simplified `indices`, keeping them sorted
+fn seed_indices(indices: &mut Vec<usize>, anchors: Vec<usize>) {
+    if anchors
+        .iter()
+        .any(|anchor| indices.binary_search(anchor).is_err())
+    {
+        indices.extend(anchors);
+        indices.sort_unstable();
+        indices.dedup();
+    }
+}
+
+/// Indices of the elevation peaks and valleys of a segment, in order
+///
+/// A peak counts when the track climbed at least `min_prominence` meters to
+/// reach it since the previous valley and drops at least as much before the
+/// next one; valleys likewise. Extremes at either end of the track only have
+/// one side and are left out (simplification keeps both ends anyway). Points
+/// without a finite elevation are skipped. A single O(n) sweep.
+fn elevation_extrema(points: &[gpx::Waypoint], min_prominence: f64) -> Vec<usize> {
+    let mut samples = points
+        .iter()
+        .enumerate()
+        .filter_map(|(i, point)| point.elevation.filter(|e| e.is_finite()).map(|e| (i, e)));
+    let Some(first) = samples.next() else {
+        return Vec::new();
+    };
+    let mut extrema = Vec::new();
+    // Lowest and highest points since the last extremum
+    let (mut low, mut high) = (first, first);
+    // Unknown until the elevation has changed by `min_prominence` once
+    let mut climbing: Option<bool> = None;
+    for (i, elevation) in samples {
+        if elevation < low.1 {
+            low = (i, elevation);
+        }
+        if elevation > high.1 {
+            high = (i, elevation);
+        }
+        match climbing {
+            Some(true) if high.1 - elevation >= min_prominence => {
+                extrema.push(high.0);
+                climbing = Some(false);
+                low = (i, elevation);
+            }
+            Some(false) if elevation - low.1 >= min_prominence => {
+                extrema.push(low.0);
+                climbing = Some(true);
+                high = (i, elevation);
+            }
+            None if high.1 - low.1 >= min_prominence => climbing = Some(high.0 > low.0),
+            _ => {}
+        }
+    }
+    extrema
+}
+
+/// Re-insert dropped points where the track turns by at least `min_turn_degrees`
+///
+/// `indices` are the sorted indices kept by simplification. The turn at each
+/// point is measured against the points `tolerance` meters of track before
+/// and after it rather than its direct neighbours, so zigzags smaller than
+/// the tolerance (GPS jitter) don't count while a hairpin sampled by several
+/// points still does. Consecutive points over the threshold belong to one
+/// turn, and only its sharpest point is put back. Cumulative track length
+/// makes finding those points a single O(n) sweep.
+fn restore_sharp_turns(
+    points: &[Point<f64>],
+    indices: &[usize],
+    tolerance: f64,
+    min_turn_degrees: f64,
+) -> Vec<usize> {
+    if points.len() < 3 {
+        return indices.to_vec();
+    }
+    let min_turn = min_turn_degrees.to_radians();
+    let track_length: Vec<f64> = std::iter::once(0.0)
+        .chain(points.windows(2).scan(0.0, |length, w| {
+            *length += (w[1].x() - w[0].x()).hypot(w[1].y() - w[0].y());
+            Some(*length)
+        }))
+        .collect();
+
+    let last = points.len() - 1;
+    let mut restored = Vec::new();
+    // Sharpest point so far of the turn being swept through
+    let mut turn_apex: Option<(usize, f64)> = None;
+    let (mut before, mut after) = (0, 0);
+    for i in 1..last {
+        while before + 1 < i && track_length[i] - track_length[before + 1] >= tolerance {
+            before += 1;
+        }
+        after = after.max(i + 1);
+        while after < last && track_length[after] - track_length[i] < tolerance {
+            after += 1;
+        }
+        let turn = turn_angle(points[before], points[i], points[after]);
+        if turn >= min_turn {
+            if turn_apex.is_none_or(|(_, sharpest)| turn > sharpest) {
+                turn_apex = Some((i, turn));
+            }
+        } else if let Some((apex, _)) = turn_apex.take() {
+            restored.push(apex);
+        }
+    }
+    restored.extend(turn_apex.map(|(apex, _)| apex));
+
+    if restored
+        .iter()
+        .all(|apex| indices.binary_search(apex).is_ok())
+    {
+        return indices.to_vec();
+    }
+    restored.extend_from_slice(indices);
+    restored.sort_unstable();
+    restored.dedup();
+    restored
+}
+
+/// Angle in radians between the directions `a` to `p` and `p` to `b`
+///
+/// 0 means `p` lies on the way from `a` to `b`, pi that the path reverses.
+fn turn_angle(a: Point<f64>, p: Point<f64>, b: Point<f64>) -> f64 {
+    let (ux, uy) = (p.x() - a.x(), p.y() - a.y());
+    let (vx, vy) = (b.x() - p.x(), b.y() - p.y());
+    if (ux == 0.0 && uy == 0.0) || (vx == 0.0 && vy == 0.0) {
+        return 0.0;
+    }
+    (ux * vy - uy * vx).abs().atan2(ux * vx + uy * vy)
+}
+
+/// Index ranges splitting a segment of `len` points into pieces of at most
+/// `max_points` (at least 2), each piece starting at the previous one's last point
+fn split_segment_ranges(len: usize, max_points: usize) -> Vec<RangeInclusive<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start + 1 < len {
+        let end = (start + max_points - 1).min(len - 1);
+        ranges.push(start..=end);
+        start = end;
+    }
+    ranges
+}
+
+/// Chunk-local indices of every run endpoint in a chunk's indices into its parent
+fn chunk_anchor_indices(parent_indices: &[usize]) -> Vec<usize> {
+    let last = parent_indices.len().saturating_sub(1);
+    (0..parent_indices.len())
+        .filter(|&k| {
+            k == 0
+                || k == last
+                || parent_indices[k - 1] + 1 != parent_indices[k]
+                || parent_indices[k] + 1 != parent_indices[k + 1]
+        })
+        .collect()
+}
+
+/// Segments with at least this many points are converted in parallel chunks
+const PARALLEL_CONVERSION_CHUNK: usize = 64 * 1024;
+
+/// Convert waypoints to Web Mercator, computing their bounding box in the same pass
+///
+/// Long segments are split into chunks converted in parallel; the chunks are
+/// joined in order and min/max are exact, so the result doesn't depend on
+/// how the work was split.
+fn segment_to_mercator(waypoints: &[gpx::Waypoint]) -> (Vec<Point<f64>>, Rect<f64>) {
+    fn convert(waypoints: &[gpx::Waypoint]) -> (Vec<Point<f64>>, [f64; 4]) {
+        let mut points = Vec::with_capacity(waypoints.len());
+        let mut bounds = [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for wp in waypoints {
+            let p = utils::wgs84_to_mercator(wp.point().y(), wp.point().x());
+            bounds = [
+                bounds[0].min(p.x()),
+                bounds[1].min(p.y()),
+                bounds[2].max(p.x()),
+                bounds[3].max(p.y()),
+            ];
+            points.push(p);
+        }
+        (points, bounds)
+    }
+
+    if waypoints.is_empty() {
+        return (Vec::new(), compute_segment_bbox(&[]));
+    }
+    let (points, [min_x, min_y, max_x, max_y]) = if waypoints.len() < PARALLEL_CONVERSION_CHUNK {
+        convert(waypoints)
+    } else {
+        let chunks: Vec<_> = waypoints
+            .par_chunks(PARALLEL_CONVERSION_CHUNK)
+            .map(convert)
+            .collect();
+        let mut points = Vec::with_capacity(waypoints.len());
+        let mut bounds = [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for (chunk, [min_x, min_y, max_x, max_y]) in chunks {
+            points.extend(chunk);
+            bounds = [
+                bounds[0].min(min_x),
+                bounds[1].min(min_y),
+                bounds[2].max(max_x),
+                bounds[3].max(max_y),
+            ];
+        }
+        (points, bounds)
+    };
+    (
+        points,
+        Rect::new(Coord { x: min_x, y: min_y }, Coord { x: max_x, y: max_y }),
+    )
+}
+
+/// Whether two mercator points are close enough to count as one position
+#[inline(always)]
+fn same_position(a: Point<f64>, b: Point<f64>) -> bool {
+    (a.x() - b.x()).abs() <= DUPLICATE_POINT_EPSILON_METERS
+        && (a.y() - b.y()).abs() <= DUPLICATE_POINT_EPSILON_METERS
+}
+
+/// Original point indices that stored point `local_index` of `raw` stands for
+///
+/// That is the point itself followed by the duplicates left out after it.
+/// They run up to the next stored point, or for the last one, to the end of
+/// the segment if every remaining point is a duplicate; otherwise the run
+/// continues in the next chunk, which starts at the same point.
+fn original_run(raw: &RawSegment, local_index: usize) -> std::ops::Range<usize> {
+    let Some(indices) = &raw.original_indices else {
+        return local_index..local_index + 1;
+    };
+    let start = indices[local_index];
+    if let Some(&next) = indices.get(local_index + 1) {
+        return start..next;
+    }
+    let points = &raw.route.tracks()[raw.track_index].segments[raw.segment_index].points;
+    let anchor = raw.mercator_points[local_index];
+    let all_duplicates = points[start + 1..]
+        .iter()
+        .all(|waypoint| same_position(anchor, utils::waypoint_to_mercator(waypoint)));
+    if all_duplicates {
+        start..points.len()
+    } else {
+        start..start + 1
+    }
+}
+
+/// Leave out points within [`DUPLICATE_POINT_EPSILON_METERS`] of the last kept one
+///
+/// Returns the kept points and, when any were left out, their indices in
+/// `points`. The first point is always kept, and a segment of two or more
+/// points keeps at least one more.
+fn dedup_consecutive_points(points: Vec<Point<f64>>) -> (Vec<Point<f64>>, Option<Vec<usize>>) {
+    let mut kept: Vec<usize> = Vec::with_capacity(points.len());
+    for (i, point) in points.iter().enumerate() {
+        if kept
+            .last()
+            .is_none_or(|&last| !same_position(points[last], *point))
+        {
+            kept.push(i);
+        }
+    }
+    if kept.len() == points.len() {
+        return (points, None);
+    }
+    if kept.len() < 2 {
+        kept.push(points.len() - 1);
+    }
+    let deduped = kept.iter().map(|&i| points[i]).collect();
+    (deduped, Some(kept))
+}
+
 /// Compute bounding box of a segment's points
 #[inline]
 fn compute_segment_bbox(points: &[Point<f64>]) -> Rect<f64> {
@@ -997,12 +2362,17 @@ type ClipRuns = SmallVec<[SmallVec<[usize; 32]>; 2]>;
 /// and re-enters the viewport - each run is a continuous sequence that should be rendered
 /// as a separate polyline.
 ///
+/// With `boundary_context`, "connected" includes the off-viewport neighbor on
+/// each side of a run and lines crossing the viewport; without it, runs are
+/// just the consecutive points inside the viewport.
+///
 /// Optimized with batched point checks and reduced branching.
 #[inline]
 fn clip_indices_to_viewport_runs(
     simplified_indices: &[usize],
     mercator_points: &[Point<f64>],
     viewport: Rect<f64>,
+    boundary_context: bool,
 ) -> ClipRuns {
     let len = simplified_indices.len();
     if len == 0 || mercator_points.is_empty() {
@@ -1026,6 +2396,7 @@ fn clip_indices_to_viewport_runs(
             vmin_y,
             vmax_x,
             vmax_y,
+            boundary_context,
         );
     }
 
@@ -1038,11 +2409,32 @@ fn clip_indices_to_viewport_runs(
         vmin_y,
         vmax_x,
         vmax_y,
+        boundary_context,
     )
 }
 
+/// Runs of consecutive in-viewport points, without boundary context
+fn visible_only_runs(simplified_indices: &[usize], is_in: impl Fn(usize) -> bool) -> ClipRuns {
+    let mut runs: ClipRuns = SmallVec::new();
+    let mut current_run: SmallVec<[usize; 32]> = SmallVec::new();
+    for (i, &idx) in simplified_indices.iter().enumerate() {
+        if is_in(i) {
+            current_run.push(idx);
+        } else if current_run.len() >= 2 {
+            runs.push(std::mem::take(&mut current_run));
+        } else {
+            current_run.clear();
+        }
+    }
+    if current_run.len() >= 2 {
+        runs.push(current_run);
+    }
+    runs
+}
+
 /// Optimized clipping for segments with <= 64 points using a bitset
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn clip_indices_small_bitset(
     simplified_indices: &[usize],
     mercator_points: &[Point<f64>],
@@ -1051,6 +2443,7 @@ fn clip_indices_small_bitset(
     vmin_y: f64,
     vmax_x: f64,
     vmax_y: f64,
+    boundary_context: bool,
 ) -> ClipRuns {
     let len = simplified_indices.len();
 
@@ -1076,6 +2469,12 @@ fn clip_indices_small_bitset(
         return runs;
     }
 
+    if !boundary_context {
+        return visible_only_runs(simplified_indices, |i| {
+            (in_viewport_bits & (1u64 << i)) != 0
+        });
+    }
+
     // Quick check: no points in viewport - need to check line crossings
     if in_viewport_bits == 0 {
         let has_crossing = (0..len.saturating_sub(1)).any(|i| {
@@ -1123,9 +2522,6 @@ fn clip_indices_small_bitset(
         let should_include = is_in_viewport || prev_relevant || next_relevant;
 
         if should_include {
-            if current_run.is_empty() && i > 0 && !prev_in {
-                current_run.push(simplified_indices[i - 1]);
-            }
             current_run.push(idx);
 
             if i + 1 < len && !next_in && (is_in_viewport || line_crosses(i, i + 1)) {
@@ -1152,6 +2548,7 @@ fn clip_indices_small_bitset(
 
 /// Standard clipping for larger segments
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn clip_indices_large(
     simplified_indices: &[usize],
     mercator_points: &[Point<f64>],
@@ -1160,6 +2557,7 @@ fn clip_indices_large(
     vmin_y: f64,
     vmax_x: f64,
     vmax_y: f64,
+    boundary_context: bool,
 ) -> ClipRuns {
     let len = simplified_indices.len();
 
@@ -1179,6 +2577,9 @@ fn clip_indices_large(
         runs.push(simplified_indices.iter().copied().collect());
         return runs;
     }
+    if !boundary_context {
+        return visible_only_runs(simplified_indices, |i| in_viewport[i]);
+    }
 
     let any_in_viewport = in_viewport.iter().any(|&v| v);
 
@@ -1216,9 +2617,6 @@ fn clip_indices_large(
         let should_include = is_in_viewport || prev_relevant || next_relevant;
 
         if should_include {
-            if current_run.is_empty() && i > 0 && !prev_in {
-                current_run.push(simplified_indices[i - 1]);
-            }
             current_run.push(idx);
 
             if i + 1 < len && !next_in && (is_in_viewport || line_crosses(i, i + 1)) {
@@ -1313,6 +2711,168 @@ mod tests {
         assert!(indices.contains(&(points.len() - 1)));
     }
 
+    /// Zigzag of long, closely spaced legs, returning it with the apex indices
+    fn switchback(legs: usize, points_per_leg: usize) -> (Vec<Point<f64>>, Vec<usize>) {
+        let mut points = Vec::new();
+        let mut apexes = Vec::new();
+        for leg in 0..legs {
+            for k in 0..points_per_leg {
+                let t = k as f64 / points_per_leg as f64;
+                let x = if leg % 2 == 0 { t } else { 1.0 - t } * 100.0;
+                points.push(Point::new(x, (leg as f64 + t) * 4.0));
+            }
+            apexes.push(points.len());
+        }
+        points.push(Point::new(
+            if legs.is_multiple_of(2) { 0.0 } else { 100.0 },
+            legs as f64 * 4.0,
+        ));
+        apexes.pop();
+        (points, apexes)
+    }
+
+    #[test]
+    fn test_sharp_turns_survive_simplification() {
+        let (points, apexes) = switchback(6, 10);
+        // Well above the 400 m² triangle each apex makes with the apexes either side
+        let tolerance = 1000.0;
+
+        let plain = simplify_vw_indices_fast(&points, tolerance);
+        assert!(
+            apexes.iter().any(|apex| !plain.contains(apex)),
+            "plain VW should cut some switchbacks: {:?}",
+            plain
+        );
+
+        let kept = restore_sharp_turns(&points, &plain, 1.0, DEFAULT_SHARP_TURN_DEGREES);
+        for apex in &apexes {
+            assert!(kept.contains(apex), "apex {} dropped: {:?}", apex, kept);
+        }
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+        assert!(plain.iter().all(|i| kept.contains(i)));
+        // Only the turns come back, not the straight legs between them
+        assert!(kept.len() <= plain.len() + apexes.len());
+    }
+
+    #[test]
+    fn test_sharp_turns_ignore_jitter() {
+        // Neighbouring points turn by 90° here, but 5 m apart the line is straight
+        let points: Vec<Point<f64>> = (0..50)
+            .map(|i| Point::new(i as f64, if i % 2 == 0 { 0.0 } else { 0.5 }))
+            .collect();
+        let plain = simplify_vw_indices_fast(&points, 10.0);
+        assert_eq!(restore_sharp_turns(&points, &plain, 5.0, 60.0), plain);
+    }
+
+    #[test]
+    fn test_elevation_extrema_survive_simplification() {
+        // A straight, gently climbing road with one spike in elevation halfway,
+        // too short to be split into chunks
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..7 {
+            let mut point = gpx::Waypoint::new(Point::new(-0.1, 51.5 + i as f64 * 0.0001));
+            point.elevation = Some(if i == 3 { 400.0 } else { 100.0 + i as f64 });
+            segment.points.push(point);
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let gpx = gpx::Gpx {
+            tracks: vec![track],
+            ..Default::default()
+        };
+        let viewport = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let build = || {
+            let route = crate::Route::new(gpx.clone()).unwrap();
+            Quadtree::new_with_route(route, 0, viewport, 1.0, DEFAULT_CHUNKING_AGGRESSIVENESS)
+                .unwrap()
+        };
+        // Indices kept when simplifying far coarser than the whole track
+        let kept = |quadtree: &Quadtree| {
+            let mut segments = Vec::new();
+            quadtree.root.collect_segments(&mut segments);
+            assert_eq!(segments.len(), 1);
+            simplify_raw_segment(segments[0], 1e9, None)
+        };
+
+        assert_eq!(kept(&build()), vec![0, 6]);
+
+        let mut anchored = build();
+        anchored.anchor_elevation_extrema(DEFAULT_EXTREMA_PROMINENCE_METERS);
+        // Only the spike comes back, not the climb on either side of it
+        assert_eq!(kept(&anchored), vec![0, 3, 6]);
+    }
+
+    #[test]
+    fn test_simplification_changes_are_not_served_from_cache() {
+        // Straight line with an elevation spike halfway, like the test above
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..7 {
+            let mut point = gpx::Waypoint::new(Point::new(-0.1, 51.5 + i as f64 * 0.0001));
+            point.elevation = Some(if i == 3 { 400.0 } else { 100.0 + i as f64 });
+            segment.points.push(point);
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let gpx = gpx::Gpx {
+            tracks: vec![track],
+            ..Default::default()
+        };
+        let reference = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let route = crate::Route::new(gpx).unwrap();
+        let mut quadtree =
+            Quadtree::new_with_route(route, 0, reference, 1.0, DEFAULT_CHUNKING_AGGRESSIVENESS)
+                .unwrap();
+        let viewport = Rect::new(
+            utils::wgs84_to_mercator(51.49, -0.2).0,
+            utils::wgs84_to_mercator(51.51, 0.0).0,
+        );
+        let indices = |quadtree: &Quadtree| -> Vec<Vec<usize>> {
+            quadtree
+                .query(viewport, (1024.0, 768.0))
+                .into_iter()
+                .flat_map(|segment| segment.parts)
+                .map(|part| part.simplified_indices)
+                .collect()
+        };
+
+        assert_eq!(indices(&quadtree), vec![vec![0, 6]]);
+        // Anchoring changes the simplification of an already cached level
+        quadtree.anchor_elevation_extrema(DEFAULT_EXTREMA_PROMINENCE_METERS);
+        assert_eq!(indices(&quadtree), vec![vec![0, 3, 6]]);
+
+        quadtree.clear_cache();
+        assert!(quadtree.simplification_cache.is_empty());
+        assert_eq!(indices(&quadtree), vec![vec![0, 3, 6]]);
+    }
+
+    #[test]
+    fn test_elevation_extrema_need_prominence_on_both_sides() {
+        let points: Vec<gpx::Waypoint> = [100.0, 130.0, 125.0, 160.0, 120.0, 150.0, 140.0]
+            .into_iter()
+            .map(|elevation| {
+                let mut point = gpx::Waypoint::new(Point::new(0.0, 0.0));
+                point.elevation = Some(elevation);
+                point
+            })
+            .collect();
+        // The 5 m dip at 2 and the 10 m one at the end don't count
+        assert_eq!(elevation_extrema(&points, 20.0), vec![3, 4]);
+        assert_eq!(elevation_extrema(&points, 50.0), Vec::<usize>::new());
+        assert!(elevation_extrema(&[gpx::Waypoint::default()], 1.0).is_empty());
+    }
+
     #[test]
     fn test_simplify_vw_indices_fast_short() {
         // Test with 2 or fewer points
@@ -1455,15 +3015,312 @@ mod tests {
     }
 
     #[test]
-    fn test_segment_spans_multiple_children() {
-        let viewport = Rect::new(
+    fn test_sub_meter_viewport_uses_finest_level() {
+        use crate::utils::wgs84_to_mercator;
+
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        let mut segment = gpx::TrackSegment::default();
+        for i in 0..500 {
+            let lat = 51.5 + (i as f64 * 0.00001);
+            let lon = -0.1 + ((i as f64) * 0.3).sin() * 0.00001;
+            segment
+                .points
+                .push(gpx::Waypoint::new(geo::Point::new(lon, lat)));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+
+        let reference_viewport = Rect::new(
             Coord { x: 0.0, y: 0.0 },
             Coord {
                 x: 1024.0,
                 y: 768.0,
             },
         );
-
+        let route = crate::Route::new(gpx).unwrap();
+        let quadtree = Quadtree::new_with_route(
+            route,
+            0,
+            reference_viewport,
+            1.0,
+            DEFAULT_CHUNKING_AGGRESSIVENESS,
+        )
+        .unwrap();
+
+        let center = wgs84_to_mercator(51.5025, -0.1);
+        let viewport_of_width = |width: f64| {
+            Rect::new(
+                Coord {
+                    x: center.x() - width / 2.0,
+                    y: center.y() - width / 2.0,
+                },
+                Coord {
+                    x: center.x() + width / 2.0,
+                    y: center.y() + width / 2.0,
+                },
+            )
+        };
+
+        let one_meter = viewport_of_width(1.0);
+        let finest = quadtree.calculate_target_level(one_meter);
+        assert!(finest <= MAX_DEPTH);
+        for width in [0.5, 1e-6, 0.0, f64::NAN] {
+            assert_eq!(
+                quadtree.calculate_target_level(viewport_of_width(width)),
+                finest,
+                "width {} should resolve to the finest level",
+                width
+            );
+        }
+
+        // A sub-meter query returns no more than the 1 m query, which is itself bounded
+        let count = |segments: Vec<SimplifiedSegment>| -> usize {
+            segments
+                .iter()
+                .flat_map(|s| s.parts.iter())
+                .map(|p| p.simplified_indices.len())
+                .sum()
+        };
+        let screen_size = (1920.0, 1080.0);
+        let points_one_meter = count(quadtree.query(one_meter, screen_size));
+        let points_tiny = count(quadtree.query(viewport_of_width(0.01), screen_size));
+        assert!(points_tiny <= points_one_meter);
+        assert!(points_one_meter <= 500);
+
+        // A degenerate screen size must not blow the tolerance up or down
+        let _ = quadtree.query(one_meter, (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_consecutive_duplicates_are_dropped_before_indexing() {
+        use crate::utils::wgs84_to_mercator;
+
+        // Standing still at the start and in the middle, then a device that stops for good
+        let positions: [&[(f64, f64)]; 2] = [
+            &[
+                (51.500, -0.100),
+                (51.500, -0.100),
+                (51.500, -0.100),
+                (51.501, -0.101),
+                (51.502, -0.099),
+                (51.502, -0.099),
+                (51.503, -0.100),
+            ],
+            &[(51.6, -0.2); 3],
+        ];
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        for points in positions {
+            let mut segment = gpx::TrackSegment::default();
+            for &(lat, lon) in points {
+                segment
+                    .points
+                    .push(gpx::Waypoint::new(geo::Point::new(lon, lat)));
+            }
+            track.segments.push(segment);
+        }
+        gpx.tracks.push(track);
+
+        let reference_viewport = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let route = crate::Route::new(gpx).unwrap();
+        let quadtree = Quadtree::new_with_route(
+            route,
+            0,
+            reference_viewport,
+            1.0,
+            DEFAULT_CHUNKING_AGGRESSIVENESS,
+        )
+        .unwrap();
+        quadtree.check_invariants().unwrap();
+        // Three from the moving segment; the stopped one keeps its two ends
+        assert_eq!(quadtree.duplicate_points_removed(), 4);
+
+        let mut segments = Vec::new();
+        quadtree.root.collect_segments(&mut segments);
+        let mut stored: Vec<Vec<usize>> = vec![Vec::new(); 2];
+        for raw in &segments {
+            let original = raw.original_indices.as_ref().unwrap();
+            for (point, &index) in raw.mercator_points.iter().zip(original.iter()) {
+                // Each stored point is the GPX point it maps back to
+                let (lat, lon) = positions[raw.segment_index][index];
+                assert_eq!(*point, wgs84_to_mercator(lat, lon));
+                stored[raw.segment_index].push(index);
+            }
+        }
+        for indices in &mut stored {
+            indices.sort_unstable();
+            indices.dedup();
+        }
+        assert_eq!(stored, vec![vec![0, 3, 4, 6], vec![0, 2]]);
+
+        // Queries still hand out indices into the original points
+        let everything = Rect::new(
+            wgs84_to_mercator(51.4, -0.3).0,
+            wgs84_to_mercator(51.7, 0.0).0,
+        );
+        let parts: Vec<SegmentPart> = quadtree
+            .query(everything, (1920.0, 1080.0))
+            .into_iter()
+            .flat_map(|segment| segment.parts)
+            .collect();
+        assert!(!parts.is_empty());
+        for part in parts {
+            assert!(
+                part.simplified_indices
+                    .iter()
+                    .all(|index| stored[part.segment_index].contains(index))
+            );
+        }
+    }
+
+    #[test]
+    fn test_chunk_anchor_indices() {
+        // Two runs: original points 3..=6 and 20..=22
+        let original = vec![3, 4, 5, 6, 20, 21, 22];
+        assert_eq!(chunk_anchor_indices(&original), vec![0, 3, 4, 6]);
+        assert_eq!(chunk_anchor_indices(&[7]), vec![0]);
+        assert!(chunk_anchor_indices(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_deduplicated_segment_simplifies_to_its_ends_at_overview() {
+        // A gentle curve with stops along the way, ending in one
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..60 {
+            let t = i as f64 * 1e-4;
+            let point = geo::Point::new(t, 45.0 + t * t);
+            let copies = if i % 10 == 9 { 3 } else { 1 };
+            for _ in 0..copies {
+                segment.points.push(gpx::Waypoint::new(point));
+            }
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let mut gpx = gpx::Gpx::default();
+        gpx.tracks.push(track);
+        let quadtree = cache_test_quadtree(&[Route::new(gpx).unwrap()]);
+        assert_eq!(quadtree.duplicate_points_removed(), 12);
+
+        let overview = Rect::new(
+            utils::wgs84_to_mercator(40.0, -5.0).0,
+            utils::wgs84_to_mercator(50.0, 5.0).0,
+        );
+        let kept: Vec<usize> = quadtree
+            .query(overview, (1024.0, 768.0))
+            .into_iter()
+            .flat_map(|segment| segment.parts)
+            .flat_map(|part| part.simplified_indices)
+            .collect();
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn test_chunk_boundaries_survive_simplification() {
+        use crate::utils::wgs84_to_mercator;
+
+        // A straight line across the prime meridian, which is a node boundary at every level
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        let mut segment = gpx::TrackSegment::default();
+        for i in 0..300 {
+            let lon = -1.0 + i as f64 * (2.0 / 299.0);
+            segment
+                .points
+                .push(gpx::Waypoint::new(geo::Point::new(lon, 10.0)));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+
+        let reference_viewport = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let route = crate::Route::new(gpx).unwrap();
+        let quadtree = Quadtree::new_with_route(
+            route,
+            0,
+            reference_viewport,
+            1.0,
+            DEFAULT_CHUNKING_AGGRESSIVENESS,
+        )
+        .unwrap();
+
+        let mut raw_segments = Vec::new();
+        quadtree.root.collect_segments(&mut raw_segments);
+        assert!(
+            raw_segments.len() > 1,
+            "the line should be split into chunks"
+        );
+
+        let min = wgs84_to_mercator(9.0, -2.0);
+        let max = wgs84_to_mercator(11.0, 2.0);
+        let everything = Rect::new(
+            Coord {
+                x: min.x(),
+                y: min.y(),
+            },
+            Coord {
+                x: max.x(),
+                y: max.y(),
+            },
+        );
+
+        for (level, tolerance) in [1.0, 1e3, 1e5, 1e8].into_iter().enumerate() {
+            let mut runs: Vec<(usize, usize)> = raw_segments
+                .iter()
+                .filter_map(|raw| {
+                    quadtree.get_or_create_simplified_clipped(
+                        raw,
+                        tolerance,
+                        level as u32,
+                        everything,
+                        true,
+                        false,
+                    )
+                })
+                .flat_map(|segment| segment.parts)
+                .map(|part| {
+                    (
+                        part.simplified_indices[0],
+                        *part.simplified_indices.last().unwrap(),
+                    )
+                })
+                .collect();
+            runs.sort_unstable();
+
+            for pair in runs.windows(2) {
+                let ((_, previous_end), (next_start, _)) = (pair[0], pair[1]);
+                assert!(
+                    next_start <= previous_end,
+                    "gap between point {} and {} at tolerance {}",
+                    previous_end,
+                    next_start,
+                    tolerance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_segment_spans_multiple_children() {
+        let viewport = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+
         let node = QuadtreeNode::new_child(
             Rect::new(Coord { x: 0.0, y: 0.0 }, Coord { x: 100.0, y: 100.0 }),
             1,
@@ -1494,7 +3351,7 @@ mod tests {
         // Viewport that only covers the middle part (15 to 25)
         let viewport = Rect::new(Coord { x: 15.0, y: -5.0 }, Coord { x: 25.0, y: 5.0 });
 
-        let runs = clip_indices_to_viewport_runs(&all_indices, &points, viewport);
+        let runs = clip_indices_to_viewport_runs(&all_indices, &points, viewport, true);
 
         // Should have one run containing point 2 (at x=20, inside viewport)
         // Plus points 1 and 3 for line continuity (lines 1-2 and 2-3 cross viewport)
@@ -1512,6 +3369,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_clip_indices_boundary_context() {
+        // Points along the x-axis at 0, 10, ..., 40 (and 0..=100 for the large path);
+        // the viewport holds only the points at 20 and 30
+        let viewport = Rect::new(Coord { x: 15.0, y: -5.0 }, Coord { x: 35.0, y: 5.0 });
+        for count in [5, 11, 100] {
+            let points: Vec<Point<f64>> = (0..count)
+                .map(|i| Point::new(i as f64 * 10.0, 0.0))
+                .collect();
+            let indices: Vec<usize> = (0..count).collect();
+
+            let with_context = clip_indices_to_viewport_runs(&indices, &points, viewport, true);
+            assert_eq!(with_context.len(), 1);
+            assert_eq!(with_context[0].as_slice(), &[1, 2, 3, 4]);
+
+            let without_context = clip_indices_to_viewport_runs(&indices, &points, viewport, false);
+            assert_eq!(without_context.len(), 1);
+            assert_eq!(without_context[0].as_slice(), &[2, 3]);
+        }
+
+        // A line crossing the viewport with no point inside is only kept with context
+        let points = vec![Point::new(0.0, 0.0), Point::new(50.0, 0.0)];
+        assert_eq!(
+            clip_indices_to_viewport_runs(&[0, 1], &points, viewport, true).len(),
+            1
+        );
+        assert!(clip_indices_to_viewport_runs(&[0, 1], &points, viewport, false).is_empty());
+    }
+
     #[test]
     fn test_clip_indices_discontinuity() {
         // Create points that form a U-shape: goes down, across, then up
@@ -1530,7 +3416,7 @@ mod tests {
         // This should see points 0 and 4, but they are NOT connected!
         let viewport = Rect::new(Coord { x: -10.0, y: 40.0 }, Coord { x: 110.0, y: 60.0 });
 
-        let runs = clip_indices_to_viewport_runs(&all_indices, &points, viewport);
+        let runs = clip_indices_to_viewport_runs(&all_indices, &points, viewport, true);
 
         // Should have TWO separate runs: one for entry (0->1) and one for exit (3->4)
         // They should NOT be connected as a single run
@@ -1699,6 +3585,8 @@ mod tests {
             mercator_points: Arc::new(points),
             bounding_box: bbox,
             original_indices: None,
+            chunk_anchors: None,
+            elevation_anchors: None,
         };
 
         // Extract chunk - should only include points in/near the node bounds
@@ -1807,4 +3695,532 @@ mod tests {
             points_large
         );
     }
+
+    #[test]
+    fn test_chunking_params_from_aggressiveness() {
+        let default = ChunkingParams::from_aggressiveness(DEFAULT_CHUNKING_AGGRESSIVENESS);
+        assert_eq!(default.min_points_for_recursion, MIN_POINTS_FOR_RECURSION);
+        assert_eq!(default.max_chunk_points, None);
+
+        let low = ChunkingParams::from_aggressiveness(0.0);
+        let high = ChunkingParams::from_aggressiveness(1.0);
+        assert!(low.min_points_for_recursion > default.min_points_for_recursion);
+        assert!(high.min_points_for_recursion < default.min_points_for_recursion);
+        assert_eq!(high.max_chunk_points, Some(64));
+
+        // Out-of-range values are clamped
+        assert_eq!(ChunkingParams::from_aggressiveness(-3.0), low);
+        assert_eq!(ChunkingParams::from_aggressiveness(7.0), high);
+    }
+
+    #[test]
+    fn test_higher_chunking_aggressiveness_yields_more_smaller_segments() {
+        // A zig-zag track across a few degrees, dense enough to be chunked
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        let mut segment = gpx::TrackSegment::default();
+        for i in 0..5000 {
+            let t = i as f64 * 0.001;
+            segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                -1.0 + t * 0.6,
+                51.0 + (t * 11.0).sin() * 0.5,
+            )));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+        let route = crate::Route::new(gpx).unwrap();
+
+        let viewport = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+
+        // (segment count, mean points per segment, largest segment in points)
+        let shape = |aggressiveness: f64| {
+            let quadtree =
+                Quadtree::new_with_route(route.clone(), 0, viewport, 1.0, aggressiveness).unwrap();
+            let mut segments = Vec::new();
+            quadtree.root.collect_segments(&mut segments);
+            let total: usize = segments.iter().map(|s| s.mercator_points.len()).sum();
+            let largest = segments
+                .iter()
+                .map(|s| s.mercator_points.len())
+                .max()
+                .unwrap_or(0);
+            (
+                segments.len(),
+                total as f64 / segments.len() as f64,
+                largest,
+            )
+        };
+
+        let (low_count, low_mean, _) = shape(0.0);
+        let (default_count, default_mean, _) = shape(DEFAULT_CHUNKING_AGGRESSIVENESS);
+        let (high_count, high_mean, high_largest) = shape(1.0);
+
+        assert!(
+            low_count <= default_count && default_count < high_count,
+            "segment counts: low={}, default={}, high={}",
+            low_count,
+            default_count,
+            high_count
+        );
+        assert!(high_mean < default_mean && default_mean <= low_mean);
+        assert!(high_largest <= 64, "largest high chunk: {}", high_largest);
+    }
+
+    #[test]
+    fn test_progressive_query_refines_in_background() {
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..5_000 {
+            let t = i as f64 * 1e-3;
+            segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                t * 0.1 + (t * 70.0).sin() * 0.002,
+                45.0 + (t * 30.0).cos() * 0.002,
+            )));
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let mut gpx = gpx::Gpx::default();
+        gpx.tracks.push(track);
+        let route = Route::new(gpx).unwrap();
+        let reference = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let build = || {
+            Quadtree::new_with_route(
+                route.clone(),
+                0,
+                reference,
+                1.0,
+                DEFAULT_CHUNKING_AGGRESSIVENESS,
+            )
+            .unwrap()
+        };
+        let viewport = |min: Point<f64>, max: Point<f64>| Rect::new(min.0, max.0);
+        let overview = viewport(
+            utils::wgs84_to_mercator(44.0, -1.0),
+            utils::wgs84_to_mercator(46.0, 1.5),
+        );
+        let zoomed = viewport(
+            utils::wgs84_to_mercator(44.99, 0.2),
+            utils::wgs84_to_mercator(45.01, 0.22),
+        );
+        let indices = |segments: Vec<SimplifiedSegment>| -> Vec<Vec<usize>> {
+            segments
+                .into_iter()
+                .flat_map(|segment| segment.parts)
+                .map(|part| part.simplified_indices)
+                .collect()
+        };
+        let exact = indices(build().query(zoomed, (1024.0, 768.0)));
+
+        // Nothing cached at all: simplified right away, nothing left to do
+        let cold = build();
+        assert_eq!(
+            indices(cold.query_progressive(zoomed, (1024.0, 768.0), true)),
+            exact
+        );
+        assert_eq!(cold.pending_refinements(), 0);
+
+        // After the overview, zooming in first shows the coarser cached level
+        let quadtree = build();
+        quadtree.query(overview, (1024.0, 768.0));
+        let first = indices(quadtree.query_progressive(zoomed, (1024.0, 768.0), true));
+        let count = |runs: &[Vec<usize>]| runs.iter().map(Vec::len).sum::<usize>();
+        assert!(count(&first) < count(&exact));
+
+        let start = std::time::Instant::now();
+        while quadtree.pending_refinements() > 0 {
+            assert!(start.elapsed().as_secs() < 10, "refinement never finished");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(
+            indices(quadtree.query_progressive(zoomed, (1024.0, 768.0), true)),
+            exact
+        );
+        // Picked up into the cache
+        assert!(quadtree.refinements.is_empty());
+        assert_eq!(indices(quadtree.query(zoomed, (1024.0, 768.0))), exact);
+    }
+
+    #[test]
+    fn test_refinements_of_a_removed_route_are_dropped() {
+        let mut quadtree = cache_test_quadtree(&[wiggly_route(45.0)]);
+        let overview = Rect::new(
+            utils::wgs84_to_mercator(44.0, -1.0).0,
+            utils::wgs84_to_mercator(46.0, 1.5).0,
+        );
+        let zoomed = Rect::new(
+            utils::wgs84_to_mercator(44.99, 0.1).0,
+            utils::wgs84_to_mercator(45.01, 0.12).0,
+        );
+        // On a single worker, the background jobs only run once `install` returns
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            quadtree.query(overview, (1024.0, 768.0));
+            quadtree.query_progressive(zoomed, (1024.0, 768.0), true);
+            assert!(quadtree.pending_refinements() > 0);
+            quadtree.remove_route(0);
+            assert_eq!(quadtree.pending_refinements(), 0);
+        });
+        // Queued behind the jobs, so they have finished when it returns
+        pool.install(|| ());
+        assert!(quadtree.refinements.is_empty());
+    }
+
+    /// A quadtree holding `routes` at indices 0.., like a collection adding them in turn
+    fn cache_test_quadtree(routes: &[Arc<Route>]) -> Quadtree {
+        let reference = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let mut quadtree = Quadtree::new(reference, 1.0);
+        for (index, route) in routes.iter().enumerate() {
+            quadtree
+                .merge(
+                    Quadtree::new_with_route(
+                        route.clone(),
+                        index,
+                        reference,
+                        1.0,
+                        DEFAULT_CHUNKING_AGGRESSIVENESS,
+                    )
+                    .unwrap(),
+                )
+                .unwrap();
+        }
+        quadtree
+    }
+
+    fn wiggly_route(latitude: f64) -> Arc<Route> {
+        use crate::{RouteBuilder, TrackPoint};
+
+        let points = (0..2_000).map(|i| {
+            let t = i as f64 * 1e-3;
+            TrackPoint::new(
+                latitude + (t * 30.0).cos() * 0.002,
+                t * 0.1 + (t * 70.0).sin() * 0.002,
+            )
+        });
+        RouteBuilder::new("Wiggly")
+            .add_segment(points)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_simplification_cache_survives_re_adding_a_route_while_a_copy_is_loaded() {
+        let gpx = wiggly_route(45.0).gpx_data().clone();
+        let copies = [
+            Route::new(gpx.clone()).unwrap(),
+            Route::new(gpx.clone()).unwrap(),
+        ];
+        let mut quadtree = cache_test_quadtree(&copies);
+        let viewport = Rect::new(
+            utils::wgs84_to_mercator(44.9, -0.1).0,
+            utils::wgs84_to_mercator(45.1, 0.3).0,
+        );
+        let first = quadtree.query(viewport, (1024.0, 768.0));
+        let snapshot: Vec<_> = quadtree
+            .simplification_cache
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+        assert!(!snapshot.is_empty());
+
+        // Removed and loaded again from the same file while the other copy
+        // stays: a new allocation, same points
+        quadtree.remove_route(0);
+        quadtree.close_route_gap(0);
+        let again = Route::new(gpx).unwrap();
+        let reference = quadtree.reference_pixel_viewport;
+        quadtree
+            .merge(
+                Quadtree::new_with_route(
+                    again.clone(),
+                    1,
+                    reference,
+                    1.0,
+                    DEFAULT_CHUNKING_AGGRESSIVENESS,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert!(
+            snapshot
+                .iter()
+                .all(|(key, _)| key.route_id == again.content_id())
+        );
+
+        // Answered from the entries cached before: nothing is recomputed
+        let second = quadtree.query(viewport, (1024.0, 768.0));
+        assert_eq!(quadtree.simplification_cache.len(), snapshot.len());
+        for (key, indices) in &snapshot {
+            assert!(Arc::ptr_eq(
+                &quadtree.simplification_cache.get(key).unwrap(),
+                indices
+            ));
+        }
+        let indices = |segments: &[SimplifiedSegment]| -> Vec<Vec<usize>> {
+            segments
+                .iter()
+                .flat_map(|segment| &segment.parts)
+                .map(|part| part.simplified_indices.clone())
+                .collect()
+        };
+        assert_eq!(indices(&second), indices(&first));
+    }
+
+    #[test]
+    fn test_removing_a_route_purges_its_cache_entries() {
+        let kept = wiggly_route(45.0);
+        let removed = wiggly_route(46.0);
+        let mut quadtree = cache_test_quadtree(&[kept.clone(), removed.clone()]);
+        let world = quadtree.root.bounding_box;
+        quadtree.query(world, (1024.0, 768.0));
+        let entries_of = |quadtree: &Quadtree, route: &Route| {
+            quadtree
+                .simplification_cache
+                .iter()
+                .filter(|entry| entry.key().route_id == route.content_id())
+                .count()
+        };
+        let kept_entries = entries_of(&quadtree, &kept);
+        assert!(kept_entries > 0);
+        assert!(entries_of(&quadtree, &removed) > 0);
+
+        quadtree.remove_route(1);
+        assert_eq!(entries_of(&quadtree, &removed), 0);
+        assert_eq!(entries_of(&quadtree, &kept), kept_entries);
+    }
+
+    #[test]
+    fn test_parallel_mercator_conversion_matches_serial() {
+        let waypoints: Vec<gpx::Waypoint> = (0..PARALLEL_CONVERSION_CHUNK * 3 + 17)
+            .map(|i| {
+                let t = i as f64 * 1e-4;
+                gpx::Waypoint::new(geo::Point::new(t.sin() * 10.0, t.cos() * 60.0))
+            })
+            .collect();
+        let serial: Vec<Point<f64>> = waypoints
+            .iter()
+            .map(|wp| utils::wgs84_to_mercator(wp.point().y(), wp.point().x()))
+            .collect();
+
+        let (points, bbox) = segment_to_mercator(&waypoints);
+        assert_eq!(points, serial);
+        assert_eq!(bbox, compute_segment_bbox(&serial));
+
+        let (points, bbox) = segment_to_mercator(&waypoints[..10]);
+        assert_eq!(points, serial[..10]);
+        assert_eq!(bbox, compute_segment_bbox(&serial[..10]));
+    }
+
+    #[test]
+    fn test_query_count_matches_raw_query() {
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        let mut segment = gpx::TrackSegment::default();
+        for i in 0..5000 {
+            let t = i as f64 * 0.001;
+            segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                -1.0 + t * 0.6,
+                51.0 + (t * 11.0).sin() * 0.5,
+            )));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+        let route = crate::Route::new(gpx).unwrap();
+        let screen = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let quadtree = Quadtree::new_with_route(
+            route.clone(),
+            0,
+            screen,
+            1.0,
+            DEFAULT_CHUNKING_AGGRESSIVENESS,
+        )
+        .unwrap();
+
+        let bounds = route.bounding_box();
+        let center = bounds.center();
+        let viewports = [
+            bounds,
+            Rect::new(bounds.min(), center),
+            Rect::new(
+                Coord {
+                    x: center.x - bounds.width() * 0.05,
+                    y: center.y - bounds.height() * 0.05,
+                },
+                Coord {
+                    x: center.x + bounds.width() * 0.05,
+                    y: center.y + bounds.height() * 0.05,
+                },
+            ),
+            Rect::new(
+                Coord {
+                    x: bounds.max().x + 1.0,
+                    y: bounds.max().y + 1.0,
+                },
+                Coord {
+                    x: bounds.max().x + 2.0,
+                    y: bounds.max().y + 2.0,
+                },
+            ),
+        ];
+        for viewport in viewports {
+            let mut raw_results = Vec::new();
+            quadtree.root.query_segments(viewport, &mut raw_results);
+            assert_eq!(quadtree.query_count(viewport), raw_results.len());
+        }
+        assert!(quadtree.query_count(bounds) > 1);
+        assert_eq!(quadtree.query_count(viewports[3]), 0);
+    }
+
+    #[test]
+    fn test_lod_reduction_lowers_detail() {
+        let mut gpx = gpx::Gpx::default();
+        let mut track = gpx::Track::default();
+        let mut segment = gpx::TrackSegment::default();
+        for i in 0..5000 {
+            let t = i as f64 * 0.001;
+            segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                -1.0 + t * 0.6,
+                51.0 + (t * 11.0).sin() * 0.5,
+            )));
+        }
+        track.segments.push(segment);
+        gpx.tracks.push(track);
+        let route = crate::Route::new(gpx).unwrap();
+        let screen = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let quadtree = Quadtree::new_with_route(
+            route.clone(),
+            0,
+            screen,
+            1.0,
+            DEFAULT_CHUNKING_AGGRESSIVENESS,
+        )
+        .unwrap();
+
+        let viewport = route.bounding_box();
+        let points = |lod_reduction: u32| -> usize {
+            quadtree
+                .query_with_lod_reduction(viewport, (1024.0, 768.0), true, false, lod_reduction)
+                .iter()
+                .flat_map(|segment| &segment.parts)
+                .map(|part| part.simplified_indices.len())
+                .sum()
+        };
+        let full = points(0);
+        assert_eq!(
+            full,
+            quadtree
+                .query(viewport, (1024.0, 768.0))
+                .iter()
+                .flat_map(|segment| &segment.parts)
+                .map(|part| part.simplified_indices.len())
+                .sum::<usize>()
+        );
+        let coarse = points(3);
+        assert!(coarse < full, "coarse={} full={}", coarse, full);
+        // The full-detail level is still cached as it was
+        assert_eq!(points(0), full);
+    }
+
+    #[test]
+    fn test_check_invariants_catches_corruption() {
+        // Zigzag of 2000 points from London towards Paris, deep enough to chunk
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..2000 {
+            let lat = 51.5 - i as f64 * 0.001;
+            let lon = -0.1 + i as f64 * 0.001 + if i % 2 == 0 { 0.0005 } else { 0.0 };
+            segment
+                .points
+                .push(gpx::Waypoint::new(Point::new(lon, lat)));
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let gpx = gpx::Gpx {
+            tracks: vec![track],
+            ..Default::default()
+        };
+        let reference = Rect::new(
+            Coord { x: 0.0, y: 0.0 },
+            Coord {
+                x: 1024.0,
+                y: 768.0,
+            },
+        );
+        let route = crate::Route::new(gpx).unwrap();
+        let quadtree =
+            Quadtree::new_with_route(route, 0, reference, 1.0, DEFAULT_CHUNKING_AGGRESSIVENESS)
+                .unwrap();
+        quadtree.check_invariants().unwrap();
+
+        fn first_segment(node: &mut QuadtreeNode) -> Option<&mut RawSegment> {
+            if !node.raw_segments.is_empty() {
+                return node.raw_segments.first_mut();
+            }
+            node.children
+                .as_mut()?
+                .iter_mut()
+                .find_map(|child| first_segment(child))
+        }
+
+        let mut out_of_range = quadtree.clone();
+        let segment = first_segment(&mut out_of_range.root).expect("the route is stored");
+        let len = segment.mercator_points.len();
+        segment.original_indices = Some(Arc::new((5000..5000 + len).collect()));
+        assert!(matches!(
+            out_of_range.check_invariants(),
+            Err(DataError::InvariantViolated(_))
+        ));
+
+        let mut misplaced = quadtree.clone();
+        let children = misplaced
+            .root
+            .children
+            .as_mut()
+            .expect("the route crosses the root's quarters");
+        let (min, max) = (
+            children[0].bounding_box.min(),
+            children[0].bounding_box.max(),
+        );
+        children[0].bounding_box = Rect::new(
+            Coord {
+                x: min.x + 1.0,
+                y: min.y,
+            },
+            max,
+        );
+        assert!(matches!(
+            misplaced.check_invariants(),
+            Err(DataError::InvariantViolated(_))
+        ));
+    }
 }
@@ -3,9 +3,49 @@
 //! This module provides the `Route` struct for storing parsed GPX data
 //! with precomputed metadata like bounding boxes and distances.
 
-use crate::{DataError, Result, utils};
+use crate::builder::RouteMetadata;
+use crate::{DataError, Result, RouteBuilder, TrackProfile};
 use geo::Rect;
-use std::sync::Arc;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Elevation changes smaller than this are GPS noise by default when
+/// totalling climbs (see [`Route::climb`])
+pub const DEFAULT_CLIMB_THRESHOLD_M: f64 = 3.0;
+
+/// Total ascent and descent, in meters (both positive)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Climb {
+    pub ascent_m: f64,
+    pub descent_m: f64,
+}
+
+impl std::ops::Add for Climb {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            ascent_m: self.ascent_m + other.ascent_m,
+            descent_m: self.descent_m + other.descent_m,
+        }
+    }
+}
+
+/// The last [`Route::climb`] result and the threshold it was computed with
+#[derive(Debug, Default)]
+struct ClimbCache(Mutex<Option<(f64, Climb)>>);
+
+impl Clone for ClimbCache {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(
+            *self
+                .0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        ))
+    }
+}
 
 /// Represents a single GPX route with raw data and precomputed metadata
 #[derive(Clone, Debug)]
@@ -19,104 +59,47 @@ pub struct Route {
     cached_total_points: usize,
     /// Cached total distance in meters (computed once during construction)
     cached_total_distance: f64,
+    /// Cached (min, max) elevation in meters, `None` if no point has elevation data
+    cached_elevation_range: Option<(f64, f64)>,
+    /// Cumulative distance and time, computed on first use
+    #[cfg_attr(feature = "serde", serde(skip))]
+    profile: OnceLock<TrackProfile>,
+    /// Ascent and descent at the threshold last asked for
+    #[cfg_attr(feature = "serde", serde(skip))]
+    climb: ClimbCache,
+    /// Hash of the points, computed on first use (see [`Route::content_id`])
+    #[cfg_attr(feature = "serde", serde(skip))]
+    content_id: OnceLock<u64>,
 }
 
 #[cfg_attr(feature = "profiling", profiling::all_functions)]
 impl Route {
     /// Create a new Route from GPX data
     ///
+    /// The document is fed through a [`RouteBuilder`], so parsed and built
+    /// routes are validated and measured by the same code.
+    ///
     /// # Arguments
     /// * `gpx_data` - Parsed GPX data containing tracks
     ///
     /// # Returns
     /// An `Arc<Route>` on success, or an error if the route is empty or invalid
     pub fn new(gpx_data: gpx::Gpx) -> Result<Arc<Self>> {
-        // High-level profiling scope for route construction.
-        // This helps attribute time spent parsing and building route metadata.
-        #[cfg(feature = "profiling")]
-        profiling::scope!("route::new");
-        // Compute all metadata in a single pass
-        let (bounding_box_mercator, total_points, total_distance) =
-            Self::compute_metadata(&gpx_data)?;
-
-        if total_points == 0 {
-            return Err(DataError::EmptyRoute);
-        }
-
-        Ok(Arc::new(Route {
-            gpx_data,
-            bounding_box_mercator,
-            cached_total_points: total_points,
-            cached_total_distance: total_distance,
-        }))
+        RouteBuilder::from_gpx(gpx_data).build()
     }
 
-    /// Compute all metadata in a single pass over the data
-    ///
-    /// Returns (bounding_box, total_points, total_distance)
-    fn compute_metadata(gpx: &gpx::Gpx) -> Result<(Rect<f64>, usize, f64)> {
-        // Profiling scope for metadata computation (bounding box, counts, distance).
-        // This is useful to separate parsing time from metadata computation in traces.
-        #[cfg(feature = "profiling")]
-        profiling::scope!("route::compute_metadata");
-        let mut min_x = f64::INFINITY;
-        let mut min_y = f64::INFINITY;
-        let mut max_x = f64::NEG_INFINITY;
-        let mut max_y = f64::NEG_INFINITY;
-
-        let mut total_points: usize = 0;
-        let mut total_distance: f64 = 0.0;
-        let mut found_valid_point = false;
-
-        for track in &gpx.tracks {
-            for segment in &track.segments {
-                let points = &segment.points;
-                let segment_len = points.len();
-                total_points += segment_len;
-
-                let mut prev_waypoint: Option<&gpx::Waypoint> = None;
-
-                for waypoint in points {
-                    let point = utils::waypoint_to_mercator(waypoint);
-
-                    if !utils::is_valid_mercator(&point) {
-                        tracing::warn!(
-                            "Skipping point outside Web Mercator bounds: ({}, {})",
-                            waypoint.point().y(),
-                            waypoint.point().x()
-                        );
-                        prev_waypoint = None; // Break distance chain
-                        continue;
-                    }
-
-                    // Update bounding box
-                    min_x = min_x.min(point.x());
-                    min_y = min_y.min(point.y());
-                    max_x = max_x.max(point.x());
-                    max_y = max_y.max(point.y());
-                    found_valid_point = true;
-
-                    // Compute distance from previous point
-                    if let Some(prev) = prev_waypoint {
-                        total_distance += Self::haversine_distance(prev, waypoint);
-                    }
-                    prev_waypoint = Some(waypoint);
-                }
-            }
-        }
-
-        if !found_valid_point {
-            return Err(DataError::InvalidGeometry(
-                "No valid points in route".to_string(),
-            ));
-        }
-
-        let bounding_box = Rect::new(
-            geo::Coord { x: min_x, y: min_y },
-            geo::Coord { x: max_x, y: max_y },
-        );
-
-        Ok((bounding_box, total_points, total_distance))
+    /// Wrap a document whose metadata a [`RouteBuilder`] has computed
+    pub(crate) fn with_metadata(gpx_data: gpx::Gpx, metadata: RouteMetadata) -> Arc<Self> {
+        Arc::new(Route {
+            gpx_data,
+            bounding_box_mercator: metadata.bounding_box,
+            cached_total_points: metadata.total_points,
+            cached_total_distance: metadata.total_distance,
+            cached_elevation_range: metadata.elevation_range,
+            profile: OnceLock::new(),
+            climb: ClimbCache::default(),
+            content_id: OnceLock::new(),
+        })
     }
 
     /// Get the bounding box in Web Mercator meters
@@ -125,6 +108,32 @@ impl Route {
         self.bounding_box_mercator
     }
 
+    /// Hash of the coordinates and elevations of every point, track by track
+    ///
+    /// Unlike the route's address, it stays the same when the same file is
+    /// removed and loaded again, so caches keyed by it survive the round trip.
+    /// Two routes with the same points share an id, which is fine for anything
+    /// that only depends on the geometry.
+    pub fn content_id(&self) -> u64 {
+        *self.content_id.get_or_init(|| {
+            let mut hasher = DefaultHasher::new();
+            for track in &self.gpx_data.tracks {
+                track.segments.len().hash(&mut hasher);
+                for segment in &track.segments {
+                    segment.points.len().hash(&mut hasher);
+                    for waypoint in &segment.points {
+                        let point = waypoint.point();
+                        point.x().to_bits().hash(&mut hasher);
+                        point.y().to_bits().hash(&mut hasher);
+                        waypoint.elevation.map(f64::to_bits).hash(&mut hasher);
+                    }
+                }
+            }
+            self.gpx_data.tracks.len().hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+
     /// Access the raw GPX data
     #[inline]
     pub fn gpx_data(&self) -> &gpx::Gpx {
@@ -171,9 +180,284 @@ impl Route {
         self.cached_total_distance
     }
 
+    /// Get the (min, max) elevation in meters across all valid points
+    ///
+    /// This is O(1) as the value is cached during construction.
+    /// Returns `None` if no point carries elevation data.
+    #[inline]
+    pub fn elevation_range(&self) -> Option<(f64, f64)> {
+        self.cached_elevation_range
+    }
+
+    /// Sum of the elevation climbed along each segment, in meters
+    ///
+    /// Adds up every rise between consecutive points that both carry an
+    /// elevation, without smoothing. Returns `None` if no point does.
+    pub fn elevation_gain(&self) -> Option<f64> {
+        self.sum_elevation_steps(1.0)
+    }
+
+    /// Sum of the elevation descended along each segment, in meters (positive)
+    ///
+    /// The counterpart of [`Route::elevation_gain`], with the same rules.
+    pub fn elevation_loss(&self) -> Option<f64> {
+        self.sum_elevation_steps(-1.0)
+    }
+
+    /// Total ascent and descent, ignoring elevation changes below `threshold_m`
+    ///
+    /// Summing every step between noisy GPS elevations counts each meter of
+    /// jitter as climbing. Instead, each segment keeps a reference elevation
+    /// that only moves once the track has gone at least `threshold_m` above or
+    /// below it, and only those moves are counted. A threshold of zero gives
+    /// the raw sums of [`Route::elevation_gain`] and [`Route::elevation_loss`].
+    /// The result for the last threshold is cached. Returns `None` if no point
+    /// carries elevation data.
+    pub fn climb(&self, threshold_m: f64) -> Option<Climb> {
+        self.cached_elevation_range?;
+        let mut cache = self
+            .climb
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some((threshold, climb)) = *cache
+            && threshold == threshold_m
+        {
+            return Some(climb);
+        }
+        let mut climb = Climb::default();
+        for segment in self.tracks().iter().flat_map(|track| &track.segments) {
+            let mut reference: Option<f64> = None;
+            for elevation in segment.points.iter().filter_map(|p| p.elevation) {
+                let Some(from) = reference else {
+                    reference = Some(elevation);
+                    continue;
+                };
+                if elevation - from >= threshold_m {
+                    climb.ascent_m += elevation - from;
+                    reference = Some(elevation);
+                } else if from - elevation >= threshold_m {
+                    climb.descent_m += from - elevation;
+                    reference = Some(elevation);
+                }
+            }
+        }
+        *cache = Some((threshold_m, climb));
+        Some(climb)
+    }
+
+    /// Add up the steps between consecutive elevations going in `direction` (±1)
+    fn sum_elevation_steps(&self, direction: f64) -> Option<f64> {
+        self.cached_elevation_range?;
+        let mut total = 0.0;
+        for segment in self.tracks().iter().flat_map(|track| &track.segments) {
+            let mut previous: Option<f64> = None;
+            for elevation in segment.points.iter().filter_map(|p| p.elevation) {
+                if let Some(previous) = previous {
+                    total += ((elevation - previous) * direction).max(0.0);
+                }
+                previous = Some(elevation);
+            }
+        }
+        Some(total)
+    }
+
+    /// Cumulative distance, elapsed time and speed at every point
+    ///
+    /// Computed on the first call and cached for the life of the route, so
+    /// everything that plots or steps along the route shares one pass.
+    pub fn profile(&self) -> &TrackProfile {
+        self.profile.get_or_init(|| TrackProfile::new(self))
+    }
+
+    /// Display name: the file's metadata name, else the first named track
+    pub fn name(&self) -> Option<&str> {
+        self.gpx_data
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.name.as_deref())
+            .or_else(|| self.tracks().iter().find_map(|track| track.name.as_deref()))
+    }
+
+    /// Split the route wherever consecutive points are too far apart in time or space
+    ///
+    /// A new sub-route starts between two points of a segment more than
+    /// `max_time` apart (when both carry a time) or more than `max_dist`
+    /// meters apart; `None` disables that check. Track and segment structure,
+    /// elevations and times are kept; file-level waypoints and routes stay
+    /// with the first sub-route. A route without gaps comes back as one
+    /// sub-route equal to itself.
+    pub fn split_by_gaps(
+        &self,
+        max_time: Option<Duration>,
+        max_dist: Option<f64>,
+    ) -> Vec<Arc<Route>> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("route::split_by_gaps");
+
+        let is_gap = |a: &gpx::Waypoint, b: &gpx::Waypoint| {
+            let time_gap = match (max_time, a.time, b.time) {
+                (Some(max_time), Some(a), Some(b)) => {
+                    let elapsed = time::OffsetDateTime::from(b) - time::OffsetDateTime::from(a);
+                    elapsed.as_seconds_f64() > max_time.as_secs_f64()
+                }
+                _ => false,
+            };
+            time_gap || max_dist.is_some_and(|max_dist| Self::haversine_distance(a, b) > max_dist)
+        };
+
+        let empty = gpx::Gpx {
+            tracks: Vec::new(),
+            waypoints: Vec::new(),
+            routes: Vec::new(),
+            ..self.gpx_data.clone()
+        };
+        let mut parts: Vec<gpx::Gpx> = Vec::new();
+        let mut current = gpx::Gpx {
+            tracks: Vec::new(),
+            ..self.gpx_data.clone()
+        };
+        for track in &self.gpx_data.tracks {
+            let structure = gpx::Track {
+                segments: Vec::new(),
+                ..track.clone()
+            };
+            current.tracks.push(structure.clone());
+            for segment in &track.segments {
+                let mut piece = gpx::TrackSegment::new();
+                for (i, point) in segment.points.iter().enumerate() {
+                    if i > 0 && is_gap(&segment.points[i - 1], point) {
+                        let mut finished = std::mem::replace(&mut current, empty.clone());
+                        finished
+                            .tracks
+                            .last_mut()
+                            .unwrap()
+                            .segments
+                            .push(std::mem::take(&mut piece));
+                        parts.push(finished);
+                        current.tracks.push(structure.clone());
+                    }
+                    piece.points.push(point.clone());
+                }
+                if !piece.points.is_empty() {
+                    current.tracks.last_mut().unwrap().segments.push(piece);
+                }
+            }
+        }
+        parts.push(current);
+
+        parts
+            .into_iter()
+            .filter_map(|mut gpx| {
+                gpx.tracks.retain(|track| !track.segments.is_empty());
+                // Every piece holds valid points of this route, only an empty one fails
+                Self::new(gpx).ok()
+            })
+            .collect()
+    }
+
+    /// Original point closest to `lat`/`lon`, as (track, segment, point)
+    /// indices and the distance to it in meters
+    ///
+    /// Looks at every point, so it suits one-off picks like a click, not
+    /// per-frame work.
+    pub fn nearest_point(&self, lat: f64, lon: f64) -> Option<((usize, usize, usize), f64)> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("route::nearest_point");
+
+        let target = gpx::Waypoint::new(geo::Point::new(lon, lat));
+        let mut best: Option<((usize, usize, usize), f64)> = None;
+        for (t, track) in self.gpx_data.tracks.iter().enumerate() {
+            for (s, segment) in track.segments.iter().enumerate() {
+                for (p, point) in segment.points.iter().enumerate() {
+                    let distance = Self::haversine_distance(&target, point);
+                    if best.is_none_or(|(_, best_distance)| distance < best_distance) {
+                        best = Some(((t, s, p), distance));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Cut the route in two at a point, which ends the first part and starts the second
+    ///
+    /// Points are copied as they are, so times, elevations and every other
+    /// field survive exactly. The track and segment holding the cut are
+    /// split (both parts keep the track's name and other details); tracks
+    /// and segments before it go to the first part, the rest to the second.
+    /// File-level waypoints and routes stay with the first part, as in
+    /// [`Route::split_by_gaps`]. Cutting at the first or last point of the
+    /// route, or at a point that doesn't exist, is an error.
+    pub fn split_at(
+        &self,
+        track_index: usize,
+        segment_index: usize,
+        point_index: usize,
+    ) -> Result<(Arc<Self>, Arc<Self>)> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("route::split_at");
+
+        if self
+            .get_waypoint(track_index, segment_index, point_index)
+            .is_none()
+        {
+            return Err(DataError::InvalidGeometry(format!(
+                "no point {} in segment {} of track {}",
+                point_index, segment_index, track_index
+            )));
+        }
+
+        let tracks = &self.gpx_data.tracks;
+        let track = &tracks[track_index];
+        let segment = &track.segments[segment_index];
+        let structure = gpx::Track {
+            segments: Vec::new(),
+            ..track.clone()
+        };
+
+        let mut first_track = structure.clone();
+        first_track
+            .segments
+            .extend_from_slice(&track.segments[..segment_index]);
+        let mut head = segment.clone();
+        head.points.truncate(point_index + 1);
+        first_track.segments.push(head);
+        let mut first = gpx::Gpx {
+            tracks: tracks[..track_index].to_vec(),
+            ..self.gpx_data.clone()
+        };
+        first.tracks.push(first_track);
+
+        let mut second_track = structure;
+        let mut tail = segment.clone();
+        tail.points.drain(..point_index);
+        second_track.segments.push(tail);
+        second_track
+            .segments
+            .extend_from_slice(&track.segments[segment_index + 1..]);
+        let mut second = gpx::Gpx {
+            tracks: vec![second_track],
+            waypoints: Vec::new(),
+            routes: Vec::new(),
+            ..self.gpx_data.clone()
+        };
+        second.tracks.extend_from_slice(&tracks[track_index + 1..]);
+
+        let first = Self::new(first)?;
+        let second = Self::new(second)?;
+        // The cut point is in both parts, so an end of the route leaves one with only that point
+        if first.total_points() < 2 || second.total_points() < 2 {
+            return Err(DataError::InvalidGeometry(
+                "can't split a route at its first or last point".to_string(),
+            ));
+        }
+        Ok((first, second))
+    }
+
     /// Calculate the Haversine distance between two waypoints in meters
     #[inline]
-    fn haversine_distance(p1: &gpx::Waypoint, p2: &gpx::Waypoint) -> f64 {
+    pub(crate) fn haversine_distance(p1: &gpx::Waypoint, p2: &gpx::Waypoint) -> f64 {
         let point1 = p1.point();
         let point2 = p2.point();
 
@@ -232,6 +516,132 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_split_by_gaps() {
+        let start = time::OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let mut segment = TrackSegment::default();
+        for i in 0..10 {
+            // ~11 m and 1 s apart, except for an hour-long pause before point 4
+            // and a ~1 km jump before point 7
+            let lon = if i >= 7 {
+                0.01 + i as f64 * 0.0001
+            } else {
+                i as f64 * 0.0001
+            };
+            let mut point = create_test_waypoint(0.0, lon);
+            let seconds = if i >= 4 { 3600 + i } else { i };
+            point.time = Some((start + time::Duration::seconds(seconds)).into());
+            point.elevation = Some(100.0 + i as f64);
+            segment.points.push(point);
+        }
+        let mut track = Track {
+            name: Some("Ride".to_string()),
+            ..Default::default()
+        };
+        track.segments.push(segment);
+        let mut gpx = Gpx::default();
+        gpx.tracks.push(track);
+        let route = Route::new(gpx).unwrap();
+
+        let parts = route.split_by_gaps(Some(Duration::from_secs(60)), Some(500.0));
+        let sizes: Vec<usize> = parts.iter().map(|part| part.total_points()).collect();
+        assert_eq!(sizes, vec![4, 3, 3]);
+        let second = &parts[1].tracks()[0];
+        assert_eq!(second.name.as_deref(), Some("Ride"));
+        assert_eq!(second.segments[0].points[0].elevation, Some(104.0));
+        assert_eq!(
+            second.segments[0].points[0].time,
+            route.tracks()[0].segments[0].points[4].time
+        );
+
+        // Only the thresholds that are set split
+        assert_eq!(route.split_by_gaps(None, Some(500.0)).len(), 2);
+        assert_eq!(
+            route
+                .split_by_gaps(Some(Duration::from_secs(60)), None)
+                .len(),
+            2
+        );
+        let whole = route.split_by_gaps(None, None);
+        assert_eq!(whole.len(), 1);
+        assert_eq!(whole[0].gpx_data(), route.gpx_data());
+    }
+
+    #[test]
+    fn test_split_at() {
+        let start = time::OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let mut gpx = Gpx::default();
+        gpx.waypoints.push(create_test_waypoint(0.0, 0.0));
+        // Two tracks of two segments of three points each
+        for t in 0..2 {
+            let mut track = Track {
+                name: Some(format!("Track {}", t)),
+                ..Default::default()
+            };
+            for s in 0..2 {
+                let mut segment = TrackSegment::default();
+                for p in 0..3 {
+                    let i = t * 6 + s * 3 + p;
+                    let mut point = create_test_waypoint(0.0, i as f64 * 0.0001);
+                    point.time = Some((start + time::Duration::seconds(i as i64)).into());
+                    point.elevation = Some(100.5 + i as f64);
+                    segment.points.push(point);
+                }
+                track.segments.push(segment);
+            }
+            gpx.tracks.push(track);
+        }
+        let route = Route::new(gpx).unwrap();
+
+        // Point 7 of 12 is the middle of the first segment of the second track
+        let ((t, s, p), distance) = route.nearest_point(0.00001, 0.00071).unwrap();
+        assert_eq!((t, s, p), (1, 0, 1));
+        assert!(distance < 5.0);
+
+        let (first, second) = route.split_at(t, s, p).unwrap();
+        assert_eq!(first.total_points(), 8);
+        assert_eq!(second.total_points(), 5);
+        assert_eq!(first.tracks().len(), 2);
+        assert_eq!(second.tracks().len(), 1);
+        assert_eq!(first.tracks()[1].name.as_deref(), Some("Track 1"));
+        assert_eq!(second.tracks()[0].name.as_deref(), Some("Track 1"));
+        assert_eq!(first.gpx_data().waypoints.len(), 1);
+        assert!(second.gpx_data().waypoints.is_empty());
+
+        // The cut point ends one part and starts the other, unchanged
+        let cut = route.get_waypoint(1, 0, 1).unwrap();
+        assert_eq!(first.get_waypoint(1, 0, 1), Some(cut));
+        assert_eq!(second.get_waypoint(0, 0, 0), Some(cut));
+        // Every other point is in exactly one part, times and elevations intact
+        let points = |route: &Route| -> Vec<gpx::Waypoint> {
+            route
+                .tracks()
+                .iter()
+                .flat_map(|track| &track.segments)
+                .flat_map(|segment| segment.points.clone())
+                .collect()
+        };
+        let mut rejoined = points(&first);
+        rejoined.extend(points(&second).into_iter().skip(1));
+        assert_eq!(rejoined, points(&route));
+
+        assert!(route.split_at(0, 0, 0).is_err());
+        assert!(route.split_at(1, 1, 2).is_err());
+        assert!(route.split_at(0, 0, 3).is_err());
+    }
+
+    #[test]
+    fn test_non_finite_coordinate_fails() {
+        let mut gpx = create_test_gpx();
+        gpx.tracks[0].segments[0]
+            .points
+            .push(create_test_waypoint(f64::NAN, -0.1272));
+        match Route::new(gpx) {
+            Err(DataError::InvalidGeometry(message)) => assert!(message.contains("point 3")),
+            other => panic!("expected an invalid geometry error, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_bounding_box() {
         let gpx = create_test_gpx();
@@ -280,4 +690,55 @@ mod tests {
         let dist2 = route.total_distance();
         assert!((dist1 - dist2).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_elevation_range() {
+        let route = Route::new(create_test_gpx()).unwrap();
+        assert!(route.elevation_range().is_none());
+
+        let mut gpx = create_test_gpx();
+        for (point, elevation) in gpx.tracks[0].segments[0]
+            .points
+            .iter_mut()
+            .zip([12.0, -3.5, 40.25])
+        {
+            point.elevation = Some(elevation);
+        }
+        let route = Route::new(gpx).unwrap();
+        assert_eq!(route.elevation_range(), Some((-3.5, 40.25)));
+    }
+
+    #[test]
+    fn test_climb_ignores_gps_noise() {
+        // Up 100 m, down 60 m and up 40 m again, with ±1.5 m of jitter on top
+        let mut segment = TrackSegment::default();
+        let mut true_elevation = 500.0;
+        for i in 0..3000 {
+            true_elevation += match i {
+                0..1000 => 0.1,
+                1000..2000 => -0.06,
+                _ => 0.04,
+            };
+            // Deterministic jitter, alternating so it never drifts
+            let jitter = if i % 2 == 0 { 1.5 } else { -1.5 } * ((i % 7) as f64 / 6.0);
+            let mut point = create_test_waypoint(45.0, i as f64 * 1e-4);
+            point.elevation = Some(true_elevation + jitter);
+            segment.points.push(point);
+        }
+        let mut gpx = create_test_gpx();
+        gpx.tracks[0].segments = vec![segment];
+        let route = Route::new(gpx).unwrap();
+
+        let filtered = route.climb(DEFAULT_CLIMB_THRESHOLD_M).unwrap();
+        assert!((filtered.ascent_m - 140.0).abs() < 5.0, "{:?}", filtered);
+        assert!((filtered.descent_m - 60.0).abs() < 5.0, "{:?}", filtered);
+        // Counting every step overshoots many times over
+        let raw = route.elevation_gain().unwrap();
+        assert!(raw > 1000.0, "{}", raw);
+        let unfiltered = route.climb(0.0).unwrap();
+        assert!((unfiltered.ascent_m - raw).abs() < 1e-6);
+        assert!((unfiltered.descent_m - route.elevation_loss().unwrap()).abs() < 1e-6);
+
+        assert_eq!(Route::new(create_test_gpx()).unwrap().climb(3.0), None);
+    }
 }
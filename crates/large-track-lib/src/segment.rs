@@ -4,6 +4,7 @@
 //! that reference the original route data without duplicating points.
 
 use crate::Route;
+use geo::Point;
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -39,6 +40,27 @@ pub struct SegmentPart {
     pub simplified_indices: Vec<usize>,
 }
 
+/// A single original (unsimplified) point returned by a raw viewport query
+///
+/// Indices address the point in the owning route's GPX data, so the full
+/// waypoint can be fetched with [`Route::get_waypoint`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawPointRef {
+    /// Index of the route in the collection
+    pub route_index: usize,
+    /// Index of the track in the route
+    pub track_index: usize,
+    /// Index of the segment in the track
+    pub segment_index: usize,
+    /// Index of the point in the original segment
+    pub point_index: usize,
+    /// Position in WGS84 (x = longitude, y = latitude)
+    pub wgs84: Point<f64>,
+    /// Position in Web Mercator meters
+    pub mercator: Point<f64>,
+}
+
 #[cfg_attr(feature = "profiling", profiling::all_functions)]
 impl SimplifiedSegment {
     /// Create a new simplified segment
@@ -144,20 +166,26 @@ impl SegmentPart {
         #[cfg(feature = "profiling")]
         profiling::scope!("segment::get_simplified_points");
 
-        let segment = match route
+        self.iter_simplified_points(route).collect()
+    }
+
+    /// Like [`SegmentPart::get_simplified_points`], without collecting them
+    ///
+    /// For callers that draw every frame and keep their own buffers.
+    pub fn iter_simplified_points<'s, 'a: 's>(
+        &'s self,
+        route: &'a Route,
+    ) -> impl Iterator<Item = &'a gpx::Waypoint> + 's {
+        let points = route
             .gpx_data()
             .tracks
             .get(self.track_index)
             .and_then(|t| t.segments.get(self.segment_index))
-        {
-            Some(seg) => seg,
-            None => return Vec::new(),
-        };
+            .map_or(&[][..], |segment| segment.points.as_slice());
 
         self.simplified_indices
             .iter()
-            .filter_map(|&idx| segment.points.get(self.point_range.start + idx))
-            .collect()
+            .filter_map(move |&idx| points.get(self.point_range.start + idx))
     }
 
     /// Get all points including boundary context for rendering
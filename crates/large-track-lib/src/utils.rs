@@ -1,4 +1,16 @@
 //! Utility functions for coordinate conversions and spatial operations
+//!
+//! All conversions use the spherical Web Mercator projection (EPSG:3857)
+//! that map tiles are drawn in:
+//!
+//! - Functions taking separate numbers take `(lat, lon)` in degrees or
+//!   `(x, y)` in meters, in that order.
+//! - A WGS84 [`Point`] follows the `geo`/GPX convention: `x` is the longitude
+//!   and `y` the latitude.
+//! - Latitudes are clamped to ±[`MAX_LATITUDE`], where the projection's
+//!   square world ends. Inside that range a WGS84 → Mercator → WGS84 round
+//!   trip lands within 1 cm of the original position (and the reverse within
+//!   1 cm on the map), which the tests check across the whole range.
 
 use geo::Point;
 
@@ -8,7 +20,12 @@ pub const EARTH_MERCATOR_MIN: f64 = -20037508.34;
 pub const EARTH_SIZE_METERS: f64 = EARTH_MERCATOR_MAX - EARTH_MERCATOR_MIN;
 
 /// Maximum latitude that can be represented in Web Mercator
-pub const MAX_LATITUDE: f64 = 85.05112878;
+///
+/// At ±this latitude the projected world is exactly as tall as it is wide.
+pub const MAX_LATITUDE: f64 = 85.0511287798066;
+
+/// Width and height of one map tile, in pixels
+pub const TILE_SIZE_PIXELS: f64 = 256.0;
 
 /// Precomputed constant: EARTH_MERCATOR_MAX / 180.0
 const LON_TO_X_FACTOR: f64 = EARTH_MERCATOR_MAX / 180.0;
@@ -22,6 +39,12 @@ const X_TO_LON_FACTOR: f64 = 180.0 / EARTH_MERCATOR_MAX;
 /// Precomputed constant: PI / EARTH_MERCATOR_MAX
 const Y_TO_LAT_FACTOR: f64 = std::f64::consts::PI / EARTH_MERCATOR_MAX;
 
+/// Clamp a latitude in degrees to the range Web Mercator can represent
+#[inline(always)]
+pub fn clamp_latitude(lat: f64) -> f64 {
+    lat.clamp(-MAX_LATITUDE, MAX_LATITUDE)
+}
+
 /// Convert WGS84 (lat, lon) to Web Mercator (x, y) in meters
 ///
 /// # Arguments
@@ -32,16 +55,13 @@ const Y_TO_LAT_FACTOR: f64 = std::f64::consts::PI / EARTH_MERCATOR_MAX;
 /// A `Point<f64>` with x (easting) and y (northing) in meters
 #[inline(always)]
 pub fn wgs84_to_mercator(lat: f64, lon: f64) -> Point<f64> {
-    // Clamp latitude to valid Web Mercator range
-    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE);
-
-    let x = lon * LON_TO_X_FACTOR;
-
-    // Optimized: compute lat_rad once
-    let lat_rad = lat.to_radians();
-    let y = (lat_rad.tan() + (1.0 / lat_rad.cos())).ln() * Y_FACTOR;
+    wgs84_to_mercator_unclamped(clamp_latitude(lat), lon)
+}
 
-    Point::new(x, y)
+/// Convert a WGS84 point (`x` = longitude, `y` = latitude) to Web Mercator
+#[inline(always)]
+pub fn wgs84_point_to_mercator(point: Point<f64>) -> Point<f64> {
+    wgs84_to_mercator(point.y(), point.x())
 }
 
 /// Convert WGS84 to Web Mercator without clamping (for trusted input)
@@ -71,6 +91,48 @@ pub fn mercator_to_wgs84(x: f64, y: f64) -> (f64, f64) {
     (lat, lon)
 }
 
+/// Convert a Web Mercator point to WGS84 (`x` = longitude, `y` = latitude)
+///
+/// The inverse of [`wgs84_point_to_mercator`], for code that keeps positions
+/// as [`Point`]s rather than `(lat, lon)` tuples.
+#[inline(always)]
+pub fn mercator_to_wgs84_point(point: Point<f64>) -> Point<f64> {
+    let (lat, lon) = mercator_to_wgs84(point.x(), point.y());
+    Point::new(lon, lat)
+}
+
+/// Web Mercator meters covered by one screen pixel at `zoom`
+///
+/// The same everywhere on the map; use [`meters_per_pixel`] for real
+/// distances.
+#[inline(always)]
+pub fn mercator_meters_per_pixel(zoom: f64) -> f64 {
+    EARTH_SIZE_METERS / (TILE_SIZE_PIXELS * zoom.exp2())
+}
+
+/// Meters on the ground covered by one screen pixel at latitude `lat` and `zoom`
+///
+/// Mercator stretches the map by `1 / cos(lat)`, so a pixel covers less
+/// ground towards the poles: `156543.03392 * cos(lat) / 2^zoom`. Scale bars,
+/// distance rings, widths in meters and pick radii all go through this, so
+/// they agree with each other and with the tiles.
+#[inline(always)]
+pub fn meters_per_pixel(lat: f64, zoom: f64) -> f64 {
+    mercator_meters_per_pixel(zoom) * clamp_latitude(lat).to_radians().cos()
+}
+
+/// Ground distance spanned by `pixels` on screen at latitude `lat` and `zoom`
+#[inline(always)]
+pub fn pixels_to_meters(pixels: f64, lat: f64, zoom: f64) -> f64 {
+    pixels * meters_per_pixel(lat, zoom)
+}
+
+/// Screen pixels spanned by `meters` on the ground at latitude `lat` and `zoom`
+#[inline(always)]
+pub fn meters_to_pixels(meters: f64, lat: f64, zoom: f64) -> f64 {
+    meters / meters_per_pixel(lat, zoom)
+}
+
 /// Convert a GPX waypoint to Web Mercator point
 #[inline(always)]
 pub fn waypoint_to_mercator(waypoint: &gpx::Waypoint) -> Point<f64> {
@@ -140,4 +202,109 @@ mod tests {
         assert!((clamped.x() - unclamped.x()).abs() < f64::EPSILON);
         assert!((clamped.y() - unclamped.y()).abs() < f64::EPSILON);
     }
+
+    /// Deterministic pseudo-random values in `range`, for sweeping a whole range
+    fn samples(range: std::ops::RangeInclusive<f64>, count: usize) -> Vec<f64> {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut values: Vec<f64> = (0..count)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                let unit = (state >> 11) as f64 / (1u64 << 53) as f64;
+                range.start() + unit * (range.end() - range.start())
+            })
+            .collect();
+        values.extend([*range.start(), *range.end(), 0.0]);
+        values
+    }
+
+    /// Approximate ground distance in meters between two nearby WGS84 positions
+    fn ground_error_meters(a: Point<f64>, b: Point<f64>) -> f64 {
+        const METERS_PER_DEGREE: f64 = 111_320.0;
+        let dlat = (a.y() - b.y()) * METERS_PER_DEGREE;
+        let dlon = (a.x() - b.x()) * METERS_PER_DEGREE * a.y().to_radians().cos();
+        dlat.hypot(dlon)
+    }
+
+    #[test]
+    fn test_wgs84_round_trip_within_a_centimeter() {
+        let lats = samples(-MAX_LATITUDE..=MAX_LATITUDE, 2_000);
+        let lons = samples(-180.0..=180.0, 50);
+        for &lat in &lats {
+            for &lon in &lons {
+                let original = Point::new(lon, lat);
+                let back = mercator_to_wgs84_point(wgs84_point_to_mercator(original));
+                let error = ground_error_meters(original, back);
+                assert!(error < 0.01, "{} m off at {}, {}", error, lat, lon);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mercator_round_trip_within_a_centimeter() {
+        let coords = samples(EARTH_MERCATOR_MIN..=EARTH_MERCATOR_MAX, 300);
+        for &x in &coords {
+            for &y in &coords {
+                let original = Point::new(x, y);
+                let back = wgs84_point_to_mercator(mercator_to_wgs84_point(original));
+                let error = (original.x() - back.x()).hypot(original.y() - back.y());
+                assert!(error < 0.01, "{} m off at {}, {}", error, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_point_and_tuple_conversions_agree() {
+        let mercator = wgs84_to_mercator(51.5074, -0.1278);
+        assert_eq!(
+            wgs84_point_to_mercator(Point::new(-0.1278, 51.5074)),
+            mercator
+        );
+        let (lat, lon) = mercator_to_wgs84(mercator.x(), mercator.y());
+        assert_eq!(mercator_to_wgs84_point(mercator), Point::new(lon, lat));
+    }
+
+    #[test]
+    fn test_latitude_is_clamped_to_the_square_world() {
+        assert_eq!(clamp_latitude(90.0), MAX_LATITUDE);
+        assert_eq!(clamp_latitude(-90.0), -MAX_LATITUDE);
+        assert_eq!(clamp_latitude(45.0), 45.0);
+
+        let pole = wgs84_to_mercator(90.0, 0.0);
+        assert!((pole.y() - EARTH_MERCATOR_MAX).abs() < 1.0);
+        assert!(is_valid_mercator(&wgs84_to_mercator(-90.0, 180.0)));
+    }
+
+    #[test]
+    fn test_meters_per_pixel() {
+        // Zoom 0 shows the whole world in one tile
+        assert!(
+            (mercator_meters_per_pixel(0.0) * TILE_SIZE_PIXELS - EARTH_SIZE_METERS).abs() < 1e-6
+        );
+        // Each zoom level halves it
+        assert!(
+            (mercator_meters_per_pixel(10.0) * 2.0 - mercator_meters_per_pixel(9.0)).abs() < 1e-9
+        );
+        // About 156 km per pixel at the equator at zoom 0, half that at 60°
+        assert!((meters_per_pixel(0.0, 0.0) - 156_543.03).abs() < 0.01);
+        assert!((meters_per_pixel(60.0, 0.0) - 78_271.52).abs() < 0.01);
+        assert_eq!(meters_per_pixel(-20.0, 3.0), meters_per_pixel(20.0, 3.0));
+        // Mid-latitudes at street zooms
+        assert!((meters_per_pixel(45.0, 10.0) - 108.098).abs() < 1e-3);
+        assert!((meters_per_pixel(60.0, 16.0) - 1.1943).abs() < 1e-4);
+        // Past the square world's edge the scale stays that of the edge
+        assert_eq!(
+            meters_per_pixel(90.0, 5.0),
+            meters_per_pixel(MAX_LATITUDE, 5.0)
+        );
+        assert!(meters_per_pixel(90.0, 5.0) > 0.0);
+
+        // 100 px at zoom 10 on the equator is about 15.3 km, and back
+        let meters = pixels_to_meters(100.0, 0.0, 10.0);
+        assert!((meters - 15_287.41).abs() < 0.01);
+        assert!((meters_to_pixels(meters, 0.0, 10.0) - 100.0).abs() < 1e-9);
+        // The same distance spans more pixels further north
+        assert!(meters_to_pixels(meters, 45.0, 10.0) > 141.0);
+    }
 }
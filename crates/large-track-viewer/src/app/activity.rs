@@ -0,0 +1,68 @@
+//! What keeps the app repainting when nobody touches it
+//!
+//! egui only repaints on input unless asked to, which is what lets an idle
+//! window use next to no power. Everything that needs frames without input
+//! (animations, loading, polling for background work) registers here during
+//! `update()` instead of requesting repaints itself; the earliest wanted
+//! repaint is requested once at the end of the frame. Map tiles aren't
+//! registered: walkers wakes the app itself when a download finishes.
+
+use std::time::Duration;
+
+/// Repaint requests gathered over one frame
+#[derive(Debug, Default)]
+pub struct Activity {
+    /// Earliest repaint wanted this frame
+    wake_after: Option<Duration>,
+    /// What wants it, in registration order without duplicates
+    reasons: Vec<&'static str>,
+    /// `reasons` of the last finished frame, for the Debug section
+    last_reasons: Vec<&'static str>,
+}
+
+impl Activity {
+    /// Ask for another frame within `after` (zero for the next one), because of `reason`
+    pub fn keep_awake(&mut self, reason: &'static str, after: Duration) {
+        self.wake_after = Some(self.wake_after.map_or(after, |wake| wake.min(after)));
+        if !self.reasons.contains(&reason) {
+            self.reasons.push(reason);
+        }
+    }
+
+    /// Request the earliest repaint asked for this frame, if any, and start over
+    pub fn finish(&mut self, ctx: &egui::Context) {
+        if let Some(after) = self.take_wake_after() {
+            ctx.request_repaint_after(after);
+        }
+    }
+
+    /// Earliest repaint asked for this frame; clears it and keeps the reasons for display
+    fn take_wake_after(&mut self) -> Option<Duration> {
+        std::mem::swap(&mut self.reasons, &mut self.last_reasons);
+        self.reasons.clear();
+        self.wake_after.take()
+    }
+
+    /// What kept the app repainting in the last frame; empty when idle
+    pub fn last_reasons(&self) -> &[&'static str] {
+        &self.last_reasons
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earliest_request_wins_and_idle_frames_request_nothing() {
+        let mut activity = Activity::default();
+        activity.keep_awake("loading", Duration::from_millis(100));
+        activity.keep_awake("toasts", Duration::from_millis(16));
+        activity.keep_awake("loading", Duration::from_millis(100));
+        assert_eq!(activity.take_wake_after(), Some(Duration::from_millis(16)));
+        assert_eq!(activity.last_reasons(), ["loading", "toasts"]);
+
+        assert_eq!(activity.take_wake_after(), None);
+        assert!(activity.last_reasons().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Notes and tags the user attaches to loaded tracks
+//!
+//! Annotations live in the viewer only; the GPX files are never modified.
+//! They are keyed by a hash of the track's content rather than its path, so
+//! they follow a file that is moved or renamed and survive reloading it. The
+//! whole store is one JSON value in the platform storage backend.
+
+use crate::app::storage::{self, StorageBackend, StorageResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Storage key of the serialized [`TrackAnnotations`]
+pub const ANNOTATIONS_KEY: &str = "track_annotations";
+
+/// Notes and tags of one track
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Annotation {
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Annotation {
+    fn is_empty(&self) -> bool {
+        self.notes.is_empty() && self.tags.is_empty()
+    }
+}
+
+/// Annotations of all tracks ever annotated, by [`content_hash`]
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TrackAnnotations {
+    #[serde(default)]
+    by_hash: BTreeMap<String, Annotation>,
+    /// Changed since the last save
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl TrackAnnotations {
+    /// Read the store from `backend`; a missing or unreadable one starts empty
+    pub fn load(backend: &dyn StorageBackend) -> Self {
+        match storage::load_json_backend(backend, ANNOTATIONS_KEY) {
+            Ok(Some(annotations)) => annotations,
+            Ok(None) => Self::default(),
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable track annotations: {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Write the store to `backend` if it changed since it was loaded or last saved
+    pub fn save_if_dirty(&mut self, backend: &dyn StorageBackend) -> StorageResult<()> {
+        if self.dirty {
+            storage::save_json_backend(backend, ANNOTATIONS_KEY, self)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&Annotation> {
+        self.by_hash.get(hash)
+    }
+
+    /// Replace the notes and tags of a track
+    pub fn set(&mut self, hash: &str, annotation: Annotation) {
+        self.update(hash, |existing| *existing = annotation);
+    }
+
+    pub fn set_notes(&mut self, hash: &str, notes: String) {
+        self.update(hash, |annotation| annotation.notes = notes);
+    }
+
+    /// Add a tag (trimmed); blank tags and ones the track already has are ignored
+    pub fn add_tag(&mut self, hash: &str, tag: &str) {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return;
+        }
+        let tag = tag.trim_start_matches('#').to_string();
+        self.update(hash, |annotation| {
+            if !annotation.tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+                annotation.tags.push(tag);
+            }
+        });
+    }
+
+    pub fn remove_tag(&mut self, hash: &str, tag: &str) {
+        self.update(hash, |annotation| annotation.tags.retain(|t| t != tag));
+    }
+
+    /// Edit one annotation, dropping it once it holds nothing
+    fn update(&mut self, hash: &str, edit: impl FnOnce(&mut Annotation)) {
+        let annotation = self.by_hash.entry(hash.to_string()).or_default();
+        edit(annotation);
+        if annotation.is_empty() {
+            self.by_hash.remove(hash);
+        }
+        self.dirty = true;
+    }
+
+    /// Whether a track with this name and hash matches the Tracks filter
+    ///
+    /// Every whitespace-separated term must match (case-insensitively): a
+    /// `#term` only matches a tag exactly, any other term matches part of the
+    /// name, a tag or the notes.
+    pub fn matches(&self, hash: Option<&str>, name: &str, query: &str) -> bool {
+        let annotation = hash.and_then(|hash| self.get(hash));
+        let tags = annotation.map(|a| a.tags.as_slice()).unwrap_or_default();
+        let notes = annotation
+            .map(|a| a.notes.to_lowercase())
+            .unwrap_or_default();
+        let name = name.to_lowercase();
+        query.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            match term.strip_prefix('#') {
+                Some(tag) => tags.iter().any(|t| t.to_lowercase() == tag),
+                None => {
+                    name.contains(&term)
+                        || notes.contains(&term)
+                        || tags.iter().any(|t| t.to_lowercase().contains(&term))
+                }
+            }
+        })
+    }
+}
+
+/// Stable hash of a track's points, used as its annotation key
+///
+/// Only positions, elevations and times count, so renaming a file or its
+/// tracks keeps its notes. FNV-1a rather than `DefaultHasher`, whose output
+/// may change between Rust releases.
+pub fn content_hash(gpx: &gpx::Gpx) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+    for track in &gpx.tracks {
+        for segment in &track.segments {
+            for point in &segment.points {
+                let position = point.point();
+                feed(&position.x().to_le_bytes());
+                feed(&position.y().to_le_bytes());
+                if let Some(elevation) = point.elevation {
+                    feed(&elevation.to_le_bytes());
+                }
+                if let Some(time) = point.time {
+                    feed(
+                        &time::OffsetDateTime::from(time)
+                            .unix_timestamp_nanos()
+                            .to_le_bytes(),
+                    );
+                }
+            }
+            // Segment boundaries count, so splitting a segment changes the hash
+            feed(b"|");
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::MemoryBackend;
+    use large_track_lib::{RouteBuilder, TrackPoint};
+
+    #[test]
+    fn content_hash_ignores_names_but_not_points() {
+        let ride = |points: [(f64, f64); 2]| {
+            let points = points.map(|(lat, lon)| TrackPoint::new(lat, lon));
+            let route = RouteBuilder::new("Ride").add_segment(points).build();
+            route.unwrap().gpx_data().clone()
+        };
+        let original = ride([(45.0, 7.0), (45.1, 7.1)]);
+        let mut renamed = original.clone();
+        renamed.tracks[0].name = Some("Morning ride".to_string());
+        let moved = ride([(45.0, 7.0), (45.2, 7.1)]);
+
+        assert_eq!(content_hash(&original), content_hash(&renamed));
+        assert_ne!(content_hash(&original), content_hash(&moved));
+    }
+
+    #[test]
+    fn filter_combines_name_tag_and_note_matching() {
+        let mut annotations = TrackAnnotations::default();
+        annotations.add_tag("a", "race");
+        annotations.add_tag("a", "2023");
+        annotations.set_notes("a", "Rainy, with Anna".to_string());
+
+        let hash = Some("a");
+        assert!(annotations.matches(hash, "alps.gpx", ""));
+        assert!(annotations.matches(hash, "alps.gpx", "ALPS"));
+        assert!(annotations.matches(hash, "alps.gpx", "#race"));
+        assert!(annotations.matches(hash, "alps.gpx", "anna 2023"));
+        assert!(annotations.matches(hash, "alps.gpx", "#race alps rainy"));
+        // `#` needs the whole tag, plain terms don't
+        assert!(!annotations.matches(hash, "alps.gpx", "#rac"));
+        assert!(annotations.matches(hash, "alps.gpx", "rac"));
+        assert!(!annotations.matches(hash, "alps.gpx", "#race dolomites"));
+        assert!(!annotations.matches(None, "alps.gpx", "#race"));
+        assert!(annotations.matches(None, "alps.gpx", "alp"));
+    }
+
+    #[test]
+    fn tags_are_deduplicated_and_empty_annotations_dropped() {
+        let mut annotations = TrackAnnotations::default();
+        annotations.add_tag("a", " #race ");
+        annotations.add_tag("a", "Race");
+        annotations.add_tag("a", "   ");
+        assert_eq!(annotations.get("a").unwrap().tags, vec!["race"]);
+
+        annotations.remove_tag("a", "race");
+        assert!(annotations.get("a").is_none());
+    }
+
+    #[test]
+    fn annotations_round_trip_through_the_backend() {
+        let backend = MemoryBackend::default();
+        let mut annotations = TrackAnnotations::default();
+        annotations.add_tag("a", "with-anna");
+        annotations.set_notes("b", "Flat tire at km 40".to_string());
+        annotations.save_if_dirty(&backend).unwrap();
+
+        let loaded = TrackAnnotations::load(&backend);
+        assert_eq!(loaded.get("a"), annotations.get("a"));
+        assert_eq!(loaded.get("b").unwrap().notes, "Flat tire at km 40");
+        assert!(!loaded.dirty);
+
+        // Nothing changed, so nothing is written
+        backend.remove(ANNOTATIONS_KEY).unwrap();
+        annotations.save_if_dirty(&backend).unwrap();
+        assert!(backend.get_string(ANNOTATIONS_KEY).unwrap().is_none());
+    }
+}
@@ -0,0 +1,133 @@
+//! Screen reader announcements of loading progress
+//!
+//! The progress label changes every frame during an import, far too often
+//! to be read out. [`LoadAnnouncer`] keeps a separate AccessKit status node,
+//! a polite live region, whose text only changes every
+//! [`ANNOUNCE_INTERVAL_SECS`] and once more when the load finishes.
+
+use egui::accesskit::{Live, Role};
+
+/// Minimum time between two progress announcements
+pub const ANNOUNCE_INTERVAL_SECS: f64 = 5.0;
+
+/// Throttled text of the loading live region
+#[derive(Debug, Default)]
+pub struct LoadAnnouncer {
+    /// Current announcement, empty before the first load
+    text: String,
+    /// When `text` last changed, in egui input time
+    changed_at: Option<f64>,
+    /// Whether a load was running last frame
+    loading: bool,
+    /// Load errors already listed when the load started
+    errors_before: usize,
+}
+
+impl LoadAnnouncer {
+    /// Account for this frame's load state
+    ///
+    /// `done` and `total` count files of the current batch, `errors` is the
+    /// length of the error list.
+    pub fn update(&mut self, time: f64, loading: bool, done: usize, total: usize, errors: usize) {
+        match (self.loading, loading) {
+            (false, true) => {
+                self.errors_before = errors;
+                self.changed_at = None;
+            }
+            (true, false) => {
+                let failed = errors.saturating_sub(self.errors_before);
+                let loaded = if done == 1 { "file" } else { "files" };
+                self.text = match failed {
+                    0 => format!("Finished loading {} {}", done, loaded),
+                    n => format!("Finished loading {} {}, {} failed", done, loaded, n),
+                };
+                self.changed_at = Some(time);
+            }
+            _ => {}
+        }
+        self.loading = loading;
+        if loading
+            && self
+                .changed_at
+                .is_none_or(|at| time - at >= ANNOUNCE_INTERVAL_SECS)
+        {
+            self.text = format!("Loaded {} of {} files", done, total);
+            self.changed_at = Some(time);
+        }
+    }
+
+    /// Add the live region to this frame's accessibility tree
+    pub fn show(&self, ctx: &egui::Context) {
+        if self.text.is_empty() {
+            return;
+        }
+        ctx.accesskit_node_builder(egui::Id::new("load_announcement"), |node| {
+            node.set_role(Role::Status);
+            node.set_live(Live::Polite);
+            node.set_label(self.text.clone());
+        });
+    }
+}
+
+/// Mark a widget as a polite live region, so changes to its text are read out
+pub fn mark_live(response: &egui::Response) {
+    response.ctx.accesskit_node_builder(response.id, |node| {
+        node.set_live(Live::Polite);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Labels of the live regions in the AccessKit tree of one frame
+    fn live_labels(announcer: &LoadAnnouncer) -> Vec<String> {
+        let ctx = egui::Context::default();
+        ctx.enable_accesskit();
+        let output = ctx.run(egui::RawInput::default(), |ctx| announcer.show(ctx));
+        let update = output
+            .platform_output
+            .accesskit_update
+            .expect("AccessKit is enabled");
+        let tree = accesskit_consumer::Tree::new(update, true);
+
+        fn collect(node: accesskit_consumer::Node<'_>, labels: &mut Vec<String>) {
+            if node.live() == Live::Polite
+                && let Some(label) = node.label()
+            {
+                labels.push(label);
+            }
+            for child in node.children() {
+                collect(child, labels);
+            }
+        }
+        let mut labels = Vec::new();
+        collect(tree.state().root(), &mut labels);
+        labels
+    }
+
+    #[test]
+    fn progress_is_announced_throttled_then_once_at_the_end() {
+        let mut announcer = LoadAnnouncer::default();
+        assert!(live_labels(&announcer).is_empty());
+
+        // A previous batch left one error listed
+        announcer.update(0.0, true, 0, 300, 1);
+        assert_eq!(live_labels(&announcer), vec!["Loaded 0 of 300 files"]);
+        // Frames in between don't change the text
+        announcer.update(1.0, true, 40, 300, 1);
+        announcer.update(4.9, true, 110, 300, 2);
+        assert_eq!(live_labels(&announcer), vec!["Loaded 0 of 300 files"]);
+        announcer.update(5.0, true, 120, 300, 2);
+        assert_eq!(live_labels(&announcer), vec!["Loaded 120 of 300 files"]);
+
+        announcer.update(9.0, false, 297, 0, 4);
+        assert_eq!(
+            live_labels(&announcer),
+            vec!["Finished loading 297 files, 3 failed"]
+        );
+        // Announced once: idle frames keep the same text
+        announcer.update(20.0, false, 297, 0, 4);
+        assert_eq!(announcer.text, "Finished loading 297 files, 3 failed");
+    }
+}
@@ -0,0 +1,414 @@
+//! Map backgrounds: tile providers and the tracks-only grid
+//!
+//! Tile sources are created the first time their provider is shown, so a
+//! session that never leaves tracks-only mode never contacts a tile server.
+//! Each source downloads at most `--tile-concurrency` tiles at a time.
+//!
+//! The provider's attribution is drawn over the map, with its logo when the
+//! source has one (the SVGs need the image loaders installed at startup).
+
+use crate::app::state::TilesProvider;
+use egui::{Color32, Stroke, Ui};
+use walkers::{
+    HttpOptions, HttpTiles, MaxParallelDownloads, Plugin, Projector, TileId,
+    sources::{Attribution, OpenStreetMap, TileSource},
+};
+
+/// Tiles downloaded at once per provider unless `--tile-concurrency` says otherwise
+pub const DEFAULT_TILE_CONCURRENCY: usize = 6;
+
+/// Custom OpenTopoMap tile source
+pub struct OpenTopoMap;
+
+impl TileSource for OpenTopoMap {
+    fn tile_url(&self, tile_id: TileId) -> String {
+        format!(
+            "http://tile.opentopomap.org/{}/{}/{}.png",
+            tile_id.zoom, tile_id.x, tile_id.y
+        )
+    }
+
+    fn attribution(&self) -> Attribution {
+        Attribution {
+            text: "© OpenTopoMap (CC-BY-SA)",
+            url: "https://opentopomap.org/",
+            logo_light: Some(egui::include_image!(
+                "../../assets/opentopomap-logo-light.svg"
+            )),
+            logo_dark: Some(egui::include_image!(
+                "../../assets/opentopomap-logo-dark.svg"
+            )),
+        }
+    }
+
+    fn max_zoom(&self) -> u8 {
+        17 // OpenTopoMap has max zoom of 17
+    }
+}
+
+/// Lazily created tile sources, one per provider
+pub struct BaseMaps {
+    egui_ctx: egui::Context,
+    /// Downloads in flight allowed per tile source (at least one)
+    tile_concurrency: usize,
+    osm: Option<HttpTiles>,
+    otm: Option<HttpTiles>,
+}
+
+impl BaseMaps {
+    pub fn new(egui_ctx: egui::Context, tile_concurrency: usize) -> Self {
+        Self {
+            egui_ctx,
+            tile_concurrency: tile_concurrency.max(1),
+            osm: None,
+            otm: None,
+        }
+    }
+
+    /// Download options for a new tile source
+    ///
+    /// walkers gates the downloads with this limit and only takes new tile
+    /// requests while a download slot is free; the rest are asked for again
+    /// on later frames if still in view, so panning doesn't build a backlog.
+    fn http_options(&self) -> HttpOptions {
+        HttpOptions {
+            max_parallel_downloads: MaxParallelDownloads(self.tile_concurrency),
+            ..Default::default()
+        }
+    }
+
+    /// Tile source for a provider, created on first use; `None` in tracks-only mode
+    pub fn tiles(&mut self, provider: TilesProvider) -> Option<&mut HttpTiles> {
+        let options = self.http_options();
+        let ctx = &self.egui_ctx;
+        match provider {
+            TilesProvider::OpenStreetMap => Some(self.osm.get_or_insert_with(|| {
+                HttpTiles::with_options(OpenStreetMap, options, ctx.clone())
+            })),
+            TilesProvider::OpenTopoMap => {
+                Some(self.otm.get_or_insert_with(|| {
+                    HttpTiles::with_options(OpenTopoMap, options, ctx.clone())
+                }))
+            }
+            TilesProvider::None => None,
+        }
+    }
+
+    /// Whether any tile source has been created (and may have made requests)
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn any_created(&self) -> bool {
+        self.osm.is_some() || self.otm.is_some()
+    }
+}
+
+/// URL of a tile the provider's health check requests; `None` in tracks-only mode
+pub fn test_tile_url(provider: TilesProvider) -> Option<String> {
+    let world = TileId {
+        x: 0,
+        y: 0,
+        zoom: 0,
+    };
+    match provider {
+        TilesProvider::OpenStreetMap => Some(OpenStreetMap.tile_url(world)),
+        TilesProvider::OpenTopoMap => Some(OpenTopoMap.tile_url(world)),
+        TilesProvider::None => None,
+    }
+}
+
+/// Attribution the provider's tiles must be shown with; `None` in tracks-only mode
+pub fn attribution(provider: TilesProvider) -> Option<Attribution> {
+    match provider {
+        TilesProvider::OpenStreetMap => Some(OpenStreetMap.attribution()),
+        TilesProvider::OpenTopoMap => Some(OpenTopoMap.attribution()),
+        TilesProvider::None => None,
+    }
+}
+
+/// Height of an attribution logo on the map; logos are drawn square
+const ATTRIBUTION_LOGO_SIZE: f32 = 16.0;
+
+/// Draw an attribution at the bottom center of the map: the logo for the
+/// current theme (or the other one if only that exists), then the text
+pub fn paint_attribution(ui: &Ui, attribution: &Attribution) {
+    let painter = ui.painter();
+    let rect = ui.max_rect();
+    let galley = painter.layout_no_wrap(
+        attribution.text.to_string(),
+        egui::FontId::proportional(10.0),
+        Color32::from_black_alpha(180),
+    );
+    let (preferred, other) = if ui.visuals().dark_mode {
+        (&attribution.logo_dark, &attribution.logo_light)
+    } else {
+        (&attribution.logo_light, &attribution.logo_dark)
+    };
+    let logo = preferred.as_ref().or(other.as_ref());
+
+    let logo_width = if logo.is_some() {
+        ATTRIBUTION_LOGO_SIZE + 4.0
+    } else {
+        0.0
+    };
+    let left = rect.center().x - (logo_width + galley.size().x) / 2.0;
+    let bottom = rect.bottom() - 5.0;
+    if let Some(logo) = logo {
+        let logo_rect = egui::Rect::from_min_size(
+            egui::pos2(left, bottom - ATTRIBUTION_LOGO_SIZE),
+            egui::Vec2::splat(ATTRIBUTION_LOGO_SIZE),
+        );
+        egui::Image::new(logo.clone()).paint_at(ui, logo_rect);
+    }
+    let text_pos = egui::pos2(left + logo_width, bottom - galley.size().y);
+    painter.galley(text_pos, galley, Color32::from_black_alpha(180));
+}
+
+/// Grid spacings in degrees, coarsest first
+const GRID_STEPS_DEGREES: [f64; 16] = [
+    30.0, 10.0, 5.0, 2.0, 1.0, 0.5, 0.2, 0.1, 0.05, 0.02, 0.01, 0.005, 0.002, 0.001, 0.0005, 0.0002,
+];
+
+/// Minimum distance between grid lines on screen
+const MIN_GRID_SPACING_PIXELS: f64 = 80.0;
+
+/// Web Mercator stops at this latitude; the grid doesn't draw beyond it
+const MAX_GRID_LATITUDE: f64 = 85.05;
+
+/// Finest grid spacing (degrees) whose lines stay at least `MIN_GRID_SPACING_PIXELS` apart
+fn grid_step_degrees(pixels_per_degree: f64) -> f64 {
+    GRID_STEPS_DEGREES
+        .iter()
+        .rev()
+        .copied()
+        .find(|step| step * pixels_per_degree >= MIN_GRID_SPACING_PIXELS)
+        .unwrap_or(GRID_STEPS_DEGREES[0])
+}
+
+/// Multiples of `step` covering `min..=max`
+fn grid_lines(min: f64, max: f64, step: f64) -> impl Iterator<Item = f64> {
+    let first = (min / step).floor() as i64;
+    let last = (max / step).ceil() as i64;
+    (first..=last).map(move |k| k as f64 * step)
+}
+
+/// Plugin painting a plain background with a latitude/longitude grid, used when
+/// no tiles are shown. Add it before other plugins so they draw on top.
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let top_left = projector.unproject(rect.min.to_vec2());
+        let bottom_right = projector.unproject(rect.max.to_vec2());
+        let origin = projector.project(walkers::lat_lon(0.0, 0.0));
+        let one_degree = projector.project(walkers::lat_lon(0.0, 1.0));
+        let step = grid_step_degrees((one_degree.x - origin.x).abs() as f64);
+
+        let line_color = ui.visuals().weak_text_color();
+        let stroke_for = |degrees: f64| {
+            // Equator and prime meridian stand out a little
+            let alpha = if degrees == 0.0 { 0.5 } else { 0.2 };
+            Stroke::new(1.0, line_color.gamma_multiply(alpha))
+        };
+
+        let min_lon = top_left.x().min(bottom_right.x());
+        let max_lon = top_left.x().max(bottom_right.x());
+        for lon in grid_lines(min_lon, max_lon, step) {
+            let x = projector.project(walkers::lat_lon(0.0, lon)).x;
+            painter.vline(x, rect.y_range(), stroke_for(lon));
+        }
+
+        let min_lat = top_left.y().min(bottom_right.y()).max(-MAX_GRID_LATITUDE);
+        let max_lat = top_left.y().max(bottom_right.y()).min(MAX_GRID_LATITUDE);
+        for lat in grid_lines(min_lat, max_lat, step) {
+            let y = projector.project(walkers::lat_lon(lat, 0.0)).y;
+            painter.hline(rect.x_range(), y, stroke_for(lat));
+        }
+
+        // Hint that the background is intentionally blank
+        painter.text(
+            rect.left_bottom() + egui::vec2(6.0, -6.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("Grid: {}°", step),
+            egui::FontId::proportional(10.0),
+            Color32::from_gray(128),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use walkers::Tiles;
+
+    #[test]
+    fn attribution_logo_is_painted_when_present() {
+        let ctx = egui::Context::default();
+        let texture = ctx.load_texture(
+            "logo",
+            egui::ColorImage::from_rgba_unmultiplied([2, 2], &[255; 16]),
+            Default::default(),
+        );
+        let logo = egui::ImageSource::Texture(egui::load::SizedTexture::new(
+            texture.id(),
+            egui::vec2(2.0, 2.0),
+        ));
+        // Meshes drawn with the logo's texture in one frame of the attribution
+        let logo_meshes = |logo_light: Option<egui::ImageSource<'static>>| {
+            let attribution = Attribution {
+                text: "© Test tiles",
+                url: "https://example.com/",
+                logo_light,
+                logo_dark: None,
+            };
+            let output = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| paint_attribution(ui, &attribution));
+            });
+            ctx.tessellate(output.shapes, output.pixels_per_point)
+                .into_iter()
+                .filter(|clipped| {
+                    matches!(&clipped.primitive, egui::epaint::Primitive::Mesh(mesh)
+                        if mesh.texture_id == texture.id())
+                })
+                .count()
+        };
+
+        assert_eq!(logo_meshes(None), 0);
+        // Only a light logo: still used in dark mode
+        ctx.set_visuals(egui::Visuals::dark());
+        assert_ne!(logo_meshes(Some(logo)), 0);
+    }
+
+    #[test]
+    fn bundled_logos_only_for_opentopomap() {
+        let otm = attribution(TilesProvider::OpenTopoMap).unwrap();
+        assert!(otm.logo_light.is_some() && otm.logo_dark.is_some());
+        assert!(attribution(TilesProvider::None).is_none());
+    }
+
+    #[test]
+    fn tracks_only_mode_has_no_tile_source() {
+        let mut base_maps = BaseMaps::new(egui::Context::default(), DEFAULT_TILE_CONCURRENCY);
+        assert!(base_maps.tiles(TilesProvider::None).is_none());
+        assert!(!base_maps.any_created());
+    }
+
+    #[test]
+    fn tile_concurrency_reaches_the_download_options() {
+        let base_maps = BaseMaps::new(egui::Context::default(), 2);
+        assert_eq!(base_maps.http_options().max_parallel_downloads.0, 2);
+        // Zero would never download anything
+        let base_maps = BaseMaps::new(egui::Context::default(), 0);
+        assert_eq!(base_maps.http_options().max_parallel_downloads.0, 1);
+    }
+
+    /// Tiles from a local server
+    struct LocalTiles(String);
+
+    impl TileSource for LocalTiles {
+        fn tile_url(&self, tile_id: TileId) -> String {
+            format!(
+                "{}/{}/{}/{}.png",
+                self.0, tile_id.zoom, tile_id.x, tile_id.y
+            )
+        }
+
+        fn attribution(&self) -> Attribution {
+            Attribution {
+                text: "",
+                url: "",
+                logo_light: None,
+                logo_dark: None,
+            }
+        }
+    }
+
+    /// Local tile server taking a while to answer each request (with a 404);
+    /// counts the requests it answered and the most it held open at once
+    fn slow_server() -> (String, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (answered, most_open) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let (answered_out, most_open_out) = (answered.clone(), most_open.clone());
+        let open = Arc::new(AtomicUsize::new(0));
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let (answered, most_open, open) =
+                    (answered.clone(), most_open.clone(), open.clone());
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line == "\r\n" || line.is_empty() {
+                            break;
+                        }
+                    }
+                    most_open.fetch_max(open.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(50));
+                    open.fetch_sub(1, Ordering::SeqCst);
+                    answered.fetch_add(1, Ordering::SeqCst);
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    );
+                });
+            }
+        });
+        (url, answered_out, most_open_out)
+    }
+
+    #[test]
+    fn no_more_tile_requests_in_flight_than_the_concurrency() {
+        let (url, answered, most_open) = slow_server();
+        let base_maps = BaseMaps::new(egui::Context::default(), 2);
+        let mut tiles = HttpTiles::with_options(
+            LocalTiles(url),
+            base_maps.http_options(),
+            egui::Context::default(),
+        );
+        // A whole row of tiles in view, asked for every frame as the map does
+        let row: Vec<TileId> = (0..8).map(|x| TileId { x, y: 0, zoom: 3 }).collect();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while answered.load(Ordering::SeqCst) < row.len() && std::time::Instant::now() < deadline {
+            for tile in &row {
+                let _ = tiles.at(*tile);
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(answered.load(Ordering::SeqCst) >= row.len());
+        let most_open = most_open.load(Ordering::SeqCst);
+        assert!(
+            (1..=2).contains(&most_open),
+            "{} requests at once",
+            most_open
+        );
+    }
+
+    #[test]
+    fn grid_step_keeps_lines_apart() {
+        // Whole world on a small screen: coarsest step
+        assert_eq!(grid_step_degrees(0.5), 30.0);
+        // ~100 px per degree: 1° lines
+        assert_eq!(grid_step_degrees(100.0), 1.0);
+        // Street level
+        assert_eq!(grid_step_degrees(500_000.0), 0.0002);
+
+        let lines: Vec<f64> = grid_lines(-0.3, 0.45, 0.2).collect();
+        assert_eq!(lines.len(), 6);
+        assert!((lines[0] + 0.4).abs() < 1e-9 && (lines[5] - 0.6).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,136 @@
+//! Comparison between two loaded routes
+//!
+//! The user picks a reference route (e.g. a planned one or an earlier run)
+//! and a recorded one; the report says how much of the recorded route stayed
+//! within a corridor around the reference, and how the two differ in length,
+//! overlap and climb. Off-corridor stretches are kept as map lines so they
+//! can be highlighted.
+
+use large_track_lib::{CorridorReport, DataError, RouteCollection, RouteDiff};
+
+/// Corridor half-width used until the user picks another one
+pub const DEFAULT_CORRIDOR_METERS: f64 = 50.0;
+
+/// Selected routes, corridor width and the last result
+#[derive(Debug)]
+pub struct CorridorComparison {
+    /// Route the recorded one is measured against
+    pub reference: Option<usize>,
+    /// Route that is measured
+    pub recorded: Option<usize>,
+    /// Corridor half-width in meters
+    pub corridor_m: f64,
+    /// Result of the last run, until the routes or the collection change
+    pub result: Option<ComparisonResult>,
+}
+
+impl Default for CorridorComparison {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            recorded: None,
+            corridor_m: DEFAULT_CORRIDOR_METERS,
+            result: None,
+        }
+    }
+}
+
+/// A finished comparison
+#[derive(Debug)]
+pub struct ComparisonResult {
+    pub reference: usize,
+    pub recorded: usize,
+    pub report: CorridorReport,
+    /// Recorded minus reference, with the corridor as the overlap buffer
+    pub diff: RouteDiff,
+    /// One line per off-corridor section, including the neighboring inside
+    /// points so the highlight joins the track
+    pub off_corridor_lines: Vec<Vec<walkers::Position>>,
+}
+
+impl CorridorComparison {
+    /// Whether both routes are picked and differ
+    pub fn can_run(&self) -> bool {
+        matches!((self.reference, self.recorded), (Some(a), Some(b)) if a != b)
+    }
+
+    /// Compare the selected routes and keep the result
+    pub fn run(&mut self, collection: &RouteCollection) -> Result<(), DataError> {
+        let (Some(reference), Some(recorded)) = (self.reference, self.recorded) else {
+            return Ok(());
+        };
+        let report = collection.compare_routes(reference, recorded, self.corridor_m)?;
+        let diff = collection.diff_routes(reference, recorded, self.corridor_m)?;
+        let route = collection
+            .get_route(recorded)
+            .ok_or(DataError::RouteNotFound(recorded))?;
+        let off_corridor_lines = report
+            .off_corridor
+            .iter()
+            .map(|section| {
+                let first = section.start_point.saturating_sub(1);
+                (first..=section.end_point + 1)
+                    .filter_map(|point| {
+                        route.get_waypoint(section.track_index, section.segment_index, point)
+                    })
+                    .map(|waypoint| walkers::lat_lon(waypoint.point().y(), waypoint.point().x()))
+                    .collect()
+            })
+            .collect();
+        self.result = Some(ComparisonResult {
+            reference,
+            recorded,
+            report,
+            diff,
+            off_corridor_lines,
+        });
+        Ok(())
+    }
+
+    /// Forget the picked routes and the result, e.g. when route indices shift
+    pub fn clear(&mut self) {
+        self.reference = None;
+        self.recorded = None;
+        self.result = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use large_track_lib::{Config, RouteBuilder, TrackPoint};
+
+    #[test]
+    fn off_corridor_lines_join_the_track() {
+        let mut collection = RouteCollection::new(Config::default());
+        let planned: Vec<TrackPoint> = (0..=10)
+            .map(|i| TrackPoint::new(45.0, 7.0 + i as f64 * 0.005))
+            .collect();
+        let mut recorded = planned.clone();
+        recorded[5].lat += 0.01;
+        for (name, points) in [("Planned", planned), ("Recorded", recorded)] {
+            let route = RouteBuilder::new(name).add_segment(points).build().unwrap();
+            collection.add_built_route(route).unwrap();
+        }
+
+        let mut comparison = CorridorComparison {
+            reference: Some(0),
+            recorded: Some(1),
+            ..Default::default()
+        };
+        assert!(comparison.can_run());
+        comparison.run(&collection).unwrap();
+
+        let result = comparison.result.as_ref().unwrap();
+        assert_eq!(result.report.off_corridor.len(), 1);
+        // The detour point plus one inside point on each side
+        assert_eq!(result.off_corridor_lines.len(), 1);
+        assert_eq!(result.off_corridor_lines[0].len(), 3);
+        // The detour adds length and the detour point overlaps neither way
+        assert!(result.diff.distance_delta_m > 0.0);
+        assert!(result.diff.overlap > 0.5 && result.diff.overlap < 1.0);
+
+        comparison.clear();
+        assert!(!comparison.can_run() && comparison.result.is_none());
+    }
+}
@@ -0,0 +1,587 @@
+//! Formatting of coordinates for readouts and copy actions
+//!
+//! Positions are WGS84 throughout the app; this only changes how they are
+//! written and read back. UTM uses the WGS84 ellipsoid and the usual
+//! transverse Mercator series (accurate to well under a meter within a zone),
+//! including the Norway and Svalbard zone exceptions. MGRS is UTM with the
+//! 100 km square written as two letters (the current "AA" lettering).
+
+/// How coordinates are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordFormat {
+    /// `45.832622, 6.865175`
+    #[default]
+    Decimal,
+    /// `45°49'57.4"N 6°51'54.6"E`
+    Dms,
+    /// `32T 334199 5077667`
+    Utm,
+    /// `32T LR 34199 77667`
+    Mgrs,
+}
+
+impl CoordFormat {
+    pub fn all() -> &'static [Self] {
+        &[Self::Decimal, Self::Dms, Self::Utm, Self::Mgrs]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Decimal => "Decimal degrees",
+            Self::Dms => "Degrees, minutes, seconds",
+            Self::Utm => "UTM",
+            Self::Mgrs => "MGRS",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Dms" => Self::Dms,
+            "Utm" => Self::Utm,
+            "Mgrs" => Self::Mgrs,
+            _ => Self::Decimal,
+        }
+    }
+}
+
+/// Write a WGS84 position in `format`
+///
+/// UTM and MGRS are not defined north of 84°N or south of 80°S; there the
+/// position is written in decimal degrees instead.
+pub fn format_coord(lat: f64, lon: f64, format: CoordFormat) -> String {
+    match format {
+        CoordFormat::Decimal => format!("{:.6}, {:.6}", lat, lon),
+        CoordFormat::Dms => format!(
+            "{} {}",
+            format_dms(lat, ['N', 'S']),
+            format_dms(lon, ['E', 'W'])
+        ),
+        CoordFormat::Utm => match to_utm(lat, lon) {
+            Some(utm) => format!(
+                "{}{} {:.0} {:.0}",
+                utm.zone, utm.band, utm.easting, utm.northing
+            ),
+            None => format_coord(lat, lon, CoordFormat::Decimal),
+        },
+        CoordFormat::Mgrs => match to_utm(lat, lon).and_then(|utm| to_mgrs(&utm)) {
+            Some(mgrs) => mgrs,
+            None => format_coord(lat, lon, CoordFormat::Decimal),
+        },
+    }
+}
+
+/// Read a position written in any [`CoordFormat`], whichever one is displayed
+///
+/// Accepts `lat, lon` in decimal degrees (comma or space separated), DMS
+/// with hemisphere letters, UTM as `zone band easting northing` and MGRS
+/// with or without spaces at any precision. Returns WGS84 `(lat, lon)`.
+pub fn parse_coord(text: &str) -> Option<(f64, f64)> {
+    let text = text.trim();
+    parse_mgrs(text)
+        .or_else(|| parse_utm(text))
+        .or_else(|| parse_dms(text))
+        .or_else(|| parse_decimal(text))
+        .filter(|(lat, lon)| (-90.0..=90.0).contains(lat) && (-180.0..=180.0).contains(lon))
+}
+
+fn parse_decimal(text: &str) -> Option<(f64, f64)> {
+    let mut numbers = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().ok());
+    let (lat, lon) = (numbers.next()??, numbers.next()??);
+    numbers.next().is_none().then_some((lat, lon))
+}
+
+/// Two angles, each as numbers separated by `°`, `'` or `"` and ending in a hemisphere letter
+fn parse_dms(text: &str) -> Option<(f64, f64)> {
+    let upper = text.to_uppercase();
+    let split = upper.find(['N', 'S'])? + 1;
+    let (lat, lon) = upper.split_at(split);
+    let lat = parse_dms_angle(lat, ['N', 'S'])?;
+    let lon = parse_dms_angle(lon.trim_start_matches([',', ' ']), ['E', 'W'])?;
+    Some((lat, lon))
+}
+
+fn parse_dms_angle(text: &str, [positive, negative]: [char; 2]) -> Option<f64> {
+    let text = text.trim();
+    let hemisphere = text.chars().last()?;
+    let sign = match hemisphere {
+        h if h == positive => 1.0,
+        h if h == negative => -1.0,
+        _ => return None,
+    };
+    let parts: Vec<f64> = text[..text.len() - hemisphere.len_utf8()]
+        .split(|c: char| matches!(c, '°' | '\'' | '"' | '′' | '″') || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    if parts.is_empty() || parts.len() > 3 || parts.iter().skip(1).any(|p| !(0.0..60.0).contains(p))
+    {
+        return None;
+    }
+    let degrees = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, divisor)| part / divisor)
+        .sum::<f64>();
+    Some(sign * degrees)
+}
+
+/// Zone number and latitude band at the start of a UTM or MGRS reference, e.g. `32T`
+fn parse_zone_band(text: &str) -> Option<(u8, char, &str)> {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    if !(1..=2).contains(&digits) {
+        return None;
+    }
+    let zone: u8 = text[..digits].parse().ok()?;
+    let band = text[digits..].chars().next()?.to_ascii_uppercase();
+    ((1..=60).contains(&zone) && UTM_BANDS.contains(&(band as u8)))
+        .then(|| (zone, band, &text[digits + 1..]))
+}
+
+fn parse_utm(text: &str) -> Option<(f64, f64)> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let (zone_band, easting, northing) = match parts.as_slice() {
+        [zone_band, easting, northing] => (zone_band.to_string(), easting, northing),
+        [zone, band, easting, northing] => (format!("{}{}", zone, band), easting, northing),
+        _ => return None,
+    };
+    let (zone, band, rest) = parse_zone_band(&zone_band)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    from_utm(&UtmCoord {
+        zone,
+        band,
+        easting: easting.parse().ok()?,
+        northing: northing.parse().ok()?,
+    })
+}
+
+fn parse_mgrs(text: &str) -> Option<(f64, f64)> {
+    let compact: String = text.split_whitespace().collect();
+    let (zone, band, rest) = parse_zone_band(&compact)?;
+    let mut chars = rest.chars();
+    let column = chars.next()?.to_ascii_uppercase();
+    let row = chars.next()?.to_ascii_uppercase();
+    let digits = chars.as_str();
+    if !digits.len().is_multiple_of(2)
+        || digits.len() > 10
+        || !digits.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let precision = digits.len() / 2;
+    let scale = 10f64.powi(5 - precision as i32);
+    let (easting, northing) = digits.split_at(precision);
+    let parse = |digits: &str| -> f64 { digits.parse::<f64>().unwrap_or(0.0) * scale };
+
+    let column_index = mgrs_column_letters(zone).find(column)?;
+    let row_offset = if zone.is_multiple_of(2) { 5 } else { 0 };
+    let row_index = (MGRS_ROW_LETTERS.find(row)? + 20 - row_offset) % 20;
+    let easting = (column_index + 1) as f64 * 100_000.0 + parse(easting);
+    let mut northing = row_index as f64 * 100_000.0 + parse(northing);
+
+    // The row letters repeat every 2000 km; the band says which repetition
+    let band_south = -80.0 + 8.0 * UTM_BANDS.iter().position(|&b| b as char == band)? as f64;
+    let central_meridian = f64::from(zone) * 6.0 - 183.0;
+    let band_min_northing = to_utm(band_south, central_meridian)?.northing - 100_000.0;
+    while northing < band_min_northing {
+        northing += 2_000_000.0;
+    }
+    from_utm(&UtmCoord {
+        zone,
+        band,
+        easting,
+        northing,
+    })
+}
+
+/// One angle as degrees, minutes and tenths of seconds with its hemisphere letter
+fn format_dms(degrees: f64, [positive, negative]: [char; 2]) -> String {
+    let hemisphere = if degrees < 0.0 { negative } else { positive };
+    // Round once, in tenths of a second, so 59.96" carries into the minutes
+    let tenths = (degrees.abs() * 36_000.0).round() as u64;
+    format!(
+        "{}°{:02}'{:02}.{}\"{}",
+        tenths / 36_000,
+        tenths / 600 % 60,
+        tenths / 10 % 60,
+        tenths % 10,
+        hemisphere
+    )
+}
+
+/// A position in the Universal Transverse Mercator grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UtmCoord {
+    /// Longitude zone, 1 to 60
+    pub zone: u8,
+    /// Latitude band letter, C to X
+    pub band: char,
+    /// Meters east, 500 000 on the zone's central meridian
+    pub easting: f64,
+    /// Meters north of the equator (plus 10 000 000 in the southern hemisphere)
+    pub northing: f64,
+}
+
+/// Latitude band letters from 80°S northwards, 8° each (X spans 72°N to 84°N)
+const UTM_BANDS: &[u8; 20] = b"CDEFGHJKLMNPQRSTUVWX";
+
+/// UTM zone of a position, with the Norway and Svalbard exceptions
+fn utm_zone(lat: f64, lon: f64) -> u8 {
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&lat) && (0.0..42.0).contains(&lon) {
+        return match lon {
+            lon if lon < 9.0 => 31,
+            lon if lon < 21.0 => 33,
+            lon if lon < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    (((lon + 180.0) / 6.0).floor() as u8 % 60) + 1
+}
+
+/// Convert a WGS84 position to UTM; `None` outside 80°S to 84°N
+pub fn to_utm(lat: f64, lon: f64) -> Option<UtmCoord> {
+    if !(-80.0..=84.0).contains(&lat) {
+        return None;
+    }
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+    const K0: f64 = 0.9996;
+    let e2 = F * (2.0 - F);
+    let e4 = e2 * e2;
+    let e6 = e4 * e2;
+    let ep2 = e2 / (1.0 - e2);
+
+    let zone = utm_zone(lat, lon);
+    let band_index = (((lat + 80.0) / 8.0).floor() as usize).min(UTM_BANDS.len() - 1);
+    let central_meridian = (f64::from(zone) * 6.0 - 183.0).to_radians();
+    // Longitude relative to the central meridian, wrapped so zone 1 works next to the antimeridian
+    let delta_lon = (lon.to_radians() - central_meridian + std::f64::consts::PI)
+        .rem_euclid(std::f64::consts::TAU)
+        - std::f64::consts::PI;
+
+    let phi = lat.to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let n = A / (1.0 - e2 * sin_phi * sin_phi).sqrt();
+    let t = phi.tan().powi(2);
+    let c = ep2 * cos_phi * cos_phi;
+    let a = cos_phi * delta_lon;
+    // Meridian arc length from the equator
+    let m = A
+        * ((1.0 - e2 / 4.0 - 3.0 * e4 / 64.0 - 5.0 * e6 / 256.0) * phi
+            - (3.0 * e2 / 8.0 + 3.0 * e4 / 32.0 + 45.0 * e6 / 1024.0) * (2.0 * phi).sin()
+            + (15.0 * e4 / 256.0 + 45.0 * e6 / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e6 / 3072.0) * (6.0 * phi).sin());
+
+    let easting = K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * ep2) * a.powi(5) / 120.0)
+        + 500_000.0;
+    let mut northing = K0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * ep2) * a.powi(6) / 720.0));
+    if lat < 0.0 {
+        northing += 10_000_000.0;
+    }
+
+    Some(UtmCoord {
+        zone,
+        band: char::from(UTM_BANDS[band_index]),
+        easting,
+        northing,
+    })
+}
+
+/// Letters of the 100 km rows, repeating every 2000 km northwards
+const MGRS_ROW_LETTERS: &str = "ABCDEFGHJKLMNPQRSTUV";
+
+/// Letters of the 100 km columns, which cycle through three sets by zone
+fn mgrs_column_letters(zone: u8) -> &'static str {
+    match zone % 3 {
+        1 => "ABCDEFGH",
+        2 => "JKLMNPQR",
+        _ => "STUVWXYZ",
+    }
+}
+
+/// Write a UTM position as an MGRS reference at 1 m precision
+///
+/// `None` for eastings outside the 100 km columns a zone can have.
+pub fn to_mgrs(utm: &UtmCoord) -> Option<String> {
+    let column = (utm.easting / 100_000.0).floor() as usize;
+    let column_letter = mgrs_column_letters(utm.zone)
+        .chars()
+        .nth(column.checked_sub(1)?)?;
+    let row_offset = if utm.zone.is_multiple_of(2) { 5 } else { 0 };
+    let row = ((utm.northing / 100_000.0).floor() as usize + row_offset) % 20;
+    let row_letter = MGRS_ROW_LETTERS.chars().nth(row)?;
+    Some(format!(
+        "{}{} {}{} {:05} {:05}",
+        utm.zone,
+        utm.band,
+        column_letter,
+        row_letter,
+        utm.easting.floor() as u64 % 100_000,
+        utm.northing.floor() as u64 % 100_000
+    ))
+}
+
+/// Convert a UTM position back to WGS84 `(lat, lon)`
+///
+/// The band letter only decides the hemisphere (N and up is north). `None`
+/// for zones outside 1 to 60.
+pub fn from_utm(utm: &UtmCoord) -> Option<(f64, f64)> {
+    if !(1..=60).contains(&utm.zone) {
+        return None;
+    }
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+    const K0: f64 = 0.9996;
+    let e2 = F * (2.0 - F);
+    let ep2 = e2 / (1.0 - e2);
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+
+    let x = utm.easting - 500_000.0;
+    let y = if utm.band < 'N' {
+        utm.northing - 10_000_000.0
+    } else {
+        utm.northing
+    };
+    // Footpoint latitude: where the meridian arc reaches the northing
+    let mu = y / K0 / (A * (1.0 - e2 / 4.0 - 3.0 * e2 * e2 / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1 * e1 / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+
+    let (sin_phi1, cos_phi1) = phi1.sin_cos();
+    let n1 = A / (1.0 - e2 * sin_phi1 * sin_phi1).sqrt();
+    let t1 = phi1.tan().powi(2);
+    let c1 = ep2 * cos_phi1 * cos_phi1;
+    let r1 = A * (1.0 - e2) / (1.0 - e2 * sin_phi1 * sin_phi1).powf(1.5);
+    let d = x / (n1 * K0);
+
+    let lat = phi1
+        - (n1 * phi1.tan() / r1)
+            * (d * d / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1 * c1 - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1 * t1 - 252.0 * ep2 - 3.0 * c1 * c1)
+                    * d.powi(6)
+                    / 720.0);
+    let central_meridian = f64::from(utm.zone) * 6.0 - 183.0;
+    let lon = central_meridian
+        + ((d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+            + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1 * c1 + 8.0 * ep2 + 24.0 * t1 * t1)
+                * d.powi(5)
+                / 120.0)
+            / cos_phi1)
+            .to_degrees();
+    let lon = (lon + 180.0).rem_euclid(360.0) - 180.0;
+    Some((lat.to_degrees(), lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_keeps_six_places() {
+        assert_eq!(
+            format_coord(48.8583, 2.2945, CoordFormat::Decimal),
+            "48.858300, 2.294500"
+        );
+    }
+
+    #[test]
+    fn dms_rounds_and_carries() {
+        assert_eq!(
+            format_coord(48.8583, 2.2945, CoordFormat::Dms),
+            "48°51'29.9\"N 2°17'40.2\"E"
+        );
+        assert_eq!(
+            format_coord(-33.856_8, -151.215_3, CoordFormat::Dms),
+            "33°51'24.5\"S 151°12'55.1\"W"
+        );
+        // 9.99999° is 9°59'59.96", which rounds up to a whole 10°
+        assert_eq!(
+            format_coord(9.999_99, 0.0, CoordFormat::Dms),
+            "10°00'00.0\"N 0°00'00.0\"E"
+        );
+    }
+
+    #[test]
+    fn utm_matches_known_points() {
+        // The equator at the prime meridian, 3° west of zone 31's central meridian
+        let origin = to_utm(0.0, 0.0).unwrap();
+        assert_eq!((origin.zone, origin.band), (31, 'N'));
+        assert!((origin.easting - 166_021.443).abs() < 0.01, "{:?}", origin);
+        assert!(origin.northing.abs() < 0.01);
+
+        assert_eq!(
+            format_coord(48.8583, 2.2945, CoordFormat::Utm),
+            "31U 448252 5411944"
+        );
+        let south = to_utm(-33.8568, 151.2153).unwrap();
+        assert_eq!((south.zone, south.band), (56, 'H'));
+        assert!(south.northing > 6_000_000.0 && south.northing < 7_000_000.0);
+    }
+
+    #[test]
+    fn utm_zone_boundaries() {
+        // 6°E starts zone 32: both sides are 3° from their zone's central meridian
+        let west = to_utm(0.0, 5.999_999).unwrap();
+        let east = to_utm(0.0, 6.0).unwrap();
+        assert_eq!((west.zone, east.zone), (31, 32));
+        assert!((west.easting - 833_978.445).abs() < 0.01, "{:?}", west);
+        assert!((east.easting - 166_021.443).abs() < 0.01, "{:?}", east);
+
+        // South-western Norway belongs to the widened zone 32
+        assert_eq!(to_utm(60.0, 5.0).unwrap().zone, 32);
+        assert_eq!(to_utm(55.9, 5.0).unwrap().zone, 31);
+        // Svalbard uses the odd zones only
+        assert_eq!(to_utm(78.0, 15.0).unwrap().zone, 33);
+        // 180°E is the same meridian as 180°W, zone 1
+        assert_eq!(to_utm(0.0, 180.0).unwrap().zone, 1);
+        assert_eq!(to_utm(0.0, -180.0).unwrap().zone, 1);
+    }
+
+    #[test]
+    fn utm_falls_back_to_decimal_near_the_poles() {
+        assert!(to_utm(85.0, 0.0).is_none());
+        assert_eq!(
+            format_coord(-85.0, 10.0, CoordFormat::Utm),
+            format_coord(-85.0, 10.0, CoordFormat::Decimal)
+        );
+        assert_eq!(to_utm(83.9, 0.0).unwrap().band, 'X');
+    }
+
+    #[test]
+    fn mgrs_matches_known_points() {
+        assert_eq!(
+            format_coord(0.0, 0.0, CoordFormat::Mgrs),
+            "31N AA 66021 00000"
+        );
+        assert_eq!(
+            format_coord(48.8583, 2.2945, CoordFormat::Mgrs),
+            "31U DQ 48251 11943"
+        );
+        // Even zones shift the row letters by five
+        assert_eq!(
+            format_coord(45.832622, 6.865175, CoordFormat::Mgrs),
+            "32T LR 34198 77667"
+        );
+        assert_eq!(
+            format_coord(89.0, 0.0, CoordFormat::Mgrs),
+            format_coord(89.0, 0.0, CoordFormat::Decimal)
+        );
+    }
+
+    #[test]
+    fn utm_round_trips_near_zone_edges_and_poles() {
+        for (lat, lon) in [
+            (0.0, 0.0),
+            (45.832622, 6.865175),
+            (-33.8568, 151.2153),
+            // Either side of the 6°E zone edge
+            (10.0, 5.999_999),
+            (10.0, 6.0),
+            // Widened Norway zone and the Svalbard zones
+            (60.0, 3.5),
+            (78.0, 20.9),
+            // Next to the antimeridian and close to the UTM limits
+            (-40.0, 179.9),
+            (-40.0, -179.9),
+            (83.9, 45.0),
+            (-79.9, -70.0),
+        ] {
+            let utm = to_utm(lat, lon).unwrap();
+            let (back_lat, back_lon) = from_utm(&utm).unwrap();
+            assert!(
+                (back_lat - lat).abs() < 1e-6 && (back_lon - lon).abs() < 1e-6,
+                "{:?} came back as {:?} from {:?}",
+                (lat, lon),
+                (back_lat, back_lon),
+                utm
+            );
+        }
+    }
+
+    #[test]
+    fn every_format_parses_back() {
+        let (lat, lon) = (45.832622, 6.865175);
+        for format in CoordFormat::all() {
+            let text = format_coord(lat, lon, *format);
+            let (parsed_lat, parsed_lon) =
+                parse_coord(&text).unwrap_or_else(|| panic!("{} did not parse", text));
+            // DMS keeps tenths of a second, UTM and MGRS whole meters
+            assert!(
+                (parsed_lat - lat).abs() < 2e-5 && (parsed_lon - lon).abs() < 2e-5,
+                "{} parsed as {:?}",
+                text,
+                (parsed_lat, parsed_lon)
+            );
+        }
+
+        // Southern and western hemispheres
+        let sydney = parse_coord("33°51'24.5\"S 151°12'55.1\"E").unwrap();
+        assert!((sydney.0 + 33.856_8).abs() < 1e-4 && (sydney.1 - 151.215_3).abs() < 1e-4);
+        let west = parse_coord("40.7128 -74.006").unwrap();
+        assert_eq!(west, (40.7128, -74.006));
+        let south = parse_coord("56H 334369 6252086").unwrap();
+        assert!(south.0 < -33.0 && south.0 > -34.0, "{:?}", south);
+    }
+
+    #[test]
+    fn mgrs_parses_any_precision_and_spacing() {
+        let full = parse_coord("32TLR3419977667").unwrap();
+        let spaced = parse_coord("32T LR 34199 77667").unwrap();
+        assert_eq!(full, spaced);
+        // 1 km precision lands within a kilometer
+        let coarse = parse_coord("32TLR341776").unwrap();
+        assert!((coarse.0 - full.0).abs() < 0.01 && (coarse.1 - full.1).abs() < 0.015);
+        // Southern hemisphere, where the rows repeat into the false northing
+        let origin = parse_coord("31N AA 66021 00000").unwrap();
+        assert!(
+            origin.0.abs() < 1e-5 && origin.1.abs() < 1e-5,
+            "{:?}",
+            origin
+        );
+        let text = format_coord(-33.8568, 151.2153, CoordFormat::Mgrs);
+        let sydney = parse_coord(&text).unwrap();
+        assert!(
+            (sydney.0 + 33.8568).abs() < 1e-4,
+            "{} parsed as {:?}",
+            text,
+            sydney
+        );
+    }
+
+    #[test]
+    fn nonsense_does_not_parse() {
+        for text in [
+            "",
+            "hello",
+            "91 0",
+            "45.0",
+            "1 2 3",
+            "61T 500000 0",
+            "32TLR123",
+            "45°70'N 6°E",
+        ] {
+            assert_eq!(parse_coord(text), None, "{:?}", text);
+        }
+    }
+}
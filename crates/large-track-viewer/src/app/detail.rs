@@ -0,0 +1,197 @@
+//! Presenting the LOD bias as a 1–10 detail level
+//!
+//! The bias spans six orders of magnitude and "higher = more detail" is hard
+//! to reason about on a logarithmic slider. The Settings tab shows a detail
+//! level instead, where each step doubles the bias and level 5 is the default
+//! bias of 1. The bias itself stays what the index is built with; the raw
+//! value is still reachable under an advanced expander.
+//!
+//! A new bias means rebuilding the index, far too slow to do for every step
+//! of a slider drag. [`BiasDebouncer`] holds the dragged value back until the
+//! slider has been still for [`BIAS_SETTLE_DELAY`]; meanwhile queries scale
+//! their tolerance by [`BiasDebouncer::preview_factor`] so the map follows
+//! the slider right away.
+
+use instant::Instant;
+use std::time::Duration;
+
+/// Lowest detail level
+pub const MIN_DETAIL_LEVEL: u32 = 1;
+
+/// Highest detail level
+pub const MAX_DETAIL_LEVEL: u32 = 10;
+
+/// Detail level of a bias of 1
+const NEUTRAL_DETAIL_LEVEL: u32 = 5;
+
+/// Bias that detail `level` stands for
+pub fn level_to_bias(level: u32) -> f64 {
+    let level = level.clamp(MIN_DETAIL_LEVEL, MAX_DETAIL_LEVEL);
+    2f64.powi(level as i32 - NEUTRAL_DETAIL_LEVEL as i32)
+}
+
+/// Detail level closest to `bias`; biases beyond either end map to that end
+pub fn bias_to_level(bias: f64) -> u32 {
+    if bias.is_nan() || bias <= 0.0 {
+        return MIN_DETAIL_LEVEL;
+    }
+    let level = bias.log2().round() + NEUTRAL_DETAIL_LEVEL as f64;
+    level.clamp(MIN_DETAIL_LEVEL as f64, MAX_DETAIL_LEVEL as f64) as u32
+}
+
+/// How long the bias slider has to be still before the index is rebuilt
+pub const BIAS_SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// Biases closer than this to the applied one aren't worth a rebuild
+const BIAS_CHANGE_THRESHOLD: f64 = 0.01;
+
+/// Collapses a run of bias changes into one rebuild once they stop
+#[derive(Debug)]
+pub struct BiasDebouncer {
+    /// Bias the index is built with
+    applied: f64,
+    /// Latest bias asked for, and when
+    pending: Option<(f64, Instant)>,
+}
+
+impl BiasDebouncer {
+    pub fn new(applied: f64) -> Self {
+        Self {
+            applied,
+            pending: None,
+        }
+    }
+
+    /// Ask for `bias`, restarting the wait
+    pub fn change(&mut self, bias: f64, now: Instant) {
+        if bias != self.target() {
+            self.pending = Some((bias, now));
+        }
+    }
+
+    /// The bias to apply, once the last change is [`BIAS_SETTLE_DELAY`] old
+    ///
+    /// Returns nothing while changes keep coming, and for a bias that ended
+    /// up back where it started.
+    pub fn poll(&mut self, now: Instant) -> Option<f64> {
+        let (bias, changed) = self.pending?;
+        if now.duration_since(changed) < BIAS_SETTLE_DELAY {
+            return None;
+        }
+        self.pending = None;
+        if (bias - self.applied).abs() <= BIAS_CHANGE_THRESHOLD {
+            return None;
+        }
+        self.applied = bias;
+        Some(bias)
+    }
+
+    /// Latest bias asked for, applied or not
+    pub fn target(&self) -> f64 {
+        self.pending.map_or(self.applied, |(bias, _)| bias)
+    }
+
+    /// Whether a change is waiting for the slider to settle
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Time left until a pending change is due
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        let (_, changed) = self.pending?;
+        Some(BIAS_SETTLE_DELAY.saturating_sub(now.duration_since(changed)))
+    }
+
+    /// How much more detail the target bias asks for than the index is built
+    /// with, to scale query tolerances by until it is applied
+    pub fn preview_factor(&self) -> f64 {
+        if self.applied > 0.0 {
+            self.target() / self.applied
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Short name of detail `level`, for the slider
+pub fn level_name(level: u32) -> &'static str {
+    match level {
+        ..=3 => "Low",
+        4..=6 => "Medium",
+        7..=8 => "High",
+        _ => "Very high",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detail_levels_and_biases_map_both_ways() {
+        assert_eq!(level_to_bias(5), 1.0);
+        assert_eq!(level_to_bias(6), 2.0);
+        assert_eq!(level_to_bias(1), 1.0 / 16.0);
+        assert_eq!(level_to_bias(10), 32.0);
+        for level in MIN_DETAIL_LEVEL..=MAX_DETAIL_LEVEL {
+            assert_eq!(bias_to_level(level_to_bias(level)), level);
+        }
+
+        // Raw biases in between snap to the nearest level, extremes to the ends
+        assert_eq!(bias_to_level(1.3), 5);
+        assert_eq!(bias_to_level(1.5), 6);
+        assert_eq!(bias_to_level(0.001), MIN_DETAIL_LEVEL);
+        assert_eq!(bias_to_level(1000.0), MAX_DETAIL_LEVEL);
+        assert_eq!(bias_to_level(0.0), MIN_DETAIL_LEVEL);
+        assert_eq!(level_to_bias(0), level_to_bias(MIN_DETAIL_LEVEL));
+
+        assert_eq!(level_name(1), "Low");
+        assert_eq!(level_name(5), "Medium");
+        assert_eq!(level_name(10), "Very high");
+    }
+
+    #[test]
+    fn a_slider_drag_collapses_into_one_rebuild() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = BiasDebouncer::new(1.0);
+        assert_eq!(debouncer.poll(at(0)), None);
+
+        // Changes every 50 ms: nothing is applied while they keep coming
+        for (step, ms) in (0..10).map(|step| (step, step * 50)) {
+            debouncer.change(1.0 + step as f64 * 0.5, at(ms));
+            assert_eq!(debouncer.poll(at(ms)), None);
+        }
+        assert!(debouncer.is_pending());
+        assert_eq!(debouncer.target(), 5.5);
+        // Queries already follow the slider
+        assert_eq!(debouncer.preview_factor(), 5.5);
+        assert_eq!(
+            debouncer.remaining(at(550)),
+            Some(Duration::from_millis(200))
+        );
+
+        // Once it has been still long enough, the last value is applied once
+        assert_eq!(debouncer.poll(at(749)), None);
+        assert_eq!(debouncer.poll(at(750)), Some(5.5));
+        assert_eq!(debouncer.poll(at(2000)), None);
+        assert!(!debouncer.is_pending());
+        assert_eq!(debouncer.preview_factor(), 1.0);
+    }
+
+    #[test]
+    fn a_drag_back_to_the_applied_bias_rebuilds_nothing() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = BiasDebouncer::new(2.0);
+        debouncer.change(4.0, at(0));
+        debouncer.change(2.005, at(100));
+        assert_eq!(debouncer.poll(at(1000)), None);
+        assert!(!debouncer.is_pending());
+        assert_eq!(debouncer.target(), 2.0);
+
+        // Setting the applied value again starts no wait at all
+        debouncer.change(2.0, at(1100));
+        assert!(!debouncer.is_pending());
+    }
+}
@@ -0,0 +1,102 @@
+//! Faint rings around the map center at round distances, for orientation
+//!
+//! Ring spacing comes from the map scale at the center latitude, so rings
+//! are exact there and stretch slightly toward the poles across the view,
+//! as everything does in Web Mercator. Distances are metric.
+
+use egui::{Color32, Stroke, Ui};
+use large_track_lib::utils::meters_per_pixel;
+
+/// Rings are at least this far apart on screen, so labels never crowd
+const MIN_RING_SPACING_POINTS: f64 = 90.0;
+
+/// Smallest round distance (1, 2 or 5 times a power of ten meters, at least
+/// 1 m) spanning `min_points` or more at `meters_per_point`
+pub fn ring_step(meters_per_point: f64, min_points: f64) -> f64 {
+    let wanted = (meters_per_point * min_points).max(1.0);
+    let mut magnitude = 10f64.powf(wanted.log10().floor());
+    loop {
+        for factor in [1.0, 2.0, 5.0] {
+            let step = factor * magnitude;
+            // Tolerate rounding in `log10` for exact powers of ten
+            if step >= wanted * (1.0 - 1e-9) {
+                return step;
+            }
+        }
+        magnitude *= 10.0;
+    }
+}
+
+/// A ring's label, in meters below a kilometer and kilometers above
+pub fn format_distance(meters: f64) -> String {
+    if meters < 1000.0 {
+        format!("{} m", meters.round())
+    } else {
+        let km = meters / 1000.0;
+        if km.fract().abs() < 1e-9 {
+            format!("{} km", km)
+        } else {
+            format!("{:.1} km", km)
+        }
+    }
+}
+
+/// Draw the rings over the map panel, centered on its middle
+pub fn draw(ui: &Ui, center_lat: f64, zoom: f64) {
+    let rect = ui.max_rect();
+    let center = rect.center();
+    let meters_per_point = meters_per_pixel(center_lat, zoom);
+    if !meters_per_point.is_finite() || meters_per_point <= 0.0 {
+        return;
+    }
+    let step = ring_step(meters_per_point, MIN_RING_SPACING_POINTS);
+    let step_points = (step / meters_per_point) as f32;
+    // Up to the farthest corner
+    let reach = rect.size().length() / 2.0;
+
+    let painter = ui.painter();
+    let line = Stroke::new(1.0, Color32::from_black_alpha(70));
+    let halo = Stroke::new(3.0, Color32::from_white_alpha(60));
+    let font = egui::FontId::proportional(11.0);
+    let mut radius = step_points;
+    let mut distance = step;
+    while radius <= reach {
+        painter.circle_stroke(center, radius, halo);
+        painter.circle_stroke(center, radius, line);
+
+        // Labeled where the ring crosses the vertical through the center, on a pill
+        let galley =
+            painter.layout_no_wrap(format_distance(distance), font.clone(), Color32::BLACK);
+        let label = egui::Rect::from_center_size(center - egui::vec2(0.0, radius), galley.size())
+            .expand2(egui::vec2(4.0, 1.0));
+        if rect.contains_rect(label) {
+            painter.rect_filled(label, 4.0, Color32::from_white_alpha(190));
+            painter.galley(label.min + egui::vec2(4.0, 1.0), galley, Color32::BLACK);
+        }
+
+        radius += step_points;
+        distance += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_step_picks_round_distances_for_the_scale() {
+        // 1 m per point: 90 points need 90 m
+        assert_eq!(ring_step(1.0, 90.0), 100.0);
+        assert_eq!(ring_step(1.0, 100.0), 100.0);
+        assert_eq!(ring_step(1.2, 100.0), 200.0);
+        assert_eq!(ring_step(3.0, 100.0), 500.0);
+        assert_eq!(ring_step(6.0, 100.0), 1000.0);
+        assert_eq!(ring_step(2500.0, 90.0), 500_000.0);
+        // Never below a meter when zoomed all the way in
+        assert_eq!(ring_step(0.001, 90.0), 1.0);
+
+        assert_eq!(format_distance(500.0), "500 m");
+        assert_eq!(format_distance(2000.0), "2 km");
+        assert_eq!(format_distance(2500.0), "2.5 km");
+    }
+}
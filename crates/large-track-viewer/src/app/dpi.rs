@@ -0,0 +1,64 @@
+//! Noticing when the window moves to a screen with another scale factor
+//!
+//! Dragging the window from a 1× to a 2× monitor changes egui's
+//! `pixels_per_point` between two frames without any input event the app
+//! would otherwise react to. [`DpiWatcher`] compares it frame to frame so
+//! whatever was derived from the old scale can be dropped.
+
+/// Relative change in scale factor below which it counts as unchanged
+///
+/// Fractional scales go through `f32` conversions on the way from the
+/// windowing system; they jitter in the last bits without the screen changing.
+const SCALE_EPSILON: f32 = 1e-3;
+
+/// Frame-to-frame scale factor tracker
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DpiWatcher {
+    /// Scale factor seen last frame, `None` before the first frame
+    pixels_per_point: Option<f32>,
+}
+
+impl DpiWatcher {
+    /// Account for this frame's `pixels_per_point`
+    ///
+    /// Returns the ratio of the new scale factor to the previous one when it
+    /// changed (2.0 going from a 1× to a 2× screen), `None` otherwise and on
+    /// the first frame.
+    pub fn observe(&mut self, pixels_per_point: f32) -> Option<f32> {
+        if !pixels_per_point.is_finite() || pixels_per_point <= 0.0 {
+            return None;
+        }
+        let previous = self.pixels_per_point.replace(pixels_per_point)?;
+        let ratio = pixels_per_point / previous;
+        ((ratio - 1.0).abs() > SCALE_EPSILON).then_some(ratio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_between_screens_reports_the_ratio() {
+        let mut watcher = DpiWatcher::default();
+        // The first frame has nothing to compare against
+        assert_eq!(watcher.observe(1.0), None);
+        assert_eq!(watcher.observe(1.0), None);
+
+        assert_eq!(watcher.observe(2.0), Some(2.0));
+        assert_eq!(watcher.observe(2.0), None);
+        assert_eq!(watcher.observe(1.5), Some(0.75));
+    }
+
+    #[test]
+    fn rounding_noise_and_bogus_scales_are_ignored() {
+        let mut watcher = DpiWatcher::default();
+        watcher.observe(1.25);
+        let jittered = 1.25 + f32::EPSILON;
+        assert_eq!(watcher.observe(jittered), None);
+        assert_eq!(watcher.observe(0.0), None);
+        assert_eq!(watcher.observe(f32::NAN), None);
+        // Compared against the last valid scale
+        assert_eq!(watcher.observe(2.5), Some(2.5 / jittered));
+    }
+}
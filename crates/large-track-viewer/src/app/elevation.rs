@@ -0,0 +1,357 @@
+//! Filling in missing elevations from a web service
+//!
+//! Only used with `--download-elevation`, since it sends track coordinates to
+//! a third party. Every n-th point lacking elevation (plus the last one of
+//! each segment) is looked up on an Open-Elevation compatible endpoint; the
+//! points in between are interpolated from their looked-up neighbors. Lookups
+//! are batched, spaced out to respect the service's rate limits, and cached
+//! per ~11 m cell so reloading a file doesn't query again.
+//!
+//! Native only: browsers block most elevation services through CORS anyway.
+
+use eframe_entrypoints::async_runtime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Look up every n-th point lacking elevation
+const DEFAULT_SAMPLE_EVERY: usize = 10;
+
+/// Locations sent per request
+const BATCH_SIZE: usize = 100;
+
+/// Minimum time between two requests to the service
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Coordinates are cached in cells of 1/10000 of a degree (about 11 m)
+const CACHE_CELLS_PER_DEGREE: f64 = 10_000.0;
+
+#[derive(Error, Debug)]
+pub enum ElevationError {
+    #[error("elevation request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("unexpected elevation response: {0}")]
+    Response(String),
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+struct Location {
+    latitude: f64,
+    longitude: f64,
+}
+
+#[derive(Serialize)]
+struct LookupRequest<'a> {
+    locations: &'a [Location],
+}
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    results: Vec<LookupResult>,
+}
+
+#[derive(Deserialize)]
+struct LookupResult {
+    elevation: f64,
+}
+
+type CacheKey = (i64, i64);
+
+fn cache_key(location: Location) -> CacheKey {
+    (
+        (location.latitude * CACHE_CELLS_PER_DEGREE).round() as i64,
+        (location.longitude * CACHE_CELLS_PER_DEGREE).round() as i64,
+    )
+}
+
+/// Client for one elevation endpoint, shared by all load workers
+pub struct ElevationService {
+    endpoint: String,
+    client: reqwest::Client,
+    sample_every: usize,
+    min_interval: Duration,
+    cache: std::sync::Mutex<HashMap<CacheKey, f64>>,
+    /// Held while a request is in flight, so workers take turns; remembers when the last one was sent
+    last_request: async_runtime::Mutex<Option<instant::Instant>>,
+}
+
+impl ElevationService {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            client: reqwest::Client::new(),
+            sample_every: DEFAULT_SAMPLE_EVERY,
+            min_interval: MIN_REQUEST_INTERVAL,
+            cache: Default::default(),
+            last_request: Default::default(),
+        }
+    }
+
+    /// Fill in the elevation of points that have none; returns how many points were filled
+    ///
+    /// Points that already have an elevation are left alone, so a file with
+    /// complete elevation data never causes a request.
+    pub async fn fill_missing(&self, gpx: &mut gpx::Gpx) -> Result<usize, ElevationError> {
+        #[cfg(feature = "profiling")]
+        profiling::scope!("elevation::fill_missing");
+
+        let sampled: Vec<Vec<Vec<usize>>> = gpx
+            .tracks
+            .iter()
+            .map(|track| {
+                track
+                    .segments
+                    .iter()
+                    .map(|segment| {
+                        let missing: Vec<usize> = (0..segment.points.len())
+                            .filter(|&i| !has_elevation(&segment.points[i]))
+                            .collect();
+                        sample_indices(&missing, self.sample_every)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut wanted: Vec<Location> = Vec::new();
+        {
+            let cache = self.cache.lock().unwrap();
+            let mut queued = std::collections::HashSet::new();
+            for (t, track) in gpx.tracks.iter().enumerate() {
+                for (s, segment) in track.segments.iter().enumerate() {
+                    for &i in &sampled[t][s] {
+                        let location = location_of(&segment.points[i]);
+                        let key = cache_key(location);
+                        if !cache.contains_key(&key) && queued.insert(key) {
+                            wanted.push(location);
+                        }
+                    }
+                }
+            }
+        }
+        for batch in wanted.chunks(BATCH_SIZE) {
+            let elevations = self.lookup(batch).await?;
+            let mut cache = self.cache.lock().unwrap();
+            for (location, elevation) in batch.iter().zip(elevations) {
+                cache.insert(cache_key(*location), elevation);
+            }
+        }
+
+        let cache = self.cache.lock().unwrap();
+        let mut filled = 0;
+        for (t, track) in gpx.tracks.iter_mut().enumerate() {
+            for (s, segment) in track.segments.iter_mut().enumerate() {
+                if sampled[t][s].is_empty() {
+                    continue;
+                }
+                for &i in &sampled[t][s] {
+                    let key = cache_key(location_of(&segment.points[i]));
+                    segment.points[i].elevation = cache.get(&key).copied();
+                }
+                filled += sampled[t][s].len() + interpolate_missing(&mut segment.points);
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Ask the service for the elevations of `locations`, waiting for the rate limit first
+    async fn lookup(&self, locations: &[Location]) -> Result<Vec<f64>, ElevationError> {
+        let mut last_request = self.last_request.lock().await;
+        if let Some(sent_at) = *last_request {
+            let wait = self.min_interval.saturating_sub(sent_at.elapsed());
+            if !wait.is_zero() {
+                async_runtime::sleep(wait).await;
+            }
+        }
+        *last_request = Some(instant::Instant::now());
+
+        let body = serde_json::to_string(&LookupRequest { locations })
+            .map_err(|e| ElevationError::Response(e.to_string()))?;
+        let text = self
+            .client
+            .post(&self.endpoint)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: LookupResponse =
+            serde_json::from_str(&text).map_err(|e| ElevationError::Response(e.to_string()))?;
+        if response.results.len() != locations.len() {
+            return Err(ElevationError::Response(format!(
+                "asked for {} elevations, got {}",
+                locations.len(),
+                response.results.len()
+            )));
+        }
+        Ok(response.results.into_iter().map(|r| r.elevation).collect())
+    }
+}
+
+fn has_elevation(waypoint: &gpx::Waypoint) -> bool {
+    waypoint.elevation.is_some_and(f64::is_finite)
+}
+
+fn location_of(waypoint: &gpx::Waypoint) -> Location {
+    let point = waypoint.point();
+    Location {
+        latitude: point.y(),
+        longitude: point.x(),
+    }
+}
+
+/// Every `every`-th of the `missing` indices, plus the last one
+fn sample_indices(missing: &[usize], every: usize) -> Vec<usize> {
+    let mut sampled: Vec<usize> = missing.iter().copied().step_by(every.max(1)).collect();
+    if let Some(&last) = missing.last()
+        && sampled.last() != Some(&last)
+    {
+        sampled.push(last);
+    }
+    sampled
+}
+
+/// Give points without elevation one interpolated (by index) from their nearest neighbors with one
+///
+/// Points before the first or after the last known elevation copy it.
+/// Returns how many points were given an elevation.
+fn interpolate_missing(points: &mut [gpx::Waypoint]) -> usize {
+    let known: Vec<(usize, f64)> = points
+        .iter()
+        .enumerate()
+        .filter_map(|(i, p)| p.elevation.filter(|e| e.is_finite()).map(|e| (i, e)))
+        .collect();
+    if known.is_empty() {
+        return 0;
+    }
+    let mut filled = 0;
+    for (i, point) in points.iter_mut().enumerate() {
+        if has_elevation(point) {
+            continue;
+        }
+        let next = known.partition_point(|&(k, _)| k < i);
+        let elevation = match (next.checked_sub(1).map(|p| known[p]), known.get(next)) {
+            (Some((a, ea)), Some(&(b, eb))) => ea + (eb - ea) * (i - a) as f64 / (b - a) as f64,
+            (Some((_, e)), None) | (None, Some(&(_, e))) => e,
+            (None, None) => unreachable!("known is not empty"),
+        };
+        point.elevation = Some(elevation);
+        filled += 1;
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use large_track_lib::{RouteBuilder, TrackPoint};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Local stand-in for the elevation service: answers every lookup with
+    /// `latitude * 1000` and counts the requests it received
+    fn mock_endpoint() -> (String, Arc<AtomicUsize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/v1/lookup", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let results: Vec<serde_json::Value> = request["locations"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|l| serde_json::json!({ "elevation": l["latitude"].as_f64().unwrap() * 1000.0 }))
+                    .collect();
+                let reply = serde_json::json!({ "results": results }).to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn flat_gpx(points: usize) -> gpx::Gpx {
+        let route = RouteBuilder::from_gpx(gpx::Gpx::default())
+            .add_segment((0..points).map(|i| TrackPoint::new(45.0 + i as f64 * 0.001, 7.0)))
+            .build()
+            .unwrap();
+        route.gpx_data().clone()
+    }
+
+    #[test]
+    fn sampled_points_gain_elevation_from_the_service() {
+        let (url, requests) = mock_endpoint();
+        let mut service = ElevationService::new(url);
+        service.min_interval = Duration::ZERO;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut gpx = flat_gpx(25);
+        gpx.tracks[0].segments[0].points[3].elevation = Some(1.0);
+        let filled = runtime.block_on(service.fill_missing(&mut gpx)).unwrap();
+        assert_eq!(filled, 24);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let points = &gpx.tracks[0].segments[0].points;
+        // Sampled: every 10th point lacking elevation (0, 11, 21) and the last one
+        for i in [0, 11, 21, 24] {
+            let expected = (45.0 + i as f64 * 0.001) * 1000.0;
+            assert!((points[i].elevation.unwrap() - expected).abs() < 1e-6);
+        }
+        // The recorded elevation is kept, the others are interpolated
+        assert_eq!(points[3].elevation, Some(1.0));
+        assert!(points.iter().all(|p| p.elevation.is_some()));
+
+        // The same coordinates again come from the cache
+        let mut again = flat_gpx(25);
+        again.tracks[0].segments[0].points[3].elevation = Some(1.0);
+        runtime.block_on(service.fill_missing(&mut again)).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn complete_files_cause_no_requests() {
+        let service = ElevationService::new("http://127.0.0.1:9/unreachable");
+        let mut gpx = flat_gpx(5);
+        for point in &mut gpx.tracks[0].segments[0].points {
+            point.elevation = Some(100.0);
+        }
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        assert_eq!(runtime.block_on(service.fill_missing(&mut gpx)).unwrap(), 0);
+    }
+}
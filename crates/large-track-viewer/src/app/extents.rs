@@ -0,0 +1,201 @@
+//! Outlines of where each loaded route lies
+//!
+//! With "Show file extents" on, every route's bounding box is drawn as a thin
+//! rectangle labeled with the route's name, which makes an unfamiliar file
+//! easy to find even when its track is too small to see. Clicking inside a
+//! rectangle (and off any track) selects its route; where rectangles
+//! overlap, clicking again picks the next one.
+
+use crate::app::render::route_color;
+use egui::{Color32, Rect, Stroke};
+use large_track_lib::RouteCollection;
+use large_track_lib::utils::mercator_to_wgs84;
+use walkers::{Plugin, Position, Projector};
+
+/// Bounding box of one route, in WGS84
+#[derive(Clone, Debug)]
+pub struct RouteExtent {
+    pub route_index: usize,
+    pub name: String,
+    /// South-west corner
+    pub min: Position,
+    /// North-east corner
+    pub max: Position,
+}
+
+impl RouteExtent {
+    fn contains(&self, position: Position) -> bool {
+        (self.min.x()..=self.max.x()).contains(&position.x())
+            && (self.min.y()..=self.max.y()).contains(&position.y())
+    }
+}
+
+/// Extent of every route in `collection`, in route order
+pub fn route_extents(collection: &RouteCollection) -> Vec<RouteExtent> {
+    collection
+        .routes()
+        .iter()
+        .enumerate()
+        .map(|(route_index, route)| {
+            let bbox = route.bounding_box();
+            let (min_lat, min_lon) = mercator_to_wgs84(bbox.min().x, bbox.min().y);
+            let (max_lat, max_lon) = mercator_to_wgs84(bbox.max().x, bbox.max().y);
+            RouteExtent {
+                route_index,
+                name: route
+                    .name()
+                    .map_or_else(|| format!("Route {}", route_index + 1), str::to_string),
+                min: walkers::lat_lon(min_lat, min_lon),
+                max: walkers::lat_lon(max_lat, max_lon),
+            }
+        })
+        .collect()
+}
+
+/// Route to select for a click at `position`: the only extent containing it,
+/// or among overlapping ones the next after `last_pick`
+pub fn pick(
+    extents: &[RouteExtent],
+    position: Position,
+    last_pick: Option<usize>,
+) -> Option<usize> {
+    let hits: Vec<usize> = extents
+        .iter()
+        .filter(|extent| extent.contains(position))
+        .map(|extent| extent.route_index)
+        .collect();
+    let next = last_pick
+        .and_then(|last| hits.iter().position(|&hit| hit == last))
+        .map_or(0, |last| (last + 1) % hits.len().max(1));
+    hits.get(next).copied()
+}
+
+/// Which labels to draw, in order, so that none covers one drawn before it
+fn place_labels(rects: &[Rect]) -> Vec<bool> {
+    let mut placed: Vec<Rect> = Vec::new();
+    rects
+        .iter()
+        .map(|rect| {
+            let free = !placed.iter().any(|other| other.intersects(*rect));
+            if free {
+                placed.push(*rect);
+            }
+            free
+        })
+        .collect()
+}
+
+/// Plugin drawing the route extents, above the tracks and below the map overlays
+pub struct ExtentsPlugin {
+    extents: Vec<RouteExtent>,
+    selected: Option<usize>,
+}
+
+impl ExtentsPlugin {
+    /// Draw `extents`, the one of the `selected` route stronger
+    pub fn new(extents: Vec<RouteExtent>, selected: Option<usize>) -> Self {
+        Self { extents, selected }
+    }
+}
+
+impl Plugin for ExtentsPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        let project = |position: Position| {
+            let v = projector.project(position);
+            egui::pos2(v.x, v.y)
+        };
+        let mut labels = Vec::new();
+        for extent in &self.extents {
+            let rect = Rect::from_two_pos(project(extent.min), project(extent.max));
+            if !rect.intersects(response.rect) {
+                continue;
+            }
+            let selected = self.selected == Some(extent.route_index);
+            let color = route_color(extent.route_index);
+            let width = if selected { 2.0 } else { 1.0 };
+            painter.rect_stroke(
+                rect,
+                0.0,
+                Stroke::new(width, color),
+                egui::StrokeKind::Middle,
+            );
+            let galley = painter.layout_no_wrap(
+                extent.name.clone(),
+                egui::FontId::proportional(11.0),
+                Color32::WHITE,
+            );
+            // Top left inside the box, kept on screen when the box is larger than the view
+            let corner = rect.intersect(response.rect).left_top() + egui::vec2(3.0, 3.0);
+            let label = Rect::from_min_size(corner, galley.size()).expand(2.0);
+            // The selected route's label wins any collision
+            labels.push((selected, label, galley, color));
+        }
+        labels.sort_by_key(|(selected, ..)| !*selected);
+        let rects: Vec<Rect> = labels.iter().map(|(_, rect, ..)| *rect).collect();
+        for ((_, rect, galley, color), shown) in labels.into_iter().zip(place_labels(&rects)) {
+            if !shown {
+                continue;
+            }
+            painter.rect_filled(rect, 2.0, color.gamma_multiply(0.8));
+            painter.galley(rect.shrink(2.0).min, galley, Color32::WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(route_index: usize, min: (f64, f64), max: (f64, f64)) -> RouteExtent {
+        RouteExtent {
+            route_index,
+            name: format!("Route {}", route_index + 1),
+            min: walkers::lat_lon(min.0, min.1),
+            max: walkers::lat_lon(max.0, max.1),
+        }
+    }
+
+    #[test]
+    fn clicks_pick_the_extent_under_them_and_cycle_through_overlaps() {
+        let extents = [
+            extent(0, (45.0, 6.0), (46.0, 7.0)),
+            extent(1, (45.5, 6.5), (46.5, 7.5)),
+            extent(2, (10.0, 10.0), (11.0, 11.0)),
+        ];
+        // Only in the first one
+        assert_eq!(pick(&extents, walkers::lat_lon(45.2, 6.2), None), Some(0));
+        assert_eq!(
+            pick(&extents, walkers::lat_lon(45.2, 6.2), Some(0)),
+            Some(0)
+        );
+        // Where the first two overlap, each click picks the next
+        let overlap = walkers::lat_lon(45.7, 6.7);
+        assert_eq!(pick(&extents, overlap, None), Some(0));
+        assert_eq!(pick(&extents, overlap, Some(0)), Some(1));
+        assert_eq!(pick(&extents, overlap, Some(1)), Some(0));
+        // A pick elsewhere starts over
+        assert_eq!(pick(&extents, overlap, Some(2)), Some(0));
+        assert_eq!(pick(&extents, walkers::lat_lon(0.0, 0.0), Some(0)), None);
+    }
+
+    #[test]
+    fn overlapping_labels_are_skipped() {
+        let label = |x: f32, y: f32| Rect::from_min_size(egui::pos2(x, y), egui::vec2(40.0, 12.0));
+        assert_eq!(
+            place_labels(&[
+                label(0.0, 0.0),
+                label(20.0, 5.0),
+                label(0.0, 20.0),
+                label(30.0, 25.0)
+            ]),
+            [true, false, true, false]
+        );
+    }
+}
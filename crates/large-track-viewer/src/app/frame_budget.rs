@@ -0,0 +1,133 @@
+//! Lowering track detail while frames run over their time budget
+//!
+//! Slow hardware, or a huge dataset in view, can push frame times well past
+//! the display's refresh. [`FrameBudget`] watches the CPU time of recent
+//! frames and queries tracks a few LOD levels coarser while they are too
+//! slow, stepping back to full detail once frames are comfortably fast.
+
+/// Budget used until the user picks another one, in milliseconds (0 = off)
+pub const DEFAULT_FRAME_BUDGET_MS: f32 = 16.0;
+
+/// Most LOD levels detail is lowered by; each level doubles the tolerance
+pub const MAX_LOD_REDUCTION: u32 = 3;
+
+/// A frame counts as slow above this multiple of the budget
+const SLOW_MARGIN: f64 = 1.2;
+/// A frame counts as fast below this multiple of the budget
+const FAST_MARGIN: f64 = 0.6;
+/// Consecutive slow frames before detail drops one level
+const SLOW_FRAMES_TO_LOWER: u32 = 3;
+/// Consecutive fast frames before detail rises one level
+const FAST_FRAMES_TO_RAISE: u32 = 30;
+
+/// Adaptive LOD controller fed with frame times
+///
+/// Frames between the fast and slow margins reset both streaks, and raising
+/// detail takes ten times as many frames as lowering it, so a frame time
+/// hovering around the budget doesn't flip detail back and forth.
+#[derive(Clone, Debug, Default)]
+pub struct FrameBudget {
+    lod_reduction: u32,
+    slow_frames: u32,
+    fast_frames: u32,
+}
+
+impl FrameBudget {
+    /// Account for one frame that took `frame_ms`, against a budget of `budget_ms`
+    ///
+    /// A budget of zero (or less) turns the controller off and restores full
+    /// detail. Returns whether the reduction changed.
+    pub fn record(&mut self, frame_ms: f64, budget_ms: f64) -> bool {
+        let before = self.lod_reduction;
+        if budget_ms <= 0.0 {
+            *self = Self::default();
+        } else if frame_ms > budget_ms * SLOW_MARGIN {
+            self.fast_frames = 0;
+            self.slow_frames += 1;
+            if self.slow_frames >= SLOW_FRAMES_TO_LOWER {
+                self.slow_frames = 0;
+                self.lod_reduction = (self.lod_reduction + 1).min(MAX_LOD_REDUCTION);
+            }
+        } else if frame_ms < budget_ms * FAST_MARGIN {
+            self.slow_frames = 0;
+            self.fast_frames += 1;
+            if self.fast_frames >= FAST_FRAMES_TO_RAISE {
+                self.fast_frames = 0;
+                self.lod_reduction = self.lod_reduction.saturating_sub(1);
+            }
+        } else {
+            self.slow_frames = 0;
+            self.fast_frames = 0;
+        }
+        self.lod_reduction != before
+    }
+
+    /// LOD levels to query below the viewport's own, 0 at full detail
+    pub fn lod_reduction(&self) -> u32 {
+        self.lod_reduction
+    }
+
+    /// Fraction of full detail being drawn: the tolerance is divided by this
+    pub fn detail_factor(&self) -> f64 {
+        0.5_f64.powi(self.lod_reduction as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(budget: &mut FrameBudget, frame_ms: f64, frames: usize) {
+        for _ in 0..frames {
+            budget.record(frame_ms, 16.0);
+        }
+    }
+
+    #[test]
+    fn slow_frames_lower_detail_and_fast_frames_restore_it() {
+        let mut budget = FrameBudget::default();
+        assert_eq!(budget.detail_factor(), 1.0);
+
+        // Two slow frames are a hiccup, the third lowers detail
+        feed(&mut budget, 40.0, 2);
+        assert_eq!(budget.lod_reduction(), 0);
+        assert!(budget.record(40.0, 16.0));
+        assert_eq!(budget.detail_factor(), 0.5);
+
+        // Sustained slowness is bounded
+        feed(&mut budget, 40.0, 100);
+        assert_eq!(budget.lod_reduction(), MAX_LOD_REDUCTION);
+
+        // Frames near the budget hold detail where it is
+        feed(&mut budget, 15.0, 200);
+        assert_eq!(budget.lod_reduction(), MAX_LOD_REDUCTION);
+
+        // Fast frames raise it one level at a time
+        feed(&mut budget, 5.0, FAST_FRAMES_TO_RAISE as usize);
+        assert_eq!(budget.lod_reduction(), MAX_LOD_REDUCTION - 1);
+        feed(&mut budget, 5.0, 1000);
+        assert_eq!(budget.lod_reduction(), 0);
+    }
+
+    #[test]
+    fn alternating_frames_do_not_oscillate() {
+        let mut budget = FrameBudget::default();
+        feed(&mut budget, 40.0, 3);
+        assert_eq!(budget.lod_reduction(), 1);
+        for _ in 0..100 {
+            assert!(!budget.record(40.0, 16.0));
+            assert!(!budget.record(5.0, 16.0));
+        }
+        assert_eq!(budget.lod_reduction(), 1);
+    }
+
+    #[test]
+    fn zero_budget_turns_the_controller_off() {
+        let mut budget = FrameBudget::default();
+        feed(&mut budget, 40.0, 9);
+        assert_eq!(budget.lod_reduction(), 3);
+        assert!(budget.record(40.0, 0.0));
+        assert_eq!(budget.lod_reduction(), 0);
+        assert!(!budget.record(1000.0, 0.0));
+    }
+}
@@ -0,0 +1,144 @@
+//! Differences between GPX 1.0 and 1.1 files
+//!
+//! GPX 1.1 keeps the file's name, description and author in a `<metadata>`
+//! element; GPX 1.0 puts them straight under `<gpx>`, with the author as
+//! plain text. The `gpx` crate reads tracks the same way for both, so the
+//! only fix-up needed is filling the 1.0 header fields into
+//! [`gpx::Metadata`] when the parser didn't, so names and descriptions show
+//! up whichever version a file declares.
+
+/// Version number as written in the file's `version` attribute
+pub fn version_label(version: &gpx::GpxVersion) -> &'static str {
+    match version {
+        gpx::GpxVersion::Gpx10 => "1.0",
+        gpx::GpxVersion::Gpx11 => "1.1",
+        gpx::GpxVersion::Unknown => "unknown",
+    }
+}
+
+/// Fill a GPX 1.0 file's name, description and author into its metadata
+///
+/// `bytes` is the document `gpx` was parsed from. Only fields the parser
+/// left empty are set, and files of other versions are left alone.
+pub fn apply_gpx10_header(gpx: &mut gpx::Gpx, bytes: &[u8]) {
+    if gpx.version != gpx::GpxVersion::Gpx10 {
+        return;
+    }
+    let header = header(bytes);
+    let name = root_element_text(&header, "name");
+    let description = root_element_text(&header, "desc");
+    let author = root_element_text(&header, "author");
+    if name.is_none() && description.is_none() && author.is_none() {
+        return;
+    }
+    let metadata = gpx.metadata.get_or_insert_with(Default::default);
+    if metadata.name.is_none() {
+        metadata.name = name;
+    }
+    if metadata.description.is_none() {
+        metadata.description = description;
+    }
+    if metadata.author.is_none()
+        && let Some(author) = author
+    {
+        metadata.author = Some(gpx::Person {
+            name: Some(author),
+            ..Default::default()
+        });
+    }
+}
+
+/// The document up to its first waypoint, route or track: where 1.0 keeps the file's fields
+fn header(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let end = ["<wpt", "<rte", "<trk"]
+        .iter()
+        .filter_map(|tag| text.find(tag))
+        .min()
+        .unwrap_or(text.len());
+    text[..end].to_string()
+}
+
+/// Text of the first `<tag>` element in `header`, unescaped and trimmed; `None` if empty
+fn root_element_text(header: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = header.find(&open)? + open.len();
+    let end = start + header[start..].find(&close)?;
+    let raw = header[start..end].trim();
+    let text = match raw
+        .strip_prefix("<![CDATA[")
+        .and_then(|rest| rest.strip_suffix("]]>"))
+    {
+        Some(cdata) => cdata.to_string(),
+        None => raw
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPX10: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.0" creator="Old Logger" xmlns="http://www.topografix.com/GPX/1/0">
+  <name>Morning &amp; evening ride</name>
+  <desc><![CDATA[Around the <lake>]]></desc>
+  <author>Sam</author>
+  <time>2024-05-01T08:00:00Z</time>
+  <trk>
+    <name>Ride</name>
+    <trkseg>
+      <trkpt lat="45.0" lon="6.0"><ele>1000</ele></trkpt>
+      <trkpt lat="45.001" lon="6.001"><ele>1010</ele></trkpt>
+      <trkpt lat="45.002" lon="6.002"><ele>1020</ele></trkpt>
+    </trkseg>
+  </trk>
+</gpx>
+"#;
+
+    #[test]
+    fn gpx10_version_and_header_are_read() {
+        let mut gpx = gpx::read(GPX10.as_bytes()).unwrap();
+        assert_eq!(gpx.version, gpx::GpxVersion::Gpx10);
+        assert_eq!(version_label(&gpx.version), "1.0");
+
+        apply_gpx10_header(&mut gpx, GPX10.as_bytes());
+        let metadata = gpx.metadata.as_ref().unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Morning & evening ride"));
+        assert_eq!(metadata.description.as_deref(), Some("Around the <lake>"));
+        assert_eq!(
+            metadata.author.as_ref().and_then(|a| a.name.as_deref()),
+            Some("Sam")
+        );
+
+        // The geometry loads like any 1.1 file
+        let route = large_track_lib::Route::new(gpx).unwrap();
+        assert_eq!(route.total_points(), 3);
+        assert_eq!(route.name(), Some("Morning & evening ride"));
+        assert_eq!(route.elevation_range(), Some((1000.0, 1020.0)));
+    }
+
+    #[test]
+    fn gpx11_is_left_alone() {
+        let document = GPX10
+            .replace("version=\"1.0\"", "version=\"1.1\"")
+            .replace("GPX/1/0", "GPX/1/1")
+            .replace(
+                "  <name>Morning &amp; evening ride</name>\n  <desc><![CDATA[Around the <lake>]]></desc>\n  <author>Sam</author>\n  <time>2024-05-01T08:00:00Z</time>\n",
+                "",
+            );
+        let mut gpx = gpx::read(document.as_bytes()).unwrap();
+        assert_eq!(version_label(&gpx.version), "1.1");
+        let before = gpx.clone();
+        // Even a stray header field isn't picked up outside 1.0
+        apply_gpx10_header(&mut gpx, GPX10.as_bytes());
+        assert_eq!(gpx, before);
+    }
+}
@@ -0,0 +1,132 @@
+//! Momentum for map drags
+//!
+//! walkers keeps the map gliding after a drag with a fixed feel. The app
+//! cancels that glide and runs its own here, so it can be switched off or
+//! made stickier or more slippery from the settings.
+
+use egui::Vec2;
+
+/// Friction used until the user picks another one: the glide speed falls by
+/// a factor of e every `1 / friction` seconds
+pub const DEFAULT_PAN_FRICTION: f32 = 4.0;
+
+/// Range offered by the friction slider
+pub const PAN_FRICTION_RANGE: std::ops::RangeInclusive<f32> = 1.0..=20.0;
+
+/// Glides slower than this (pixels per second) stop
+const MIN_SPEED: f32 = 5.0;
+
+/// Weight of the newest drag sample in the release velocity, so one jittery
+/// frame at the end of the drag doesn't decide the throw
+const DRAG_SAMPLE_WEIGHT: f32 = 0.5;
+
+/// Velocity after gliding `dt` seconds with `friction`; exactly zero once it
+/// drops below [`MIN_SPEED`]
+pub fn decay(velocity: Vec2, friction: f32, dt: f32) -> Vec2 {
+    let decayed = velocity * (-friction.max(0.0) * dt).exp();
+    if decayed.length() < MIN_SPEED {
+        Vec2::ZERO
+    } else {
+        decayed
+    }
+}
+
+/// Drag velocity while the map is held, then the glide after it is let go
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PanInertia {
+    /// Screen pixels per second the map content moves
+    velocity: Vec2,
+    dragging: bool,
+}
+
+impl PanInertia {
+    /// Record one frame of dragging by `delta` pixels over `dt` seconds
+    pub fn drag(&mut self, delta: Vec2, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        let sample = delta / dt;
+        self.velocity = if self.dragging {
+            self.velocity + (sample - self.velocity) * DRAG_SAMPLE_WEIGHT
+        } else {
+            sample
+        };
+        self.dragging = true;
+    }
+
+    /// Whether the map is being held; the glide starts on the first frame it isn't
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    /// Let go of the map: no glide with `enabled` off
+    pub fn release(&mut self, enabled: bool) {
+        self.dragging = false;
+        if !enabled {
+            self.stop();
+        }
+    }
+
+    /// Stop any glide
+    pub fn stop(&mut self) {
+        self.velocity = Vec2::ZERO;
+        self.dragging = false;
+    }
+
+    /// Whether the map is still gliding
+    pub fn is_gliding(&self) -> bool {
+        !self.dragging && self.velocity != Vec2::ZERO
+    }
+
+    /// Advance the glide by `dt` seconds; returns how far the content moves, in pixels
+    pub fn step(&mut self, friction: f32, dt: f32) -> Option<Vec2> {
+        if !self.is_gliding() {
+            return None;
+        }
+        let offset = self.velocity * dt;
+        self.velocity = decay(self.velocity, friction, dt);
+        Some(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glide_decays_to_zero() {
+        let mut velocity = Vec2::new(1200.0, -300.0);
+        let mut frames = 0;
+        while velocity != Vec2::ZERO {
+            let next = decay(velocity, DEFAULT_PAN_FRICTION, 1.0 / 60.0);
+            assert!(next.length() < velocity.length());
+            velocity = next;
+            frames += 1;
+            assert!(frames < 10_000, "glide never stopped");
+        }
+        // About ln(1237 / 5) / 4 ≈ 1.4 s at 60 fps
+        assert!((60..=120).contains(&frames), "{} frames", frames);
+    }
+
+    #[test]
+    fn more_friction_stops_sooner() {
+        let velocity = Vec2::new(500.0, 0.0);
+        let sticky = decay(velocity, 12.0, 0.1);
+        let slippery = decay(velocity, 2.0, 0.1);
+        assert!(sticky.x < slippery.x);
+    }
+
+    #[test]
+    fn releasing_glides_only_when_enabled() {
+        let mut inertia = PanInertia::default();
+        inertia.drag(Vec2::new(10.0, 0.0), 0.01);
+        assert!(!inertia.is_gliding());
+        inertia.release(true);
+        let offset = inertia.step(DEFAULT_PAN_FRICTION, 0.01).unwrap();
+        assert!((offset.x - 10.0).abs() < 1e-3);
+
+        inertia.drag(Vec2::new(10.0, 0.0), 0.01);
+        inertia.release(false);
+        assert!(inertia.step(DEFAULT_PAN_FRICTION, 0.01).is_none());
+    }
+}
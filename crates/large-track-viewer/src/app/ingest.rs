@@ -0,0 +1,391 @@
+//! Turning files into parsed tracks
+//!
+//! Every file the user opens, drops or restores goes through one
+//! [`Ingestor`], whether it comes as a path or as bytes: it is queued, handed
+//! to a load worker once there is room, and its parsed GPX (or the reason it
+//! failed) comes back on one channel in the order the workers finish.
+//! `AppState::process_parallel_results` is the only consumer, so errors,
+//! stats and fitting the map work the same for every file. Loading one file
+//! at a time is just a concurrency of 1.
+
+use crate::app::gpx_version;
+use crate::app::load_error::LoadError;
+use eframe_entrypoints::async_runtime::{self, mpsc};
+use egui::DroppedFile;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::elevation::ElevationService;
+
+/// Files kept in flight per load worker; the rest of a batch waits in the queue
+pub const LOAD_WINDOW_PER_WORKER: usize = 2;
+
+/// A loaded file: its path (see [`synthetic_path_for`]) and its tracks, or why it failed
+pub type LoadResult = (PathBuf, Result<gpx::Gpx, LoadError>);
+
+/// Number of files parsed at once
+///
+/// Native uses one per logical core; web workers have more overhead, so the
+/// browser gets a small fixed number.
+pub fn load_concurrency() -> usize {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4) // fallback to 4 if detection fails
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        4
+    }
+}
+
+/// Generate a stable synthetic path for a dropped file when a real path is unavailable.
+pub fn synthetic_path_for(dropped: &DroppedFile) -> PathBuf {
+    // Avoid duplicates by appending hash of content.
+    let fakepath = if let Some(p) = dropped.path.as_ref() {
+        p.clone()
+    } else {
+        // Produce a URI-like synthetic identifier: web://<id>-<size>-<name>
+        PathBuf::from(dropped.name.clone())
+    };
+    if let Some(bytes) = &dropped.bytes {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hash;
+        use std::hash::Hasher;
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let hash_str = format!("{:x}", hasher.finish());
+        let hash_str = hash_str[..8.min(hash_str.len())].to_string();
+        PathBuf::from(format!("{}-{}", fakepath.display(), hash_str))
+    } else {
+        fakepath
+    }
+}
+
+/// Run `f`, returning the panic message as an error if it panics
+///
+/// Parsing and indexing run on user data, and a bug they trip over (e.g. on
+/// degenerate coordinates) must only fail that one file instead of taking the
+/// whole app down with it.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string())
+    })
+}
+
+/// Queue of files on their way to the map, and the workers parsing them
+pub struct Ingestor {
+    /// Files waiting for a load worker
+    queued: VecDeque<DroppedFile>,
+
+    /// Files handed to a load worker whose result has not been taken yet
+    in_flight: Vec<PathBuf>,
+
+    /// Files parsed at once
+    concurrency: usize,
+
+    /// Parse slots shared by every worker
+    permits: Arc<async_runtime::Semaphore>,
+
+    /// Where workers send their results
+    sender: mpsc::UnboundedSender<LoadResult>,
+
+    /// Results not taken yet, oldest first
+    results: mpsc::UnboundedReceiver<LoadResult>,
+
+    /// Fills in missing elevations while loading (`--download-elevation`)
+    #[cfg(not(target_arch = "wasm32"))]
+    elevation: Option<Arc<ElevationService>>,
+}
+
+impl Ingestor {
+    /// Parse up to `concurrency` files at once
+    pub fn new(concurrency: usize) -> Self {
+        let concurrency = concurrency.max(1);
+        let (sender, results) = mpsc::unbounded_channel();
+        Self {
+            queued: VecDeque::new(),
+            in_flight: Vec::new(),
+            concurrency,
+            permits: Arc::new(async_runtime::Semaphore::new(concurrency)),
+            sender,
+            results,
+            #[cfg(not(target_arch = "wasm32"))]
+            elevation: None,
+        }
+    }
+
+    /// Fill in missing elevations from `elevation` before handing files back
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_elevation(mut self, elevation: Option<Arc<ElevationService>>) -> Self {
+        self.elevation = elevation;
+        self
+    }
+
+    /// Most files in flight at once; the rest wait in the queue
+    pub fn window(&self) -> usize {
+        self.concurrency * LOAD_WINDOW_PER_WORKER
+    }
+
+    /// Queue `files` and start on as many as the window allows
+    pub fn submit(&mut self, files: impl IntoIterator<Item = DroppedFile>) {
+        self.queued.extend(files);
+        self.spawn_queued();
+    }
+
+    /// Take the oldest result, if a worker has sent one
+    ///
+    /// Its file leaves the window; [`Ingestor::spawn_queued`] hands the
+    /// freed room to the next queued file. A file dropped again while it was
+    /// loading is in flight twice and leaves once per result.
+    pub fn next_result(&mut self) -> Option<LoadResult> {
+        let (path, result) = self.results.try_recv().ok()?;
+        if let Some(index) = self.in_flight.iter().position(|p| p == &path) {
+            self.in_flight.swap_remove(index);
+        }
+        Some((path, result))
+    }
+
+    /// Whether a result is waiting to be taken
+    pub fn has_results(&self) -> bool {
+        !self.results.is_empty()
+    }
+
+    /// Results waiting to be taken
+    #[cfg(test)]
+    pub fn ready_count(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Files waiting for a worker
+    pub fn queued(&self) -> impl Iterator<Item = &DroppedFile> {
+        self.queued.iter()
+    }
+
+    /// Drop the files still waiting for a worker
+    pub fn cancel_queued(&mut self) {
+        self.queued.clear();
+    }
+
+    /// Files being parsed, or parsed and waiting to be taken
+    pub fn in_flight(&self) -> &[PathBuf] {
+        &self.in_flight
+    }
+
+    /// Files submitted whose result hasn't been taken yet
+    pub fn unfinished_count(&self) -> usize {
+        self.queued.len() + self.in_flight.len()
+    }
+
+    /// Hand queued files to load workers while fewer than [`Ingestor::window`]
+    /// files are in flight
+    ///
+    /// A file stays in flight until its result has been taken, so this also
+    /// bounds the parsed tracks waiting for a frame to index them. Files
+    /// dropped in the browser carry their bytes, which are freed as soon as
+    /// the file is parsed; the rest of a large drop waits here untouched
+    /// instead of being parsed into memory all at once.
+    pub fn spawn_queued(&mut self) {
+        while self.in_flight.len() < self.window() {
+            let Some(dropped_file) = self.queued.pop_front() else {
+                break;
+            };
+            let sender = self.sender.clone();
+            let semaphore = self.permits.clone();
+            // A stable identifier for this file (real path when available,
+            // synthetic web://<name> otherwise)
+            let path = synthetic_path_for(&dropped_file);
+            self.in_flight.push(path.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            let elevation = self.elevation.clone();
+            // Use async_runtime::spawn which works on both native (tokio) and web (tokio-with-wasm)
+            async_runtime::spawn(async move {
+                // Per-worker profiling scope with tag for file identifier (path or synthetic id).
+                // This attaches a small data field to the span which is useful for filtering
+                // traces without emitting additional events.
+                #[cfg(feature = "profiling")]
+                {
+                    let tag = format!("file={}", path.to_string_lossy());
+                    // Top-level worker span tagged with the file id for easy lookup in traces
+                    profiling::scope!("file_loader::worker", tag.as_str());
+                    // Name the worker thread so profilers can show the worker as a meaningful thread
+                    ::profiling::register_thread!("FileLoaderWorker");
+                }
+
+                let permit = semaphore.acquire_owned().await.unwrap();
+
+                // Profile the actual IO + parse operation inside the worker scope
+                #[cfg(feature = "profiling")]
+                {
+                    let tag = format!("file={}", path.to_string_lossy());
+                    profiling::scope!("file_loader::io_and_parse", tag.as_str());
+                }
+                #[allow(unused_mut)]
+                let mut result = load_file_to_gpx(&dropped_file).await;
+                if let Ok(gpx) = &result {
+                    tracing::debug!(
+                        "{}: GPX {}",
+                        path.display(),
+                        gpx_version::version_label(&gpx.version)
+                    );
+                }
+                // Free dropped bytes now rather than after the elevation lookup
+                drop(dropped_file);
+                #[cfg(not(target_arch = "wasm32"))]
+                if let (Some(elevation), Ok(gpx)) = (&elevation, &mut result) {
+                    // A failed lookup leaves the file as it was; it still loads
+                    match elevation.fill_missing(gpx).await {
+                        Ok(0) => {}
+                        Ok(filled) => tracing::debug!(
+                            "Downloaded elevation for {} points of {}",
+                            filled,
+                            path.display()
+                        ),
+                        Err(e) => tracing::warn!(
+                            "Could not download elevation for {}: {}",
+                            path.display(),
+                            e
+                        ),
+                    }
+                }
+                // The receiver only goes away with the app
+                let _ = sender.send((path, result));
+                drop(permit); // release semaphore
+                // Yield to allow other tasks to run (helps UI responsiveness)
+                async_runtime::yield_now().await;
+            });
+        }
+    }
+
+    /// Send `result` as if a worker had loaded `path`
+    #[cfg(test)]
+    pub fn send_result(&mut self, path: PathBuf, result: Result<gpx::Gpx, LoadError>) {
+        self.in_flight.push(path.clone());
+        let _ = self.sender.send((path, result));
+    }
+}
+
+/// Read and parse one file
+#[cfg_attr(feature = "profiling", profiling::function)]
+async fn load_file_to_gpx(file: &DroppedFile) -> Result<gpx::Gpx, LoadError> {
+    let buf = if let Some(bs) = file.bytes.as_ref() {
+        bs.to_vec()
+    } else {
+        #[cfg(target_arch = "wasm32")]
+        {
+            return Err(LoadError::BytesUnavailable);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use tokio::io::AsyncReadExt;
+            let file = tokio::fs::File::open(
+                file.path
+                    .as_ref()
+                    .expect("file was read or has a path to be read from"),
+            )
+            .await
+            .map_err(LoadError::Open)?;
+            let mut reader = tokio::io::BufReader::new(file);
+            let mut buf = Vec::new();
+            reader
+                .read_to_end(&mut buf)
+                .await
+                .map_err(LoadError::Read)?;
+            buf
+        }
+    };
+    let mut gpx =
+        catch_panic(|| large_track_lib::parse_gpx(&buf)).map_err(LoadError::ParserCrashed)??;
+    gpx_version::apply_gpx10_header(&mut gpx, &buf);
+    Ok(gpx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn panic_becomes_error_message() {
+        let result: Result<(), String> = catch_panic(|| panic!("degenerate segment {}", 3));
+        assert_eq!(result, Err("degenerate segment 3".to_string()));
+        assert_eq!(catch_panic(|| 7), Ok(7));
+    }
+
+    #[test]
+    fn one_at_a_time_is_a_concurrency_of_one() {
+        let file = |i: usize, bytes: &[u8]| DroppedFile {
+            name: format!("file{}.gpx", i),
+            bytes: Some(bytes.to_vec().into()),
+            ..Default::default()
+        };
+        let track = br#"<gpx version="1.1" creator="test"><trk><trkseg>
+            <trkpt lat="45.0" lon="6.0"/><trkpt lat="45.1" lon="6.1"/>
+            </trkseg></trk></gpx>"#;
+        let files: Vec<DroppedFile> = (0..5)
+            .map(|i| {
+                if i == 2 {
+                    file(i, b"not a gpx file")
+                } else {
+                    file(i, track)
+                }
+            })
+            .collect();
+        let paths: Vec<PathBuf> = files.iter().map(synthetic_path_for).collect();
+
+        let mut ingestor = Ingestor::new(1);
+        ingestor.submit(files);
+        assert_eq!(ingestor.window(), LOAD_WINDOW_PER_WORKER);
+        assert_eq!(ingestor.in_flight().len(), LOAD_WINDOW_PER_WORKER);
+        assert_eq!(ingestor.unfinished_count(), 5);
+
+        // Every file comes back once, failures included, never more than a window at a time
+        let mut results = Vec::new();
+        let deadline = instant::Instant::now() + Duration::from_secs(10);
+        while results.len() < paths.len() && instant::Instant::now() < deadline {
+            while let Some(result) = ingestor.next_result() {
+                results.push(result);
+            }
+            assert!(ingestor.in_flight().len() <= ingestor.window());
+            ingestor.spawn_queued();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // In the order the workers finish, which files in flight together may swap
+        let mut returned: Vec<&PathBuf> = results.iter().map(|(path, _)| path).collect();
+        returned.sort();
+        let mut submitted: Vec<&PathBuf> = paths.iter().collect();
+        submitted.sort();
+        assert_eq!(returned, submitted);
+        for (path, result) in &results {
+            if *path == paths[2] {
+                assert!(matches!(result, Err(LoadError::Parse(_))));
+            } else {
+                assert!(result.is_ok());
+            }
+        }
+        assert_eq!(ingestor.unfinished_count(), 0);
+        assert!(!ingestor.has_results());
+    }
+
+    #[test]
+    fn a_file_in_flight_twice_finishes_once_per_result() {
+        let mut ingestor = Ingestor::new(1);
+        let path = PathBuf::from("/tracks/ride.gpx");
+        ingestor.send_result(path.clone(), Ok(gpx::Gpx::default()));
+        ingestor.send_result(path.clone(), Ok(gpx::Gpx::default()));
+        assert_eq!(ingestor.unfinished_count(), 2);
+
+        assert!(ingestor.next_result().is_some());
+        assert_eq!(ingestor.in_flight(), [path]);
+        assert_eq!(ingestor.unfinished_count(), 1);
+        assert!(ingestor.next_result().is_some());
+        assert_eq!(ingestor.unfinished_count(), 0);
+    }
+}
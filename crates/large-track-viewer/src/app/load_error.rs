@@ -0,0 +1,193 @@
+//! Why a file failed to load
+//!
+//! Every failure on the way from a file to an indexed route ends up as a
+//! [`LoadError`]. Its [`LoadErrorKind`] groups the error list and decides how
+//! it is shown: an empty file is a warning, a crash is an error. Whether a
+//! retry can help depends on the error itself, not only on its kind.
+
+use crate::app::notifications::NotificationLevel;
+use large_track_lib::DataError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+    /// Dropped in the browser without its contents
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    #[error("File bytes not available on WASM")]
+    BytesUnavailable,
+
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    #[error("Error opening file: {0:?}")]
+    Open(std::io::Error),
+
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    #[error("Failed to read file: {0}")]
+    Read(std::io::Error),
+
+    #[error("Failed to parse GPX: {0}")]
+    Parse(gpx::errors::GpxError),
+
+    #[error("GPX parser crashed: {0}")]
+    ParserCrashed(String),
+
+    /// Parsed, but not usable (an empty file, for one)
+    #[error("{0}")]
+    Data(DataError),
+
+    /// The index refused the route
+    #[error("Failed to add route: {0}")]
+    Rejected(DataError),
+
+    /// The index was held by another task
+    #[error("Failed to add route: the track index was busy")]
+    LockUnavailable,
+
+    #[error("Indexing crashed: {0}")]
+    IndexingCrashed(String),
+}
+
+impl From<DataError> for LoadError {
+    fn from(error: DataError) -> Self {
+        match error {
+            DataError::GpxParse(error) => Self::Parse(error),
+            error => Self::Data(error),
+        }
+    }
+}
+
+impl LoadError {
+    pub fn kind(&self) -> LoadErrorKind {
+        match self {
+            Self::BytesUnavailable | Self::Open(_) | Self::Read(_) => LoadErrorKind::Unreadable,
+            Self::Data(DataError::EmptyFile) | Self::Rejected(DataError::EmptyRoute) => {
+                LoadErrorKind::Empty
+            }
+            Self::Parse(_) | Self::Data(_) | Self::Rejected(_) => LoadErrorKind::Invalid,
+            Self::LockUnavailable => LoadErrorKind::Busy,
+            Self::ParserCrashed(_) | Self::IndexingCrashed(_) => LoadErrorKind::Crashed,
+        }
+    }
+
+    /// Whether loading the file again may succeed: after a passing failure,
+    /// or once the file is fixed. A crash would happen again, and bytes that
+    /// weren't kept can't be read again.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            Self::BytesUnavailable | Self::ParserCrashed(_) | Self::IndexingCrashed(_)
+        )
+    }
+}
+
+/// Group of load errors in the error list, in the order they are listed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadErrorKind {
+    /// A crash in the parser or the index
+    Crashed,
+    /// Not valid GPX, or rejected by the index
+    Invalid,
+    /// Couldn't be opened or read
+    Unreadable,
+    /// Nothing in it to show
+    Empty,
+    /// The index was busy; trying again usually works
+    Busy,
+}
+
+impl LoadErrorKind {
+    /// Heading of the group in the error list
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Crashed => "Crashed",
+            Self::Invalid => "Not valid GPX",
+            Self::Unreadable => "Couldn't read",
+            Self::Empty => "Empty",
+            Self::Busy => "Index busy",
+        }
+    }
+
+    /// Whether the group is shown as a warning or as an error
+    pub fn level(&self) -> NotificationLevel {
+        match self {
+            Self::Empty | Self::Busy => NotificationLevel::Warn,
+            Self::Crashed | Self::Invalid | Self::Unreadable => NotificationLevel::Error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_error_maps_to_its_group_level_and_retry() {
+        let io = || std::io::Error::from(std::io::ErrorKind::NotFound);
+        let parse = gpx::read("not a gpx file".as_bytes()).unwrap_err();
+        let cases = [
+            (
+                LoadError::BytesUnavailable,
+                LoadErrorKind::Unreadable,
+                false,
+            ),
+            (LoadError::Open(io()), LoadErrorKind::Unreadable, true),
+            (LoadError::Read(io()), LoadErrorKind::Unreadable, true),
+            (LoadError::Parse(parse), LoadErrorKind::Invalid, true),
+            (
+                LoadError::ParserCrashed("boom".to_string()),
+                LoadErrorKind::Crashed,
+                false,
+            ),
+            (
+                LoadError::Data(DataError::EmptyFile),
+                LoadErrorKind::Empty,
+                true,
+            ),
+            (
+                LoadError::Data(DataError::InvalidGeometry("nan".to_string())),
+                LoadErrorKind::Invalid,
+                true,
+            ),
+            (
+                LoadError::Rejected(DataError::EmptyRoute),
+                LoadErrorKind::Empty,
+                true,
+            ),
+            (
+                LoadError::Rejected(DataError::InvariantViolated("order".to_string())),
+                LoadErrorKind::Invalid,
+                true,
+            ),
+            (LoadError::LockUnavailable, LoadErrorKind::Busy, true),
+            (
+                LoadError::IndexingCrashed("boom".to_string()),
+                LoadErrorKind::Crashed,
+                false,
+            ),
+        ];
+        for (error, kind, retryable) in cases {
+            assert_eq!(error.kind(), kind, "{}", error);
+            assert_eq!(error.is_retryable(), retryable, "{}", error);
+        }
+
+        assert_eq!(LoadErrorKind::Empty.level(), NotificationLevel::Warn);
+        assert_eq!(LoadErrorKind::Busy.level(), NotificationLevel::Warn);
+        assert_eq!(LoadErrorKind::Crashed.level(), NotificationLevel::Error);
+        assert_eq!(LoadErrorKind::Invalid.level(), NotificationLevel::Error);
+        assert_eq!(LoadErrorKind::Unreadable.level(), NotificationLevel::Error);
+
+        // The library's errors keep their messages
+        assert_eq!(
+            LoadError::from(DataError::EmptyFile).to_string(),
+            "File is empty"
+        );
+        assert!(
+            LoadError::from(large_track_lib::parse_gpx(b"<gpx").unwrap_err())
+                .to_string()
+                .starts_with("Failed to parse GPX")
+        );
+        assert_eq!(
+            LoadError::IndexingCrashed("degenerate segment".to_string()).to_string(),
+            "Indexing crashed: degenerate segment"
+        );
+    }
+}
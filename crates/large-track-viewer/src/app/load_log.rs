@@ -0,0 +1,67 @@
+//! Periodic progress lines for bulk loads
+//!
+//! Logging every file of a several-thousand-file import floods stderr and
+//! slows it down, so per-file lines are debug-level and the load is summed
+//! up at info level every `--load-log-every` files instead.
+
+/// Default of `--load-log-every`
+pub const DEFAULT_LOAD_LOG_EVERY: usize = 500;
+
+/// Decides when a batch's progress is worth a log line
+#[derive(Debug)]
+pub struct LoadLog {
+    /// Files between summaries, 0 for none
+    every: usize,
+    /// Progress at the last summary of the current batch
+    logged: usize,
+}
+
+impl LoadLog {
+    pub fn new(every: usize) -> Self {
+        Self { every, logged: 0 }
+    }
+
+    /// Start counting a new batch
+    pub fn reset(&mut self) {
+        self.logged = 0;
+    }
+
+    /// Whether to log now that `done` of `total` files are loaded
+    ///
+    /// Fires each time `done` passes a multiple of the interval, and once
+    /// when the batch completes.
+    pub fn due(&mut self, done: usize, total: usize) -> bool {
+        if self.every == 0 || done <= self.logged {
+            return false;
+        }
+        let due = done / self.every > self.logged / self.every || done >= total;
+        if due {
+            self.logged = done;
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summaries_fire_every_interval_and_at_the_end() {
+        let mut log = LoadLog::new(500);
+        // Several files land per frame, so progress skips numbers
+        let fired: Vec<usize> = (0..=1203)
+            .step_by(4)
+            .chain([1203])
+            .filter(|&done| log.due(done, 1203))
+            .collect();
+        assert_eq!(fired, vec![500, 1000, 1203]);
+        assert!(!log.due(1203, 1203));
+
+        log.reset();
+        assert!(log.due(3, 3));
+
+        let mut silent = LoadLog::new(0);
+        assert!(!silent.due(500, 500));
+    }
+}
@@ -0,0 +1,410 @@
+//! Live "follow me" position display
+//!
+//! Positions come from the browser Geolocation API on the web and from a local
+//! gpsd daemon on native builds with the `gpsd` feature. Each source pushes
+//! updates into a channel that the UI drains once per frame, so no platform
+//! callback ever touches the app state directly.
+//!
+//! Fixes are never sent anywhere, and are only kept while the user records a
+//! track (see `recorder`).
+//!
+//! Apps embedding the viewer can also feed a position of their own, which
+//! is shown apart from these sources (see [`ExternalPosition`]).
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Without an update for this long, the last fix is treated as lost
+const FIX_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A single position fix
+#[derive(Clone, Copy, Debug)]
+pub struct LiveFix {
+    /// Reported position
+    pub position: walkers::Position,
+    /// Horizontal accuracy radius in meters
+    pub accuracy_m: f64,
+    /// Altitude in meters, if the source reports one
+    pub altitude_m: Option<f64>,
+    /// When the fix was taken, if the source reports it
+    pub time: Option<time::OffsetDateTime>,
+}
+
+/// Message sent by a platform position source
+#[derive(Debug)]
+#[cfg_attr(not(any(target_arch = "wasm32", feature = "gpsd")), allow(dead_code))]
+enum LocationUpdate {
+    /// A new fix
+    Fix(LiveFix),
+    /// The source is still running but currently has no fix
+    Lost,
+    /// The source failed or was denied permission and will not send more updates
+    Error(String),
+}
+
+/// Opt-in live position state shared by the map and the settings panel
+#[derive(Default)]
+pub struct LiveLocation {
+    /// Running source and the channel it reports on (None while disabled)
+    source: Option<(platform::Source, mpsc::Receiver<LocationUpdate>)>,
+    /// Last known fix, kept (greyed out) after the fix is lost
+    fix: Option<LiveFix>,
+    /// When the last fix arrived
+    last_fix_at: Option<instant::Instant>,
+    /// Whether the source reported losing its fix since the last one
+    lost: bool,
+    /// Last error reported by the source, shown in the settings panel
+    error: Option<String>,
+    /// Keep the camera centered on the current fix
+    pub follow: bool,
+}
+
+impl LiveLocation {
+    /// Whether a position source is currently running
+    pub fn is_enabled(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Start or stop the platform position source.
+    ///
+    /// Starting may trigger the platform's permission prompt. Stopping drops
+    /// the source (which unregisters it) and forgets the last fix.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled == self.is_enabled() {
+            return;
+        }
+        *self = Self {
+            follow: self.follow,
+            ..Self::default()
+        };
+        if enabled {
+            let (sender, receiver) = mpsc::channel();
+            match platform::start(sender) {
+                Ok(source) => self.source = Some((source, receiver)),
+                Err(e) => {
+                    tracing::warn!("Live position not available: {}", e);
+                    self.error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Drain pending updates, returning the new fixes in arrival order
+    pub fn poll(&mut self) -> Vec<LiveFix> {
+        let Some((_, receiver)) = &self.source else {
+            return Vec::new();
+        };
+
+        let mut fixes = Vec::new();
+        let mut failed = false;
+        while let Ok(update) = receiver.try_recv() {
+            match update {
+                LocationUpdate::Fix(fix) => {
+                    self.fix = Some(fix);
+                    self.last_fix_at = Some(instant::Instant::now());
+                    self.lost = false;
+                    fixes.push(fix);
+                }
+                LocationUpdate::Lost => self.lost = true,
+                LocationUpdate::Error(e) => {
+                    tracing::warn!("Live position source stopped: {}", e);
+                    self.error = Some(e);
+                    failed = true;
+                }
+            }
+        }
+
+        if failed {
+            // Keep the last fix on screen (greyed out) but stop listening
+            self.source = None;
+            self.lost = true;
+        }
+        fixes
+    }
+
+    /// Last known fix, if any was received since enabling
+    pub fn fix(&self) -> Option<&LiveFix> {
+        self.fix.as_ref()
+    }
+
+    /// Whether the last fix is still current (not lost or timed out)
+    pub fn has_current_fix(&self) -> bool {
+        self.is_enabled()
+            && !self.lost
+            && self
+                .last_fix_at
+                .is_some_and(|at| at.elapsed() < FIX_TIMEOUT)
+    }
+
+    /// Last error reported by the position source
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Position fed by an embedding app, e.g. from its own GPS feed
+///
+/// Set through `LargeTrackViewerApp::set_live_position` and drawn as a
+/// pulsing marker, whatever tracks are loaded and whether or not
+/// [`LiveLocation`] is enabled.
+#[derive(Default)]
+pub struct ExternalPosition {
+    /// Position to mark, if any
+    position: Option<walkers::Position>,
+    /// Keep the camera centered on the position
+    pub follow: bool,
+    /// Whether the position changed since the camera last followed it
+    moved: bool,
+}
+
+impl ExternalPosition {
+    /// Mark `position`, or remove the marker with `None`
+    pub fn set(&mut self, position: Option<walkers::Position>) {
+        self.moved |= position.is_some() && position != self.position;
+        self.position = position;
+    }
+
+    /// Position currently marked
+    pub fn position(&self) -> Option<walkers::Position> {
+        self.position
+    }
+
+    /// Where to center the camera this frame: the position if it moved and is followed
+    pub fn take_recenter(&mut self) -> Option<walkers::Position> {
+        let moved = std::mem::take(&mut self.moved);
+        self.position.filter(|_| moved && self.follow)
+    }
+}
+
+//
+// Web implementation (browser Geolocation API)
+//
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use super::{LiveFix, LocationUpdate};
+    use std::sync::mpsc::Sender;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+    use web_sys::{GeolocationPosition, GeolocationPositionError, PositionOptions};
+
+    /// An active `watchPosition` registration, cleared on drop
+    pub struct Source {
+        geolocation: web_sys::Geolocation,
+        watch_id: i32,
+        // The callbacks must outlive the registration
+        _on_position: Closure<dyn FnMut(GeolocationPosition)>,
+        _on_error: Closure<dyn FnMut(GeolocationPositionError)>,
+    }
+
+    pub fn start(sender: Sender<LocationUpdate>) -> Result<Source, String> {
+        let geolocation = web_sys::window()
+            .ok_or_else(|| "no window".to_string())?
+            .navigator()
+            .geolocation()
+            .map_err(|e| format!("Geolocation API not available: {:?}", e))?;
+
+        let position_sender = sender.clone();
+        let on_position =
+            Closure::<dyn FnMut(GeolocationPosition)>::new(move |position: GeolocationPosition| {
+                let coords = position.coords();
+                let _ = position_sender.send(LocationUpdate::Fix(LiveFix {
+                    position: walkers::lat_lon(coords.latitude(), coords.longitude()),
+                    accuracy_m: coords.accuracy(),
+                    altitude_m: coords.altitude(),
+                    // DOM timestamps are milliseconds since the Unix epoch
+                    time: time::OffsetDateTime::from_unix_timestamp_nanos(
+                        (position.timestamp() * 1e6) as i128,
+                    )
+                    .ok(),
+                }));
+            });
+        let on_error = Closure::<dyn FnMut(GeolocationPositionError)>::new(
+            move |error: GeolocationPositionError| {
+                let update = if error.code() == GeolocationPositionError::PERMISSION_DENIED {
+                    LocationUpdate::Error("Location permission was denied".to_string())
+                } else {
+                    LocationUpdate::Lost
+                };
+                let _ = sender.send(update);
+            },
+        );
+
+        let options = PositionOptions::new();
+        options.set_enable_high_accuracy(true);
+        let watch_id = geolocation
+            .watch_position_with_error_callback_and_options(
+                on_position.as_ref().unchecked_ref(),
+                Some(on_error.as_ref().unchecked_ref()),
+                &options,
+            )
+            .map_err(|e| format!("watchPosition failed: {:?}", e))?;
+
+        Ok(Source {
+            geolocation,
+            watch_id,
+            _on_position: on_position,
+            _on_error: on_error,
+        })
+    }
+
+    impl Drop for Source {
+        fn drop(&mut self) {
+            self.geolocation.clear_watch(self.watch_id);
+        }
+    }
+}
+
+//
+// Native implementation (gpsd JSON protocol over TCP)
+//
+#[cfg(all(not(target_arch = "wasm32"), feature = "gpsd"))]
+mod platform {
+    use super::{LiveFix, LocationUpdate};
+    use std::io::{BufRead, BufReader, ErrorKind, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+
+    /// Default gpsd address
+    const GPSD_ADDRESS: &str = "127.0.0.1:2947";
+
+    /// A background reader thread, stopped on drop
+    pub struct Source {
+        stop: Arc<AtomicBool>,
+    }
+
+    pub fn start(sender: Sender<LocationUpdate>) -> Result<Source, String> {
+        let mut stream = TcpStream::connect(GPSD_ADDRESS)
+            .map_err(|e| format!("Could not connect to gpsd at {}: {}", GPSD_ADDRESS, e))?;
+        // A read timeout lets the thread notice the stop flag without a fix arriving
+        stream
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .map_err(|e| format!("Failed to configure gpsd socket: {}", e))?;
+        stream
+            .write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")
+            .map_err(|e| format!("Failed to start gpsd watch: {}", e))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        std::thread::Builder::new()
+            .name("gpsd".to_string())
+            .spawn(move || read_reports(stream, sender, &thread_stop))
+            .map_err(|e| format!("Failed to spawn gpsd reader: {}", e))?;
+
+        Ok(Source { stop })
+    }
+
+    fn read_reports(stream: TcpStream, sender: Sender<LocationUpdate>, stop: &AtomicBool) {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while !stop.load(Ordering::Relaxed) {
+            match reader.read_line(&mut line) {
+                Ok(0) => {
+                    let _ = sender.send(LocationUpdate::Error(
+                        "gpsd closed the connection".to_string(),
+                    ));
+                    return;
+                }
+                Ok(_) => {
+                    if let Some(update) = parse_report(&line)
+                        && sender.send(update).is_err()
+                    {
+                        return; // UI side went away
+                    }
+                    line.clear();
+                }
+                // Partial data stays in `line` and is completed by the next read
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => {
+                    let _ = sender.send(LocationUpdate::Error(format!("gpsd read failed: {}", e)));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Turn one gpsd JSON report into an update; only TPV reports are relevant
+    fn parse_report(line: &str) -> Option<LocationUpdate> {
+        let report: serde_json::Value = serde_json::from_str(line).ok()?;
+        if report["class"] != "TPV" {
+            return None;
+        }
+        // Mode 0/1 means no fix; 2 and 3 are 2D/3D fixes
+        if report["mode"].as_u64().unwrap_or(0) < 2 {
+            return Some(LocationUpdate::Lost);
+        }
+        let lat = report["lat"].as_f64()?;
+        let lon = report["lon"].as_f64()?;
+        let accuracy_m = report["eph"].as_f64().unwrap_or_else(|| {
+            let epx = report["epx"].as_f64().unwrap_or(0.0);
+            let epy = report["epy"].as_f64().unwrap_or(0.0);
+            epx.max(epy)
+        });
+        // Prefer altitude above mean sea level; `alt` is its deprecated alias
+        let altitude_m = report["altMSL"].as_f64().or_else(|| report["alt"].as_f64());
+        let time = report["time"]
+            .as_str()
+            .and_then(|t| {
+                time::OffsetDateTime::parse(t, &time::format_description::well_known::Rfc3339).ok()
+            })
+            .unwrap_or_else(time::OffsetDateTime::now_utc);
+        Some(LocationUpdate::Fix(LiveFix {
+            position: walkers::lat_lon(lat, lon),
+            accuracy_m,
+            altitude_m,
+            time: Some(time),
+        }))
+    }
+
+    impl Drop for Source {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_tpv_report() {
+            match parse_report(
+                r#"{"class":"TPV","mode":3,"lat":51.5,"lon":-0.12,"epx":4.0,"epy":6.5,"altMSL":35.2,"time":"2024-05-01T10:00:00.000Z"}"#,
+            ) {
+                Some(LocationUpdate::Fix(fix)) => {
+                    assert_eq!(fix.position.y(), 51.5);
+                    assert_eq!(fix.position.x(), -0.12);
+                    assert_eq!(fix.accuracy_m, 6.5);
+                    assert_eq!(fix.altitude_m, Some(35.2));
+                    assert_eq!(fix.time.unwrap().unix_timestamp(), 1_714_557_600);
+                }
+                other => panic!("expected a fix, got {:?}", other),
+            }
+
+            assert!(matches!(
+                parse_report(r#"{"class":"TPV","mode":1}"#),
+                Some(LocationUpdate::Lost)
+            ));
+            assert!(parse_report(r#"{"class":"SKY","satellites":[]}"#).is_none());
+        }
+    }
+}
+
+//
+// Native builds without a position backend
+//
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "gpsd")))]
+mod platform {
+    use super::LocationUpdate;
+    use std::sync::mpsc::Sender;
+
+    // Never constructed: `start` always fails without a backend
+    #[allow(dead_code)]
+    pub struct Source;
+
+    pub fn start(_sender: Sender<LocationUpdate>) -> Result<Source, String> {
+        Err("this build has no position source (rebuild with the `gpsd` feature)".to_string())
+    }
+}
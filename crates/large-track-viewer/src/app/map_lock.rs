@@ -0,0 +1,264 @@
+//! Locking the map against accidental panning
+//!
+//! Embedded in a web page, a finger swiping to scroll the page lands on the
+//! map and pans it instead. While the map is locked, [`MapLock::filter`]
+//! goes over each frame's raw input before egui (and so the map) sees it:
+//! a press on the map is held back until it is known to be a tap, which
+//! then goes through as is, or a drag, which is dropped whole. Plain wheel
+//! turns over the map are dropped too. A second finger releases the held
+//! press, so two-finger pans and pinches keep working.
+
+use egui::{Event, Pos2, TouchId, TouchPhase};
+
+/// How far a press may move and still count as a tap, in points
+const TAP_SLOP: f32 = 6.0;
+
+/// The press held back while the map is locked
+#[derive(Clone, Debug)]
+struct HeldPress {
+    /// The `PointerButton` event, at the position it was pressed
+    press: Event,
+    origin: Pos2,
+    /// Moved past [`TAP_SLOP`]: a drag, dropped whole unless a second finger comes
+    dragged: bool,
+}
+
+/// Tracks the pointer and fingers across frames to filter the locked map's input
+#[derive(Debug, Default)]
+pub struct MapLock {
+    /// Fingers on the screen
+    touches: Vec<TouchId>,
+    /// Latest pointer position
+    pointer: Option<Pos2>,
+    held: Option<HeldPress>,
+}
+
+impl MapLock {
+    /// Drop the single-pointer drags and plain wheel turns that `on_map`
+    /// says are over the map, when `locked`
+    ///
+    /// Returns whether a gesture was dropped, to hint at how to move the map.
+    pub fn filter(
+        &mut self,
+        events: &mut Vec<Event>,
+        locked: bool,
+        on_map: impl Fn(Pos2) -> bool,
+    ) -> bool {
+        let mut blocked = false;
+        let mut passed = Vec::with_capacity(events.len());
+        for event in events.drain(..) {
+            match &event {
+                Event::Touch { id, phase, .. } => match phase {
+                    TouchPhase::Start => {
+                        if !self.touches.contains(id) {
+                            self.touches.push(*id);
+                        }
+                        if self.touches.len() >= 2
+                            && let Some(held) = self.held.take()
+                        {
+                            // Two fingers move the map: let the press go through where it is now
+                            let position = self.pointer.unwrap_or(held.origin);
+                            passed.push(Event::PointerMoved(position));
+                            passed.push(press_at(held.press, position));
+                        }
+                    }
+                    TouchPhase::End | TouchPhase::Cancel => self.touches.retain(|t| t != id),
+                    TouchPhase::Move => {}
+                },
+                Event::PointerMoved(position) => {
+                    self.pointer = Some(*position);
+                    if let Some(held) = &mut self.held {
+                        if !held.dragged && held.origin.distance(*position) > TAP_SLOP {
+                            held.dragged = true;
+                            blocked = true;
+                        }
+                        continue;
+                    }
+                }
+                Event::PointerButton { pos, pressed, .. } => {
+                    self.pointer = Some(*pos);
+                    if *pressed {
+                        if locked && self.held.is_none() && self.touches.len() < 2 && on_map(*pos) {
+                            self.held = Some(HeldPress {
+                                press: event.clone(),
+                                origin: *pos,
+                                dragged: false,
+                            });
+                            continue;
+                        }
+                    } else if let Some(held) = self.held.take() {
+                        if !held.dragged {
+                            // A tap: press and release where it was pressed
+                            passed.push(Event::PointerMoved(held.origin));
+                            passed.push(held.press);
+                            passed.push(press_at(event, held.origin));
+                        }
+                        continue;
+                    }
+                }
+                Event::PointerGone => self.pointer = None,
+                Event::MouseWheel { modifiers, .. }
+                    if locked && modifiers.is_none() && self.pointer.is_some_and(&on_map) =>
+                {
+                    blocked = true;
+                    continue;
+                }
+                _ => {}
+            }
+            passed.push(event);
+        }
+        *events = passed;
+        blocked
+    }
+}
+
+/// `event`, a `PointerButton` event, moved to `position`
+fn press_at(event: Event, position: Pos2) -> Event {
+    match event {
+        Event::PointerButton {
+            button,
+            pressed,
+            modifiers,
+            ..
+        } => Event::PointerButton {
+            pos: position,
+            button,
+            pressed,
+            modifiers,
+        },
+        event => event,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn button(x: f32, y: f32, pressed: bool) -> Event {
+        Event::PointerButton {
+            pos: egui::pos2(x, y),
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        }
+    }
+
+    fn moved(x: f32, y: f32) -> Event {
+        Event::PointerMoved(egui::pos2(x, y))
+    }
+
+    fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> Event {
+        Event::Touch {
+            device_id: egui::TouchDeviceId(0),
+            id: TouchId(id),
+            phase,
+            pos: egui::pos2(x, y),
+            force: None,
+        }
+    }
+
+    /// Run each frame's events through the lock, returning what got through
+    /// and whether anything was blocked
+    fn run(lock: &mut MapLock, locked: bool, frames: Vec<Vec<Event>>) -> (Vec<Event>, bool) {
+        let mut passed = Vec::new();
+        let mut blocked = false;
+        for mut events in frames {
+            // The map covers everything left of x = 100
+            blocked |= lock.filter(&mut events, locked, |position| position.x < 100.0);
+            passed.extend(events);
+        }
+        (passed, blocked)
+    }
+
+    #[test]
+    fn locked_drags_on_the_map_are_dropped_and_taps_go_through() {
+        let mut lock = MapLock::default();
+        // A one-finger swipe across the map never reaches egui
+        let swipe = vec![
+            vec![
+                touch(1, TouchPhase::Start, 50.0, 50.0),
+                button(50.0, 50.0, true),
+            ],
+            vec![touch(1, TouchPhase::Move, 50.0, 20.0), moved(50.0, 20.0)],
+            vec![
+                touch(1, TouchPhase::End, 50.0, 20.0),
+                button(50.0, 20.0, false),
+            ],
+        ];
+        let (passed, blocked) = run(&mut lock, true, swipe.clone());
+        assert!(blocked);
+        assert!(
+            passed
+                .iter()
+                .all(|event| matches!(event, Event::Touch { .. })),
+            "{:?}",
+            passed
+        );
+
+        // A tap that wobbles a little goes through whole, where it was pressed
+        let tap = vec![
+            vec![button(50.0, 50.0, true)],
+            vec![moved(52.0, 51.0)],
+            vec![button(52.0, 51.0, false)],
+        ];
+        let (passed, blocked) = run(&mut lock, true, tap);
+        assert!(!blocked);
+        assert_eq!(
+            passed,
+            [
+                moved(50.0, 50.0),
+                button(50.0, 50.0, true),
+                button(50.0, 50.0, false)
+            ]
+        );
+
+        // Off the map, or unlocked, everything passes untouched
+        let off_map: Vec<Vec<Event>> = vec![vec![
+            button(150.0, 50.0, true),
+            moved(150.0, 20.0),
+            button(150.0, 20.0, false),
+        ]];
+        assert_eq!(run(&mut lock, true, off_map.clone()).0, off_map.concat());
+        let (passed, blocked) = run(&mut lock, false, swipe.clone());
+        assert_eq!((passed, blocked), (swipe.concat(), false));
+    }
+
+    #[test]
+    fn a_second_finger_releases_the_held_press() {
+        let mut lock = MapLock::default();
+        let (passed, blocked) = run(
+            &mut lock,
+            true,
+            vec![
+                vec![
+                    touch(1, TouchPhase::Start, 50.0, 50.0),
+                    button(50.0, 50.0, true),
+                ],
+                vec![touch(1, TouchPhase::Move, 50.0, 30.0), moved(50.0, 30.0)],
+                vec![touch(2, TouchPhase::Start, 80.0, 30.0)],
+                vec![touch(1, TouchPhase::Move, 50.0, 10.0), moved(50.0, 10.0)],
+                vec![
+                    touch(1, TouchPhase::End, 50.0, 10.0),
+                    button(50.0, 10.0, false),
+                ],
+            ],
+        );
+        // The first finger alone was blocked, then the pan starts where it is
+        assert!(blocked);
+        let pointer: Vec<&Event> = passed
+            .iter()
+            .filter(|event| !matches!(event, Event::Touch { .. }))
+            .collect();
+        assert_eq!(
+            pointer,
+            [
+                &moved(50.0, 30.0),
+                &button(50.0, 30.0, true),
+                &moved(50.0, 10.0),
+                &button(50.0, 10.0, false)
+            ]
+        );
+        assert!(lock.held.is_none());
+        assert_eq!(lock.touches, [TouchId(2)]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Guarding imports against running out of memory
+//!
+//! Before a batch of files is loaded, their total size is turned into an
+//! estimated in-memory cost and compared with a budget. Going over the budget
+//! only asks for confirmation; the user can always load anyway.
+
+use egui::DroppedFile;
+use large_track_lib::estimate_memory_for_gpx_bytes;
+use std::time::Duration;
+
+/// Budget used when the system memory can't be detected (e.g. on the web,
+/// where a wasm32 heap is capped at 4 GiB anyway)
+const FALLBACK_BUDGET_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Fraction of the system memory the default budget allows the app to use
+const DEFAULT_BUDGET_FRACTION: f64 = 0.5;
+
+/// A batch load that was held back because it would exceed the budget
+pub struct MemoryWarning {
+    /// Files of the batch, in load order
+    pub files: Vec<DroppedFile>,
+    /// Size on disk of each file, matching `files`
+    pub file_sizes: Vec<u64>,
+    /// Estimated memory of the whole batch
+    pub estimated_bytes: u64,
+    /// Estimated memory already used by loaded data
+    pub loaded_bytes: u64,
+}
+
+impl MemoryWarning {
+    /// Number of leading files that fit in the budget next to the loaded data
+    pub fn files_within(&self, budget_bytes: u64) -> usize {
+        let mut remaining = budget_bytes.saturating_sub(self.loaded_bytes);
+        self.file_sizes
+            .iter()
+            .take_while(|&&size| {
+                let cost = estimate_memory_for_gpx_bytes(size);
+                let fits = cost <= remaining;
+                remaining = remaining.saturating_sub(cost);
+                fits
+            })
+            .count()
+    }
+}
+
+/// Total physical memory, if it can be detected on this platform
+pub fn system_memory_bytes() -> Option<u64> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use sysinfo::{MemoryRefreshKind, RefreshKind, System};
+        let system = System::new_with_specifics(
+            RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+        );
+        Some(system.total_memory()).filter(|&total| total > 0)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        None
+    }
+}
+
+/// Default budget: half of the system memory, or a fixed size when unknown
+pub fn default_budget_bytes() -> u64 {
+    system_memory_bytes()
+        .map(|total| (total as f64 * DEFAULT_BUDGET_FRACTION) as u64)
+        .unwrap_or(FALLBACK_BUDGET_BYTES)
+}
+
+/// Resident memory of this process, if it can be queried on this platform
+pub fn process_memory_bytes() -> Option<u64> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+        let pid = sysinfo::get_current_pid().ok()?;
+        let mut system = System::new();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing().with_memory(),
+        );
+        system.process(pid).map(|process| process.memory())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        None
+    }
+}
+
+/// Time between two samples of the Debug section's memory readout
+pub const MEMORY_READOUT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Memory figures shown in the Debug section, refreshed every [`MEMORY_READOUT_INTERVAL`]
+#[derive(Default)]
+pub struct MemoryReadout {
+    /// When the figures were taken
+    pub sampled_at: Option<instant::Instant>,
+    /// Resident memory of the process, where it can be queried
+    pub process_bytes: Option<u64>,
+    /// Estimated memory of the spatial index
+    pub index_bytes: u64,
+}
+
+impl MemoryReadout {
+    /// Whether the figures are older than [`MEMORY_READOUT_INTERVAL`] at `now`
+    pub fn is_due(&self, now: instant::Instant) -> bool {
+        self.sampled_at
+            .is_none_or(|at| now.duration_since(at) >= MEMORY_READOUT_INTERVAL)
+    }
+}
+
+/// Size of a dropped or queued file, without reading it
+pub fn file_size(file: &DroppedFile) -> u64 {
+    if let Some(bytes) = &file.bytes {
+        return bytes.len() as u64;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &file.path
+        && let Ok(metadata) = std::fs::metadata(path)
+    {
+        return metadata.len();
+    }
+    0
+}
+
+/// Format a byte count for display, e.g. "1.5 GB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_within_budget_counts_leading_files_that_fit() {
+        let mb = 1024 * 1024;
+        let warning = MemoryWarning {
+            files: Vec::new(),
+            file_sizes: vec![10 * mb, 10 * mb, 100 * mb, 1],
+            estimated_bytes: 0,
+            loaded_bytes: estimate_memory_for_gpx_bytes(5 * mb),
+        };
+        let budget = estimate_memory_for_gpx_bytes(30 * mb);
+        // 5 MB loaded + 10 + 10 fits, the 100 MB file doesn't, and loading stops there
+        assert_eq!(warning.files_within(budget), 2);
+        assert_eq!(warning.files_within(0), 0);
+    }
+
+    #[test]
+    fn format_bytes_picks_a_readable_unit() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+}
@@ -7,62 +7,415 @@
 //! - Map navigation controls for accessibility
 //! - Responsive layout (sidebar from bottom on portrait displays)
 
+mod activity;
+mod annotations;
+mod announce;
+mod basemap;
+mod comparison;
+mod coords;
+mod detail;
+mod distance_grid;
+mod dpi;
+#[cfg(not(target_arch = "wasm32"))]
+mod elevation;
+mod extents;
+mod frame_budget;
+mod gpx_version;
+mod inertia;
+mod ingest;
+mod load_error;
+mod load_log;
+mod location;
+mod map_lock;
+mod memory_budget;
+mod motion;
+mod notifications;
+mod overlay;
+mod playback;
 mod plugin;
+mod recorder;
+mod render;
+mod roi;
+mod route_limit;
+#[cfg(not(target_arch = "wasm32"))]
+mod self_test;
 pub(crate) mod settings;
+mod shortcuts;
+mod sidecar;
+mod smoothing;
+mod split;
+mod startup;
 mod state;
 pub mod storage;
+mod style_rules;
+mod svg_export;
+mod symbols;
+mod synthetic;
+mod tile_health;
+mod timezone;
+mod track_groups;
+mod ui_memory;
 mod ui_panels;
 
-use crate::app::plugin::{RenderStats, TrackPlugin};
+use crate::app::annotations::TrackAnnotations;
+use crate::app::announce::LoadAnnouncer;
+use crate::app::basemap::{BaseMaps, GridPlugin};
+use crate::app::coords::CoordFormat;
+use crate::app::detail::BiasDebouncer;
+use crate::app::dpi::DpiWatcher;
+use crate::app::extents::ExtentsPlugin;
+use crate::app::inertia::PanInertia;
+use crate::app::load_log::LoadLog;
+use crate::app::location::LiveFix;
+use crate::app::map_lock::MapLock;
+use crate::app::plugin::{
+    ExternalPositionPlugin, FrameParams, HighlightPlugin, PlaybackPlugin, PositionPlugin,
+    RenderStats, SplitPreviewPlugin, TrackPlugin, WaypointPlugin,
+};
+use crate::app::recorder::RECORDING_PATH_PREFIX;
+use crate::app::render::{BlendMode, WidthMode, meters_to_points};
 use crate::app::settings::Settings;
-use crate::app::state::{AppState, SidebarTab, TilesProvider};
+use crate::app::shortcuts::ShortcutAction;
+use crate::app::startup::StartupTimings;
+use crate::app::state::{
+    AppState, FilePrecedence, SidebarTab, StartupMode, TilesProvider, UiSettings, clamp_zoom,
+    zoom_glide_step,
+};
+use crate::app::storage::{StorageBackend, StorageResult};
+use crate::app::tile_health::TileHealth;
+use crate::app::timezone::TimeZone;
+use crate::app::track_groups::TrackGrouping;
+use crate::app::ui_memory::UiMemory;
 use eframe::egui;
 use eframe_entrypoints::async_runtime::RwLock;
 use egui::DroppedFile;
+use large_track_lib::utils::{EARTH_SIZE_METERS, mercator_to_wgs84, meters_per_pixel};
 use std::sync::Arc;
-use walkers::{
-    HttpTiles, Map, MapMemory, TileId,
-    sources::{Attribution, OpenStreetMap, TileSource},
-};
-
-/// Custom OpenTopoMap tile source
-pub struct OpenTopoMap;
-
-impl TileSource for OpenTopoMap {
-    fn tile_url(&self, tile_id: TileId) -> String {
-        format!(
-            "http://tile.opentopomap.org/{}/{}/{}.png",
-            tile_id.zoom, tile_id.x, tile_id.y
-        )
-    }
-
-    fn attribution(&self) -> Attribution {
-        Attribution {
-            text: "© OpenTopoMap (CC-BY-SA)",
-            url: "https://opentopomap.org/",
-            logo_light: None,
-            logo_dark: None,
-        }
-    }
-
-    fn max_zoom(&self) -> u8 {
-        17 // OpenTopoMap has max zoom of 17
-    }
+use walkers::{Map, MapMemory};
+
+/// Runs first thing in `main`, while the process is still single-threaded
+pub fn before_start() {
+    // Later, with threads running, the OS may no longer tell
+    timezone::local_offset_at_startup();
+    // Safe mode skips what it can, down to a session ENABLE_PROFILING asks for
+    let safe_mode = eframe_entrypoints::parse_args::<Settings>().is_ok_and(|args| args.safe_mode);
+    eframe_entrypoints::profiling::set_autostart(!safe_mode);
 }
 
 /// Persisted settings (lightweight, no route data)
 #[derive(serde::Serialize, serde::Deserialize)]
 struct PersistedSettings {
-    /// UI settings
+    /// UI settings, flattened so the stored JSON keeps its layout
+    #[serde(flatten)]
+    ui: PersistedUiSettings,
+    /// Map position when this was written, restored in "keep last camera" mode
+    #[serde(default)]
+    camera: Option<PersistedCamera>,
+    /// Files expanded in the Tracks list, by canonical path
+    #[serde(default)]
+    expanded_file_paths: Vec<String>,
+    /// Line colors picked for files, by canonical path
+    #[serde(default)]
+    route_colors: std::collections::BTreeMap<String, [u8; 3]>,
+    /// Named settings profiles (see [`AppState::settings_profiles`])
+    #[serde(default)]
+    settings_profiles: std::collections::BTreeMap<String, PersistedUiSettings>,
+    /// File paths that were loaded (will need to be reloaded)
+    loaded_file_paths: Vec<String>,
+    /// File paths that were still pending or in flight when this was written
+    #[serde(default)]
+    pending_file_paths: Vec<String>,
+}
+
+/// The [`UiSettings`] part of [`PersistedSettings`], and what a settings profile is saved as
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PersistedUiSettings {
     line_width: f32,
+    #[serde(default)]
+    width_mode: String,
+    #[serde(default = "default_line_width_m")]
+    line_width_m: f32,
     show_outline: bool,
+    #[serde(default)]
+    blend_mode: String,
+    #[serde(default)]
+    offset_stacked_tracks: bool,
+    #[serde(default = "default_stack_offset")]
+    stack_offset: f32,
     bias: f64,
     sidebar_open: bool,
+    #[serde(default)]
+    map_locked: bool,
     active_tab: String,
     tiles_provider: String,
-    show_profiling: bool,
-    /// File paths that were loaded (will need to be reloaded)
-    loaded_file_paths: Vec<String>,
+    /// Only saved by builds that can profile, so other builds don't carry it over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    show_profiling: Option<bool>,
+    #[serde(default = "default_over_zoom_levels")]
+    over_zoom_levels: u8,
+    #[serde(default = "default_chunking_aggressiveness")]
+    chunking_aggressiveness: f64,
+    #[serde(default)]
+    show_center_crosshair: bool,
+    #[serde(default)]
+    show_distance_grid: bool,
+    #[serde(default)]
+    show_waypoints: bool,
+    #[serde(default)]
+    show_file_extents: bool,
+    #[serde(default)]
+    show_legend: bool,
+    #[serde(default)]
+    track_grouping: String,
+    #[serde(default)]
+    track_sidecars: bool,
+    #[serde(default = "default_true")]
+    boundary_context: bool,
+    #[serde(default)]
+    show_memory_readout: bool,
+    #[serde(default = "default_true")]
+    show_tracks: bool,
+    #[serde(default = "default_autosave_interval_secs")]
+    autosave_interval_secs: u64,
+    #[serde(default = "default_stats_smoothing_secs")]
+    stats_smoothing_secs: f32,
+    #[serde(default = "default_climb_threshold_m")]
+    climb_threshold_m: f32,
+    #[serde(default)]
+    startup_mode: String,
+    #[serde(default)]
+    file_precedence: String,
+    #[serde(default)]
+    auto_tile_fallback: bool,
+    /// Provider replaced by an automatic fallback, shown again at the next start
+    #[serde(default)]
+    preferred_tiles_provider: Option<String>,
+    #[serde(default = "default_true")]
+    pan_inertia: bool,
+    #[serde(default = "default_pan_friction")]
+    pan_friction: f32,
+    #[serde(default = "default_reduce_motion")]
+    reduce_motion: bool,
+    #[serde(default)]
+    theme: String,
+    #[serde(default)]
+    coord_format: String,
+    #[serde(default)]
+    time_zone: String,
+    #[serde(default)]
+    utc_offset_minutes: i32,
+    #[serde(default)]
+    keep_sharp_turns: bool,
+    #[serde(default)]
+    full_detail_selection: bool,
+    #[serde(default)]
+    style_rules: Vec<crate::app::style_rules::StyleRule>,
+    #[serde(default = "default_frame_budget_ms")]
+    frame_budget_ms: f32,
+    #[serde(default)]
+    zoom_on_select: bool,
+}
+
+impl PersistedUiSettings {
+    /// The persisted form of `ui`
+    fn new(ui: &UiSettings) -> Self {
+        Self {
+            line_width: ui.line_width,
+            width_mode: format!("{:?}", ui.width_mode),
+            line_width_m: ui.line_width_m,
+            show_outline: ui.show_outline,
+            blend_mode: format!("{:?}", ui.blend_mode),
+            offset_stacked_tracks: ui.offset_stacked_tracks,
+            stack_offset: ui.stack_offset,
+            bias: ui.bias,
+            sidebar_open: ui.sidebar_open,
+            map_locked: ui.map_locked,
+            active_tab: format!("{:?}", ui.active_tab),
+            tiles_provider: format!("{:?}", ui.tiles_provider),
+            show_profiling: eframe_entrypoints::profiling::is_available()
+                .then_some(ui.show_profiling),
+            over_zoom_levels: ui.over_zoom_levels,
+            chunking_aggressiveness: ui.chunking_aggressiveness,
+            show_center_crosshair: ui.show_center_crosshair,
+            show_distance_grid: ui.show_distance_grid,
+            show_waypoints: ui.show_waypoints,
+            show_file_extents: ui.show_file_extents,
+            show_legend: ui.show_legend,
+            track_grouping: format!("{:?}", ui.track_grouping),
+            track_sidecars: ui.track_sidecars,
+            boundary_context: ui.boundary_context,
+            show_memory_readout: ui.show_memory_readout,
+            show_tracks: ui.show_tracks,
+            autosave_interval_secs: ui.autosave_interval_secs,
+            stats_smoothing_secs: ui.stats_smoothing_secs,
+            climb_threshold_m: ui.climb_threshold_m,
+            startup_mode: format!("{:?}", ui.startup_mode),
+            file_precedence: format!("{:?}", ui.file_precedence),
+            auto_tile_fallback: ui.auto_tile_fallback,
+            preferred_tiles_provider: ui.preferred_tiles_provider.map(|p| format!("{:?}", p)),
+            pan_inertia: ui.pan_inertia,
+            pan_friction: ui.pan_friction,
+            reduce_motion: ui.reduce_motion,
+            theme: format!("{:?}", ui.theme),
+            coord_format: format!("{:?}", ui.coord_format),
+            time_zone: format!("{:?}", ui.time_zone),
+            utc_offset_minutes: ui.utc_offset_minutes,
+            keep_sharp_turns: ui.keep_sharp_turns,
+            full_detail_selection: ui.full_detail_selection,
+            style_rules: ui.style_rules.clone(),
+            frame_budget_ms: ui.frame_budget_ms,
+            zoom_on_select: ui.zoom_on_select,
+        }
+    }
+
+    /// The settings to run with; `lock_map` replaces the saved map lock
+    fn into_ui_settings(self, lock_map: Option<bool>) -> UiSettings {
+        UiSettings {
+            line_width: self.line_width,
+            width_mode: WidthMode::from_name(&self.width_mode),
+            line_width_m: self.line_width_m,
+            show_outline: self.show_outline,
+            blend_mode: BlendMode::from_name(&self.blend_mode),
+            offset_stacked_tracks: self.offset_stacked_tracks,
+            stack_offset: self.stack_offset,
+            bias: self.bias,
+            // A provider swapped out by an automatic fallback gets another try
+            tiles_provider: parse_tiles_provider(
+                self.preferred_tiles_provider
+                    .as_deref()
+                    .unwrap_or(&self.tiles_provider),
+            ),
+            sidebar_open: self.sidebar_open,
+            map_locked: lock_map.unwrap_or(self.map_locked),
+            active_tab: match self.active_tab.as_str() {
+                "Settings" => SidebarTab::Settings,
+                _ => SidebarTab::Tracks,
+            },
+            // A session recording since startup (ENABLE_PROFILING) shows its controls
+            show_profiling: eframe_entrypoints::profiling::is_available()
+                && (self.show_profiling.unwrap_or(false)
+                    || eframe_entrypoints::profiling::is_profiling_enabled()),
+            over_zoom_levels: self.over_zoom_levels,
+            chunking_aggressiveness: self.chunking_aggressiveness,
+            show_center_crosshair: self.show_center_crosshair,
+            show_distance_grid: self.show_distance_grid,
+            show_waypoints: self.show_waypoints,
+            show_file_extents: self.show_file_extents,
+            show_legend: self.show_legend,
+            track_grouping: TrackGrouping::from_name(&self.track_grouping),
+            track_sidecars: self.track_sidecars,
+            boundary_context: self.boundary_context,
+            show_memory_readout: self.show_memory_readout,
+            show_tracks: self.show_tracks,
+            autosave_interval_secs: self.autosave_interval_secs,
+            stats_smoothing_secs: self.stats_smoothing_secs,
+            climb_threshold_m: self.climb_threshold_m,
+            startup_mode: StartupMode::from_name(&self.startup_mode),
+            file_precedence: FilePrecedence::from_name(&self.file_precedence),
+            auto_tile_fallback: self.auto_tile_fallback,
+            preferred_tiles_provider: self
+                .preferred_tiles_provider
+                .as_deref()
+                .map(parse_tiles_provider),
+            pan_inertia: self.pan_inertia,
+            pan_friction: self.pan_friction,
+            reduce_motion: self.reduce_motion,
+            theme: parse_theme(&self.theme),
+            coord_format: CoordFormat::from_name(&self.coord_format),
+            time_zone: TimeZone::from_name(&self.time_zone),
+            utc_offset_minutes: self.utc_offset_minutes,
+            keep_sharp_turns: self.keep_sharp_turns,
+            full_detail_selection: self.full_detail_selection,
+            style_rules: self.style_rules,
+            frame_budget_ms: self.frame_budget_ms,
+            zoom_on_select: self.zoom_on_select,
+        }
+    }
+}
+
+/// Map center and zoom level
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct PersistedCamera {
+    lat: f64,
+    lon: f64,
+    zoom: f64,
+}
+
+fn default_over_zoom_levels() -> u8 {
+    crate::app::state::DEFAULT_OVER_ZOOM_LEVELS
+}
+
+fn default_chunking_aggressiveness() -> f64 {
+    large_track_lib::DEFAULT_CHUNKING_AGGRESSIVENESS
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_autosave_interval_secs() -> u64 {
+    crate::app::state::DEFAULT_AUTOSAVE_INTERVAL_SECS
+}
+
+fn default_stats_smoothing_secs() -> f32 {
+    crate::app::smoothing::DEFAULT_STATS_SMOOTHING_SECS
+}
+
+fn default_climb_threshold_m() -> f32 {
+    large_track_lib::DEFAULT_CLIMB_THRESHOLD_M as f32
+}
+
+fn default_line_width_m() -> f32 {
+    crate::app::render::DEFAULT_LINE_WIDTH_M
+}
+
+fn default_stack_offset() -> f32 {
+    crate::app::render::DEFAULT_STACK_OFFSET
+}
+
+fn default_frame_budget_ms() -> f32 {
+    crate::app::frame_budget::DEFAULT_FRAME_BUDGET_MS
+}
+
+fn default_pan_friction() -> f32 {
+    crate::app::inertia::DEFAULT_PAN_FRICTION
+}
+
+fn default_reduce_motion() -> bool {
+    crate::app::motion::system_prefers_reduced_motion()
+}
+
+/// Storage key of the serialized [`PersistedSettings`]
+const PERSISTED_SETTINGS_KEY: &str = "persisted_settings";
+
+/// Storage key present while a session is running; cleared on clean exit
+const SESSION_RUNNING_KEY: &str = "session_running";
+
+/// Storage key of the GPX text of the current or last unsaved recording
+const RECORDING_AUTOSAVE_KEY: &str = "recording_autosave";
+
+/// How often to check for finished files while a load is waiting on workers
+const LOADING_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// How far one arrow-key press pans the map, in screen pixels
+const PAN_STEP_PIXELS: f64 = 100.0;
+
+/// Tile provider from its persisted (`Debug`) name
+fn parse_tiles_provider(name: &str) -> TilesProvider {
+    match name {
+        "OpenTopoMap" => TilesProvider::OpenTopoMap,
+        "None" => TilesProvider::None,
+        _ => TilesProvider::OpenStreetMap,
+    }
+}
+
+/// Theme preference from its persisted (`Debug`) name
+fn parse_theme(name: &str) -> egui::ThemePreference {
+    match name {
+        "Dark" => egui::ThemePreference::Dark,
+        "Light" => egui::ThemePreference::Light,
+        _ => egui::ThemePreference::System,
+    }
 }
 
 /// Main application structure
@@ -70,180 +423,389 @@ pub struct LargeTrackViewerApp {
     /// Application state (routes, UI settings, etc.)
     state: AppState,
 
-    /// Map tiles provider (OpenStreetMap)
-    tiles_osm: HttpTiles,
+    /// Map tile sources, created when their provider is first shown
+    base_maps: BaseMaps,
 
-    /// Map tiles provider (OpenTopoMap)
-    tiles_otm: HttpTiles,
+    /// Background check of the shown tile provider
+    tile_health: TileHealth,
 
     /// Map state (camera position, zoom, etc.)
     map_memory: MapMemory,
 
+    /// Glide of the map after a drag
+    pan_inertia: PanInertia,
+    /// Holds back the one-finger drags a locked map ignores
+    map_lock: MapLock,
+    /// Provider whose zoom limit was applied last frame, to notice switches
+    zoom_limit_provider: TilesProvider,
+    /// Whether the zoom is gliding down to a new provider's limit
+    zoom_glide: bool,
+    /// Notices the window moving to a screen with another scale factor
+    dpi_watcher: DpiWatcher,
+    /// Screen reader announcements of loading progress
+    load_announcer: LoadAnnouncer,
+    /// Map position that was right-clicked to open the context menu
+    context_menu_position: Option<walkers::Position>,
+    /// Text of the context menu's go-to field
+    goto_text: String,
+    /// Whether the go-to text last submitted could not be read as a position
+    goto_error: bool,
+    /// Route last selected by clicking its extent, to cycle through overlapping ones
+    last_extent_pick: Option<usize>,
+
     /// Show help overlay
     show_help: bool,
 
     /// Shared render statistics (updated by plugin each frame)
     render_stats: Arc<RwLock<RenderStats>>,
 
+    /// Track layer of the map, kept across frames for its buffers
+    track_plugin: TrackPlugin,
+
     /// Whether we've finished restoring persisted state
     restored_persisted_state: bool,
 
     /// Whether we've started initial parallel load
     started_initial_parallel_load: bool,
+
+    /// Platform storage backend (file on native, localStorage on web), if available
+    storage_backend: Option<Box<dyn StorageBackend>>,
+
+    /// Hash of the last settings JSON written, used to skip unchanged autosaves
+    last_persisted_hash: Option<u64>,
+
+    /// Context whose memory (collapsed sections, scroll positions) is autosaved
+    egui_ctx: egui::Context,
+
+    /// egui's memory as last autosaved to the platform backend
+    ui_memory: UiMemory,
+
+    /// When the session was last autosaved
+    last_autosave: instant::Instant,
+
+    /// Whether a recording was running last frame, to catch the moment it stops
+    was_recording: bool,
+
+    /// Set by `--ignore-persisted`: settings are never written, keeping the persisted ones
+    read_only_settings: bool,
 }
 
 impl LargeTrackViewerApp {
+    /// Show a position from an outside source, e.g. the GPS feed of an app embedding the viewer
+    ///
+    /// `position` is `(latitude, longitude)` in degrees and is drawn as a
+    /// pulsing marker until replaced or cleared with `None`. It doesn't
+    /// depend on loaded tracks or on the built-in live position setting.
+    // For embedders: the bundled binary never calls it
+    #[allow(dead_code)]
+    pub fn set_live_position(&mut self, position: Option<(f64, f64)>) {
+        self.state
+            .external_position
+            .set(position.map(|(lat, lon)| walkers::lat_lon(lat, lon)));
+    }
+
+    /// Recenter the map on each new position given to [`Self::set_live_position`]
+    #[allow(dead_code)]
+    pub fn set_follow_live_position(&mut self, follow: bool) {
+        self.state.external_position.follow = follow;
+    }
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let cli_args = Settings::from_cli();
+        #[cfg(not(target_arch = "wasm32"))]
+        if cli_args.self_test {
+            std::process::exit(if self_test::run() { 0 } else { 1 });
+        }
+        // The platform backend is opened once and reused for autosaves and the session marker
+        Self::with_settings(cc, cli_args, crate::app::storage::platform_backend())
+    }
+
+    /// Build the app from already parsed arguments, saving to `storage_backend`
+    ///
+    /// Tests start it with `--no-basemap` and no backend, so it neither
+    /// contacts a tile server nor touches the user's saved session.
+    fn with_settings(
+        cc: &eframe::CreationContext<'_>,
+        cli_args: Settings,
+        storage_backend: Option<Box<dyn StorageBackend>>,
+    ) -> Self {
+        let mut startup = StartupTimings::default();
+        // Loaders for the SVG attribution logos
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
+        // A leftover "running" marker means the previous session never reached `on_exit`
+        let unclean_shutdown = storage_backend
+            .as_deref()
+            .is_some_and(|backend| matches!(backend.get_string(SESSION_RUNNING_KEY), Ok(Some(_))));
+        if unclean_shutdown {
+            tracing::warn!("Previous session did not shut down cleanly");
+        }
+        if cli_args.safe_mode {
+            tracing::warn!(
+                "Safe mode: persisted files and settings are ignored, map tiles are off \
+                 and nothing is saved this session"
+            );
+        }
+        if cli_args.precompute_lod {
+            tracing::warn!(
+                "--precompute-lod: every track is simplified at all LOD levels while loading; \
+                 expect slower loads and several times the index memory"
+            );
+        }
 
         // Try to restore persisted settings (not route data)
-        let mut state = if !cli_args.ignore_persisted {
-            if let Some(storage) = cc.storage {
-                Self::load_persisted_settings(storage, &cli_args)
-            } else {
-                AppState::new(&cli_args)
-            }
+        let persisted = if !cli_args.ignore_persisted {
+            startup.stage("settings", || {
+                Self::load_persisted_settings(cc.storage, storage_backend.as_deref())
+            })
         } else {
             tracing::info!("Ignoring persisted state (--ignore-persisted flag)");
-            AppState::new(&cli_args)
+            None
         };
 
-        // Add any CLI-specified files to pending (they take priority)
-        for file_path in &cli_args.gpx_files {
-            state.queue_file(DroppedFile {
-                name: file_path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_str()
-                    .unwrap_or_default()
-                    .to_owned(),
-                path: Some(file_path.clone()),
-                ..Default::default()
+        let (mut state, camera) = startup.stage("state", || {
+            Self::startup_state(persisted, &cli_args, unclean_shutdown)
+        });
+        if let Some(backend) = storage_backend.as_deref() {
+            startup.stage("annotations", || {
+                state.annotations = TrackAnnotations::load(backend);
+                if !cli_args.ignore_persisted {
+                    UiMemory::restore(&cc.egui_ctx, backend);
+                }
             });
         }
 
-        // Create tiles providers
-        let tiles_osm = HttpTiles::new(OpenStreetMap, cc.egui_ctx.clone());
-        let tiles_otm = HttpTiles::new(OpenTopoMap, cc.egui_ctx.clone());
+        if let Some(backend) = storage_backend.as_deref()
+            && let Err(e) = backend.set_string(SESSION_RUNNING_KEY, "1")
+        {
+            tracing::warn!("Failed to write session marker: {:?}", e);
+        }
 
-        // Create map memory with default settings
-        let map_memory = MapMemory::default();
+        // A recording that was never saved or discarded (possibly cut short by a crash),
+        // left in storage in safe mode in case it is what crashes
+        if !cli_args.safe_mode
+            && let Some(backend) = storage_backend.as_deref()
+            && let Ok(Some(text)) = backend.get_string(RECORDING_AUTOSAVE_KEY)
+        {
+            match gpx::read(text.as_bytes()) {
+                Ok(gpx) => {
+                    let name = gpx
+                        .tracks
+                        .first()
+                        .and_then(|track| track.name.clone())
+                        .unwrap_or_else(|| "Recovered recording".to_string());
+                    let path =
+                        std::path::PathBuf::from(format!("{}{}", RECORDING_PATH_PREFIX, name));
+                    tracing::info!("Recovered unsaved recording {:?}", name);
+                    state.index_recording(path.clone(), gpx);
+                    state.finished_recording = Some(path);
+                }
+                Err(e) => tracing::warn!("Discarding unreadable autosaved recording: {}", e),
+            }
+        }
+
+        if cli_args.no_basemap {
+            state.ui_settings.tiles_provider = TilesProvider::None;
+        }
+        if cli_args.safe_mode {
+            state.notify_warn("Safe mode: saved files and settings were not loaded");
+        }
+        // Tile sources are only created once shown, so tracks-only mode makes no requests
+        let base_maps = startup.stage("tile sources", || {
+            BaseMaps::new(cc.egui_ctx.clone(), cli_args.tile_concurrency)
+        });
+
+        // Create map memory with default settings, or where the last session left it
+        let mut map_memory = MapMemory::default();
+        if let Some(camera) = camera {
+            map_memory.center_at(walkers::lat_lon(camera.lat, camera.lon));
+            let _ = map_memory.set_zoom(state.clamp_map_zoom(camera.zoom));
+        }
 
         tracing::info!(
             "Initialized with {} files to load",
             state.file_loader.pending_files.len()
         );
 
+        let zoom_limit_provider = state.ui_settings.tiles_provider;
+        let render_stats = Arc::new(RwLock::new(RenderStats::default()));
+        let track_plugin = startup.stage("track plugin", || {
+            TrackPlugin::new(
+                state.route_collection.clone(),
+                render_stats.clone(),
+                state.selected_route.clone(),
+                state.roi.clone(),
+            )
+        });
+        // The first frame finishes the summary (see `update`)
+        state.startup = startup;
+
         Self {
             state,
-            tiles_osm,
-            tiles_otm,
+            base_maps,
+            tile_health: TileHealth::new(),
             map_memory,
+            pan_inertia: PanInertia::default(),
+            map_lock: MapLock::default(),
+            zoom_limit_provider,
+            zoom_glide: false,
+            dpi_watcher: DpiWatcher::default(),
+            load_announcer: LoadAnnouncer::default(),
+            context_menu_position: None,
+            goto_text: String::new(),
+            goto_error: false,
+            last_extent_pick: None,
             show_help: false,
-            render_stats: Arc::new(RwLock::new(RenderStats::default())),
+            render_stats,
+            track_plugin,
             restored_persisted_state: false,
             started_initial_parallel_load: false,
+            storage_backend,
+            last_persisted_hash: None,
+            egui_ctx: cc.egui_ctx.clone(),
+            ui_memory: UiMemory::default(),
+            last_autosave: instant::Instant::now(),
+            was_recording: false,
+            read_only_settings: cli_args.ignore_persisted,
+        }
+    }
+
+    /// Build the initial state according to the startup mode
+    ///
+    /// Returns the camera to restore, if the mode keeps it. CLI files are
+    /// queued in every mode.
+    fn startup_state(
+        persisted: Option<PersistedSettings>,
+        cli_args: &Settings,
+        unclean_shutdown: bool,
+    ) -> (AppState, Option<PersistedCamera>) {
+        let mut camera = None;
+        let mut state = match persisted {
+            Some(mut settings) => {
+                let mode = if cli_args.fresh {
+                    StartupMode::StartEmpty
+                } else {
+                    StartupMode::from_name(&settings.ui.startup_mode)
+                };
+                let skipped_files: Vec<String> = if mode == StartupMode::StartEmpty {
+                    let mut files = std::mem::take(&mut settings.loaded_file_paths);
+                    files.append(&mut settings.pending_file_paths);
+                    files
+                } else {
+                    Vec::new()
+                };
+                if mode == StartupMode::RestoreFilesKeepCamera {
+                    camera = settings.camera;
+                }
+                let expanded = std::mem::take(&mut settings.expanded_file_paths);
+                let route_colors = std::mem::take(&mut settings.route_colors);
+                let profiles = std::mem::take(&mut settings.settings_profiles);
+
+                // After a crash, files that were still importing are offered instead of retried blindly
+                let interrupted = if unclean_shutdown {
+                    std::mem::take(&mut settings.pending_file_paths)
+                } else {
+                    Vec::new()
+                };
+                let mut state = Self::state_from_persisted_settings(settings, cli_args);
+                state.interrupted_import = interrupted
+                    .into_iter()
+                    .map(std::path::PathBuf::from)
+                    .filter(|path| path.exists())
+                    .collect();
+                state.restore_expanded_files(&expanded);
+                state.restore_route_colors(&route_colors);
+                state.settings_profiles = profiles
+                    .into_iter()
+                    .map(|(name, profile)| (name, profile.into_ui_settings(None)))
+                    .collect();
+                // The choice in the settings stays; --fresh only affects this start
+                if cli_args.fresh {
+                    state.held_file_paths = skipped_files;
+                }
+                // Without a saved camera there is nothing to keep; fit as usual
+                state.keep_startup_camera =
+                    camera.is_some() && !state.file_loader.pending_files.is_empty();
+                state
+            }
+            None => AppState::new(cli_args),
+        };
+
+        // Add any CLI-specified files to pending (they take priority)
+        let cli_file = |file_path: &std::path::PathBuf| DroppedFile {
+            name: file_path
+                .file_name()
+                .unwrap_or_default()
+                .to_str()
+                .unwrap_or_default()
+                .to_owned(),
+            path: Some(file_path.clone()),
+            ..Default::default()
+        };
+        for file_path in &cli_args.gpx_files {
+            state.queue_file(cli_file(file_path));
+        }
+        for file_path in &cli_args.temporary_files {
+            state.queue_temporary_file(cli_file(file_path));
         }
+        (state, camera)
     }
 
     /// Load persisted settings from storage (fast, no route data)
     ///
     /// Behavior:
     /// 1. Try eframe's provided `storage` first (this covers most desktop & web runner cases).
-    /// 2. If not present there, try the platform backend:
-    ///    - On web: use the browser localStorage backend (direct default backend function).
-    ///    - On native: use the file-backed backend (returns Result<Box<dyn StorageBackend>, ...>).
-    fn load_persisted_settings(storage: &dyn eframe::Storage, cli_args: &Settings) -> AppState {
+    /// 2. If not present there, try the platform backend (browser localStorage on web,
+    ///    the JSON file backend on native).
+    fn load_persisted_settings(
+        storage: Option<&dyn eframe::Storage>,
+        backend: Option<&dyn StorageBackend>,
+    ) -> Option<PersistedSettings> {
         // 1) Try eframe storage first
-        if let Some(json) = storage.get_string("persisted_settings")
+        if let Some(storage) = storage
+            && let Some(json) = storage.get_string(PERSISTED_SETTINGS_KEY)
             && !json.is_empty()
             && let Ok(settings) = serde_json::from_str::<PersistedSettings>(&json)
         {
             tracing::info!("Restored settings from eframe storage, will reload files");
-            return Self::state_from_persisted_settings(settings, cli_args);
+            return Some(settings);
         }
 
         // 2) Try platform default storage backend (use free JSON helper to read structured settings)
-        #[cfg(target_arch = "wasm32")]
-        {
-            // On web the default backend returns a concrete backend directly; use it and attempt to load JSON.
-            let backend = crate::app::storage::default_storage_backend();
+        if let Some(backend) = backend {
             match crate::app::storage::load_json_backend::<PersistedSettings>(
-                backend.as_ref(),
-                "persisted_settings",
+                backend,
+                PERSISTED_SETTINGS_KEY,
             ) {
                 Ok(Some(settings)) => {
-                    tracing::info!(
-                        "Restored settings from platform backend (web localStorage), will reload files"
-                    );
-                    return Self::state_from_persisted_settings(settings, cli_args);
+                    tracing::info!("Restored settings from platform backend, will reload files");
+                    return Some(settings);
                 }
                 Ok(None) => {}
-                Err(e) => {
-                    tracing::debug!("Error reading platform persisted settings (web): {:?}", e)
-                }
-            }
-        }
-
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            // On native the default backend may return a Result (file backend). Handle initialization errors.
-            match crate::app::storage::default_storage_backend() {
-                Ok(backend_box) => {
-                    let backend = backend_box.as_ref();
-                    match crate::app::storage::load_json_backend::<PersistedSettings>(
-                        backend,
-                        "persisted_settings",
-                    ) {
-                        Ok(Some(settings)) => {
-                            tracing::info!(
-                                "Restored settings from platform backend (file storage), will reload files"
-                            );
-                            return Self::state_from_persisted_settings(settings, cli_args);
-                        }
-                        Ok(None) => {}
-                        Err(e) => tracing::debug!(
-                            "Error reading platform persisted settings (file): {:?}",
-                            e
-                        ),
-                    }
-                }
-                Err(e) => tracing::debug!("Platform storage backend not available: {:?}", e),
+                Err(e) => tracing::debug!("Error reading platform persisted settings: {:?}", e),
             }
         }
 
         tracing::info!("No persisted settings found, starting fresh");
-        AppState::new(cli_args)
+        None
     }
 
     /// Create AppState from persisted settings
     fn state_from_persisted_settings(settings: PersistedSettings, cli_args: &Settings) -> AppState {
-        use crate::app::state::{FileLoader, UiSettings};
-        use large_track_lib::{Config, RouteCollection};
-
-        let ui_settings = UiSettings {
-            line_width: settings.line_width,
-            show_outline: settings.show_outline,
-            bias: settings.bias,
-            tiles_provider: match settings.tiles_provider.as_str() {
-                "OpenTopoMap" => TilesProvider::OpenTopoMap,
-                _ => TilesProvider::OpenStreetMap,
-            },
-            sidebar_open: settings.sidebar_open,
-            active_tab: match settings.active_tab.as_str() {
-                "Settings" => SidebarTab::Settings,
-                _ => SidebarTab::Tracks,
-            },
-            show_profiling: settings.show_profiling,
+        use crate::app::state::FileLoader;
+        use large_track_lib::{
+            Config, DEFAULT_MAX_SEGMENT_POINTS, DEFAULT_SHARP_TURN_DEGREES, RouteCollection,
         };
 
+        let ui_settings = settings.ui.into_ui_settings(cli_args.lock_map);
+
         // Queue files for reloading (persisted + CLI), deduplicating by canonical path
         let mut pending_files: Vec<DroppedFile> = Vec::new();
         let mut seen_paths: std::collections::HashSet<std::path::PathBuf> =
             std::collections::HashSet::new();
 
         // Helper to add files with deduplication
-        let mut add_file = |path: std::path::PathBuf| {
+        let add_file = |path: std::path::PathBuf| {
             if path.exists() {
                 // Use canonical path to detect duplicates regardless of relative/absolute paths
                 let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
@@ -261,19 +823,33 @@ impl LargeTrackViewerApp {
             }
         };
 
-        // Add persisted files first
-        for path_str in &settings.loaded_file_paths {
-            add_file(std::path::PathBuf::from(path_str));
-        }
-
-        // Add CLI files (will be deduplicated if already in persisted)
-        for path in &cli_args.gpx_files {
-            add_file(path.clone());
+        // Persisted files are completed loads, then whatever was still importing
+        let persisted_files = settings
+            .loaded_file_paths
+            .iter()
+            .chain(&settings.pending_file_paths)
+            .map(std::path::PathBuf::from);
+        let cli_files = cli_args.gpx_files.iter().cloned();
+
+        // Whichever group comes first keeps the files shared with the other
+        match ui_settings.file_precedence {
+            FilePrecedence::PersistedFirst => persisted_files.chain(cli_files).for_each(add_file),
+            FilePrecedence::CliFirst => cli_files.chain(persisted_files).for_each(add_file),
         }
 
         let config = Config {
-            bias: settings.bias,
+            bias: ui_settings.bias,
             max_points_per_node: cli_args.max_points_per_node,
+            precompute_lod: cli_args.precompute_lod,
+            chunking_aggressiveness: ui_settings.chunking_aggressiveness,
+            boundary_context: ui_settings.boundary_context,
+            // rayon jobs only run in the background on native
+            progressive_refinement: cfg!(not(target_arch = "wasm32")),
+            sharp_turn_threshold: ui_settings
+                .keep_sharp_turns
+                .then_some(DEFAULT_SHARP_TURN_DEGREES),
+            elevation_extrema_prominence: None,
+            max_segment_points: DEFAULT_MAX_SEGMENT_POINTS,
             reference_pixel_viewport: geo::Rect::new(
                 geo::Coord { x: 0.0, y: 0.0 },
                 geo::Coord {
@@ -283,94 +859,604 @@ impl LargeTrackViewerApp {
             ),
         };
 
-        let file_loader = FileLoader {
-            pending_files,
-            errors: Vec::new(),
-            loaded_files: Vec::new(),
-            // Use a standard mutex for the results queue and an atomic counter for totals.
-            // This simplifies concurrency: workers push into the mutex-protected Vec and
-            // update the atomic counter; the UI thread can lock briefly to pop results.
-            parallel_load_results: Arc::new(std::sync::Mutex::new(Vec::new())),
-            parallel_total_files: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
-        };
+        let file_loader = FileLoader::new(pending_files, cli_args);
+        let bias_debouncer = BiasDebouncer::new(ui_settings.bias);
 
         AppState {
             route_collection: Arc::new(RwLock::new(RouteCollection::new(config))),
             ui_settings,
             file_loader,
             stats: Default::default(),
+            frame_budget: Default::default(),
             // Initialize the shared async RwLock used for selection throughout the app.
             selected_route: Arc::new(RwLock::new(None)),
-            show_wheel_warning: false,
-            wheel_warning_shown_at: None,
+            roi: Arc::new(RwLock::new(Default::default())),
+            comparison: Default::default(),
+            playback: Default::default(),
+            split: Default::default(),
+            annotations: Default::default(),
+            annotation_keys: Default::default(),
+            track_filter: String::new(),
+            new_tag: String::new(),
+            settings_profiles: Default::default(),
+            new_profile_name: String::new(),
+            live_location: Default::default(),
+            external_position: Default::default(),
+            recorder: None,
+            finished_recording: None,
+            recording_save: None,
             pending_fit_bounds: false,
+            pending_fit_route: None,
+            legend_hovered: None,
+            map_rect: None,
+            fit_after_load: false,
+            keep_startup_camera: false,
+            tile_failure: None,
+            expanded_files: Default::default(),
+            route_colors: Default::default(),
+            held_file_paths: Vec::new(),
+            results_per_frame: cli_args.results_per_frame,
+            load_log: LoadLog::new(cli_args.load_log_every),
+            activity: Default::default(),
             pending_reload: false,
+            interrupted_import: Vec::new(),
+            session_dirty: false,
+            notifications: Default::default(),
+            load_failures: 0,
+            save_requested: false,
+            last_saved_at: None,
+            save_error: None,
+            shortcuts: Default::default(),
+            memory_budget_bytes: crate::app::state::memory_budget_bytes(cli_args),
+            memory_warning: None,
+            memory_check_confirmed: false,
+            max_routes: cli_args.max_routes,
+            route_limit_warning: None,
+            route_limit_confirmed: false,
+            synthetic_params: Default::default(),
+            startup: StartupTimings::default(),
+            local_offset: timezone::local_offset_at_startup(),
+            bias_debouncer,
+        }
+    }
+
+    /// Snapshot the current settings and file list (see [`AppState::persisted_file_paths`])
+    fn persisted_settings(&self) -> PersistedSettings {
+        let (loaded_file_paths, pending_file_paths) = self.state.persisted_file_paths();
+
+        // Not detached means still at the map's default position
+        let center = self
+            .map_memory
+            .detached()
+            .unwrap_or(walkers::lat_lon(0.0, 0.0));
+
+        PersistedSettings {
+            ui: PersistedUiSettings::new(&self.state.ui_settings),
+            camera: Some(PersistedCamera {
+                lat: center.y(),
+                lon: center.x(),
+                zoom: self.map_memory.zoom(),
+            }),
+            expanded_file_paths: self.state.expanded_file_paths(),
+            route_colors: self.state.route_color_paths(),
+            loaded_file_paths,
+            pending_file_paths,
+            settings_profiles: self
+                .state
+                .settings_profiles
+                .iter()
+                .map(|(name, ui)| (name.clone(), PersistedUiSettings::new(ui)))
+                .collect(),
+        }
+    }
+
+    /// Persist settings (no route data - fast), as `save()` does, and record when
+    ///
+    /// Unless `force` is set, writes are skipped when nothing changed since the
+    /// last one.
+    fn persist_now(&mut self, storage: Option<&mut dyn eframe::Storage>, force: bool) {
+        use std::hash::{Hash, Hasher};
+
+        self.last_autosave = instant::Instant::now();
+        if self.read_only_settings {
+            tracing::debug!("Not saving settings (--ignore-persisted)");
+            return;
         }
+
+        let annotations = match self.storage_backend.as_deref() {
+            Some(backend) => self
+                .state
+                .annotations
+                .save_if_dirty(backend)
+                .and(self.ui_memory.save_if_changed(&self.egui_ctx, backend)),
+            None => Ok(()),
+        };
+
+        let settings = self.persisted_settings();
+        let Ok(json) = serde_json::to_string(&settings) else {
+            return;
+        };
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        json.hash(&mut hasher);
+        let hash = hasher.finish();
+        let saved = if !force && self.last_persisted_hash == Some(hash) {
+            Ok(())
+        } else {
+            let saved =
+                self.persist_settings(self.storage_backend.as_deref(), storage, &settings, json);
+            // A failed write leaves the hash stale, so the next autosave tries again
+            if saved.is_ok() {
+                self.last_persisted_hash = Some(hash);
+                self.state.last_saved_at = Some(instant::Instant::now());
+            }
+            saved
+        };
+        self.state.record_save_result(annotations.and(saved));
+    }
+
+    /// Write settings to eframe storage, when given, and to the platform backend
+    fn persist_settings(
+        &self,
+        backend: Option<&dyn StorageBackend>,
+        storage: Option<&mut dyn eframe::Storage>,
+        settings: &PersistedSettings,
+        json: String,
+    ) -> StorageResult<()> {
+        if let Some(storage) = storage {
+            storage.set_string(PERSISTED_SETTINGS_KEY, json);
+            tracing::debug!("Saved settings to eframe storage");
+        }
+
+        match backend {
+            Some(backend) => {
+                crate::app::storage::save_json_backend(backend, PERSISTED_SETTINGS_KEY, settings)?;
+                tracing::debug!("Saved settings to platform storage");
+            }
+            None => tracing::debug!("No platform storage backend, skipped platform save"),
+        }
+        Ok(())
+    }
+
+    /// Feed new fixes into the active recording, re-indexing and autosaving it when due
+    fn update_recording(&mut self, fixes: &[LiveFix]) {
+        if let Some(recorder) = self.state.recorder.as_mut() {
+            if !self.state.live_location.has_current_fix() {
+                recorder.mark_gap();
+            }
+            for fix in fixes {
+                recorder.push_fix(fix);
+            }
+
+            if recorder.reindex_due() {
+                recorder.mark_reindexed();
+                let (path, gpx) = (recorder.path().clone(), recorder.gpx().clone());
+                self.state.index_recording(path, gpx);
+            }
+
+            if let Some(recorder) = self.state.recorder.as_mut()
+                && recorder.autosave_due()
+            {
+                recorder.mark_autosaved();
+                let gpx = recorder.gpx().clone();
+                self.write_recording_autosave(&gpx);
+            }
+        } else if self.was_recording
+            && let Some(path) = &self.state.finished_recording
+            && let Some((_, gpx, _, _)) = self
+                .state
+                .file_loader
+                .loaded_files
+                .iter()
+                .find(|(loaded, _, _, _)| loaded == path)
+        {
+            // Just stopped: store the complete track until it is saved or discarded
+            self.write_recording_autosave(gpx);
+        }
+        self.was_recording = self.state.recorder.is_some();
+    }
+
+    fn write_recording_autosave(&self, gpx: &gpx::Gpx) {
+        let Some(backend) = self.storage_backend.as_deref() else {
+            return;
+        };
+        match recorder::to_gpx_string(gpx) {
+            Ok(text) => {
+                if let Err(e) = backend.set_string(RECORDING_AUTOSAVE_KEY, &text) {
+                    tracing::warn!("Failed to autosave recording: {:?}", e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to serialize recording: {}", e),
+        }
+    }
+
+    fn clear_recording_autosave(&self) {
+        if let Some(backend) = self.storage_backend.as_deref()
+            && let Err(e) = backend.remove(RECORDING_AUTOSAVE_KEY)
+        {
+            tracing::warn!("Failed to clear autosaved recording: {:?}", e);
+        }
+    }
+
+    /// Run the actions triggered by this frame's keyboard shortcuts
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        for action in self.state.shortcuts.consume_triggered(ctx) {
+            match action {
+                ShortcutAction::ToggleHelp => self.show_help = !self.show_help,
+                ShortcutAction::ToggleSidebar => {
+                    self.state.ui_settings.sidebar_open = !self.state.ui_settings.sidebar_open;
+                }
+                ShortcutAction::FitBounds => self.state.pending_fit_bounds = true,
+                ShortcutAction::ToggleTracks => self.state.toggle_tracks(ctx),
+                ShortcutAction::ZoomIn => self.set_map_zoom(self.map_memory.zoom() + 1.0),
+                ShortcutAction::ZoomOut => self.set_map_zoom(self.map_memory.zoom() - 1.0),
+                ShortcutAction::PanLeft => self.pan_by_pixels(-PAN_STEP_PIXELS, 0.0),
+                ShortcutAction::PanRight => self.pan_by_pixels(PAN_STEP_PIXELS, 0.0),
+                ShortcutAction::PanUp => self.pan_by_pixels(0.0, -PAN_STEP_PIXELS),
+                ShortcutAction::PanDown => self.pan_by_pixels(0.0, PAN_STEP_PIXELS),
+            }
+        }
+    }
+
+    /// Set the map zoom, clamped to what the current provider can show
+    fn set_map_zoom(&mut self, zoom: f64) {
+        let _ = self.map_memory.set_zoom(self.state.clamp_map_zoom(zoom));
+    }
+
+    /// Keep the zoom within the provider's limit after the map handled its input
+    ///
+    /// Scrolling past the limit just stops there. After switching to a
+    /// provider with shallower tiles the map glides down to its limit instead
+    /// of jumping (unless motion is reduced).
+    fn limit_zoom(&mut self, ctx: &egui::Context) {
+        let provider = self.state.ui_settings.tiles_provider;
+        let switched = std::mem::replace(&mut self.zoom_limit_provider, provider) != provider;
+        let max_zoom = self.state.max_map_zoom();
+        let zoom = self.map_memory.zoom();
+        if zoom <= max_zoom {
+            self.zoom_glide = false;
+            return;
+        }
+        if switched && motion::animations_enabled(&self.state.ui_settings) {
+            self.zoom_glide = true;
+        }
+        if !self.zoom_glide {
+            self.set_map_zoom(max_zoom);
+            return;
+        }
+        let dt = f64::from(ctx.input(|i| i.stable_dt));
+        // Not clamped: the glide passes through the levels above the limit
+        let _ = self
+            .map_memory
+            .set_zoom(zoom_glide_step(zoom, max_zoom, dt));
+        self.state
+            .activity
+            .keep_awake("zoom glide", std::time::Duration::ZERO);
+    }
+
+    /// Move the map center by a screen-space offset (positive `dy` is down)
+    fn pan_by_pixels(&mut self, dx: f64, dy: f64) {
+        // When not detached the map is centered on the position passed to `Map::new`
+        let center = self
+            .map_memory
+            .detached()
+            .unwrap_or(walkers::lat_lon(0.0, 0.0));
+        // Ground distance along a meridian, turned into degrees of latitude
+        let degrees_per_pixel =
+            meters_per_pixel(center.y(), self.map_memory.zoom()) * 360.0 / EARTH_SIZE_METERS;
+        let lat = (center.y() - dy * degrees_per_pixel).clamp(-85.0, 85.0);
+        let lon = center.x() + dx * degrees_per_pixel / center.y().to_radians().cos();
+        self.map_memory.center_at(walkers::lat_lon(lat, lon));
+    }
+
+    /// Replace walkers' glide after a drag with the configurable one of [`PanInertia`]
+    fn update_pan_inertia(&mut self, ctx: &egui::Context, map_response: &egui::Response) {
+        let dt = ctx.input(|i| i.stable_dt);
+        if map_response.dragged() {
+            self.pan_inertia.drag(map_response.drag_delta(), dt);
+            return;
+        }
+        if self.pan_inertia.is_dragging() {
+            let settings = &self.state.ui_settings;
+            self.pan_inertia
+                .release(settings.pan_inertia && motion::animations_enabled(settings));
+            // Pin the map where the drag ended, which cancels walkers' own glide
+            if let Some(position) = self.map_memory.detached() {
+                self.map_memory.center_at(position);
+            }
+        }
+        // Touching the map again or zooming catches it
+        if map_response.is_pointer_button_down_on()
+            || ctx.input(|i| i.raw_scroll_delta != egui::Vec2::ZERO)
+        {
+            self.pan_inertia.stop();
+        }
+        if let Some(offset) = self
+            .pan_inertia
+            .step(self.state.ui_settings.pan_friction, dt)
+        {
+            // The content moves with the glide, so the center moves against it
+            self.pan_by_pixels(-offset.x as f64, -offset.y as f64);
+            self.state
+                .activity
+                .keep_awake("pan glide", std::time::Duration::ZERO);
+        }
+    }
+
+    /// Pick where to split the route from a map click while the split tool is on
+    fn split_click(&mut self, map_response: &egui::Response) {
+        if !self.state.split.is_active() || !map_response.clicked() {
+            return;
+        }
+        let Some(pointer) = map_response.interact_pointer_pos() else {
+            return;
+        };
+        let projector = walkers::Projector::new(
+            map_response.rect,
+            &self.map_memory,
+            walkers::lat_lon(0.0, 0.0),
+        );
+        let position = projector.unproject(pointer.to_vec2());
+        let max_distance_m = meters_per_pixel(position.y(), self.map_memory.zoom())
+            * f64::from(split::PICK_RADIUS_POINTS);
+        self.state.pick_split_point(position, max_distance_m);
+    }
+
+    /// Select the route whose extent was clicked, when the click missed every track
+    fn extent_click(&mut self, map_response: &egui::Response) {
+        if !self.state.ui_settings.show_file_extents
+            || self.state.split.is_active()
+            || !map_response.clicked()
+        {
+            return;
+        }
+        let Some(pointer) = map_response.interact_pointer_pos() else {
+            return;
+        };
+        let Ok(mut selected) = self.state.selected_route.try_write() else {
+            return;
+        };
+        // The track plugin already picked the track under the click
+        if self.state.ui_settings.show_tracks && selected.is_some() {
+            self.last_extent_pick = None;
+            return;
+        }
+        let Ok(collection) = self.state.route_collection.try_read() else {
+            return;
+        };
+        let projector = walkers::Projector::new(
+            map_response.rect,
+            &self.map_memory,
+            walkers::lat_lon(0.0, 0.0),
+        );
+        let position = projector.unproject(pointer.to_vec2());
+        let picked = extents::pick(
+            &extents::route_extents(&collection),
+            position,
+            self.last_extent_pick,
+        );
+        if picked.is_some() {
+            *selected = picked;
+        }
+        self.last_extent_pick = picked;
+    }
+
+    /// Right-click menu on the map for copying coordinates and jumping to one
+    fn map_context_menu(&mut self, map_response: &egui::Response) {
+        if map_response.secondary_clicked()
+            && let Some(pointer) = map_response.interact_pointer_pos()
+        {
+            let projector = walkers::Projector::new(
+                map_response.rect,
+                &self.map_memory,
+                walkers::lat_lon(0.0, 0.0),
+            );
+            self.context_menu_position = Some(projector.unproject(pointer.to_vec2()));
+        }
+        let format = self.state.ui_settings.coord_format;
+        // Not detached means the map sits on the position given to `Map::new`
+        let center = self
+            .map_memory
+            .detached()
+            .unwrap_or(walkers::lat_lon(0.0, 0.0));
+        let clicked = self.context_menu_position;
+        let map_memory = &mut self.map_memory;
+        let goto_text = &mut self.goto_text;
+        let goto_error = &mut self.goto_error;
+        map_response.context_menu(|ui| {
+            if let Some(position) = clicked
+                && ui.button("📋 Copy coordinates here").clicked()
+            {
+                ui.ctx()
+                    .copy_text(ui_panels::format_position(position, format));
+                ui.close();
+            }
+            if ui.button("📋 Copy map center").clicked() {
+                ui.ctx()
+                    .copy_text(ui_panels::format_position(center, format));
+                ui.close();
+            }
+            ui.separator();
+            let submitted = ui
+                .horizontal(|ui| {
+                    let field = ui.add(
+                        egui::TextEdit::singleline(goto_text)
+                            .hint_text("Go to…")
+                            .desired_width(180.0),
+                    );
+                    let entered =
+                        field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    field.on_hover_text(
+                        "Decimal degrees, degrees/minutes/seconds, UTM or MGRS, whichever format is displayed",
+                    );
+                    ui.button("Go").clicked() || entered
+                })
+                .inner;
+            if submitted {
+                match coords::parse_coord(goto_text) {
+                    Some((lat, lon)) => {
+                        map_memory.center_at(walkers::lat_lon(lat, lon));
+                        *goto_error = false;
+                        ui.close();
+                    }
+                    None => *goto_error = true,
+                }
+            }
+            if *goto_error {
+                ui.colored_label(ui.visuals().error_fg_color, "Not a position");
+            }
+        });
+    }
+
+    /// Drop what depends on the previous scale factor after the window moved screens
+    ///
+    /// Line widths are in points and follow the new scale by themselves, but
+    /// frame times measured at the old resolution say nothing about the new
+    /// one, so the frame budget starts over from full detail. The new screen
+    /// gets a fresh frame right away instead of waiting for input.
+    fn handle_scale_change(&mut self, ctx: &egui::Context, ratio: f32) {
+        tracing::debug!(
+            "Scale factor changed by {:.2}x to {}",
+            ratio,
+            ctx.pixels_per_point()
+        );
+        self.state.frame_budget = Default::default();
+        ctx.request_repaint();
+    }
+
+    /// Move the playback cursor and keep the map centered on it while it moves
+    ///
+    /// Returns the cursor's position for the marker, `None` when stopped.
+    fn update_playback(&mut self, ctx: &egui::Context) -> Option<walkers::Position> {
+        let route_index = self.state.playback.route()?;
+        let collection = self.state.route_collection.try_read().ok()?;
+        let Some(route) = collection.get_route(route_index) else {
+            self.state.playback.stop();
+            return None;
+        };
+        let profile = route.profile();
+        let elapsed = f64::from(ctx.input(|i| i.stable_dt));
+        let moved = self.state.playback.advance(profile, elapsed);
+        let (track, segment, point) = profile.location(self.state.playback.index(profile))?;
+        let point = route.tracks()[track].segments[segment].points[point].point();
+        let position = walkers::lat_lon(point.y(), point.x());
+        if moved {
+            self.map_memory.center_at(position);
+        }
+        if self.state.playback.playing {
+            self.state
+                .activity
+                .keep_awake("playback", std::time::Duration::ZERO);
+        }
+        Some(position)
     }
 
     /// Fit the map view to the bounding box of all loaded tracks
     fn fit_to_bounds(&mut self) {
         // Use try_read for non-blocking UI polling.
-        let collection = match self.state.route_collection.try_read() {
-            Ok(guard) => guard,
+        let bounds = match self.state.route_collection.try_read() {
+            Ok(collection) => collection.bounding_box_wgs84(),
             Err(_) => return, // Skip if lock is not available
         };
+        if let Some(bounds) = bounds {
+            self.fit_to_rect(bounds);
+        }
+    }
 
-        if let Some((min_lat, min_lon, max_lat, max_lon)) = collection.bounding_box_wgs84() {
-            let center_lat = (min_lat + max_lat) / 2.0;
-            let center_lon = (min_lon + max_lon) / 2.0;
+    /// Fit the map view to the bounding box of one route
+    fn fit_to_route(&mut self, route_index: usize) {
+        let bounds = match self.state.route_collection.try_read() {
+            Ok(collection) => collection.get_route(route_index).map(|route| {
+                let bbox = route.bounding_box();
+                let (min_lat, min_lon) = mercator_to_wgs84(bbox.min().x, bbox.min().y);
+                let (max_lat, max_lon) = mercator_to_wgs84(bbox.max().x, bbox.max().y);
+                (min_lat, min_lon, max_lat, max_lon)
+            }),
+            Err(_) => return, // Skip if lock is not available
+        };
+        if let Some(bounds) = bounds {
+            self.fit_to_rect(bounds);
+        }
+    }
 
-            let lat_span = (max_lat - min_lat).abs();
-            let lon_span = (max_lon - min_lon).abs();
-            let max_span = lat_span.max(lon_span);
+    /// Center the map on a `(min_lat, min_lon, max_lat, max_lon)` box, zoomed to show all of it
+    fn fit_to_rect(&mut self, (min_lat, min_lon, max_lat, max_lon): (f64, f64, f64, f64)) {
+        let center_lat = (min_lat + max_lat) / 2.0;
+        let center_lon = (min_lon + max_lon) / 2.0;
+
+        let lat_span = (max_lat - min_lat).abs();
+        let lon_span = (max_lon - min_lon).abs();
+        let max_span = lat_span.max(lon_span);
+
+        let zoom = if max_span > 0.0 {
+            let zoom_estimate = (4.0 * 360.0 / max_span).log2();
+            // Fitting never over-zooms: the provider's own tiles are deep enough
+            clamp_zoom(
+                (zoom_estimate - 0.5).max(1.0),
+                self.state.ui_settings.tiles_provider,
+                0,
+            )
+        } else {
+            12.0
+        };
 
-            let zoom = if max_span > 0.0 {
-                let zoom_estimate = (4.0 * 360.0 / max_span).log2() as f32;
-                (zoom_estimate - 0.5).clamp(1.0, 18.0)
-            } else {
-                12.0
-            };
-
-            self.map_memory
-                .center_at(walkers::lat_lon(center_lat, center_lon));
-            let _ = self.map_memory.set_zoom(zoom as f64);
-
-            tracing::trace!(
-                "Auto-zoomed to bounds: ({:.4}, {:.4}) - ({:.4}, {:.4}), zoom: {:.1}",
-                min_lat,
-                min_lon,
-                max_lat,
-                max_lon,
-                zoom
-            );
-        }
+        self.map_memory
+            .center_at(walkers::lat_lon(center_lat, center_lon));
+        let _ = self.map_memory.set_zoom(zoom);
+
+        tracing::trace!(
+            "Auto-zoomed to bounds: ({:.4}, {:.4}) - ({:.4}, {:.4}), zoom: {:.1}",
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+            zoom
+        );
     }
 }
 
 #[profiling::all_functions]
 impl eframe::App for LargeTrackViewerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let frame_start = instant::Instant::now();
+
         // Handle keyboard shortcuts
-        ctx.input(|i| {
-            if i.key_pressed(egui::Key::F1) {
-                self.show_help = !self.show_help;
-            }
-            if i.key_pressed(egui::Key::H) && i.modifiers.ctrl {
-                self.show_help = !self.show_help;
-            }
+        self.handle_shortcuts(ctx);
 
-            if i.raw_scroll_delta.y != 0.0 && !i.modifiers.ctrl && !self.state.show_wheel_warning {
-                self.state.show_wheel_zoom_warning();
+        motion::apply(ctx, &self.state.ui_settings);
+        if ctx.options(|options| options.theme_preference) != self.state.ui_settings.theme {
+            ctx.set_theme(self.state.ui_settings.theme);
+        }
+
+        if let Some(ratio) = self.dpi_watcher.observe(ctx.pixels_per_point()) {
+            self.handle_scale_change(ctx, ratio);
+        }
+
+        // `cpu_usage` leaves out waiting for vsync, so only real work counts against the budget
+        if let Some(cpu_seconds) = frame.info().cpu_usage {
+            self.state.frame_budget.record(
+                f64::from(cpu_seconds) * 1000.0,
+                f64::from(self.state.ui_settings.frame_budget_ms),
+            );
+            // Keep sampling while detail is lowered, so it comes back without waiting for input
+            if self.state.frame_budget.lod_reduction() > 0 {
+                self.state
+                    .activity
+                    .keep_awake("lowered detail", std::time::Duration::from_millis(100));
             }
-        });
+        }
 
         // Auto-zoom to fit loaded tracks if requested
         if self.state.pending_fit_bounds {
             self.state.pending_fit_bounds = false;
             self.fit_to_bounds();
         }
+        if let Some(route_index) = self.state.pending_fit_route.take() {
+            self.fit_to_route(route_index);
+        }
 
         // Process pending reload (e.g., after LOD bias change)
+        self.state.process_bias_change();
         self.state.process_pending_reload();
 
         // Handle drag and drop
@@ -380,94 +1466,288 @@ impl eframe::App for LargeTrackViewerApp {
         eframe_entrypoints::file_picker::render_rust_file_dialog(ctx);
         ui_panels::manage_pending_files(&mut self.state);
 
+        // Offer to resume files that were interrupted by an unclean shutdown
+        if !self.state.interrupted_import.is_empty() {
+            ui_panels::resume_import_prompt(ctx, &mut self.state);
+        }
+
+        // Confirm loads that would exceed the route limit or the memory budget
+        if self.state.route_limit_warning.is_some() {
+            ui_panels::route_limit_prompt(ctx, &mut self.state);
+        }
+        if self.state.memory_warning.is_some() {
+            ui_panels::memory_warning_prompt(ctx, &mut self.state);
+        }
+
+        // Offer to save a stopped recording
+        if self.state.finished_recording.is_some()
+            && ui_panels::recording_save_prompt(ctx, &mut self.state)
+        {
+            self.clear_recording_autosave();
+        }
+
         // Show help overlay if enabled
         if self.show_help {
-            ui_panels::help_overlay(ctx, &mut self.show_help);
+            ui_panels::help_overlay(ctx, &mut self.show_help, &self.state.shortcuts);
+        }
+
+        // Drain live position updates and keep the camera on the fix when following
+        if self.state.live_location.is_enabled() {
+            let fixes = self.state.live_location.poll();
+            if !fixes.is_empty()
+                && self.state.live_location.follow
+                && let Some(fix) = self.state.live_location.fix()
+            {
+                self.map_memory.center_at(fix.position);
+            }
+            self.update_recording(&fixes);
+            // Updates arrive from outside egui, so keep polling while enabled
+            self.state
+                .activity
+                .keep_awake("live position", std::time::Duration::from_millis(500));
+        } else {
+            self.update_recording(&[]);
+        }
+        if let Some(position) = self.state.external_position.take_recenter() {
+            self.map_memory.center_at(position);
+        }
+        if self.state.external_position.position().is_some()
+            && motion::animations_enabled(&self.state.ui_settings)
+        {
+            // Keep the marker pulsing
+            self.state
+                .activity
+                .keep_awake("position marker", std::time::Duration::from_millis(33));
         }
 
         // Render the main sidebar (responsive: side or bottom based on orientation)
         ui_panels::render_sidebar(ctx, &mut self.state);
 
+        // Check the shown tile provider in the background, falling back if it's down
+        self.tile_health
+            .ensure_checked(self.state.ui_settings.tiles_provider);
+        for (provider, result) in self.tile_health.poll() {
+            self.state.handle_tile_health(provider, result);
+        }
+        if self.tile_health.is_checking() {
+            self.state
+                .activity
+                .keep_awake("tile check", std::time::Duration::from_millis(500));
+        }
+
         // Capture values we need before the closure
-        let route_collection = self.state.route_collection.clone();
-        let line_width = self.state.ui_settings.line_width;
-        let show_outline = self.state.ui_settings.show_outline;
+        let ui_settings = &self.state.ui_settings;
+        // Overlays on the tracks match their width, taken at the map center in meters mode
+        let overlay_width = match ui_settings.width_mode {
+            WidthMode::Pixels => ui_settings.line_width,
+            WidthMode::Meters => {
+                let center = self
+                    .map_memory
+                    .detached()
+                    .unwrap_or(walkers::lat_lon(0.0, 0.0));
+                meters_to_points(
+                    ui_settings.line_width_m,
+                    meters_per_pixel(center.y(), self.map_memory.zoom()),
+                )
+            }
+        };
         let tiles_provider = self.state.ui_settings.tiles_provider;
-        let attribution_text = self.state.ui_settings.tiles_provider.attribution();
-        let render_stats = self.render_stats.clone();
+        let attribution = basemap::attribution(tiles_provider);
+        let compare_pair = self
+            .state
+            .comparison
+            .result
+            .as_ref()
+            .map(|result| (result.reference, result.recorded));
+        let frame_params = FrameParams {
+            width: self.state.ui_settings.line_width,
+            show_outline: self.state.ui_settings.show_outline,
+            compare_pair,
+            route_colors: self.state.route_color_overrides(),
+            route_dashes: self.state.route_dash_patterns(),
+            blend_mode: self.state.ui_settings.blend_mode,
+            hovered: self.state.legend_hovered,
+            lod_reduction: self.state.frame_budget.lod_reduction(),
+            detail_preview: self.state.bias_debouncer.preview_factor(),
+            tool_takes_clicks: self.state.split.is_active(),
+            full_detail_selection: self.state.ui_settings.full_detail_selection,
+            width_mode: self.state.ui_settings.width_mode,
+            width_m: self.state.ui_settings.line_width_m,
+            stack_offset: if self.state.ui_settings.offset_stacked_tracks {
+                self.state.ui_settings.stack_offset
+            } else {
+                0.0
+            },
+        };
+        self.track_plugin
+            .begin_frame(&self.state.route_collection, frame_params);
+        let playback_plugin = self.update_playback(ctx).map(PlaybackPlugin::new);
+        let waypoint_plugin = self.state.ui_settings.show_waypoints.then(|| {
+            WaypointPlugin::new(
+                self.state
+                    .file_loader
+                    .loaded_files
+                    .iter()
+                    .map(|(_, gpx, _, _)| gpx),
+            )
+        });
+        let extents_plugin = self
+            .state
+            .ui_settings
+            .show_file_extents
+            .then(|| self.state.route_collection.try_read().ok())
+            .flatten()
+            .map(|collection| {
+                let selected = self
+                    .state
+                    .selected_route
+                    .try_read()
+                    .ok()
+                    .and_then(|guard| *guard);
+                ExtentsPlugin::new(extents::route_extents(&collection), selected)
+            });
+        let external_position_plugin = ExternalPositionPlugin::new(
+            &self.state.external_position,
+            motion::animations_enabled(&self.state.ui_settings).then(|| ctx.input(|i| i.time)),
+        );
+        let position_plugin = self
+            .state
+            .live_location
+            .fix()
+            .map(|fix| PositionPlugin::new(*fix, self.state.live_location.has_current_fix()));
 
         // Central panel: Map view (full screen)
         egui::CentralPanel::default()
             .frame(egui::Frame::NONE)
             .show(ctx, |ui| {
                 profiling::scope!("map_panel");
+                self.state.map_rect = Some(ui.max_rect());
 
-                // Use the AppState's shared `selected_route` handle directly and pass it into the plugin.
-                // This centralizes selection in AppState so the plugin and the sidebar share the same lock.
-                let selected_handle = self.state.selected_route.clone();
-                let track_plugin = TrackPlugin::new(
-                    route_collection,
-                    line_width,
-                    show_outline,
-                    render_stats,
-                    selected_handle,
-                );
+                // The plugin shares AppState's `selected_route` handle, so it and the sidebar
+                // use the same lock. With tracks hidden it isn't lent to the map, so nothing is queried
+                let track_plugin = self
+                    .state
+                    .ui_settings
+                    .show_tracks
+                    .then_some(&mut self.track_plugin);
 
                 let query_start = instant::Instant::now();
 
-                let tiles: &mut HttpTiles = match tiles_provider {
-                    TilesProvider::OpenStreetMap => &mut self.tiles_osm,
-                    TilesProvider::OpenTopoMap => &mut self.tiles_otm,
-                };
-
-                let map = Map::new(
-                    Some(tiles),
-                    &mut self.map_memory,
-                    walkers::lat_lon(0.0, 0.0),
-                )
-                .with_plugin(track_plugin);
-
-                ui.add(map);
+                let tiles = self
+                    .base_maps
+                    .tiles(tiles_provider)
+                    .map(|tiles| tiles as &mut dyn walkers::Tiles);
+                let show_grid = tiles.is_none();
 
-                // Show wheel warning and auto-hide after 0.5 seconds
-                ctx.input(|i| {
-                    if i.raw_scroll_delta.y != 0.0
-                        && !i.modifiers.ctrl
-                        && !self.state.show_wheel_warning
+                let mut map = Map::new(tiles, &mut self.map_memory, walkers::lat_lon(0.0, 0.0));
+                if show_grid {
+                    map = map.with_plugin(GridPlugin);
+                }
+                if let Some(track_plugin) = track_plugin {
+                    map = map.with_plugin(track_plugin);
+                    if let Some(result) = &self.state.comparison.result
+                        && !result.off_corridor_lines.is_empty()
                     {
-                        self.state.show_wheel_zoom_warning();
+                        map = map.with_plugin(HighlightPlugin::new(
+                            result.off_corridor_lines.clone(),
+                            overlay_width,
+                        ));
                     }
+                    if let Some(preview) = &self.state.split.preview {
+                        map = map.with_plugin(SplitPreviewPlugin::new(
+                            preview.lines.clone(),
+                            overlay_width,
+                        ));
+                    }
+                    if let Some(waypoint_plugin) = waypoint_plugin {
+                        map = map.with_plugin(waypoint_plugin);
+                    }
+                }
+                if let Some(extents_plugin) = extents_plugin {
+                    map = map.with_plugin(extents_plugin);
+                }
+                if let Some(playback_plugin) = playback_plugin {
+                    map = map.with_plugin(playback_plugin);
+                }
+                if let Some(position_plugin) = position_plugin {
+                    map = map.with_plugin(position_plugin);
+                }
+                if let Some(external_position_plugin) = external_position_plugin {
+                    map = map.with_plugin(external_position_plugin);
+                }
+
+                let map_response = ui.add(map);
+                self.update_pan_inertia(ui.ctx(), &map_response);
+                self.map_context_menu(&map_response);
+                self.split_click(&map_response);
+                self.extent_click(&map_response);
+
+                // Tiles past the provider's max zoom are upscaled by walkers; only cap the
+                // zoom at the configured over-zoom allowance.
+                self.limit_zoom(ui.ctx());
+
+                // Remind how to zoom while the wheel is turned without the modifier
+                let unmodified_scroll = ctx.input(|i| {
+                    i.raw_scroll_delta.y != 0.0 && !self.state.shortcuts.is_scroll_zoom(i.modifiers)
                 });
-                if self.state.show_wheel_warning && self.state.should_hide_wheel_warning() {
-                    self.state.hide_wheel_zoom_warning();
+                if unmodified_scroll {
+                    let modifier = self.state.shortcuts.scroll_zoom_text(ctx);
+                    self.state.show_wheel_zoom_warning(&modifier);
                 }
 
                 let query_time = query_start.elapsed();
                 self.state.stats.last_query_time_ms = query_time.as_secs_f64() * 1000.0;
 
-                {
+                // While tracks are hidden the shared stats are stale; keep the cleared ones
+                if self.state.ui_settings.show_tracks {
                     // Use try_read for non-blocking UI polling.
                     if let Ok(render_stats) = self.render_stats.try_read() {
-                        self.state.stats.last_query_segments = render_stats.segments_rendered;
-                        self.state.stats.last_query_simplified_points =
-                            render_stats.simplified_points_rendered;
+                        self.state.apply_render_stats(&render_stats);
                     }
                 }
+                let smoothing = std::time::Duration::from_secs_f32(
+                    self.state.ui_settings.stats_smoothing_secs.max(0.0),
+                );
+                self.state
+                    .stats
+                    .smooth_query_stats(instant::Instant::now(), smoothing);
 
                 ui_panels::sidebar_toggle_button(ui, &mut self.state);
+                ui_panels::map_lock_button(ui, &mut self.state);
 
-                let painter = ui.painter();
-                let screen_rect = ui.max_rect();
-                painter.text(
-                    screen_rect.center_bottom() + egui::vec2(0.0, -5.0),
-                    egui::Align2::CENTER_BOTTOM,
-                    attribution_text,
-                    egui::FontId::proportional(10.0),
-                    egui::Color32::from_black_alpha(180),
-                );
+                if let Some(attribution) = &attribution {
+                    basemap::paint_attribution(ui, attribution);
+                }
 
-                if self.state.show_wheel_warning {
-                    ui_panels::show_wheel_zoom_warning(ui, &mut self.state);
+                if self.state.ui_settings.show_distance_grid {
+                    let center = self
+                        .map_memory
+                        .detached()
+                        .unwrap_or(walkers::lat_lon(0.0, 0.0));
+                    distance_grid::draw(ui, center.y(), self.map_memory.zoom());
+                }
+
+                if self.state.ui_settings.show_center_crosshair {
+                    // Not detached means the map sits on the position given to `Map::new`
+                    let center = self
+                        .map_memory
+                        .detached()
+                        .unwrap_or(walkers::lat_lon(0.0, 0.0));
+                    ui_panels::center_crosshair(ui, center, self.state.ui_settings.coord_format);
+                }
+
+                if !self.state.ui_settings.show_tracks {
+                    ui_panels::tracks_hidden_indicator(ui, &self.state);
+                }
+
+                if self.state.tile_failure.is_some() {
+                    ui_panels::tile_failure_banner(ui, &mut self.state);
+                }
+
+                if self.state.ui_settings.show_legend && self.state.ui_settings.show_tracks {
+                    ui_panels::track_legend(ui, &mut self.state);
+                } else {
+                    self.state.legend_hovered = None;
                 }
             });
 
@@ -478,18 +1758,31 @@ impl eframe::App for LargeTrackViewerApp {
             ctx.request_repaint();
         }
 
-        // Process parallel load results (one at a time for UI responsiveness)
+        // Process parallel load results (a few per frame for UI responsiveness)
+        let processed_before = self.state.file_loader.processed_count();
         let has_more_results = self.state.process_parallel_results();
-        if self.state.is_parallel_loading() || has_more_results {
-            // Request immediate repaint to process next result quickly while still yielding to UI
-            ctx.request_repaint();
+        let processed_any = self.state.file_loader.processed_count() != processed_before;
+        if has_more_results || processed_any {
+            // Something changed (or is queued): show it and take the next results right away
+            self.state
+                .activity
+                .keep_awake("loading", std::time::Duration::ZERO);
+        } else if self.state.is_parallel_loading() {
+            // Workers are still parsing; the map hasn't changed, so only poll for them
+            self.state
+                .activity
+                .keep_awake("loading", LOADING_POLL_INTERVAL);
         }
 
-        // Process any remaining files one at a time (fallback or WASM)
-        if self.state.file_loader.is_busy() && !self.state.is_parallel_loading() {
-            self.state.process_pending_files();
-            ctx.request_repaint();
-        }
+        let (done, total) = self.state.batch_progress();
+        self.load_announcer.update(
+            ctx.input(|i| i.time),
+            self.state.file_loader.is_busy() || self.state.is_parallel_loading(),
+            done,
+            total,
+            self.state.file_loader.errors.len(),
+        );
+        self.load_announcer.show(ctx);
 
         // After all persisted files are loaded, fit to bounds once
         if !self.restored_persisted_state
@@ -497,119 +1790,494 @@ impl eframe::App for LargeTrackViewerApp {
             && !self.state.file_loader.loaded_files.is_empty()
         {
             self.restored_persisted_state = true;
-            self.fit_to_bounds();
+            // "Keep last camera" leaves the restored view alone, later loads fit as usual
+            if !std::mem::take(&mut self.state.keep_startup_camera) {
+                self.fit_to_bounds();
+            }
+        }
+
+        // Autosave after each completed file and periodically, so a crash loses little.
+        // eframe's own save() may never run when the app is killed (common on Android and web).
+        let autosave_interval =
+            std::time::Duration::from_secs(self.state.ui_settings.autosave_interval_secs);
+        if std::mem::take(&mut self.state.save_requested) {
+            self.state.session_dirty = false;
+            self.persist_now(
+                frame.storage_mut().map(|s| s as &mut dyn eframe::Storage),
+                true,
+            );
+        } else if self.state.session_dirty || self.last_autosave.elapsed() >= autosave_interval {
+            self.state.session_dirty = false;
+            self.persist_now(
+                frame.storage_mut().map(|s| s as &mut dyn eframe::Storage),
+                false,
+            );
+        }
+
+        // Last, so this also shows the toasts pushed while saving above; their
+        // layer is below prompts (see `overlay`)
+        ui_panels::render_notifications(ctx, &mut self.state);
+
+        // Only the first frame counts towards startup; later calls do nothing
+        let first_query =
+            std::time::Duration::from_secs_f64(self.state.stats.last_query_time_ms / 1000.0);
+        self.state
+            .startup
+            .finish_first_frame(frame_start.elapsed(), first_query);
+
+        // Nothing registered means nothing moves: egui waits for input
+        self.state.activity.finish(ctx);
+    }
+
+    fn raw_input_hook(&mut self, ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        // A press can't be taken back once egui has handed it to the map, so the
+        // lock filters the input before the frame starts, against last frame's layout
+        let map_rect = self.state.map_rect;
+        let blocked = self.map_lock.filter(
+            &mut raw_input.events,
+            self.state.ui_settings.map_locked,
+            |position| {
+                map_rect.is_some_and(|rect| rect.contains(position))
+                    && ctx.layer_id_at(position) == Some(egui::LayerId::background())
+            },
+        );
+        if blocked {
+            self.state.show_map_lock_hint();
         }
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        // Save settings only (no route data - fast)
-        // Include ONLY real filesystem paths (skip synthetic web:// identifiers).
-        // We intentionally do NOT persist browser-only dropped files (which are identified
-        // by the synthetic web:// prefix) because they are not reloadable from disk.
-        let mut all_file_paths: Vec<String> = self
-            .state
-            .file_loader
-            .loaded_files
-            .iter()
-            .map(|(path, _, _)| path.to_string_lossy().to_string())
-            // Filter out synthetic web-only paths (we use "web://" prefix for those)
-            .filter(|s| !s.starts_with("web://"))
-            .collect();
+        self.persist_now(Some(storage), false);
+    }
 
-        // Add pending files (only persist those with a real filesystem path)
-        for path in &self.state.file_loader.pending_files {
-            if let Some(p) = path.path.as_ref() {
-                let path_str = p.to_string_lossy().to_string();
-                if !all_file_paths.contains(&path_str) {
-                    all_file_paths.push(path_str);
-                }
-            } else {
-                // Skip browser-dropped files without a real path (do not persist)
-            }
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Keep a running recording so it is offered for saving next time
+        if let Some(recorder) = &self.state.recorder {
+            self.write_recording_autosave(recorder.gpx());
         }
 
-        // Add files being processed in parallel (from results queue)
+        // Reaching this point means the shutdown is clean; drop the running marker
+        if let Some(backend) = self.storage_backend.as_deref()
+            && let Err(e) = backend.remove(SESSION_RUNNING_KEY)
         {
-            // Use the mutex-based results container to read any in-progress results.
-            // Locking here is brief and deterministic; on native this is a std::sync::Mutex
-            // and on wasm it is likewise safe because we only hold the lock very briefly.
-            let guard = self
-                .state
-                .file_loader
-                .parallel_load_results
-                .lock()
-                .expect("failed to acquire lock on parallel_load_results mutex in save()");
-            for (path, _) in guard.iter() {
-                let path_str: String = path.to_string_lossy().to_string();
-                // Skip synthetic web-only identifiers
-                if path_str.starts_with("web://") {
-                    continue;
-                }
-                if !all_file_paths.contains(&path_str) {
-                    all_file_paths.push(path_str);
-                }
+            tracing::warn!("Failed to clear session marker: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::MemoryBackend;
+    use crate::app::style_rules::{DashPattern, StyleRule};
+    use clap::Parser;
+    use egui_kittest::kittest::Queryable;
+
+    /// A scratch directory, removed with everything in it when dropped
+    struct TempDir(std::path::PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Persisted settings listing two existing files, with a saved camera
+    ///
+    /// The files live until the returned directory is dropped.
+    fn persisted(mode: StartupMode) -> (PersistedSettings, Vec<String>, TempDir) {
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let dir = TempDir(std::env::temp_dir().join(format!(
+            "ltv-startup-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        )));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        let files: Vec<String> = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let path = dir.0.join(format!("{}.gpx", name));
+                std::fs::write(&path, "").unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        let settings = serde_json::from_value(serde_json::json!({
+            "line_width": 2.0,
+            "show_outline": true,
+            "bias": 1.0,
+            "sidebar_open": true,
+            "active_tab": "Tracks",
+            "tiles_provider": "None",
+            "show_profiling": false,
+            "startup_mode": format!("{:?}", mode),
+            "camera": { "lat": 45.0, "lon": 7.0, "zoom": 11.0 },
+            "loaded_file_paths": files,
+        }))
+        .unwrap();
+        (settings, files, dir)
+    }
+
+    fn pending(state: &AppState) -> usize {
+        state.file_loader.pending_files.len()
+    }
+
+    #[test]
+    fn restore_session_reloads_files_and_fits() {
+        let cli = Settings::parse_from(["large-track-viewer"]);
+        let (settings, _, _dir) = persisted(StartupMode::RestoreSession);
+        let (state, camera) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(pending(&state), 2);
+        assert_eq!(camera, None);
+        assert!(!state.keep_startup_camera);
+    }
+
+    #[test]
+    fn keep_camera_reloads_files_without_fitting() {
+        let cli = Settings::parse_from(["large-track-viewer"]);
+        let (settings, _, _dir) = persisted(StartupMode::RestoreFilesKeepCamera);
+        let (state, camera) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(pending(&state), 2);
+        assert_eq!(
+            camera,
+            Some(PersistedCamera {
+                lat: 45.0,
+                lon: 7.0,
+                zoom: 11.0
+            })
+        );
+        assert!(state.keep_startup_camera);
+        assert_eq!(
+            state.ui_settings.startup_mode,
+            StartupMode::RestoreFilesKeepCamera
+        );
+    }
+
+    #[test]
+    fn start_empty_loads_nothing_but_restores_settings() {
+        let cli = Settings::parse_from(["large-track-viewer"]);
+        let (settings, _, _dir) = persisted(StartupMode::StartEmpty);
+        let (state, camera) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(pending(&state), 0);
+        assert_eq!(camera, None);
+        assert!(state.held_file_paths.is_empty());
+        assert_eq!(state.ui_settings.line_width, 2.0);
+        assert!(state.ui_settings.show_outline);
+        assert_eq!(state.ui_settings.tiles_provider, TilesProvider::None);
+        assert_eq!(state.ui_settings.startup_mode, StartupMode::StartEmpty);
+    }
+
+    #[test]
+    fn safe_mode_starts_from_defaults_whatever_was_persisted() {
+        let cli = Settings::parse_from(["large-track-viewer", "--safe-mode"]).with_safe_mode();
+        assert!(cli.ignore_persisted && cli.no_basemap);
+        // `--ignore-persisted` keeps the persisted settings from being read at
+        // all; even after a crash, nothing is offered to load again
+        let (state, camera) = LargeTrackViewerApp::startup_state(None, &cli, true);
+        assert_eq!(pending(&state), 0);
+        assert_eq!(camera, None);
+        assert!(state.interrupted_import.is_empty());
+        assert!(state.held_file_paths.is_empty());
+
+        let defaults = AppState::new(&cli).ui_settings;
+        assert_eq!(state.ui_settings.line_width, defaults.line_width);
+        assert_eq!(state.ui_settings.show_outline, defaults.show_outline);
+        assert_eq!(state.ui_settings.startup_mode, StartupMode::default());
+    }
+
+    #[test]
+    fn settings_profiles_are_saved_listed_and_applied_across_restarts() {
+        let cli = Settings::parse_from(["large-track-viewer"]);
+        let mut state = AppState::new(&cli);
+        state.ui_settings.line_width = 4.5;
+        state.ui_settings.coord_format = CoordFormat::Utm;
+        state.ui_settings.theme = egui::ThemePreference::Dark;
+        state.ui_settings.blend_mode = BlendMode::Additive;
+        state.ui_settings.style_rules.push(StyleRule {
+            pattern: "planned-*".to_string(),
+            dash: DashPattern::Dashed,
+            color: None,
+        });
+        state.save_settings_profile(" cycling ");
+        state.ui_settings.line_width = 1.5;
+        state.ui_settings.coord_format = CoordFormat::Dms;
+        state.ui_settings.theme = egui::ThemePreference::Light;
+        state.save_settings_profile("presentation");
+        state.save_settings_profile("  ");
+        assert_eq!(
+            state.settings_profiles.keys().collect::<Vec<_>>(),
+            ["cycling", "presentation"]
+        );
+
+        // Saved with the session, without any files of their own
+        let (mut settings, _, _dir) = persisted(StartupMode::StartEmpty);
+        settings.settings_profiles = state
+            .settings_profiles
+            .iter()
+            .map(|(name, ui)| (name.clone(), PersistedUiSettings::new(ui)))
+            .collect();
+        let json = serde_json::to_value(&settings).unwrap();
+        assert_eq!(json["line_width"], 2.0);
+        for profile in json["settings_profiles"].as_object().unwrap().values() {
+            for files in [
+                "loaded_file_paths",
+                "pending_file_paths",
+                "expanded_file_paths",
+                "route_colors",
+            ] {
+                assert!(profile.get(files).is_none(), "{files} saved in a profile");
             }
         }
+        let settings: PersistedSettings = serde_json::from_value(json).unwrap();
+        let (mut restored, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(restored.settings_profiles.len(), 2);
+
+        restored.ui_settings.sidebar_open = false;
+        restored.ui_settings.startup_mode = StartupMode::StartEmpty;
+        restored.ui_settings.autosave_interval_secs = 7;
+        assert!(restored.apply_settings_profile("cycling"));
+        assert_eq!(restored.ui_settings.line_width, 4.5);
+        assert_eq!(restored.ui_settings.coord_format, CoordFormat::Utm);
+        assert_eq!(restored.ui_settings.theme, egui::ThemePreference::Dark);
+        assert_eq!(restored.ui_settings.blend_mode, BlendMode::Additive);
+        assert_eq!(
+            restored.ui_settings.style_rules,
+            state.settings_profiles["cycling"].style_rules
+        );
+        // Neither are where the sidebar is nor how the app starts and saves
+        assert!(!restored.ui_settings.sidebar_open);
+        assert_eq!(restored.ui_settings.startup_mode, StartupMode::StartEmpty);
+        assert_eq!(restored.ui_settings.autosave_interval_secs, 7);
+        assert!(restored.apply_settings_profile("presentation"));
+        assert_eq!(restored.ui_settings.coord_format, CoordFormat::Dms);
+        assert_eq!(restored.ui_settings.theme, egui::ThemePreference::Light);
+        assert!(!restored.apply_settings_profile("missing"));
+
+        restored.delete_settings_profile("cycling");
+        assert_eq!(
+            restored.settings_profiles.keys().collect::<Vec<_>>(),
+            ["presentation"]
+        );
+    }
 
-        let loaded_file_paths: Vec<String> = all_file_paths
-            .into_iter()
-            .filter(|p| !p.starts_with("web://"))
+    #[test]
+    fn file_opened_with_the_app_is_not_loaded_twice() {
+        let (settings, files, _dir) = persisted(StartupMode::RestoreSession);
+        // Spelled differently than the persisted path, as a file manager might
+        let file = std::path::Path::new(&files[0]);
+        let opened = file
+            .parent()
+            .unwrap()
+            .join(".")
+            .join(file.file_name().unwrap());
+        let cli = Settings::parse_from([
+            std::ffi::OsStr::new("large-track-viewer"),
+            opened.as_os_str(),
+        ])
+        .with_positional_files();
+        let (state, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(pending(&state), 2);
+    }
+
+    #[test]
+    fn cli_first_queues_shared_cli_files_at_the_front() {
+        let (mut settings, files, _dir) = persisted(StartupMode::RestoreSession);
+        settings.ui.file_precedence = format!("{:?}", FilePrecedence::CliFirst);
+        // The second restored file is also given on the command line
+        let cli =
+            Settings::parse_from(["large-track-viewer", files[1].as_str()]).with_positional_files();
+        let (state, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        let queued: Vec<_> = state
+            .file_loader
+            .pending_files
+            .iter()
+            .map(|file| file.path.clone().unwrap())
             .collect();
+        assert_eq!(
+            queued,
+            vec![
+                std::path::PathBuf::from(&files[1]),
+                std::path::PathBuf::from(&files[0])
+            ]
+        );
+        assert_eq!(state.ui_settings.file_precedence, FilePrecedence::CliFirst);
+    }
 
-        let settings = PersistedSettings {
-            line_width: self.state.ui_settings.line_width,
-            show_outline: self.state.ui_settings.show_outline,
-            bias: self.state.ui_settings.bias,
-            sidebar_open: self.state.ui_settings.sidebar_open,
-            active_tab: format!("{:?}", self.state.ui_settings.active_tab),
-            tiles_provider: format!("{:?}", self.state.ui_settings.tiles_provider),
-            show_profiling: self.state.ui_settings.show_profiling,
-            loaded_file_paths,
+    #[test]
+    fn fresh_starts_empty_but_keeps_the_file_list() {
+        let cli = Settings::parse_from(["large-track-viewer", "--fresh"]);
+        let (settings, files, _dir) = persisted(StartupMode::RestoreSession);
+        let (state, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert_eq!(pending(&state), 0);
+        assert_eq!(state.held_file_paths, files);
+        // The saved choice is untouched
+        assert_eq!(state.ui_settings.startup_mode, StartupMode::RestoreSession);
+    }
+
+    #[test]
+    fn files_importing_during_a_crash_are_offered_instead_of_retried() {
+        let cli = Settings::parse_from(["large-track-viewer"]);
+        let with_pending = || {
+            let (mut settings, files, dir) = persisted(StartupMode::RestoreSession);
+            settings.loaded_file_paths = vec![files[0].clone()];
+            settings.pending_file_paths = vec![files[1].clone()];
+            (settings, files, dir)
         };
 
-        // Serialize settings once and persist to both eframe storage and the platform backend.
-        if let Ok(json) = serde_json::to_string(&settings) {
-            // Persist to eframe storage (existing behavior)
-            storage.set_string("persisted_settings", json.clone());
-            tracing::debug!("Saved settings to eframe storage on exit");
+        let (settings, files, _dir) = with_pending();
+        let (state, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, true);
+        assert_eq!(
+            state.interrupted_import,
+            [std::path::PathBuf::from(&files[1])]
+        );
+        assert_eq!(pending(&state), 1);
 
-            // Persist to platform-specific default storage backend using the object-safe helpers.
-            // On web: default_storage_backend() returns a concrete backend (Box<dyn StorageBackend>).
-            // On native: default_storage_backend() returns Result<Box<dyn StorageBackend>, StorageError>.
-            #[cfg(target_arch = "wasm32")]
-            {
-                let backend = crate::app::storage::default_storage_backend();
-                match crate::app::storage::save_json_backend(
-                    backend.as_ref(),
-                    "persisted_settings",
-                    &settings,
-                ) {
-                    Ok(()) => tracing::debug!("Saved settings to web storage (localStorage)"),
-                    Err(e) => tracing::warn!("Failed to save settings to web storage: {:?}", e),
-                }
-            }
+        // After a clean exit they are simply loaded again
+        let (settings, _, _dir) = with_pending();
+        let (state, _) = LargeTrackViewerApp::startup_state(Some(settings), &cli, false);
+        assert!(state.interrupted_import.is_empty());
+        assert_eq!(pending(&state), 2);
+    }
 
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                match crate::app::storage::default_storage_backend() {
-                    Ok(backend_box) => {
-                        let backend = backend_box.as_ref();
-                        match crate::app::storage::save_json_backend(
-                            backend,
-                            "persisted_settings",
-                            &settings,
-                        ) {
-                            Ok(()) => tracing::debug!("Saved settings to file storage"),
-                            Err(e) => {
-                                tracing::warn!("Failed to save settings to file storage: {:?}", e)
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to initialize file storage backend: {:?}", e);
-                    }
-                }
-            }
+    /// The app without tiles, saving to `backend`
+    fn harness_with_backend(
+        backend: &MemoryBackend,
+    ) -> egui_kittest::Harness<'static, LargeTrackViewerApp> {
+        let backend = backend.clone();
+        egui_kittest::Harness::builder().build_eframe(move |cc| {
+            let cli = Settings::parse_from(["large-track-viewer", "--no-basemap"]);
+            LargeTrackViewerApp::with_settings(cc, cli, Some(Box::new(backend.clone())))
+        })
+    }
+
+    #[test]
+    fn session_marker_tells_a_crash_from_a_clean_exit() {
+        let backend = MemoryBackend::default();
+        let (mut settings, files, _dir) = persisted(StartupMode::RestoreSession);
+        settings.loaded_file_paths.clear();
+        settings.pending_file_paths = vec![files[1].clone()];
+        crate::app::storage::save_json_backend(&backend, PERSISTED_SETTINGS_KEY, &settings)
+            .unwrap();
+        // Left behind by a session that never reached `on_exit`
+        backend.set_string(SESSION_RUNNING_KEY, "1").unwrap();
+
+        let mut harness = harness_with_backend(&backend);
+        assert_eq!(
+            harness.state().state.interrupted_import,
+            [std::path::PathBuf::from(&files[1])]
+        );
+        assert!(backend.get_string(SESSION_RUNNING_KEY).unwrap().is_some());
+        eframe::App::on_exit(harness.state_mut(), None);
+        assert_eq!(backend.get_string(SESSION_RUNNING_KEY).unwrap(), None);
+        drop(harness);
+
+        let harness = harness_with_backend(&backend);
+        assert!(harness.state().state.interrupted_import.is_empty());
+    }
+
+    #[test]
+    fn unchanged_settings_are_not_written_again() {
+        let backend = MemoryBackend::default();
+        let mut harness = harness_with_backend(&backend);
+        let app = harness.state_mut();
+        app.persist_now(None, false);
+        let writes = backend.writes();
+        assert!(
+            backend
+                .get_string(PERSISTED_SETTINGS_KEY)
+                .unwrap()
+                .is_some()
+        );
+
+        app.persist_now(None, false);
+        assert_eq!(backend.writes(), writes);
+        // A change, or a forced save, writes again
+        app.state.ui_settings.line_width += 1.0;
+        app.persist_now(None, false);
+        assert_eq!(backend.writes(), writes + 1);
+        app.persist_now(None, true);
+        assert_eq!(backend.writes(), writes + 2);
+    }
+
+    /// The whole app in a landscape window, without tiles or a saved session
+    fn harness() -> egui_kittest::Harness<'static, LargeTrackViewerApp> {
+        egui_kittest::Harness::builder()
+            .with_size(egui::vec2(1280.0, 800.0))
+            .build_eframe(|cc| {
+                let cli = Settings::parse_from([
+                    "large-track-viewer",
+                    "--no-basemap",
+                    "--ignore-persisted",
+                ]);
+                LargeTrackViewerApp::with_settings(cc, cli, None)
+            })
+    }
+
+    #[test]
+    fn sidebar_toggle_opens_and_closes_the_panel() {
+        let mut harness = harness();
+        harness.run_steps(2);
+        assert!(harness.query_by_label("📂 Tracks").is_some());
+
+        harness.get_by_label("Close sidebar").click();
+        harness.run_steps(2);
+        assert!(!harness.state().state.ui_settings.sidebar_open);
+        assert!(harness.query_by_label("📂 Tracks").is_none());
+
+        harness.get_by_label("Open sidebar").click();
+        harness.run_steps(2);
+        assert!(harness.query_by_label("📂 Tracks").is_some());
+    }
+
+    #[test]
+    fn switching_tabs_shows_their_sections() {
+        let mut harness = harness();
+        harness.run_steps(2);
+        assert!(harness.query_by_label("🎯 Fit").is_some());
+
+        harness.get_by_label("⚙ Settings").click();
+        harness.run_steps(2);
+        assert!(harness.query_by_label("🎨 Track Appearance").is_some());
+        assert!(harness.query_by_label("🎯 Fit").is_none());
+
+        harness.get_by_label("📂 Tracks").click();
+        harness.run_steps(2);
+        assert!(harness.query_by_label("🎯 Fit").is_some());
+        assert!(harness.query_by_label("🎨 Track Appearance").is_none());
+    }
+
+    #[test]
+    fn loaded_file_is_listed_and_removing_it_empties_the_list() {
+        let mut harness = harness();
+        let gpx = r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+<trk><name>Fixture</name><trkseg>
+<trkpt lat="45.0" lon="6.0"/><trkpt lat="45.01" lon="6.01"/><trkpt lat="45.02" lon="6.0"/>
+</trkseg></trk></gpx>"#;
+        let state = &mut harness.state_mut().state;
+        state.queue_file(DroppedFile {
+            name: "fixture.gpx".to_string(),
+            bytes: Some(gpx.as_bytes().into()),
+            ..Default::default()
+        });
+        state.start_parallel_load();
+
+        let deadline = instant::Instant::now() + std::time::Duration::from_secs(10);
+        while harness.state().state.file_loader.loaded_files.is_empty()
+            && instant::Instant::now() < deadline
+        {
+            harness.step();
+            std::thread::sleep(std::time::Duration::from_millis(5));
         }
+        harness.run_steps(2);
+        assert_eq!(harness.state().state.stats.route_count, 1);
+        assert!(harness.query_by_label("✓ Loaded Files").is_some());
+        // Dropped bytes are listed under their name and a hash of the content
+        assert!(harness.query_by_label_contains("📄 fixture.gpx").is_some());
+
+        harness.get_by_label("🗑").click();
+        harness.run_steps(2);
+        assert!(harness.state().state.file_loader.loaded_files.is_empty());
+        assert!(harness.query_by_label_contains("📄 fixture.gpx").is_none());
+        assert!(harness.query_by_label("✓ Loaded Files").is_none());
     }
 }
@@ -0,0 +1,52 @@
+//! The "reduce animations" accessibility setting
+//!
+//! With it on, animations jump straight to their final state: fades turn into
+//! plain show/hide, egui's own transitions (collapsing headers, tooltips) are
+//! instant, camera moves jump instead of gliding and the map stops as soon as
+//! a drag is let go. Anything that animates asks [`animations_enabled`].
+
+use crate::app::state::UiSettings;
+
+/// Whether to animate at all; every fade, glide and pulse checks this
+pub fn animations_enabled(settings: &UiSettings) -> bool {
+    !settings.reduce_motion
+}
+
+/// Whether the system asks apps to keep motion to a minimum
+///
+/// Only the browser exposes this (the `prefers-reduced-motion` media query);
+/// elsewhere the answer is `false` and the setting starts off.
+pub fn system_prefers_reduced_motion() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| {
+                window
+                    .match_media("(prefers-reduced-motion: reduce)")
+                    .ok()
+                    .flatten()
+            })
+            .is_some_and(|query| query.matches())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        false
+    }
+}
+
+/// Seconds egui spends on its own transitions
+pub fn animation_time(animate: bool) -> f32 {
+    if animate {
+        egui::Style::default().animation_time
+    } else {
+        0.0
+    }
+}
+
+/// Make egui's own transitions follow the setting; cheap to call every frame
+pub fn apply(ctx: &egui::Context, settings: &UiSettings) {
+    let wanted = animation_time(animations_enabled(settings));
+    if ctx.style().animation_time != wanted {
+        ctx.all_styles_mut(|style| style.animation_time = wanted);
+    }
+}
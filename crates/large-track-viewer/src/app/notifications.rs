@@ -0,0 +1,319 @@
+//! Short user-facing messages shown as stacked toasts over the map
+//!
+//! Anything the user should notice but doesn't need to act on goes through
+//! here instead of a dedicated label somewhere in the sidebar. Toasts expire
+//! on their own (errors stay up a little longer than notices), fade in and
+//! out, and may carry one action button such as "Undo".
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// At most this many toasts are kept; older ones are dropped first
+const MAX_NOTIFICATIONS: usize = 5;
+
+/// How long a toast takes to fade in, and again to fade out
+const FADE: Duration = Duration::from_millis(150);
+
+/// How serious a notification is, which decides its color and lifetime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl NotificationLevel {
+    /// How long a notification of this level stays visible
+    pub fn lifetime(&self) -> Duration {
+        match self {
+            Self::Info => Duration::from_secs(3),
+            Self::Warn => Duration::from_secs(5),
+            Self::Error => Duration::from_secs(8),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::Info => "ℹ",
+            Self::Warn => "⚠",
+            Self::Error => "❌",
+        }
+    }
+}
+
+/// Something a toast's button does when clicked
+#[derive(Clone, Debug, PartialEq)]
+pub enum NotificationAction {
+    /// Load these files again, e.g. to undo clearing them
+    ReloadFiles(Vec<PathBuf>),
+    /// Put back the file split into these parts
+    UndoSplit([PathBuf; 2]),
+}
+
+impl NotificationAction {
+    /// Text of the button
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::ReloadFiles(_) | Self::UndoSplit(_) => "Undo",
+        }
+    }
+}
+
+/// One message in the toast queue
+#[derive(Clone, Debug)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub text: String,
+    pub created_at: instant::Instant,
+    /// How long it stays up, fades included
+    pub lifetime: Duration,
+    pub action: Option<NotificationAction>,
+}
+
+impl Notification {
+    /// When this notification should disappear
+    pub fn expires_at(&self) -> instant::Instant {
+        self.created_at + self.lifetime
+    }
+
+    /// Opacity at `now`, see [`fade_alpha`]
+    pub fn alpha(&self, now: instant::Instant, animate: bool) -> f32 {
+        fade_alpha(
+            now.saturating_duration_since(self.created_at),
+            self.lifetime,
+            animate,
+        )
+    }
+}
+
+/// Opacity of something shown for `lifetime`, `elapsed` after it appeared
+///
+/// Fades in over the first 0.15s and out over the last 0.15s (each at most
+/// a third of `lifetime`); without `animate` (see
+/// [`crate::app::motion::animations_enabled`]) it is simply shown until it
+/// expires.
+pub fn fade_alpha(elapsed: Duration, lifetime: Duration, animate: bool) -> f32 {
+    if elapsed >= lifetime {
+        return 0.0;
+    }
+    if !animate {
+        return 1.0;
+    }
+    let fade = FADE.min(lifetime / 3).as_secs_f32();
+    let fade_in = elapsed.as_secs_f32() / fade;
+    let fade_out = (lifetime - elapsed).as_secs_f32() / fade;
+    fade_in.min(fade_out).min(1.0)
+}
+
+/// Toasts currently shown, oldest first
+#[derive(Clone, Debug, Default)]
+pub struct Toasts {
+    items: Vec<Notification>,
+}
+
+impl Toasts {
+    /// Show `text` for `lifetime`, dropping the oldest toasts past [`MAX_NOTIFICATIONS`]
+    ///
+    /// Pushing the same text and level as a toast still up keeps that one up
+    /// for `lifetime` from now instead of stacking a copy, so a message sent
+    /// every frame while something lasts (like the wheel warning) stays put.
+    pub fn push(
+        &mut self,
+        level: NotificationLevel,
+        text: String,
+        lifetime: Duration,
+        action: Option<NotificationAction>,
+        now: instant::Instant,
+    ) {
+        if let Some(existing) = self
+            .items
+            .iter_mut()
+            .find(|n| n.level == level && n.text == text && n.expires_at() > now)
+        {
+            existing.lifetime = existing
+                .lifetime
+                .max(now.saturating_duration_since(existing.created_at) + lifetime);
+            existing.action = action;
+            return;
+        }
+        self.items.push(Notification {
+            level,
+            text,
+            created_at: now,
+            lifetime,
+            action,
+        });
+        let excess = self.items.len().saturating_sub(MAX_NOTIFICATIONS);
+        self.items.drain(..excess);
+    }
+
+    /// Remove expired notifications; returns the time until the next one expires
+    pub fn expire(&mut self, now: instant::Instant) -> Option<Duration> {
+        self.items.retain(|n| n.expires_at() > now);
+        self.items
+            .iter()
+            .map(|n| n.expires_at().duration_since(now))
+            .min()
+    }
+
+    /// When to draw again for the fades and expiries to show, `None` with no toasts
+    pub fn repaint_after(&self, now: instant::Instant, animate: bool) -> Option<Duration> {
+        self.items
+            .iter()
+            .map(|n| {
+                let remaining = n.expires_at().saturating_duration_since(now);
+                if !animate {
+                    remaining
+                } else if n.alpha(now, true) < 1.0 {
+                    Duration::ZERO
+                } else {
+                    remaining.saturating_sub(FADE.min(n.lifetime / 3))
+                }
+            })
+            .min()
+    }
+
+    /// Take away the toast at `index` (in [`Toasts::iter`] order) before it expires
+    pub fn dismiss(&mut self, index: usize) -> Option<Notification> {
+        (index < self.items.len()).then(|| self.items.remove(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Notification> {
+        self.items.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push(toasts: &mut Toasts, level: NotificationLevel, text: &str, now: instant::Instant) {
+        toasts.push(level, text.to_string(), level.lifetime(), None, now);
+    }
+
+    #[test]
+    fn notifications_expire_by_level() {
+        let start = instant::Instant::now();
+        let mut notifications = Toasts::default();
+        push(&mut notifications, NotificationLevel::Info, "Copied", start);
+        push(
+            &mut notifications,
+            NotificationLevel::Error,
+            "Failed",
+            start + Duration::from_secs(1),
+        );
+
+        // Nothing expired yet; the info toast goes first
+        let next = notifications.expire(start + Duration::from_secs(2));
+        assert_eq!(notifications.iter().count(), 2);
+        assert_eq!(next, Some(Duration::from_secs(1)));
+
+        // The info toast is gone, the error stays until 1 + 8 seconds
+        let next = notifications.expire(start + Duration::from_secs(3));
+        assert_eq!(notifications.iter().count(), 1);
+        assert_eq!(
+            notifications.iter().next().unwrap().level,
+            NotificationLevel::Error
+        );
+        assert_eq!(next, Some(Duration::from_secs(6)));
+
+        assert_eq!(notifications.expire(start + Duration::from_secs(9)), None);
+        assert_eq!(notifications.iter().count(), 0);
+    }
+
+    #[test]
+    fn oldest_notifications_are_dropped_past_the_cap() {
+        let now = instant::Instant::now();
+        let mut notifications = Toasts::default();
+        for i in 0..MAX_NOTIFICATIONS + 2 {
+            push(
+                &mut notifications,
+                NotificationLevel::Warn,
+                &i.to_string(),
+                now,
+            );
+        }
+        assert_eq!(notifications.iter().count(), MAX_NOTIFICATIONS);
+        assert_eq!(notifications.iter().next().unwrap().text, "2");
+    }
+
+    #[test]
+    fn repeated_toasts_extend_instead_of_stacking() {
+        let start = instant::Instant::now();
+        let mut toasts = Toasts::default();
+        let lifetime = Duration::from_secs(1);
+        for ms in [0, 400, 800] {
+            toasts.push(
+                NotificationLevel::Info,
+                "Hold Ctrl to zoom".into(),
+                lifetime,
+                None,
+                start + Duration::from_millis(ms),
+            );
+        }
+        assert_eq!(toasts.iter().count(), 1);
+        // Up until a second after the last push, without fading in again
+        let toast = toasts.iter().next().unwrap();
+        assert_eq!(toast.expires_at(), start + Duration::from_millis(1800));
+        assert_eq!(toast.alpha(start + Duration::from_millis(900), false), 1.0);
+        assert_eq!(
+            toasts
+                .expire(start + Duration::from_millis(1700))
+                .map(|d| d.as_millis()),
+            Some(100)
+        );
+
+        // Once expired the same text is a new toast
+        toasts.expire(start + Duration::from_secs(2));
+        toasts.push(
+            NotificationLevel::Info,
+            "Hold Ctrl to zoom".into(),
+            lifetime,
+            Some(NotificationAction::ReloadFiles(Vec::new())),
+            start + Duration::from_secs(2),
+        );
+        assert_eq!(toasts.iter().count(), 1);
+        let dismissed = toasts.dismiss(0).unwrap();
+        assert_eq!(dismissed.action.unwrap().label(), "Undo");
+        assert_eq!(toasts.iter().count(), 0);
+        assert!(toasts.dismiss(0).is_none());
+    }
+
+    #[test]
+    fn toasts_fade_in_and_out() {
+        let lifetime = Duration::from_millis(500);
+        let at = |ms: u64, animate: bool| fade_alpha(Duration::from_millis(ms), lifetime, animate);
+        assert_eq!(at(0, true), 0.0);
+        assert!((at(75, true) - 0.5).abs() < 1e-6);
+        assert_eq!(at(150, true), 1.0);
+        assert_eq!(at(300, true), 1.0);
+        assert!((at(425, true) - 0.5).abs() < 1e-6);
+        assert_eq!(at(500, true), 0.0);
+
+        // Without animations it shows at once for the whole lifetime
+        for ms in [0, 75, 300, 499] {
+            assert_eq!(at(ms, false), 1.0, "at {}ms", ms);
+        }
+        assert_eq!(at(500, false), 0.0);
+
+        // Repaint right away while fading, otherwise when the fade out starts
+        let start = instant::Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push(
+            NotificationLevel::Info,
+            "Saved".into(),
+            lifetime,
+            None,
+            start,
+        );
+        assert_eq!(toasts.repaint_after(start, true), Some(Duration::ZERO));
+        assert_eq!(
+            toasts.repaint_after(start + Duration::from_millis(200), true),
+            Some(Duration::from_millis(150))
+        );
+        assert_eq!(
+            toasts.repaint_after(start + Duration::from_millis(200), false),
+            Some(Duration::from_millis(300))
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Layers and placement of what floats over the map
+//!
+//! Overlays are placed relative to the map panel, never the whole window,
+//! so they can't end up over the sidebar in either of its layouts. From
+//! bottom to top, the app draws:
+//!
+//! 1. The sidebar and the map panel, with what is painted inside the map
+//!    (crosshair, distance rings, attribution), on egui's background layer.
+//! 2. Overlays, each on its own [`OVERLAY_ORDER`] layer: the track legend
+//!    (top left of the map), the tile failure banner (top center) and the
+//!    notifications, wheel-zoom warning included (bottom right). They sit in
+//!    separate corners, so their order among each other never shows.
+//! 3. The drop preview while files are dragged in, painted straight on its
+//!    [`OVERLAY_ORDER`] layer, which egui draws above that order's areas.
+//! 4. Dialogs (help and prompts) on [`DIALOG_ORDER`], so no overlay covers them.
+//! 5. egui's own tooltips and menus, shown above the rest as they open.
+
+use egui::{Align2, Area, Context, Id, LayerId, Order, Rect, Vec2};
+
+/// egui order of the overlays over the map
+pub const OVERLAY_ORDER: Order = Order::Foreground;
+
+/// egui order of dialog windows, above every overlay
+pub const DIALOG_ORDER: Order = Order::Tooltip;
+
+/// The layer of the overlay called `name`
+pub fn layer(name: &'static str) -> LayerId {
+    LayerId::new(OVERLAY_ORDER, Id::new(name))
+}
+
+/// An area for the overlay called `name`, with its `align` corner (or edge
+/// center) `offset` from the same corner of `map_rect`, and kept inside it
+pub fn area(name: &'static str, map_rect: Rect, align: Align2, offset: Vec2) -> Area {
+    Area::new(Id::new(name))
+        .order(OVERLAY_ORDER)
+        .pivot(align)
+        .fixed_pos(align.pos_in_rect(&map_rect) + offset)
+        .constrain_to(map_rect)
+}
+
+/// Where the map was last drawn, or the whole window before the first frame
+pub fn map_rect(ctx: &Context, last_map_rect: Option<Rect>) -> Rect {
+    last_map_rect.unwrap_or_else(|| ctx.content_rect())
+}
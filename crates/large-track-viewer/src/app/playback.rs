@@ -0,0 +1,206 @@
+//! Replaying a route with the map following along
+//!
+//! A cursor moves along one route at a chosen speed while the camera stays
+//! centered on it. Timestamped routes are replayed in track time, so pauses
+//! and climbs play out as they were ridden; routes without timestamps advance
+//! a fixed number of points per second instead.
+
+use large_track_lib::TrackProfile;
+
+/// Playback speeds offered in the UI, as multiples of real time
+pub const PLAYBACK_SPEEDS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+/// Points advanced per second at 1× on routes without timestamps
+const POINTS_PER_SECOND: f64 = 10.0;
+
+/// Cursor along the route being replayed
+#[derive(Clone, Debug)]
+pub struct Playback {
+    /// Route index (into the collection) being replayed, `None` when stopped
+    route: Option<usize>,
+    /// Track seconds from the first timestamp, or a fractional point index
+    /// on routes without timestamps
+    cursor: f64,
+    /// Whether the cursor advances with the clock
+    pub playing: bool,
+    /// Multiple of real time the cursor moves at
+    pub speed: f64,
+    /// Whether the cursor was moved by hand since the last frame
+    seeked: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            route: None,
+            cursor: 0.0,
+            playing: false,
+            speed: 10.0,
+            seeked: false,
+        }
+    }
+}
+
+impl Playback {
+    /// Route being replayed
+    pub fn route(&self) -> Option<usize> {
+        self.route
+    }
+
+    /// Start replaying `route` from its beginning
+    pub fn start(&mut self, route: usize) {
+        self.route = Some(route);
+        self.cursor = 0.0;
+        self.playing = true;
+        self.seeked = true;
+    }
+
+    /// Stop and forget the route, e.g. because route indices changed
+    pub fn stop(&mut self) {
+        self.route = None;
+        self.playing = false;
+    }
+
+    /// Move the cursor by `elapsed` seconds of wall-clock time while playing
+    ///
+    /// Playback pauses at the end of the route. Returns whether the cursor
+    /// moved, by playing or by a [`Playback::seek`] since the last call, so
+    /// the camera only follows while playback is driving it.
+    pub fn advance(&mut self, profile: &TrackProfile, elapsed: f64) -> bool {
+        let seeked = std::mem::take(&mut self.seeked);
+        if !self.playing || self.route.is_none() {
+            return seeked;
+        }
+        let rate = if Self::by_time(profile) {
+            self.speed
+        } else {
+            self.speed * POINTS_PER_SECOND
+        };
+        let end = Self::end(profile);
+        self.cursor = (self.cursor + elapsed.max(0.0) * rate).min(end);
+        if self.cursor >= end {
+            self.playing = false;
+        }
+        true
+    }
+
+    /// Point under the cursor, an index into the profile's points
+    pub fn index(&self, profile: &TrackProfile) -> usize {
+        if Self::by_time(profile) {
+            profile.index_at_time(self.cursor).unwrap_or(0)
+        } else {
+            (self.cursor as usize).min(profile.len().saturating_sub(1))
+        }
+    }
+
+    /// How far along the route the cursor is, from 0 to 1
+    pub fn progress(&self, profile: &TrackProfile) -> f64 {
+        let end = Self::end(profile);
+        if end > 0.0 { self.cursor / end } else { 0.0 }
+    }
+
+    /// Put the cursor at `fraction` (0 to 1) of the route
+    pub fn seek(&mut self, profile: &TrackProfile, fraction: f64) {
+        self.cursor = fraction.clamp(0.0, 1.0) * Self::end(profile);
+        self.seeked = true;
+    }
+
+    /// Seconds of track time under the cursor, `None` on routes without timestamps
+    pub fn track_time(&self, profile: &TrackProfile) -> Option<f64> {
+        Self::by_time(profile).then_some(self.cursor)
+    }
+
+    /// Whether `profile` is replayed in track time rather than by point count
+    fn by_time(profile: &TrackProfile) -> bool {
+        profile.duration().is_some_and(|duration| duration > 0.0)
+    }
+
+    /// Last cursor value: the duration, or the last point index
+    fn end(profile: &TrackProfile) -> f64 {
+        if Self::by_time(profile) {
+            profile.duration().unwrap_or(0.0)
+        } else {
+            profile.len().saturating_sub(1) as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use large_track_lib::{Route, RouteBuilder, TrackPoint};
+    use std::sync::Arc;
+
+    /// One point per `seconds` entry, ~11 m apart along the equator
+    fn route(seconds: &[Option<i64>]) -> Arc<Route> {
+        let points = seconds.iter().enumerate().map(|(i, seconds)| TrackPoint {
+            time: seconds.map(|seconds| {
+                time::OffsetDateTime::from_unix_timestamp(1_700_000_000 + seconds).unwrap()
+            }),
+            ..TrackPoint::new(0.0, i as f64 * 0.0001)
+        });
+        RouteBuilder::from_gpx(gpx::Gpx::default())
+            .add_segment(points)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn timestamped_routes_advance_in_track_time() {
+        // A 60 s stop between the second and third points
+        let route = route(&[Some(0), Some(10), Some(70), Some(80), Some(90)]);
+        let profile = route.profile();
+        let mut playback = Playback {
+            speed: 10.0,
+            ..Default::default()
+        };
+        playback.start(0);
+        assert!(playback.advance(profile, 0.0));
+        assert_eq!(playback.index(profile), 0);
+
+        // 1.5 s at 10× is 15 s of track time: past the second point
+        assert!(playback.advance(profile, 1.5));
+        assert_eq!(playback.track_time(profile), Some(15.0));
+        assert_eq!(playback.index(profile), 1);
+        // The stop takes six seconds at 10×
+        playback.advance(profile, 5.0);
+        assert_eq!(playback.index(profile), 1);
+        playback.advance(profile, 1.0);
+        assert_eq!(playback.index(profile), 2);
+
+        // Playback pauses on the last point
+        playback.advance(profile, 100.0);
+        assert_eq!(playback.index(profile), 4);
+        assert_eq!(playback.progress(profile), 1.0);
+        assert!(!playback.playing);
+        assert!(!playback.advance(profile, 1.0));
+
+        // Seeking moves the cursor once, even while paused
+        playback.seek(profile, 0.5);
+        assert_eq!(playback.track_time(profile), Some(45.0));
+        assert!(playback.advance(profile, 1.0));
+        assert_eq!(playback.index(profile), 1);
+        assert!(!playback.advance(profile, 1.0));
+    }
+
+    #[test]
+    fn routes_without_timestamps_advance_by_points() {
+        let route = route(&[None; 50]);
+        let profile = route.profile();
+        let mut playback = Playback {
+            speed: 2.0,
+            ..Default::default()
+        };
+        playback.start(0);
+        assert_eq!(playback.track_time(profile), None);
+        playback.advance(profile, 1.0);
+        assert_eq!(playback.index(profile), 2 * POINTS_PER_SECOND as usize);
+        playback.advance(profile, 10.0);
+        assert_eq!(playback.index(profile), 49);
+        assert!(!playback.playing);
+
+        playback.stop();
+        assert_eq!(playback.route(), None);
+        assert!(!playback.advance(profile, 1.0));
+    }
+}
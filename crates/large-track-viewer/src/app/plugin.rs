@@ -3,12 +3,26 @@
 //! This module provides a custom walkers plugin that queries visible route segments
 //! from the data module and renders them on the map with proper LOD handling.
 
+use crate::app::location::{ExternalPosition, LiveFix};
+use crate::app::render::{
+    BlendMode, COMPARE_COLORS, MIN_STACK_OFFSET_ZOOM, PreparedTracks, SkipCounters, TrackStyle,
+    WidthMode, full_detail, meters_to_points, prepare_tracks,
+};
+use crate::app::roi::RoiPolygon;
+use crate::app::style_rules::DashPattern;
+use crate::app::symbols::{self, SymbolIcon};
 use eframe_entrypoints::async_runtime::RwLock;
 use egui::{Color32, Stroke};
+use large_track_lib::utils::meters_per_pixel;
 use large_track_lib::{RouteCollection, SimplifiedSegment};
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 use walkers::{Plugin, Projector};
 
+/// How often to look for refined segments while background refinement runs,
+/// instead of repainting every frame until it is done
+const REFINEMENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
 /// Statistics from the last render pass
 #[derive(Default, Clone, Debug)]
 pub struct RenderStats {
@@ -16,177 +30,192 @@ pub struct RenderStats {
     pub segments_rendered: usize,
     /// Number of simplified points rendered (actual points drawn)
     pub simplified_points_rendered: usize,
+    /// Why queried segments were not drawn
+    pub skipped: SkipCounters,
+    /// Routes that had segments in view but none of them drawn, sorted
+    pub fully_skipped_routes: Vec<usize>,
+    /// Routes with something drawn, sorted
+    pub visible_routes: Vec<usize>,
+    /// Milliseconds spent handing outlines to the painter
+    pub outline_pass_ms: f64,
+    /// Milliseconds spent handing track lines to the painter
+    pub line_pass_ms: f64,
+}
+
+impl RenderStats {
+    /// Take the figures of a frame that drew `prepared` from `segments_rendered`
+    /// queried segments, with the time of its line and outline passes
+    pub fn record_frame(
+        &mut self,
+        segments_rendered: usize,
+        prepared: &PreparedTracks,
+        [line_pass_ms, outline_pass_ms]: [f64; 2],
+    ) {
+        self.segments_rendered = segments_rendered;
+        self.simplified_points_rendered = prepared.points_drawn;
+        self.skipped.clone_from(&prepared.skipped);
+        self.fully_skipped_routes
+            .clone_from(&prepared.fully_skipped_routes);
+        self.visible_routes.clone_from(&prepared.drawn_routes);
+        self.outline_pass_ms = outline_pass_ms;
+        self.line_pass_ms = line_pass_ms;
+    }
+}
+
+/// Settings of the track plugin for one frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameParams {
+    /// Line width for rendering tracks
+    pub width: f32,
+    /// Whether to show outline/border around tracks
+    pub show_outline: bool,
+    /// Routes drawn in fixed contrasting colors while being compared
+    pub compare_pair: Option<(usize, usize)>,
+    /// Colors picked for routes, by route index
+    pub route_colors: BTreeMap<usize, Color32>,
+    /// Dash patterns given to routes by style rules, by route index
+    pub route_dashes: BTreeMap<usize, DashPattern>,
+    /// How overlapping track lines combine
+    pub blend_mode: BlendMode,
+    /// Route hovered in the legend, drawn emphasized
+    pub hovered: Option<usize>,
+    /// LOD levels below the viewport's own to draw at (see [`crate::app::frame_budget`])
+    pub lod_reduction: u32,
+    /// Detail to query at relative to the index's bias, while a new bias
+    /// waits to be applied (see [`crate::app::detail::BiasDebouncer`])
+    pub detail_preview: f64,
+    /// Whether `width` is in pixels or `width_m` in meters applies
+    pub width_mode: WidthMode,
+    /// Track width on the ground in [`WidthMode::Meters`]
+    pub width_m: f32,
+    /// Map clicks go to a tool (like splitting a route) instead of selecting routes
+    pub tool_takes_clicks: bool,
+    /// Pixels between tracks on the same path when zoomed in, 0 to leave them stacked
+    pub stack_offset: f32,
+    /// Draw the selected route from all its points in view (see [`full_detail`])
+    pub full_detail_selection: bool,
 }
 
 /// Plugin for rendering GPX tracks on the map
+///
+/// Lives as long as the app and is lent to the map every frame, so the
+/// buffers tracks are prepared in keep their memory from frame to frame.
+/// What changes per frame comes in through [`TrackPlugin::begin_frame`].
 pub struct TrackPlugin {
-    /// Reference to the route collection
+    /// Reference to the route collection (replaced when it is rebuilt)
     collection: Arc<RwLock<RouteCollection>>,
-    /// Line width for rendering tracks
-    width: f32,
-    /// Whether to show outline/border around tracks
-    show_outline: bool,
+    /// Settings of the current frame
+    params: FrameParams,
     /// Shared statistics output (updated after each render)
     stats: Arc<RwLock<RenderStats>>,
     /// Shared selected route handle (owned by AppState). Use async RwLock for cross-platform compatibility.
     selected: Arc<RwLock<Option<usize>>>,
+    /// Shared polygon region of interest (owned by AppState)
+    roi: Arc<RwLock<RoiPolygon>>,
+    /// Polylines of the last frame, cleared and refilled every frame
+    prepared: PreparedTracks,
+    /// Region of interest in screen space, reprojected every frame
+    clip_polygon: Vec<egui::Pos2>,
+    /// Routes an embedding app wants highlighted, see [`TrackPlugin::with_selection_highlight`]
+    external_selection: Option<Arc<RwLock<HashSet<usize>>>>,
+    /// Copy of `external_selection` taken for the current frame
+    highlighted: HashSet<usize>,
 }
 
 impl TrackPlugin {
     /// Create a new track plugin with a shared stats output and a shared selection handle
     pub fn new(
         collection: Arc<RwLock<RouteCollection>>,
-        width: f32,
-        show_outline: bool,
         stats: Arc<RwLock<RenderStats>>,
         selected: Arc<RwLock<Option<usize>>>,
+        roi: Arc<RwLock<RoiPolygon>>,
     ) -> Self {
         Self {
             collection,
-            width,
-            show_outline,
+            params: FrameParams::default(),
             stats,
             selected,
+            roi,
+            prepared: PreparedTracks::default(),
+            clip_polygon: Vec::new(),
+            external_selection: None,
+            highlighted: HashSet::new(),
         }
     }
 
-    /// Generate a color for a route based on its index
-    fn get_route_color(route_id: usize) -> Color32 {
-        // Use golden angle for good color distribution
-        let hue = (route_id as f32 * 137.508) % 360.0;
-        let saturation = 0.75;
-        let value = 0.85;
-
-        // Convert HSV to RGB
-        let c = value * saturation;
-        let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
-        let m = value - c;
-
-        let (r, g, b) = if hue < 60.0 {
-            (c, x, 0.0)
-        } else if hue < 120.0 {
-            (x, c, 0.0)
-        } else if hue < 180.0 {
-            (0.0, c, x)
-        } else if hue < 240.0 {
-            (0.0, x, c)
-        } else if hue < 300.0 {
-            (x, 0.0, c)
-        } else {
-            (c, 0.0, x)
-        };
-
-        Color32::from_rgb(
-            ((r + m) * 255.0) as u8,
-            ((g + m) * 255.0) as u8,
-            ((b + m) * 255.0) as u8,
-        )
+    /// Also highlight the routes in `selection`, like the selected one
+    ///
+    /// For embedding apps that pick routes in their own UI: they update the
+    /// set and the next frame draws it, whatever the internal selection and
+    /// legend hover are.
+    #[allow(dead_code)] // For embedders: the bundled binary never calls it
+    pub fn with_selection_highlight(mut self, selection: Arc<RwLock<HashSet<usize>>>) -> Self {
+        self.external_selection = Some(selection);
+        self
     }
 
-    /// Render a single simplified segment and return the number of points drawn
-    fn render_segment(
-        &self,
-        segment: &SimplifiedSegment,
-        projector: &Projector,
-        painter: &egui::Painter,
-    ) -> usize {
-        // Use route_index as a stable, cheap color seed (avoids hashing metadata string)
-        let color = Self::get_route_color(segment.route_index);
-
-        // Tag render work with route index so heavy draws can be attributed to routes.
-        #[cfg(feature = "profiling")]
-        profiling::scope!(
-            "plugin::render_segment",
-            format!("route={}", segment.route_index).as_str()
-        );
-
-        // Inner stroke with the route color
-        let inner_stroke = Stroke::new(self.width, color);
-        // Outer stroke (dark outline) for better visibility - only used if show_outline is true
-        let outline_stroke = Stroke::new(self.width + 2.0, Color32::from_black_alpha(180));
-
-        let mut points_drawn = 0;
-
-        for part in &segment.parts {
-            let points = part.get_simplified_points(&segment.route);
-
-            if points.is_empty() {
-                continue;
-            }
-
-            // Convert WGS84 coordinates to screen space
-            // Pre-allocate to avoid repeated allocations during mapping
-            let mut screen_points: Vec<egui::Pos2> = Vec::with_capacity(points.len());
-            for waypoint in points {
-                let point = waypoint.point();
-                let position = walkers::lat_lon(point.y(), point.x());
-                let screen_vec = projector.project(position);
-                screen_points.push(egui::Pos2::new(screen_vec.x, screen_vec.y));
-            }
-
-            // Draw the polyline if we have at least 2 points
-            if screen_points.len() >= 2 {
-                points_drawn += screen_points.len();
-
-                if self.show_outline {
-                    // Draw outline first (underneath)
-                    painter.add(egui::Shape::line(screen_points.clone(), outline_stroke));
-                }
-                // Draw colored line on top
-                painter.add(egui::Shape::line(screen_points, inner_stroke));
-            }
+    /// Copy the external selection for this frame, keeping the last one if it is locked
+    fn refresh_highlighted(&mut self) {
+        let Some(selection) = &self.external_selection else {
+            return;
+        };
+        let highlighted = &mut self.highlighted;
+        let mut copy = |set: &HashSet<usize>| {
+            highlighted.clear();
+            highlighted.extend(set);
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        eframe_entrypoints::async_runtime::blocking_read(selection, &mut copy);
+        #[cfg(target_arch = "wasm32")]
+        if let Ok(set) = selection.try_read() {
+            copy(&set);
         }
-
-        points_drawn
     }
 
-    /// Render a segment using an explicit highlight color/stroke (used for selected route)
-    fn render_segment_highlight(
-        &self,
-        segment: &SimplifiedSegment,
-        projector: &Projector,
-        painter: &egui::Painter,
-    ) {
-        #[cfg(feature = "profiling")]
-        profiling::scope!("plugin::render_segment_highlight");
-        let highlight_color = Color32::from_rgb(255, 200, 0);
-        let highlight_stroke = Stroke::new(self.width + 3.0, highlight_color);
-        let outline_stroke = Stroke::new(self.width + 5.0, Color32::from_black_alpha(200));
-
-        for part in &segment.parts {
-            let points = part.get_points_with_context(&segment.route);
-
-            if points.is_empty() {
-                continue;
-            }
-
-            // Pre-allocate screen_points to avoid allocation churn during rendering
-            let mut screen_points: Vec<egui::Pos2> = Vec::with_capacity(points.len());
-            for waypoint in points {
-                let point = waypoint.point();
-                let position = walkers::lat_lon(point.y(), point.x());
-                let screen_vec = projector.project(position);
-                screen_points.push(egui::Pos2::new(screen_vec.x, screen_vec.y));
-            }
+    /// Set what to draw this frame, from `collection`
+    pub fn begin_frame(&mut self, collection: &Arc<RwLock<RouteCollection>>, params: FrameParams) {
+        if !Arc::ptr_eq(&self.collection, collection) {
+            self.collection = collection.clone();
+        }
+        self.params = params;
+    }
 
-            if screen_points.len() >= 2 {
-                if self.show_outline {
-                    painter.add(egui::Shape::line(screen_points.clone(), outline_stroke));
-                }
-                painter.add(egui::Shape::line(screen_points, highlight_stroke));
+    /// Draw the region of interest outline (open while it is still being drawn)
+    fn render_roi(roi: &RoiPolygon, projector: &Projector, painter: &egui::Painter) {
+        let mut screen_points: Vec<egui::Pos2> = roi
+            .vertices
+            .iter()
+            .map(|position| {
+                let v = projector.project(*position);
+                egui::pos2(v.x, v.y)
+            })
+            .collect();
+
+        let stroke = Stroke::new(2.0, Color32::from_rgb(0, 120, 255));
+        if roi.drawing {
+            for point in &screen_points {
+                painter.circle_filled(*point, 4.0, stroke.color);
             }
+        } else if roi.closed
+            && let Some(first) = screen_points.first().copied()
+        {
+            screen_points.push(first);
+        }
+        if screen_points.len() >= 2 {
+            painter.add(egui::Shape::line(screen_points, stroke));
         }
     }
 }
 
-impl Plugin for TrackPlugin {
+impl Plugin for &mut TrackPlugin {
     fn run(
         self: Box<Self>,
         ui: &mut egui::Ui,
         response: &egui::Response,
         projector: &Projector,
-        _map_memory: &walkers::MapMemory,
+        map_memory: &walkers::MapMemory,
     ) {
+        let this: &mut TrackPlugin = *self;
         let painter = ui.painter();
 
         // Get the viewport bounds in screen space
@@ -235,7 +264,16 @@ impl Plugin for TrackPlugin {
             // Query visible segments from the collection
             // Pass screen size for dynamic LOD adjustment
             let screen_size = (viewport_rect.width() as f64, viewport_rect.height() as f64);
-            let segments: Vec<SimplifiedSegment> = {
+            // Tolerance shrinks in proportion to the screen's sides, as it
+            // does with the bias: a scaled screen previews a bias change
+            // without rebuilding the index
+            let preview = if this.params.detail_preview > 0.0 {
+                this.params.detail_preview
+            } else {
+                1.0
+            };
+            let query_screen_size = (screen_size.0 * preview, screen_size.1 * preview);
+            let mut segments: Vec<SimplifiedSegment> = {
                 // Attach a tag with screen and viewport size to the query span for better filtering.
                 #[cfg(feature = "profiling")]
                 {
@@ -255,24 +293,71 @@ impl Plugin for TrackPlugin {
                 {
                     // Block briefly on native to ensure we get a consistent result
                     eframe_entrypoints::async_runtime::blocking_read(
-                        &self.collection,
-                        |collection| collection.query_visible(viewport, screen_size),
+                        &this.collection,
+                        |collection| {
+                            let segments = collection.query_visible_coarser(
+                                viewport,
+                                query_screen_size,
+                                this.params.lod_reduction,
+                            );
+                            // Some segments were drawn coarse; draw again once refined
+                            if collection.pending_refinements() > 0 {
+                                ui.ctx().request_repaint_after(REFINEMENT_POLL_INTERVAL);
+                            }
+                            segments
+                        },
                     )
                 }
                 #[cfg(target_arch = "wasm32")]
                 {
                     // On web avoid blocking the main thread; fall back to try_read.
-                    if let Ok(collection) = self.collection.try_read() {
-                        collection.query_visible(viewport, screen_size)
+                    if let Ok(collection) = this.collection.try_read() {
+                        collection.query_visible_coarser(
+                            viewport,
+                            query_screen_size,
+                            this.params.lod_reduction,
+                        )
                     } else {
                         Vec::new()
                     }
                 }
             };
 
+            // Snapshot the region of interest for this frame
+            let roi = {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    eframe_entrypoints::async_runtime::blocking_read(&this.roi, |g| g.clone())
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    this.roi.try_read().map(|g| g.clone()).unwrap_or_default()
+                }
+            };
+
+            // While drawing the region, clicks add vertices and a double-click closes it
+            if roi.drawing && response.clicked() {
+                if let Some(click_pos) = ui.ctx().input(|i| i.pointer.interact_pos()) {
+                    let position = projector.unproject(egui::Vec2::new(click_pos.x, click_pos.y));
+                    let double_clicked = response.double_clicked();
+                    let update = move |roi: &mut RoiPolygon| {
+                        if double_clicked {
+                            roi.close();
+                        } else {
+                            roi.add_vertex(position);
+                        }
+                    };
+                    #[cfg(not(target_arch = "wasm32"))]
+                    eframe_entrypoints::async_runtime::blocking_write(&this.roi, update);
+                    #[cfg(target_arch = "wasm32")]
+                    if let Ok(mut guard) = this.roi.try_write() {
+                        update(&mut guard);
+                    }
+                }
+            }
             // Handle map click to select nearest route.
             // If the map area was clicked, find nearest visible route (by projected screen distance)
-            if response.clicked() {
+            else if response.clicked() && !this.params.tool_takes_clicks {
                 // Retrieve the pointer position via the UI context (safe and available here).
                 if let Some(click_pos) = ui.ctx().input(|i| i.pointer.interact_pos()) {
                     // Convert click to geographic and mercator
@@ -296,11 +381,11 @@ impl Plugin for TrackPlugin {
                     // Query segments near click (use same screen_size)
                     #[cfg(not(target_arch = "wasm32"))]
                     let nearby_segments = eframe_entrypoints::async_runtime::blocking_read(
-                        &self.collection,
+                        &this.collection,
                         |collection| collection.query_visible(query_rect, screen_size),
                     );
                     #[cfg(target_arch = "wasm32")]
-                    let nearby_segments = if let Ok(collection) = self.collection.try_read() {
+                    let nearby_segments = if let Ok(collection) = this.collection.try_read() {
                         collection.query_visible(query_rect, screen_size)
                     } else {
                         Vec::new()
@@ -340,13 +425,13 @@ impl Plugin for TrackPlugin {
                             #[cfg(not(target_arch = "wasm32"))]
                             {
                                 eframe_entrypoints::async_runtime::blocking_write(
-                                    &self.selected,
+                                    &this.selected,
                                     |g| *g = Some(route_idx),
                                 );
                             }
                             #[cfg(target_arch = "wasm32")]
                             {
-                                if let Ok(mut guard) = self.selected.try_write() {
+                                if let Ok(mut guard) = this.selected.try_write() {
                                     *guard = Some(route_idx);
                                 }
                             }
@@ -354,13 +439,13 @@ impl Plugin for TrackPlugin {
                             #[cfg(not(target_arch = "wasm32"))]
                             {
                                 eframe_entrypoints::async_runtime::blocking_write(
-                                    &self.selected,
+                                    &this.selected,
                                     |g| *g = None,
                                 );
                             }
                             #[cfg(target_arch = "wasm32")]
                             {
-                                if let Ok(mut guard) = self.selected.try_write() {
+                                if let Ok(mut guard) = this.selected.try_write() {
                                     *guard = None;
                                 }
                             }
@@ -369,13 +454,13 @@ impl Plugin for TrackPlugin {
                         #[cfg(not(target_arch = "wasm32"))]
                         {
                             eframe_entrypoints::async_runtime::blocking_write(
-                                &self.selected,
+                                &this.selected,
                                 |g| *g = None,
                             );
                         }
                         #[cfg(target_arch = "wasm32")]
                         {
-                            if let Ok(mut guard) = self.selected.try_write() {
+                            if let Ok(mut guard) = this.selected.try_write() {
                                 *guard = None;
                             }
                         }
@@ -383,76 +468,491 @@ impl Plugin for TrackPlugin {
                 }
             }
 
+            // Project the region of interest once; rendered runs are clipped against it
+            this.clip_polygon.clear();
+            if roi.is_active() {
+                this.clip_polygon
+                    .extend(roi.vertices.iter().map(|position| {
+                        let v = projector.project(*position);
+                        egui::pos2(v.x, v.y)
+                    }));
+            }
+            this.refresh_highlighted();
+            let clip = roi.is_active().then_some(this.clip_polygon.as_slice());
+
             // Render all visible segments and count points.
-            // We render non-selected routes first, then selected route(s) on top.
-            let mut total_points = 0usize;
+            // Non-selected routes are drawn first, then selected route(s) on top.
+            let selected = {
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let mut tmp: Option<usize> = None;
+                    eframe_entrypoints::async_runtime::blocking_read(&this.selected, |g| {
+                        tmp = *g;
+                    });
+                    tmp
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    if let Ok(guard) = this.selected.try_read() {
+                        *guard
+                    } else {
+                        None
+                    }
+                }
+            };
+            if this.params.full_detail_selection
+                && let Some(route_index) = selected
             {
+                full_detail(&mut segments, route_index);
+            }
+            let (prepared, pass_ms) = {
                 #[cfg(feature = "profiling")]
                 profiling::scope!(
                     "render_segments",
                     format!("segments={}", segments.len()).as_str()
                 );
 
-                let selected = {
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        let mut tmp: Option<usize> = None;
-                        eframe_entrypoints::async_runtime::blocking_read(&self.selected, |g| {
-                            tmp = *g;
-                        });
-                        tmp
-                    }
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        if let Ok(guard) = self.selected.try_read() {
-                            *guard
-                        } else {
-                            None
-                        }
+                let project = |position: walkers::Position| {
+                    let v = projector.project(position);
+                    egui::pos2(v.x, v.y)
+                };
+                let width = match this.params.width_mode {
+                    WidthMode::Pixels => this.params.width,
+                    // Exact at the viewport center; Web Mercator stretches it slightly elsewhere
+                    WidthMode::Meters => {
+                        let center_lat = projector.unproject(viewport_rect.center().to_vec2()).y();
+                        meters_to_points(
+                            this.params.width_m,
+                            meters_per_pixel(center_lat, map_memory.zoom()),
+                        )
                     }
                 };
-
-                // First pass: non-selected
-                for segment in &segments {
-                    if Some(segment.route_index) == selected {
-                        continue;
+                let style = TrackStyle {
+                    width,
+                    show_outline: this.params.show_outline,
+                    compare_pair: this.params.compare_pair,
+                    route_colors: &this.params.route_colors,
+                    route_dashes: &this.params.route_dashes,
+                    blend_mode: this.params.blend_mode,
+                    hovered: this.params.hovered,
+                    highlighted: this
+                        .external_selection
+                        .is_some()
+                        .then_some(&this.highlighted),
+                    stack_offset: if map_memory.zoom() >= MIN_STACK_OFFSET_ZOOM {
+                        this.params.stack_offset
+                    } else {
+                        0.0
+                    },
+                };
+                let prepared = &mut this.prepared;
+                prepare_tracks(prepared, &segments, &project, clip, selected, style);
+                // Outline and line passes alternate per layer; time each kind over all layers
+                let mut pass_ms = [0.0; 2];
+                let mut pass_start = instant::Instant::now();
+                let mut current_pass = None;
+                for line in &prepared.polylines {
+                    if current_pass != Some(line.outline) {
+                        if let Some(outline) = current_pass {
+                            pass_ms[usize::from(outline)] +=
+                                pass_start.elapsed().as_secs_f64() * 1000.0;
+                        }
+                        current_pass = Some(line.outline);
+                        pass_start = instant::Instant::now();
                     }
-                    total_points += self.render_segment(segment, projector, painter);
-                }
-
-                // Second pass: selected route(s) drawn on top with highlight
-                if let Some(sel_idx) = selected {
-                    for segment in &segments {
-                        if segment.route_index == sel_idx {
-                            // count points using the regular renderer for stats, but draw highlight
-                            // We'll count simplified points for stats
-                            for part in &segment.parts {
-                                let pts = part.get_simplified_points(&segment.route);
-                                total_points += pts.len();
-                            }
-                            self.render_segment_highlight(segment, projector, painter);
+                    // The painter keeps its shapes, so only these copies are made per frame
+                    let points = prepared.line_points(line);
+                    match line.dash {
+                        None => {
+                            painter.add(egui::Shape::line(points.to_vec(), line.stroke));
+                        }
+                        Some((dash, gap)) => {
+                            painter.extend(egui::Shape::dashed_line(
+                                points,
+                                line.stroke,
+                                dash,
+                                gap,
+                            ));
                         }
                     }
                 }
+                if let Some(outline) = current_pass {
+                    pass_ms[usize::from(outline)] += pass_start.elapsed().as_secs_f64() * 1000.0;
+                }
+                (prepared, pass_ms)
+            };
+
+            if !roi.vertices.is_empty() {
+                TrackPlugin::render_roi(&roi, projector, painter);
             }
 
             // Update shared statistics
             {
+                let update =
+                    |s: &mut RenderStats| s.record_frame(segments.len(), prepared, pass_ms);
                 #[cfg(not(target_arch = "wasm32"))]
                 {
-                    eframe_entrypoints::async_runtime::blocking_write(&self.stats, |s| {
-                        s.segments_rendered = segments.len();
-                        s.simplified_points_rendered = total_points;
-                    });
+                    eframe_entrypoints::async_runtime::blocking_write(&this.stats, update);
                 }
                 #[cfg(target_arch = "wasm32")]
                 {
-                    if let Ok(mut stats) = self.stats.try_write() {
-                        stats.segments_rendered = segments.len();
-                        stats.simplified_points_rendered = total_points;
+                    if let Ok(mut stats) = this.stats.try_write() {
+                        update(&mut stats);
                     }
                 }
             }
         }
     }
 }
+
+/// Plugin drawing the live position dot and its accuracy circle
+pub struct PositionPlugin {
+    /// Fix to draw
+    fix: LiveFix,
+    /// Whether the fix is current; a lost fix is drawn greyed out
+    current: bool,
+}
+
+impl PositionPlugin {
+    /// Create a plugin for the given fix
+    pub fn new(fix: LiveFix, current: bool) -> Self {
+        Self { fix, current }
+    }
+}
+
+impl Plugin for PositionPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        let position = self.fix.position;
+        let v = projector.project(position);
+        let center = egui::pos2(v.x, v.y);
+
+        // Project a point `accuracy` meters north to get the circle radius in pixels
+        const METERS_PER_DEGREE_LAT: f64 = 111_320.0;
+        let north = walkers::lat_lon(
+            position.y() + self.fix.accuracy_m / METERS_PER_DEGREE_LAT,
+            position.x(),
+        );
+        let n = projector.project(north);
+        let radius = (egui::pos2(n.x, n.y) - center).length();
+
+        let color = if self.current {
+            Color32::from_rgb(30, 136, 229)
+        } else {
+            Color32::GRAY
+        };
+        if radius > 7.0 {
+            painter.circle(
+                center,
+                radius,
+                color.gamma_multiply(0.15),
+                Stroke::new(1.0, color.gamma_multiply(0.6)),
+            );
+        }
+        painter.circle(center, 7.0, color, Stroke::new(2.0, Color32::WHITE));
+    }
+}
+
+/// Plugin drawing the position fed by an embedding app, with a ring pulsing out of it
+pub struct ExternalPositionPlugin {
+    position: walkers::Position,
+    /// Progress of the current pulse, from 0 (starting) to 1 (faded out);
+    /// `None` draws no pulse, for when animations are off
+    phase: Option<f32>,
+}
+
+impl ExternalPositionPlugin {
+    /// Seconds between pulses
+    const PULSE_PERIOD: f64 = 1.5;
+    /// Marker radius in pixels; the ring grows to three times this
+    const RADIUS: f32 = 7.0;
+
+    /// Plugin marking the position of `external`, if it has one, pulsing at `time` seconds
+    pub fn new(external: &ExternalPosition, time: Option<f64>) -> Option<Self> {
+        external.position().map(|position| Self {
+            position,
+            phase: time
+                .map(|time| (time.rem_euclid(Self::PULSE_PERIOD) / Self::PULSE_PERIOD) as f32),
+        })
+    }
+
+    /// Marker and pulse ring, centered on the projected position
+    fn shapes(&self, projector: &Projector) -> [egui::Shape; 2] {
+        let v = projector.project(self.position);
+        let center = egui::pos2(v.x, v.y);
+        let color = Color32::from_rgb(0, 172, 193);
+        let ring = match self.phase {
+            Some(phase) => egui::Shape::circle_stroke(
+                center,
+                Self::RADIUS * (1.0 + 2.0 * phase),
+                Stroke::new(3.0, color.gamma_multiply(1.0 - phase)),
+            ),
+            None => egui::Shape::Noop,
+        };
+        [
+            ring,
+            egui::Shape::Circle(egui::epaint::CircleShape {
+                center,
+                radius: Self::RADIUS,
+                fill: color,
+                stroke: Stroke::new(2.0, Color32::WHITE),
+            }),
+        ]
+    }
+}
+
+impl Plugin for ExternalPositionPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let [ring, marker] = self.shapes(projector);
+        let painter = ui.painter();
+        painter.add(ring);
+        painter.add(marker);
+    }
+}
+
+/// Plugin highlighting polylines on top of the tracks, e.g. off-corridor sections
+pub struct HighlightPlugin {
+    lines: Vec<Vec<walkers::Position>>,
+    stroke: Stroke,
+}
+
+impl HighlightPlugin {
+    /// Highlight `lines` in red, a little wider than tracks of `track_width`
+    pub fn new(lines: Vec<Vec<walkers::Position>>, track_width: f32) -> Self {
+        Self {
+            lines,
+            stroke: Stroke::new(track_width + 2.0, Color32::from_rgb(220, 30, 30)),
+        }
+    }
+}
+
+impl Plugin for HighlightPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        for line in self.lines {
+            let points: Vec<egui::Pos2> = line
+                .into_iter()
+                .map(|position| {
+                    let v = projector.project(position);
+                    egui::pos2(v.x, v.y)
+                })
+                .collect();
+            painter.add(egui::Shape::line(points, self.stroke));
+        }
+    }
+}
+
+/// Plugin previewing a split: both parts of the route in contrasting colors and the cut
+pub struct SplitPreviewPlugin {
+    lines: [Vec<walkers::Position>; 2],
+    track_width: f32,
+}
+
+impl SplitPreviewPlugin {
+    /// Draw the parts of a split preview over tracks of `track_width`
+    pub fn new(lines: [Vec<walkers::Position>; 2], track_width: f32) -> Self {
+        Self { lines, track_width }
+    }
+}
+
+impl Plugin for SplitPreviewPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        let project = |position: &walkers::Position| {
+            let v = projector.project(*position);
+            egui::pos2(v.x, v.y)
+        };
+        for (line, color) in self.lines.iter().zip(COMPARE_COLORS) {
+            let points: Vec<egui::Pos2> = line.iter().map(project).collect();
+            painter.add(egui::Shape::line(
+                points,
+                Stroke::new(self.track_width + 2.0, color),
+            ));
+        }
+        // The cut ends the first part
+        if let Some(cut) = self.lines[0].last() {
+            painter.circle(
+                project(cut),
+                6.0,
+                Color32::WHITE,
+                Stroke::new(2.0, Color32::BLACK),
+            );
+        }
+    }
+}
+
+/// Plugin drawing the playback cursor, a marker under the map center while it follows
+pub struct PlaybackPlugin {
+    position: walkers::Position,
+}
+
+impl PlaybackPlugin {
+    /// Mark `position` on the map
+    pub fn new(position: walkers::Position) -> Self {
+        Self { position }
+    }
+}
+
+impl Plugin for PlaybackPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        _response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let v = projector.project(self.position);
+        ui.painter().circle(
+            egui::pos2(v.x, v.y),
+            6.0,
+            Color32::from_rgb(255, 140, 0),
+            Stroke::new(2.0, Color32::WHITE),
+        );
+    }
+}
+
+/// Plugin drawing the waypoints of loaded files with the icon of their symbol
+pub struct WaypointPlugin {
+    /// Position, icon and name of each waypoint
+    waypoints: Vec<(walkers::Position, &'static SymbolIcon, Option<String>)>,
+}
+
+impl WaypointPlugin {
+    /// Marker radius in pixels
+    const RADIUS: f32 = 9.0;
+
+    /// Draw the waypoints of `gpx_files`
+    pub fn new<'a>(gpx_files: impl IntoIterator<Item = &'a gpx::Gpx>) -> Self {
+        let waypoints = gpx_files
+            .into_iter()
+            .flat_map(|gpx| &gpx.waypoints)
+            .map(|waypoint| {
+                let point = waypoint.point();
+                (
+                    walkers::lat_lon(point.y(), point.x()),
+                    symbols::icon_for(waypoint.symbol.as_deref()),
+                    waypoint.name.clone(),
+                )
+            })
+            .collect();
+        Self { waypoints }
+    }
+}
+
+impl Plugin for WaypointPlugin {
+    fn run(
+        self: Box<Self>,
+        ui: &mut egui::Ui,
+        response: &egui::Response,
+        projector: &Projector,
+        _map_memory: &walkers::MapMemory,
+    ) {
+        let painter = ui.painter();
+        let visible = response.rect.expand(Self::RADIUS);
+        let hover = response.hover_pos();
+        let mut hovered_name = None;
+        for (position, icon, name) in &self.waypoints {
+            let v = projector.project(*position);
+            let center = egui::pos2(v.x, v.y);
+            if !visible.contains(center) {
+                continue;
+            }
+            painter.circle(
+                center,
+                Self::RADIUS,
+                icon.color,
+                Stroke::new(1.5, Color32::WHITE),
+            );
+            painter.text(
+                center,
+                egui::Align2::CENTER_CENTER,
+                icon.glyph,
+                egui::FontId::proportional(Self::RADIUS * 1.1),
+                Color32::WHITE,
+            );
+            if hover.is_some_and(|hover| hover.distance(center) <= Self::RADIUS) {
+                hovered_name = Some((center, name.as_deref().unwrap_or(icon.label)));
+            }
+        }
+        // Name of the waypoint under the pointer, above the other markers
+        if let Some((center, name)) = hovered_name {
+            let galley = painter.layout_no_wrap(
+                name.to_string(),
+                egui::FontId::proportional(12.0),
+                Color32::WHITE,
+            );
+            let rect = egui::Align2::LEFT_CENTER
+                .anchor_size(center + egui::vec2(Self::RADIUS + 4.0, 0.0), galley.size())
+                .expand(3.0);
+            painter.rect_filled(rect, 3.0, Color32::from_black_alpha(200));
+            painter.galley(rect.shrink(3.0).min, galley, Color32::WHITE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_position_is_marked_until_cleared() {
+        let mut external = ExternalPosition::default();
+        assert!(ExternalPositionPlugin::new(&external, Some(0.0)).is_none());
+
+        let position = walkers::lat_lon(45.83, 6.86);
+        external.set(Some(position));
+        let mut map_memory = walkers::MapMemory::default();
+        map_memory.center_at(walkers::lat_lon(45.8, 6.8));
+        let projector = Projector::new(
+            egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(800.0, 600.0)),
+            &map_memory,
+            walkers::lat_lon(0.0, 0.0),
+        );
+        let v = projector.project(position);
+        let plugin = ExternalPositionPlugin::new(&external, Some(0.75)).expect("a position is set");
+        let [ring, marker] = plugin.shapes(&projector);
+        for shape in [ring, marker] {
+            let egui::Shape::Circle(circle) = shape else {
+                panic!("expected circles, got {:?}", shape);
+            };
+            assert_eq!(circle.center, egui::pos2(v.x, v.y));
+        }
+        // Without animations the marker doesn't pulse
+        let still = ExternalPositionPlugin::new(&external, None).expect("a position is set");
+        assert!(matches!(still.shapes(&projector)[0], egui::Shape::Noop));
+
+        // Following recenters once per new position
+        assert_eq!(external.take_recenter(), None);
+        external.follow = true;
+        external.set(Some(walkers::lat_lon(45.84, 6.86)));
+        assert!(external.take_recenter().is_some());
+        assert_eq!(external.take_recenter(), None);
+
+        external.set(None);
+        assert!(ExternalPositionPlugin::new(&external, Some(0.75)).is_none());
+    }
+}
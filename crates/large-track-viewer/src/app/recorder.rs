@@ -0,0 +1,190 @@
+//! Recording a new track from live position fixes
+//!
+//! Fixes are appended to an in-memory GPX document. The app periodically
+//! re-indexes that document into the route collection so the growing track
+//! renders like any loaded file, and autosaves it so a crash loses at most a
+//! minute of recording.
+
+use crate::app::location::LiveFix;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Path prefix identifying recordings in the loaded file list (never reloadable from disk)
+pub const RECORDING_PATH_PREFIX: &str = "recording://";
+
+/// How often the growing track is re-indexed into the collection
+const REINDEX_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the partial recording is written to storage
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An in-progress recording
+pub struct TrackRecorder {
+    /// Recorded track (a single track; one segment per uninterrupted stretch of fixes)
+    gpx: gpx::Gpx,
+    /// Identifier used for the recording's entry in the loaded file list
+    path: PathBuf,
+    /// Last appended position, used to drop identical consecutive fixes
+    last_position: Option<walkers::Position>,
+    /// Whether the fix was lost since the last appended point
+    gap: bool,
+    /// Whether points were added since the last re-index
+    dirty: bool,
+    /// Whether points were added since the last autosave
+    unsaved: bool,
+    last_reindex: instant::Instant,
+    last_autosave: instant::Instant,
+}
+
+impl TrackRecorder {
+    pub fn new() -> Self {
+        let started = time::OffsetDateTime::now_utc();
+        let name = format!(
+            "Recording {}-{:02}-{:02} {:02}:{:02}",
+            started.year(),
+            u8::from(started.month()),
+            started.day(),
+            started.hour(),
+            started.minute()
+        );
+
+        let mut track = gpx::Track::new();
+        track.name = Some(name.clone());
+        let gpx = gpx::Gpx {
+            version: gpx::GpxVersion::Gpx11,
+            creator: Some("Large Track Viewer".to_string()),
+            tracks: vec![track],
+            ..Default::default()
+        };
+
+        Self {
+            gpx,
+            path: PathBuf::from(format!("{}{}", RECORDING_PATH_PREFIX, name)),
+            last_position: None,
+            gap: true,
+            dirty: false,
+            unsaved: false,
+            last_reindex: instant::Instant::now(),
+            last_autosave: instant::Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn gpx(&self) -> &gpx::Gpx {
+        &self.gpx
+    }
+
+    /// Number of recorded points across all segments
+    pub fn point_count(&self) -> usize {
+        self.gpx.tracks[0]
+            .segments
+            .iter()
+            .map(|segment| segment.points.len())
+            .sum()
+    }
+
+    /// Note that the fix was lost; the next point starts a new segment
+    pub fn mark_gap(&mut self) {
+        self.gap = true;
+    }
+
+    /// Append a fix, skipping it if it repeats the previous position exactly
+    pub fn push_fix(&mut self, fix: &LiveFix) {
+        if !self.gap && self.last_position == Some(fix.position) {
+            return;
+        }
+
+        let track = &mut self.gpx.tracks[0];
+        if self.gap || track.segments.is_empty() {
+            track.segments.push(gpx::TrackSegment::new());
+            self.gap = false;
+        }
+
+        let mut point = gpx::Waypoint::new(geo::Point::new(fix.position.x(), fix.position.y()));
+        point.elevation = fix.altitude_m;
+        point.time = fix.time.map(gpx::Time::from);
+        track
+            .segments
+            .last_mut()
+            .expect("segment pushed above")
+            .points
+            .push(point);
+
+        self.last_position = Some(fix.position);
+        self.dirty = true;
+        self.unsaved = true;
+    }
+
+    /// Whether the collection copy is stale and due for a refresh
+    pub fn reindex_due(&self) -> bool {
+        // A single point has no extent to index yet
+        self.dirty && self.point_count() >= 2 && self.last_reindex.elapsed() >= REINDEX_INTERVAL
+    }
+
+    pub fn mark_reindexed(&mut self) {
+        self.dirty = false;
+        self.last_reindex = instant::Instant::now();
+    }
+
+    pub fn autosave_due(&self) -> bool {
+        self.unsaved && self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL
+    }
+
+    pub fn mark_autosaved(&mut self) {
+        self.unsaved = false;
+        self.last_autosave = instant::Instant::now();
+    }
+}
+
+/// Serialize a recording as GPX text
+pub fn to_gpx_string(gpx: &gpx::Gpx) -> Result<String, String> {
+    let mut buf = Vec::new();
+    gpx::write(gpx, &mut buf).map_err(|e| e.to_string())?;
+    String::from_utf8(buf).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(lat: f64, lon: f64) -> LiveFix {
+        LiveFix {
+            position: walkers::lat_lon(lat, lon),
+            accuracy_m: 5.0,
+            altitude_m: Some(100.0),
+            time: None,
+        }
+    }
+
+    #[test]
+    fn identical_consecutive_fixes_are_dropped() {
+        let mut recorder = TrackRecorder::new();
+        recorder.push_fix(&fix(51.0, 0.0));
+        recorder.push_fix(&fix(51.0, 0.0));
+        recorder.push_fix(&fix(51.001, 0.0));
+        recorder.push_fix(&fix(51.0, 0.0));
+
+        assert_eq!(recorder.point_count(), 3);
+        let point = &recorder.gpx().tracks[0].segments[0].points[0];
+        assert_eq!(point.elevation, Some(100.0));
+        assert_eq!(point.point().y(), 51.0);
+    }
+
+    #[test]
+    fn lost_fix_starts_new_segment() {
+        let mut recorder = TrackRecorder::new();
+        recorder.push_fix(&fix(51.0, 0.0));
+        recorder.push_fix(&fix(51.001, 0.0));
+        recorder.mark_gap();
+        recorder.push_fix(&fix(51.001, 0.0));
+        recorder.push_fix(&fix(51.002, 0.0));
+
+        let segments = &recorder.gpx().tracks[0].segments;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].points.len(), 2);
+        assert!(to_gpx_string(recorder.gpx()).unwrap().contains("<trkseg>"));
+    }
+}
@@ -0,0 +1,1308 @@
+//! Turning queried segments into styled screen-space polylines
+//!
+//! This is everything the track plugin does between querying the collection
+//! and handing shapes to egui's painter: projection, clipping to the region
+//! of interest, colors, outlines and the selection highlight. Keeping it free
+//! of the painter lets it be rasterized elsewhere (golden-image tests,
+//! exports) with exactly the look of the map.
+
+use crate::app::roi::clip_polyline_to_polygon;
+use crate::app::style_rules::DashPattern;
+use egui::{Color32, Pos2, Stroke, Vec2};
+use large_track_lib::SimplifiedSegment;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// Why queried segments were left off the map, counted over one render pass
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct SkipCounters {
+    /// Segments whose drawable runs all fell outside the region of interest
+    pub outside_region: usize,
+    /// Segments with fewer than two points left to draw
+    pub too_few_points: usize,
+}
+
+impl SkipCounters {
+    /// Count a segment that drew nothing. `had_lines` tells whether it had
+    /// anything to draw before clipping to the region of interest.
+    pub fn record(&mut self, had_lines: bool, clipped: bool) {
+        if had_lines && clipped {
+            self.outside_region += 1;
+        } else {
+            self.too_few_points += 1;
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.outside_region + self.too_few_points
+    }
+}
+
+/// Line style shared by all tracks
+#[derive(Clone, Copy, Debug)]
+pub struct TrackStyle<'a> {
+    /// Track line width in pixels
+    pub width: f32,
+    /// Whether to draw a dark border under each line
+    pub show_outline: bool,
+    /// Two routes being compared, drawn in [`COMPARE_COLORS`] instead of their own colors
+    pub compare_pair: Option<(usize, usize)>,
+    /// Colors picked for routes, by route index, instead of [`route_color`]
+    pub route_colors: &'a BTreeMap<usize, Color32>,
+    /// Dash patterns of routes styled by a rule, by route index; the rest are solid
+    pub route_dashes: &'a BTreeMap<usize, DashPattern>,
+    /// How track lines combine where they overlap
+    pub blend_mode: BlendMode,
+    /// Route pointed at outside the map (e.g. in the legend), drawn wider
+    /// and brighter on top of the others
+    pub hovered: Option<usize>,
+    /// Routes an embedding app highlights, drawn like the selected route
+    pub highlighted: Option<&'a HashSet<usize>>,
+    /// Pixels between tracks stacked on the same path, drawn side by side
+    /// instead of on top of each other; 0 to leave them stacked
+    pub stack_offset: f32,
+}
+
+/// How overlapping track lines combine
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The line on top covers the ones below
+    #[default]
+    Normal,
+    /// Lines add up, so busy corridors glow brighter than single tracks
+    ///
+    /// Each line adds [`ADDITIVE_INTENSITY`] of its color: a single track is
+    /// dimmer than in normal mode, two overlapping ones of the same color
+    /// reach it, and channels saturate at white from there on. Outlines and
+    /// the selected route are still painted normally.
+    Additive,
+}
+
+/// Share of a line's color that each line adds in [`BlendMode::Additive`]
+pub const ADDITIVE_INTENSITY: f32 = 0.5;
+
+impl BlendMode {
+    pub fn all() -> &'static [Self] {
+        &[Self::Normal, Self::Additive]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Normal => "Normal",
+            Self::Additive => "Additive (density)",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Additive" => Self::Additive,
+            _ => Self::Normal,
+        }
+    }
+
+    /// Color to paint a line of `color` with
+    ///
+    /// egui blends premultiplied colors as `src + dst * (1 - src_alpha)`, so
+    /// a color with zero alpha is simply added to what is below it.
+    pub fn line_color(self, color: Color32) -> Color32 {
+        match self {
+            Self::Normal => color,
+            Self::Additive => color.gamma_multiply(ADDITIVE_INTENSITY).additive(),
+        }
+    }
+}
+
+/// What the track width setting measures
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WidthMode {
+    /// A fixed width on screen, whatever the zoom
+    #[default]
+    Pixels,
+    /// A corridor on the ground, wider on screen the closer the map is zoomed in
+    Meters,
+}
+
+impl WidthMode {
+    pub fn all() -> &'static [Self] {
+        &[Self::Pixels, Self::Meters]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pixels => "Pixels",
+            Self::Meters => "Meters",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Meters" => Self::Meters,
+            _ => Self::Pixels,
+        }
+    }
+}
+
+/// Default track width in [`WidthMode::Meters`]
+pub const DEFAULT_LINE_WIDTH_M: f32 = 50.0;
+
+/// Narrowest a track drawn in meters gets on screen, so it stays visible zoomed out
+pub const MIN_METERS_WIDTH_POINTS: f32 = 1.0;
+
+/// Screen width of a `meters` wide line where one point spans `meters_per_point`
+pub fn meters_to_points(meters: f32, meters_per_point: f64) -> f32 {
+    let points = (f64::from(meters) / meters_per_point) as f32;
+    // A degenerate scale (e.g. at a pole) falls back to the minimum too
+    if points.is_finite() {
+        points.max(MIN_METERS_WIDTH_POINTS)
+    } else {
+        MIN_METERS_WIDTH_POINTS
+    }
+}
+
+/// Default of the pixels between stacked tracks, when they are offset
+pub const DEFAULT_STACK_OFFSET: f32 = 3.0;
+
+/// Widest a stack of offset tracks gets, in pixels; larger stacks are packed tighter
+pub const MAX_STACK_SPREAD: f32 = 16.0;
+
+/// Map zoom below which stacked tracks are no longer offset: zoomed far out,
+/// a few pixels span whole streets and the offsets would only blur the map
+pub const MIN_STACK_OFFSET_ZOOM: f64 = 12.0;
+
+/// Tracks whose simplified points all agree to this many degrees (about a
+/// meter) count as stacked
+const STACK_TOLERANCE_DEG: f64 = 1e-5;
+
+/// Line color of the selected and externally highlighted routes
+pub const HIGHLIGHT_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+
+/// Fixed colors of the first and second compared route, chosen to contrast
+/// with each other and with the selection highlight
+pub const COMPARE_COLORS: [Color32; 2] = [
+    Color32::from_rgb(0, 160, 255),
+    Color32::from_rgb(230, 0, 150),
+];
+
+impl TrackStyle<'_> {
+    /// Line color of a route, honoring the compared pair and picked colors
+    fn color_of(&self, route_index: usize) -> Color32 {
+        match self.compare_pair {
+            Some((first, _)) if first == route_index => COMPARE_COLORS[0],
+            Some((_, second)) if second == route_index => COMPARE_COLORS[1],
+            _ => self
+                .route_colors
+                .get(&route_index)
+                .copied()
+                .unwrap_or_else(|| route_color(route_index)),
+        }
+    }
+
+    /// Dash and gap lengths of a route's lines, `None` for solid ones
+    ///
+    /// Taken from the track width, so a route's outline and highlight break
+    /// up in step with its line.
+    fn dash_of(&self, route_index: usize) -> Option<(f32, f32)> {
+        self.route_dashes
+            .get(&route_index)
+            .and_then(|dash| dash.lengths(self.width))
+    }
+}
+
+/// Stroke of a line, of the outline under it if any, and its dashes if any
+#[derive(Clone, Copy, Debug)]
+struct LineLook {
+    stroke: Stroke,
+    outline: Option<Stroke>,
+    dash: Option<(f32, f32)>,
+}
+
+impl LineLook {
+    /// A track line of `stroke`, with an outline two pixels wider if `show_outline`
+    fn track(stroke: Stroke, dash: Option<(f32, f32)>, show_outline: bool) -> Self {
+        let outline = Stroke::new(stroke.width + 2.0, Color32::from_black_alpha(180));
+        Self {
+            stroke,
+            outline: show_outline.then_some(outline),
+            dash,
+        }
+    }
+}
+
+/// One polyline to paint, in screen coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct StyledPolyline {
+    /// Range of [`PreparedTracks::points`] holding the line's points
+    pub points: Range<usize>,
+    pub stroke: Stroke,
+    /// Whether this is the dark border drawn under the lines
+    pub outline: bool,
+    /// Dash and gap lengths, `None` for a continuous line
+    pub dash: Option<(f32, f32)>,
+}
+
+/// Polylines for one frame, in paint order, plus what the stats readouts need
+///
+/// Outlines are painted as an under-layer: every outline of a layer comes
+/// before any of its lines, so where tracks cross or overlap the border never
+/// covers a neighboring line and translucent borders blend the same
+/// everywhere. The selected route is its own layer on top.
+///
+/// Kept between frames by the track plugin: [`prepare_tracks`] clears
+/// the buffers without freeing them, so a steady view allocates nothing here.
+#[derive(Default, Debug)]
+pub struct PreparedTracks {
+    /// Screen points of all polylines; an outline shares its line's points
+    pub points: Vec<Pos2>,
+    /// Paint these in order; later lines go on top
+    pub polylines: Vec<StyledPolyline>,
+    /// Lines of the current layer, appended after its outlines when it is finished
+    pending_lines: Vec<StyledPolyline>,
+    /// Projected points of a run waiting to be clipped to the region of interest
+    unclipped: Vec<Pos2>,
+    /// Segments sharing each path this frame, and how many of them are drawn so far
+    stacks: HashMap<u64, (usize, usize)>,
+    /// Whether each route in view drew anything
+    route_drawn: HashMap<usize, bool>,
+    /// Simplified points drawn (for the selected route: its simplified points)
+    pub points_drawn: usize,
+    /// Why segments were not drawn
+    pub skipped: SkipCounters,
+    /// Routes that had segments in view but none of them drawn, sorted
+    pub fully_skipped_routes: Vec<usize>,
+    /// Routes with something drawn, sorted
+    pub drawn_routes: Vec<usize>,
+}
+
+/// Generate a color for a route based on its index
+pub fn route_color(route_id: usize) -> Color32 {
+    // Use golden angle for good color distribution
+    let hue = (route_id as f32 * 137.508) % 360.0;
+    let saturation = 0.75;
+    let value = 0.85;
+
+    // Convert HSV to RGB
+    let c = value * saturation;
+    let x = c * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let m = value - c;
+
+    let (r, g, b) = if hue < 60.0 {
+        (c, x, 0.0)
+    } else if hue < 120.0 {
+        (x, c, 0.0)
+    } else if hue < 180.0 {
+        (0.0, c, x)
+    } else if hue < 240.0 {
+        (0.0, x, c)
+    } else if hue < 300.0 {
+        (x, 0.0, c)
+    } else {
+        (c, 0.0, x)
+    };
+
+    Color32::from_rgb(
+        ((r + m) * 255.0) as u8,
+        ((g + m) * 255.0) as u8,
+        ((b + m) * 255.0) as u8,
+    )
+}
+
+/// Draw the route at `route_index` from all its points in view
+///
+/// Each queried part of the route gets every original point from its first
+/// simplified point to its last, which the query already bounds to the view,
+/// so inspecting one route shows what was recorded however far out the map
+/// is zoomed. Other routes keep the detail they were queried at.
+pub fn full_detail(segments: &mut [SimplifiedSegment], route_index: usize) {
+    for segment in segments
+        .iter_mut()
+        .filter(|segment| segment.route_index == route_index)
+    {
+        for part in &mut segment.parts {
+            if let (Some(&first), Some(&last)) = (
+                part.simplified_indices.first(),
+                part.simplified_indices.last(),
+            ) {
+                part.simplified_indices.clear();
+                part.simplified_indices.extend(first..=last);
+            }
+        }
+    }
+}
+
+/// Prepare the polylines of all queried segments into `prepared`, replacing
+/// the previous frame's
+///
+/// Non-selected routes come first, then the hovered route (if any) and the
+/// selected route highlighted on top, along with the routes in
+/// `style.highlighted`; a highlighted route is never drawn as hovered.
+/// `project` maps a WGS84 position to screen space.
+pub fn prepare_tracks(
+    prepared: &mut PreparedTracks,
+    segments: &[SimplifiedSegment],
+    project: &dyn Fn(walkers::Position) -> Pos2,
+    clip: Option<&[Pos2]>,
+    selected: Option<usize>,
+    style: TrackStyle<'_>,
+) {
+    prepared.clear();
+
+    let is_highlighted = |route_index: usize| {
+        Some(route_index) == selected
+            || style
+                .highlighted
+                .is_some_and(|highlighted| highlighted.contains(&route_index))
+    };
+
+    // First pass: non-selected
+    let hovered = style.hovered.filter(|&hovered| !is_highlighted(hovered));
+    let in_first_pass = |segment: &SimplifiedSegment| {
+        !is_highlighted(segment.route_index) && Some(segment.route_index) != hovered
+    };
+    if style.stack_offset > 0.0 {
+        for segment in segments.iter().filter(|segment| in_first_pass(segment)) {
+            prepared.stacks.entry(path_key(segment)).or_default().0 += 1;
+        }
+    }
+    for segment in segments.iter().filter(|segment| in_first_pass(segment)) {
+        let inner = Stroke::new(
+            style.width,
+            style
+                .blend_mode
+                .line_color(style.color_of(segment.route_index)),
+        );
+        let offset = if style.stack_offset > 0.0 {
+            prepared.next_stack_offset(path_key(segment), style.stack_offset)
+        } else {
+            0.0
+        };
+        let look = LineLook::track(
+            inner,
+            style.dash_of(segment.route_index),
+            style.show_outline,
+        );
+        prepared.push_segment(segment, look, offset, project, clip);
+    }
+    prepared.finish_layer();
+
+    // Hovered route over the others, wider and at full strength
+    if let Some(hovered) = hovered {
+        let color = style.color_of(hovered).lerp_to_gamma(Color32::WHITE, 0.3);
+        let stroke = Stroke::new(style.width + 2.0, color);
+        let look = LineLook::track(stroke, style.dash_of(hovered), style.show_outline);
+        for segment in segments.iter().filter(|s| s.route_index == hovered) {
+            prepared.push_segment(segment, look, 0.0, project, clip);
+        }
+        prepared.finish_layer();
+    }
+
+    // Second pass: selected route drawn on top with highlight, including context points
+    if selected.is_some() || style.highlighted.is_some_and(|set| !set.is_empty()) {
+        let highlight = Stroke::new(style.width + 3.0, HIGHLIGHT_COLOR);
+        let outline = Stroke::new(style.width + 5.0, Color32::from_black_alpha(200));
+        let outline = style.show_outline.then_some(outline);
+        for segment in segments.iter().filter(|s| is_highlighted(s.route_index)) {
+            let look = LineLook {
+                stroke: highlight,
+                outline,
+                dash: style.dash_of(segment.route_index),
+            };
+            let mut had_lines = false;
+            let mut drawn = false;
+            for part in &segment.parts {
+                // Stats count simplified points, like for the other routes
+                prepared.points_drawn += part.simplified_indices.len();
+                let prev = part.get_prev_point(&segment.route);
+                let next = part.get_next_point(&segment.route);
+                let len = part.simplified_indices.len()
+                    + usize::from(prev.is_some())
+                    + usize::from(next.is_some());
+                if len == 0 {
+                    continue;
+                }
+                had_lines |= len >= 2;
+                let points = prev
+                    .into_iter()
+                    .chain(part.iter_simplified_points(&segment.route))
+                    .chain(next);
+                drawn |= prepared.push_runs(points, project, clip, 0.0, look) > 0;
+            }
+            if !drawn {
+                prepared.skipped.record(had_lines, clip.is_some());
+            }
+            *prepared.route_drawn.entry(segment.route_index).or_default() |= drawn;
+        }
+        prepared.finish_layer();
+    }
+
+    for (&route_index, &drawn) in &prepared.route_drawn {
+        if drawn {
+            prepared.drawn_routes.push(route_index);
+        } else {
+            prepared.fully_skipped_routes.push(route_index);
+        }
+    }
+    prepared.drawn_routes.sort_unstable();
+    prepared.fully_skipped_routes.sort_unstable();
+}
+
+/// Key shared by segments on the same path, to the stacking tolerance
+fn path_key(segment: &SimplifiedSegment) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for part in &segment.parts {
+        for waypoint in part.iter_simplified_points(&segment.route) {
+            let point = waypoint.point();
+            ((point.x() / STACK_TOLERANCE_DEG).round() as i64).hash(&mut hasher);
+            ((point.y() / STACK_TOLERANCE_DEG).round() as i64).hash(&mut hasher);
+        }
+        // Parts split the same way too
+        usize::MAX.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Screen displacement that moves a point `distance` pixels sideways from a
+/// line running along `direction`: to the right of it for a positive
+/// distance, as seen on screen (y down). Zero for a zero direction.
+pub fn perpendicular_offset(direction: Vec2, distance: f32) -> Vec2 {
+    let length = direction.length();
+    if length <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+    Vec2::new(-direction.y, direction.x) * (distance / length)
+}
+
+/// Move every point of a polyline `distance` pixels sideways, each along the
+/// direction between its neighbors so corners stay joined
+fn offset_polyline(points: &mut [Pos2], distance: f32) {
+    if points.len() < 2 || distance == 0.0 {
+        return;
+    }
+    let mut previous = points[0];
+    for i in 0..points.len() {
+        let next = points[(i + 1).min(points.len() - 1)];
+        let current = points[i];
+        points[i] = current + perpendicular_offset(next - previous, distance);
+        previous = current;
+    }
+}
+
+impl PreparedTracks {
+    /// Screen points of a polyline of this frame
+    pub fn line_points(&self, line: &StyledPolyline) -> &[Pos2] {
+        &self.points[line.points.clone()]
+    }
+
+    /// Empty every buffer, keeping their memory for the next frame
+    fn clear(&mut self) {
+        self.points.clear();
+        self.polylines.clear();
+        self.pending_lines.clear();
+        self.route_drawn.clear();
+        self.stacks.clear();
+        self.points_drawn = 0;
+        self.skipped = SkipCounters::default();
+        self.fully_skipped_routes.clear();
+        self.drawn_routes.clear();
+    }
+
+    /// Sideways offset of the next segment drawn on the path `key`: the
+    /// stack is spread `spacing` apart (narrowed to [`MAX_STACK_SPREAD`])
+    /// and centered on the path
+    fn next_stack_offset(&mut self, key: u64, spacing: f32) -> f32 {
+        let Some((count, drawn)) = self.stacks.get_mut(&key) else {
+            return 0.0;
+        };
+        let rank = *drawn as f32;
+        *drawn += 1;
+        if *count < 2 {
+            return 0.0;
+        }
+        let gaps = (*count - 1) as f32;
+        let spacing = spacing.min(MAX_STACK_SPREAD / gaps);
+        (rank - gaps / 2.0) * spacing
+    }
+
+    /// Add the simplified runs of one segment as lines of `look`, moved
+    /// `offset` pixels sideways
+    ///
+    /// Counts the points drawn, or the reason nothing was, and whether the
+    /// route drew anything.
+    fn push_segment(
+        &mut self,
+        segment: &SimplifiedSegment,
+        look: LineLook,
+        offset: f32,
+        project: &dyn Fn(walkers::Position) -> Pos2,
+        clip: Option<&[Pos2]>,
+    ) {
+        let mut had_lines = false;
+        let mut points_drawn = 0;
+        for part in &segment.parts {
+            let len = part.simplified_indices.len();
+            if len == 0 {
+                continue;
+            }
+            had_lines |= len >= 2;
+            let points = part.iter_simplified_points(&segment.route);
+            points_drawn += self.push_runs(points, project, clip, offset, look);
+        }
+        if points_drawn == 0 {
+            self.skipped.record(had_lines, clip.is_some());
+        }
+        self.points_drawn += points_drawn;
+        *self.route_drawn.entry(segment.route_index).or_default() |= points_drawn > 0;
+    }
+
+    /// Project waypoints to screen space and add them as lines moved `offset`
+    /// pixels sideways, clipped to the region of interest if any
+    ///
+    /// Runs of fewer than two points are dropped. Returns the number of
+    /// points in the lines added.
+    fn push_runs<'a>(
+        &mut self,
+        waypoints: impl Iterator<Item = &'a gpx::Waypoint>,
+        project: &dyn Fn(walkers::Position) -> Pos2,
+        clip: Option<&[Pos2]>,
+        offset: f32,
+        look: LineLook,
+    ) -> usize {
+        let to_screen = |waypoint: &gpx::Waypoint| {
+            let point = waypoint.point();
+            project(walkers::lat_lon(point.y(), point.x()))
+        };
+        let Some(polygon) = clip else {
+            let start = self.points.len();
+            self.points.extend(waypoints.map(to_screen));
+            offset_polyline(&mut self.points[start..], offset);
+            return self.push_line(start, look);
+        };
+
+        self.unclipped.clear();
+        self.unclipped.extend(waypoints.map(to_screen));
+        offset_polyline(&mut self.unclipped, offset);
+        let mut points_drawn = 0;
+        for run in clip_polyline_to_polygon(&self.unclipped, polygon) {
+            let start = self.points.len();
+            self.points.extend(run);
+            points_drawn += self.push_line(start, look);
+        }
+        points_drawn
+    }
+
+    /// Make the points from `start` on a line of the current layer, with its
+    /// outline in the layer's under-layer if any
+    ///
+    /// Fewer than two points are dropped instead. Returns the number of points kept.
+    fn push_line(&mut self, start: usize, look: LineLook) -> usize {
+        let points = start..self.points.len();
+        if points.len() < 2 {
+            self.points.truncate(start);
+            return 0;
+        }
+        if let Some(outline) = look.outline {
+            self.polylines.push(StyledPolyline {
+                points: points.clone(),
+                stroke: outline,
+                outline: true,
+                dash: look.dash,
+            });
+        }
+        let len = points.len();
+        self.pending_lines.push(StyledPolyline {
+            points,
+            stroke: look.stroke,
+            outline: false,
+            dash: look.dash,
+        });
+        len
+    }
+
+    /// Put the current layer's lines over its outlines; later lines start a new layer
+    fn finish_layer(&mut self) {
+        self.polylines.append(&mut self.pending_lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    #[test]
+    fn skipped_segments_are_attributed_to_the_region_only_when_clipped() {
+        let mut skipped = SkipCounters::default();
+        skipped.record(true, true);
+        skipped.record(true, false);
+        skipped.record(false, true);
+        assert_eq!(
+            skipped,
+            SkipCounters {
+                outside_region: 1,
+                too_few_points: 2,
+            }
+        );
+        assert_eq!(skipped.total(), 3);
+    }
+
+    /// Counts allocations of the current thread while [`count_allocations`]
+    /// runs, so tests running in parallel don't add to each other's count
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|count| {
+            if let Some(n) = count.get() {
+                count.set(Some(n + 1));
+            }
+        });
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Number of allocations `f` makes on this thread
+    fn count_allocations(f: impl FnOnce()) -> usize {
+        ALLOCATIONS.with(|count| count.set(Some(0)));
+        f();
+        ALLOCATIONS.with(|count| count.replace(None)).unwrap_or(0)
+    }
+
+    #[test]
+    fn externally_highlighted_routes_are_the_ones_drawn_highlighted() {
+        // Route i runs along latitude i, so its screen y tells it apart
+        let segments: Vec<SimplifiedSegment> = (0..4)
+            .map(|route_index| {
+                let mut segment = gpx::TrackSegment::new();
+                for i in 0..10 {
+                    segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                        f64::from(i) * 0.001,
+                        route_index as f64,
+                    )));
+                }
+                let mut track = gpx::Track::new();
+                track.segments.push(segment);
+                let route = large_track_lib::Route::new(gpx::Gpx {
+                    tracks: vec![track],
+                    ..Default::default()
+                })
+                .unwrap();
+                let part = large_track_lib::SegmentPart::new(0, 0, 0..10, (0..10).collect());
+                SimplifiedSegment::new(route, route_index, vec![part])
+            })
+            .collect();
+        let project =
+            |position: walkers::Position| Pos2::new(position.x() as f32 * 1e4, position.y() as f32);
+        let route_colors = BTreeMap::new();
+        let highlighted = HashSet::from([1, 3]);
+        let style = TrackStyle {
+            width: 2.0,
+            show_outline: true,
+            compare_pair: None,
+            route_colors: &route_colors,
+            route_dashes: &BTreeMap::new(),
+            blend_mode: BlendMode::Normal,
+            // Hovering a highlighted route doesn't take its highlight away
+            hovered: Some(3),
+            highlighted: Some(&highlighted),
+            stack_offset: 0.0,
+        };
+
+        let highlighted_rows = |prepared: &PreparedTracks| {
+            let mut rows: Vec<usize> = prepared
+                .polylines
+                .iter()
+                .filter(|line| !line.outline && line.stroke.color == HIGHLIGHT_COLOR)
+                .map(|line| prepared.line_points(line)[0].y.round() as usize)
+                .collect();
+            rows.dedup();
+            rows
+        };
+
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, None, None, style);
+        assert_eq!(highlighted_rows(&prepared), vec![1, 3]);
+        assert_eq!(prepared.drawn_routes, vec![0, 1, 2, 3]);
+
+        // The internal selection adds to the external set
+        prepare_tracks(&mut prepared, &segments, &project, None, Some(0), style);
+        assert_eq!(highlighted_rows(&prepared), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn style_rules_dash_the_routes_of_matching_files() {
+        let segments: Vec<SimplifiedSegment> = (0..2)
+            .map(|route_index| {
+                let mut segment = gpx::TrackSegment::new();
+                for i in 0..10 {
+                    segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                        f64::from(i) * 0.001,
+                        route_index as f64,
+                    )));
+                }
+                let mut track = gpx::Track::new();
+                track.segments.push(segment);
+                let route = large_track_lib::Route::new(gpx::Gpx {
+                    tracks: vec![track],
+                    ..Default::default()
+                })
+                .unwrap();
+                let part = large_track_lib::SegmentPart::new(0, 0, 0..10, (0..10).collect());
+                SimplifiedSegment::new(route, route_index, vec![part])
+            })
+            .collect();
+        let project =
+            |position: walkers::Position| Pos2::new(position.x() as f32 * 1e4, position.y() as f32);
+        let rules = [crate::app::style_rules::StyleRule {
+            pattern: "planned-*".to_string(),
+            dash: DashPattern::Dashed,
+            color: None,
+        }];
+        let files = ["tracks/planned-teide.gpx", "tracks/teide.gpx"];
+        let route_dashes: BTreeMap<usize, DashPattern> = files
+            .iter()
+            .enumerate()
+            .filter_map(|(route_index, file)| {
+                let rule =
+                    crate::app::style_rules::first_match(&rules, std::path::Path::new(file))?;
+                Some((route_index, rule.dash))
+            })
+            .collect();
+        let style = TrackStyle {
+            width: 2.0,
+            show_outline: true,
+            compare_pair: None,
+            route_colors: &BTreeMap::new(),
+            route_dashes: &route_dashes,
+            blend_mode: BlendMode::Normal,
+            hovered: None,
+            highlighted: None,
+            stack_offset: 0.0,
+        };
+
+        // Selected too, so the highlight keeps the dashes
+        for selected in [None, Some(0)] {
+            let mut prepared = PreparedTracks::default();
+            prepare_tracks(&mut prepared, &segments, &project, None, selected, style);
+            let dashes_of_row = |row: usize| -> Vec<Option<(f32, f32)>> {
+                prepared
+                    .polylines
+                    .iter()
+                    .filter(|line| prepared.line_points(line)[0].y.round() as usize == row)
+                    .map(|line| line.dash)
+                    .collect()
+            };
+            // The planned route's line and outline are dashed alike, the other one is solid
+            let planned = dashes_of_row(0);
+            assert!(planned.len() >= 2, "{:?}", planned);
+            assert!(
+                planned
+                    .iter()
+                    .all(|dash| *dash == DashPattern::Dashed.lengths(2.0))
+            );
+            let recorded = dashes_of_row(1);
+            assert!(!recorded.is_empty());
+            assert!(recorded.iter().all(Option::is_none));
+        }
+    }
+
+    #[test]
+    fn full_detail_draws_every_point_of_the_selected_route_only() {
+        let mut collection = large_track_lib::RouteCollection::new(Default::default());
+        for route_index in 0..2 {
+            // A gentle zigzag: easily simplified away when zoomed out
+            let mut segment = gpx::TrackSegment::new();
+            for i in 0..2000 {
+                segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                    f64::from(i) * 1e-4,
+                    route_index as f64 * 0.05 + f64::from(i % 2) * 1e-5,
+                )));
+            }
+            let mut track = gpx::Track::new();
+            track.segments.push(segment);
+            collection
+                .add_route(gpx::Gpx {
+                    tracks: vec![track],
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let min = large_track_lib::utils::wgs84_to_mercator(-0.1, -0.1);
+        let max = large_track_lib::utils::wgs84_to_mercator(0.2, 0.3);
+        let viewport = geo::Rect::new(min.0, max.0);
+        let mut segments = collection.query_visible(viewport, (800.0, 600.0));
+        // Distinct original points drawn of a route; pieces of it share their ends
+        let points_of = |segments: &[SimplifiedSegment], route_index: usize| -> usize {
+            segments
+                .iter()
+                .filter(|segment| segment.route_index == route_index)
+                .flat_map(|segment| &segment.parts)
+                .flat_map(|part| {
+                    part.simplified_indices
+                        .iter()
+                        .map(|index| part.point_range.start + index)
+                })
+                .collect::<HashSet<usize>>()
+                .len()
+        };
+        let simplified = points_of(&segments, 0);
+        assert!(simplified < 2000);
+        assert!(points_of(&segments, 1) < 2000);
+
+        full_detail(&mut segments, 1);
+        // Route 1 is wholly in view, so all of it is drawn; route 0 is untouched
+        assert_eq!(points_of(&segments, 1), 2000);
+        assert_eq!(points_of(&segments, 0), simplified);
+
+        let project = |position: walkers::Position| {
+            Pos2::new(position.x() as f32 * 1e3, position.y() as f32 * 1e3)
+        };
+        let style = TrackStyle {
+            width: 2.0,
+            show_outline: false,
+            compare_pair: None,
+            route_colors: &BTreeMap::new(),
+            route_dashes: &BTreeMap::new(),
+            blend_mode: BlendMode::Normal,
+            hovered: None,
+            highlighted: None,
+            stack_offset: 0.0,
+        };
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, None, Some(1), style);
+        assert!(prepared.points_drawn >= 2000 + simplified);
+        assert_eq!(prepared.drawn_routes, vec![0, 1]);
+    }
+
+    #[test]
+    fn preparing_a_steady_view_again_allocates_nothing() {
+        let mut segment = gpx::TrackSegment::new();
+        for i in 0..50 {
+            segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                f64::from(i) * 0.001,
+                f64::from(i % 7) * 0.001,
+            )));
+        }
+        let mut track = gpx::Track::new();
+        track.segments.push(segment);
+        let route = large_track_lib::Route::new(gpx::Gpx {
+            tracks: vec![track],
+            ..Default::default()
+        })
+        .unwrap();
+        let part = |range: Range<usize>| {
+            let every_other = (0..range.len()).step_by(2).collect();
+            large_track_lib::SegmentPart::new(0, 0, range, every_other)
+        };
+        let segments: Vec<SimplifiedSegment> = (0..3)
+            .map(|route_index| {
+                SimplifiedSegment::new(route.clone(), route_index, vec![part(0..20), part(20..50)])
+            })
+            .collect();
+        let project = |position: walkers::Position| {
+            Pos2::new(position.x() as f32 * 1e4, position.y() as f32 * 1e4)
+        };
+        let route_colors = BTreeMap::new();
+        let style = TrackStyle {
+            width: 2.0,
+            show_outline: true,
+            compare_pair: None,
+            route_colors: &route_colors,
+            route_dashes: &BTreeMap::new(),
+            blend_mode: BlendMode::Normal,
+            hovered: Some(1),
+            highlighted: None,
+            stack_offset: 0.0,
+        };
+
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, None, Some(2), style);
+        let first = prepared.polylines.clone();
+        assert_eq!(prepared.drawn_routes, vec![0, 1, 2]);
+
+        let allocations = count_allocations(|| {
+            prepare_tracks(&mut prepared, &segments, &project, None, Some(2), style);
+        });
+        assert_eq!(allocations, 0);
+        assert_eq!(prepared.polylines, first);
+        assert_eq!(prepared.drawn_routes, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn perpendicular_offsets_are_sideways_and_sized_by_distance() {
+        let right = perpendicular_offset(Vec2::new(1.0, 0.0), 2.0);
+        assert_eq!(right, Vec2::new(0.0, 2.0));
+        // Only the direction matters, not its length
+        assert_eq!(perpendicular_offset(Vec2::new(10.0, 0.0), 2.0), right);
+        assert_eq!(
+            perpendicular_offset(Vec2::new(0.0, -3.0), 1.0),
+            Vec2::new(1.0, 0.0)
+        );
+        assert_eq!(
+            perpendicular_offset(Vec2::new(1.0, 0.0), -2.0),
+            Vec2::new(0.0, -2.0)
+        );
+        let diagonal = perpendicular_offset(Vec2::new(3.0, 4.0), 5.0);
+        assert!((diagonal - Vec2::new(-4.0, 3.0)).length() < 1e-5);
+        assert_eq!(perpendicular_offset(Vec2::ZERO, 2.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn tracks_on_the_same_path_are_spread_side_by_side() {
+        // Routes 0 to 2 share a path along latitude 0, route 3 runs elsewhere
+        let segments: Vec<SimplifiedSegment> = [0.0, 0.0, 0.0, 0.01]
+            .iter()
+            .enumerate()
+            .map(|(route_index, &lat)| {
+                let mut segment = gpx::TrackSegment::new();
+                for i in 0..5 {
+                    segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                        f64::from(i) * 0.001,
+                        lat,
+                    )));
+                }
+                let mut track = gpx::Track::new();
+                track.segments.push(segment);
+                let route = large_track_lib::Route::new(gpx::Gpx {
+                    tracks: vec![track],
+                    ..Default::default()
+                })
+                .unwrap();
+                let part = large_track_lib::SegmentPart::new(0, 0, 0..5, (0..5).collect());
+                SimplifiedSegment::new(route, route_index, vec![part])
+            })
+            .collect();
+        let project = |position: walkers::Position| {
+            Pos2::new(position.x() as f32 * 1e4, position.y() as f32 * 1e4)
+        };
+        let route_colors = BTreeMap::new();
+        let route_dashes = BTreeMap::new();
+        let style = |stack_offset| TrackStyle {
+            width: 2.0,
+            show_outline: false,
+            compare_pair: None,
+            route_colors: &route_colors,
+            route_dashes: &route_dashes,
+            blend_mode: BlendMode::Normal,
+            hovered: None,
+            highlighted: None,
+            stack_offset,
+        };
+        let rows = |prepared: &PreparedTracks| -> Vec<f32> {
+            prepared
+                .polylines
+                .iter()
+                .map(|line| {
+                    let points = prepared.line_points(line);
+                    // Straight lines stay straight, only moved sideways
+                    assert!(points.iter().all(|p| p.y == points[0].y));
+                    points[0].y
+                })
+                .collect()
+        };
+
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, None, None, style(0.0));
+        assert_eq!(rows(&prepared), [0.0, 0.0, 0.0, 100.0]);
+
+        prepare_tracks(&mut prepared, &segments, &project, None, None, style(3.0));
+        assert_eq!(rows(&prepared), [-3.0, 0.0, 3.0, 100.0]);
+
+        // A wide spacing is narrowed to keep the stack within bounds
+        prepare_tracks(&mut prepared, &segments, &project, None, None, style(100.0));
+        let half = MAX_STACK_SPREAD / 2.0;
+        assert_eq!(rows(&prepared), [-half, 0.0, half, 100.0]);
+    }
+
+    /// Paint premultiplied `src` over `dst` the way egui's renderers blend
+    fn paint_over(dst: Color32, src: Color32) -> Color32 {
+        let keep = 1.0 - f32::from(src.a()) / 255.0;
+        let channel = |s: u8, d: u8| (f32::from(s) + f32::from(d) * keep).round().min(255.0) as u8;
+        Color32::from_rgba_premultiplied(
+            channel(src.r(), dst.r()),
+            channel(src.g(), dst.g()),
+            channel(src.b(), dst.b()),
+            channel(src.a(), dst.a()),
+        )
+    }
+
+    #[test]
+    fn meter_widths_follow_the_map_scale() {
+        use large_track_lib::utils::meters_per_pixel;
+
+        // Zoom 16 at 60°N: 40075016.68 m * cos(60°) / (256 * 2^16) ≈ 1.194 m per point
+        let scale = meters_per_pixel(60.0, 16.0);
+        assert!((scale - 1.1943).abs() < 1e-3);
+        assert!((meters_to_points(50.0, scale) - 41.87).abs() < 0.01);
+        // One zoom level in doubles the width
+        let closer = meters_to_points(50.0, meters_per_pixel(60.0, 17.0));
+        assert!((closer - 2.0 * 41.87).abs() < 0.02);
+        // Zoom 10 at the equator: ≈ 152.9 m per point, so 50 m would be a third of a point
+        assert_eq!(
+            meters_to_points(50.0, meters_per_pixel(0.0, 10.0)),
+            MIN_METERS_WIDTH_POINTS
+        );
+        // A degenerate scale falls back to the minimum too
+        assert_eq!(meters_to_points(50.0, 0.0), MIN_METERS_WIDTH_POINTS);
+    }
+
+    #[test]
+    fn additive_lines_add_up_where_they_overlap() {
+        let background = Color32::from_rgb(20, 20, 20);
+        let red = Color32::from_rgb(200, 0, 0);
+        let blue = Color32::from_rgb(0, 0, 200);
+
+        // Normal: the line on top hides the one below
+        let normal = BlendMode::Normal;
+        let covered = paint_over(paint_over(background, normal.line_color(red)), blue);
+        assert_eq!(covered, blue);
+
+        // Additive: each line adds half its color to what is below
+        let additive = BlendMode::Additive;
+        let once = paint_over(background, additive.line_color(red));
+        assert_eq!(once, Color32::from_rgb(120, 20, 20));
+        let mixed = paint_over(once, additive.line_color(blue));
+        assert_eq!(mixed, Color32::from_rgb(120, 20, 120));
+        let twice = paint_over(once, additive.line_color(red));
+        assert_eq!(twice, Color32::from_rgb(220, 20, 20));
+        // Channels saturate instead of wrapping around
+        let thrice = paint_over(twice, additive.line_color(red));
+        assert_eq!(thrice, Color32::from_rgb(255, 20, 20));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod golden {
+    //! Golden-image tests: fixture tracks are prepared exactly as on the map,
+    //! rasterized with tiny-skia and compared against checked-in PNGs.
+    //!
+    //! Run with `cargo test -p large-track-viewer golden -- --ignored`; set
+    //! `UPDATE_GOLDEN=1` to (re)write the images after an intended change.
+
+    use super::*;
+    use large_track_lib::{Config, RouteCollection, utils::wgs84_to_mercator};
+    use std::path::PathBuf;
+
+    const WIDTH: u32 = 256;
+    const HEIGHT: u32 = 256;
+
+    /// Fraction of pixels allowed to differ beyond `CHANNEL_TOLERANCE`
+    const MAX_DIFFERING_PIXELS: f64 = 0.002;
+    const CHANNEL_TOLERANCE: u8 = 16;
+
+    fn test_data_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests")
+    }
+
+    fn fixture_collection(names: &[&str]) -> RouteCollection {
+        let mut collection = RouteCollection::new(Config::default());
+        for name in names {
+            let path = test_data_dir().join("fixtures").join(name);
+            let file = std::fs::File::open(&path).expect("fixture exists");
+            let gpx = gpx::read(std::io::BufReader::new(file)).expect("fixture parses");
+            collection.add_route(gpx).expect("fixture indexes");
+        }
+        collection
+    }
+
+    /// Query `collection` for a lat/lon box and prepare it for a WIDTH x HEIGHT screen
+    fn prepare_view(
+        collection: &RouteCollection,
+        (min_lat, min_lon, max_lat, max_lon): (f64, f64, f64, f64),
+        clip: Option<&[Pos2]>,
+        selected: Option<usize>,
+    ) -> PreparedTracks {
+        let min = wgs84_to_mercator(min_lat, min_lon);
+        let max = wgs84_to_mercator(max_lat, max_lon);
+        let viewport = geo::Rect::new(min.0, max.0);
+        let segments = collection.query_visible(viewport, (f64::from(WIDTH), f64::from(HEIGHT)));
+
+        let project = |position: walkers::Position| {
+            let p = wgs84_to_mercator(position.y(), position.x());
+            Pos2::new(
+                ((p.x() - min.x()) / (max.x() - min.x()) * f64::from(WIDTH)) as f32,
+                ((max.y() - p.y()) / (max.y() - min.y()) * f64::from(HEIGHT)) as f32,
+            )
+        };
+        let style = TrackStyle {
+            width: 3.0,
+            show_outline: true,
+            compare_pair: None,
+            route_colors: &BTreeMap::new(),
+            route_dashes: &BTreeMap::new(),
+            blend_mode: BlendMode::Normal,
+            hovered: None,
+            highlighted: None,
+            stack_offset: 0.0,
+        };
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, clip, selected, style);
+        prepared
+    }
+
+    fn rasterize(prepared: &PreparedTracks) -> tiny_skia::Pixmap {
+        let mut pixmap = tiny_skia::Pixmap::new(WIDTH, HEIGHT).expect("non-zero size");
+        pixmap.fill(tiny_skia::Color::WHITE);
+        for line in &prepared.polylines {
+            let points = prepared.line_points(line);
+            let mut builder = tiny_skia::PathBuilder::new();
+            builder.move_to(points[0].x, points[0].y);
+            for point in &points[1..] {
+                builder.line_to(point.x, point.y);
+            }
+            let Some(path) = builder.finish() else {
+                continue;
+            };
+            let [r, g, b, a] = line.stroke.color.to_srgba_unmultiplied();
+            let mut paint = tiny_skia::Paint::default();
+            paint.set_color_rgba8(r, g, b, a);
+            paint.anti_alias = true;
+            let stroke = tiny_skia::Stroke {
+                width: line.stroke.width,
+                line_cap: tiny_skia::LineCap::Round,
+                line_join: tiny_skia::LineJoin::Round,
+                ..Default::default()
+            };
+            pixmap.stroke_path(
+                &path,
+                &paint,
+                &stroke,
+                tiny_skia::Transform::identity(),
+                None,
+            );
+        }
+        pixmap
+    }
+
+    fn assert_matches_golden(name: &str, actual: &tiny_skia::Pixmap) {
+        let golden_path = test_data_dir().join("golden").join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            actual.save_png(&golden_path).expect("golden image written");
+            return;
+        }
+        let golden = tiny_skia::Pixmap::load_png(&golden_path).unwrap_or_else(|e| {
+            panic!(
+                "missing golden image {} ({}); run with UPDATE_GOLDEN=1 to create it",
+                golden_path.display(),
+                e
+            )
+        });
+        assert_eq!(
+            (golden.width(), golden.height()),
+            (actual.width(), actual.height())
+        );
+
+        let differing = golden
+            .pixels()
+            .iter()
+            .zip(actual.pixels())
+            .filter(|(expected, got)| {
+                let (e, g) = (expected.demultiply(), got.demultiply());
+                [
+                    e.red().abs_diff(g.red()),
+                    e.green().abs_diff(g.green()),
+                    e.blue().abs_diff(g.blue()),
+                    e.alpha().abs_diff(g.alpha()),
+                ]
+                .into_iter()
+                .any(|d| d > CHANNEL_TOLERANCE)
+            })
+            .count();
+        let fraction = differing as f64 / golden.pixels().len() as f64;
+        if fraction > MAX_DIFFERING_PIXELS {
+            let actual_path = std::env::temp_dir().join(format!("{}.actual.png", name));
+            let _ = actual.save_png(&actual_path);
+            panic!(
+                "{} differs from its golden image in {:.2}% of pixels; actual image at {}",
+                name,
+                fraction * 100.0,
+                actual_path.display()
+            );
+        }
+    }
+
+    /// Box around both fixtures
+    const BOTH_FIXTURES: (f64, f64, f64, f64) = (51.498, -0.132, 51.512, -0.112);
+
+    #[test]
+    fn outlines_go_under_lines_and_selection_on_top() {
+        let collection = fixture_collection(&["loop.gpx", "crossing.gpx"]);
+        let prepared = prepare_view(&collection, BOTH_FIXTURES, None, Some(0));
+
+        let outline_width = 3.0 + 2.0;
+        // Two layers, each all outlines and then the same runs as lines
+        let layers: Vec<&[StyledPolyline]> = prepared
+            .polylines
+            .chunk_by(|a, b| a.outline || !b.outline)
+            .collect();
+        assert_eq!(layers.len(), 2);
+        for layer in &layers {
+            let (outlines, lines) = layer.split_at(layer.len() / 2);
+            assert!(outlines.iter().all(|line| line.outline));
+            assert!(lines.iter().all(|line| !line.outline));
+            for (outline, line) in outlines.iter().zip(lines) {
+                assert_eq!(outline.points, line.points);
+                assert!(outline.stroke.width > line.stroke.width);
+            }
+        }
+        // Route 1 first in its own color, the selected route 0 last
+        assert_eq!(layers[0][0].stroke.width, outline_width);
+        let first_line = layers[0].iter().find(|line| !line.outline).unwrap();
+        assert_eq!(first_line.stroke.color, route_color(1));
+        let last = prepared.polylines.last().unwrap();
+        assert_eq!(last.stroke.color, Color32::from_rgb(255, 200, 0));
+    }
+
+    #[test]
+    #[ignore = "golden images; run with --ignored"]
+    fn golden_two_tracks_with_outline() {
+        let collection = fixture_collection(&["loop.gpx", "crossing.gpx"]);
+        let prepared = prepare_view(&collection, BOTH_FIXTURES, None, None);
+        assert!(prepared.fully_skipped_routes.is_empty());
+        assert_matches_golden("two_tracks_with_outline", &rasterize(&prepared));
+    }
+
+    #[test]
+    #[ignore = "golden images; run with --ignored"]
+    fn golden_selected_track_on_top() {
+        let collection = fixture_collection(&["loop.gpx", "crossing.gpx"]);
+        let prepared = prepare_view(&collection, BOTH_FIXTURES, None, Some(0));
+        assert_matches_golden("selected_track_on_top", &rasterize(&prepared));
+    }
+
+    #[test]
+    #[ignore = "golden images; run with --ignored"]
+    fn golden_clipped_to_region() {
+        let collection = fixture_collection(&["loop.gpx", "crossing.gpx"]);
+        let region = [
+            Pos2::new(64.0, 64.0),
+            Pos2::new(192.0, 64.0),
+            Pos2::new(192.0, 192.0),
+            Pos2::new(64.0, 192.0),
+        ];
+        let prepared = prepare_view(&collection, BOTH_FIXTURES, Some(&region), None);
+        assert_matches_golden("clipped_to_region", &rasterize(&prepared));
+    }
+}
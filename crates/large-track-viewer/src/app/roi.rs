@@ -0,0 +1,225 @@
+//! Polygon region of interest
+//!
+//! The user can draw a polygon on the map (click to add vertices, double-click
+//! to close it). While a closed polygon is active, the track plugin only draws
+//! the portions of each track that lie inside it.
+
+use egui::Pos2;
+
+/// A user-drawn polygon, stored in WGS84 so it stays put while panning/zooming
+#[derive(Clone, Debug, Default)]
+pub struct RoiPolygon {
+    /// Polygon vertices (x = longitude, y = latitude)
+    pub vertices: Vec<walkers::Position>,
+
+    /// Whether the polygon has been closed (and therefore clips rendering)
+    pub closed: bool,
+
+    /// Whether map clicks currently add vertices instead of selecting routes
+    pub drawing: bool,
+}
+
+impl RoiPolygon {
+    /// Discard any existing polygon and start collecting vertices
+    pub fn start_drawing(&mut self) {
+        self.vertices.clear();
+        self.closed = false;
+        self.drawing = true;
+    }
+
+    /// Append a vertex (only while drawing)
+    pub fn add_vertex(&mut self, position: walkers::Position) {
+        if self.drawing {
+            self.vertices.push(position);
+        }
+    }
+
+    /// Close the polygon. Returns false if there are not enough vertices yet.
+    pub fn close(&mut self) -> bool {
+        if self.vertices.len() < 3 {
+            return false;
+        }
+        self.closed = true;
+        self.drawing = false;
+        true
+    }
+
+    /// Remove the polygon entirely
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether rendering should currently be clipped to this polygon
+    pub fn is_active(&self) -> bool {
+        self.closed && self.vertices.len() >= 3
+    }
+}
+
+/// Even-odd (ray casting) point-in-polygon test
+pub fn point_in_polygon(point: Pos2, polygon: &[Pos2]) -> bool {
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for i in 0..polygon.len() {
+        let (pi, pj) = (polygon[i], polygon[j]);
+        if (pi.y > point.y) != (pj.y > point.y)
+            && point.x < (pj.x - pi.x) * (point.y - pi.y) / (pj.y - pi.y) + pi.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Parameters `t` in `(0, 1]` along `a -> b` where the segment crosses a polygon edge
+fn edge_crossings(a: Pos2, b: Pos2, polygon: &[Pos2]) -> Vec<f32> {
+    let cross = |u: egui::Vec2, v: egui::Vec2| u.x * v.y - u.y * v.x;
+    let ab = b - a;
+    let mut crossings = Vec::new();
+    for i in 0..polygon.len() {
+        let c = polygon[i];
+        let d = polygon[(i + 1) % polygon.len()];
+        let cd = d - c;
+        let denom = cross(ab, cd);
+        if denom.abs() < f32::EPSILON {
+            continue; // Parallel or degenerate edge
+        }
+        let t = cross(c - a, cd) / denom;
+        let u = cross(c - a, ab) / denom;
+        // Half-open ranges make shared endpoints count exactly once
+        if t > 0.0 && t <= 1.0 && (0.0..1.0).contains(&u) {
+            crossings.push(t);
+        }
+    }
+    crossings.sort_by(|x, y| x.total_cmp(y));
+    crossings
+}
+
+/// Clip a polyline to a polygon, returning the runs that lie inside it.
+///
+/// Vertices are classified with [`point_in_polygon`] and every crossing of a
+/// polygon edge inserts the exact intersection point, so runs start and end on
+/// the polygon outline. Runs with fewer than two points are dropped.
+pub fn clip_polyline_to_polygon(points: &[Pos2], polygon: &[Pos2]) -> Vec<Vec<Pos2>> {
+    let mut runs: Vec<Vec<Pos2>> = Vec::new();
+    if points.len() < 2 || polygon.len() < 3 {
+        return runs;
+    }
+
+    let mut current: Vec<Pos2> = Vec::new();
+    let mut inside = point_in_polygon(points[0], polygon);
+    if inside {
+        current.push(points[0]);
+    }
+
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        for t in edge_crossings(a, b, polygon) {
+            current.push(a + (b - a) * t);
+            if inside {
+                runs.push(std::mem::take(&mut current));
+            }
+            inside = !inside;
+        }
+
+        // Re-synchronize with the exact test to stay robust against degenerate crossings
+        let b_inside = point_in_polygon(b, polygon);
+        if b_inside != inside {
+            if inside {
+                runs.push(std::mem::take(&mut current));
+            }
+            inside = b_inside;
+        }
+        if inside {
+            current.push(b);
+        } else {
+            current.clear();
+        }
+    }
+    runs.push(current);
+
+    runs.retain(|run| run.len() >= 2);
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::pos2;
+
+    fn square() -> Vec<Pos2> {
+        vec![
+            pos2(0.0, 0.0),
+            pos2(10.0, 0.0),
+            pos2(10.0, 10.0),
+            pos2(0.0, 10.0),
+        ]
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        let polygon = square();
+        assert!(point_in_polygon(pos2(5.0, 5.0), &polygon));
+        assert!(!point_in_polygon(pos2(15.0, 5.0), &polygon));
+        assert!(!point_in_polygon(pos2(-1.0, -1.0), &polygon));
+    }
+
+    #[test]
+    fn test_clip_crossing_track() {
+        // Enters through the left edge and leaves through the right edge
+        let track = [pos2(-5.0, 5.0), pos2(5.0, 5.0), pos2(15.0, 5.0)];
+        let runs = clip_polyline_to_polygon(&track, &square());
+
+        assert_eq!(runs.len(), 1);
+        let run = &runs[0];
+        assert_eq!(run.len(), 3);
+        assert!((run[0] - pos2(0.0, 5.0)).length() < 1e-4);
+        assert_eq!(run[1], pos2(5.0, 5.0));
+        assert!((run[2] - pos2(10.0, 5.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn test_clip_track_leaving_and_reentering() {
+        // Inside, out through the top, back in, and ends inside
+        let track = [
+            pos2(2.0, 5.0),
+            pos2(4.0, 15.0),
+            pos2(6.0, 15.0),
+            pos2(8.0, 5.0),
+        ];
+        let runs = clip_polyline_to_polygon(&track, &square());
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0][0], pos2(2.0, 5.0));
+        assert!((runs[0][1].y - 10.0).abs() < 1e-4);
+        assert!((runs[1][0].y - 10.0).abs() < 1e-4);
+        assert_eq!(*runs[1].last().unwrap(), pos2(8.0, 5.0));
+    }
+
+    #[test]
+    fn test_clip_track_fully_inside_or_outside() {
+        let polygon = square();
+
+        let inside = [pos2(1.0, 1.0), pos2(9.0, 9.0)];
+        assert_eq!(
+            clip_polyline_to_polygon(&inside, &polygon),
+            vec![inside.to_vec()]
+        );
+
+        let outside = [pos2(20.0, 20.0), pos2(30.0, 20.0)];
+        assert!(clip_polyline_to_polygon(&outside, &polygon).is_empty());
+    }
+
+    #[test]
+    fn test_polygon_needs_three_vertices_to_close() {
+        let mut roi = RoiPolygon::default();
+        roi.start_drawing();
+        roi.add_vertex(walkers::lat_lon(0.0, 0.0));
+        roi.add_vertex(walkers::lat_lon(0.0, 1.0));
+        assert!(!roi.close());
+        roi.add_vertex(walkers::lat_lon(1.0, 1.0));
+        assert!(roi.close());
+        assert!(roi.is_active());
+        assert!(!roi.drawing);
+    }
+}
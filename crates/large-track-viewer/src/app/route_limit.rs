@@ -0,0 +1,56 @@
+//! Guarding imports against an unreasonable number of files
+//!
+//! Pointing the viewer at a huge folder by mistake would queue every file in
+//! it. A batch that would take the number of tracks over `--max-routes` is
+//! held back until the user loads all of it, only the files that fit, or
+//! none.
+
+use egui::DroppedFile;
+
+/// Default for `--max-routes`
+pub const DEFAULT_MAX_ROUTES: usize = 20_000;
+
+/// A batch load that was held back because it would exceed the route limit
+pub struct RouteLimitWarning {
+    /// Files of the batch, in load order
+    pub files: Vec<DroppedFile>,
+    /// Tracks loaded or already on their way when the batch was held back
+    pub loaded: usize,
+}
+
+impl RouteLimitWarning {
+    /// Number of leading files that fit under `max_routes` next to the loaded tracks
+    pub fn files_within(&self, max_routes: usize) -> usize {
+        max_routes.saturating_sub(self.loaded).min(self.files.len())
+    }
+}
+
+/// Whether `pending` more files next to `loaded` tracks go over `max_routes`
+/// (0 for no limit)
+pub fn exceeds_limit(loaded: usize, pending: usize, max_routes: usize) -> bool {
+    max_routes > 0 && loaded.saturating_add(pending) > max_routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches_over_the_limit_are_flagged() {
+        assert!(!exceeds_limit(0, 20_000, DEFAULT_MAX_ROUTES));
+        assert!(exceeds_limit(0, 20_001, DEFAULT_MAX_ROUTES));
+        assert!(exceeds_limit(19_990, 11, DEFAULT_MAX_ROUTES));
+        // Already over the limit: any more files need confirmation
+        assert!(exceeds_limit(25_000, 1, DEFAULT_MAX_ROUTES));
+        // 0 turns the limit off
+        assert!(!exceeds_limit(usize::MAX, 100_000, 0));
+
+        let warning = RouteLimitWarning {
+            files: vec![DroppedFile::default(); 50],
+            loaded: 80,
+        };
+        assert_eq!(warning.files_within(100), 20);
+        assert_eq!(warning.files_within(50), 0);
+        assert_eq!(warning.files_within(1000), 50);
+    }
+}
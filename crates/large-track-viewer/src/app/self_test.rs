@@ -0,0 +1,58 @@
+//! `--self-test`: check the spatial index on synthetic tracks
+//!
+//! Builds a collection from generated tracks (see [`synthetic`]), the same
+//! way loaded files are indexed, and runs [`RouteCollection::check_invariants`] on it.
+//! Meant for CI and bug reports: no window is needed to read the result, and
+//! the exit code says whether it passed.
+
+use crate::app::synthetic::{self, SyntheticParams};
+use large_track_lib::{Config, RouteCollection};
+
+/// Build the synthetic collection, check it and print the outcome
+///
+/// Returns whether every check passed.
+pub fn run() -> bool {
+    // Spread over most of Europe, so routes land in nodes at many levels
+    let params = SyntheticParams {
+        route_count: 24,
+        min_lat: 36.0,
+        max_lat: 60.0,
+        min_lon: -10.0,
+        max_lon: 30.0,
+        timestamps: false,
+        elevation: false,
+        ..Default::default()
+    };
+    let mut collection = RouteCollection::new(Config::default());
+    if let Err(e) = collection.add_routes_parallel(synthetic::generate(&params)) {
+        println!(
+            "self-test: FAIL (could not index the synthetic routes: {})",
+            e
+        );
+        return false;
+    }
+    match collection.check_invariants() {
+        Ok(()) => {
+            println!(
+                "self-test: PASS ({} routes, {} points)",
+                collection.route_count(),
+                collection.total_points()
+            );
+            true
+        }
+        Err(e) => {
+            println!("self-test: FAIL ({})", e);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synthetic_index_passes_the_self_test() {
+        assert!(run());
+    }
+}
@@ -10,6 +10,15 @@ pub struct Settings {
     #[clap(short, long, value_name = "FILE")]
     pub gpx_files: Vec<PathBuf>,
 
+    /// GPX files to load on startup, like --gpx-files. Opening a file with the
+    /// app from a file manager passes it this way.
+    #[clap(value_name = "FILE")]
+    pub files: Vec<PathBuf>,
+
+    /// GPX files to show for this session only; they are not reloaded on the next start
+    #[clap(long = "temporary", value_name = "FILE")]
+    pub temporary_files: Vec<PathBuf>,
+
     /// LOD bias (higher = more detail, range: 0.001-1000)
     #[clap(short, long, default_value = "1.0")]
     pub bias: f64,
@@ -34,16 +43,92 @@ pub struct Settings {
     #[clap(long, default_value = "true")]
     pub show_outline: bool,
 
-    /// Ignore previously persisted state and start fresh
+    /// Simplify every track at all zoom levels while loading, so rendering never
+    /// simplifies on the fly. Loads slower and uses noticeably more memory;
+    /// meant for static datasets viewed at mostly-fixed zoom.
+    #[clap(long, default_value = "false")]
+    pub precompute_lod: bool,
+
+    /// Start without map tiles: tracks are drawn on a plain grid and no tile
+    /// server is contacted. Overrides the persisted tile provider.
+    #[clap(long, default_value = "false")]
+    pub no_basemap: bool,
+
+    /// Maximum number of loaded files added to the map per frame. Higher values
+    /// drain a large import in fewer frames at the cost of longer frames.
+    #[clap(long, default_value = "4", value_name = "N")]
+    pub results_per_frame: usize,
+
+    /// Log a "Loaded N/M files" summary every N files of a bulk load (0 for
+    /// none). Per-file lines are debug level, shown with
+    /// RUST_LOG=large_track_viewer=debug.
+    #[clap(long, default_value_t = crate::app::load_log::DEFAULT_LOAD_LOG_EVERY, value_name = "N")]
+    pub load_log_every: usize,
+
+    /// Memory budget in MiB for loaded tracks; loads beyond it ask for confirmation.
+    /// Defaults to half of the system memory.
+    #[clap(long, value_name = "MIB")]
+    pub memory_budget_mb: Option<u64>,
+
+    /// Most tracks to load without asking; a batch of files going over it asks
+    /// whether to load all of it, only the files that fit, or none. 0 for no limit.
+    #[clap(long, default_value_t = crate::app::route_limit::DEFAULT_MAX_ROUTES, value_name = "N")]
+    pub max_routes: usize,
+
+    /// Fill in missing elevations from an elevation web service while loading.
+    /// Off by default, as sampled track coordinates are sent to the service.
+    /// Native only.
+    #[clap(long, default_value = "false")]
+    pub download_elevation: bool,
+
+    /// Open-Elevation compatible lookup endpoint used by --download-elevation
+    #[clap(
+        long,
+        default_value = "https://api.open-elevation.com/api/v1/lookup",
+        value_name = "URL"
+    )]
+    pub elevation_endpoint: String,
+
+    /// Maximum number of map tiles downloaded at once. Tiles requested beyond it
+    /// wait (or are dropped if they scroll out of view) instead of queueing up,
+    /// which leaves bandwidth for loading tracks on slow connections.
+    #[clap(long, default_value_t = crate::app::basemap::DEFAULT_TILE_CONCURRENCY, value_name = "N")]
+    pub tile_concurrency: usize,
+
+    /// Start with the map locked (true) or unlocked (false), replacing the saved
+    /// choice. A locked map ignores one-finger drags and plain wheel turns; in
+    /// the browser, the `clilock-map=true` query parameter keeps a map embedded
+    /// in a page from catching the page's scrolling.
+    #[clap(long, value_name = "BOOL")]
+    pub lock_map: Option<bool>,
+
+    /// Start empty this time, as the "Start empty" startup setting does. Settings
+    /// are restored and the previous file list is kept for the next start.
+    #[clap(long, default_value = "false")]
+    pub fresh: bool,
+
+    /// Ignore previously persisted state and start with defaults. Nothing is
+    /// saved during the session, so the persisted state is still there next time.
     #[clap(long, default_value = "false")]
     pub ignore_persisted: bool,
+
+    /// Start in safe mode, for when a saved file or setting crashes the app on
+    /// startup: like --ignore-persisted and --no-basemap together, so default
+    /// settings, no restored files and no tile downloads, and profiling is not
+    /// started by ENABLE_PROFILING
+    #[clap(long, default_value = "false")]
+    pub safe_mode: bool,
+
+    /// Check the spatial index on synthetic tracks, print pass/fail and exit
+    #[clap(long, hide = true)]
+    pub self_test: bool,
 }
 
 impl Settings {
     /// Create default settings
     pub fn from_cli() -> Self {
         match parse_args::<Settings>() {
-            Ok(args) => args,
+            Ok(args) => args.with_positional_files().with_safe_mode(),
             Err(e) => {
                 #[cfg(not(target_arch = "wasm32"))]
                 e.exit();
@@ -66,4 +151,44 @@ impl Settings {
             }
         }
     }
+
+    /// Turn on the flags `--safe-mode` stands for
+    pub fn with_safe_mode(mut self) -> Self {
+        if self.safe_mode {
+            self.ignore_persisted = true;
+            self.no_basemap = true;
+        }
+        self
+    }
+
+    /// Move the plain (flagless) file arguments into `gpx_files`, after the flagged ones
+    pub fn with_positional_files(mut self) -> Self {
+        let files = std::mem::take(&mut self.files);
+        self.gpx_files.extend(files);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positional_paths_with_spaces_and_unicode_are_loaded() {
+        let opened = "/home/ana/Mis rutas/Vuelta al Teide ⛰ 2024.gpx";
+        let args = Settings::parse_from(["large-track-viewer", opened]).with_positional_files();
+        assert_eq!(args.gpx_files, vec![PathBuf::from(opened)]);
+        assert!(args.files.is_empty());
+
+        let args = Settings::parse_from(["large-track-viewer", "-g", "a.gpx", opened, "b.gpx"])
+            .with_positional_files();
+        assert_eq!(
+            args.gpx_files,
+            vec![
+                PathBuf::from("a.gpx"),
+                PathBuf::from(opened),
+                PathBuf::from("b.gpx")
+            ]
+        );
+    }
 }
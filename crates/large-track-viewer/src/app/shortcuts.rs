@@ -0,0 +1,178 @@
+//! Keyboard shortcut registry
+//!
+//! Every shortcut is declared once here. The input handler consumes them from
+//! the registry and the help overlay and Settings tab render their text from
+//! it, so the documented keys can't drift from the handled ones.
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Something a shortcut can trigger
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortcutAction {
+    ToggleHelp,
+    ToggleSidebar,
+    FitBounds,
+    ToggleTracks,
+    ZoomIn,
+    ZoomOut,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+}
+
+impl ShortcutAction {
+    /// All actions, in the order they are listed to the user
+    pub const ALL: [Self; 10] = [
+        Self::ToggleHelp,
+        Self::ToggleSidebar,
+        Self::FitBounds,
+        Self::ToggleTracks,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::PanLeft,
+        Self::PanRight,
+        Self::PanUp,
+        Self::PanDown,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::ToggleHelp => "Toggle help",
+            Self::ToggleSidebar => "Toggle sidebar",
+            Self::FitBounds => "Fit map to tracks",
+            Self::ToggleTracks => "Show/hide tracks",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::PanLeft => "Pan left",
+            Self::PanRight => "Pan right",
+            Self::PanUp => "Pan up",
+            Self::PanDown => "Pan down",
+        }
+    }
+}
+
+/// Mapping from actions to the shortcuts that trigger them
+///
+/// An action may have several shortcuts (e.g. F1 and Ctrl+H both toggle help).
+pub struct Shortcuts {
+    bindings: Vec<(ShortcutAction, KeyboardShortcut)>,
+    /// Modifier that turns the scroll wheel into zoom (handled by the map widget)
+    scroll_zoom_modifiers: Modifiers,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        use ShortcutAction::*;
+        let bind = |action, modifiers, key| (action, KeyboardShortcut::new(modifiers, key));
+        Self {
+            bindings: vec![
+                bind(ToggleHelp, Modifiers::NONE, Key::F1),
+                bind(ToggleHelp, Modifiers::CTRL, Key::H),
+                bind(ToggleSidebar, Modifiers::CTRL, Key::B),
+                bind(FitBounds, Modifiers::CTRL, Key::Num0),
+                bind(ToggleTracks, Modifiers::NONE, Key::T),
+                bind(ZoomIn, Modifiers::CTRL, Key::Plus),
+                bind(ZoomIn, Modifiers::CTRL, Key::Equals),
+                bind(ZoomOut, Modifiers::CTRL, Key::Minus),
+                bind(PanLeft, Modifiers::NONE, Key::ArrowLeft),
+                bind(PanRight, Modifiers::NONE, Key::ArrowRight),
+                bind(PanUp, Modifiers::NONE, Key::ArrowUp),
+                bind(PanDown, Modifiers::NONE, Key::ArrowDown),
+            ],
+            scroll_zoom_modifiers: Modifiers::CTRL,
+        }
+    }
+}
+
+impl Shortcuts {
+    /// Consume this frame's shortcut presses and return the triggered actions
+    ///
+    /// Nothing fires while a text field has keyboard focus, so typing (and
+    /// moving the cursor with the arrow keys) never moves the map.
+    pub fn consume_triggered(&self, ctx: &egui::Context) -> Vec<ShortcutAction> {
+        if ctx.wants_keyboard_input() {
+            return Vec::new();
+        }
+        ctx.input_mut(|i| {
+            // Shortcuts with more modifiers first, so Ctrl+H isn't taken by a bare H binding
+            let mut bindings: Vec<_> = self.bindings.iter().collect();
+            bindings.sort_by_key(|(_, shortcut)| std::cmp::Reverse(modifier_count(shortcut)));
+            bindings
+                .into_iter()
+                .filter(|(_, shortcut)| i.consume_shortcut(shortcut))
+                .map(|(action, _)| *action)
+                .collect()
+        })
+    }
+
+    /// Human readable shortcuts for an action, e.g. "F1 / Ctrl+H"
+    pub fn keys_text(&self, ctx: &egui::Context, action: ShortcutAction) -> String {
+        self.bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .map(|(_, shortcut)| ctx.format_shortcut(shortcut))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// One "keys - description" line per action
+    pub fn help_lines(&self, ctx: &egui::Context) -> Vec<String> {
+        ShortcutAction::ALL
+            .iter()
+            .map(|&action| format!("{} - {}", self.keys_text(ctx, action), action.description()))
+            .collect()
+    }
+
+    /// Whether the given modifiers make the scroll wheel zoom the map
+    pub fn is_scroll_zoom(&self, modifiers: Modifiers) -> bool {
+        modifiers.matches_logically(self.scroll_zoom_modifiers)
+    }
+
+    /// Human readable scroll-zoom gesture, e.g. "Ctrl + Scroll"
+    pub fn scroll_zoom_text(&self, ctx: &egui::Context) -> String {
+        let is_mac = ctx.os() == egui::os::OperatingSystem::Mac;
+        let modifiers = egui::ModifierNames::NAMES.format(&self.scroll_zoom_modifiers, is_mac);
+        format!("{} + Scroll", modifiers)
+    }
+}
+
+fn modifier_count(shortcut: &KeyboardShortcut) -> usize {
+    let m = shortcut.modifiers;
+    [m.alt, m.ctrl, m.shift, m.mac_cmd, m.command]
+        .into_iter()
+        .filter(|&set| set)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_has_a_unique_binding() {
+        let shortcuts = Shortcuts::default();
+        for action in ShortcutAction::ALL {
+            assert!(
+                shortcuts.bindings.iter().any(|(bound, _)| *bound == action),
+                "{:?} has no shortcut",
+                action
+            );
+        }
+        for (i, (_, a)) in shortcuts.bindings.iter().enumerate() {
+            for (_, b) in &shortcuts.bindings[i + 1..] {
+                assert_ne!(a, b, "shortcut bound twice");
+            }
+        }
+    }
+
+    #[test]
+    fn help_lines_come_from_the_registry() {
+        let ctx = egui::Context::default();
+        let shortcuts = Shortcuts::default();
+        let lines = shortcuts.help_lines(&ctx);
+        assert_eq!(lines.len(), ShortcutAction::ALL.len());
+        assert!(lines[0].starts_with("F1 / "));
+        assert!(lines[0].ends_with("Toggle help"));
+    }
+}
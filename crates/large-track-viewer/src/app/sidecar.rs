@@ -0,0 +1,151 @@
+//! Per-track customizations shared as a sidecar file next to the GPX
+//!
+//! With "Sidecar files" on, a file's color, notes and tags are also written
+//! to `<file>.ltv.json` when they are edited, so they travel with the file
+//! to anyone sharing the folder. The sidecar is read when the file is loaded.
+//!
+//! Merge rule on load: every field set in the sidecar (a color, non-empty
+//! notes, any tags) replaces the local value, fields it leaves unset keep the
+//! local one. The local store ([`crate::app::annotations`] and the picked
+//! colors) then holds the merged result, which the next edit writes back.
+//!
+//! Sidecars are best effort: missing, unreadable or unwritable ones (e.g. a
+//! read-only folder) are logged and otherwise ignored.
+
+use crate::app::annotations::Annotation;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Appended to the GPX file name to name its sidecar
+pub const SIDECAR_SUFFIX: &str = ".ltv.json";
+
+/// Customizations of one track, as stored in its sidecar
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Sidecar {
+    /// Line color as RGB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl Sidecar {
+    pub fn new(color: Option<[u8; 3]>, annotation: Option<&Annotation>) -> Self {
+        let annotation = annotation.cloned().unwrap_or_default();
+        Self {
+            color,
+            notes: annotation.notes,
+            tags: annotation.tags,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.color.is_none() && self.notes.is_empty() && self.tags.is_empty()
+    }
+
+    /// Merge into the local color and annotation (see the module docs)
+    pub fn merge(
+        &self,
+        color: Option<[u8; 3]>,
+        annotation: Option<&Annotation>,
+    ) -> (Option<[u8; 3]>, Annotation) {
+        let mut merged = annotation.cloned().unwrap_or_default();
+        if !self.notes.is_empty() {
+            merged.notes = self.notes.clone();
+        }
+        if !self.tags.is_empty() {
+            merged.tags = self.tags.clone();
+        }
+        (self.color.or(color), merged)
+    }
+}
+
+/// Sidecar path of a GPX file, `None` for files that aren't on disk
+pub fn sidecar_path(path: &Path) -> Option<PathBuf> {
+    // Dropped, recorded and generated files get `scheme://` paths
+    if path.to_string_lossy().contains("://") {
+        return None;
+    }
+    let mut name = path.file_name()?.to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    Some(path.with_file_name(name))
+}
+
+/// Read the sidecar of `path`, if it has a readable one
+pub fn read(path: &Path) -> Option<Sidecar> {
+    let sidecar = sidecar_path(path)?;
+    let json = match std::fs::read_to_string(&sidecar) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            tracing::warn!("Could not read {}: {}", sidecar.display(), e);
+            return None;
+        }
+    };
+    serde_json::from_str(&json)
+        .inspect_err(|e| tracing::warn!("Ignoring malformed {}: {}", sidecar.display(), e))
+        .ok()
+}
+
+/// Write the sidecar of `path`, or remove it once it holds nothing
+pub fn write(path: &Path, contents: &Sidecar) {
+    let Some(sidecar) = sidecar_path(path) else {
+        return;
+    };
+    let result = if contents.is_empty() {
+        match std::fs::remove_file(&sidecar) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    } else {
+        serde_json::to_string_pretty(contents)
+            .map_err(std::io::Error::other)
+            .and_then(|json| std::fs::write(&sidecar, json))
+    };
+    if let Err(e) = result {
+        tracing::warn!("Could not write {}: {}", sidecar.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sidecar_round_trips_and_wins_where_set() {
+        let dir = std::env::temp_dir().join(format!("ltv-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let gpx = dir.join("alps.gpx");
+        assert_eq!(
+            sidecar_path(&gpx),
+            Some(dir.join(format!("alps.gpx{}", SIDECAR_SUFFIX)))
+        );
+        assert_eq!(sidecar_path(Path::new("web://alps.gpx")), None);
+
+        let local = Annotation {
+            notes: "Mine".to_string(),
+            tags: vec!["local".to_string()],
+        };
+        let shared = Sidecar {
+            color: Some([200, 0, 0]),
+            notes: String::new(),
+            tags: vec!["team".to_string()],
+        };
+        write(&gpx, &shared);
+        let read_back = read(&gpx).unwrap();
+        assert_eq!(read_back, shared);
+
+        let (color, merged) = read_back.merge(Some([0, 0, 200]), Some(&local));
+        assert_eq!(color, Some([200, 0, 0]));
+        assert_eq!(merged.notes, "Mine");
+        assert_eq!(merged.tags, vec!["team"]);
+
+        // Clearing everything removes the file
+        write(&gpx, &Sidecar::default());
+        assert!(read(&gpx).is_none());
+        assert!(!sidecar_path(&gpx).unwrap().exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
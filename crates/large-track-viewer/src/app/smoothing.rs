@@ -0,0 +1,77 @@
+//! Exponential moving averages for numbers that change every frame
+//!
+//! Per-frame readouts like the query time jump around too fast to read. The
+//! sidebar shows them through an [`Ema`] instead, while the raw values stay
+//! in [`crate::app::state::Stats`] for profiling.
+
+use std::time::Duration;
+
+/// Time constant used until the user picks another one, in seconds
+pub const DEFAULT_STATS_SMOOTHING_SECS: f32 = 0.5;
+
+/// Exponential moving average weighted by elapsed time
+///
+/// Weighting by time rather than by sample keeps the smoothing the same at
+/// any frame rate: after one time constant a step change is ~63% through.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ema {
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// Blend in a sample taken `elapsed` after the previous one and return the new average
+    ///
+    /// The first sample, and any sample with a zero time constant, is taken as is.
+    pub fn update(&mut self, sample: f64, elapsed: Duration, time_constant: Duration) -> f64 {
+        let value = match self.value {
+            Some(previous) if !time_constant.is_zero() => {
+                let alpha = 1.0 - (-elapsed.as_secs_f64() / time_constant.as_secs_f64()).exp();
+                previous + alpha * (sample - previous)
+            }
+            _ => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    /// The current average, `None` before the first sample
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ema_converges_to_a_steady_value() {
+        let frame = Duration::from_millis(16);
+        let time_constant = Duration::from_millis(500);
+        let mut ema = Ema::default();
+        assert_eq!(ema.update(0.0, frame, time_constant), 0.0);
+
+        // Half a second in, a step to 10 is about 63% through
+        let mut value = 0.0;
+        for _ in 0..31 {
+            value = ema.update(10.0, frame, time_constant);
+        }
+        assert!((value - 6.3).abs() < 0.2, "{value}");
+
+        for _ in 0..300 {
+            value = ema.update(10.0, frame, time_constant);
+        }
+        assert!((value - 10.0).abs() < 1e-3);
+        assert_eq!(ema.value(), Some(value));
+    }
+
+    #[test]
+    fn zero_time_constant_disables_smoothing() {
+        let mut ema = Ema::default();
+        ema.update(1.0, Duration::from_millis(16), Duration::ZERO);
+        assert_eq!(
+            ema.update(5.0, Duration::from_millis(16), Duration::ZERO),
+            5.0
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Cutting a route in two at a point picked on the map
+//!
+//! With a route selected, "Split at point" waits for a click near it. The
+//! click snaps to the route's nearest original point and both parts are
+//! previewed in their own colors; confirming replaces the file with two
+//! in-memory files sharing the cut point (see [`SPLIT_PATH_PREFIX`]), each
+//! listed, colored and exportable on its own. Undo puts the original back.
+
+use large_track_lib::Route;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Path prefix of the parts of a split file, which only live in memory
+pub const SPLIT_PATH_PREFIX: &str = "split://";
+
+/// Clicks farther than this from the route, in points, don't pick a cut
+pub const PICK_RADIUS_POINTS: f32 = 24.0;
+
+/// Most points drawn of each part in the preview
+const PREVIEW_MAX_POINTS: usize = 4000;
+
+/// A cut picked but not applied yet
+pub struct SplitPreview {
+    /// Both parts, ready to replace the route
+    pub parts: (Arc<Route>, Arc<Route>),
+    /// Both parts thinned out for drawing
+    pub lines: [Vec<walkers::Position>; 2],
+}
+
+/// What's needed to undo one split
+pub struct SplitUndo {
+    /// The file that was split, and its contents
+    pub original: (PathBuf, gpx::Gpx),
+    /// Paths the parts were listed under
+    pub parts: [PathBuf; 2],
+}
+
+/// State of the split tool
+#[derive(Default)]
+pub struct SplitTool {
+    /// Route being split while the tool is on
+    pub route: Option<usize>,
+    /// The picked cut, once the route was clicked
+    pub preview: Option<SplitPreview>,
+    /// Splits of this session that can still be undone, latest last
+    pub undo: Vec<SplitUndo>,
+}
+
+impl SplitTool {
+    /// Whether map clicks pick a cut instead of selecting routes
+    pub fn is_active(&self) -> bool {
+        self.route.is_some()
+    }
+
+    /// Wait for a click on `route`
+    pub fn start(&mut self, route: usize) {
+        self.route = Some(route);
+        self.preview = None;
+    }
+
+    /// Leave the tool without splitting
+    pub fn cancel(&mut self) {
+        self.route = None;
+        self.preview = None;
+    }
+}
+
+/// Preview of cutting `route` at `cut`, given as (track, segment, point)
+/// indices, or why it can't be cut there
+pub fn preview(route: &Route, cut: (usize, usize, usize)) -> Result<SplitPreview, String> {
+    let (first, second) = route
+        .split_at(cut.0, cut.1, cut.2)
+        .map_err(|e| e.to_string())?;
+    let lines = [preview_line(&first), preview_line(&second)];
+    Ok(SplitPreview {
+        parts: (first, second),
+        lines,
+    })
+}
+
+/// All points of `route` in order, keeping every n-th so at most
+/// [`PREVIEW_MAX_POINTS`] (plus the last one) are drawn
+fn preview_line(route: &Route) -> Vec<walkers::Position> {
+    let step = route.total_points().div_ceil(PREVIEW_MAX_POINTS).max(1);
+    let points: Vec<&gpx::Waypoint> = route
+        .tracks()
+        .iter()
+        .flat_map(|track| &track.segments)
+        .flat_map(|segment| &segment.points)
+        .collect();
+    let mut line: Vec<walkers::Position> = points
+        .iter()
+        .step_by(step)
+        .map(|point| walkers::lat_lon(point.point().y(), point.point().x()))
+        .collect();
+    // Keep the far end, so the part visibly reaches it
+    if !(points.len() - 1).is_multiple_of(step)
+        && let Some(last) = points.last()
+    {
+        line.push(walkers::lat_lon(last.point().y(), last.point().x()));
+    }
+    line
+}
+
+/// Paths to list the two parts of `path` under, skipping ones `is_taken`
+pub fn part_paths(path: &Path, is_taken: impl Fn(&Path) -> bool) -> [PathBuf; 2] {
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let part = |n: usize, copy: usize| {
+        let suffix = if copy > 1 {
+            format!(" #{}", copy)
+        } else {
+            String::new()
+        };
+        PathBuf::from(format!(
+            "{}{} ({} of 2){}.gpx",
+            SPLIT_PATH_PREFIX, stem, n, suffix
+        ))
+    };
+    let mut copy = 1;
+    while is_taken(&part(1, copy)) || is_taken(&part(2, copy)) {
+        copy += 1;
+    }
+    [part(1, copy), part(2, copy)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use large_track_lib::{RouteBuilder, TrackPoint};
+
+    #[test]
+    fn parts_are_named_after_the_file_and_previewed_whole() {
+        let [first, second] = part_paths(Path::new("/rides/out-and-back.gpx"), |_| false);
+        assert_eq!(first, PathBuf::from("split://out-and-back (1 of 2).gpx"));
+        assert_eq!(second, PathBuf::from("split://out-and-back (2 of 2).gpx"));
+        // Another file with the same name was split already
+        let [again, _] = part_paths(Path::new("/other/out-and-back.gpx"), |path| *path == first);
+        assert_eq!(again, PathBuf::from("split://out-and-back (1 of 2) #2.gpx"));
+
+        let route = RouteBuilder::from_gpx(gpx::Gpx::default())
+            .add_segment((0..10_001).map(|i| TrackPoint::new(45.0, i as f64 * 1e-5)))
+            .build()
+            .unwrap();
+        let preview = preview(&route, (0, 0, 2000)).unwrap();
+        assert_eq!(preview.parts.0.total_points(), 2001);
+        assert_eq!(preview.parts.1.total_points(), 8001);
+        // Thinned out, but both lines meet at the cut and reach the ends
+        let [head, tail] = &preview.lines;
+        assert!(tail.len() <= PREVIEW_MAX_POINTS + 1);
+        assert_eq!(head.last(), tail.first());
+        assert!(head[0].x().abs() < 1e-12);
+        assert!((tail.last().unwrap().x() - 0.1).abs() < 1e-9);
+
+        assert!(super::preview(&route, (0, 0, 0)).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Timing the stages of a cold start
+//!
+//! `LargeTrackViewerApp::new` runs each stage (settings load, state restore,
+//! tile sources, ...) through [`StartupTimings::stage`], which wraps it in a
+//! tracing span and keeps its duration. Once the first frame is drawn, one
+//! summary line is logged at info level and kept for the Debug section, so
+//! a slow start can be pinned on a stage from a user's report.
+
+use std::time::Duration;
+
+/// Durations of the startup stages, in the order they ran
+#[derive(Debug)]
+pub struct StartupTimings {
+    /// When the app started being built
+    started: instant::Instant,
+    /// Each stage and how long it took
+    stages: Vec<(&'static str, Duration)>,
+    /// Logged once the first frame is done
+    summary: Option<String>,
+}
+
+impl Default for StartupTimings {
+    fn default() -> Self {
+        Self {
+            started: instant::Instant::now(),
+            stages: Vec::new(),
+            summary: None,
+        }
+    }
+}
+
+impl StartupTimings {
+    /// Run `stage` inside a tracing span and record how long it took
+    pub fn stage<T>(&mut self, name: &'static str, stage: impl FnOnce() -> T) -> T {
+        let _span = tracing::info_span!("startup", stage = name).entered();
+        let start = instant::Instant::now();
+        let result = stage();
+        self.stages.push((name, start.elapsed()));
+        result
+    }
+
+    /// Record the first frame, which took `frame` and spent `query` querying
+    /// tracks, and log the summary; later calls do nothing
+    pub fn finish_first_frame(&mut self, frame: Duration, query: Duration) {
+        if self.summary.is_some() {
+            return;
+        }
+        self.stages.push(("first frame", frame));
+        self.stages.push(("first query", query));
+        let summary = summary_line(self.started.elapsed(), &self.stages);
+        tracing::info!("{}", summary);
+        self.summary = Some(summary);
+    }
+
+    /// The summary line, once the first frame is drawn
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+}
+
+/// "Started in 412 ms: settings 3 ms, state 40 ms, ..."
+fn summary_line(total: Duration, stages: &[(&'static str, Duration)]) -> String {
+    let ms = |duration: Duration| format!("{:.0} ms", duration.as_secs_f64() * 1000.0);
+    let stages: Vec<String> = stages
+        .iter()
+        .map(|(name, duration)| format!("{} {}", name, ms(*duration)))
+        .collect();
+    format!("Started in {}: {}", ms(total), stages.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_summarized_once_in_order() {
+        let mut timings = StartupTimings::default();
+        assert_eq!(timings.stage("settings", || 7), 7);
+        timings.stage("tile sources", || ());
+        assert!(timings.summary().is_none());
+
+        timings.finish_first_frame(Duration::from_millis(120), Duration::from_millis(30));
+        let summary = timings.summary().unwrap().to_string();
+        assert!(summary.starts_with("Started in "), "{}", summary);
+        let settings = summary.find("settings ").unwrap();
+        let tiles = summary.find("tile sources ").unwrap();
+        assert!(settings < tiles);
+        assert!(summary.ends_with("first frame 120 ms, first query 30 ms"));
+
+        // Later frames leave it alone
+        timings.finish_first_frame(Duration::from_secs(1), Duration::ZERO);
+        assert_eq!(timings.summary(), Some(summary.as_str()));
+
+        assert_eq!(
+            summary_line(
+                Duration::from_micros(412_400),
+                &[("settings", Duration::from_micros(2_600))]
+            ),
+            "Started in 412 ms: settings 3 ms"
+        );
+    }
+}
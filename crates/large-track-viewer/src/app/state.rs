@@ -3,35 +3,82 @@
 //! This module manages the application state including route collections,
 //! UI settings, and file loading operations.
 
+use crate::app::activity::Activity;
+use crate::app::annotations::{self, TrackAnnotations};
+use crate::app::comparison::CorridorComparison;
+use crate::app::coords::CoordFormat;
+use crate::app::detail::BiasDebouncer;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::app::elevation::ElevationService;
+use crate::app::frame_budget::{DEFAULT_FRAME_BUDGET_MS, FrameBudget};
+use crate::app::inertia::DEFAULT_PAN_FRICTION;
+use crate::app::ingest::{Ingestor, catch_panic, load_concurrency, synthetic_path_for};
+use crate::app::load_error::LoadError;
+use crate::app::load_log::LoadLog;
+use crate::app::location::{ExternalPosition, LiveLocation};
+use crate::app::memory_budget::{self, MemoryReadout, MemoryWarning};
+use crate::app::notifications::{NotificationAction, NotificationLevel, Toasts};
+use crate::app::playback::Playback;
+use crate::app::plugin::RenderStats;
+use crate::app::recorder::{RECORDING_PATH_PREFIX, TrackRecorder};
+use crate::app::render::{
+    BlendMode, DEFAULT_LINE_WIDTH_M, DEFAULT_STACK_OFFSET, SkipCounters, WidthMode, route_color,
+};
+use crate::app::roi::RoiPolygon;
+use crate::app::route_limit::{self, RouteLimitWarning};
 use crate::app::settings::Settings;
+use crate::app::shortcuts::Shortcuts;
+use crate::app::sidecar::{self, Sidecar};
+use crate::app::smoothing::{DEFAULT_STATS_SMOOTHING_SECS, Ema};
+use crate::app::split::{self, SPLIT_PATH_PREFIX, SplitTool, SplitUndo};
+use crate::app::startup::StartupTimings;
+use crate::app::storage::StorageResult;
+use crate::app::style_rules::{self, DashPattern, StyleRule};
+use crate::app::svg_export::SvgRoute;
+use crate::app::synthetic::{self, SYNTHETIC_PATH_PREFIX, SyntheticParams};
+use crate::app::timezone::{self, DisplayZone, TimeZone};
+use crate::app::track_groups::TrackGrouping;
 use eframe_entrypoints::async_runtime;
 use eframe_entrypoints::async_runtime::RwLock;
-use egui::DroppedFile;
-use large_track_lib::{Config, RouteCollection};
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
-
-/// Generate a stable synthetic path for a dropped file when a real path is unavailable.
-fn synthetic_path_for(dropped: &DroppedFile) -> PathBuf {
-    // Avoid duplicates by appending hash of content.
-    let fakepath = if let Some(p) = dropped.path.as_ref() {
-        p.clone()
-    } else {
-        // Produce a URI-like synthetic identifier: web://<id>-<size>-<name>
-        PathBuf::from(dropped.name.clone())
-    };
-    if let Some(bytes) = &dropped.bytes {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::Hash;
-        use std::hash::Hasher;
-        let mut hasher = DefaultHasher::new();
-        bytes.hash(&mut hasher);
-        let hash_str = format!("{:x}", hasher.finish());
-        let hash_str = hash_str[..8.min(hash_str.len())].to_string();
-        PathBuf::from(format!("{}-{}", fakepath.display(), hash_str))
-    } else {
-        fakepath
+use eframe_entrypoints::file_picker::SaveOutcome;
+use egui::{Color32, DroppedFile};
+use large_track_lib::utils::wgs84_point_to_mercator;
+use large_track_lib::{
+    Climb, Config, DEFAULT_CHUNKING_AGGRESSIVENESS, DEFAULT_CLIMB_THRESHOLD_M,
+    DEFAULT_MAX_SEGMENT_POINTS, DEFAULT_SHARP_TURN_DEGREES, RouteCollection,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Key of a file in [`AppState::expanded_files`] and
+/// [`AppState::route_colors`]: its canonical path, so different spellings of
+/// one file share their state. Files that can't be resolved (e.g. synthetic
+/// web:// entries) are keyed as given.
+fn file_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// How long the wheel warning stays up after the last wheel turn
+const WHEEL_WARNING_DURATION: Duration = Duration::from_millis(1500);
+
+/// How long the locked map's hint stays up after the last ignored gesture
+const MAP_LOCK_HINT_DURATION: Duration = Duration::from_millis(2500);
+
+/// Whether a failed file can be read again from its path
+///
+/// Files that came as bytes (drops and browser picks) get a synthetic path
+/// that doesn't exist on disk.
+fn can_reload(path: &Path) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = path;
+        false
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        path.is_file()
     }
 }
 
@@ -43,28 +90,172 @@ pub struct AppState {
     /// Current UI settings
     pub ui_settings: UiSettings,
 
+    /// Settings saved under a name to switch between, e.g. one for rides and
+    /// one for presentations; they hold no files
+    pub settings_profiles: BTreeMap<String, UiSettings>,
+
+    /// Name being typed for a new settings profile
+    pub new_profile_name: String,
+
     /// File loading state
     pub file_loader: FileLoader,
 
     /// Statistics about loaded data
     pub stats: Stats,
 
+    /// Lowers track detail while frames run over `ui_settings.frame_budget_ms`
+    pub frame_budget: FrameBudget,
+
     /// Currently selected route (index into the collection) for highlighting/overlay.
     /// Shared across UI and plugin so both can read/write selection using an async RwLock.
     /// `None` means no route is selected.
     pub selected_route: Arc<RwLock<Option<usize>>>,
 
-    /// Whether to show the mouse wheel zoom warning
-    pub show_wheel_warning: bool,
+    /// Polygon region of interest, shared with the plugin which adds vertices on map clicks
+    pub roi: Arc<RwLock<RoiPolygon>>,
+
+    /// Corridor comparison between two routes (Tracks tab)
+    pub comparison: CorridorComparison,
+
+    /// Replay of one route with the map following it (Tracks tab)
+    pub playback: Playback,
+
+    /// Cutting the selected route in two at a clicked point (Tracks tab)
+    pub split: SplitTool,
+
+    /// Notes and tags of tracks, persisted apart from the settings
+    pub annotations: TrackAnnotations,
+
+    /// Annotation key ([`annotations::content_hash`]) of each loaded file, computed once
+    pub annotation_keys: HashMap<PathBuf, String>,
+
+    /// Tracks list filter: matches file and track names, `#tags` and notes
+    pub track_filter: String,
+
+    /// Tag being typed for the selected track
+    pub new_tag: String,
+
+    /// Opt-in live position ("follow me") display
+    pub live_location: LiveLocation,
 
-    /// Timestamp when the warning was last shown
-    pub wheel_warning_shown_at: Option<instant::Instant>,
+    /// Position fed by an embedding app, drawn apart from `live_location`
+    pub external_position: ExternalPosition,
+
+    /// Track being recorded from live fixes, if any
+    pub recorder: Option<TrackRecorder>,
+
+    /// Recording that was stopped (or recovered after a crash) and not yet saved or discarded.
+    /// Identifies its entry in `file_loader.loaded_files`.
+    pub finished_recording: Option<PathBuf>,
+
+    /// How the save dialog for `finished_recording` ends, while it is open
+    pub recording_save: Option<async_runtime::oneshot::Receiver<SaveOutcome>>,
 
     /// Whether we need to fit the map to the loaded tracks' bounds
     pub pending_fit_bounds: bool,
 
+    /// Route whose bounds the map is fit to at the start of the next frame
+    pub pending_fit_route: Option<usize>,
+
+    /// Route under the pointer in the map legend, drawn emphasized
+    pub legend_hovered: Option<usize>,
+
+    /// Where the map panel was last drawn; overlays are placed inside it
+    pub map_rect: Option<egui::Rect>,
+
+    /// Set when a route was added by the current parallel load; the map is fit
+    /// once the load settles instead of after every file
+    pub fit_after_load: bool,
+
+    /// Set while the files restored at startup load in "keep last camera" mode,
+    /// so finishing that load doesn't fit the map
+    pub keep_startup_camera: bool,
+
+    /// Shown tile provider that failed its health check, with the error, while
+    /// the "switch provider" banner is up
+    pub tile_failure: Option<(TilesProvider, String)>,
+
+    /// Files whose details are expanded in the Tracks list, by canonical path
+    pub expanded_files: BTreeSet<PathBuf>,
+
+    /// Line colors picked for files, by canonical path
+    ///
+    /// Route indices change whenever files are removed or reloaded, so colors
+    /// follow the file and are mapped to its current route index when drawn.
+    /// They are kept while a file is unloaded, in case it comes back.
+    pub route_colors: BTreeMap<PathBuf, Color32>,
+
+    /// Persisted file paths not reloaded this session (`--fresh`) or split
+    /// into parts; they are saved again so the next normal start still restores them
+    pub held_file_paths: Vec<String>,
+
+    /// Maximum number of parallel load results added to the collection per frame
+    pub results_per_frame: usize,
+
+    /// When to log a summary of the running load (`--load-log-every`)
+    pub load_log: LoadLog,
+
+    /// What needs frames without input this frame; see [`Activity`]
+    pub activity: Activity,
+
     /// Whether we need to reload routes due to config change
     pub pending_reload: bool,
+
+    /// Files that were still pending or in flight when the previous session
+    /// ended uncleanly. They are only re-queued if the user accepts the offer.
+    pub interrupted_import: Vec<PathBuf>,
+
+    /// Set whenever the persisted file list may have changed, so the app can
+    /// autosave promptly instead of waiting for the next periodic save.
+    pub session_dirty: bool,
+
+    /// Toasts currently shown over the map, oldest first
+    pub notifications: Toasts,
+
+    /// Files that failed in the current load, summarized once the load settles
+    pub load_failures: usize,
+
+    /// Set by "Save now"; the app writes the settings at the end of the frame
+    pub save_requested: bool,
+
+    /// When settings were last written to storage
+    pub last_saved_at: Option<instant::Instant>,
+
+    /// Why the last write to platform storage failed; cleared by the next one that works
+    pub save_error: Option<String>,
+
+    /// Keyboard shortcut registry, shared by the input handler and the help texts
+    pub shortcuts: Shortcuts,
+
+    /// Estimated memory (bytes) loaded tracks may use before loads need confirmation
+    pub memory_budget_bytes: u64,
+
+    /// Batch load waiting for confirmation because it would exceed the memory budget
+    pub memory_warning: Option<MemoryWarning>,
+
+    /// Set once the user confirmed the held-back batch, so the next load skips the check
+    pub memory_check_confirmed: bool,
+
+    /// Most tracks loaded before a batch needs confirmation (0 = no limit)
+    pub max_routes: usize,
+
+    /// Batch load waiting for confirmation because it would exceed `max_routes`
+    pub route_limit_warning: Option<RouteLimitWarning>,
+
+    /// Set once the user confirmed the held-back batch, so the next load skips the route check
+    pub route_limit_confirmed: bool,
+
+    /// Parameters of the Debug section's "Generate test tracks" action
+    pub synthetic_params: SyntheticParams,
+
+    /// How long each stage of this start took, for the Debug section
+    pub startup: StartupTimings,
+
+    /// The OS time zone's offset from UTC at startup, if it could be read
+    pub local_offset: Option<time::UtcOffset>,
+
+    /// Bias slider changes waiting for the slider to settle before a rebuild
+    pub bias_debouncer: BiasDebouncer,
 }
 
 /// UI-specific settings that can be adjusted at runtime
@@ -73,9 +264,24 @@ pub struct UiSettings {
     /// Track line width in pixels
     pub line_width: f32,
 
+    /// Whether tracks are drawn `line_width` pixels or `line_width_m` meters wide
+    pub width_mode: WidthMode,
+
+    /// Track line width in meters on the ground, in [`WidthMode::Meters`]
+    pub line_width_m: f32,
+
     /// Show outline/border around tracks
     pub show_outline: bool,
 
+    /// How overlapping track lines combine
+    pub blend_mode: BlendMode,
+
+    /// Whether tracks on the same path are drawn side by side (zoomed in)
+    pub offset_stacked_tracks: bool,
+
+    /// Pixels between stacked tracks drawn side by side
+    pub stack_offset: f32,
+
     /// LOD bias (higher = more detail)
     pub bias: f64,
 
@@ -85,13 +291,142 @@ pub struct UiSettings {
     /// Whether sidebar is open
     pub sidebar_open: bool,
 
+    /// Whether the map ignores one-finger drags and plain wheel turns, so
+    /// scrolling past an embedded map doesn't pan it
+    pub map_locked: bool,
+
     /// Current active tab in sidebar
     pub active_tab: SidebarTab,
 
     /// Whether to show profiling in settings
     pub show_profiling: bool,
+
+    /// How many zoom levels past the provider's deepest tiles the map may go
+    /// (deepest tiles are upscaled while tracks keep gaining detail)
+    pub over_zoom_levels: u8,
+
+    /// How eagerly tracks are split into quadtree chunks (0 = keep whole, 1 = split eagerly)
+    pub chunking_aggressiveness: f64,
+
+    /// Whether to draw a crosshair and coordinate readout at the map center
+    pub show_center_crosshair: bool,
+
+    /// Whether to draw rings at round distances around the map center
+    pub show_distance_grid: bool,
+
+    /// Whether to draw the waypoints of loaded files with their symbol's icon
+    pub show_waypoints: bool,
+
+    /// Whether to outline each route's bounding box on the map (see [`crate::app::extents`])
+    pub show_file_extents: bool,
+
+    /// Whether to show the legend of the routes in view over the map
+    pub show_legend: bool,
+
+    /// How the Tracks list is split into groups
+    pub track_grouping: TrackGrouping,
+
+    /// Share each file's color, notes and tags in a sidecar next to it (see [`sidecar`])
+    pub track_sidecars: bool,
+
+    /// Whether lines continue to the first point past the view edge (see `Config::boundary_context`)
+    pub boundary_context: bool,
+
+    /// Whether the Debug section shows process and index memory
+    pub show_memory_readout: bool,
+
+    /// Whether tracks are queried and drawn at all (off leaves only the base map)
+    pub show_tracks: bool,
+
+    /// How often settings are autosaved, in seconds
+    pub autosave_interval_secs: u64,
+
+    /// Time constant of the smoothing applied to the performance readouts, in seconds (0 = raw values)
+    pub stats_smoothing_secs: f32,
+
+    /// Elevation changes below this many meters don't count toward ascent and descent
+    pub climb_threshold_m: f32,
+
+    /// What the next start restores
+    pub startup_mode: StartupMode,
+
+    /// Whether restored or command-line files are queued first at startup
+    pub file_precedence: FilePrecedence,
+
+    /// Switch to the other tile provider when the shown one fails its health check
+    pub auto_tile_fallback: bool,
+
+    /// Provider replaced by an automatic fallback, tried again at the next start
+    pub preferred_tiles_provider: Option<TilesProvider>,
+
+    /// Keep the map gliding after a drag is let go
+    pub pan_inertia: bool,
+
+    /// How quickly the glide after a drag slows down (see [`crate::app::inertia::decay`])
+    pub pan_friction: f32,
+
+    /// Skip animations (see [`crate::app::motion::animations_enabled`])
+    pub reduce_motion: bool,
+
+    /// Light or dark look, or whichever the system uses
+    pub theme: egui::ThemePreference,
+
+    /// How coordinates are written in readouts and copied
+    pub coord_format: CoordFormat,
+
+    /// Time zone track times are shown in
+    pub time_zone: TimeZone,
+
+    /// Offset from UTC, in minutes, of [`TimeZone::Fixed`], and of local time
+    /// where the OS doesn't report one
+    pub utc_offset_minutes: i32,
+
+    /// Whether simplification keeps the apex of sharp turns (see `Config::sharp_turn_threshold`)
+    pub keep_sharp_turns: bool,
+
+    /// Whether the selected route is drawn from all its points in view, whatever the zoom
+    pub full_detail_selection: bool,
+
+    /// Line styles by file name or folder, the first matching rule wins
+    pub style_rules: Vec<StyleRule>,
+
+    /// Frame time above which tracks are drawn with less detail, in milliseconds (0 = off)
+    pub frame_budget_ms: f32,
+
+    /// Fit the map to a track when it is picked in the Tracks list
+    pub zoom_on_select: bool,
+}
+
+/// Default over-zoom allowance in zoom levels
+pub const DEFAULT_OVER_ZOOM_LEVELS: u8 = 2;
+
+/// Clamp a map zoom to what `provider` can show with `over_zoom_levels` of upscaling
+///
+/// Every zoom the app sets goes through here, so no view ends up past the
+/// deepest tiles plus the allowance, where walkers has nothing left to upscale.
+pub fn clamp_zoom(zoom: f64, provider: TilesProvider, over_zoom_levels: u8) -> f64 {
+    zoom.clamp(0.0, f64::from(provider.max_zoom() + over_zoom_levels))
+}
+
+/// Fraction of the remaining distance a zoom glide covers per second
+const ZOOM_GLIDE_RATE: f64 = 8.0;
+
+/// Next zoom of a glide towards `target` after `dt` seconds
+///
+/// The glide slows as it closes in and lands exactly on `target` once it is
+/// within a hundredth of a level.
+pub fn zoom_glide_step(zoom: f64, target: f64, dt: f64) -> f64 {
+    let next = target + (zoom - target) * (-ZOOM_GLIDE_RATE * dt).exp();
+    if (next - target).abs() < 0.01 {
+        target
+    } else {
+        next
+    }
 }
 
+/// Default time between settings autosaves, in seconds
+pub const DEFAULT_AUTOSAVE_INTERVAL_SECS: u64 = 30;
+
 /// Sidebar tabs
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SidebarTab {
@@ -104,6 +439,8 @@ pub enum SidebarTab {
 pub enum TilesProvider {
     OpenStreetMap,
     OpenTopoMap,
+    /// No basemap: tracks on a plain grid, without contacting any tile server
+    None,
 }
 
 impl TilesProvider {
@@ -111,17 +448,113 @@ impl TilesProvider {
         match self {
             Self::OpenStreetMap => "© OpenStreetMap contributors",
             Self::OpenTopoMap => "© OpenTopoMap (CC-BY-SA)",
+            Self::None => "",
         }
     }
 
     pub fn all() -> &'static [Self] {
-        &[Self::OpenStreetMap, Self::OpenTopoMap]
+        &[Self::OpenStreetMap, Self::OpenTopoMap, Self::None]
     }
 
     pub fn name(&self) -> &'static str {
         match self {
             Self::OpenStreetMap => "OpenStreetMap",
             Self::OpenTopoMap => "OpenTopoMap",
+            Self::None => "None (tracks only)",
+        }
+    }
+
+    /// Provider offered in place of this one when it fails to serve tiles
+    pub fn fallback(&self) -> Option<Self> {
+        match self {
+            Self::OpenStreetMap => Some(Self::OpenTopoMap),
+            Self::OpenTopoMap => Some(Self::OpenStreetMap),
+            Self::None => None,
+        }
+    }
+
+    /// Deepest zoom level the provider serves tiles for (matches its `TileSource`)
+    pub fn max_zoom(&self) -> u8 {
+        match self {
+            Self::OpenStreetMap => 19,
+            Self::OpenTopoMap => 17,
+            // Nothing to upscale; deep enough to inspect single GPS points
+            Self::None => 22,
+        }
+    }
+}
+
+/// What the viewer restores when it starts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartupMode {
+    /// Reload the previous files and fit the map to them
+    #[default]
+    RestoreSession,
+    /// Reload the previous files, leaving the map where it was
+    RestoreFilesKeepCamera,
+    /// Start without files; settings are still restored
+    StartEmpty,
+}
+
+impl StartupMode {
+    pub fn all() -> &'static [Self] {
+        &[
+            Self::RestoreSession,
+            Self::RestoreFilesKeepCamera,
+            Self::StartEmpty,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RestoreSession => "Restore previous session",
+            Self::RestoreFilesKeepCamera => "Restore files but keep last camera",
+            Self::StartEmpty => "Start empty",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "RestoreFilesKeepCamera" => Self::RestoreFilesKeepCamera,
+            "StartEmpty" => Self::StartEmpty,
+            _ => Self::RestoreSession,
+        }
+    }
+}
+
+/// Which files load first when a start both restores files and gets some
+/// on the command line
+///
+/// Files load in queue order, which also decides their route index and so
+/// their default color. A file given both ways is queued once, where its
+/// group goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilePrecedence {
+    /// The restored session first, then new files from the command line
+    #[default]
+    PersistedFirst,
+    /// Command-line files first, then the rest of the restored session
+    CliFirst,
+}
+
+impl FilePrecedence {
+    pub fn all() -> &'static [Self] {
+        &[Self::PersistedFirst, Self::CliFirst]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PersistedFirst => "Restored files first",
+            Self::CliFirst => "Command-line files first",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "CliFirst" => Self::CliFirst,
+            _ => Self::PersistedFirst,
         }
     }
 }
@@ -132,19 +565,34 @@ pub struct FileLoader {
     pub pending_files: Vec<DroppedFile>,
 
     /// Load errors
-    pub errors: Vec<(PathBuf, String)>,
+    pub errors: Vec<(PathBuf, LoadError)>,
+
+    /// Failed files that can't be loaded again: their bytes came from a drop
+    /// or the browser and weren't kept, or loading them crashed
+    pub unretryable: HashSet<PathBuf>,
+
+    /// Errors dismissed this session, kept for the Debug section
+    pub dismissed_errors: Vec<(PathBuf, LoadError)>,
+
+    /// Files opened for this session only: shown like any other, but never
+    /// saved in the file list that is reloaded on restart
+    pub temporary: HashSet<PathBuf>,
 
     /// Successfully loaded files with their GPX data and the starting route index
     /// within the collection where routes from this file begin. This allows mapping
     /// loaded files to route indices later (for selection & highlighting).
-    pub loaded_files: Vec<(PathBuf, gpx::Gpx, usize)>,
+    /// Last comes the file's key (see [`file_key`]), resolved once when loaded.
+    pub loaded_files: Vec<(PathBuf, gpx::Gpx, usize, PathBuf)>,
 
-    /// Results from parallel loading (path, result) - accumulated incrementally
-    #[allow(clippy::type_complexity)]
-    pub parallel_load_results: Arc<Mutex<Vec<(PathBuf, Result<gpx::Gpx, String>)>>>,
+    /// Files of the current batch on their way from disk or the browser, see
+    /// [`AppState::start_parallel_load`]
+    pub ingestor: Ingestor,
 
-    /// Total number of files in current parallel load batch
-    pub parallel_total_files: Arc<AtomicUsize>,
+    /// Files loaded before the current batch, so progress starts from zero
+    pub batch_start: usize,
+
+    /// Files loaded before and in the current batch once it is done; 0 when no batch runs
+    pub batch_total: usize,
 }
 
 /// Statistics about loaded data
@@ -159,6 +607,15 @@ pub struct Stats {
     /// Total distance in meters
     pub total_distance: f64,
 
+    /// Lowest elevation in meters (None if no elevation data)
+    pub min_elevation: Option<f64>,
+
+    /// Highest elevation in meters (None if no elevation data)
+    pub max_elevation: Option<f64>,
+
+    /// Total ascent and descent at the climb threshold (None if no elevation data)
+    pub climb: Option<Climb>,
+
     /// Last query time in milliseconds
     pub last_query_time_ms: f64,
 
@@ -167,6 +624,52 @@ pub struct Stats {
 
     /// Number of simplified points in last query (actually rendered)
     pub last_query_simplified_points: usize,
+
+    /// Why segments of the last query were not drawn
+    pub last_query_skipped: SkipCounters,
+
+    /// Routes in view during the last query that had nothing drawn, sorted
+    pub fully_skipped_routes: Vec<usize>,
+
+    /// Routes the last query drew something of, sorted
+    pub visible_routes: Vec<usize>,
+
+    /// Milliseconds the last frame spent painting track outlines and lines
+    pub last_outline_pass_ms: f64,
+    pub last_line_pass_ms: f64,
+
+    /// Smoothed query time, segment and point counts, for display
+    pub smoothed_query_time_ms: Ema,
+    pub smoothed_query_segments: Ema,
+    pub smoothed_query_simplified_points: Ema,
+
+    /// When the smoothed values were last updated
+    pub smoothed_at: Option<instant::Instant>,
+
+    /// Estimated memory of the loaded data, as of the last import check
+    pub estimated_loaded_bytes: u64,
+
+    /// Estimated memory of the last batch of files checked against the budget
+    pub last_import_estimate_bytes: u64,
+
+    /// Process and index memory for the Debug section, while it is shown
+    pub memory_readout: MemoryReadout,
+}
+
+/// Memory budget from the CLI, or the platform default
+pub fn memory_budget_bytes(settings: &Settings) -> u64 {
+    settings
+        .memory_budget_mb
+        .map(|mib| mib.saturating_mul(1024 * 1024))
+        .unwrap_or_else(memory_budget::default_budget_bytes)
+}
+
+/// Elevation service for loads, if the user opted in
+#[cfg(not(target_arch = "wasm32"))]
+fn elevation_service(settings: &Settings) -> Option<Arc<ElevationService>> {
+    settings
+        .download_elevation
+        .then(|| Arc::new(ElevationService::new(settings.elevation_endpoint.clone())))
 }
 
 impl AppState {
@@ -176,6 +679,14 @@ impl AppState {
         let config = Config {
             bias: settings.bias,
             max_points_per_node: settings.max_points_per_node,
+            precompute_lod: settings.precompute_lod,
+            chunking_aggressiveness: DEFAULT_CHUNKING_AGGRESSIVENESS,
+            boundary_context: true,
+            // rayon jobs only run in the background on native
+            progressive_refinement: cfg!(not(target_arch = "wasm32")),
+            sharp_turn_threshold: None,
+            elevation_extrema_prominence: None,
+            max_segment_points: DEFAULT_MAX_SEGMENT_POINTS,
             reference_pixel_viewport: geo::Rect::new(
                 geo::Coord { x: 0.0, y: 0.0 },
                 geo::Coord {
@@ -189,16 +700,54 @@ impl AppState {
 
         let ui_settings = UiSettings {
             line_width: settings.line_width,
+            width_mode: WidthMode::default(),
+            line_width_m: DEFAULT_LINE_WIDTH_M,
             show_outline: settings.show_outline,
+            blend_mode: BlendMode::default(),
+            offset_stacked_tracks: false,
+            stack_offset: DEFAULT_STACK_OFFSET,
             bias: settings.bias,
             tiles_provider: TilesProvider::OpenStreetMap,
             sidebar_open: true,
+            map_locked: settings.lock_map.unwrap_or(false),
             active_tab: SidebarTab::Tracks,
-            show_profiling: false,
+            // A session recording since startup (ENABLE_PROFILING) shows its controls
+            show_profiling: eframe_entrypoints::profiling::is_profiling_enabled(),
+            over_zoom_levels: DEFAULT_OVER_ZOOM_LEVELS,
+            chunking_aggressiveness: DEFAULT_CHUNKING_AGGRESSIVENESS,
+            show_center_crosshair: false,
+            show_distance_grid: false,
+            show_waypoints: false,
+            show_file_extents: false,
+            show_legend: false,
+            track_grouping: TrackGrouping::None,
+            track_sidecars: false,
+            boundary_context: true,
+            show_memory_readout: false,
+            show_tracks: true,
+            autosave_interval_secs: DEFAULT_AUTOSAVE_INTERVAL_SECS,
+            stats_smoothing_secs: DEFAULT_STATS_SMOOTHING_SECS,
+            climb_threshold_m: DEFAULT_CLIMB_THRESHOLD_M as f32,
+            startup_mode: StartupMode::default(),
+            file_precedence: FilePrecedence::default(),
+            auto_tile_fallback: false,
+            preferred_tiles_provider: None,
+            pan_inertia: true,
+            pan_friction: DEFAULT_PAN_FRICTION,
+            reduce_motion: crate::app::motion::system_prefers_reduced_motion(),
+            theme: egui::ThemePreference::System,
+            coord_format: CoordFormat::default(),
+            time_zone: TimeZone::default(),
+            utc_offset_minutes: 0,
+            keep_sharp_turns: false,
+            full_detail_selection: false,
+            style_rules: Vec::new(),
+            frame_budget_ms: DEFAULT_FRAME_BUDGET_MS,
+            zoom_on_select: false,
         };
 
-        let file_loader = FileLoader {
-            pending_files: settings
+        let file_loader = FileLoader::new(
+            settings
                 .gpx_files
                 .iter()
                 .map(|path| DroppedFile {
@@ -211,58 +760,66 @@ impl AppState {
                     ..Default::default()
                 })
                 .collect(),
-            errors: Vec::new(),
-            loaded_files: Vec::new(),
-            parallel_load_results: Arc::new(Mutex::new(Vec::new())),
-            parallel_total_files: Arc::new(AtomicUsize::new(0)),
-        };
+            settings,
+        );
 
         Self {
             route_collection,
             ui_settings,
             file_loader,
             stats: Stats::default(),
+            frame_budget: FrameBudget::default(),
             selected_route: Arc::new(RwLock::new(None)),
-            show_wheel_warning: false,
-            wheel_warning_shown_at: None,
+            roi: Arc::new(RwLock::new(RoiPolygon::default())),
+            comparison: Default::default(),
+            playback: Playback::default(),
+            split: SplitTool::default(),
+            annotations: Default::default(),
+            annotation_keys: HashMap::new(),
+            track_filter: String::new(),
+            new_tag: String::new(),
+            settings_profiles: BTreeMap::new(),
+            new_profile_name: String::new(),
+            live_location: LiveLocation::default(),
+            external_position: ExternalPosition::default(),
+            recorder: None,
+            finished_recording: None,
+            recording_save: None,
             pending_fit_bounds: false,
+            pending_fit_route: None,
+            legend_hovered: None,
+            map_rect: None,
+            fit_after_load: false,
+            keep_startup_camera: false,
+            tile_failure: None,
+            expanded_files: BTreeSet::new(),
+            route_colors: BTreeMap::new(),
+            held_file_paths: Vec::new(),
+            results_per_frame: settings.results_per_frame,
+            load_log: LoadLog::new(settings.load_log_every),
+            activity: Activity::default(),
             pending_reload: false,
+            interrupted_import: Vec::new(),
+            session_dirty: false,
+            notifications: Toasts::default(),
+            load_failures: 0,
+            save_requested: false,
+            last_saved_at: None,
+            save_error: None,
+            shortcuts: Shortcuts::default(),
+            memory_budget_bytes: memory_budget_bytes(settings),
+            memory_warning: None,
+            memory_check_confirmed: false,
+            max_routes: settings.max_routes,
+            route_limit_warning: None,
+            route_limit_confirmed: false,
+            synthetic_params: SyntheticParams::default(),
+            startup: StartupTimings::default(),
+            local_offset: timezone::local_offset_at_startup(),
+            bias_debouncer: BiasDebouncer::new(settings.bias),
         }
     }
 
-    // Load a single file
-    #[cfg_attr(feature = "profiling", profiling::function)]
-    async fn load_file_to_gpx(file: &DroppedFile) -> Result<gpx::Gpx, String> {
-        let buf = if let Some(bs) = file.bytes.as_ref() {
-            bs.to_vec()
-        } else {
-            #[cfg(target_arch = "wasm32")]
-            {
-                return Err("File bytes not available on WASM".to_string());
-            }
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                use tokio::io::AsyncReadExt;
-                let file = tokio::fs::File::open(
-                    file.path
-                        .as_ref()
-                        .expect("file was read or has a path to be read from"),
-                )
-                .await
-                .map_err(|e| format!("Error opening file: {:?}", e))?;
-                let mut reader = tokio::io::BufReader::new(file);
-                let mut buf = Vec::new();
-                reader
-                    .read_to_end(&mut buf)
-                    .await
-                    .map_err(|e| format!("Failed to read file: {}", e))?;
-                buf
-            }
-        };
-        let cursor = std::io::Cursor::new(buf);
-        gpx::read(cursor).map_err(|e| format!("Failed to parse GPX: {}", e))
-    }
-
     /// Start parallel loading of all pending files
     #[cfg_attr(feature = "profiling", profiling::function)]
     pub fn start_parallel_load(&mut self) {
@@ -275,84 +832,52 @@ impl AppState {
         if files_to_load.is_empty() {
             return;
         }
+        let files_to_load = match self.check_route_limit(files_to_load) {
+            Ok(files) => files,
+            Err(warning) => {
+                tracing::warn!(
+                    "Holding back {} file(s): {} tracks would exceed the limit of {}",
+                    warning.files.len(),
+                    warning.loaded + warning.files.len(),
+                    self.max_routes
+                );
+                self.route_limit_warning = Some(warning);
+                return;
+            }
+        };
+        let files_to_load = match self.check_memory_budget(files_to_load) {
+            Ok(files) => files,
+            Err(warning) => {
+                tracing::warn!(
+                    "Holding back {} file(s): estimated {} exceeds the memory budget of {}",
+                    warning.files.len(),
+                    memory_budget::format_bytes(warning.estimated_bytes),
+                    memory_budget::format_bytes(self.memory_budget_bytes)
+                );
+                self.memory_warning = Some(warning);
+                return;
+            }
+        };
 
-        let results = self.file_loader.parallel_load_results.clone();
-        let total_files = self.file_loader.parallel_total_files.clone();
-
-        // Set the totals and reset counters
-        let files_len = files_to_load.len();
-
-        // Store total files atomically (works on all platforms).
-        // `total_files` is an `Arc<AtomicUsize>`, so we can update it directly.
-        total_files.store(files_len, Ordering::SeqCst);
-        // Limit concurrency to number of logical CPU cores (native only)
-        // On web, we use a smaller number since web workers have more overhead
-        #[cfg(not(target_arch = "wasm32"))]
-        let max_concurrent = std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4); // fallback to 4 if detection fails
-        #[cfg(target_arch = "wasm32")]
-        let max_concurrent = 4; // Use a reasonable default for web workers
-
-        let semaphore = std::sync::Arc::new(async_runtime::Semaphore::new(max_concurrent));
-
-        for dropped_file in files_to_load {
-            let results = results.clone();
-            let semaphore = semaphore.clone();
-            // Use async_runtime::spawn which works on both native (tokio) and web (tokio-with-wasm)
-            async_runtime::spawn(async move {
-                // Per-worker profiling scope with tag for file identifier (path or synthetic id).
-                // This attaches a small data field to the span which is useful for filtering
-                // traces without emitting additional events.
-                #[cfg(feature = "profiling")]
-                {
-                    // Build a string identifier for the file being processed
-                    let file_id = synthetic_path_for(&dropped_file)
-                        .to_string_lossy()
-                        .to_string();
-                    let tag = format!("file={}", file_id);
-                    // Top-level worker span tagged with the file id for easy lookup in traces
-                    profiling::scope!("file_loader::worker", tag.as_str());
-                    // Name the worker thread so profilers can show the worker as a meaningful thread
-                    ::profiling::register_thread!("FileLoaderWorker");
-                }
-
-                let permit = semaphore.acquire_owned().await.unwrap();
-
-                // Profile the actual IO + parse operation inside the worker scope
-                // Reuse the same tag string to correlate IO work with the worker span.
-                #[cfg(feature = "profiling")]
-                {
-                    let file_id = synthetic_path_for(&dropped_file)
-                        .to_string_lossy()
-                        .to_string();
-                    let tag = format!("file={}", file_id);
-                    profiling::scope!("file_loader::io_and_parse", tag.as_str());
-                }
-                let result = Self::load_file_to_gpx(&dropped_file).await;
-                {
-                    // Compute a stable identifier for this file (real path when available,
-                    // synthetic web://<name> otherwise).
-                    let path = synthetic_path_for(&dropped_file);
-                    let mut guard = results
-                        .lock()
-                        .expect("failed to acquire lock on parallel_load_results mutex to push worker result");
-                    guard.push((path, result));
-                }
-                drop(permit); // release semaphore
-                // Yield to allow other tasks to run (helps UI responsiveness)
-                async_runtime::yield_now().await;
-            });
+        // Progress counts from the start of the batch; files added while one
+        // is running join it
+        let loader = &mut self.file_loader;
+        if loader.batch_total == 0 {
+            loader.batch_start = loader.loaded_files.len();
+            loader.batch_total = loader.batch_start;
+            self.load_log.reset();
         }
+        loader.batch_total += files_to_load.len();
+        loader.ingestor.submit(files_to_load);
     }
 
     /// Process results from parallel loading incrementally.
-    /// Processes one result per call to keep UI responsive during indexing.
+    /// Processes up to `results_per_frame` results per call to keep UI responsive during indexing.
     /// Returns true if there are more results to process.
     #[cfg_attr(feature = "profiling", profiling::function)]
     pub fn process_parallel_results(&mut self) -> bool {
         // Check if we're done (all added)
-        let total = self.file_loader.parallel_total_files.load(Ordering::SeqCst);
+        let total = self.file_loader.batch_total;
 
         let added = self.file_loader.loaded_files.len();
 
@@ -369,281 +894,1406 @@ impl AppState {
             return false;
         }
 
-        // Process exactly one result per frame to keep UI fluid during indexing
-        // Take one result (non-blocking)
-        let result: Option<(PathBuf, Result<gpx::Gpx, String>)> = {
-            let mut guard =
-                self.file_loader.parallel_load_results.lock().expect(
-                    "failed to acquire lock on parallel_load_results mutex to pop UI result",
-                );
-            if guard.is_empty() {
-                None
-            } else {
-                Some(guard.remove(0))
-            }
-        };
-
-        let Some((path, parse_result)) = result else {
-            // No results ready yet, but we're still loading
-            return self.is_parallel_loading();
-        };
-
-        match parse_result {
-            Ok(gpx) => {
-                // Add this single route to the collection and record the starting index
-                let mut start_idx_opt: Option<usize> = None;
-                let add_result = {
-                    #[cfg(not(target_arch = "wasm32"))]
-                    {
-                        let mut res_opt = Err(large_track_lib::DataError::InvalidGeometry(
-                            "Could not acquire write lock on route_collection".to_string(),
-                        ));
-                        async_runtime::blocking_write(&self.route_collection, |collection| {
-                            // Tag the add_route operation with the source file so traces can link
-                            // route addition time to the originating file.
-                            #[cfg(feature = "profiling")]
-                            {
-                                // `path` is available from the outer scope; include only file name for brevity.
-                                let file_name =
-                                    path.file_name().unwrap_or_default().to_string_lossy();
-                                let tag = format!(
-                                    "file={},start_idx={}",
-                                    file_name,
-                                    collection.route_count()
-                                );
-                                profiling::scope!("collection::add_route", tag.as_str());
-                            }
-
-                            // The route will be appended; record the index where it will be inserted.
-                            let start_idx = collection.route_count();
-                            let res = collection.add_route(gpx.clone());
-                            if res.is_ok() {
-                                start_idx_opt = Some(start_idx);
-                            }
-                            res_opt = res;
-                        });
-                        res_opt
-                    }
-                    #[cfg(target_arch = "wasm32")]
-                    {
-                        if let Ok(mut collection) = self.route_collection.try_write() {
-                            // The route will be appended; record the index where it will be inserted.
-                            let start_idx = collection.route_count();
-                            // On wasm, we still tag the call (if profiling enabled) at this higher-level.
-                            #[cfg(feature = "profiling")]
-                            {
-                                let file_name =
-                                    path.file_name().unwrap_or_default().to_string_lossy();
-                                let tag = format!("file={},start_idx={}", file_name, start_idx);
-                                profiling::scope!("collection::add_route", tag.as_str());
-                            }
-                            let res = collection.add_route(gpx.clone());
-                            if res.is_ok() {
-                                start_idx_opt = Some(start_idx);
-                            }
-                            res
-                        } else {
-                            Err(large_track_lib::DataError::InvalidGeometry(
-                                "Could not acquire write lock on route_collection".to_string(),
-                            ))
-                        }
-                    }
-                };
-
-                match add_result {
-                    Ok(_) => {
-                        // Record the starting route index for this file so the UI can map files -> routes.
-                        let start_idx = start_idx_opt.unwrap_or(0);
-                        self.file_loader.loaded_files.push((path, gpx, start_idx));
-                        self.update_stats();
-                        self.pending_fit_bounds = true;
-                    }
-                    Err(e) => {
-                        // Format a user-facing error message, push to the error list and set a transient last_error
-                        let err_msg = format!("Failed to add route: {}", e);
-                        // Push the error record (clone path so we preserve semantics)
-                        self.file_loader
-                            .errors
-                            .push((path.clone(), err_msg.clone()));
-                    }
-                }
-                // No need to increment a processed counter; progress is now based on loaded_files + errors.
+        for _ in 0..self.results_per_frame.max(1) {
+            let Some((path, result)) = self.file_loader.ingestor.next_result() else {
+                // No results ready yet, but we may still be loading
+                break;
+            };
+            self.apply_load_result(path, result);
+        }
+        self.file_loader.ingestor.spawn_queued();
+        if self.file_loader.loaded_files.len() > added {
+            self.update_stats();
+        }
+        let (done, batch_total) = self.batch_progress();
+        if self.load_log.due(done, batch_total) {
+            tracing::info!("Loaded {}/{} files", done, batch_total);
+        }
+
+        // Return true if there are more results to process or still loading
+        let more = self.file_loader.ingestor.has_results() || self.is_parallel_loading();
+        if !more {
+            if std::mem::take(&mut self.fit_after_load) && !self.keep_startup_camera {
+                self.pending_fit_bounds = true;
             }
+            match std::mem::take(&mut self.load_failures) {
+                0 => {}
+                1 => self.notify_warn("1 file failed to load, see the Tracks tab"),
+                n => self.notify_warn(format!("{} files failed to load, see the Tracks tab", n)),
+            }
+        }
+        more
+    }
+
+    /// Add a loaded file's route to the collection, or record why it failed
+    ///
+    /// The one place a load result is applied, whichever way the file came in.
+    fn apply_load_result(&mut self, path: PathBuf, result: Result<gpx::Gpx, LoadError>) {
+        // Whatever the outcome, the persisted list changes
+        self.session_dirty = true;
+        let gpx = match result {
+            Ok(gpx) => gpx,
             Err(e) => {
-                // Preserve the error String for both storage and transient UI feedback.
-                self.file_loader.errors.push((path, e));
-                // Safely decrement the total count, preventing underflow if it is already zero.
-                // Use a compare-exchange loop so we only subtract when the current value > 0.
-                let mut prev = self.file_loader.parallel_total_files.load(Ordering::SeqCst);
-                while prev > 0 {
-                    match self.file_loader.parallel_total_files.compare_exchange(
-                        prev,
-                        prev - 1,
-                        Ordering::SeqCst,
-                        Ordering::SeqCst,
-                    ) {
-                        Ok(_) => break,
-                        Err(actual) => prev = actual,
-                    }
+                self.record_load_error(path, e);
+                return;
+            }
+        };
+
+        // The outer error is a panic while indexing, the inner one a rejected route
+        // or a busy index. The route is appended, so it starts at the current count.
+        let added = self
+            .write_collection(|collection| {
+                // Tag the add_route operation with the source file so traces can link
+                // route addition time to the originating file.
+                #[cfg(feature = "profiling")]
+                {
+                    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let tag = format!("file={},start_idx={}", file_name, collection.route_count());
+                    profiling::scope!("collection::add_route", tag.as_str());
+                }
+                let start_idx = collection.route_count();
+                catch_panic(|| {
+                    collection
+                        .add_route(gpx.clone())
+                        .map(|()| start_idx)
+                        .map_err(LoadError::Rejected)
+                })
+            })
+            .unwrap_or(Ok(Err(LoadError::LockUnavailable)));
+
+        match added {
+            Ok(Ok(start_idx)) => {
+                // Record the starting route index for this file so the UI can map files -> routes.
+                let split = self
+                    .route_collection
+                    .try_read()
+                    .map_or(0, |collection| collection.config().segments_to_split(&gpx));
+                if split > 0 {
+                    self.notify_warn(format!(
+                        "{}: {} very long segment(s) split into pieces for indexing",
+                        path.file_name().unwrap_or_default().to_string_lossy(),
+                        split
+                    ));
                 }
-                // No need to increment a processed counter; progress is now based on loaded_files + errors.
+                self.apply_sidecar(&path, &gpx);
+                let key = file_key(&path);
+                self.file_loader
+                    .loaded_files
+                    .push((path, gpx, start_idx, key));
+                self.fit_after_load = true;
+            }
+            Ok(Err(e)) => self.record_load_error(path, e),
+            Err(panic) => {
+                tracing::error!("Indexing {} panicked: {}", path.display(), panic);
+                self.record_load_error(path, LoadError::IndexingCrashed(panic));
+                // The panic may have left the route half-merged into the index
+                self.rebuild_collection();
             }
         }
+    }
 
-        // Return true if there are more results to process or still loading
-        let more_results = !self
-            .file_loader
-            .parallel_load_results
-            .lock()
-            .unwrap()
-            .is_empty();
-        more_results || self.is_parallel_loading()
+    /// Run `f` on the collection for writing, or return `None` if it is busy
+    ///
+    /// Native waits for the lock; the browser can't block its only thread,
+    /// so there a busy collection is reported instead.
+    fn write_collection<R>(&self, f: impl FnOnce(&mut RouteCollection) -> R) -> Option<R> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            Some(async_runtime::blocking_write(&self.route_collection, f))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut collection = self.route_collection.try_write().ok()?;
+            Some(f(&mut collection))
+        }
     }
 
-    /// Check if parallel loading is in progress
-    pub fn is_parallel_loading(&self) -> bool {
-        // Use atomic load for the total file count. This is simple, correct,
-        // and works uniformly across native and wasm targets.
-        let total = self.file_loader.parallel_total_files.load(Ordering::SeqCst);
-        total > 0 && self.file_loader.loaded_files.len() < total
+    /// Add a failed file to the error list and drop it from the batch total
+    fn record_load_error(&mut self, path: PathBuf, error: LoadError) {
+        if !can_reload(&path) || !error.is_retryable() {
+            self.file_loader.unretryable.insert(path.clone());
+        }
+        self.file_loader.errors.push((path, error));
+        self.load_failures += 1;
+        self.forget_one_parallel_file();
     }
 
-    /// Reset parallel loading state (called when all routes are added)
-    fn reset_parallel_loading(&mut self) {
+    /// Number of listed errors that [`AppState::retry_failed_files`] would retry
+    pub fn retryable_error_count(&self) -> usize {
         self.file_loader
-            .parallel_total_files
-            .store(0, Ordering::SeqCst);
+            .errors
+            .iter()
+            .filter(|(path, _)| !self.file_loader.unretryable.contains(path))
+            .count()
     }
 
-    /// Get loading progress (0.0 to 1.0)
-    pub fn loading_progress(&self) -> f32 {
-        let total = self.file_loader.parallel_total_files.load(Ordering::SeqCst);
-        if total == 0 {
-            0.0
-        } else {
-            self.file_loader.loaded_files.len() as f32 / total as f32
+    /// Load every failed file that can be read again, in one parallel batch
+    ///
+    /// Files that can't be read again stay in the list. Returns how many were queued.
+    pub fn retry_failed_files(&mut self) -> usize {
+        let (kept, retried): (Vec<_>, Vec<_>) = std::mem::take(&mut self.file_loader.errors)
+            .into_iter()
+            .partition(|(path, _)| self.file_loader.unretryable.contains(path));
+        self.file_loader.errors = kept;
+        let count = retried.len();
+        for (path, _) in retried {
+            self.queue_file(DroppedFile {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                path: Some(path),
+                ..Default::default()
+            });
         }
+        self.start_parallel_load();
+        count
     }
 
-    /// Get loading status text
-    pub fn loading_status(&self) -> String {
-        let total = self.file_loader.parallel_total_files.load(Ordering::SeqCst);
-        let processed = self.file_loader.loaded_files.len();
-        if processed < total {
-            format!("{}/{}", processed, total)
-        } else {
-            format!("{}/{} done", processed, total)
-        }
+    /// Clear the error list, moving it to the session log of dismissed errors
+    pub fn dismiss_errors(&mut self) {
+        self.file_loader.unretryable.clear();
+        let errors = std::mem::take(&mut self.file_loader.errors);
+        self.file_loader.dismissed_errors.extend(errors);
     }
 
-    /// Process pending file loads (one at a time, for incremental loading)
-    ///
-    /// On wasm we avoid doing heavy synchronous work on the main thread. If
-    /// there are pending files and no parallel load in progress, spawn a single
-    /// async task to handle exactly one file and push its result into the
-    /// `parallel_load_results` queue. This keeps parsing cooperative with the
-    /// JS event loop and prevents freezing during initial load.
-    pub fn process_pending_files(&mut self) {
-        // First process any ready parallel results
-        self.process_parallel_results();
+    /// Drop a failed file from the batch total, so progress can still reach the end
+    fn forget_one_parallel_file(&mut self) {
+        self.file_loader.batch_total = self.file_loader.batch_total.saturating_sub(1);
+    }
 
-        // If a parallel load is already in progress, let that continue.
-        if self.is_parallel_loading() {
-            // parallel loading in progress; nothing to do here
+    /// Pass a batch through if it stays within the route limit, otherwise hold it
+    /// back for confirmation, along with any batch already held back
+    fn check_route_limit(
+        &mut self,
+        files: Vec<DroppedFile>,
+    ) -> Result<Vec<DroppedFile>, RouteLimitWarning> {
+        let loaded =
+            self.file_loader.loaded_files.len() + self.file_loader.ingestor.unfinished_count();
+        if let Some(mut held) = self.route_limit_warning.take() {
+            held.files.extend(files);
+            return Err(held);
         }
+        if std::mem::take(&mut self.route_limit_confirmed)
+            || !route_limit::exceeds_limit(loaded, files.len(), self.max_routes)
+        {
+            return Ok(files);
+        }
+        Err(RouteLimitWarning { files, loaded })
     }
 
-    /// Add a file to the pending load queue
-    pub fn queue_file(&mut self, dropped_file: DroppedFile) {
-        // Use a stable file identifier (prefers real path, falls back to a synthetic web://<name>)
-        let file_id = synthetic_path_for(&dropped_file);
-        let already_loaded = self
-            .file_loader
+    /// Load the first `count` files of the batch held back by the route limit
+    /// (all of them if `None`)
+    pub fn confirm_route_limit_warning(&mut self, count: Option<usize>) {
+        let Some(warning) = self.route_limit_warning.take() else {
+            return;
+        };
+        let mut files = warning.files;
+        if let Some(count) = count
+            && count < files.len()
+        {
+            self.notify_warn(format!(
+                "Loading the first {} of {} files; the other {} are over the limit of {} tracks",
+                count,
+                files.len(),
+                files.len() - count,
+                self.max_routes
+            ));
+            files.truncate(count);
+        }
+        self.file_loader.pending_files = files;
+        self.route_limit_confirmed = true;
+        self.start_parallel_load();
+    }
+
+    /// Drop the batch held back by the route limit without loading it
+    pub fn cancel_route_limit_warning(&mut self) {
+        self.route_limit_warning = None;
+        self.session_dirty = true;
+    }
+
+    /// Pass a batch through if it fits the memory budget, otherwise hold it back for confirmation
+    fn check_memory_budget(
+        &mut self,
+        files: Vec<DroppedFile>,
+    ) -> Result<Vec<DroppedFile>, MemoryWarning> {
+        let file_sizes: Vec<u64> = files.iter().map(memory_budget::file_size).collect();
+        let estimated_bytes =
+            large_track_lib::estimate_memory_for_gpx_bytes(file_sizes.iter().sum());
+        // Walks the index, but only once per batch
+        if let Ok(collection) = self.route_collection.try_read() {
+            self.stats.estimated_loaded_bytes = collection.memory_estimate() as u64;
+        }
+        self.stats.last_import_estimate_bytes = estimated_bytes;
+
+        let loaded_bytes = self.stats.estimated_loaded_bytes;
+        if std::mem::take(&mut self.memory_check_confirmed)
+            || loaded_bytes.saturating_add(estimated_bytes) <= self.memory_budget_bytes
+        {
+            return Ok(files);
+        }
+        Err(MemoryWarning {
+            files,
+            file_sizes,
+            estimated_bytes,
+            loaded_bytes,
+        })
+    }
+
+    /// Load the first `count` files of the held-back batch (all of them if `None`)
+    pub fn confirm_memory_warning(&mut self, count: Option<usize>) {
+        let Some(warning) = self.memory_warning.take() else {
+            return;
+        };
+        let mut files = warning.files;
+        if let Some(count) = count {
+            files.truncate(count);
+        }
+        self.file_loader.pending_files = files;
+        self.memory_check_confirmed = true;
+        self.start_parallel_load();
+    }
+
+    /// Drop the held-back batch without loading it
+    pub fn cancel_memory_warning(&mut self) {
+        self.memory_warning = None;
+        self.session_dirty = true;
+    }
+
+    /// Check if parallel loading is in progress
+    pub fn is_parallel_loading(&self) -> bool {
+        let total = self.file_loader.batch_total;
+        total > 0 && self.file_loader.loaded_files.len() < total
+    }
+
+    /// Reset parallel loading state (called when all routes are added)
+    fn reset_parallel_loading(&mut self) {
+        self.file_loader.batch_total = 0;
+    }
+
+    /// Files of the current batch loaded so far, and the batch size
+    pub fn batch_progress(&self) -> (usize, usize) {
+        let start = self.file_loader.batch_start;
+        let total = self.file_loader.batch_total;
+        (
+            self.file_loader.loaded_files.len().saturating_sub(start),
+            total.saturating_sub(start),
+        )
+    }
+
+    /// Get loading progress (0.0 to 1.0)
+    pub fn loading_progress(&self) -> f32 {
+        let (processed, total) = self.batch_progress();
+        if total == 0 {
+            0.0
+        } else {
+            processed as f32 / total as f32
+        }
+    }
+
+    /// Get loading status text
+    pub fn loading_status(&self) -> String {
+        let (processed, total) = self.batch_progress();
+        if processed < total {
+            format!("{}/{}", processed, total)
+        } else {
+            format!("{}/{} done", processed, total)
+        }
+    }
+
+    /// Add a file to the pending load queue
+    pub fn queue_file(&mut self, dropped_file: DroppedFile) {
+        // Use a stable file identifier (prefers real path, falls back to a synthetic web://<name>)
+        let file_id = synthetic_path_for(&dropped_file);
+        let already_loaded = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .any(|(p, _, _, _)| p == &file_id);
+
+        if !self.file_loader.pending_files.contains(&dropped_file) && !already_loaded {
+            self.file_loader.pending_files.push(dropped_file);
+            self.session_dirty = true;
+        }
+    }
+
+    /// Add a file to the pending load queue for this session only (see [`FileLoader::temporary`])
+    pub fn queue_temporary_file(&mut self, dropped_file: DroppedFile) {
+        self.file_loader
+            .temporary
+            .insert(synthetic_path_for(&dropped_file));
+        self.queue_file(dropped_file);
+    }
+
+    /// Whether a loaded or queued file is left out of the saved file list
+    pub fn is_temporary(&self, path: &Path) -> bool {
+        self.file_loader.temporary.contains(path)
+    }
+
+    /// Keep a file for this session only, or have it reloaded on restart again
+    pub fn set_temporary(&mut self, path: &Path, temporary: bool) {
+        let changed = if temporary {
+            self.file_loader.temporary.insert(path.to_path_buf())
+        } else {
+            self.file_loader.temporary.remove(path)
+        };
+        self.session_dirty |= changed;
+    }
+
+    /// Paths to reload on restart: loaded files, then files still pending or in flight
+    ///
+    /// Everything comes from the loader's own lists, never from the workers'
+    /// results channel: a file stays in flight until its result is indexed,
+    /// so a save in the middle of a load sees every file of the batch without
+    /// waiting on the workers. Files held back by the memory warning count
+    /// as pending until it is answered.
+    ///
+    /// Only real filesystem paths are kept: files dropped in the browser
+    /// (synthetic `web://` paths) and unsaved recordings can't be read again,
+    /// and temporary files are left out on purpose. Files held back by
+    /// `--fresh` stay in the loaded list unless they were loaded again.
+    pub fn persisted_file_paths(&self) -> (Vec<String>, Vec<String>) {
+        let reloadable = |path: &Path| {
+            let path_str = path.to_string_lossy().to_string();
+            (!path_str.starts_with("web://")
+                && !path_str.starts_with(RECORDING_PATH_PREFIX)
+                && !path_str.starts_with(SYNTHETIC_PATH_PREFIX)
+                && !path_str.starts_with(SPLIT_PATH_PREFIX)
+                && !self.is_temporary(path))
+            .then_some(path_str)
+        };
+        let mut loaded_file_paths: Vec<String> = self
+            .file_loader
             .loaded_files
             .iter()
-            .any(|(p, _, _)| p == &file_id);
+            .filter_map(|(path, _, _, _)| reloadable(path))
+            .collect();
+        for path in &self.held_file_paths {
+            if !loaded_file_paths.contains(path) {
+                loaded_file_paths.push(path.clone());
+            }
+        }
+
+        let mut pending_file_paths: Vec<String> = Vec::new();
+        let pending = self
+            .file_loader
+            .pending_files
+            .iter()
+            .filter_map(|file| file.path.as_deref());
+        let queued = self
+            .file_loader
+            .ingestor
+            .queued()
+            .filter_map(|file| file.path.as_deref());
+        let in_flight = self
+            .file_loader
+            .ingestor
+            .in_flight()
+            .iter()
+            .map(PathBuf::as_path);
+        let held_back = self
+            .memory_warning
+            .iter()
+            .flat_map(|warning| &warning.files)
+            .chain(
+                self.route_limit_warning
+                    .iter()
+                    .flat_map(|warning| &warning.files),
+            )
+            .filter_map(|file| file.path.as_deref());
+        for path_str in pending
+            .chain(queued)
+            .chain(in_flight)
+            .chain(held_back)
+            .filter_map(reloadable)
+        {
+            if !loaded_file_paths.contains(&path_str) && !pending_file_paths.contains(&path_str) {
+                pending_file_paths.push(path_str);
+            }
+        }
+        (loaded_file_paths, pending_file_paths)
+    }
+
+    /// Re-queue the files interrupted by an unclean shutdown of the previous session
+    pub fn resume_interrupted_import(&mut self) {
+        for path in std::mem::take(&mut self.interrupted_import) {
+            self.queue_file(DroppedFile {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                path: Some(path),
+                ..Default::default()
+            });
+        }
+        self.start_parallel_load();
+    }
+
+    /// Forget the interrupted import instead of resuming it
+    pub fn discard_interrupted_import(&mut self) {
+        self.interrupted_import.clear();
+        self.session_dirty = true;
+    }
+
+    /// Remove a loaded file by index
+    pub fn remove_file(&mut self, index: usize) {
+        if index < self.file_loader.loaded_files.len() {
+            let (path, _, route_idx, key) = self.file_loader.loaded_files.remove(index);
+            // A running batch waits for one file less, so its progress can still finish
+            if self.is_parallel_loading() {
+                self.forget_one_parallel_file();
+                if index < self.file_loader.batch_start {
+                    self.file_loader.batch_start -= 1;
+                }
+            }
+            self.file_loader.temporary.remove(&path);
+            self.expanded_files.remove(&key);
+            self.annotation_keys.remove(&path);
+            // Route indices after the removed file shift down
+            self.comparison.clear();
+            self.playback.stop();
+            self.session_dirty = true;
+            self.remove_route(route_idx);
+            self.update_stats();
+        }
+    }
+
+    /// Drop one file's route from the collection, renumbering the files after it
+    ///
+    /// Falls back to a full rebuild if the collection doesn't hold that route.
+    fn remove_route(&mut self, route_idx: usize) {
+        let remove = |collection: &mut RouteCollection| collection.remove_route(route_idx);
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = async_runtime::blocking_write(&self.route_collection, remove);
+        #[cfg(target_arch = "wasm32")]
+        let result = match self.route_collection.try_write() {
+            Ok(mut collection) => remove(&mut collection),
+            Err(_) => {
+                self.rebuild_collection();
+                return;
+            }
+        };
+        match result {
+            Ok(_) => {
+                for (_, _, start_idx, _) in &mut self.file_loader.loaded_files {
+                    if *start_idx > route_idx {
+                        *start_idx -= 1;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Rebuilding the index after a failed removal: {}", e);
+                self.rebuild_collection();
+            }
+        }
+    }
+
+    /// Whether a file's details are expanded in the Tracks list, by its key
+    /// in `file_loader.loaded_files`
+    pub fn is_expanded(&self, key: &Path) -> bool {
+        self.expanded_files.contains(key)
+    }
+
+    /// Expand or collapse a file's details in the Tracks list
+    pub fn toggle_expanded(&mut self, path: &Path) {
+        let key = file_key(path);
+        if !self.expanded_files.remove(&key) {
+            self.expanded_files.insert(key);
+        }
+        self.session_dirty = true;
+    }
+
+    /// Restore persisted expansion choices, dropping files that no longer exist
+    pub fn restore_expanded_files(&mut self, paths: &[String]) {
+        self.expanded_files = paths
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.exists())
+            .map(|path| file_key(&path))
+            .collect();
+    }
+
+    /// Color picked for a file, if any
+    pub fn route_color(&self, path: &Path) -> Option<Color32> {
+        self.route_colors.get(&file_key(path)).copied()
+    }
+
+    /// Pick a line color for a file, or go back to its automatic color with `None`
+    pub fn set_route_color(&mut self, path: &Path, color: Option<Color32>) {
+        let key = file_key(path);
+        match color {
+            Some(color) => self.route_colors.insert(key, color),
+            None => self.route_colors.remove(&key),
+        };
+        self.session_dirty = true;
+        self.write_sidecar(path);
+    }
+
+    /// Merge the sidecar of a file being loaded into its color and annotation
+    fn apply_sidecar(&mut self, path: &Path, gpx: &gpx::Gpx) {
+        if !self.ui_settings.track_sidecars {
+            return;
+        }
+        let Some(shared) = sidecar::read(path) else {
+            return;
+        };
+        let key = annotations::content_hash(gpx);
+        let local_color = self
+            .route_color(path)
+            .map(|color| [color.r(), color.g(), color.b()]);
+        let (color, annotation) = shared.merge(local_color, self.annotations.get(&key));
+        if let Some([r, g, b]) = color {
+            self.route_colors
+                .insert(file_key(path), Color32::from_rgb(r, g, b));
+        }
+        self.annotations.set(&key, annotation);
+        self.annotation_keys.insert(path.to_path_buf(), key);
+        self.session_dirty = true;
+    }
+
+    /// Write a file's color, notes and tags to its sidecar, with sidecars enabled
+    pub fn write_sidecar(&self, path: &Path) {
+        if !self.ui_settings.track_sidecars {
+            return;
+        }
+        let color = self
+            .route_color(path)
+            .map(|color| [color.r(), color.g(), color.b()]);
+        let annotation = self
+            .annotation_keys
+            .get(path)
+            .and_then(|key| self.annotations.get(key));
+        sidecar::write(path, &Sidecar::new(color, annotation));
+    }
+
+    /// Picked colors by the current route index of each loaded file, for drawing
+    ///
+    /// A file without a picked color takes the color of its style rule, if any.
+    pub fn route_color_overrides(&self) -> BTreeMap<usize, Color32> {
+        let rules = &self.ui_settings.style_rules;
+        if self.route_colors.is_empty() && rules.is_empty() {
+            return BTreeMap::new();
+        }
+        self.file_loader
+            .loaded_files
+            .iter()
+            .filter_map(|(path, _, start_idx, key)| {
+                self.route_colors
+                    .get(key)
+                    .copied()
+                    .or_else(|| style_rules::first_match(rules, path)?.color())
+                    .map(|color| (*start_idx, color))
+            })
+            .collect()
+    }
+
+    /// Dash patterns of the style rules by the current route index of each
+    /// loaded file, leaving out solid lines
+    pub fn route_dash_patterns(&self) -> BTreeMap<usize, DashPattern> {
+        let rules = &self.ui_settings.style_rules;
+        if rules.is_empty() {
+            return BTreeMap::new();
+        }
+        self.file_loader
+            .loaded_files
+            .iter()
+            .filter_map(|(path, _, start_idx, _)| {
+                let dash = style_rules::first_match(rules, path)?.dash;
+                (dash != DashPattern::Solid).then_some((*start_idx, dash))
+            })
+            .collect()
+    }
+
+    /// Restore persisted colors, dropping files that no longer exist
+    pub fn restore_route_colors(&mut self, colors: &BTreeMap<String, [u8; 3]>) {
+        self.route_colors = colors
+            .iter()
+            .map(|(path, rgb)| (PathBuf::from(path), rgb))
+            .filter(|(path, _)| path.exists())
+            .map(|(path, [r, g, b])| (file_key(&path), Color32::from_rgb(*r, *g, *b)))
+            .collect();
+    }
+
+    /// Picked colors by canonical path, for persisting
+    pub fn route_color_paths(&self) -> BTreeMap<String, [u8; 3]> {
+        self.route_colors
+            .iter()
+            .map(|(path, color)| {
+                (
+                    path.to_string_lossy().to_string(),
+                    [color.r(), color.g(), color.b()],
+                )
+            })
+            .collect()
+    }
+
+    /// Expanded files that are still listed (loaded, queued or held back), for persisting
+    pub fn expanded_file_paths(&self) -> Vec<String> {
+        let loader = &self.file_loader;
+        let listed: HashSet<PathBuf> = loader
+            .loaded_files
+            .iter()
+            .map(|(_, _, _, key)| key.clone())
+            .chain(
+                loader
+                    .pending_files
+                    .iter()
+                    .filter_map(|f| f.path.clone())
+                    .chain(loader.ingestor.in_flight().iter().cloned())
+                    .chain(self.held_file_paths.iter().map(PathBuf::from))
+                    .map(|path| file_key(&path)),
+            )
+            .collect();
+        self.expanded_files
+            .iter()
+            .filter(|path| listed.contains(*path))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Rebuild the entire collection from loaded files
+    fn rebuild_collection(&mut self) {
+        self.rebuild_collection_with_bias(self.ui_settings.bias);
+    }
+
+    /// Rebuild the collection with a specific bias value
+    fn rebuild_collection_with_bias(&mut self, bias: f64) {
+        profiling::scope!("rebuild_collection");
+
+        // Create new collection with updated bias
+        #[cfg(not(target_arch = "wasm32"))]
+        let old_config = {
+            let mut cfg_opt: Option<large_track_lib::Config> = None;
+            async_runtime::blocking_read(&self.route_collection, |guard| {
+                cfg_opt = Some(guard.config().clone());
+            });
+            cfg_opt.expect("route_collection read should eventually succeed")
+        };
+        #[cfg(target_arch = "wasm32")]
+        let old_config = match self.route_collection.try_read() {
+            Ok(guard) => guard.config().clone(),
+            Err(_) => return, // Skip if lock is not available
+        };
+        let config = Config {
+            bias,
+            chunking_aggressiveness: self.ui_settings.chunking_aggressiveness,
+            ..old_config
+        };
+        let mut new_collection = RouteCollection::new(config);
+
+        // Re-add all routes, refreshing each file's index since earlier removals shift them
+        for (_, gpx, start_idx, _) in &mut self.file_loader.loaded_files {
+            *start_idx = new_collection.route_count();
+            let _ = new_collection.add_route(gpx.clone());
+        }
+
+        // Replace the collection
+        self.route_collection = Arc::new(RwLock::new(new_collection));
+    }
+
+    /// Start recording a new track from live fixes, enabling the live position if needed
+    pub fn start_recording(&mut self) {
+        if self.recorder.is_some() {
+            return;
+        }
+        if !self.live_location.is_enabled() {
+            self.live_location.set_enabled(true);
+        }
+        self.recorder = Some(TrackRecorder::new());
+    }
+
+    /// Stop recording; the finished track is kept loaded until saved or discarded
+    pub fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        if recorder.point_count() == 0 {
+            return;
+        }
+        self.index_recording(recorder.path().clone(), recorder.gpx().clone());
+        self.finished_recording = Some(recorder.path().clone());
+    }
+
+    /// Insert or refresh a recording's route in the collection
+    #[cfg_attr(feature = "profiling", profiling::function)]
+    pub fn index_recording(&mut self, path: PathBuf, gpx: gpx::Gpx) {
+        let entry = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .position(|(loaded, _, _, _)| *loaded == path);
+        let existing_idx = entry.map(|i| self.file_loader.loaded_files[i].2);
+
+        let apply = |collection: &mut RouteCollection| match existing_idx {
+            Some(idx) => collection.replace_route(idx, gpx.clone()).map(|_| idx),
+            None => {
+                let idx = collection.route_count();
+                collection.add_route(gpx.clone()).map(|_| idx)
+            }
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = async_runtime::blocking_write(&self.route_collection, apply);
+        #[cfg(target_arch = "wasm32")]
+        let result = match self.route_collection.try_write() {
+            Ok(mut collection) => apply(&mut collection),
+            Err(_) => return, // Retried on the next re-index
+        };
+
+        match (result, entry) {
+            (Ok(_), Some(i)) => self.file_loader.loaded_files[i].1 = gpx,
+            (Ok(start_idx), None) => {
+                let key = file_key(&path);
+                self.file_loader
+                    .loaded_files
+                    .push((path, gpx, start_idx, key));
+            }
+            (Err(e), _) => {
+                tracing::warn!("Failed to index recording: {}", e);
+                return;
+            }
+        }
+        self.update_stats();
+    }
+
+    /// Generate routes as set in `synthetic_params` and add them to the map
+    ///
+    /// The routes go straight to [`RouteCollection::add_routes_parallel`],
+    /// without files, and are listed like loaded files under `synthetic://`
+    /// paths. They are never reloaded on restart. Returns how many were added.
+    pub fn add_synthetic_routes(&mut self) -> usize {
+        let routes = synthetic::generate(&self.synthetic_params);
+        let count = routes.len();
+        let apply = |collection: &mut RouteCollection| {
+            let start_idx = collection.route_count();
+            collection
+                .add_routes_parallel(routes.clone())
+                .map(|_| start_idx)
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = async_runtime::blocking_write(&self.route_collection, apply);
+        #[cfg(target_arch = "wasm32")]
+        let result = match self.route_collection.try_write() {
+            Ok(mut collection) => apply(&mut collection),
+            Err(_) => {
+                self.notify_warn("Tracks are busy loading, try generating again");
+                return 0;
+            }
+        };
+
+        let start_idx = match result {
+            Ok(start_idx) => start_idx,
+            Err(e) => {
+                self.notify_error(format!("Failed to generate test tracks: {}", e));
+                return 0;
+            }
+        };
+        let seed = self.synthetic_params.seed;
+        for (i, gpx) in routes.into_iter().enumerate() {
+            let path = PathBuf::from(format!(
+                "{}seed-{}-route-{}.gpx",
+                SYNTHETIC_PATH_PREFIX,
+                seed,
+                start_idx + i
+            ));
+            let key = file_key(&path);
+            self.file_loader
+                .loaded_files
+                .push((path, gpx, start_idx + i, key));
+        }
+        if !self.keep_startup_camera {
+            self.pending_fit_bounds = true;
+        }
+        self.update_stats();
+        self.notify_info(format!("Generated {} test track(s)", count));
+        count
+    }
+
+    /// Drop the finished recording from the map without saving it
+    pub fn discard_recording(&mut self) {
+        let Some(path) = self.finished_recording.take() else {
+            return;
+        };
+        if let Some(index) = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .position(|(loaded, _, _, _)| *loaded == path)
+        {
+            self.remove_file(index);
+        }
+    }
+
+    /// Nominal time zone where the selected route starts (see [`timezone::offset_hint`])
+    pub fn selected_offset_hint(&self) -> Option<time::UtcOffset> {
+        let route_index = (*self.selected_route.try_read().ok()?)?;
+        let collection = self.route_collection.try_read().ok()?;
+        let route = collection.get_route(route_index)?;
+        let start = route
+            .tracks()
+            .iter()
+            .flat_map(|track| &track.segments)
+            .find_map(|segment| segment.points.first())?;
+        Some(timezone::offset_hint(start.point().x()))
+    }
+
+    /// Time zone track times are shown in
+    pub fn display_zone(&self) -> DisplayZone {
+        DisplayZone::new(
+            self.ui_settings.time_zone,
+            self.local_offset,
+            self.ui_settings.utc_offset_minutes,
+        )
+    }
+
+    /// Refresh the Debug memory readout if its figures are due
+    pub fn sample_memory_readout(&mut self) {
+        let now = instant::Instant::now();
+        if !self.stats.memory_readout.is_due(now) {
+            return;
+        }
+        // Walks the index; a busy collection is sampled on a later frame
+        let Ok(collection) = self.route_collection.try_read() else {
+            return;
+        };
+        self.stats.memory_readout = MemoryReadout {
+            sampled_at: Some(now),
+            process_bytes: memory_budget::process_memory_bytes(),
+            index_bytes: collection.index_memory_estimate() as u64,
+        };
+    }
+
+    /// Update statistics from the route collection
+    pub fn update_stats(&mut self) {
+        profiling::scope!("update_stats");
+
+        if let Ok(collection) = self.route_collection.try_read() {
+            let info = collection.get_info();
+
+            self.stats.route_count = info.route_count;
+            self.stats.total_points = info.total_points;
+            self.stats.total_distance = info.total_distance_meters;
+            self.stats.min_elevation = info.min_elevation;
+            self.stats.max_elevation = info.max_elevation;
+            // Cached per route, so only new routes or a new threshold cost a pass
+            self.stats.climb = collection.climb(self.ui_settings.climb_threshold_m as f64);
+        }
+    }
+
+    /// Copy what the track plugin drew last frame into the stats readouts
+    pub fn apply_render_stats(&mut self, render_stats: &RenderStats) {
+        self.stats.last_query_segments = render_stats.segments_rendered;
+        self.stats.last_query_simplified_points = render_stats.simplified_points_rendered;
+        self.stats.last_query_skipped = render_stats.skipped.clone();
+        self.stats
+            .fully_skipped_routes
+            .clone_from(&render_stats.fully_skipped_routes);
+        self.stats
+            .visible_routes
+            .clone_from(&render_stats.visible_routes);
+        self.stats.last_outline_pass_ms = render_stats.outline_pass_ms;
+        self.stats.last_line_pass_ms = render_stats.line_pass_ms;
+    }
+
+    /// Clear all loaded routes
+    pub fn clear_routes(&mut self) {
+        let config = match self.route_collection.try_read() {
+            Ok(guard) => guard.config().clone(),
+            Err(_) => return, // Skip if lock is not available
+        };
+        self.route_collection = Arc::new(RwLock::new(RouteCollection::new(config)));
+        self.file_loader.loaded_files.clear();
+        self.file_loader.errors.clear();
+        self.file_loader.unretryable.clear();
+        self.file_loader.temporary.clear();
+        self.file_loader.pending_files.clear();
+        self.file_loader.ingestor.cancel_queued();
+        self.stats = Stats::default();
+        self.comparison.clear();
+        self.playback.stop();
+        self.expanded_files.clear();
+        self.annotation_keys.clear();
+        self.session_dirty = true;
+    }
+
+    /// Hash the loaded files that have no annotation key yet
+    pub fn refresh_annotation_keys(&mut self) {
+        for (path, gpx, _, _) in &self.file_loader.loaded_files {
+            if !self.annotation_keys.contains_key(path) {
+                self.annotation_keys
+                    .insert(path.clone(), annotations::content_hash(gpx));
+            }
+        }
+    }
+
+    /// Whether a loaded file passes the Tracks list filter
+    pub fn file_matches_filter(&self, path: &Path, gpx: &gpx::Gpx) -> bool {
+        if self.track_filter.trim().is_empty() {
+            return true;
+        }
+        let mut name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        for track_name in gpx.tracks.iter().filter_map(|track| track.name.as_deref()) {
+            name.push('\n');
+            name.push_str(track_name);
+        }
+        let key = self.annotation_keys.get(path).map(String::as_str);
+        self.annotations.matches(key, &name, &self.track_filter)
+    }
+
+    /// Select a route picked in the Tracks list, and fit the map to it with `zoom_on_select`
+    pub fn select_route(&mut self, route_index: usize) {
+        if let Ok(mut guard) = self.selected_route.try_write() {
+            *guard = Some(route_index);
+            if self.ui_settings.zoom_on_select {
+                self.pending_fit_route = Some(route_index);
+            }
+        }
+    }
+
+    /// Routes drawn by the last frame with their names, sorted by name
+    ///
+    /// A route is named after its file's first named track, or the file name
+    /// if no track has one.
+    pub fn visible_route_names(&self) -> Vec<(usize, String)> {
+        let mut names: Vec<(usize, String)> = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .filter(|(_, _, start_idx, _)| {
+                self.stats.visible_routes.binary_search(start_idx).is_ok()
+            })
+            .map(|(path, gpx, start_idx, _)| {
+                let name = gpx
+                    .tracks
+                    .iter()
+                    .find_map(|track| track.name.clone())
+                    .unwrap_or_else(|| {
+                        path.file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string()
+                    });
+                (*start_idx, name)
+            })
+            .collect();
+        names.sort_by(|(a_idx, a), (b_idx, b)| {
+            a.to_lowercase()
+                .cmp(&b.to_lowercase())
+                .then(a_idx.cmp(b_idx))
+        });
+        names
+    }
+
+    /// The routes drawn by the last frame in their map colors, in legend
+    /// order, or `None` while the index is busy
+    pub fn svg_routes(&mut self) -> Option<Vec<SvgRoute>> {
+        let names = self.visible_route_names();
+        let colors = self.route_color_overrides();
+        let collection = self.route_collection.try_read().ok()?;
+        let routes = names
+            .into_iter()
+            .filter_map(|(route_index, name)| {
+                let route = collection.routes().get(route_index)?;
+                let points = route
+                    .tracks()
+                    .iter()
+                    .flat_map(|track| &track.segments)
+                    .flat_map(|segment| &segment.points)
+                    .map(|point| wgs84_point_to_mercator(point.point()))
+                    .collect();
+                Some(SvgRoute {
+                    name,
+                    color: colors
+                        .get(&route_index)
+                        .copied()
+                        .unwrap_or_else(|| route_color(route_index)),
+                    points,
+                })
+            })
+            .collect();
+        Some(routes)
+    }
+
+    /// Index into `file_loader.loaded_files` of the file the selected route belongs to
+    pub fn selected_file(&self) -> Option<usize> {
+        let selected = (*self.selected_route.try_read().ok()?)?;
+        self.file_loader
+            .loaded_files
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, _, start_idx, _))| *start_idx <= selected)
+            .max_by_key(|(_, (_, _, start_idx, _))| *start_idx)
+            .map(|(idx, _)| idx)
+    }
+
+    /// Run the corridor comparison for the picked routes, reporting failures as a notification
+    pub fn run_comparison(&mut self) {
+        let result = match self.route_collection.try_read() {
+            Ok(collection) => self.comparison.run(&collection),
+            Err(_) => return, // Skip if lock is not available
+        };
+        if let Err(e) = result {
+            self.notify_error(format!("Comparison failed: {}", e));
+        }
+    }
+
+    /// Whether the selected route can be split now
+    ///
+    /// Not while files load (the list they are added to changes under the
+    /// split) or while the route is still being recorded.
+    pub fn can_split(&self) -> bool {
+        let Some(file) = self.selected_file() else {
+            return false;
+        };
+        let path = &self.file_loader.loaded_files[file].0;
+        !self.is_parallel_loading()
+            && !self.file_loader.is_busy()
+            && self
+                .recorder
+                .as_ref()
+                .is_none_or(|recorder| recorder.path() != path)
+    }
+
+    /// Turn the split tool on for the selected route
+    pub fn start_split(&mut self) {
+        if !self.can_split() {
+            return;
+        }
+        if let Some(file) = self.selected_file() {
+            self.split.start(self.file_loader.loaded_files[file].2);
+        }
+    }
+
+    /// Preview cutting the route being split at its point nearest to `position`
+    ///
+    /// Clicks more than `max_distance_m` from every point of the route are ignored.
+    pub fn pick_split_point(&mut self, position: walkers::Position, max_distance_m: f64) {
+        let Some(route_index) = self.split.route else {
+            return;
+        };
+        let route = match self.route_collection.try_read() {
+            Ok(collection) => collection.get_route(route_index).cloned(),
+            Err(_) => return, // Skip if lock is not available
+        };
+        let Some(route) = route else {
+            self.split.cancel();
+            return;
+        };
+        let Some((cut, distance)) = route.nearest_point(position.y(), position.x()) else {
+            return;
+        };
+        if distance > max_distance_m {
+            return;
+        }
+        match split::preview(&route, cut) {
+            Ok(preview) => self.split.preview = Some(preview),
+            Err(e) => self.notify_warn(format!("Can't split there: {}", e)),
+        }
+    }
+
+    /// Replace the route being split with its two previewed parts
+    ///
+    /// The first part keeps the route's index and color, the second is
+    /// listed right after it. Both only live in memory until exported; the
+    /// original file stays in the list reloaded on restart.
+    pub fn confirm_split(&mut self) {
+        let (Some(route_index), Some(preview)) = (self.split.route, self.split.preview.take())
+        else {
+            return;
+        };
+        let Some(file) = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .position(|(_, _, start_idx, _)| *start_idx == route_index)
+        else {
+            self.split.cancel();
+            return;
+        };
+
+        let (first, second) = preview.parts;
+        let parts = vec![first.clone(), second.clone()];
+        let replace =
+            |collection: &mut RouteCollection| collection.replace_route_with(route_index, parts);
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = async_runtime::blocking_write(&self.route_collection, replace);
+        #[cfg(target_arch = "wasm32")]
+        let result = match self.route_collection.try_write() {
+            Ok(mut collection) => replace(&mut collection),
+            Err(_) => {
+                self.notify_warn("The map is busy, try splitting again");
+                return;
+            }
+        };
+        let indices = match result {
+            Ok(indices) => indices,
+            Err(e) => {
+                self.split.cancel();
+                self.notify_error(format!("Splitting failed: {}", e));
+                // The collection may hold half of the change; index the file list again
+                self.rebuild_collection();
+                return;
+            }
+        };
+
+        let loaded_files = &mut self.file_loader.loaded_files;
+        let paths = split::part_paths(&loaded_files[file].0, |path| {
+            loaded_files.iter().any(|(loaded, _, _, _)| loaded == path)
+        });
+        let (original_path, original_gpx, _, _) = std::mem::replace(
+            &mut loaded_files[file],
+            (
+                paths[0].clone(),
+                first.gpx_data().clone(),
+                indices[0],
+                file_key(&paths[0]),
+            ),
+        );
+        loaded_files.insert(
+            file + 1,
+            (
+                paths[1].clone(),
+                second.gpx_data().clone(),
+                indices[1],
+                file_key(&paths[1]),
+            ),
+        );
+
+        // Until the parts are exported, a restart brings back the whole file
+        if can_reload(&original_path) && !self.is_temporary(&original_path) {
+            self.held_file_paths
+                .push(original_path.to_string_lossy().to_string());
+        }
+
+        let name = original_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        self.split.undo.push(SplitUndo {
+            original: (original_path, original_gpx),
+            parts: paths.clone(),
+        });
+        self.split.cancel();
+        // The second part's index is new; views tied to the old route start over
+        self.comparison.clear();
+        self.playback.stop();
+        self.session_dirty = true;
+        self.update_stats();
+        self.notify_with_action(
+            format!("Split {} in two", name),
+            NotificationAction::UndoSplit(paths),
+        );
+    }
+
+    /// Put back the file split into `parts`, if both parts are still loaded
+    pub fn undo_split(&mut self, parts: &[PathBuf; 2]) {
+        let Some(undo_index) = self
+            .split
+            .undo
+            .iter()
+            .rposition(|undo| undo.parts == *parts)
+        else {
+            return;
+        };
+        let position = |path: &PathBuf| {
+            self.file_loader
+                .loaded_files
+                .iter()
+                .position(|(loaded, _, _, _)| loaded == path)
+        };
+        let (Some(first), Some(second)) = (position(&parts[0]), position(&parts[1])) else {
+            self.split.undo.remove(undo_index);
+            self.notify_warn("Can't undo the split: a part was removed");
+            return;
+        };
+        let route_index = self.file_loader.loaded_files[first].2;
+        let second_index = self.file_loader.loaded_files[second].2;
+
+        let gpx = self.split.undo[undo_index].original.1.clone();
+        let restore = |collection: &mut RouteCollection| collection.replace_route(route_index, gpx);
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = async_runtime::blocking_write(&self.route_collection, restore);
+        #[cfg(target_arch = "wasm32")]
+        let result = match self.route_collection.try_write() {
+            Ok(mut collection) => restore(&mut collection),
+            Err(_) => {
+                self.notify_warn("The map is busy, try undoing again");
+                return;
+            }
+        };
 
-        if !self.file_loader.pending_files.contains(&dropped_file) && !already_loaded {
-            self.file_loader.pending_files.push(dropped_file);
+        let SplitUndo {
+            original: (path, gpx),
+            ..
+        } = self.split.undo.remove(undo_index);
+        let path_str = path.to_string_lossy().to_string();
+        self.held_file_paths.retain(|held| *held != path_str);
+        let key = file_key(&path);
+        self.file_loader.loaded_files[first] = (path, gpx, route_index, key);
+        self.file_loader.loaded_files.remove(second);
+        self.comparison.clear();
+        self.playback.stop();
+        self.session_dirty = true;
+        match result {
+            Ok(()) => self.remove_route(second_index),
+            Err(e) => {
+                tracing::warn!("Rebuilding the index after a failed undo: {}", e);
+                self.rebuild_collection();
+            }
         }
+        self.update_stats();
     }
 
-    /// Remove a loaded file by index
-    pub fn remove_file(&mut self, index: usize) {
-        if index < self.file_loader.loaded_files.len() {
-            self.file_loader.loaded_files.remove(index);
-            self.rebuild_collection();
-            self.update_stats();
-        }
+    /// Maximum map zoom for the current provider, including the over-zoom allowance
+    pub fn max_map_zoom(&self) -> f64 {
+        self.clamp_map_zoom(f64::INFINITY)
     }
 
-    /// Rebuild the entire collection from loaded files
-    fn rebuild_collection(&mut self) {
-        self.rebuild_collection_with_bias(self.ui_settings.bias);
+    /// Clamp a zoom to the current provider and over-zoom allowance (see [`clamp_zoom`])
+    pub fn clamp_map_zoom(&self, zoom: f64) -> f64 {
+        clamp_zoom(
+            zoom,
+            self.ui_settings.tiles_provider,
+            self.ui_settings.over_zoom_levels,
+        )
     }
 
-    /// Rebuild the collection with a specific bias value
-    fn rebuild_collection_with_bias(&mut self, bias: f64) {
-        profiling::scope!("rebuild_collection");
+    /// Ask for a new LOD bias, rebuilt with once the slider settles (see [`BiasDebouncer`])
+    pub fn update_bias(&mut self, new_bias: f64) {
+        self.bias_debouncer
+            .change(new_bias, instant::Instant::now());
+    }
 
-        // Create new collection with updated bias
-        #[cfg(not(target_arch = "wasm32"))]
-        let old_config = {
-            let mut cfg_opt: Option<large_track_lib::Config> = None;
-            async_runtime::blocking_read(&self.route_collection, |guard| {
-                cfg_opt = Some(guard.config().clone());
-            });
-            cfg_opt.expect("route_collection read should eventually succeed")
-        };
-        #[cfg(target_arch = "wasm32")]
-        let old_config = match self.route_collection.try_read() {
-            Ok(guard) => guard.config().clone(),
-            Err(_) => return, // Skip if lock is not available
-        };
-        let config = Config { bias, ..old_config };
-        let mut new_collection = RouteCollection::new(config);
+    /// Apply a settled bias change by scheduling a rebuild, or keep the UI
+    /// awake until it settles
+    pub fn process_bias_change(&mut self) {
+        let now = instant::Instant::now();
+        if let Some(bias) = self.bias_debouncer.poll(now) {
+            self.ui_settings.bias = bias;
+            self.pending_reload = true;
+        } else if let Some(remaining) = self.bias_debouncer.remaining(now) {
+            self.activity.keep_awake("bias change", remaining);
+        }
+    }
 
-        // Re-add all routes
-        for (_, gpx, _) in &self.file_loader.loaded_files {
-            let _ = new_collection.add_route(gpx.clone());
+    /// Update chunking aggressiveness and trigger reload
+    pub fn update_chunking_aggressiveness(&mut self, aggressiveness: f64) {
+        if (self.ui_settings.chunking_aggressiveness - aggressiveness).abs() > 0.01 {
+            self.ui_settings.chunking_aggressiveness = aggressiveness;
+            self.pending_reload = true;
         }
+    }
 
-        // Replace the collection
-        self.route_collection = Arc::new(RwLock::new(new_collection));
+    /// Turn boundary context on or off; takes effect on the next query without a rebuild
+    pub fn update_boundary_context(&mut self, enabled: bool) {
+        self.ui_settings.boundary_context = enabled;
+        #[cfg(not(target_arch = "wasm32"))]
+        async_runtime::blocking_write(&self.route_collection, |collection| {
+            collection.set_boundary_context(enabled)
+        });
+        #[cfg(target_arch = "wasm32")]
+        if let Ok(mut collection) = self.route_collection.try_write() {
+            collection.set_boundary_context(enabled);
+        }
     }
 
-    /// Update statistics from the route collection
-    pub fn update_stats(&mut self) {
-        profiling::scope!("update_stats");
+    /// Save the current settings as profile `name`, replacing any of that name
+    pub fn save_settings_profile(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() {
+            self.settings_profiles
+                .insert(name.to_string(), self.ui_settings.clone());
+            self.session_dirty = true;
+        }
+    }
 
-        if let Ok(collection) = self.route_collection.try_read() {
-            let info = collection.get_info();
+    pub fn delete_settings_profile(&mut self, name: &str) {
+        if self.settings_profiles.remove(name).is_some() {
+            self.session_dirty = true;
+        }
+    }
 
-            self.stats.route_count = info.route_count;
-            self.stats.total_points = info.total_points;
-            self.stats.total_distance = info.total_distance_meters;
+    /// Switch to the settings saved as profile `name`, if there is one
+    ///
+    /// The sidebar, map lock, profiling controls, startup mode, autosave
+    /// interval and preferred tile provider stay as they are. Index
+    /// settings go through their usual updates, so a different LOD bias or
+    /// chunking rebuilds the index as if changed by hand.
+    pub fn apply_settings_profile(&mut self, name: &str) -> bool {
+        let Some(profile) = self.settings_profiles.get(name).cloned() else {
+            return false;
+        };
+        let current = &self.ui_settings;
+        self.ui_settings = UiSettings {
+            sidebar_open: current.sidebar_open,
+            active_tab: current.active_tab,
+            map_locked: current.map_locked,
+            show_profiling: current.show_profiling,
+            startup_mode: current.startup_mode,
+            autosave_interval_secs: current.autosave_interval_secs,
+            preferred_tiles_provider: current.preferred_tiles_provider,
+            // Changed through their updates below
+            bias: current.bias,
+            chunking_aggressiveness: current.chunking_aggressiveness,
+            boundary_context: current.boundary_context,
+            keep_sharp_turns: current.keep_sharp_turns,
+            ..profile.clone()
+        };
+        if profile.bias != self.ui_settings.bias {
+            self.update_bias(profile.bias);
         }
+        self.update_chunking_aggressiveness(profile.chunking_aggressiveness);
+        if profile.boundary_context != self.ui_settings.boundary_context {
+            self.update_boundary_context(profile.boundary_context);
+        }
+        if profile.keep_sharp_turns != self.ui_settings.keep_sharp_turns {
+            self.update_keep_sharp_turns(profile.keep_sharp_turns);
+        }
+        true
     }
 
-    /// Clear all loaded routes
-    pub fn clear_routes(&mut self) {
-        let config = match self.route_collection.try_read() {
-            Ok(guard) => guard.config().clone(),
-            Err(_) => return, // Skip if lock is not available
+    /// Keep or cut sharp turns when simplifying; takes effect on the next query without a rebuild
+    ///
+    /// Tracks simplified the other way are dropped from the cache rather than
+    /// kept around for switching back.
+    pub fn update_keep_sharp_turns(&mut self, enabled: bool) {
+        self.ui_settings.keep_sharp_turns = enabled;
+        let threshold = enabled.then_some(DEFAULT_SHARP_TURN_DEGREES);
+        let apply = |collection: &mut RouteCollection| {
+            collection.set_sharp_turn_threshold(threshold);
+            collection.clear_cache();
         };
-        self.route_collection = Arc::new(RwLock::new(RouteCollection::new(config)));
-        self.file_loader.loaded_files.clear();
-        self.file_loader.errors.clear();
-        self.file_loader.pending_files.clear();
-        self.stats = Stats::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        async_runtime::blocking_write(&self.route_collection, apply);
+        #[cfg(target_arch = "wasm32")]
+        if let Ok(mut collection) = self.route_collection.try_write() {
+            apply(&mut collection);
+        }
     }
 
-    /// Update LOD bias and trigger reload
-    pub fn update_bias(&mut self, new_bias: f64) {
-        if (self.ui_settings.bias - new_bias).abs() > 0.01 {
-            self.ui_settings.bias = new_bias;
-            self.pending_reload = true;
+    /// Show or hide all tracks
+    ///
+    /// Hiding clears the render statistics, as nothing is queried until tracks
+    /// are shown again; showing repaints right away instead of waiting for the
+    /// next map interaction.
+    pub fn toggle_tracks(&mut self, ctx: &egui::Context) {
+        self.ui_settings.show_tracks = !self.ui_settings.show_tracks;
+        if !self.ui_settings.show_tracks {
+            self.stats.last_query_segments = 0;
+            self.stats.last_query_simplified_points = 0;
+            self.stats.last_query_skipped = Default::default();
+            self.stats.fully_skipped_routes.clear();
+            self.stats.visible_routes.clear();
+            self.stats.last_outline_pass_ms = 0.0;
+            self.stats.last_line_pass_ms = 0.0;
         }
+        ctx.request_repaint();
     }
 
     /// Process pending reload if needed
@@ -655,74 +2305,277 @@ impl AppState {
         }
     }
 
-    /// Show the mouse wheel zoom warning
-    pub fn show_wheel_zoom_warning(&mut self) {
-        self.show_wheel_warning = true;
-        self.wheel_warning_shown_at = Some(instant::Instant::now());
+    /// Show a notice that disappears on its own after a few seconds
+    pub fn notify_info(&mut self, text: impl Into<String>) {
+        self.notify(NotificationLevel::Info, text.into());
     }
 
-    /// Hide the mouse wheel zoom warning
-    pub fn hide_wheel_zoom_warning(&mut self) {
-        self.show_wheel_warning = false;
+    /// Show a warning that disappears on its own after a few seconds
+    pub fn notify_warn(&mut self, text: impl Into<String>) {
+        self.notify(NotificationLevel::Warn, text.into());
     }
 
-    /// Check if the warning should auto-hide (after 0.5 seconds)
-    pub fn should_hide_wheel_warning(&self) -> bool {
-        if let Some(shown_at) = self.wheel_warning_shown_at {
-            shown_at.elapsed().as_secs_f32() >= 0.5
-        } else {
-            false
+    /// Show an error that disappears on its own after a few seconds
+    pub fn notify_error(&mut self, text: impl Into<String>) {
+        self.notify(NotificationLevel::Error, text.into());
+    }
+
+    fn notify(&mut self, level: NotificationLevel, text: String) {
+        self.notifications
+            .push(level, text, level.lifetime(), None, instant::Instant::now());
+    }
+
+    /// Show a notice with a button that runs `action` (see [`AppState::run_notification_action`])
+    pub fn notify_with_action(&mut self, text: impl Into<String>, action: NotificationAction) {
+        self.notifications.push(
+            NotificationLevel::Info,
+            text.into(),
+            // Long enough to read the message and reach for the button
+            Duration::from_secs(8),
+            Some(action),
+            instant::Instant::now(),
+        );
+    }
+
+    /// Do what a toast's button was clicked for
+    pub fn run_notification_action(&mut self, action: NotificationAction) {
+        match action {
+            NotificationAction::UndoSplit(parts) => self.undo_split(&parts),
+            NotificationAction::ReloadFiles(paths) => {
+                for path in paths {
+                    self.queue_file(DroppedFile {
+                        name: path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string(),
+                        path: Some(path),
+                        ..Default::default()
+                    });
+                }
+                self.start_parallel_load();
+            }
         }
     }
 
-    /// Get fade alpha for the wheel warning (0.0 to 1.0)
-    /// Fade in over 0.15s, stay visible, fade out over 0.15s
-    pub fn get_wheel_warning_alpha(&self) -> f32 {
-        if let Some(shown_at) = self.wheel_warning_shown_at {
-            let elapsed = shown_at.elapsed().as_secs_f32();
+    /// Clear all loaded routes, offering to load the ones read from disk again
+    pub fn clear_routes_with_undo(&mut self) {
+        let count = self.file_loader.loaded_files.len();
+        let reloadable: Vec<PathBuf> = self
+            .file_loader
+            .loaded_files
+            .iter()
+            .map(|(path, _, _, _)| path.clone())
+            .filter(|path| can_reload(path))
+            .collect();
+        self.clear_routes();
+        match (count, reloadable.is_empty()) {
+            (0, _) => {}
+            (_, true) => self.notify_info(format!("Removed {} file(s)", count)),
+            (_, false) => self.notify_with_action(
+                format!("Removed {} file(s)", count),
+                NotificationAction::ReloadFiles(reloadable),
+            ),
+        }
+    }
+
+    /// Show a provider chosen by the user, forgetting any pending fallback
+    pub fn select_tiles_provider(&mut self, provider: TilesProvider) {
+        self.ui_settings.tiles_provider = provider;
+        self.ui_settings.preferred_tiles_provider = None;
+        self.tile_failure = None;
+    }
+
+    /// Act on a tile provider health check: switch to the fallback provider
+    /// (auto-fallback) or offer it in a banner
+    pub fn handle_tile_health(&mut self, provider: TilesProvider, result: Result<(), String>) {
+        // The user moved on to another provider while this one was checked
+        if provider != self.ui_settings.tiles_provider {
+            return;
+        }
+        let error = match result {
+            Ok(()) => {
+                if self.ui_settings.preferred_tiles_provider == Some(provider) {
+                    tracing::info!("{} tiles are back", provider.name());
+                    self.ui_settings.preferred_tiles_provider = None;
+                }
+                self.tile_failure = None;
+                return;
+            }
+            Err(error) => error,
+        };
+        tracing::warn!("{} tiles are not loading: {}", provider.name(), error);
+        // Once on a fallback, a failing fallback doesn't bounce back to the provider that failed first
+        let on_fallback = self
+            .ui_settings
+            .preferred_tiles_provider
+            .is_some_and(|preferred| preferred != provider);
+        match provider.fallback() {
+            Some(fallback) if self.ui_settings.auto_tile_fallback && !on_fallback => {
+                self.ui_settings.preferred_tiles_provider = Some(provider);
+                self.ui_settings.tiles_provider = fallback;
+                self.notify_warn(format!(
+                    "{} tiles are not loading, switched to {}",
+                    provider.name(),
+                    fallback.name()
+                ));
+            }
+            _ => self.tile_failure = Some((provider, error)),
+        }
+    }
 
-            if elapsed < 0.15 {
-                // Fade in
-                elapsed / 0.15
-            } else if elapsed < 0.35 {
-                // Fully visible
-                1.0
-            } else if elapsed < 0.5 {
-                // Fade out
-                1.0 - ((elapsed - 0.35) / 0.15)
-            } else {
-                0.0
+    /// Track the outcome of a save, warning once when saving starts failing
+    pub fn record_save_result(&mut self, result: StorageResult<()>) {
+        match result {
+            Ok(()) => {
+                if self.save_error.take().is_some() {
+                    self.notify_info("Settings are being saved again");
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Failed to save to platform storage: {:?}", e);
+                // Autosaves keep retrying; only the first failure in a row notifies
+                if self.save_error.is_none() {
+                    self.notify_warn(format!("Could not save settings: {}", e));
+                }
+                self.save_error = Some(e.to_string());
             }
-        } else {
-            0.0
         }
     }
+
+    /// Remind that the wheel only zooms with a modifier held
+    ///
+    /// Called on every unmodified wheel turn; the toast stays up until a
+    /// moment after the last one.
+    pub fn show_wheel_zoom_warning(&mut self, modifier: &str) {
+        self.notifications.push(
+            NotificationLevel::Info,
+            format!("Hold {} to zoom", modifier),
+            WHEEL_WARNING_DURATION,
+            None,
+            instant::Instant::now(),
+        );
+    }
+
+    /// Tell how to move a locked map, after a gesture it ignored
+    pub fn show_map_lock_hint(&mut self) {
+        self.notifications.push(
+            NotificationLevel::Info,
+            "Map locked: use two fingers, or tap 🔒 to unlock".to_string(),
+            MAP_LOCK_HINT_DURATION,
+            None,
+            instant::Instant::now(),
+        );
+    }
 }
 
 impl Default for UiSettings {
     fn default() -> Self {
         Self {
             line_width: 1.0,
+            width_mode: WidthMode::default(),
+            line_width_m: DEFAULT_LINE_WIDTH_M,
             show_outline: false,
+            blend_mode: BlendMode::default(),
+            offset_stacked_tracks: false,
+            stack_offset: DEFAULT_STACK_OFFSET,
             bias: 1.0,
             tiles_provider: TilesProvider::OpenStreetMap,
             sidebar_open: true,
+            map_locked: false,
             active_tab: SidebarTab::Tracks,
             show_profiling: false,
+            over_zoom_levels: DEFAULT_OVER_ZOOM_LEVELS,
+            chunking_aggressiveness: DEFAULT_CHUNKING_AGGRESSIVENESS,
+            show_center_crosshair: false,
+            show_distance_grid: false,
+            show_waypoints: false,
+            show_file_extents: false,
+            show_legend: false,
+            track_grouping: TrackGrouping::None,
+            track_sidecars: false,
+            boundary_context: true,
+            show_memory_readout: false,
+            show_tracks: true,
+            autosave_interval_secs: DEFAULT_AUTOSAVE_INTERVAL_SECS,
+            stats_smoothing_secs: DEFAULT_STATS_SMOOTHING_SECS,
+            climb_threshold_m: DEFAULT_CLIMB_THRESHOLD_M as f32,
+            startup_mode: StartupMode::default(),
+            file_precedence: FilePrecedence::default(),
+            auto_tile_fallback: false,
+            preferred_tiles_provider: None,
+            pan_inertia: true,
+            pan_friction: DEFAULT_PAN_FRICTION,
+            reduce_motion: false,
+            theme: egui::ThemePreference::System,
+            coord_format: CoordFormat::default(),
+            time_zone: TimeZone::default(),
+            utc_offset_minutes: 0,
+            keep_sharp_turns: false,
+            full_detail_selection: false,
+            style_rules: Vec::new(),
+            frame_budget_ms: DEFAULT_FRAME_BUDGET_MS,
+            zoom_on_select: false,
         }
     }
 }
 
 impl FileLoader {
+    /// A loader with `pending_files` queued and nothing loaded yet
+    pub fn new(pending_files: Vec<DroppedFile>, settings: &Settings) -> Self {
+        let ingestor = Ingestor::new(load_concurrency());
+        #[cfg(not(target_arch = "wasm32"))]
+        let ingestor = ingestor.with_elevation(elevation_service(settings));
+        #[cfg(target_arch = "wasm32")]
+        let _ = settings;
+        Self {
+            pending_files,
+            errors: Vec::new(),
+            unretryable: HashSet::new(),
+            temporary: HashSet::new(),
+            dismissed_errors: Vec::new(),
+            loaded_files: Vec::new(),
+            ingestor,
+            batch_start: 0,
+            batch_total: 0,
+        }
+    }
+
     /// Check if any files are being processed
     pub fn is_busy(&self) -> bool {
-        let total = self.parallel_total_files.load(Ordering::SeqCst);
-        let processed = self.loaded_files.len() + self.errors.len();
-        !self.pending_files.is_empty() || (total > 0 && processed < total)
+        let total = self.batch_total;
+        !self.pending_files.is_empty()
+            || self.ingestor.queued().next().is_some()
+            || (total > 0 && self.processed_count() < total)
+    }
+
+    /// Files whose load finished, successfully or not
+    pub fn processed_count(&self) -> usize {
+        self.loaded_files.len() + self.errors.len()
     }
 }
 
 impl Stats {
+    /// Blend the last query's numbers into their smoothed counterparts
+    pub fn smooth_query_stats(&mut self, now: instant::Instant, time_constant: Duration) {
+        let elapsed = self
+            .smoothed_at
+            .map_or(Duration::ZERO, |at| now.duration_since(at));
+        self.smoothed_at = Some(now);
+        self.smoothed_query_time_ms
+            .update(self.last_query_time_ms, elapsed, time_constant);
+        self.smoothed_query_segments.update(
+            self.last_query_segments as f64,
+            elapsed,
+            time_constant,
+        );
+        self.smoothed_query_simplified_points.update(
+            self.last_query_simplified_points as f64,
+            elapsed,
+            time_constant,
+        );
+    }
+
     /// Format distance as human-readable string
     pub fn format_distance(&self) -> String {
         let km = self.total_distance / 1000.0;
@@ -740,6 +2593,14 @@ impl Stats {
         format_number_with_commas(self.total_points)
     }
 
+    /// Format elevation range, if any elevation data is loaded
+    pub fn format_elevation_range(&self) -> Option<String> {
+        match (self.min_elevation, self.max_elevation) {
+            (Some(min), Some(max)) => Some(format!("{:.0} – {:.0} m", min, max)),
+            _ => None,
+        }
+    }
+
     /// Format route count
     pub fn format_routes(&self) -> String {
         format!("{}", self.route_count)
@@ -758,3 +2619,915 @@ fn format_number_with_commas(n: usize) -> String {
     }
     result.chars().rev().collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use large_track_lib::{RouteBuilder, TrackPoint};
+
+    fn small_gpx(lat: f64) -> gpx::Gpx {
+        let points = [0.0, 0.001, 0.002].map(|lon| TrackPoint::new(lat, lon));
+        let route = RouteBuilder::from_gpx(gpx::Gpx::default())
+            .add_segment(points)
+            .build()
+            .unwrap();
+        route.gpx_data().clone()
+    }
+
+    #[test]
+    fn results_are_added_in_batches_and_fit_once() {
+        let mut state = AppState::new(&Settings::parse_from([
+            "large-track-viewer",
+            "--results-per-frame",
+            "3",
+        ]));
+        let files = 7;
+        state.file_loader.batch_total = files;
+        for i in 0..files {
+            state.file_loader.ingestor.send_result(
+                PathBuf::from(format!("track{}.gpx", i)),
+                Ok(small_gpx(i as f64)),
+            );
+        }
+
+        let mut loaded_after_call = Vec::new();
+        let mut fits = 0;
+        while state.process_parallel_results() {
+            loaded_after_call.push(state.file_loader.loaded_files.len());
+            fits += std::mem::take(&mut state.pending_fit_bounds) as usize;
+        }
+        loaded_after_call.push(state.file_loader.loaded_files.len());
+        fits += std::mem::take(&mut state.pending_fit_bounds) as usize;
+
+        assert_eq!(loaded_after_call, vec![3, 6, 7]);
+        assert_eq!(fits, 1);
+        assert_eq!(state.stats.route_count, files);
+    }
+
+    #[test]
+    fn loader_runs_without_an_app_or_runtime() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let mut bytes = Vec::new();
+        gpx::write(
+            &gpx::Gpx {
+                version: gpx::GpxVersion::Gpx11,
+                ..small_gpx(45.0)
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        state.file_loader.pending_files.push(DroppedFile {
+            name: "track.gpx".to_string(),
+            bytes: Some(bytes.into()),
+            ..Default::default()
+        });
+
+        // No tokio runtime in this test: the loader spawns on one created on demand
+        state.start_parallel_load();
+        let deadline = instant::Instant::now() + Duration::from_secs(10);
+        while state.file_loader.loaded_files.is_empty() && instant::Instant::now() < deadline {
+            state.process_parallel_results();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(state.file_loader.loaded_files.len(), 1);
+        assert!(state.file_loader.errors.is_empty());
+        assert_eq!(state.stats.route_count, 1);
+    }
+
+    #[test]
+    fn loaded_file_is_queried_drawn_and_counted_consistently() {
+        use crate::app::render::{PreparedTracks, TrackStyle, prepare_tracks};
+        use large_track_lib::utils::wgs84_to_mercator;
+
+        let mut track = gpx::Track::new();
+        for segment_start in [0.0, 0.02] {
+            let mut segment = gpx::TrackSegment::new();
+            for i in 0..150 {
+                let t = i as f64 * 1e-4;
+                segment.points.push(gpx::Waypoint::new(geo::Point::new(
+                    6.0 + segment_start + t,
+                    45.0 + (t * 400.0).sin() * 0.001,
+                )));
+            }
+            track.segments.push(segment);
+        }
+        let mut bytes = Vec::new();
+        gpx::write(
+            &gpx::Gpx {
+                version: gpx::GpxVersion::Gpx11,
+                tracks: vec![track],
+                ..Default::default()
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("ltv-pipeline-{}.gpx", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.queue_file(DroppedFile {
+            name: "pipeline.gpx".to_string(),
+            path: Some(path.clone()),
+            ..Default::default()
+        });
+        state.start_parallel_load();
+        let deadline = instant::Instant::now() + Duration::from_secs(10);
+        while state.file_loader.loaded_files.is_empty() && instant::Instant::now() < deadline {
+            state.process_parallel_results();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(state.file_loader.errors.is_empty());
+        assert_eq!(state.stats.route_count, 1);
+        assert_eq!(state.stats.total_points, 300);
+        assert!(state.stats.total_distance > 0.0);
+
+        // What the track plugin does each frame, minus the painter
+        let (width, height) = (800.0, 600.0);
+        let min = wgs84_to_mercator(44.99, 5.99);
+        let max = wgs84_to_mercator(45.01, 6.04);
+        let segments = state
+            .route_collection
+            .try_read()
+            .unwrap()
+            .query_visible(geo::Rect::new(min.0, max.0), (width, height));
+        let project = |position: walkers::Position| {
+            let p = wgs84_to_mercator(position.y(), position.x());
+            egui::pos2(
+                ((p.x() - min.x()) / (max.x() - min.x()) * width) as f32,
+                ((max.y() - p.y()) / (max.y() - min.y()) * height) as f32,
+            )
+        };
+        let style = TrackStyle {
+            width: 2.0,
+            show_outline: false,
+            compare_pair: None,
+            route_colors: &BTreeMap::new(),
+            route_dashes: &BTreeMap::new(),
+            blend_mode: BlendMode::Normal,
+            hovered: None,
+            highlighted: None,
+            stack_offset: 0.0,
+        };
+        let mut prepared = PreparedTracks::default();
+        prepare_tracks(&mut prepared, &segments, &project, None, None, style);
+        let mut render_stats = RenderStats::default();
+        render_stats.record_frame(segments.len(), &prepared, [0.0; 2]);
+        state.apply_render_stats(&render_stats);
+
+        // Both segments are in view and every drawn point is one of the file's
+        let stats = &state.stats;
+        assert_eq!(stats.last_query_segments, segments.len());
+        assert!(stats.last_query_segments >= 2);
+        assert!(stats.last_query_simplified_points > 0);
+        assert!(stats.last_query_simplified_points <= stats.total_points + 2 * segments.len());
+        assert_eq!(stats.visible_routes, [0]);
+        assert!(stats.fully_skipped_routes.is_empty());
+        assert_eq!(state.visible_route_names().len(), 1);
+    }
+
+    #[test]
+    fn failing_tiles_fall_back_once_and_recover() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.ui_settings.auto_tile_fallback = true;
+        let osm = TilesProvider::OpenStreetMap;
+        let otm = TilesProvider::OpenTopoMap;
+
+        state.handle_tile_health(osm, Err("timed out".into()));
+        assert_eq!(state.ui_settings.tiles_provider, otm);
+        assert_eq!(state.ui_settings.preferred_tiles_provider, Some(osm));
+        assert!(state.tile_failure.is_none());
+
+        // The fallback failing too offers the banner instead of switching back
+        state.handle_tile_health(otm, Err("503".into()));
+        assert_eq!(state.ui_settings.tiles_provider, otm);
+        assert_eq!(state.tile_failure, Some((otm, "503".to_string())));
+
+        // Next start retries the preferred provider; once it answers it's kept
+        state.ui_settings.tiles_provider = osm;
+        state.handle_tile_health(osm, Ok(()));
+        assert_eq!(state.ui_settings.preferred_tiles_provider, None);
+        assert!(state.tile_failure.is_none());
+    }
+
+    #[test]
+    fn failing_tiles_without_auto_fallback_show_the_banner() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let osm = TilesProvider::OpenStreetMap;
+
+        // Stale reports for a provider no longer shown are ignored
+        state.handle_tile_health(TilesProvider::OpenTopoMap, Err("down".into()));
+        assert!(state.tile_failure.is_none());
+
+        state.handle_tile_health(osm, Err("down".into()));
+        assert_eq!(state.ui_settings.tiles_provider, osm);
+        assert_eq!(state.tile_failure, Some((osm, "down".to_string())));
+
+        state.select_tiles_provider(TilesProvider::OpenTopoMap);
+        assert!(state.tile_failure.is_none());
+        assert_eq!(state.ui_settings.preferred_tiles_provider, None);
+    }
+
+    #[test]
+    fn tracks_filter_matches_tags_of_loaded_files() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let alps = PathBuf::from("/tracks/alps.gpx");
+        let coast = PathBuf::from("/tracks/coast.gpx");
+        state
+            .file_loader
+            .loaded_files
+            .push((alps.clone(), small_gpx(45.0), 0, file_key(&alps)));
+        state
+            .file_loader
+            .loaded_files
+            .push((coast.clone(), small_gpx(44.0), 1, file_key(&coast)));
+        state.refresh_annotation_keys();
+        let key = state.annotation_keys[&coast].clone();
+        state.annotations.add_tag(&key, "race");
+
+        state.track_filter = "#race".to_string();
+        assert!(!state.file_matches_filter(&alps, &state.file_loader.loaded_files[0].1));
+        assert!(state.file_matches_filter(&coast, &state.file_loader.loaded_files[1].1));
+
+        *state.selected_route.try_write().unwrap() = Some(1);
+        assert_eq!(state.selected_file(), Some(1));
+    }
+
+    #[test]
+    fn legend_lists_drawn_routes_by_name() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let mut named = small_gpx(45.0);
+        named.tracks[0].name = Some("Alps loop".to_string());
+        for (idx, (path, gpx)) in [
+            ("/tracks/zermatt.gpx", small_gpx(46.0)),
+            ("/tracks/coast.gpx", small_gpx(44.0)),
+            ("/tracks/day1.gpx", named),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            state.file_loader.loaded_files.push((
+                PathBuf::from(path),
+                gpx,
+                idx,
+                file_key(Path::new(path)),
+            ));
+        }
+        state.stats.visible_routes = vec![0, 2];
+
+        assert_eq!(
+            state.visible_route_names(),
+            vec![(2, "Alps loop".to_string()), (0, "zermatt.gpx".to_string())]
+        );
+    }
+
+    #[test]
+    fn generated_tracks_are_indexed_but_not_persisted() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.synthetic_params = SyntheticParams {
+            route_count: 3,
+            points_per_route: 200,
+            ..Default::default()
+        };
+        assert_eq!(state.add_synthetic_routes(), 3);
+        assert_eq!(state.add_synthetic_routes(), 3);
+
+        let indices: Vec<usize> = state
+            .file_loader
+            .loaded_files
+            .iter()
+            .map(|(_, _, idx, _)| *idx)
+            .collect();
+        assert_eq!(indices, (0..6).collect::<Vec<_>>());
+        assert_eq!(state.route_collection.try_read().unwrap().route_count(), 6);
+        assert_eq!(state.persisted_file_paths(), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn selecting_a_track_fits_the_map_only_with_zoom_on_select() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.select_route(2);
+        assert_eq!(*state.selected_route.try_read().unwrap(), Some(2));
+        assert_eq!(state.pending_fit_route, None);
+
+        state.ui_settings.zoom_on_select = true;
+        state.select_route(1);
+        assert_eq!(*state.selected_route.try_read().unwrap(), Some(1));
+        assert_eq!(state.pending_fit_route, Some(1));
+    }
+
+    #[test]
+    fn expanded_files_persist_by_canonical_path() {
+        let dir = std::env::temp_dir().join(format!("ltv-expanded-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("ride.gpx");
+        std::fs::write(&file, "").unwrap();
+        // Another spelling of the same file
+        let spelled = dir.join(".").join("ride.gpx");
+
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.file_loader.loaded_files.push((
+            spelled.clone(),
+            gpx::Gpx::default(),
+            0,
+            file_key(&spelled),
+        ));
+        state.toggle_expanded(&spelled);
+        // Resolved once when the file was added
+        let key = state.file_loader.loaded_files[0].3.clone();
+        assert_eq!(key, file.canonicalize().unwrap());
+        assert!(state.is_expanded(&key));
+
+        let missing = dir.join("gone.gpx").to_string_lossy().to_string();
+        let mut persisted = state.expanded_file_paths();
+        assert_eq!(
+            persisted,
+            vec![file.canonicalize().unwrap().to_string_lossy().to_string()]
+        );
+        persisted.push(missing);
+
+        let mut restored = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        restored.restore_expanded_files(&persisted);
+        assert!(restored.is_expanded(&key));
+        assert_eq!(restored.expanded_files.len(), 1);
+
+        // Files that left the list aren't persisted
+        state.remove_file(0);
+        assert!(!state.is_expanded(&key));
+        state.expanded_files.insert(file.canonicalize().unwrap());
+        assert!(state.expanded_file_paths().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn route_colors_follow_the_file_across_reloads() {
+        let dir = std::env::temp_dir().join(format!("ltv-colors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let alps = dir.join("alps.gpx");
+        let coast = dir.join("coast.gpx");
+        let gone = dir.join("gone.gpx");
+        for file in [&alps, &coast, &gone] {
+            std::fs::write(file, "").unwrap();
+        }
+        let red = Color32::from_rgb(200, 0, 0);
+        let blue = Color32::from_rgb(0, 0, 200);
+
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        for (i, file) in [&alps, &coast].into_iter().enumerate() {
+            state.file_loader.loaded_files.push((
+                file.clone(),
+                small_gpx(45.0 - i as f64),
+                i,
+                file_key(file),
+            ));
+        }
+        state.set_route_color(&dir.join(".").join("coast.gpx"), Some(red));
+        state.set_route_color(&gone, Some(blue));
+        assert_eq!(state.route_color_overrides(), BTreeMap::from([(1, red)]));
+        let persisted = state.route_color_paths();
+        std::fs::remove_file(&gone).unwrap();
+
+        // Next session loads coast first, and gone.gpx no longer exists
+        let mut restored = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        restored.restore_route_colors(&persisted);
+        for (i, file) in [&coast, &alps].into_iter().enumerate() {
+            restored.file_loader.loaded_files.push((
+                file.clone(),
+                small_gpx(45.0 - i as f64),
+                i,
+                file_key(file),
+            ));
+        }
+        assert_eq!(restored.route_colors.len(), 1);
+        assert_eq!(restored.route_color_overrides(), BTreeMap::from([(0, red)]));
+
+        // Removing a file shifts the others down; the color moves with its file
+        restored.set_route_color(&alps, Some(blue));
+        restored.remove_file(0);
+        assert_eq!(
+            restored.route_color_overrides(),
+            BTreeMap::from([(0, blue)])
+        );
+        assert_eq!(restored.route_color(&coast), Some(red));
+        restored.set_route_color(&alps, None);
+        assert!(restored.route_color_overrides().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pathological_file_becomes_an_error_entry() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let gpx = degenerate_gpx();
+
+        let path = PathBuf::from("degenerate.gpx");
+        state.file_loader.batch_total = 1;
+        state
+            .file_loader
+            .ingestor
+            .send_result(path.clone(), Ok(gpx));
+        state.process_parallel_results();
+
+        assert!(state.file_loader.loaded_files.is_empty());
+        assert_eq!(state.file_loader.errors.len(), 1);
+        assert_eq!(state.file_loader.errors[0].0, path);
+        assert!(!state.is_parallel_loading());
+    }
+
+    /// A track the index rejects: its points aren't coordinates
+    fn degenerate_gpx() -> gpx::Gpx {
+        let mut gpx = small_gpx(0.0);
+        let points = &mut gpx.tracks[0].segments[0].points;
+        points[0] = gpx::Waypoint::new(geo::Point::new(0.0, f64::NAN));
+        points[1] = gpx::Waypoint::new(geo::Point::new(f64::INFINITY, 0.0));
+        gpx
+    }
+
+    #[test]
+    fn mixed_batch_loads_the_good_files_and_lists_the_rest() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let parse_error = gpx::read(&b"not a gpx file"[..]).unwrap_err();
+        state.file_loader.batch_total = 4;
+        let ingestor = &mut state.file_loader.ingestor;
+        ingestor.send_result(PathBuf::from("a.gpx"), Ok(small_gpx(45.0)));
+        ingestor.send_result(
+            PathBuf::from("broken.gpx"),
+            Err(LoadError::Parse(parse_error)),
+        );
+        ingestor.send_result(PathBuf::from("degenerate.gpx"), Ok(degenerate_gpx()));
+        ingestor.send_result(PathBuf::from("b.gpx"), Ok(small_gpx(46.0)));
+        assert!(state.is_parallel_loading());
+
+        let mut fits = 0;
+        for _ in 0..10 {
+            state.process_parallel_results();
+            fits += std::mem::take(&mut state.pending_fit_bounds) as usize;
+        }
+
+        let loaded: Vec<&Path> = state
+            .file_loader
+            .loaded_files
+            .iter()
+            .map(|(path, _, _, _)| path.as_path())
+            .collect();
+        assert_eq!(loaded, [Path::new("a.gpx"), Path::new("b.gpx")]);
+        let failed: Vec<&Path> = state
+            .file_loader
+            .errors
+            .iter()
+            .map(|(path, _)| path.as_path())
+            .collect();
+        assert_eq!(
+            failed,
+            [Path::new("broken.gpx"), Path::new("degenerate.gpx")]
+        );
+        assert!(matches!(state.file_loader.errors[0].1, LoadError::Parse(_)));
+        assert_eq!(state.stats.route_count, 2);
+        // The batch finishes once, however its files ended
+        assert_eq!(fits, 1);
+        let toasts: Vec<&str> = state
+            .notifications
+            .iter()
+            .map(|n| n.text.as_str())
+            .collect();
+        assert!(toasts.contains(&"2 files failed to load, see the Tracks tab"));
+        assert!(!state.is_parallel_loading());
+    }
+
+    #[test]
+    fn wheel_warning_is_a_single_toast_while_scrolling() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        for _ in 0..10 {
+            state.show_wheel_zoom_warning("Ctrl");
+        }
+        assert_eq!(state.notifications.iter().count(), 1);
+        let toast = state.notifications.iter().next().unwrap();
+        assert_eq!(toast.text, "Hold Ctrl to zoom");
+        assert!(toast.lifetime >= WHEEL_WARNING_DURATION);
+
+        // Without animations it shows without fading, otherwise it fades in
+        let shown = toast.created_at;
+        assert_eq!(toast.alpha(shown, false), 1.0);
+        assert!(toast.alpha(shown + Duration::from_millis(50), true) < 1.0);
+        assert_eq!(toast.alpha(toast.expires_at(), false), 0.0);
+    }
+
+    #[test]
+    fn clearing_offers_to_reload_files_from_disk() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let path = std::env::temp_dir().join(format!("ltv-undo-clear-{}.gpx", std::process::id()));
+        let mut bytes = Vec::new();
+        gpx::write(
+            &gpx::Gpx {
+                version: gpx::GpxVersion::Gpx11,
+                ..small_gpx(45.0)
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+        state.queue_file(DroppedFile {
+            name: "undo.gpx".to_string(),
+            path: Some(path.clone()),
+            ..Default::default()
+        });
+        state.queue_file(DroppedFile {
+            name: "dropped.gpx".to_string(),
+            bytes: Some(bytes.into()),
+            ..Default::default()
+        });
+        let wait_for_loaded = |state: &mut AppState, files: usize| {
+            let deadline = instant::Instant::now() + Duration::from_secs(10);
+            while state.file_loader.loaded_files.len() < files && instant::Instant::now() < deadline
+            {
+                state.process_parallel_results();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+        state.start_parallel_load();
+        wait_for_loaded(&mut state, 2);
+        assert_eq!(state.file_loader.loaded_files.len(), 2);
+
+        state.clear_routes_with_undo();
+        assert!(state.file_loader.loaded_files.is_empty());
+        let toast = state.notifications.iter().last().unwrap();
+        assert_eq!(toast.text, "Removed 2 file(s)");
+        // Only the file on disk can come back
+        let action = toast.action.clone().unwrap();
+        assert_eq!(action, NotificationAction::ReloadFiles(vec![path.clone()]));
+
+        state.run_notification_action(action);
+        wait_for_loaded(&mut state, 1);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(state.file_loader.loaded_files.len(), 1);
+        assert_eq!(state.file_loader.loaded_files[0].0, path);
+    }
+
+    #[test]
+    fn split_lists_two_parts_and_undo_puts_the_file_back() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let ride = small_gpx(45.0);
+        state.index_recording(PathBuf::from("synthetic://ride.gpx"), ride.clone());
+        state.index_recording(PathBuf::from("synthetic://other.gpx"), small_gpx(46.0));
+        let listed = |state: &AppState| -> Vec<(String, usize, usize)> {
+            state
+                .file_loader
+                .loaded_files
+                .iter()
+                .map(|(path, gpx, start_idx, _)| {
+                    let points = gpx.tracks[0].segments.iter().map(|s| s.points.len()).sum();
+                    (path.to_string_lossy().to_string(), points, *start_idx)
+                })
+                .collect()
+        };
+
+        state.select_route(0);
+        assert!(state.can_split());
+        state.start_split();
+        // A click far from the track picks nothing
+        state.pick_split_point(walkers::lat_lon(45.5, 0.001), 50.0);
+        assert!(state.split.preview.is_none());
+        // Snaps to the middle point
+        state.pick_split_point(walkers::lat_lon(45.0, 0.0011), 50.0);
+        state.confirm_split();
+        assert!(!state.split.is_active());
+        assert_eq!(
+            listed(&state),
+            vec![
+                ("split://ride (1 of 2).gpx".to_string(), 2, 0),
+                ("split://ride (2 of 2).gpx".to_string(), 2, 2),
+                ("synthetic://other.gpx".to_string(), 3, 1),
+            ]
+        );
+        assert_eq!(state.route_collection.try_read().unwrap().route_count(), 3);
+        // Parts only live in memory
+        assert!(state.persisted_file_paths().0.is_empty());
+
+        let action = state.notifications.iter().last().unwrap().action.clone();
+        state.run_notification_action(action.unwrap());
+        assert_eq!(
+            listed(&state),
+            vec![
+                ("synthetic://ride.gpx".to_string(), 3, 0),
+                ("synthetic://other.gpx".to_string(), 3, 1),
+            ]
+        );
+        let collection = state.route_collection.try_read().unwrap();
+        assert_eq!(collection.route_count(), 2);
+        assert_eq!(collection.get_route(0).unwrap().gpx_data(), &ride);
+        assert!(state.split.undo.is_empty());
+    }
+
+    #[test]
+    fn retry_all_reloads_failed_files_and_dismiss_keeps_a_log() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let path = std::env::temp_dir().join(format!("ltv-retry-{}.gpx", std::process::id()));
+        std::fs::write(&path, "not a gpx file").unwrap();
+        state.queue_file(DroppedFile {
+            name: "retry.gpx".to_string(),
+            path: Some(path.clone()),
+            ..Default::default()
+        });
+        state.queue_file(DroppedFile {
+            name: "dropped.gpx".to_string(),
+            bytes: Some(b"not a gpx file either".to_vec().into()),
+            ..Default::default()
+        });
+        let wait_until_done = |state: &mut AppState, files: usize| {
+            let deadline = instant::Instant::now() + Duration::from_secs(10);
+            while state.file_loader.loaded_files.len() + state.file_loader.errors.len() < files
+                && instant::Instant::now() < deadline
+            {
+                state.process_parallel_results();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        };
+        state.start_parallel_load();
+        wait_until_done(&mut state, 2);
+        assert_eq!(state.file_loader.errors.len(), 2);
+        assert_eq!(state.retryable_error_count(), 1);
+
+        // Fixing the file on disk and retrying loads it; the dropped bytes are gone
+        let mut bytes = Vec::new();
+        gpx::write(
+            &gpx::Gpx {
+                version: gpx::GpxVersion::Gpx11,
+                ..small_gpx(45.0)
+            },
+            &mut bytes,
+        )
+        .unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(state.retry_failed_files(), 1);
+        wait_until_done(&mut state, 2);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(state.file_loader.loaded_files.len(), 1);
+        assert_eq!(state.file_loader.loaded_files[0].0, path);
+        assert_eq!(state.file_loader.errors.len(), 1);
+        assert_eq!(state.retryable_error_count(), 0);
+
+        state.dismiss_errors();
+        assert!(state.file_loader.errors.is_empty());
+        assert_eq!(state.file_loader.dismissed_errors.len(), 1);
+    }
+
+    #[test]
+    fn empty_files_are_reported_as_empty_not_as_parse_errors() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        for (name, bytes) in [
+            ("empty.gpx", &b""[..]),
+            ("blank.gpx", &b" \n\t\n"[..]),
+            ("broken.gpx", &b"<gpx"[..]),
+        ] {
+            state.queue_file(DroppedFile {
+                name: name.to_string(),
+                bytes: Some(bytes.to_vec().into()),
+                ..Default::default()
+            });
+        }
+        state.start_parallel_load();
+        let deadline = instant::Instant::now() + Duration::from_secs(10);
+        while state.file_loader.errors.len() < 3 && instant::Instant::now() < deadline {
+            state.process_parallel_results();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let mut errors: Vec<(String, String)> = state
+            .file_loader
+            .errors
+            .iter()
+            .map(|(path, error)| (path.to_string_lossy().to_string(), error.to_string()))
+            .collect();
+        errors.sort();
+        // Dropped bytes are keyed by name and a hash of their content
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].0.starts_with("blank.gpx-"));
+        assert_eq!(errors[0].1, "File is empty");
+        assert!(errors[1].0.starts_with("broken.gpx-"));
+        assert!(errors[1].1.starts_with("Failed to parse GPX"));
+        assert!(errors[2].0.starts_with("empty.gpx-"));
+        assert_eq!(errors[2].1, "File is empty");
+    }
+
+    #[test]
+    fn zoom_is_clamped_to_the_provider_and_over_zoom() {
+        assert_eq!(clamp_zoom(25.0, TilesProvider::OpenStreetMap, 0), 19.0);
+        assert_eq!(clamp_zoom(25.0, TilesProvider::OpenTopoMap, 0), 17.0);
+        assert_eq!(clamp_zoom(25.0, TilesProvider::None, 0), 22.0);
+        // The allowance adds whole levels on top of each provider's tiles
+        assert_eq!(clamp_zoom(25.0, TilesProvider::OpenStreetMap, 2), 21.0);
+        assert_eq!(clamp_zoom(25.0, TilesProvider::OpenTopoMap, 2), 19.0);
+        assert_eq!(clamp_zoom(18.5, TilesProvider::OpenTopoMap, 2), 18.5);
+        // Zooms within range and below zero
+        assert_eq!(clamp_zoom(12.3, TilesProvider::OpenTopoMap, 0), 12.3);
+        assert_eq!(clamp_zoom(-1.0, TilesProvider::None, 0), 0.0);
+
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        state.ui_settings.tiles_provider = TilesProvider::OpenTopoMap;
+        state.ui_settings.over_zoom_levels = 1;
+        assert_eq!(state.max_map_zoom(), 18.0);
+        assert_eq!(state.clamp_map_zoom(19.0), 18.0);
+    }
+
+    #[test]
+    fn zoom_glide_settles_on_the_target() {
+        let mut zoom = 21.0;
+        let mut frames = 0;
+        while zoom != 17.0 {
+            let next = zoom_glide_step(zoom, 17.0, 1.0 / 60.0);
+            assert!(next < zoom && next >= 17.0);
+            zoom = next;
+            frames += 1;
+            assert!(frames < 120, "glide did not settle");
+        }
+        // Long frames land straight on the target
+        assert_eq!(zoom_glide_step(21.0, 17.0, 2.0), 17.0);
+    }
+
+    #[test]
+    fn temporary_files_are_shown_but_not_persisted() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let dir = std::env::temp_dir();
+        let kept = dir.join(format!("ltv-kept-{}.gpx", std::process::id()));
+        let scratch = dir.join(format!("ltv-scratch-{}.gpx", std::process::id()));
+        for (path, lat) in [(&kept, 45.0), (&scratch, 46.0)] {
+            let mut bytes = Vec::new();
+            gpx::write(
+                &gpx::Gpx {
+                    version: gpx::GpxVersion::Gpx11,
+                    ..small_gpx(lat)
+                },
+                &mut bytes,
+            )
+            .unwrap();
+            std::fs::write(path, bytes).unwrap();
+        }
+        let file = |path: &PathBuf| DroppedFile {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        state.queue_file(file(&kept));
+        state.queue_temporary_file(file(&scratch));
+
+        // Still queued: only the kept file would be reloaded
+        let kept_str = kept.to_string_lossy().to_string();
+        assert_eq!(
+            state.persisted_file_paths(),
+            (Vec::new(), vec![kept_str.clone()])
+        );
+
+        state.start_parallel_load();
+        let deadline = instant::Instant::now() + Duration::from_secs(10);
+        while state.file_loader.loaded_files.len() < 2 && instant::Instant::now() < deadline {
+            state.process_parallel_results();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        std::fs::remove_file(&kept).unwrap();
+        std::fs::remove_file(&scratch).unwrap();
+        assert_eq!(state.file_loader.loaded_files.len(), 2);
+
+        // Both are drawn
+        let world = geo::Rect::new(
+            geo::Coord {
+                x: -20_037_508.0,
+                y: -20_037_508.0,
+            },
+            geo::Coord {
+                x: 20_037_508.0,
+                y: 20_037_508.0,
+            },
+        );
+        let drawn: HashSet<usize> = state
+            .route_collection
+            .try_read()
+            .unwrap()
+            .query_visible(world, (1024.0, 768.0))
+            .iter()
+            .map(|segment| segment.route_index)
+            .collect();
+        assert_eq!(drawn.len(), 2);
+
+        assert!(state.is_temporary(&scratch));
+        assert_eq!(
+            state.persisted_file_paths(),
+            (vec![kept_str.clone()], Vec::new())
+        );
+
+        // Keeping it after all puts it back in the list
+        state.set_temporary(&scratch, false);
+        assert_eq!(
+            state.persisted_file_paths().0,
+            vec![kept_str, scratch.to_string_lossy().to_string()]
+        );
+    }
+
+    #[test]
+    fn saving_mid_load_keeps_every_file_of_the_batch() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let file = |name: &str| DroppedFile {
+            name: name.to_string(),
+            path: Some(PathBuf::from(format!("/tracks/{}.gpx", name))),
+            ..Default::default()
+        };
+        state.file_loader.pending_files.push(file("pending"));
+        // Handed to a worker, which may not have read it yet
+        state.file_loader.ingestor.submit([file("queued")]);
+        // Parsed by a worker, but not indexed yet
+        state
+            .file_loader
+            .ingestor
+            .send_result(PathBuf::from("/tracks/parsed.gpx"), Ok(small_gpx(45.0)));
+        state.memory_warning = Some(MemoryWarning {
+            files: vec![file("held")],
+            file_sizes: vec![1],
+            estimated_bytes: 1,
+            loaded_bytes: 0,
+        });
+
+        // Results waiting on the channel don't make the save miss files
+        let (loaded, pending) = state.persisted_file_paths();
+        assert!(loaded.is_empty());
+        assert_eq!(
+            pending,
+            ["pending", "queued", "parsed", "held"]
+                .map(|name| format!("/tracks/{}.gpx", name))
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn large_drops_are_loaded_through_a_bounded_window() {
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let gpx_bytes = |lat: f64| {
+            let mut bytes = Vec::new();
+            gpx::write(
+                &gpx::Gpx {
+                    version: gpx::GpxVersion::Gpx11,
+                    ..small_gpx(lat)
+                },
+                &mut bytes,
+            )
+            .unwrap();
+            bytes
+        };
+        let dropped = |i: usize| DroppedFile {
+            name: format!("drop{}.gpx", i),
+            bytes: Some(gpx_bytes(i as f64 * 0.1).into()),
+            ..Default::default()
+        };
+        let wait_for =
+            |state: &mut AppState, loaded: usize, mut check: Box<dyn FnMut(&AppState)>| {
+                let deadline = instant::Instant::now() + Duration::from_secs(30);
+                while state.file_loader.loaded_files.len() < loaded
+                    && instant::Instant::now() < deadline
+                {
+                    state.process_parallel_results();
+                    check(state);
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                state.process_parallel_results();
+            };
+
+        // A file from an earlier batch doesn't count towards the drop's progress
+        state.queue_file(dropped(500));
+        state.start_parallel_load();
+        wait_for(&mut state, 1, Box::new(|_| {}));
+        assert_eq!(state.file_loader.loaded_files.len(), 1);
+        assert!(!state.is_parallel_loading());
+
+        // The same queue the drop handler fills
+        let files = 200;
+        for i in 0..files {
+            state.queue_file(dropped(i));
+        }
+        state.start_parallel_load();
+        let window = state.file_loader.ingestor.window();
+        let in_flight = state.file_loader.ingestor.in_flight().len();
+        assert!(in_flight <= window);
+        assert_eq!(
+            state.file_loader.ingestor.queued().count(),
+            files - in_flight
+        );
+        assert_eq!(state.loading_progress(), 0.0);
+        assert_eq!(state.loading_status(), format!("0/{}", files));
+
+        let mut last_progress = 0.0;
+        wait_for(
+            &mut state,
+            files + 1,
+            Box::new(move |state| {
+                assert!(state.file_loader.ingestor.in_flight().len() <= window);
+                // Parsed results never pile up beyond the window either
+                assert!(state.file_loader.ingestor.ready_count() <= window);
+                let progress = state.loading_progress();
+                assert!(progress >= last_progress || progress == 0.0);
+                last_progress = progress;
+            }),
+        );
+        assert_eq!(state.file_loader.loaded_files.len(), files + 1);
+        assert_eq!(state.file_loader.ingestor.unfinished_count(), 0);
+        assert!(!state.file_loader.is_busy());
+        assert_eq!(state.stats.route_count, files + 1);
+    }
+}
@@ -187,6 +187,8 @@ mod file_storage {
     /// Implementation notes:
     /// - On init, file is read into memory (HashMap).
     /// - Mutations update memory and flush the file back to disk synchronously.
+    ///   A mutation whose flush fails is undone in memory, so memory never
+    ///   holds anything the file doesn't.
     pub struct FileStorage {
         /// Path to the backing JSON file.
         path: PathBuf,
@@ -195,29 +197,39 @@ mod file_storage {
     }
 
     impl FileStorage {
-        /// Determine a good default storage file path for the current user.
-        /// Uses environment variables when available:
-        /// - On Windows: %APPDATA%/LargeTrackViewer/storage.json
-        /// - Else: $HOME/.config/large-track-viewer/storage.json
-        fn default_storage_path() -> PathBuf {
-            // Prefer APPDATA on Windows
+        /// Storage file in the platform's config directory: `~/.config` on
+        /// Linux, `~/Library/Application Support` on macOS and the roaming
+        /// `%APPDATA%` on Windows
+        pub(super) fn project_storage_path() -> Option<PathBuf> {
+            directories::ProjectDirs::from("", "", "large-track-viewer")
+                .map(|dirs| dirs.config_dir().join("storage.json"))
+        }
+
+        /// Where storage was kept before [`Self::project_storage_path`]:
+        /// `%APPDATA%/LargeTrackViewer` on Windows, `$HOME/.config/large-track-viewer` elsewhere
+        fn legacy_storage_path() -> Option<PathBuf> {
             if cfg!(windows)
                 && let Ok(appdata) = std::env::var("APPDATA")
             {
-                return Path::new(&appdata)
-                    .join("LargeTrackViewer")
-                    .join("storage.json");
+                return Some(
+                    Path::new(&appdata)
+                        .join("LargeTrackViewer")
+                        .join("storage.json"),
+                );
             }
-
-            if let Ok(home) = std::env::var("HOME") {
-                return Path::new(&home)
+            std::env::var("HOME").ok().map(|home| {
+                Path::new(&home)
                     .join(".config")
                     .join("large-track-viewer")
-                    .join("storage.json");
-            }
+                    .join("storage.json")
+            })
+        }
 
-            // Fallback to current directory
-            Path::new(".").join("large-track-viewer-storage.json")
+        /// Determine a good default storage file path for the current user
+        fn default_storage_path() -> PathBuf {
+            resolve_storage_path(Self::project_storage_path(), Self::legacy_storage_path())
+                // Fallback to current directory
+                .unwrap_or_else(|| Path::new(".").join("large-track-viewer-storage.json"))
         }
 
         pub fn new_with_path(path: Option<PathBuf>) -> Result<Self, StorageError> {
@@ -279,8 +291,15 @@ mod file_storage {
                 .inner
                 .lock()
                 .map_err(|e| StorageError::Platform(format!("mutex poisoned: {:?}", e)))?;
-            guard.insert(key.to_string(), value.to_string());
-            self.flush_locked(&guard)
+            let previous = guard.insert(key.to_string(), value.to_string());
+            self.flush_locked(&guard).inspect_err(|_| match previous {
+                Some(previous) => {
+                    guard.insert(key.to_string(), previous);
+                }
+                None => {
+                    guard.remove(key);
+                }
+            })
         }
 
         fn get_string(&self, key: &str) -> StorageResult<Option<String>> {
@@ -296,8 +315,12 @@ mod file_storage {
                 .inner
                 .lock()
                 .map_err(|e| StorageError::Platform(format!("mutex poisoned: {:?}", e)))?;
-            guard.remove(key);
-            self.flush_locked(&guard)
+            let Some(previous) = guard.remove(key) else {
+                return Ok(());
+            };
+            self.flush_locked(&guard).inspect_err(|_| {
+                guard.insert(key.to_string(), previous);
+            })
         }
 
         fn keys(&self) -> StorageResult<Vec<String>> {
@@ -312,6 +335,51 @@ mod file_storage {
     pub fn default_backend() -> Result<Box<dyn StorageBackend>, StorageError> {
         Ok(Box::new(FileStorage::new_with_path(None)?))
     }
+
+    /// Pick the storage file: `preferred`, after moving a file left at
+    /// `legacy` by an older version there
+    ///
+    /// When the legacy file can't be copied over it keeps being used where
+    /// it is, so settings are never lost to a failed migration. The legacy
+    /// file itself is left in place for older versions.
+    pub(super) fn resolve_storage_path(
+        preferred: Option<PathBuf>,
+        legacy: Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        let legacy = legacy.filter(|legacy| legacy.is_file());
+        let Some(preferred) = preferred else {
+            return legacy;
+        };
+        let Some(legacy) = legacy else {
+            return Some(preferred);
+        };
+        if preferred == legacy || preferred.exists() {
+            return Some(preferred);
+        }
+        let copied = preferred
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(&legacy, &preferred));
+        match copied {
+            Ok(_) => {
+                tracing::info!(
+                    "Moved storage from {} to {}",
+                    legacy.display(),
+                    preferred.display()
+                );
+                Some(preferred)
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not move storage from {} to {}, still using it there: {}",
+                    legacy.display(),
+                    preferred.display(),
+                    e
+                );
+                Some(legacy)
+            }
+        }
+    }
 }
 
 //
@@ -322,3 +390,151 @@ pub use web_storage::default_backend as default_storage_backend;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use file_storage::default_backend as default_storage_backend;
+
+/// Open the default backend for the current platform, hiding the fact that the
+/// web backend is infallible while the native one may fail to initialize.
+///
+/// Returns `None` (after logging) when no backend is available, so callers can
+/// treat platform persistence as best-effort.
+pub fn platform_backend() -> Option<Box<dyn StorageBackend>> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Some(default_storage_backend())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        match default_storage_backend() {
+            Ok(backend) => Some(backend),
+            Err(e) => {
+                tracing::warn!("Platform storage backend not available: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Backend keeping everything in memory, for tests; clones share their entries
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    entries: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>,
+    writes: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    /// How many values were stored so far
+    pub fn writes(&self) -> usize {
+        self.writes.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+impl StorageBackend for MemoryBackend {
+    fn set_string(&self, key: &str, value: &str) -> StorageResult<()> {
+        self.writes
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn get_string(&self, key: &str) -> StorageResult<Option<String>> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    fn remove(&self, key: &str) -> StorageResult<()> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::file_storage::{FileStorage, resolve_storage_path};
+    use super::*;
+
+    #[test]
+    fn storage_lives_in_the_platform_config_directory() {
+        let Some(path) = FileStorage::project_storage_path() else {
+            // No home directory to resolve against
+            return;
+        };
+        let base = directories::BaseDirs::new().unwrap();
+        assert!(path.starts_with(base.config_dir()), "{}", path.display());
+        #[cfg(target_os = "macos")]
+        assert!(base.config_dir().ends_with("Library/Application Support"));
+        assert!(path.ends_with("storage.json"));
+    }
+
+    #[test]
+    fn settings_at_the_old_location_are_migrated() {
+        let dir = std::env::temp_dir().join(format!("ltv-migrate-{}", std::process::id()));
+        let legacy = dir.join("old").join("storage.json");
+        let preferred = dir.join("new").join("storage.json");
+        std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        std::fs::write(&legacy, r#"{"persisted_settings":"{}"}"#).unwrap();
+
+        let resolved = resolve_storage_path(Some(preferred.clone()), Some(legacy.clone()));
+        assert_eq!(resolved.as_ref(), Some(&preferred));
+        let storage = FileStorage::new_with_path(resolved).unwrap();
+        assert_eq!(
+            storage.get_string("persisted_settings").unwrap().as_deref(),
+            Some("{}")
+        );
+
+        // Once migrated, the new file wins over the old one
+        storage.set_string("persisted_settings", "[]").unwrap();
+        let resolved = resolve_storage_path(Some(preferred.clone()), Some(legacy.clone()));
+        let storage = FileStorage::new_with_path(resolved).unwrap();
+        assert_eq!(
+            storage.get_string("persisted_settings").unwrap().as_deref(),
+            Some("[]")
+        );
+        // Without a config directory the old file is still read where it is
+        assert_eq!(
+            resolve_storage_path(None, Some(legacy.clone())),
+            Some(legacy)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write_is_reported_and_undone_in_memory() {
+        let path = std::env::temp_dir().join(format!("ltv-storage-{}.json", std::process::id()));
+        let storage = FileStorage::new_with_path(Some(path.clone())).unwrap();
+        storage.set_string("kept", "1").unwrap();
+        storage.set_string("removed", "x").unwrap();
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+
+        // A directory in place of the file makes every write fail
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+        assert!(matches!(
+            storage.set_string("kept", "2"),
+            Err(StorageError::Io(_))
+        ));
+        assert!(storage.set_string("new", "3").is_err());
+        assert!(storage.remove("removed").is_err());
+        assert_eq!(storage.get_string("kept").unwrap().as_deref(), Some("1"));
+        assert_eq!(storage.get_string("new").unwrap(), None);
+        assert_eq!(storage.get_string("removed").unwrap().as_deref(), Some("x"));
+
+        // Once writable again, the failed changes don't reach the file either
+        std::fs::remove_dir(&path).unwrap();
+        std::fs::write(&path, on_disk).unwrap();
+        storage.set_string("other", "4").unwrap();
+        let reopened = FileStorage::new_with_path(Some(path.clone())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.get_string("kept").unwrap().as_deref(), Some("1"));
+        assert_eq!(reopened.get_string("new").unwrap(), None);
+        assert_eq!(
+            reopened.get_string("removed").unwrap().as_deref(),
+            Some("x")
+        );
+        assert_eq!(reopened.get_string("other").unwrap().as_deref(), Some("4"));
+    }
+}
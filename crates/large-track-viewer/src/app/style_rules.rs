@@ -0,0 +1,171 @@
+//! Line styles for categories of tracks, by file name or folder
+//!
+//! Planned routes next to recorded ones, hikes next to rides: a rule gives
+//! every file whose path matches its pattern a dash pattern and optionally a
+//! color. Rules are tried in order and the first match wins; files no rule
+//! matches keep the default solid line in their own color. A color picked
+//! for a single file still beats the one of its rule.
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// How a track line is broken up
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DashPattern {
+    /// One continuous line
+    #[default]
+    Solid,
+    /// Long dashes, e.g. for planned routes
+    Dashed,
+    /// Short dashes about as long as the line is wide
+    Dotted,
+}
+
+impl DashPattern {
+    pub fn all() -> &'static [Self] {
+        &[Self::Solid, Self::Dashed, Self::Dotted]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Solid => "Solid",
+            Self::Dashed => "Dashed",
+            Self::Dotted => "Dotted",
+        }
+    }
+
+    /// Dash and gap lengths for a line `width` points wide, `None` when solid
+    ///
+    /// Both grow with the width, so a wide line doesn't turn into a row of squares.
+    pub fn lengths(self, width: f32) -> Option<(f32, f32)> {
+        match self {
+            Self::Solid => None,
+            Self::Dashed => Some(((width * 4.0).max(6.0), (width * 2.5).max(4.0))),
+            Self::Dotted => Some((width.max(1.0), (width * 1.5).max(3.0))),
+        }
+    }
+}
+
+/// Files matching `pattern` are drawn with `dash`, and in `color` if set
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct StyleRule {
+    /// Text found anywhere in the file's path, or a glob (`*` and `?`) matched
+    /// against its file name or whole path; letter case is ignored
+    pub pattern: String,
+    #[serde(default)]
+    pub dash: DashPattern,
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+}
+
+impl StyleRule {
+    /// Whether the file at `path` belongs to this rule; an empty pattern matches nothing
+    pub fn matches(&self, path: &Path) -> bool {
+        let pattern = self.pattern.trim().to_lowercase();
+        if pattern.is_empty() {
+            return false;
+        }
+        let full = path.to_string_lossy().to_lowercase();
+        if !pattern.contains(['*', '?']) {
+            return full.contains(&pattern);
+        }
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_lowercase();
+        glob_match(&pattern, &name) || glob_match(&pattern, &full)
+    }
+
+    pub fn color(&self) -> Option<Color32> {
+        self.color.map(|[r, g, b]| Color32::from_rgb(r, g, b))
+    }
+}
+
+/// The first of `rules` that `path` matches
+pub fn first_match<'a>(rules: &'a [StyleRule], path: &Path) -> Option<&'a StyleRule> {
+    rules.iter().find(|rule| rule.matches(path))
+}
+
+/// Whether all of `text` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it was tried against, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_matching_rule_styles_the_route() {
+        let rules = vec![
+            StyleRule {
+                pattern: "planned-*".to_string(),
+                dash: DashPattern::Dashed,
+                color: Some([0, 0, 255]),
+            },
+            StyleRule {
+                pattern: "/hiking/".to_string(),
+                dash: DashPattern::Dotted,
+                color: None,
+            },
+            // Never reached for planned files: the first rule wins
+            StyleRule {
+                pattern: "*.gpx".to_string(),
+                dash: DashPattern::Solid,
+                color: Some([255, 0, 0]),
+            },
+        ];
+        let planned = first_match(&rules, Path::new("/tracks/hiking/Planned-Teide.gpx")).unwrap();
+        assert_eq!(planned.dash, DashPattern::Dashed);
+        assert_eq!(planned.color(), Some(Color32::from_rgb(0, 0, 255)));
+
+        let hike = first_match(&rules, Path::new("/tracks/hiking/teide.gpx")).unwrap();
+        assert_eq!(hike.dash, DashPattern::Dotted);
+        assert_eq!(
+            first_match(&rules, Path::new("/tracks/ride.gpx")).map(|rule| rule.dash),
+            Some(DashPattern::Solid)
+        );
+        // No rule matches: the default style
+        assert!(first_match(&rules, Path::new("/tracks/ride.fit")).is_none());
+        assert!(first_match(&rules[..1], Path::new("/tracks/not-planned.gpx")).is_none());
+    }
+
+    #[test]
+    fn globs_take_any_run_of_characters() {
+        assert!(glob_match("planned-*", "planned-"));
+        assert!(glob_match("*-2024-??.gpx", "ride-2024-05.gpx"));
+        assert!(glob_match("a*b*c", "axxbyybzc"));
+        assert!(!glob_match("a*b*c", "axxbyyb"));
+        assert!(!glob_match("?", ""));
+        assert!(!StyleRule::default().matches(Path::new("anything.gpx")));
+    }
+}
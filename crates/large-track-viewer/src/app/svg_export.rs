@@ -0,0 +1,171 @@
+//! Exporting the shown tracks and their legend as SVG
+//!
+//! For documents and print: every route on the map becomes one `<polyline>`
+//! in the color it is drawn with, projected to Web Mercator and fit to the
+//! routes' combined extent, with a legend of names and colors below. Unlike a
+//! screenshot it stays sharp at any size, and it leaves the tiles out.
+
+use egui::Color32;
+use geo::{LineString, Simplify};
+use std::fmt::Write;
+
+/// Width of the drawing, in SVG user units
+const WIDTH: f64 = 800.0;
+/// Space around the tracks and the legend
+const MARGIN: f64 = 16.0;
+/// Height of one legend entry
+const LEGEND_ROW: f64 = 20.0;
+/// Points closer than this to the simplified line are left out, in user units
+const SIMPLIFY_TOLERANCE: f64 = 0.25;
+
+/// One route to draw
+#[derive(Clone, Debug)]
+pub struct SvgRoute {
+    pub name: String,
+    pub color: Color32,
+    /// The route's points in Web Mercator, its segments one after the other
+    pub points: Vec<geo::Point<f64>>,
+}
+
+/// An SVG of `routes` drawn `line_width` wide, north up, with their legend
+/// in the order given
+pub fn to_svg(routes: &[SvgRoute], line_width: f32) -> String {
+    let points = || routes.iter().flat_map(|route| route.points.iter());
+    let (min_x, max_x) = range(points().map(|point| point.x()));
+    let (min_y, max_y) = range(points().map(|point| point.y()));
+    let inner = WIDTH - 2.0 * MARGIN;
+    let scale = inner / (max_x - min_x).max(max_y - min_y).max(f64::EPSILON);
+    let map_height = ((max_y - min_y) * scale).max(if routes.is_empty() { 0.0 } else { 1.0 });
+    let legend_top = MARGIN + map_height + MARGIN;
+    let height = legend_top + routes.len() as f64 * LEGEND_ROW + MARGIN;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h:.0}" viewBox="0 0 {w} {h:.0}">"#,
+        w = WIDTH,
+        h = height,
+    );
+    svg.push_str("<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+    for route in routes {
+        // Mercator y grows northwards, SVG y downwards
+        let line: LineString<f64> = route
+            .points
+            .iter()
+            .map(|point| {
+                (
+                    MARGIN + (point.x() - min_x) * scale,
+                    MARGIN + (max_y - point.y()) * scale,
+                )
+            })
+            .collect();
+        let line = line.simplify(SIMPLIFY_TOLERANCE);
+        let coordinates: Vec<String> = line
+            .coords()
+            .map(|c| format!("{:.1},{:.1}", c.x, c.y))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" stroke-linecap="round"/>"#,
+            coordinates.join(" "),
+            hex(route.color),
+            line_width,
+        );
+    }
+    for (row, route) in routes.iter().enumerate() {
+        let y = legend_top + row as f64 * LEGEND_ROW;
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{:.1}" width="16" height="4" fill="{}"/>"#,
+            MARGIN,
+            y + 6.0,
+            hex(route.color),
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{:.1}" font-family="sans-serif" font-size="12">{}</text>"#,
+            MARGIN + 24.0,
+            y + 12.0,
+            escape(&route.name),
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Smallest and largest of `values`, or (0, 0) for none
+fn range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values
+        .fold(None, |range: Option<(f64, f64)>, value| {
+            Some(range.map_or((value, value), |(min, max)| {
+                (min.min(value), max.max(value))
+            }))
+        })
+        .unwrap_or((0.0, 0.0))
+}
+
+/// "#rrggbb"
+fn hex(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// `text` with the characters XML gives a meaning escaped
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(name: &str, color: Color32, points: &[(f64, f64)]) -> SvgRoute {
+        SvgRoute {
+            name: name.to_string(),
+            color,
+            points: points.iter().map(|&(x, y)| geo::Point::new(x, y)).collect(),
+        }
+    }
+
+    #[test]
+    fn each_route_is_one_polyline_in_its_color_with_a_legend_entry() {
+        let routes = [
+            route(
+                "Morning <ride>",
+                Color32::from_rgb(0xd9, 0x3b, 0x2c),
+                &[(0.0, 0.0), (500.0, 250.0), (1000.0, 0.0)],
+            ),
+            route(
+                "Lap & back",
+                Color32::from_rgb(0x1f, 0x77, 0xb4),
+                &[(0.0, 500.0), (1000.0, 500.0)],
+            ),
+        ];
+        let svg = to_svg(&routes, 2.0);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let polylines: Vec<&str> = svg
+            .lines()
+            .filter(|line| line.starts_with("<polyline"))
+            .collect();
+        assert_eq!(polylines.len(), 2);
+        assert!(polylines[0].contains(r##"stroke="#d93b2c""##));
+        assert!(polylines[1].contains(r##"stroke="#1f77b4""##));
+        // Fit to the width, north up: the southernmost points are at the bottom
+        assert!(polylines[0].contains(r#"points="16.0,400.0 400.0,208.0 784.0,400.0""#));
+        assert!(polylines[1].contains(r#"points="16.0,16.0 784.0,16.0""#));
+
+        // The legend lists both, with names escaped
+        assert!(svg.contains(">Morning &lt;ride&gt;</text>"));
+        assert!(svg.contains(">Lap &amp; back</text>"));
+        assert_eq!(svg.matches(r##"fill="#1f77b4""##).count(), 1);
+
+        // Nothing to draw is still a valid, empty drawing
+        let empty = to_svg(&[], 2.0);
+        assert!(!empty.contains("<polyline"));
+        assert!(empty.trim_end().ends_with("</svg>"));
+    }
+}
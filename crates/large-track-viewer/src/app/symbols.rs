@@ -0,0 +1,136 @@
+//! Icons for GPX waypoint symbols
+//!
+//! Waypoints name their symbol in `<sym>` as free text. GPS units and most
+//! mapping apps use Garmin's names ("Summit", "Water Source", ...), so the
+//! common ones get a glyph and color of their own; anything else is drawn as
+//! a generic pin. [`SYMBOL_ICONS`] is also what the Settings tab lists, so
+//! the map and its explanation can't drift apart.
+
+use egui::Color32;
+
+/// How waypoints with one kind of symbol are drawn
+#[derive(Debug, PartialEq, Eq)]
+pub struct SymbolIcon {
+    /// Drawn in white on the marker
+    pub glyph: &'static str,
+    /// Marker fill
+    pub color: Color32,
+    /// Shown in the Settings tab
+    pub label: &'static str,
+    /// `<sym>` values drawn with this icon, lowercase
+    names: &'static [&'static str],
+}
+
+/// Icons of the recognized symbols
+pub const SYMBOL_ICONS: &[SymbolIcon] = &[
+    SymbolIcon {
+        glyph: "⛰",
+        color: Color32::from_rgb(121, 85, 72),
+        label: "Summit",
+        names: &["summit", "peak", "mountain"],
+    },
+    SymbolIcon {
+        glyph: "💧",
+        color: Color32::from_rgb(30, 136, 229),
+        label: "Water",
+        names: &["water source", "drinking water", "water", "spring"],
+    },
+    SymbolIcon {
+        glyph: "⛺",
+        color: Color32::from_rgb(67, 160, 71),
+        label: "Campground",
+        names: &["campground", "camping", "campsite"],
+    },
+    SymbolIcon {
+        glyph: "🏠",
+        color: Color32::from_rgb(142, 36, 170),
+        label: "Lodging",
+        names: &["lodging", "lodge", "hut", "shelter"],
+    },
+    SymbolIcon {
+        glyph: "🍴",
+        color: Color32::from_rgb(251, 140, 0),
+        label: "Food",
+        names: &["restaurant", "food", "fast food", "cafe"],
+    },
+    SymbolIcon {
+        glyph: "P",
+        color: Color32::from_rgb(57, 73, 171),
+        label: "Parking",
+        names: &["parking area", "parking"],
+    },
+    SymbolIcon {
+        glyph: "WC",
+        color: Color32::from_rgb(0, 137, 123),
+        label: "Restroom",
+        names: &["restroom", "toilet", "toilets"],
+    },
+    SymbolIcon {
+        glyph: "i",
+        color: Color32::from_rgb(3, 155, 229),
+        label: "Information",
+        names: &["information", "info"],
+    },
+    SymbolIcon {
+        glyph: "⚠",
+        color: Color32::from_rgb(229, 57, 53),
+        label: "Danger",
+        names: &["danger area", "danger", "skull and crossbones"],
+    },
+    SymbolIcon {
+        glyph: "⚑",
+        color: Color32::from_rgb(216, 27, 96),
+        label: "Flag",
+        names: &["flag", "flag, blue", "flag, green", "flag, red"],
+    },
+];
+
+/// Icon of waypoints without a symbol or with one not in [`SYMBOL_ICONS`]
+pub const FALLBACK_ICON: SymbolIcon = SymbolIcon {
+    glyph: "📍",
+    color: Color32::from_rgb(97, 97, 97),
+    label: "Other",
+    names: &[],
+};
+
+/// Icon for a waypoint's `<sym>`, ignoring case and surrounding spaces
+pub fn icon_for(symbol: Option<&str>) -> &'static SymbolIcon {
+    let Some(symbol) = symbol.map(|symbol| symbol.trim().to_lowercase()) else {
+        return &FALLBACK_ICON;
+    };
+    SYMBOL_ICONS
+        .iter()
+        .find(|icon| icon.names.contains(&symbol.as_str()))
+        .unwrap_or(&FALLBACK_ICON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoint_symbol(sym: &str) -> Option<String> {
+        let gpx = gpx::read(
+            format!(
+                r#"<?xml version="1.0"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <wpt lat="45.83" lon="6.86"><name>Top</name><sym>{}</sym></wpt>
+</gpx>"#,
+                sym
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        gpx.waypoints[0].symbol.clone()
+    }
+
+    #[test]
+    fn known_symbols_get_their_icon_and_others_the_pin() {
+        let summit = icon_for(waypoint_symbol("Summit").as_deref());
+        assert_eq!(summit.label, "Summit");
+        assert_eq!(icon_for(Some(" water source ")).label, "Water");
+
+        let unknown = icon_for(waypoint_symbol("Geocache Found").as_deref());
+        assert_eq!(unknown, &FALLBACK_ICON);
+        assert_eq!(icon_for(None), &FALLBACK_ICON);
+    }
+}
@@ -0,0 +1,197 @@
+//! Procedurally generated tracks for demos and performance testing
+//!
+//! Each route is a random walk with GPS-like point spacing, optionally with
+//! elevation and timestamps. Routes only depend on the seed and their index
+//! within the batch, so the same parameters always give the same dataset
+//! and asking for more routes keeps the first ones unchanged.
+
+/// Path prefix identifying generated routes in the loaded file list (never reloadable from disk)
+pub const SYNTHETIC_PATH_PREFIX: &str = "synthetic://";
+
+/// Meters per degree of latitude
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Unix time of the first point of the first route (2024-01-01 00:00 UTC)
+const START_TIME: i64 = 1_704_067_200;
+
+/// What to generate
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntheticParams {
+    pub route_count: usize,
+    pub points_per_route: usize,
+    /// Region the routes start and stay in, in degrees
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+    pub seed: u64,
+    pub timestamps: bool,
+    pub elevation: bool,
+}
+
+impl Default for SyntheticParams {
+    fn default() -> Self {
+        // Roughly the Alps
+        Self {
+            route_count: 20,
+            points_per_route: 5_000,
+            min_lat: 45.0,
+            max_lat: 48.0,
+            min_lon: 5.0,
+            max_lon: 11.0,
+            seed: 1,
+            timestamps: true,
+            elevation: true,
+        }
+    }
+}
+
+/// Generate `params.route_count` routes, one track of one segment each
+pub fn generate(params: &SyntheticParams) -> Vec<gpx::Gpx> {
+    (0..params.route_count)
+        .map(|index| random_walk(params, index))
+        .collect()
+}
+
+/// Route `index` of the batch described by `params`
+///
+/// Points are 2 to 8 m apart, taken every second or two, and the heading
+/// drifts gradually; the walk turns back when it reaches the region's edge.
+fn random_walk(params: &SyntheticParams, index: usize) -> gpx::Gpx {
+    let mut rng = XorShift::new(params.seed ^ (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    let (min_lat, max_lat) = ordered(params.min_lat, params.max_lat);
+    let (min_lon, max_lon) = ordered(params.min_lon, params.max_lon);
+
+    let mut lat = min_lat + rng.next_f64() * (max_lat - min_lat);
+    let mut lon = min_lon + rng.next_f64() * (max_lon - min_lon);
+    let mut heading = rng.next_f64() * std::f64::consts::TAU;
+    let mut elevation = 200.0 + rng.next_f64() * 1800.0;
+    let mut climb: f64 = 0.0;
+    let mut time = START_TIME + index as i64 * 3600;
+
+    let mut segment = gpx::TrackSegment::new();
+    for _ in 0..params.points_per_route {
+        let mut point = gpx::Waypoint::new(geo::Point::new(lon, lat));
+        if params.elevation {
+            point.elevation = Some(elevation);
+        }
+        if params.timestamps {
+            point.time = time::OffsetDateTime::from_unix_timestamp(time)
+                .ok()
+                .map(gpx::Time::from);
+        }
+        segment.points.push(point);
+
+        heading += (rng.next_f64() - 0.5) * 0.3;
+        let step = 2.0 + rng.next_f64() * 6.0;
+        let next_lat = lat + heading.sin() * step / METERS_PER_DEGREE;
+        let next_lon =
+            lon + heading.cos() * step / (METERS_PER_DEGREE * lat.to_radians().cos().max(0.01));
+        if next_lat < min_lat || next_lat > max_lat {
+            heading = -heading;
+        } else {
+            lat = next_lat;
+        }
+        if next_lon < min_lon || next_lon > max_lon {
+            heading = std::f64::consts::PI - heading;
+        } else {
+            lon = next_lon;
+        }
+
+        climb = (climb + (rng.next_f64() - 0.5) * 0.02).clamp(-0.15, 0.15);
+        elevation = (elevation + climb * step).clamp(0.0, 4800.0);
+        time += 1 + (rng.next_f64() * 2.0) as i64;
+    }
+
+    let mut track = gpx::Track::new();
+    track.name = Some(format!(
+        "Synthetic route {} (seed {})",
+        index + 1,
+        params.seed
+    ));
+    track.segments.push(segment);
+    gpx::Gpx {
+        version: gpx::GpxVersion::Gpx11,
+        creator: Some("Large Track Viewer".to_string()),
+        tracks: vec![track],
+        ..Default::default()
+    }
+}
+
+fn ordered(a: f64, b: f64) -> (f64, f64) {
+    if a <= b { (a, b) } else { (b, a) }
+}
+
+/// Small deterministic generator, so a seed always gives the same tracks
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // One splitmix64 round, so similar seeds don't start similar sequences
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        // Xorshift never leaves zero
+        Self((z ^ (z >> 31)).max(1))
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_routes_within_the_region() {
+        let params = SyntheticParams {
+            route_count: 3,
+            points_per_route: 500,
+            ..Default::default()
+        };
+        let routes = generate(&params);
+        let coords = |routes: &[gpx::Gpx]| -> Vec<Vec<(f64, f64)>> {
+            routes
+                .iter()
+                .map(|gpx| {
+                    gpx.tracks[0].segments[0]
+                        .points
+                        .iter()
+                        .map(|point| (point.point().x(), point.point().y()))
+                        .collect()
+                })
+                .collect()
+        };
+        assert_eq!(coords(&routes), coords(&generate(&params)));
+        // More routes keep the first ones
+        let more = generate(&SyntheticParams {
+            route_count: 5,
+            ..params.clone()
+        });
+        assert_eq!(coords(&routes), coords(&more[..3]));
+        let reseeded = generate(&SyntheticParams {
+            seed: 2,
+            ..params.clone()
+        });
+        assert_ne!(coords(&routes), coords(&reseeded));
+
+        let points = &routes[0].tracks[0].segments[0].points;
+        assert_eq!(points.len(), 500);
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0].point(), pair[1].point());
+            assert!((params.min_lat..=params.max_lat).contains(&b.y()));
+            assert!((params.min_lon..=params.max_lon).contains(&b.x()));
+            // Within GPS-like spacing, a few meters apart
+            let meters = (a.y() - b.y()).hypot((a.x() - b.x()) * a.y().to_radians().cos())
+                * METERS_PER_DEGREE;
+            assert!(meters <= 8.5, "{} m", meters);
+            assert!(pair[0].time.is_some() && pair[0].elevation.is_some());
+        }
+    }
+}
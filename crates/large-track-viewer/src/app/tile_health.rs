@@ -0,0 +1,183 @@
+//! Background check that the shown tile provider actually serves tiles
+//!
+//! A provider that is down otherwise leaves the map grey without a word.
+//! Whenever a provider is shown (at startup or after switching), one test tile
+//! is requested in the background and retried once. The outcome arrives on a
+//! channel drained once per frame, like live location updates, so rendering
+//! never waits on the network.
+//!
+//! Native only: in the browser the check is skipped and reported as healthy.
+
+use crate::app::basemap;
+use crate::app::state::TilesProvider;
+use eframe_entrypoints::async_runtime;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Wait before retrying a test tile that failed
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Give up on a test tile request after this long
+#[cfg(not(target_arch = "wasm32"))]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of checking one provider: an error message if it is not serving tiles
+pub type HealthReport = (TilesProvider, Result<(), String>);
+
+/// Checks the shown provider once each time it changes
+pub struct TileHealth {
+    /// Provider the last check was started for
+    checked: Option<TilesProvider>,
+    /// Checks started whose report hasn't been received yet
+    in_flight: usize,
+    sender: mpsc::Sender<HealthReport>,
+    receiver: mpsc::Receiver<HealthReport>,
+}
+
+impl TileHealth {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            checked: None,
+            in_flight: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Start a check if `provider` isn't the last one checked; call every frame
+    pub fn ensure_checked(&mut self, provider: TilesProvider) {
+        if self.checked == Some(provider) {
+            return;
+        }
+        self.checked = Some(provider);
+        let Some(url) = basemap::test_tile_url(provider) else {
+            return;
+        };
+        self.in_flight += 1;
+        let sender = self.sender.clone();
+        async_runtime::spawn(async move {
+            let result = probe(&url, RETRY_DELAY).await;
+            // The receiver lives as long as the app
+            let _ = sender.send((provider, result));
+        });
+    }
+
+    /// Whether a check is still running (the caller keeps repainting to receive it)
+    pub fn is_checking(&self) -> bool {
+        self.in_flight > 0
+    }
+
+    /// Reports that arrived since the last call
+    pub fn poll(&mut self) -> Vec<HealthReport> {
+        let reports: Vec<HealthReport> = self.receiver.try_iter().collect();
+        self.in_flight = self.in_flight.saturating_sub(reports.len());
+        reports
+    }
+}
+
+/// Request `url`, retrying once after `retry_delay`; network and HTTP errors both fail
+#[cfg(not(target_arch = "wasm32"))]
+async fn probe(url: &str, retry_delay: Duration) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        // Tile servers (OpenStreetMap in particular) reject requests without one
+        .user_agent(concat!("large-track-viewer/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let fetch = || async {
+        client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    };
+    match fetch().await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::debug!("Test tile {} failed, retrying: {}", url, e);
+            async_runtime::sleep(retry_delay).await;
+            fetch().await
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn probe(_url: &str, _retry_delay: Duration) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Local tile server answering the first `failures` requests with 503 and
+    /// the rest with 200; counts the requests it received
+    fn flaky_server(failures: usize) -> (String, Arc<AtomicUsize>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/0/0/0.png", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                }
+                let status = if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                    "503 Service Unavailable"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn one_failure_is_retried() {
+        let (url, requests) = flaky_server(1);
+        assert_eq!(block_on(probe(&url, Duration::ZERO)), Ok(()));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn failing_twice_reports_the_error() {
+        let (url, requests) = flaky_server(usize::MAX);
+        let error = block_on(probe(&url, Duration::ZERO)).unwrap_err();
+        assert!(error.contains("503"), "{}", error);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn tracks_only_mode_is_never_checked() {
+        let mut health = TileHealth::new();
+        health.ensure_checked(TilesProvider::None);
+        assert!(!health.is_checking());
+        assert!(health.poll().is_empty());
+    }
+}
@@ -0,0 +1,292 @@
+//! Showing track times in UTC, local time or at a fixed offset
+//!
+//! GPX times are UTC. They are shown in the time zone picked in the settings.
+//! Local time asks the OS for the offset at each instant, so times on either
+//! side of a daylight saving change both come out right. Where it can't be
+//! read per instant (some platforms only allow it single-threaded, and
+//! browsers may hide it), the offset detected at startup stands in, and
+//! without that the offset set by hand in the settings.
+
+use std::sync::OnceLock;
+use time::{OffsetDateTime, UtcOffset};
+
+/// Time zone track times are shown in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeZone {
+    /// As recorded
+    Utc,
+    /// The computer's time zone
+    #[default]
+    Local,
+    /// The offset set by hand, e.g. where the tracks were recorded
+    Fixed,
+}
+
+impl TimeZone {
+    pub fn all() -> &'static [Self] {
+        &[Self::Local, Self::Utc, Self::Fixed]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utc => "UTC",
+            Self::Local => "Local time",
+            Self::Fixed => "Fixed offset",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Utc" => Self::Utc,
+            "Fixed" => Self::Fixed,
+            _ => Self::Local,
+        }
+    }
+}
+
+/// The OS time zone's offset from UTC at startup, if the platform reports it
+///
+/// Read on the first call and kept. Some platforms only report it while the
+/// process is single-threaded, so that call is made in `main` before any
+/// thread starts.
+pub fn local_offset_at_startup() -> Option<UtcOffset> {
+    static OFFSET: OnceLock<Option<UtcOffset>> = OnceLock::new();
+    *OFFSET.get_or_init(|| UtcOffset::current_local_offset().ok())
+}
+
+/// Whole-hour offset of the nominal time zone at `longitude`, a guess at
+/// where a track was recorded that ignores borders and daylight saving
+pub fn offset_hint(longitude: f64) -> UtcOffset {
+    let hours = (longitude / 15.0).round().clamp(-12.0, 12.0) as i8;
+    UtcOffset::from_hms(hours, 0, 0).unwrap_or(UtcOffset::UTC)
+}
+
+/// The time zone setting, resolved to an offset for any instant
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayZone {
+    zone: TimeZone,
+    /// The OS offset at an instant, where the platform knows it
+    local_at: fn(OffsetDateTime) -> Option<UtcOffset>,
+    /// The OS offset at startup
+    detected: Option<UtcOffset>,
+    /// The offset set by hand
+    fixed: UtcOffset,
+}
+
+impl DisplayZone {
+    /// Show times in `zone`, with `detected` the local offset at startup and
+    /// `fixed_minutes` the offset east of UTC set by hand
+    pub fn new(zone: TimeZone, detected: Option<UtcOffset>, fixed_minutes: i32) -> Self {
+        Self {
+            zone,
+            local_at: |time| UtcOffset::local_offset_at(time).ok(),
+            detected,
+            fixed: UtcOffset::from_whole_seconds(fixed_minutes * 60).unwrap_or(UtcOffset::UTC),
+        }
+    }
+
+    /// Offset the instant `time` is shown at
+    pub fn offset_at(&self, time: OffsetDateTime) -> UtcOffset {
+        match self.zone {
+            TimeZone::Utc => UtcOffset::UTC,
+            TimeZone::Fixed => self.fixed,
+            TimeZone::Local => (self.local_at)(time)
+                .or(self.detected)
+                .unwrap_or(self.fixed),
+        }
+    }
+}
+
+/// "2024-05-01 14:03:07 UTC+02:00" (just "UTC" at a zero offset)
+pub fn format_timestamp(time: OffsetDateTime, zone: &DisplayZone) -> String {
+    let offset = zone.offset_at(time);
+    let local = time.to_offset(offset);
+    format!(
+        "{} {} {}",
+        date(local),
+        clock(local, true),
+        offset_label(offset)
+    )
+}
+
+/// Start and end of a recording, the end without its date when on the same
+/// day: "2024-05-01 14:03 – 16:20 UTC+02:00". Each end gets its own offset
+/// when a daylight saving change falls in between.
+pub fn format_time_span(start: OffsetDateTime, end: OffsetDateTime, zone: &DisplayZone) -> String {
+    let (start_offset, end_offset) = (zone.offset_at(start), zone.offset_at(end));
+    let (start, end) = (start.to_offset(start_offset), end.to_offset(end_offset));
+    let end = if start.date() == end.date() {
+        clock(end, false)
+    } else {
+        format!("{} {}", date(end), clock(end, false))
+    };
+    if start_offset == end_offset {
+        format!(
+            "{} {} – {} {}",
+            date(start),
+            clock(start, false),
+            end,
+            offset_label(end_offset)
+        )
+    } else {
+        format!(
+            "{} {} {} – {} {}",
+            date(start),
+            clock(start, false),
+            offset_label(start_offset),
+            end,
+            offset_label(end_offset)
+        )
+    }
+}
+
+fn date(time: OffsetDateTime) -> String {
+    format!(
+        "{}-{:02}-{:02}",
+        time.year(),
+        u8::from(time.month()),
+        time.day()
+    )
+}
+
+fn clock(time: OffsetDateTime, seconds: bool) -> String {
+    if seconds {
+        format!(
+            "{:02}:{:02}:{:02}",
+            time.hour(),
+            time.minute(),
+            time.second()
+        )
+    } else {
+        format!("{:02}:{:02}", time.hour(), time.minute())
+    }
+}
+
+fn offset_label(offset: UtcOffset) -> String {
+    if offset.is_utc() {
+        return "UTC".to_string();
+    }
+    let (hours, minutes, _) = offset.as_hms();
+    let sign = if offset.is_negative() { '-' } else { '+' };
+    format!("UTC{}{:02}:{:02}", sign, hours.abs(), minutes.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central European time: UTC+01:00, or UTC+02:00 from the last Sunday of
+    /// March to the last Sunday of October 2024, switching at 01:00 UTC
+    fn berlin_2024(time: OffsetDateTime) -> Option<UtcOffset> {
+        let summer = 1_711_846_800..1_729_990_800;
+        let hours = if summer.contains(&time.unix_timestamp()) {
+            2
+        } else {
+            1
+        };
+        UtcOffset::from_hms(hours, 0, 0).ok()
+    }
+
+    fn fixed(hours: i8, minutes: i8) -> DisplayZone {
+        let minutes = i32::from(hours) * 60 + i32::from(minutes);
+        DisplayZone::new(TimeZone::Fixed, None, minutes)
+    }
+
+    #[test]
+    fn utc_times_are_shown_at_the_zone_offset() {
+        // 2024-05-01 12:03:07 UTC
+        let time = OffsetDateTime::from_unix_timestamp(1_714_564_987).unwrap();
+        assert_eq!(
+            format_timestamp(time, &fixed(2, 0)),
+            "2024-05-01 14:03:07 UTC+02:00"
+        );
+        assert_eq!(
+            format_timestamp(time, &DisplayZone::new(TimeZone::Utc, None, 120)),
+            "2024-05-01 12:03:07 UTC"
+        );
+        // West of UTC, across midnight
+        assert_eq!(
+            format_timestamp(time, &fixed(-12, -30)),
+            "2024-04-30 23:33:07 UTC-12:30"
+        );
+
+        let end = time + time::Duration::minutes(137);
+        assert_eq!(
+            format_time_span(time, end, &fixed(2, 0)),
+            "2024-05-01 14:03 – 16:20 UTC+02:00"
+        );
+        let next_day = time + time::Duration::hours(11);
+        assert_eq!(
+            format_time_span(time, next_day, &fixed(2, 0)),
+            "2024-05-01 14:03 – 2024-05-02 01:03 UTC+02:00"
+        );
+    }
+
+    #[test]
+    fn local_times_follow_daylight_saving() {
+        let zone = DisplayZone {
+            local_at: berlin_2024,
+            ..DisplayZone::new(TimeZone::Local, None, 0)
+        };
+        // The same UTC clock time in winter and summer
+        let winter = OffsetDateTime::from_unix_timestamp(1_705_320_000).unwrap();
+        let summer = OffsetDateTime::from_unix_timestamp(1_718_452_800).unwrap();
+        assert_eq!(
+            format_timestamp(winter, &zone),
+            "2024-01-15 13:00:00 UTC+01:00"
+        );
+        assert_eq!(
+            format_timestamp(summer, &zone),
+            "2024-06-15 14:00:00 UTC+02:00"
+        );
+
+        // A ride through the spring change: clocks jump from 02:00 to 03:00
+        let start = OffsetDateTime::from_unix_timestamp(1_711_845_000).unwrap();
+        let end = start + time::Duration::minutes(60);
+        assert_eq!(
+            format_time_span(start, end, &zone),
+            "2024-03-31 01:30 UTC+01:00 – 03:30 UTC+02:00"
+        );
+        // And back in autumn, where 02:30 happens twice
+        let start = OffsetDateTime::from_unix_timestamp(1_729_989_000).unwrap();
+        let end = start + time::Duration::minutes(60);
+        assert_eq!(
+            format_time_span(start, end, &zone),
+            "2024-10-27 02:30 UTC+02:00 – 02:30 UTC+01:00"
+        );
+        // Entirely on one side, one offset is enough
+        assert_eq!(
+            format_time_span(summer, summer + time::Duration::hours(2), &zone),
+            "2024-06-15 14:00 – 16:00 UTC+02:00"
+        );
+    }
+
+    #[test]
+    fn undetected_local_offsets_fall_back_in_order() {
+        let time = OffsetDateTime::from_unix_timestamp(1_714_564_987).unwrap();
+        let plus_two = UtcOffset::from_hms(2, 0, 0).unwrap();
+        let zone = |detected| DisplayZone {
+            local_at: |_| None,
+            ..DisplayZone::new(TimeZone::Local, detected, 120)
+        };
+        // The startup offset, then the hand-set one
+        assert_eq!(zone(Some(UtcOffset::UTC)).offset_at(time), UtcOffset::UTC);
+        assert_eq!(zone(None).offset_at(time), plus_two);
+        // A fixed offset ignores the OS
+        let fixed = DisplayZone {
+            local_at: berlin_2024,
+            ..DisplayZone::new(TimeZone::Fixed, Some(UtcOffset::UTC), -420)
+        };
+        assert_eq!(
+            fixed.offset_at(time),
+            UtcOffset::from_hms(-7, 0, 0).unwrap()
+        );
+
+        // Hints from the starting longitude
+        assert_eq!(offset_hint(-112.0), UtcOffset::from_hms(-7, 0, 0).unwrap());
+        assert_eq!(offset_hint(13.4), UtcOffset::from_hms(1, 0, 0).unwrap());
+        assert_eq!(offset_hint(179.9), UtcOffset::from_hms(12, 0, 0).unwrap());
+        assert_eq!(offset_hint(0.0), UtcOffset::UTC);
+    }
+}
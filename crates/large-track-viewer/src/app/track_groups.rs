@@ -0,0 +1,182 @@
+//! Grouping of the Tracks list by source folder or by month
+//!
+//! With hundreds of files a flat list is hard to scan, so the list can be
+//! split into collapsible groups. Groups are computed from what is already
+//! loaded (paths and GPX times) every time the list is drawn; nothing is
+//! cached or persisted besides the chosen [`TrackGrouping`].
+
+use std::path::Path;
+
+/// Group of files that aren't on disk (dropped in the browser, recorded or generated)
+pub const NO_FOLDER: &str = "Not on disk";
+
+/// Group of files without any timestamp
+pub const NO_DATE: &str = "No date";
+
+/// How the Tracks list is grouped
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrackGrouping {
+    /// One flat list, in load order
+    #[default]
+    None,
+    /// By the folder each file was loaded from
+    Folder,
+    /// By the month the track starts in
+    Month,
+}
+
+impl TrackGrouping {
+    pub fn all() -> &'static [Self] {
+        &[Self::None, Self::Folder, Self::Month]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Folder => "Folder",
+            Self::Month => "Month",
+        }
+    }
+
+    /// Parse the persisted `Debug` name, falling back to the default
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Folder" => Self::Folder,
+            "Month" => Self::Month,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Label of the group a file falls in, `None` when the list isn't grouped
+pub fn group_label(grouping: TrackGrouping, path: &Path, gpx: &gpx::Gpx) -> Option<String> {
+    match grouping {
+        TrackGrouping::None => None,
+        TrackGrouping::Folder => Some(folder_label(path)),
+        TrackGrouping::Month => Some(month_label(gpx)),
+    }
+}
+
+/// Split `files` (list index, path and contents) into labeled groups
+///
+/// Folders are sorted by name and months newest first; files that can't be
+/// placed ([`NO_FOLDER`], [`NO_DATE`]) come last. Files keep their relative
+/// order within a group. Without grouping everything is one unlabeled group.
+pub fn group_files<'a>(
+    grouping: TrackGrouping,
+    files: impl IntoIterator<Item = (usize, &'a Path, &'a gpx::Gpx)>,
+) -> Vec<(String, Vec<usize>)> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, path, gpx) in files {
+        let label = group_label(grouping, path, gpx).unwrap_or_default();
+        match groups.iter_mut().find(|(existing, _)| *existing == label) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((label, vec![index])),
+        }
+    }
+    let unplaced = |label: &str| label == NO_FOLDER || label == NO_DATE;
+    match grouping {
+        TrackGrouping::None => {}
+        TrackGrouping::Folder => groups.sort_by(|(a, _), (b, _)| {
+            (unplaced(a), a.to_lowercase()).cmp(&(unplaced(b), b.to_lowercase()))
+        }),
+        // "YYYY-MM" labels sort by date as text
+        TrackGrouping::Month => {
+            groups.sort_by(|(a, _), (b, _)| unplaced(a).cmp(&unplaced(b)).then_with(|| b.cmp(a)))
+        }
+    }
+    groups
+}
+
+/// Folder a file was loaded from, or [`NO_FOLDER`]
+fn folder_label(path: &Path) -> String {
+    // Files that aren't on disk get `scheme://` paths
+    if path.to_string_lossy().contains("://") {
+        return NO_FOLDER.to_string();
+    }
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .map(|parent| parent.display().to_string())
+        .unwrap_or_else(|| NO_FOLDER.to_string())
+}
+
+/// Month the track starts in as "YYYY-MM", or [`NO_DATE`]
+///
+/// Uses the file's metadata time, or else the first timestamped point.
+fn month_label(gpx: &gpx::Gpx) -> String {
+    let first_point_time = || {
+        gpx.tracks
+            .iter()
+            .flat_map(|track| &track.segments)
+            .flat_map(|segment| &segment.points)
+            .find_map(|point| point.time)
+    };
+    let Some(time) = gpx
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.time)
+        .or_else(first_point_time)
+    else {
+        return NO_DATE.to_string();
+    };
+    let time = time::OffsetDateTime::from(time);
+    format!("{}-{:02}", time.year(), u8::from(time.month()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use large_track_lib::{RouteBuilder, TrackPoint};
+    use std::path::PathBuf;
+
+    fn gpx_at(unix_time: Option<i64>) -> gpx::Gpx {
+        let point = TrackPoint {
+            time: unix_time.map(|t| time::OffsetDateTime::from_unix_timestamp(t).unwrap()),
+            ..TrackPoint::new(45.83, 6.86)
+        };
+        let route = RouteBuilder::from_gpx(gpx::Gpx::default())
+            .add_segment([point])
+            .build()
+            .unwrap();
+        route.gpx_data().clone()
+    }
+
+    #[test]
+    fn files_land_in_their_folder_and_month() {
+        // 2023-07-01, 2024-01-01 and 2023-07-22
+        let (july, january, late_july) = (1_688_169_600, 1_704_067_200, 1_690_000_000);
+        let files = [
+            (PathBuf::from("/rides/2023/b.gpx"), gpx_at(Some(july))),
+            (PathBuf::from("/hikes/a.gpx"), gpx_at(Some(january))),
+            (PathBuf::from("web://dropped.gpx"), gpx_at(None)),
+            (PathBuf::from("/rides/2023/c.gpx"), gpx_at(Some(late_july))),
+        ];
+        let entries = || {
+            files
+                .iter()
+                .enumerate()
+                .map(|(i, (path, gpx))| (i, path.as_path(), gpx))
+        };
+
+        assert_eq!(
+            group_files(TrackGrouping::Folder, entries()),
+            vec![
+                ("/hikes".to_string(), vec![1]),
+                ("/rides/2023".to_string(), vec![0, 3]),
+                (NO_FOLDER.to_string(), vec![2]),
+            ]
+        );
+        assert_eq!(
+            group_files(TrackGrouping::Month, entries()),
+            vec![
+                ("2024-01".to_string(), vec![1]),
+                ("2023-07".to_string(), vec![0, 3]),
+                (NO_DATE.to_string(), vec![2]),
+            ]
+        );
+        assert_eq!(
+            group_files(TrackGrouping::None, entries()),
+            vec![(String::new(), vec![0, 1, 2, 3])]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! egui's own memory (collapsed sections, scroll positions, window placement)
+//!
+//! eframe keeps egui's memory in its own storage, which isn't available on
+//! every platform (Android has no data directory for it) and is only written
+//! by `App::save`, which an app that gets killed never reaches. [`UiMemory`]
+//! keeps a copy in the platform storage backend next to our settings, written
+//! on every autosave and restored at startup. It is stored as RON, the format
+//! eframe itself uses for it: some of egui's maps have non-string keys that
+//! JSON can't represent.
+
+use crate::app::storage::{StorageBackend, StorageResult};
+use eframe::egui;
+
+/// Storage key of the serialized [`egui::Memory`]
+pub const UI_MEMORY_KEY: &str = "egui_memory";
+
+/// Copy of egui's memory in the platform backend
+#[derive(Debug, Default)]
+pub struct UiMemory {
+    /// Text last written, so unchanged memory isn't written again
+    last_saved: Option<String>,
+}
+
+impl UiMemory {
+    /// Put the memory saved in `backend` back into `ctx`, if there is any
+    pub fn restore(ctx: &egui::Context, backend: &dyn StorageBackend) {
+        let text = match backend.get_string(UI_MEMORY_KEY) {
+            Ok(Some(text)) => text,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::debug!("Error reading saved UI memory: {:?}", e);
+                return;
+            }
+        };
+        match eframe::get_value::<egui::Memory>(&RonText(Some(text)), UI_MEMORY_KEY) {
+            Some(memory) => ctx.memory_mut(|current| *current = memory),
+            None => tracing::warn!("Ignoring unreadable saved UI memory"),
+        }
+    }
+
+    /// Write the memory of `ctx` to `backend` if it changed since the last save
+    pub fn save_if_changed(
+        &mut self,
+        ctx: &egui::Context,
+        backend: &dyn StorageBackend,
+    ) -> StorageResult<()> {
+        let mut text = RonText(None);
+        ctx.memory(|memory| eframe::set_value(&mut text, UI_MEMORY_KEY, memory));
+        let Some(text) = text.0 else {
+            return Ok(());
+        };
+        if self.last_saved.as_ref() != Some(&text) {
+            backend.set_string(UI_MEMORY_KEY, &text)?;
+            self.last_saved = Some(text);
+        }
+        Ok(())
+    }
+}
+
+/// A single value as eframe serializes it, for `eframe::get_value`/`set_value`
+struct RonText(Option<String>);
+
+impl eframe::Storage for RonText {
+    fn get_string(&self, _key: &str) -> Option<String> {
+        self.0.clone()
+    }
+
+    fn set_string(&mut self, _key: &str, value: String) {
+        self.0 = Some(value);
+    }
+
+    fn flush(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::storage::MemoryBackend;
+
+    #[test]
+    fn collapsed_sections_survive_a_restart() {
+        let backend = MemoryBackend::default();
+        let id = egui::Id::new("skipped_segments");
+        let ctx = egui::Context::default();
+        ctx.data_mut(|data| data.insert_persisted(id, true));
+        let mut ui_memory = UiMemory::default();
+        ui_memory.save_if_changed(&ctx, &backend).unwrap();
+
+        let restarted = egui::Context::default();
+        UiMemory::restore(&restarted, &backend);
+        assert_eq!(
+            restarted.data_mut(|data| data.get_persisted::<bool>(id)),
+            Some(true)
+        );
+
+        // Nothing changed, so nothing is written
+        backend.remove(UI_MEMORY_KEY).unwrap();
+        ui_memory.save_if_changed(&ctx, &backend).unwrap();
+        assert!(backend.get_string(UI_MEMORY_KEY).unwrap().is_none());
+    }
+}
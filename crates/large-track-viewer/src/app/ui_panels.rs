@@ -3,37 +3,137 @@
 //! This module provides reusable UI components for the sidebar design
 //! with tabs, map controls, and drag-and-drop support.
 
-use crate::app::state::{AppState, SidebarTab, TilesProvider};
+use crate::app::announce;
+use crate::app::coords::{self, CoordFormat};
+use crate::app::detail;
+use crate::app::gpx_version;
+use crate::app::inertia;
+use crate::app::load_error::{LoadError, LoadErrorKind};
+use crate::app::memory_budget::{MEMORY_READOUT_INTERVAL, format_bytes};
+use crate::app::motion;
+use crate::app::notifications::NotificationLevel;
+use crate::app::overlay;
+use crate::app::playback::PLAYBACK_SPEEDS;
+use crate::app::render::{BlendMode, COMPARE_COLORS, WidthMode, route_color};
+use crate::app::shortcuts::{ShortcutAction, Shortcuts};
+use crate::app::split::SPLIT_PATH_PREFIX;
+use crate::app::state::{AppState, FilePrecedence, SidebarTab, StartupMode, TilesProvider};
+use crate::app::style_rules::{DashPattern, StyleRule};
+use crate::app::svg_export;
+use crate::app::symbols;
+use crate::app::timezone::{self, DisplayZone, TimeZone};
+use crate::app::track_groups::{self, TrackGrouping};
+use eframe_entrypoints::async_runtime::oneshot::error::TryRecvError;
+use eframe_entrypoints::file_picker::SaveOutcome;
 use egui::{Color32, RichText, Ui};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 /// Check if a filename is already loaded to avoid duplicates
 fn is_filename_already_loaded(state: &AppState, filename: &str) -> bool {
-    state.file_loader.loaded_files.iter().any(|(path, _, _)| {
-        path.file_name()
-            .map(|n| n.to_string_lossy() == filename)
-            .unwrap_or(false)
-    })
+    state
+        .file_loader
+        .loaded_files
+        .iter()
+        .any(|(path, _, _, _)| {
+            path.file_name()
+                .map(|n| n.to_string_lossy() == filename)
+                .unwrap_or(false)
+        })
+}
+
+/// Folder containing a loaded file, or `None` for files that don't live on disk
+#[cfg_attr(any(target_arch = "wasm32", target_os = "android"), allow(dead_code))]
+fn containing_folder(path: &Path) -> Option<&Path> {
+    let path_str = path.to_string_lossy();
+    if path_str.starts_with("web://")
+        || path_str.starts_with(crate::app::recorder::RECORDING_PATH_PREFIX)
+        || path_str.starts_with(crate::app::synthetic::SYNTHETIC_PATH_PREFIX)
+        || path_str.starts_with(SPLIT_PATH_PREFIX)
+    {
+        return None;
+    }
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+}
+
+/// Open the folder containing `path` in the system file manager
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn open_containing_folder(path: &Path) -> Result<(), String> {
+    let folder = containing_folder(path).ok_or("File has no folder on disk")?;
+    if !folder.is_dir() {
+        return Err(format!("{} no longer exists", folder.display()));
+    }
+    open::that_detached(folder).map_err(|e| format!("Could not open {}: {}", folder.display(), e))
 }
 
 /// Render the sidebar toggle button (overlaid on top-right of map)
+///
+/// Allocated after the map in the map's own layer, so it sits on top of it
+/// for input. It senses drags as well as clicks: egui hands a drag to the
+/// topmost widget that senses drags, which would otherwise be the map, and
+/// a press on the button that wobbled a little would pan it.
 pub fn sidebar_toggle_button(ui: &mut Ui, state: &mut AppState) {
     // UI panel specific profiling scope to help attribute time spent in UI layout/draw.
     #[cfg(feature = "profiling")]
     profiling::scope!("ui::sidebar_toggle_button");
 
+    // Draw icon (hamburger menu or X based on state)
+    let (icon, label) = if state.ui_settings.sidebar_open {
+        ("✕", "Close sidebar")
+    } else {
+        ("☰", "Open sidebar")
+    };
+    if map_corner_button(ui, 0, icon, label).clicked() {
+        state.ui_settings.sidebar_open = !state.ui_settings.sidebar_open;
+    }
+}
+
+/// Render the map lock button, below the sidebar toggle
+///
+/// Taps on it get through a locked map like any other tap (see
+/// [`crate::app::map_lock`]), so it always unlocks.
+pub fn map_lock_button(ui: &mut Ui, state: &mut AppState) {
+    let (icon, label, hint) = if state.ui_settings.map_locked {
+        (
+            "🔒",
+            "Unlock map",
+            "Map locked: one-finger drags and plain scrolling don't move it. Tap to unlock",
+        )
+    } else {
+        (
+            "🔓",
+            "Lock map",
+            "Lock the map, so one-finger drags and plain scrolling don't move it",
+        )
+    };
+    if map_corner_button(ui, 1, icon, label)
+        .on_hover_text(hint)
+        .clicked()
+    {
+        state.ui_settings.map_locked = !state.ui_settings.map_locked;
+    }
+}
+
+/// A square button in the map's top-right corner, the `row`th from the top
+///
+/// Senses drags for the reason given on [`sidebar_toggle_button`]. Screen
+/// readers (and UI tests) know it by `label`, as the icon is only painted.
+fn map_corner_button(ui: &mut Ui, row: usize, icon: &str, label: &str) -> egui::Response {
     let button_size = egui::vec2(40.0, 40.0);
     let margin = 10.0;
 
     // Position button in top-right corner
     let rect = ui.max_rect();
-    let button_pos = rect.right_top() + egui::vec2(-button_size.x - margin, margin);
+    let button_pos = rect.right_top()
+        + egui::vec2(
+            -button_size.x - margin,
+            margin + row as f32 * (button_size.y + margin),
+        );
     let button_rect = egui::Rect::from_min_size(button_pos, button_size);
 
-    let response = ui.allocate_rect(button_rect, egui::Sense::click());
-
-    if response.clicked() {
-        state.ui_settings.sidebar_open = !state.ui_settings.sidebar_open;
-    }
+    let response = ui.allocate_rect(button_rect, egui::Sense::click_and_drag());
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, true, label));
 
     // Draw button background
     let bg_color = if response.hovered() {
@@ -48,13 +148,6 @@ pub fn sidebar_toggle_button(ui: &mut Ui, state: &mut AppState) {
         bg_color,
     );
 
-    // Draw icon (hamburger menu or X based on state)
-    let icon = if state.ui_settings.sidebar_open {
-        "✕"
-    } else {
-        "☰"
-    };
-
     ui.painter().text(
         button_rect.center(),
         egui::Align2::CENTER_CENTER,
@@ -62,6 +155,7 @@ pub fn sidebar_toggle_button(ui: &mut Ui, state: &mut AppState) {
         egui::FontId::proportional(20.0),
         ui.visuals().text_color(),
     );
+    response
 }
 
 /// Render the main sidebar (responsive: side on landscape, bottom on portrait)
@@ -176,13 +270,28 @@ fn render_tracks_tab(ui: &mut Ui, state: &mut AppState) {
                 state.pending_fit_bounds = true;
             }
             if ui.button("🗑 Clear").clicked() {
-                state.clear_routes();
+                state.clear_routes_with_undo();
             }
         });
     });
 
     ui.add_space(8.0);
 
+    render_roi_controls(ui, state);
+
+    ui.add_space(8.0);
+
+    render_split_controls(ui, state);
+
+    ui.add_space(8.0);
+
+    if state.file_loader.loaded_files.len() >= 2 {
+        render_comparison_controls(ui, state);
+        ui.add_space(8.0);
+    }
+
+    render_playback_controls(ui, state);
+
     // Loading progress
     if state.file_loader.is_busy() || state.is_parallel_loading() {
         ui.separator();
@@ -209,37 +318,87 @@ fn render_tracks_tab(ui: &mut Ui, state: &mut AppState) {
 
     // Error list (shown BEFORE loaded files, with fixed height)
     if !state.file_loader.errors.is_empty() {
-        ui.label(
+        let retryable = state.retryable_error_count();
+        let mut groups: BTreeMap<LoadErrorKind, Vec<&(PathBuf, LoadError)>> = BTreeMap::new();
+        for entry in &state.file_loader.errors {
+            groups.entry(entry.1.kind()).or_default().push(entry);
+        }
+        let (error_color, warn_color) = (ui.visuals().error_fg_color, ui.visuals().warn_fg_color);
+        let level_color = move |level: NotificationLevel| match level {
+            NotificationLevel::Error => error_color,
+            _ => warn_color,
+        };
+        // Only warnings (empty files, a busy index) aren't shown as failures
+        let worst = groups
+            .keys()
+            .map(LoadErrorKind::level)
+            .find(|level| *level == NotificationLevel::Error)
+            .unwrap_or(NotificationLevel::Warn);
+        let response = ui.label(
             RichText::new(format!(
-                "⚠ Errors ({} files)",
-                state.file_loader.errors.len()
+                "⚠ Errors ({} files, {} retryable)",
+                state.file_loader.errors.len(),
+                retryable
             ))
             .strong()
-            .color(Color32::RED),
+            .color(level_color(worst)),
         );
+        announce::mark_live(&response);
         ui.add_space(4.0);
 
         egui::ScrollArea::vertical()
             .id_salt("errors_scroll")
             .max_height(100.0)
             .show(ui, |ui| {
-                for (file, error) in &state.file_loader.errors {
+                for (kind, errors) in &groups {
+                    let color = level_color(kind.level());
                     ui.label(
-                        RichText::new(format!(
-                            "• {}: {}",
-                            file.file_name().unwrap_or_default().to_string_lossy(),
-                            error
-                        ))
-                        .small()
-                        .color(Color32::RED),
+                        RichText::new(format!("{} ({})", kind.label(), errors.len()))
+                            .small()
+                            .strong()
+                            .color(color),
                     );
+                    for (file, error) in errors {
+                        let response = ui.label(
+                            RichText::new(format!(
+                                "• {}: {}",
+                                file.file_name().unwrap_or_default().to_string_lossy(),
+                                error
+                            ))
+                            .small()
+                            .color(color),
+                        );
+                        if !error.is_retryable() {
+                            response.on_hover_text(
+                                "Can't be retried: loading it again would fail the same way",
+                            );
+                        } else if state.file_loader.unretryable.contains(file) {
+                            response.on_hover_text(
+                                "Can't be retried: the file's contents weren't kept, add it again",
+                            );
+                        }
+                    }
                 }
             });
 
         ui.add_space(4.0);
-        if ui.button("Clear Errors").clicked() {
-            state.file_loader.errors.clear();
-        }
+        ui.horizontal(|ui| {
+            let loading = state.is_parallel_loading();
+            if ui
+                .add_enabled(retryable > 0 && !loading, egui::Button::new("🔄 Retry all"))
+                .on_hover_text("Load every failed file that can be read again")
+                .clicked()
+            {
+                state.retry_failed_files();
+            }
+            if ui
+                .button("Dismiss all")
+                .on_hover_text("Clear the list; dismissed errors stay listed in Settings → Debug")
+                .clicked()
+            {
+                state.dismiss_errors();
+            }
+        });
 
         ui.add_space(8.0);
         ui.separator();
@@ -254,7 +413,50 @@ fn render_tracks_tab(ui: &mut Ui, state: &mut AppState) {
         );
         ui.add_space(4.0);
 
-        let mut to_remove = None;
+        state.refresh_annotation_keys();
+        render_selected_track_annotations(ui, state);
+        ui.horizontal(|ui| {
+            ui.label("🔎");
+            ui.add(
+                egui::TextEdit::singleline(&mut state.track_filter)
+                    .hint_text("Filter by name, #tag or note")
+                    .desired_width(ui.available_width() - 28.0),
+            );
+            if !state.track_filter.is_empty() && ui.small_button("✖").clicked() {
+                state.track_filter.clear();
+            }
+        });
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.label("Group by:");
+            egui::ComboBox::from_id_salt("track_grouping")
+                .selected_text(state.ui_settings.track_grouping.name())
+                .show_ui(ui, |ui| {
+                    for grouping in TrackGrouping::all() {
+                        ui.selectable_value(
+                            &mut state.ui_settings.track_grouping,
+                            *grouping,
+                            grouping.name(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("Folder the files were loaded from, or month the tracks start in");
+        });
+        ui.add_space(4.0);
+
+        let mut actions = FileListActions::default();
+        let grouping = state.ui_settings.track_grouping;
+        let groups = track_groups::group_files(
+            grouping,
+            state
+                .file_loader
+                .loaded_files
+                .iter()
+                .enumerate()
+                .filter(|(_, (path, gpx, _, _))| state.file_matches_filter(path, gpx))
+                .map(|(idx, (path, gpx, _, _))| (idx, path.as_path(), gpx)),
+        );
 
         // Use all remaining available height for the loaded files list
         let available_height = ui.available_height().max(80.0);
@@ -263,52 +465,638 @@ fn render_tracks_tab(ui: &mut Ui, state: &mut AppState) {
             .id_salt("loaded_files_scroll")
             .max_height(available_height - 8.0) // Leave small margin at bottom
             .show(ui, |ui| {
-                for (idx, (path, _, start_idx)) in state.file_loader.loaded_files.iter().enumerate()
-                {
-                    ui.horizontal(|ui| {
-                        // File display name
-                        let file_name = path
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-
-                        // Determine whether this file's start index is the currently selected route
-                        let is_selected = if let Ok(guard) = state.selected_route.try_read() {
-                            match *guard {
-                                Some(sel) => sel == *start_idx,
-                                None => false,
-                            }
-                        } else {
-                            false
-                        };
-
-                        // Clickable/selectable label for selecting the file (selects the start route of this file)
-                        if ui
-                            .selectable_label(is_selected, format!("📄 {}", file_name))
-                            .clicked()
-                            && let Ok(mut guard) = state.selected_route.try_write()
-                        {
-                            *guard = Some(*start_idx);
+                for (label, indices) in &groups {
+                    if grouping == TrackGrouping::None {
+                        for &idx in indices {
+                            render_loaded_file(ui, state, idx, &mut actions);
                         }
-
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            if ui.small_button("🗑").clicked() {
-                                to_remove = Some(idx);
+                        continue;
+                    }
+                    egui::CollapsingHeader::new(format!("{} ({})", label, indices.len()))
+                        .id_salt(("track_group", label))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for &idx in indices {
+                                render_loaded_file(ui, state, idx, &mut actions);
                             }
                         });
-                    });
                 }
             });
 
-        if let Some(idx) = to_remove {
+        if let Some(route_index) = actions.select {
+            state.select_route(route_index);
+        }
+        if let Some(path) = actions.toggle {
+            state.toggle_expanded(&path);
+        }
+        if let Some((path, temporary)) = actions.set_temporary {
+            state.set_temporary(&path, temporary);
+        }
+        if let Some((path, color)) = actions.set_color {
+            state.set_route_color(&path, color);
+        }
+        if let Some(filter) = actions.filter {
+            state.track_filter = filter;
+        }
+        if let Some(idx) = actions.export {
+            export_split_part(state, idx);
+        }
+        if let Some(idx) = actions.remove {
             state.remove_file(idx);
         }
+        #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+        if let Some(e) = actions.folder_error {
+            state.notify_error(e);
+        }
+    }
+}
+
+/// Changes asked for from rows of the loaded files list, applied once the list is drawn
+#[derive(Default)]
+struct FileListActions {
+    remove: Option<usize>,
+    toggle: Option<PathBuf>,
+    filter: Option<String>,
+    set_temporary: Option<(PathBuf, bool)>,
+    set_color: Option<(PathBuf, Option<Color32>)>,
+    select: Option<usize>,
+    export: Option<usize>,
+    #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+    folder_error: Option<String>,
+}
+
+/// One row of the loaded files list, with its details when expanded
+fn render_loaded_file(ui: &mut Ui, state: &AppState, idx: usize, actions: &mut FileListActions) {
+    let (path, gpx, start_idx, key) = &state.file_loader.loaded_files[idx];
+    let expanded = state.is_expanded(key);
+    ui.horizontal(|ui| {
+        if ui
+            .small_button(if expanded { "⏷" } else { "⏵" })
+            .on_hover_text(if expanded {
+                "Hide details"
+            } else {
+                "Show details"
+            })
+            .clicked()
+        {
+            actions.toggle = Some(path.clone());
+        }
+
+        // File display name
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        // Determine whether this file's start index is the currently selected route
+        let is_selected = if let Ok(guard) = state.selected_route.try_read() {
+            match *guard {
+                Some(sel) => sel == *start_idx,
+                None => false,
+            }
+        } else {
+            false
+        };
+
+        // Clickable/selectable label for selecting the file (selects the start route of this file)
+        let temporary = state.is_temporary(path);
+        let label = ui.selectable_label(is_selected, format!("📄 {}", file_name));
+        label.context_menu(|ui| {
+            let mut reload = !temporary;
+            if ui.checkbox(&mut reload, "Reload on restart").changed() {
+                actions.set_temporary = Some((path.clone(), !reload));
+                ui.close();
+            }
+            let picked = state.route_color(path);
+            let color = picked.unwrap_or_else(|| route_color(*start_idx));
+            let mut rgb = [color.r(), color.g(), color.b()];
+            ui.horizontal(|ui| {
+                if ui.color_edit_button_srgb(&mut rgb).changed() {
+                    actions.set_color = Some((
+                        path.clone(),
+                        Some(Color32::from_rgb(rgb[0], rgb[1], rgb[2])),
+                    ));
+                }
+                ui.label("Line color");
+            });
+            if picked.is_some() && ui.button("Automatic color").clicked() {
+                actions.set_color = Some((path.clone(), None));
+                ui.close();
+            }
+        });
+        if label.clicked() {
+            actions.select = Some(*start_idx);
+        }
+        if temporary {
+            ui.label(RichText::new("temporary").small().weak())
+                .on_hover_text(
+                    "Shown for this session only: not reloaded on restart. \
+                     Right-click the file to keep it.",
+                );
+        }
+        let tags = state
+            .annotation_keys
+            .get(path)
+            .and_then(|key| state.annotations.get(key))
+            .map(|annotation| annotation.tags.as_slice())
+            .unwrap_or_default();
+        for tag in tags {
+            if ui
+                .small_button(RichText::new(format!("#{}", tag)).small())
+                .on_hover_text("Show tracks with this tag")
+                .clicked()
+            {
+                actions.filter = Some(format!("#{}", tag));
+            }
+        }
+        if state
+            .stats
+            .fully_skipped_routes
+            .binary_search(start_idx)
+            .is_ok()
+        {
+            ui.label(RichText::new("⚠").weak()).on_hover_text(
+                "In view, but nothing of it was drawn this frame. \
+                 See Settings → Debug → Why is something missing?",
+            );
+        }
+
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if ui.small_button("🗑").clicked() {
+                actions.remove = Some(idx);
+            }
+            if path.to_string_lossy().starts_with(SPLIT_PATH_PREFIX)
+                && ui
+                    .small_button("💾")
+                    .on_hover_text("Save this part as a GPX file; it is only kept in memory")
+                    .clicked()
+            {
+                actions.export = Some(idx);
+            }
+            #[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+            if containing_folder(path).is_some()
+                && ui
+                    .small_button("📁")
+                    .on_hover_text("Open containing folder")
+                    .clicked()
+                && let Err(e) = open_containing_folder(path)
+            {
+                actions.folder_error = Some(e);
+            }
+        });
+    });
+    if expanded {
+        render_file_details(ui, gpx, &state.display_zone());
+    }
+}
+
+/// Play/pause, speed and scrubber for replaying the selected route
+fn render_playback_controls(ui: &mut Ui, state: &mut AppState) {
+    let selected = state
+        .selected_route
+        .try_read()
+        .ok()
+        .and_then(|guard| *guard);
+    let Some(route_index) = state.playback.route().or(selected) else {
+        return;
+    };
+    let Ok(collection) = state.route_collection.try_read() else {
+        return;
+    };
+    let Some(route) = collection.get_route(route_index) else {
+        return;
+    };
+    let profile = route.profile();
+    let display_zone = state.display_zone();
+    let playback = &mut state.playback;
+
+    egui::CollapsingHeader::new("▶ Playback")
+        .id_salt("playback")
+        .show(ui, |ui| {
+            let active = playback.route() == Some(route_index);
+            ui.horizontal(|ui| {
+                if !active {
+                    if ui.button("▶ Play").clicked() {
+                        playback.start(route_index);
+                    }
+                } else if playback.playing {
+                    if ui.button("⏸ Pause").clicked() {
+                        playback.playing = false;
+                    }
+                } else if ui.button("▶ Play").clicked() {
+                    if playback.progress(profile) >= 1.0 {
+                        playback.seek(profile, 0.0);
+                    }
+                    playback.playing = true;
+                }
+                if active && ui.button("⏹ Stop").clicked() {
+                    playback.stop();
+                }
+                egui::ComboBox::from_id_salt("playback_speed")
+                    .selected_text(format!("{}×", playback.speed))
+                    .width(60.0)
+                    .show_ui(ui, |ui| {
+                        for speed in PLAYBACK_SPEEDS {
+                            ui.selectable_value(&mut playback.speed, *speed, format!("{}×", speed));
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Multiple of track time, or of 10 points per second without timestamps",
+                    );
+            });
+
+            if active {
+                let mut progress = playback.progress(profile);
+                let readout = match playback.track_time(profile) {
+                    Some(track_seconds) => {
+                        let seconds = track_seconds as u64;
+                        let elapsed = format!(
+                            "{}:{:02}:{:02}",
+                            seconds / 3600,
+                            seconds / 60 % 60,
+                            seconds % 60
+                        );
+                        match profile.start_time() {
+                            Some(start) => format!(
+                                "{} · {}",
+                                elapsed,
+                                timezone::format_timestamp(
+                                    start + time::Duration::seconds_f64(track_seconds),
+                                    &display_zone
+                                )
+                            ),
+                            None => elapsed,
+                        }
+                    }
+                    None => format!("point {} of {}", playback.index(profile) + 1, profile.len()),
+                };
+                ui.horizontal(|ui| {
+                    if ui
+                        .add(egui::Slider::new(&mut progress, 0.0..=1.0).show_value(false))
+                        .changed()
+                    {
+                        playback.seek(profile, progress);
+                    }
+                    ui.label(readout);
+                });
+            }
+        });
+    ui.add_space(8.0);
+}
+
+/// Notes and tags of the selected track, editable above the Tracks list
+fn render_selected_track_annotations(ui: &mut Ui, state: &mut AppState) {
+    let Some(idx) = state.selected_file() else {
+        return;
+    };
+    let path = state.file_loader.loaded_files[idx].0.clone();
+    let Some(key) = state.annotation_keys.get(&path).cloned() else {
+        return;
+    };
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let annotation = state.annotations.get(&key).cloned().unwrap_or_default();
+
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.label(RichText::new(format!("📝 {}", file_name)).strong());
+        let mut notes = annotation.notes;
+        let response = ui.add(
+            egui::TextEdit::multiline(&mut notes)
+                .hint_text("Notes")
+                .desired_rows(2)
+                .desired_width(f32::INFINITY),
+        );
+        if response.changed() {
+            state.annotations.set_notes(&key, notes);
+            state.session_dirty = true;
+        }
+        // Not on every keystroke
+        if response.lost_focus() {
+            state.write_sidecar(&path);
+        }
+        ui.horizontal_wrapped(|ui| {
+            for tag in &annotation.tags {
+                if ui
+                    .small_button(format!("#{} ✖", tag))
+                    .on_hover_text("Remove tag")
+                    .clicked()
+                {
+                    state.annotations.remove_tag(&key, tag);
+                    state.session_dirty = true;
+                    state.write_sidecar(&path);
+                }
+            }
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut state.new_tag)
+                    .hint_text("Add tag")
+                    .desired_width(80.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (submitted || ui.small_button("+").clicked()) && !state.new_tag.trim().is_empty() {
+                let tag = std::mem::take(&mut state.new_tag);
+                state.annotations.add_tag(&key, &tag);
+                state.session_dirty = true;
+                state.write_sidecar(&path);
+            }
+        });
+    });
+    ui.add_space(4.0);
+}
+
+/// Details of one loaded file, shown under its row when expanded
+fn render_file_details(ui: &mut Ui, gpx: &gpx::Gpx, display_zone: &DisplayZone) {
+    let segments = gpx
+        .tracks
+        .iter()
+        .map(|track| track.segments.len())
+        .sum::<usize>();
+    let points = gpx
+        .tracks
+        .iter()
+        .flat_map(|track| &track.segments)
+        .map(|segment| segment.points.len())
+        .sum::<usize>();
+    ui.indent("file_details", |ui| {
+        for track in &gpx.tracks {
+            if let Some(name) = &track.name {
+                ui.label(RichText::new(name).small());
+            }
+        }
+        ui.label(
+            RichText::new(format!(
+                "{} track(s) · {} segment(s) · {} points",
+                gpx.tracks.len(),
+                segments,
+                points
+            ))
+            .small()
+            .weak(),
+        );
+        let mut times = gpx
+            .tracks
+            .iter()
+            .flat_map(|track| &track.segments)
+            .flat_map(|segment| &segment.points)
+            .filter_map(|point| point.time)
+            .map(time::OffsetDateTime::from);
+        if let Some(first) = times.next() {
+            let last = times.next_back().unwrap_or(first);
+            ui.label(
+                RichText::new(timezone::format_time_span(first, last, display_zone))
+                    .small()
+                    .weak(),
+            );
+        }
+        let version = format!("GPX {}", gpx_version::version_label(&gpx.version));
+        let source = match &gpx.creator {
+            Some(creator) => format!("{} · created by {}", version, creator),
+            None => version,
+        };
+        ui.label(RichText::new(source).small().weak());
+    });
+}
+
+/// Render the polygon region-of-interest controls (used in Tracks tab)
+fn render_roi_controls(ui: &mut Ui, state: &mut AppState) {
+    let Ok(mut roi) = state.roi.try_write() else {
+        return;
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("⬠ Region:");
+        if roi.drawing {
+            if ui
+                .add_enabled(roi.vertices.len() >= 3, egui::Button::new("✔ Close"))
+                .clicked()
+            {
+                roi.close();
+            }
+            if ui.button("✕ Cancel").clicked() {
+                roi.clear();
+            }
+        } else {
+            if ui
+                .button("✏ Draw")
+                .on_hover_text("Only the parts of tracks inside the polygon are drawn")
+                .clicked()
+            {
+                roi.start_drawing();
+            }
+            if !roi.vertices.is_empty() && ui.button("🗑 Remove").clicked() {
+                roi.clear();
+            }
+        }
+    });
+
+    if roi.drawing {
+        ui.label(
+            RichText::new("Click the map to add vertices, double-click to close")
+                .small()
+                .weak(),
+        );
+    }
+}
+
+/// Render the split tool controls (used in Tracks tab)
+fn render_split_controls(ui: &mut Ui, state: &mut AppState) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("✂ Split:");
+        if state.split.is_active() {
+            if ui
+                .add_enabled(
+                    state.split.preview.is_some(),
+                    egui::Button::new("✔ Split here"),
+                )
+                .clicked()
+            {
+                state.confirm_split();
+            }
+            if ui.button("✕ Cancel").clicked() {
+                state.split.cancel();
+            }
+        } else {
+            if ui
+                .add_enabled(state.can_split(), egui::Button::new("✂ Split at point"))
+                .on_hover_text("Cut the selected track in two where you click it")
+                .on_disabled_hover_text("Select a track first; not while files are loading")
+                .clicked()
+            {
+                state.start_split();
+            }
+            if let Some(parts) = state.split.undo.last().map(|undo| undo.parts.clone())
+                && ui.button("↶ Undo split").clicked()
+            {
+                state.undo_split(&parts);
+            }
+        }
+    });
+
+    if state.split.is_active() {
+        let hint = if state.split.preview.is_some() {
+            "The first part ends at the white dot, where the second starts. \
+             Click elsewhere on the track to move the cut."
+        } else {
+            "Click the selected track where it should be cut"
+        };
+        ui.label(RichText::new(hint).small().weak());
+    }
+}
+
+/// Save one part of a split file through the save dialog
+fn export_split_part(state: &mut AppState, idx: usize) {
+    let (path, gpx, _, _) = &state.file_loader.loaded_files[idx];
+    let file_name = path
+        .to_string_lossy()
+        .trim_start_matches(SPLIT_PATH_PREFIX)
+        .replace([' ', ':'], "_");
+    match crate::app::recorder::to_gpx_string(gpx) {
+        Ok(text) => {
+            eframe_entrypoints::file_picker::save_native_file(&file_name, text.into_bytes());
+        }
+        Err(e) => {
+            tracing::error!("Failed to export {}: {}", file_name, e);
+            state.notify_error(format!("Could not save {}: {}", file_name, e));
+        }
+    }
+}
+
+/// Render the corridor comparison between two routes (used in Tracks tab)
+fn render_comparison_controls(ui: &mut Ui, state: &mut AppState) {
+    let route_names: Vec<(usize, String)> = state
+        .file_loader
+        .loaded_files
+        .iter()
+        .map(|(path, _, start_idx, _)| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            (*start_idx, name.to_string())
+        })
+        .collect();
+    let name_of = |route: Option<usize>| {
+        route
+            .and_then(|route| route_names.iter().find(|(idx, _)| *idx == route))
+            .map_or("Pick a route", |(_, name)| name.as_str())
+            .to_string()
+    };
+
+    let mut run = false;
+    egui::CollapsingHeader::new("🔀 Compare routes")
+        .id_salt("corridor_comparison")
+        .show(ui, |ui| {
+            let comparison = &mut state.comparison;
+            egui::Grid::new("comparison_grid")
+                .num_columns(2)
+                .spacing([8.0, 4.0])
+                .show(ui, |ui| {
+                    for (label, picked) in [
+                        ("Reference:", &mut comparison.reference),
+                        ("Recorded:", &mut comparison.recorded),
+                    ] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(label)
+                            .selected_text(name_of(*picked))
+                            .width(180.0)
+                            .show_ui(ui, |ui| {
+                                for (idx, name) in &route_names {
+                                    ui.selectable_value(picked, Some(*idx), name);
+                                }
+                            });
+                        ui.end_row();
+                    }
+
+                    ui.label("Corridor:");
+                    ui.add(
+                        egui::Slider::new(&mut comparison.corridor_m, 5.0..=1000.0)
+                            .logarithmic(true)
+                            .suffix(" m"),
+                    )
+                    .on_hover_text(
+                        "Distance on each side of the reference that counts as on route",
+                    );
+                    ui.end_row();
+                });
+
+            ui.horizontal(|ui| {
+                run = ui
+                    .add_enabled(comparison.can_run(), egui::Button::new("▶ Compare"))
+                    .clicked();
+                if comparison.result.is_some() && ui.button("✕ Clear").clicked() {
+                    comparison.result = None;
+                }
+            });
+
+            if let Some(result) = &comparison.result {
+                let report = &result.report;
+                ui.label(
+                    RichText::new(format!(
+                        "{:.1}% of {} within {:.0} m of {}",
+                        report.fraction_inside() * 100.0,
+                        name_of(Some(result.recorded)),
+                        report.corridor_m,
+                        name_of(Some(result.reference)),
+                    ))
+                    .strong(),
+                );
+                ui.label(format!(
+                    "Outside: {:.2} of {:.2} km in {} section(s)",
+                    (report.total_length_m - report.inside_length_m) / 1000.0,
+                    report.total_length_m / 1000.0,
+                    report.off_corridor.len()
+                ));
+                ui.label(format!(
+                    "Largest excursion: {:.0} m",
+                    report.max_excursion_m
+                ));
+                if !report.off_corridor.is_empty() {
+                    ui.label(
+                        RichText::new("Off-corridor sections are highlighted in red")
+                            .small()
+                            .weak(),
+                    );
+                }
+
+                ui.separator();
+                let diff = &result.diff;
+                egui::Grid::new("comparison_diff_grid")
+                    .num_columns(2)
+                    .spacing([8.0, 2.0])
+                    .show(ui, |ui| {
+                        for (color, route) in COMPARE_COLORS
+                            .iter()
+                            .zip([result.reference, result.recorded])
+                        {
+                            ui.label(RichText::new("━━").color(*color).strong());
+                            ui.label(name_of(Some(route)));
+                            ui.end_row();
+                        }
+                        ui.label("Distance Δ:");
+                        ui.label(format!("{:+.2} km", diff.distance_delta_m / 1000.0));
+                        ui.end_row();
+                        ui.label("Overlap:");
+                        ui.label(format!("{:.1}%", diff.overlap * 100.0))
+                            .on_hover_text("Share of both routes within the corridor of the other");
+                        ui.end_row();
+                        ui.label("Elevation gain Δ:");
+                        ui.label(diff.elevation_gain_delta_m.map_or_else(
+                            || "No elevation data".to_string(),
+                            |delta| format!("{:+.0} m", delta),
+                        ));
+                        ui.end_row();
+                    });
+            }
+        });
+
+    if run {
+        state.run_comparison();
     }
 }
 
 /// Render statistics section (used in Tracks tab)
-fn render_stats_section(ui: &mut Ui, state: &AppState) {
+fn render_stats_section(ui: &mut Ui, state: &mut AppState) {
     // Profiling scope for the stats rendering since it's often used to surface
     // performance numbers; seeing this in traces helps correlate UI cost with data.
     #[cfg(feature = "profiling")]
@@ -338,57 +1126,149 @@ fn render_stats_section(ui: &mut Ui, state: &AppState) {
             ui.label(RichText::new(state.stats.format_distance()).strong());
             ui.end_row();
 
-            // Performance stats (if we have query data)
-            if state.stats.last_query_time_ms >= 0.0 {
+            if let Some(elevation) = state.stats.format_elevation_range() {
+                ui.label("Elevation:");
+                ui.label(RichText::new(elevation).strong());
+                ui.end_row();
+            }
+
+            if let Some(climb) = state.stats.climb {
+                let threshold = format!(
+                    "Elevation changes under {} m are ignored as GPS noise (see Settings)",
+                    state.ui_settings.climb_threshold_m
+                );
+                ui.label("Ascent:");
+                ui.label(RichText::new(format!("{:.0} m", climb.ascent_m)).strong())
+                    .on_hover_text(&threshold);
+                ui.end_row();
+
+                ui.label("Descent:");
+                ui.label(RichText::new(format!("{:.0} m", climb.descent_m)).strong())
+                    .on_hover_text(&threshold);
+                ui.end_row();
+            }
+
+            // Performance stats (if we have query data), smoothed so they can be read
+            let stats = &state.stats;
+            let query_time_ms = stats
+                .smoothed_query_time_ms
+                .value()
+                .unwrap_or(stats.last_query_time_ms);
+            let segments = stats
+                .smoothed_query_segments
+                .value()
+                .map_or(stats.last_query_segments, |v| v.round() as usize);
+            let points = stats
+                .smoothed_query_simplified_points
+                .value()
+                .map_or(stats.last_query_simplified_points, |v| v.round() as usize);
+            if query_time_ms >= 0.0 {
                 ui.separator();
                 ui.separator();
                 ui.end_row();
 
                 ui.label("Query Time:");
-                let time_color = if state.stats.last_query_time_ms < 16.0 {
+                let time_color = if query_time_ms < 16.0 {
                     Color32::GREEN
-                } else if state.stats.last_query_time_ms < 50.0 {
+                } else if query_time_ms < 50.0 {
                     Color32::YELLOW
                 } else {
                     Color32::RED
                 };
-                ui.label(
-                    RichText::new(format!("{:.1} ms", state.stats.last_query_time_ms))
-                        .color(time_color),
-                );
+                ui.label(RichText::new(format!("{:.1} ms", query_time_ms)).color(time_color));
                 ui.end_row();
 
                 ui.label("Segments:");
-                ui.label(RichText::new(format!("{}", state.stats.last_query_segments)).strong());
+                ui.label(RichText::new(format!("{}", segments)).strong());
                 ui.end_row();
 
                 ui.label("Points Rendered:");
-                let reduction_text = if state.stats.total_points > 0 {
-                    let pct = 100.0
-                        * (1.0
-                            - state.stats.last_query_simplified_points as f64
-                                / state.stats.total_points as f64);
+                let reduction_text = if stats.total_points > 0 {
+                    let pct = 100.0 * (1.0 - points as f64 / stats.total_points as f64);
+                    format!("{} ({:.0}% reduced)", points, pct)
+                } else {
+                    format!("{}", points)
+                };
+                ui.label(RichText::new(reduction_text).strong());
+                ui.end_row();
+
+                ui.label("Paint:");
+                let paint_text = if state.ui_settings.show_outline {
                     format!(
-                        "{} ({:.0}% reduced)",
-                        state.stats.last_query_simplified_points, pct
+                        "{:.2} ms outlines + {:.2} ms lines",
+                        stats.last_outline_pass_ms, stats.last_line_pass_ms
                     )
                 } else {
-                    format!("{}", state.stats.last_query_simplified_points)
+                    format!("{:.2} ms", stats.last_line_pass_ms)
                 };
-                ui.label(RichText::new(reduction_text).strong());
+                ui.label(paint_text)
+                    .on_hover_text("Time spent handing the frame's track shapes to the painter");
                 ui.end_row();
             }
         });
+
+    if state.stats.route_count > 0 {
+        ui.add_space(4.0);
+        if ui
+            .button("📄 Export stats CSV")
+            .on_hover_text("Save distance, elevation and extent of every route as a spreadsheet")
+            .clicked()
+        {
+            export_stats_csv(state);
+        }
+        if !state.stats.visible_routes.is_empty()
+            && ui
+                .button("🖼 Export tracks SVG")
+                .on_hover_text(
+                    "Save the tracks on the map in their colors, with a legend, as a vector \
+                     drawing for documents and print",
+                )
+                .clicked()
+        {
+            export_tracks_svg(state);
+        }
+    }
 }
 
-/// Render the Settings tab
-fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
-    // Per-settings panel profiling scope so toggling and slider interactions are visible.
-    #[cfg(feature = "profiling")]
-    profiling::scope!("ui::render_settings_tab");
+/// Ask where to save the tracks on the map and their legend as SVG
+fn export_tracks_svg(state: &mut AppState) {
+    let Some(routes) = state.svg_routes() else {
+        state.notify_warn("Tracks are still loading, try exporting again in a moment");
+        return;
+    };
+    let svg = svg_export::to_svg(&routes, state.ui_settings.line_width);
+    eframe_entrypoints::file_picker::save_native_file("tracks.svg", svg.into_bytes());
+}
 
-    // Track Appearance section
-    ui.label(RichText::new("🎨 Track Appearance").strong());
+/// Ask where to save the per-route statistics as CSV
+fn export_stats_csv(state: &mut AppState) {
+    let csv = state.route_collection.try_read().ok().map(|collection| {
+        let mut bytes = Vec::new();
+        collection.write_stats_csv(&mut bytes).map(|()| bytes)
+    });
+    let Some(csv) = csv else {
+        state.notify_warn("Tracks are still loading, try exporting again in a moment");
+        return;
+    };
+    match csv {
+        Ok(bytes) => {
+            eframe_entrypoints::file_picker::save_native_file("track-stats.csv", bytes);
+        }
+        Err(e) => {
+            tracing::error!("Failed to export stats: {}", e);
+            state.notify_error(format!("Could not export stats: {}", e));
+        }
+    }
+}
+
+/// Render the Settings tab
+fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
+    // Per-settings panel profiling scope so toggling and slider interactions are visible.
+    #[cfg(feature = "profiling")]
+    profiling::scope!("ui::render_settings_tab");
+
+    // Track Appearance section
+    ui.label(RichText::new("🎨 Track Appearance").strong());
     ui.add_space(6.0);
 
     egui::Grid::new("appearance_grid")
@@ -396,11 +1276,35 @@ fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
         .spacing([12.0, 8.0])
         .show(ui, |ui| {
             ui.label("Line Width:");
-            ui.add(
-                egui::Slider::new(&mut state.ui_settings.line_width, 0.5..=8.0)
-                    .suffix(" px")
-                    .step_by(0.5),
-            );
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("width_mode")
+                    .selected_text(state.ui_settings.width_mode.name())
+                    .show_ui(ui, |ui| {
+                        for mode in WidthMode::all() {
+                            ui.selectable_value(
+                                &mut state.ui_settings.width_mode,
+                                *mode,
+                                mode.name(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Pixels keep the same width at any zoom; meters scale with the map",
+                    );
+                match state.ui_settings.width_mode {
+                    WidthMode::Pixels => ui.add(
+                        egui::Slider::new(&mut state.ui_settings.line_width, 0.5..=8.0)
+                            .suffix(" px")
+                            .step_by(0.5),
+                    ),
+                    WidthMode::Meters => ui.add(
+                        egui::Slider::new(&mut state.ui_settings.line_width_m, 1.0..=1000.0)
+                            .suffix(" m")
+                            .logarithmic(true),
+                    ),
+                };
+            });
             ui.end_row();
 
             ui.label("Show Outline:");
@@ -409,6 +1313,202 @@ fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
                 "Dark border for visibility",
             );
             ui.end_row();
+
+            ui.label("Overlaps:");
+            egui::ComboBox::from_id_salt("blend_mode")
+                .selected_text(state.ui_settings.blend_mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in BlendMode::all() {
+                        ui.selectable_value(&mut state.ui_settings.blend_mode, *mode, mode.name());
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Additive makes tracks that overlap brighter, showing where many of them run; colors can wash out to white",
+                );
+            ui.end_row();
+
+            ui.label("Stacked Tracks:");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut state.ui_settings.offset_stacked_tracks, "Side by side")
+                    .on_hover_text(
+                        "Tracks on the same path are drawn next to each other instead of on top, when zoomed in",
+                    );
+                if state.ui_settings.offset_stacked_tracks {
+                    ui.add(
+                        egui::Slider::new(&mut state.ui_settings.stack_offset, 1.0..=8.0)
+                            .suffix(" px"),
+                    );
+                }
+            });
+            ui.end_row();
+
+            ui.label("Show Tracks:");
+            let mut show_tracks = state.ui_settings.show_tracks;
+            let keys = state
+                .shortcuts
+                .keys_text(ui.ctx(), ShortcutAction::ToggleTracks);
+            if ui
+                .checkbox(&mut show_tracks, "Draw loaded tracks")
+                .on_hover_text(format!(
+                    "Off leaves only the base map and skips all track work ({})",
+                    keys
+                ))
+                .changed()
+            {
+                state.toggle_tracks(ui.ctx());
+            }
+            ui.end_row();
+
+            ui.label("On Selection:");
+            ui.checkbox(&mut state.ui_settings.zoom_on_select, "Zoom to the track")
+                .on_hover_text("Fit the map to a track when it is picked in the Tracks list");
+            ui.end_row();
+
+            ui.label("Crosshair:");
+            ui.checkbox(
+                &mut state.ui_settings.show_center_crosshair,
+                "Mark the map center",
+            );
+            ui.end_row();
+
+            ui.label("Distance Rings:");
+            ui.checkbox(
+                &mut state.ui_settings.show_distance_grid,
+                "Rings at round distances from the center",
+            );
+            ui.end_row();
+
+            ui.label("Legend:");
+            ui.checkbox(
+                &mut state.ui_settings.show_legend,
+                "List the tracks in view over the map",
+            )
+            .on_hover_text("Point at a track in the legend to highlight it, click to select it");
+            ui.end_row();
+
+            ui.label("Waypoints:");
+            ui.checkbox(
+                &mut state.ui_settings.show_waypoints,
+                "Show waypoints by symbol",
+            );
+            ui.end_row();
+
+            ui.label("Sidecar Files:");
+            ui.checkbox(
+                &mut state.ui_settings.track_sidecars,
+                "Share colors, notes and tags",
+            )
+            .on_hover_text(
+                "Write them to a .ltv.json file next to each GPX when edited, and read it when the file is loaded; it wins over what is stored locally",
+            );
+            ui.end_row();
+
+            ui.label("Theme:");
+            egui::ComboBox::from_id_salt("theme")
+                .selected_text(format!("{:?}", state.ui_settings.theme))
+                .show_ui(ui, |ui| {
+                    for theme in [
+                        egui::ThemePreference::System,
+                        egui::ThemePreference::Light,
+                        egui::ThemePreference::Dark,
+                    ] {
+                        ui.selectable_value(
+                            &mut state.ui_settings.theme,
+                            theme,
+                            format!("{:?}", theme),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("System follows the light or dark mode of the OS or browser");
+            ui.end_row();
+
+            ui.label("Coordinates:");
+            egui::ComboBox::from_id_salt("coord_format")
+                .selected_text(state.ui_settings.coord_format.name())
+                .show_ui(ui, |ui| {
+                    for format in CoordFormat::all() {
+                        ui.selectable_value(
+                            &mut state.ui_settings.coord_format,
+                            *format,
+                            format.name(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Used by the center readout and when copying coordinates; the map's go-to field reads any of them",
+                );
+            ui.end_row();
+
+            ui.label("Times:");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("time_zone")
+                    .selected_text(state.ui_settings.time_zone.name())
+                    .show_ui(ui, |ui| {
+                        for zone in TimeZone::all() {
+                            ui.selectable_value(&mut state.ui_settings.time_zone, *zone, zone.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Time zone of the recording times in file details and playback");
+                let zone = state.ui_settings.time_zone;
+                let local_unavailable = zone == TimeZone::Local && state.local_offset.is_none();
+                if local_unavailable {
+                    ui.label("⚠ Local time isn't available, using")
+                        .on_hover_text("This system doesn't report its time zone; set its offset from UTC");
+                }
+                if zone == TimeZone::Fixed || local_unavailable {
+                    let response = ui.add(
+                        egui::DragValue::new(&mut state.ui_settings.utc_offset_minutes)
+                            .range(-12 * 60..=14 * 60)
+                            .speed(15.0)
+                            .custom_formatter(|minutes, _| {
+                                let minutes = minutes as i32;
+                                format!(
+                                    "UTC{}{:02}:{:02}",
+                                    if minutes < 0 { '-' } else { '+' },
+                                    minutes.abs() / 60,
+                                    minutes.abs() % 60
+                                )
+                            }),
+                    );
+                    if local_unavailable {
+                        response.on_hover_text(
+                            "This system doesn't report its time zone; set its offset from UTC",
+                        );
+                    }
+                }
+                if zone == TimeZone::Fixed
+                    && let Some(hint) = state.selected_offset_hint()
+                    && ui
+                        .small_button("From track")
+                        .on_hover_text(
+                            "Use the time zone the selected track's start longitude suggests; borders and daylight saving aren't known",
+                        )
+                        .clicked()
+                {
+                    state.ui_settings.utc_offset_minutes = i32::from(hint.whole_minutes());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Climb Threshold:");
+            if ui
+                .add(
+                    egui::Slider::new(&mut state.ui_settings.climb_threshold_m, 0.0..=20.0)
+                        .step_by(0.5)
+                        .suffix(" m"),
+                )
+                .on_hover_text(
+                    "Ascent and descent ignore elevation changes smaller than this, which are mostly GPS noise (0 counts every change)",
+                )
+                .changed()
+            {
+                state.update_stats();
+            }
+            ui.end_row();
         });
 
     ui.add_space(4.0);
@@ -418,6 +1518,33 @@ fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
             .weak(),
     );
 
+    ui.add_space(4.0);
+    egui::CollapsingHeader::new("Line styles")
+        .id_salt("style_rules")
+        .show(ui, |ui| render_style_rules(ui, state));
+
+    if state.ui_settings.show_waypoints {
+        ui.add_space(4.0);
+        egui::CollapsingHeader::new("Waypoint symbols")
+            .id_salt("waypoint_symbols")
+            .show(ui, |ui| {
+                for icon in symbols::SYMBOL_ICONS
+                    .iter()
+                    .chain([&symbols::FALLBACK_ICON])
+                {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(icon.glyph).color(icon.color).strong());
+                        ui.label(icon.label);
+                    });
+                }
+                ui.label(
+                    RichText::new("Picked from each waypoint's GPX symbol name, e.g. \"Summit\"")
+                        .small()
+                        .weak(),
+                );
+            });
+    }
+
     ui.add_space(12.0);
     ui.separator();
     ui.add_space(8.0);
@@ -426,29 +1553,132 @@ fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
     ui.label(RichText::new("📐 Level of Detail").strong());
     ui.add_space(6.0);
 
-    ui.label("LOD Bias (Higher = More Detail):");
+    ui.label("Track Detail:");
     ui.add_space(4.0);
 
-    let mut bias = state.ui_settings.bias;
-    let bias_changed = ui
+    // The slider shows the bias asked for, which may not be applied yet
+    let mut bias = state.bias_debouncer.target();
+    let mut level = detail::bias_to_level(bias);
+    let mut bias_changed = ui
         .add(
-            egui::Slider::new(&mut bias, 0.001..=1000.0)
-                .logarithmic(true)
-                .custom_formatter(|v, _| {
-                    if v >= 1.0 {
-                        format!("{:.0}", v)
-                    } else if v >= 0.01 {
-                        format!("{:.2}", v)
-                    } else {
-                        format!("{:.3}", v)
-                    }
-                }),
+            egui::Slider::new(
+                &mut level,
+                detail::MIN_DETAIL_LEVEL..=detail::MAX_DETAIL_LEVEL,
+            )
+            .custom_formatter(|v, _| format!("{:.0} ({})", v, detail::level_name(v as u32))),
+        )
+        .on_hover_text(
+            "How closely lines follow the recorded points. Higher keeps more points, \
+             lower draws faster. Changing it rebuilds the index.",
         )
         .changed();
+    if bias_changed {
+        bias = detail::level_to_bias(level);
+    }
+
+    egui::CollapsingHeader::new("Advanced: LOD bias")
+        .id_salt("lod_bias_advanced")
+        .show(ui, |ui| {
+            bias_changed |= ui
+                .add(
+                    egui::Slider::new(&mut bias, 0.001..=1000.0)
+                        .logarithmic(true)
+                        .custom_formatter(|v, _| {
+                            if v >= 1.0 {
+                                format!("{:.0}", v)
+                            } else if v >= 0.01 {
+                                format!("{:.2}", v)
+                            } else {
+                                format!("{:.3}", v)
+                            }
+                        }),
+                )
+                .on_hover_text(
+                    "The value the detail level sets: each level doubles it, level 5 is 1. \
+                     Higher = more detail.",
+                )
+                .changed();
+        });
 
     if bias_changed {
         state.update_bias(bias);
     }
+    if state.bias_debouncer.is_pending() {
+        ui.label(RichText::new("Applying…").small().weak())
+            .on_hover_text("The index is rebuilt once the slider is still; until then the map previews the new detail");
+    }
+    ui.add_space(4.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Frame budget:");
+        ui.add(
+            egui::Slider::new(&mut state.ui_settings.frame_budget_ms, 0.0..=100.0)
+                .step_by(1.0)
+                .suffix(" ms"),
+        )
+        .on_hover_text("Draw tracks with less detail while frames take longer than this (0 = off)");
+    });
+    if state.frame_budget.lod_reduction() > 0 {
+        ui.label(
+            RichText::new(format!(
+                "Frames are slow: drawing at {:.0}% detail",
+                state.frame_budget.detail_factor() * 100.0
+            ))
+            .small()
+            .weak(),
+        );
+    }
+
+    ui.add_space(8.0);
+    ui.label("Chunking Aggressiveness:");
+    ui.add_space(4.0);
+
+    let mut aggressiveness = state.ui_settings.chunking_aggressiveness;
+    let aggressiveness_changed = ui
+        .add(egui::Slider::new(&mut aggressiveness, 0.0..=1.0).step_by(0.05))
+        .on_hover_text(
+            "Higher splits tracks into more, smaller pieces: faster panning when zoomed in, \
+             but more per-piece overhead when zoomed out. Changing it rebuilds the index.",
+        )
+        .changed();
+
+    if aggressiveness_changed {
+        state.update_chunking_aggressiveness(aggressiveness);
+    }
+
+    ui.add_space(8.0);
+    let mut boundary_context = state.ui_settings.boundary_context;
+    if ui
+        .checkbox(&mut boundary_context, "Connect lines to the view edge")
+        .on_hover_text(
+            "Draw each line on to its first point outside the view, so tracks reach the \
+             edge instead of stopping short. Off: only points inside the view are joined.",
+        )
+        .changed()
+    {
+        state.update_boundary_context(boundary_context);
+    }
+
+    let mut keep_sharp_turns = state.ui_settings.keep_sharp_turns;
+    if ui
+        .checkbox(&mut keep_sharp_turns, "Keep sharp turns")
+        .on_hover_text(
+            "Put back the tip of hairpins and switchbacks that simplification would cut \
+             straight across, at the cost of a few more points per track.",
+        )
+        .changed()
+    {
+        state.update_keep_sharp_turns(keep_sharp_turns);
+    }
+
+    ui.checkbox(
+        &mut state.ui_settings.full_detail_selection,
+        "Full detail for selection",
+    )
+    .on_hover_text(
+        "Draw the selected route from every recorded point in view, even zoomed out. \
+         The other routes stay simplified.",
+    );
 
     if state.pending_reload && !state.file_loader.loaded_files.is_empty() {
         ui.add_space(4.0);
@@ -459,62 +1689,661 @@ fn render_settings_tab(ui: &mut Ui, state: &mut AppState) {
         );
     }
 
-    ui.add_space(12.0);
-    ui.separator();
-    ui.add_space(8.0);
+    ui.add_space(12.0);
+    ui.separator();
+    ui.add_space(8.0);
+
+    // Map Tiles section
+    ui.label(RichText::new("🗺 Map Tiles").strong());
+    ui.add_space(6.0);
+
+    for provider in TilesProvider::all() {
+        let selected = state.ui_settings.tiles_provider == *provider;
+        if ui.selectable_label(selected, provider.name()).clicked() {
+            state.select_tiles_provider(*provider);
+        }
+    }
+    ui.checkbox(
+        &mut state.ui_settings.auto_tile_fallback,
+        "Switch provider when tiles fail",
+    )
+    .on_hover_text(
+        "If the shown provider doesn't serve a test tile, switch to the other one \
+         and try the original again at the next start",
+    );
+    if let Some(preferred) = state.ui_settings.preferred_tiles_provider {
+        ui.label(
+            RichText::new(format!(
+                "{} will be tried again at the next start",
+                preferred.name()
+            ))
+            .small()
+            .weak(),
+        );
+    }
+
+    ui.add_space(4.0);
+    let tiles_note = match state.ui_settings.tiles_provider {
+        TilesProvider::None => "No tile server is contacted in this mode",
+        provider => provider.attribution(),
+    };
+    ui.label(RichText::new(tiles_note).small().italics().weak());
+
+    ui.add_space(6.0);
+    ui.horizontal(|ui| {
+        ui.label("Over-zoom:");
+        ui.add(egui::Slider::new(&mut state.ui_settings.over_zoom_levels, 0..=6).suffix(" levels"))
+            .on_hover_text(
+                "Zoom past the provider's deepest tiles by upscaling them; tracks keep full detail",
+            );
+    });
+    ui.label(
+        RichText::new(format!(
+            "Max zoom {} (tiles up to {})",
+            state.max_map_zoom(),
+            state.ui_settings.tiles_provider.max_zoom()
+        ))
+        .small()
+        .weak(),
+    );
+
+    ui.add_space(6.0);
+    ui.checkbox(&mut state.ui_settings.reduce_motion, "Reduce animations")
+        .on_hover_text(
+            "No fades, and the map jumps instead of gliding. \
+             Starts on if the browser asks for reduced motion",
+        );
+    let animate = motion::animations_enabled(&state.ui_settings);
+    ui.add_enabled_ui(animate, |ui| {
+        ui.checkbox(&mut state.ui_settings.pan_inertia, "Pan inertia")
+            .on_hover_text("Keep the map gliding after a drag is let go");
+    });
+    let gliding = state.ui_settings.pan_inertia && animate;
+    ui.add_enabled_ui(gliding, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Friction:");
+            ui.add(egui::Slider::new(
+                &mut state.ui_settings.pan_friction,
+                inertia::PAN_FRICTION_RANGE,
+            ))
+            .on_hover_text("Higher values stop the glide sooner");
+        });
+    });
+
+    ui.add_space(12.0);
+    ui.separator();
+    ui.add_space(8.0);
+
+    render_live_location_settings(ui, state);
+
+    ui.add_space(12.0);
+    ui.separator();
+    ui.add_space(8.0);
+
+    render_save_settings(ui, state);
+
+    ui.add_space(12.0);
+    ui.separator();
+    ui.add_space(8.0);
+
+    ui.add_space(4.0);
+
+    // Debug section
+    ui.label(RichText::new("🔧 Debug").strong());
+    ui.add_space(6.0);
+
+    ui.label(
+        RichText::new(format!(
+            "Memory budget: {} · loaded ≈ {} · last import ≈ {}",
+            format_bytes(state.memory_budget_bytes),
+            format_bytes(state.stats.estimated_loaded_bytes),
+            format_bytes(state.stats.last_import_estimate_bytes)
+        ))
+        .small()
+        .weak(),
+    )
+    .on_hover_text("Estimates from file sizes; set the budget with --memory-budget-mb");
+    ui.checkbox(
+        &mut state.ui_settings.show_memory_readout,
+        "Show memory usage",
+    );
+    if state.ui_settings.show_memory_readout {
+        state.sample_memory_readout();
+        state
+            .activity
+            .keep_awake("memory readout", MEMORY_READOUT_INTERVAL);
+        let readout = &state.stats.memory_readout;
+        ui.label(
+            RichText::new(format!(
+                "Process: {} · index ≈ {}",
+                readout
+                    .process_bytes
+                    .map_or_else(|| "unknown".to_string(), format_bytes),
+                format_bytes(readout.index_bytes)
+            ))
+            .small()
+            .weak(),
+        )
+        .on_hover_text(format!(
+            "Resident memory of the app and estimated size of the spatial index, every {} s",
+            MEMORY_READOUT_INTERVAL.as_secs()
+        ));
+    }
+    let repaints = match state.activity.last_reasons() {
+        [] => "Idle: repaints on input only".to_string(),
+        reasons => format!("Repainting for: {}", reasons.join(", ")),
+    };
+    ui.label(RichText::new(repaints).small().weak())
+        .on_hover_text("What kept the map redrawing without input last frame");
+    ui.checkbox(
+        &mut state.ui_settings.show_file_extents,
+        "Show file extents",
+    )
+    .on_hover_text(
+        "Outline where each track lies, labeled with its name; click inside an outline to select the track",
+    );
+    if let Some(summary) = state.startup.summary() {
+        ui.label(RichText::new(summary).small().weak())
+            .on_hover_text("How long each stage of this start took, up to the first frame");
+    }
+    ui.add_space(4.0);
+
+    if !state.file_loader.dismissed_errors.is_empty() {
+        egui::CollapsingHeader::new(format!(
+            "Dismissed errors ({})",
+            state.file_loader.dismissed_errors.len()
+        ))
+        .id_salt("dismissed_errors")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .id_salt("dismissed_errors_scroll")
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    for (file, error) in &state.file_loader.dismissed_errors {
+                        ui.label(
+                            RichText::new(format!("• {}: {}", file.display(), error))
+                                .small()
+                                .weak(),
+                        );
+                    }
+                });
+            if ui.small_button("Clear log").clicked() {
+                state.file_loader.dismissed_errors.clear();
+            }
+        });
+        ui.add_space(4.0);
+    }
+
+    egui::CollapsingHeader::new("Why is something missing?")
+        .id_salt("skipped_segments")
+        .show(ui, |ui| {
+            let skipped = &state.stats.last_query_skipped;
+            let queried = state.stats.last_query_segments;
+            ui.label(
+                RichText::new(format!(
+                    "Last frame: {} segment(s) in view, {} drawn",
+                    queried,
+                    queried.saturating_sub(skipped.total())
+                ))
+                .small(),
+            );
+            ui.label(
+                RichText::new(format!(
+                    "  {} outside the region of interest",
+                    skipped.outside_region
+                ))
+                .small()
+                .weak(),
+            );
+            ui.label(
+                RichText::new(format!(
+                    "  {} with too few points to draw",
+                    skipped.too_few_points
+                ))
+                .small()
+                .weak(),
+            );
+            ui.label(
+                RichText::new(format!(
+                    "{} loaded file(s) in view with nothing drawn (marked ⚠ in the Tracks tab)",
+                    state.stats.fully_skipped_routes.len()
+                ))
+                .small()
+                .weak(),
+            );
+        });
+    ui.add_space(4.0);
+
+    egui::CollapsingHeader::new("Generate test tracks…")
+        .id_salt("synthetic_tracks")
+        .show(ui, |ui| render_synthetic_tracks(ui, state));
+    ui.add_space(4.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Stats smoothing:");
+        ui.add(
+            egui::Slider::new(&mut state.ui_settings.stats_smoothing_secs, 0.0..=2.0)
+                .step_by(0.1)
+                .suffix(" s"),
+        )
+        .on_hover_text("How long the performance numbers take to follow a change (0 = raw values)");
+    });
+    ui.add_space(4.0);
+
+    if eframe_entrypoints::profiling::is_available() {
+        ui.checkbox(&mut state.ui_settings.show_profiling, "Show profiling data");
+        if state.ui_settings.show_profiling {
+            ui.add_space(4.0);
+            eframe_entrypoints::profiling_ui(ui);
+        }
+    } else {
+        ui.label(
+            RichText::new(
+                "Profiling is not part of this build; build with --features profiling to enable it",
+            )
+            .small()
+            .weak(),
+        );
+    }
+
+    ui.add_space(12.0);
+    ui.separator();
+    ui.add_space(8.0);
+
+    // About section
+    ui.label(RichText::new("ℹ About").strong());
+    ui.add_space(4.0);
+    ui.label(RichText::new("Large Track Viewer").small());
+    ui.label(
+        RichText::new("Efficiently view large GPS tracks with LOD")
+            .small()
+            .weak(),
+    );
+    ui.add_space(4.0);
+    ui.label(RichText::new("Keyboard shortcuts:").small());
+    for line in state.shortcuts.help_lines(ui.ctx()) {
+        ui.label(RichText::new(format!("  {}", line)).small().weak());
+    }
+    let scroll_zoom = state.shortcuts.scroll_zoom_text(ui.ctx());
+    ui.label(
+        RichText::new(format!("  {} - Zoom map", scroll_zoom))
+            .small()
+            .weak(),
+    );
+}
+
+/// Editor of the rules that dash and color tracks by file name or folder
+fn render_style_rules(ui: &mut Ui, state: &mut AppState) {
+    ui.label(
+        RichText::new(
+            "Files whose path contains the text, or matches a glob like \"planned-*\", \
+             get the rule's line; the first matching rule wins",
+        )
+        .small()
+        .weak(),
+    );
+    let rules = &mut state.ui_settings.style_rules;
+    let mut move_up = None;
+    let mut remove = None;
+    for (index, rule) in rules.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut rule.pattern)
+                    .hint_text("planned-*")
+                    .desired_width(110.0),
+            );
+            egui::ComboBox::from_id_salt(("style_rule_dash", index))
+                .width(70.0)
+                .selected_text(rule.dash.name())
+                .show_ui(ui, |ui| {
+                    for dash in DashPattern::all() {
+                        ui.selectable_value(&mut rule.dash, *dash, dash.name());
+                    }
+                });
+            let mut has_color = rule.color.is_some();
+            if ui
+                .checkbox(&mut has_color, "")
+                .on_hover_text("Draw matching files in one color")
+                .changed()
+            {
+                rule.color = has_color.then_some([0x1f, 0x77, 0xb4]);
+            }
+            if let Some(rgb) = &mut rule.color {
+                ui.color_edit_button_srgb(rgb);
+            }
+            if ui
+                .add_enabled(index > 0, egui::Button::new("⏶").small())
+                .on_hover_text("Try this rule earlier")
+                .clicked()
+            {
+                move_up = Some(index);
+            }
+            if ui.small_button("🗑").on_hover_text("Remove rule").clicked() {
+                remove = Some(index);
+            }
+        });
+    }
+    if let Some(index) = move_up {
+        rules.swap(index - 1, index);
+    }
+    if let Some(index) = remove {
+        rules.remove(index);
+    }
+    if ui.small_button("➕ Add rule").clicked() {
+        rules.push(StyleRule {
+            dash: DashPattern::Dashed,
+            ..Default::default()
+        });
+    }
+}
+
+/// Parameters and button of the Debug section's test track generator
+fn render_synthetic_tracks(ui: &mut Ui, state: &mut AppState) {
+    ui.label(
+        RichText::new("Random walks for demos and benchmarks; the same seed gives the same tracks")
+            .small()
+            .weak(),
+    );
+    let params = &mut state.synthetic_params;
+    egui::Grid::new("synthetic_grid")
+        .num_columns(2)
+        .spacing([8.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Routes:");
+            ui.add(egui::DragValue::new(&mut params.route_count).range(1..=10_000));
+            ui.end_row();
+
+            ui.label("Points per route:");
+            ui.add(
+                egui::DragValue::new(&mut params.points_per_route)
+                    .range(2..=1_000_000)
+                    .speed(100.0),
+            );
+            ui.end_row();
+
+            ui.label("Latitude:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut params.min_lat)
+                        .range(-85.0..=85.0)
+                        .speed(0.1),
+                );
+                ui.label("to");
+                ui.add(
+                    egui::DragValue::new(&mut params.max_lat)
+                        .range(-85.0..=85.0)
+                        .speed(0.1),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Longitude:");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut params.min_lon)
+                        .range(-180.0..=180.0)
+                        .speed(0.1),
+                );
+                ui.label("to");
+                ui.add(
+                    egui::DragValue::new(&mut params.max_lon)
+                        .range(-180.0..=180.0)
+                        .speed(0.1),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Seed:");
+            ui.add(egui::DragValue::new(&mut params.seed));
+            ui.end_row();
+
+            ui.label("Include:");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut params.timestamps, "Timestamps");
+                ui.checkbox(&mut params.elevation, "Elevation");
+            });
+            ui.end_row();
+        });
+
+    let total = params.route_count.saturating_mul(params.points_per_route);
+    if ui
+        .button("Generate")
+        .on_hover_text(format!("Adds {} points to the map", total))
+        .clicked()
+    {
+        state.add_synthetic_routes();
+    }
+}
+
+/// Autosave interval and manual save controls for the settings tab
+fn render_save_settings(ui: &mut Ui, state: &mut AppState) {
+    ui.label(RichText::new("💾 Saving").strong());
+    ui.add_space(6.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Autosave every:");
+        ui.add(
+            egui::Slider::new(&mut state.ui_settings.autosave_interval_secs, 5..=600)
+                .logarithmic(true)
+                .suffix(" s"),
+        )
+        .on_hover_text(
+            "Settings are also saved after each loaded file. Closing the app saves \
+             too, but on phones and in browsers the app is often killed instead.",
+        );
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("On startup:");
+        egui::ComboBox::from_id_salt("startup_mode")
+            .selected_text(state.ui_settings.startup_mode.name())
+            .show_ui(ui, |ui| {
+                for mode in StartupMode::all() {
+                    ui.selectable_value(&mut state.ui_settings.startup_mode, *mode, mode.name());
+                }
+            })
+            .response
+            .on_hover_text("Start with --fresh to start empty once without changing this");
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Load first:");
+        egui::ComboBox::from_id_salt("file_precedence")
+            .selected_text(state.ui_settings.file_precedence.name())
+            .show_ui(ui, |ui| {
+                for precedence in FilePrecedence::all() {
+                    ui.selectable_value(
+                        &mut state.ui_settings.file_precedence,
+                        *precedence,
+                        precedence.name(),
+                    );
+                }
+            })
+            .response
+            .on_hover_text(
+                "Which files get loaded, and so numbered and colored, first when a start \
+                 restores files and also gets some on the command line",
+            );
+    });
+
+    ui.add_space(4.0);
+    egui::CollapsingHeader::new("Profiles")
+        .id_salt("settings_profiles")
+        .show(ui, |ui| render_settings_profiles(ui, state));
+
+    ui.horizontal(|ui| {
+        if ui.button("💾 Save now").clicked() {
+            state.save_requested = true;
+        }
+        let saved = match state.last_saved_at {
+            Some(at) => {
+                // Keep the readout current: seconds change every second, minutes every minute
+                let elapsed = at.elapsed();
+                let tick = if elapsed.as_secs() < 60 { 1 } else { 60 };
+                state
+                    .activity
+                    .keep_awake("save time readout", std::time::Duration::from_secs(tick));
+                format!("Last saved {} ago", format_elapsed(elapsed))
+            }
+            None => "Not saved yet this session".to_string(),
+        };
+        ui.label(RichText::new(saved).small().weak());
+    });
+    if let Some(error) = state.save_error.clone() {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(
+                RichText::new(format!("⚠ Saving failed: {}", error))
+                    .small()
+                    .color(ui.visuals().warn_fg_color),
+            );
+            if ui.small_button("Retry").clicked() {
+                state.save_requested = true;
+            }
+        });
+    }
+}
+
+/// Saving, applying and deleting named sets of settings
+fn render_settings_profiles(ui: &mut Ui, state: &mut AppState) {
+    ui.label(
+        RichText::new(
+            "Switch all settings at once, e.g. between rides and presentations; files stay loaded",
+        )
+        .small()
+        .weak(),
+    );
+    let mut apply = None;
+    let mut overwrite = None;
+    let mut delete = None;
+    for name in state.settings_profiles.keys() {
+        ui.horizontal(|ui| {
+            ui.label(name);
+            if ui.small_button("Apply").clicked() {
+                apply = Some(name.clone());
+            }
+            if ui
+                .small_button("💾")
+                .on_hover_text("Save the current settings over this profile")
+                .clicked()
+            {
+                overwrite = Some(name.clone());
+            }
+            if ui
+                .small_button("🗑")
+                .on_hover_text("Delete profile")
+                .clicked()
+            {
+                delete = Some(name.clone());
+            }
+        });
+    }
+    if let Some(name) = apply {
+        state.apply_settings_profile(&name);
+    }
+    if let Some(name) = overwrite {
+        state.save_settings_profile(&name);
+    }
+    if let Some(name) = delete {
+        state.delete_settings_profile(&name);
+    }
+
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut state.new_profile_name)
+                .hint_text("Profile name")
+                .desired_width(120.0),
+        );
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if (submitted || ui.small_button("➕ Save as profile").clicked())
+            && !state.new_profile_name.trim().is_empty()
+        {
+            let name = std::mem::take(&mut state.new_profile_name);
+            state.save_settings_profile(&name);
+        }
+    });
+}
+
+/// Short human readable duration, e.g. "45 s" or "3 min"
+fn format_elapsed(elapsed: std::time::Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{} s", secs)
+    } else if secs < 3600 {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} h", secs / 3600)
+    }
+}
+
+/// Opt-in live position controls for the settings tab
+fn render_live_location_settings(ui: &mut Ui, state: &mut AppState) {
+    let recording = state.recorder.is_some();
+    let live = &mut state.live_location;
 
-    // Map Tiles section
-    ui.label(RichText::new("🗺 Map Tiles").strong());
+    ui.label(RichText::new("📍 Live Position").strong());
     ui.add_space(6.0);
 
-    for provider in TilesProvider::all() {
-        let selected = state.ui_settings.tiles_provider == *provider;
-        if ui.selectable_label(selected, provider.name()).clicked() {
-            state.ui_settings.tiles_provider = *provider;
-        }
+    let mut enabled = live.is_enabled();
+    // Turning the position off mid-recording would silently stall the track
+    if ui
+        .add_enabled(
+            !recording,
+            egui::Checkbox::new(&mut enabled, "Show my position"),
+        )
+        .changed()
+    {
+        live.set_enabled(enabled);
     }
-
-    ui.add_space(4.0);
     ui.label(
-        RichText::new(state.ui_settings.tiles_provider.attribution())
-            .small()
-            .italics()
-            .weak(),
+        RichText::new(
+            "Off by default. Enabling it asks your browser or system for location \
+             permission. The position is only drawn on the map and is never sent \
+             anywhere; it is kept only while you record a track.",
+        )
+        .small()
+        .weak(),
     );
 
-    ui.add_space(12.0);
-    ui.separator();
-    ui.add_space(8.0);
+    ui.add_enabled(
+        live.is_enabled(),
+        egui::Checkbox::new(&mut live.follow, "Keep map centered on me"),
+    );
 
-    ui.add_space(4.0);
+    if let Some(error) = live.error() {
+        ui.label(
+            RichText::new(format!("⚠ {}", error))
+                .small()
+                .color(ui.visuals().warn_fg_color),
+        );
+    } else if live.is_enabled() {
+        let status = match live.fix() {
+            None => "Waiting for a position fix...".to_string(),
+            Some(fix) if live.has_current_fix() => format!("Accuracy ±{:.0} m", fix.accuracy_m),
+            Some(_) => "Fix lost, showing last known position".to_string(),
+        };
+        ui.label(RichText::new(status).small().weak());
+    }
 
-    // Debug section
-    ui.label(RichText::new("🔧 Debug").strong());
     ui.add_space(6.0);
-
-    ui.checkbox(&mut state.ui_settings.show_profiling, "Show profiling data");
-    if state.ui_settings.show_profiling {
-        ui.add_space(4.0);
-        eframe_entrypoints::profiling_ui(ui);
+    match &state.recorder {
+        Some(recorder) => {
+            let label = format!("⏹ Stop recording ({} points)", recorder.point_count());
+            if ui.button(label).clicked() {
+                state.stop_recording();
+            }
+        }
+        None => {
+            // Only one unsaved recording at a time keeps the save prompt unambiguous
+            let can_start = state.finished_recording.is_none();
+            if ui
+                .add_enabled(can_start, egui::Button::new("⏺ Record track"))
+                .on_disabled_hover_text("Save or discard the previous recording first")
+                .clicked()
+            {
+                state.start_recording();
+            }
+        }
     }
-
-    ui.add_space(12.0);
-    ui.separator();
-    ui.add_space(8.0);
-
-    // About section
-    ui.label(RichText::new("ℹ About").strong());
-    ui.add_space(4.0);
-    ui.label(RichText::new("Large Track Viewer").small());
-    ui.label(
-        RichText::new("Efficiently view large GPS tracks with LOD")
-            .small()
-            .weak(),
-    );
-    ui.add_space(4.0);
-    ui.label(RichText::new("Keyboard shortcuts:").small());
-    ui.label(RichText::new("  F1 / Ctrl+H - Toggle help").small().weak());
-    ui.label(RichText::new("  Ctrl + Scroll - Zoom map").small().weak());
 }
 
 /// Cross-platform file picker integration.
@@ -545,11 +2374,12 @@ pub fn manage_pending_files(state: &mut AppState) {
 }
 
 /// Help overlay
-pub fn help_overlay(ctx: &egui::Context, show_help: &mut bool) {
+pub fn help_overlay(ctx: &egui::Context, show_help: &mut bool, shortcuts: &Shortcuts) {
     egui::Window::new("Help")
         .collapsible(false)
         .resizable(false)
         .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(overlay::DIALOG_ORDER)
         .show(ctx, |ui| {
             ui.heading("Large Track Viewer");
             ui.add_space(8.0);
@@ -563,13 +2393,18 @@ pub fn help_overlay(ctx: &egui::Context, show_help: &mut bool) {
             ui.add_space(8.0);
 
             ui.label(RichText::new("Navigation").strong());
-            ui.label("• Ctrl + Scroll wheel to zoom");
+            ui.label(format!(
+                "• {} wheel to zoom",
+                shortcuts.scroll_zoom_text(ctx)
+            ));
             ui.label("• Click and drag to pan");
-            ui.label("• 'Fit to Bounds' to see all tracks");
+            ui.label("• '🎯 Fit' to see all tracks");
             ui.add_space(8.0);
 
             ui.label(RichText::new("Keyboard Shortcuts").strong());
-            ui.label("• F1 or Ctrl+H - Toggle this help");
+            for line in shortcuts.help_lines(ctx) {
+                ui.label(format!("• {}", line));
+            }
             ui.add_space(12.0);
 
             if ui.button("Close").clicked() {
@@ -578,6 +2413,259 @@ pub fn help_overlay(ctx: &egui::Context, show_help: &mut bool) {
         });
 }
 
+/// Offer to resume an import interrupted by an unclean shutdown
+pub fn resume_import_prompt(ctx: &egui::Context, state: &mut AppState) {
+    egui::Window::new("Resume import?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(overlay::DIALOG_ORDER)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "The previous session ended unexpectedly while {} file(s) were still loading.",
+                state.interrupted_import.len()
+            ));
+            ui.add_space(4.0);
+
+            egui::ScrollArea::vertical()
+                .id_salt("interrupted_import_scroll")
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    for path in &state.interrupted_import {
+                        ui.label(
+                            RichText::new(format!(
+                                "• {}",
+                                path.file_name().unwrap_or_default().to_string_lossy()
+                            ))
+                            .small(),
+                        );
+                    }
+                });
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if ui.button("▶ Resume").clicked() {
+                    state.resume_interrupted_import();
+                }
+                if ui.button("Discard").clicked() {
+                    state.discard_interrupted_import();
+                }
+            });
+        });
+}
+
+/// Ask before loading a batch that would take the number of tracks over the limit
+pub fn route_limit_prompt(ctx: &egui::Context, state: &mut AppState) {
+    let Some(warning) = &state.route_limit_warning else {
+        return;
+    };
+    let max_routes = state.max_routes;
+    let fitting = warning.files_within(max_routes);
+    let file_count = warning.files.len();
+
+    let mut choice: Option<Option<usize>> = None;
+    let mut cancel = false;
+    egui::Window::new("Load that many files?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(overlay::DIALOG_ORDER)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Loading {} more file(s) next to the {} track(s) already loaded or loading \
+                 goes over the limit of {} tracks.",
+                file_count, warning.loaded, max_routes
+            ));
+            ui.add_space(4.0);
+            ui.label(
+                RichText::new(
+                    "This usually means a folder with far more files than intended was opened. \
+                     The limit can be changed with --max-routes.",
+                )
+                .small()
+                .weak(),
+            );
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if fitting > 0 && ui.button(format!("Load first {}", fitting)).clicked() {
+                    choice = Some(Some(fitting));
+                }
+                if ui.button("⚠ Load all").clicked() {
+                    choice = Some(None);
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if let Some(count) = choice {
+        state.confirm_route_limit_warning(count);
+    } else if cancel {
+        state.cancel_route_limit_warning();
+    }
+}
+
+/// Ask before loading a batch that would exceed the memory budget
+pub fn memory_warning_prompt(ctx: &egui::Context, state: &mut AppState) {
+    let Some(warning) = &state.memory_warning else {
+        return;
+    };
+    let budget = state.memory_budget_bytes;
+    let fitting = warning.files_within(budget);
+    let file_count = warning.files.len();
+
+    let mut choice: Option<Option<usize>> = None;
+    let mut cancel = false;
+    egui::Window::new("Not enough memory?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(overlay::DIALOG_ORDER)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Loading {} file(s) needs an estimated {} of memory. With {} already loaded, \
+                 that exceeds the budget of {}.",
+                file_count,
+                format_bytes(warning.estimated_bytes),
+                format_bytes(warning.loaded_bytes),
+                format_bytes(budget)
+            ));
+            ui.add_space(4.0);
+            ui.label(
+                RichText::new(
+                    "Loading anyway may make the system swap or close the app. Simplifying the \
+                     files before importing them, or loading only some of them, avoids that.",
+                )
+                .small()
+                .weak(),
+            );
+
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if fitting > 0
+                    && fitting < file_count
+                    && ui.button(format!("Load first {}", fitting)).clicked()
+                {
+                    choice = Some(Some(fitting));
+                }
+                if ui.button("⚠ Load anyway").clicked() {
+                    choice = Some(None);
+                }
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        });
+
+    if let Some(count) = choice {
+        state.confirm_memory_warning(count);
+    } else if cancel {
+        state.cancel_memory_warning();
+    }
+}
+
+/// How often the recording prompt checks on its open save dialog
+const SAVE_DIALOG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Offer to save a finished recording as GPX. Returns true once it was saved or discarded.
+pub fn recording_save_prompt(ctx: &egui::Context, state: &mut AppState) -> bool {
+    let Some(path) = state.finished_recording.clone() else {
+        return false;
+    };
+    let Some((_, gpx, _, _)) = state
+        .file_loader
+        .loaded_files
+        .iter()
+        .find(|(loaded, _, _, _)| *loaded == path)
+    else {
+        // The entry was removed from the track list; nothing left to save
+        state.finished_recording = None;
+        return true;
+    };
+
+    // Only a confirmed write lets the prompt go; a cancelled or failed save leaves it up
+    let mut saved = false;
+    let mut saving = false;
+    let mut error: Option<String> = None;
+    if let Some(receiver) = state.recording_save.as_mut() {
+        match receiver.try_recv() {
+            Ok(SaveOutcome::Saved) => saved = true,
+            Ok(SaveOutcome::Cancelled) => {}
+            Ok(SaveOutcome::Failed(e)) => {
+                error = Some(format!("Could not save the recording: {}", e));
+            }
+            Err(TryRecvError::Empty) => {
+                saving = true;
+                // Nothing else wakes the UI when the dialog closes
+                ctx.request_repaint_after(SAVE_DIALOG_POLL_INTERVAL);
+            }
+            Err(TryRecvError::Closed) => {
+                error = Some("The save dialog closed without saving the recording".to_string());
+            }
+        }
+        if !saving {
+            state.recording_save = None;
+        }
+    }
+
+    let name = path
+        .to_string_lossy()
+        .trim_start_matches(crate::app::recorder::RECORDING_PATH_PREFIX)
+        .to_string();
+    let mut discarded = false;
+    let mut save = None;
+    egui::Window::new("Save recording?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .order(overlay::DIALOG_ORDER)
+        .show(ctx, |ui| {
+            ui.label(format!("\"{}\" is only kept in memory until saved.", name));
+            ui.add_space(8.0);
+            ui.add_enabled_ui(!saving, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("💾 Save GPX…").clicked() {
+                        match crate::app::recorder::to_gpx_string(gpx) {
+                            Ok(text) => {
+                                let file_name = format!("{}.gpx", name.replace([' ', ':'], "_"));
+                                save = Some(eframe_entrypoints::file_picker::save_native_file(
+                                    &file_name,
+                                    text.into_bytes(),
+                                ));
+                            }
+                            Err(e) => {
+                                tracing::error!("Failed to serialize recording: {}", e);
+                                error = Some(format!("Could not save the recording: {}", e));
+                            }
+                        }
+                    }
+                    if ui.button("Discard").clicked() {
+                        discarded = true;
+                    }
+                });
+            });
+            if saving {
+                ui.label(RichText::new("Waiting for the save dialog…").weak());
+            }
+        });
+
+    if let Some(error) = error {
+        state.notify_error(error);
+    }
+    if save.is_some() {
+        state.recording_save = save;
+    }
+    if discarded {
+        state.discard_recording();
+    } else if saved {
+        // The saved track stays on the map; only the prompt goes away
+        state.finished_recording = None;
+    }
+    saved || discarded
+}
+
 /// Handle drag and drop of GPX files
 pub fn handle_drag_and_drop(ctx: &egui::Context, state: &mut AppState) {
     // Only read input state inside ctx.input
@@ -586,20 +2674,19 @@ pub fn handle_drag_and_drop(ctx: &egui::Context, state: &mut AppState) {
 
     // Show drop preview if files are hovered
     if hovered_files {
-        let painter = ctx.layer_painter(egui::LayerId::new(
-            egui::Order::Foreground,
-            egui::Id::new("drop_preview"),
-        ));
-        let screen_rect = ctx.content_rect();
+        let map_rect = overlay::map_rect(ctx, state.map_rect);
+        let painter = ctx
+            .layer_painter(overlay::layer("drop_preview"))
+            .with_clip_rect(map_rect);
         let bg_size = egui::vec2(340.0, 80.0);
-        let bg_rect = egui::Rect::from_center_size(screen_rect.center(), bg_size);
+        let bg_rect = egui::Rect::from_center_size(map_rect.center(), bg_size);
         painter.rect_filled(
             bg_rect,
             16.0, // rounding
             egui::Color32::from_black_alpha(180),
         );
         painter.text(
-            screen_rect.center(),
+            map_rect.center(),
             egui::Align2::CENTER_CENTER,
             "📂 Drop GPX files here",
             egui::FontId::proportional(32.0),
@@ -619,7 +2706,17 @@ pub fn handle_drag_and_drop(ctx: &egui::Context, state: &mut AppState) {
             .map(|s| s.eq_ignore_ascii_case("gpx"))
             .unwrap_or(false);
 
-        if is_gpx {
+        if !is_gpx {
+            state.notify_warn(format!(
+                "Skipped {}: only .gpx files are supported",
+                dropped_file
+                    .path
+                    .as_ref()
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| dropped_file.name.clone())
+            ));
+        } else {
             let filename = dropped_file
                 .path
                 .as_ref()
@@ -637,33 +2734,457 @@ pub fn handle_drag_and_drop(ctx: &egui::Context, state: &mut AppState) {
     }
 }
 
-/// Show mouse wheel zoom warning
-pub fn show_wheel_zoom_warning(ui: &mut Ui, state: &mut AppState) {
-    let alpha = state.get_wheel_warning_alpha();
-    if alpha <= 0.0 {
-        return;
+/// Latitude and longitude of a map position, with the longitude wrapped to [-180, 180)
+///
+/// Panning across the antimeridian keeps increasing the longitude, so the
+/// raw map center can be far outside the usual range.
+fn normalized_lat_lon(position: walkers::Position) -> (f64, f64) {
+    let lon = position.x();
+    // Shifting a longitude already in range would only add rounding error
+    let lon = if (-180.0..180.0).contains(&lon) {
+        lon
+    } else {
+        (lon + 180.0).rem_euclid(360.0) - 180.0
+    };
+    (position.y().clamp(-90.0, 90.0), lon)
+}
+
+/// A map position written in `format`, with the longitude wrapped to [-180, 180)
+pub fn format_position(position: walkers::Position, format: CoordFormat) -> String {
+    let (lat, lon) = normalized_lat_lon(position);
+    coords::format_coord(lat, lon, format)
+}
+
+/// Crosshair at the center of the map with the center's coordinate below it
+pub fn center_crosshair(ui: &mut Ui, center: walkers::Position, format: CoordFormat) {
+    let painter = ui.painter();
+    let at = ui.max_rect().center();
+    let arm = 10.0;
+    for stroke in [
+        egui::Stroke::new(3.0, Color32::from_white_alpha(200)),
+        egui::Stroke::new(1.0, Color32::BLACK),
+    ] {
+        painter.line_segment(
+            [at - egui::vec2(arm, 0.0), at + egui::vec2(arm, 0.0)],
+            stroke,
+        );
+        painter.line_segment(
+            [at - egui::vec2(0.0, arm), at + egui::vec2(0.0, arm)],
+            stroke,
+        );
     }
 
-    let rect = ui.max_rect();
-    let warning_size = egui::vec2(280.0, 50.0);
-    let warning_pos = rect.center() - warning_size / 2.0;
-    let warning_rect = egui::Rect::from_min_size(warning_pos, warning_size);
+    let text = format_position(center, format);
+    let galley = painter.layout_no_wrap(text, egui::FontId::monospace(11.0), Color32::BLACK);
+    let text_rect =
+        egui::Rect::from_center_size(at + egui::vec2(0.0, arm + 12.0), galley.size()).expand(3.0);
+    painter.rect_filled(text_rect, 3.0, Color32::from_white_alpha(200));
+    painter.galley(text_rect.min + egui::vec2(3.0, 3.0), galley, Color32::BLACK);
+}
 
-    // Background with fade
-    let bg_alpha = (180.0 * alpha) as u8;
-    ui.painter().rect_filled(
-        warning_rect,
-        10.0,
-        egui::Color32::from_black_alpha(bg_alpha),
+/// Small note at the top of the map while tracks are hidden, so loaded data doesn't look lost
+pub fn tracks_hidden_indicator(ui: &mut Ui, state: &AppState) {
+    let keys = state
+        .shortcuts
+        .keys_text(ui.ctx(), ShortcutAction::ToggleTracks);
+    let rect = ui.max_rect();
+    let galley = ui.painter().layout_no_wrap(
+        format!("Tracks hidden - press {} to show", keys),
+        egui::FontId::proportional(12.0),
+        egui::Color32::from_white_alpha(220),
     );
-
-    // Text with fade
-    let text_alpha = (255.0 * alpha) as u8;
-    ui.painter().text(
-        warning_rect.center(),
-        egui::Align2::CENTER_CENTER,
-        "Hold Ctrl + Scroll to zoom",
-        egui::FontId::proportional(16.0),
-        egui::Color32::from_white_alpha(text_alpha),
+    let pill = egui::Rect::from_center_size(
+        rect.center_top() + egui::vec2(0.0, 8.0 + galley.size().y / 2.0 + 4.0),
+        galley.size() + egui::vec2(16.0, 8.0),
     );
+    ui.painter()
+        .rect_filled(pill, 8.0, egui::Color32::from_black_alpha(140));
+    ui.painter().galley(
+        pill.center() - galley.size() / 2.0,
+        galley,
+        egui::Color32::WHITE,
+    );
+}
+
+/// Most tracks listed in the map legend; the rest are counted
+const LEGEND_MAX_ENTRIES: usize = 20;
+
+/// Legend in the top-left corner of the map with the color and name of each track in view
+///
+/// Pointing at an entry highlights its track on the map and clicking it
+/// selects the track.
+pub fn track_legend(ui: &mut Ui, state: &mut AppState) {
+    let names = state.visible_route_names();
+    if names.is_empty() {
+        state.legend_hovered = None;
+        return;
+    }
+    let colors = state.route_color_overrides();
+    let selected = state
+        .selected_route
+        .try_read()
+        .ok()
+        .and_then(|guard| *guard);
+    let mut hovered = None;
+    let mut clicked = None;
+    overlay::area(
+        "track_legend",
+        ui.max_rect(),
+        egui::Align2::LEFT_TOP,
+        egui::vec2(8.0, 8.0),
+    )
+    .show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            ui.set_max_width(220.0);
+            for (route_index, name) in names.iter().take(LEGEND_MAX_ENTRIES) {
+                ui.horizontal(|ui| {
+                    let color = colors
+                        .get(route_index)
+                        .copied()
+                        .unwrap_or_else(|| route_color(*route_index));
+                    let (swatch, _) =
+                        ui.allocate_exact_size(egui::vec2(14.0, 4.0), egui::Sense::hover());
+                    ui.painter().rect_filled(swatch, 1.0, color);
+                    let response = ui.selectable_label(Some(*route_index) == selected, name);
+                    if response.hovered() {
+                        hovered = Some(*route_index);
+                    }
+                    if response.clicked() {
+                        clicked = Some(*route_index);
+                    }
+                });
+            }
+            if names.len() > LEGEND_MAX_ENTRIES {
+                ui.label(
+                    RichText::new(format!("and {} more", names.len() - LEGEND_MAX_ENTRIES))
+                        .small()
+                        .weak(),
+                );
+            }
+        });
+    });
+    if let Some(route_index) = clicked {
+        state.select_route(route_index);
+    }
+    if hovered != state.legend_hovered {
+        state.legend_hovered = hovered;
+        ui.ctx().request_repaint();
+    }
+}
+
+/// Non-modal banner over the map offering the fallback for a provider that fails
+pub fn tile_failure_banner(ui: &mut Ui, state: &mut AppState) {
+    let Some((provider, error)) = state.tile_failure.clone() else {
+        return;
+    };
+    let mut switch_to = None;
+    let mut dismissed = false;
+    overlay::area(
+        "tile_failure_banner",
+        ui.max_rect(),
+        egui::Align2::CENTER_TOP,
+        egui::vec2(0.0, 8.0),
+    )
+    .show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(
+                    RichText::new(format!("⚠ {} tiles are not loading", provider.name()))
+                        .color(ui.visuals().warn_fg_color),
+                )
+                .on_hover_text(&error);
+                if let Some(fallback) = provider.fallback()
+                    && ui
+                        .button(format!("Switch to {}", fallback.name()))
+                        .clicked()
+                {
+                    switch_to = Some(fallback);
+                }
+                if ui.button("Dismiss").clicked() {
+                    dismissed = true;
+                }
+            });
+        });
+    });
+    if let Some(fallback) = switch_to {
+        state.select_tiles_provider(fallback);
+    } else if dismissed {
+        state.tile_failure = None;
+    }
+}
+
+/// Stack the current notifications in the bottom-right corner of the map, newest at the bottom
+///
+/// Clicking a toast's text dismisses it early; its button, if any, runs the
+/// action and dismisses it too.
+pub fn render_notifications(ctx: &egui::Context, state: &mut AppState) {
+    let now = instant::Instant::now();
+    state.notifications.expire(now);
+    let animate = motion::animations_enabled(&state.ui_settings);
+    let Some(repaint_after) = state.notifications.repaint_after(now, animate) else {
+        return;
+    };
+    state.activity.keep_awake("notifications", repaint_after);
+
+    let mut dismissed = None;
+    let mut triggered = false;
+    overlay::area(
+        "notifications",
+        overlay::map_rect(ctx, state.map_rect),
+        egui::Align2::RIGHT_BOTTOM,
+        egui::vec2(-12.0, -28.0),
+    )
+    .show(ctx, |ui| {
+        ui.set_max_width(320.0);
+        for (i, notification) in state.notifications.iter().enumerate() {
+            let color = match notification.level {
+                NotificationLevel::Info => ui.visuals().text_color(),
+                NotificationLevel::Warn => ui.visuals().warn_fg_color,
+                NotificationLevel::Error => ui.visuals().error_fg_color,
+            };
+            ui.scope(|ui| {
+                ui.set_opacity(notification.alpha(now, animate));
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let text = RichText::new(format!(
+                            "{} {}",
+                            notification.level.icon(),
+                            notification.text
+                        ))
+                        .color(color);
+                        if ui
+                            .add(egui::Label::new(text).sense(egui::Sense::click()))
+                            .on_hover_text("Click to dismiss")
+                            .clicked()
+                        {
+                            dismissed = Some(i);
+                        }
+                        if let Some(action) = &notification.action
+                            && ui.small_button(action.label()).clicked()
+                        {
+                            dismissed = Some(i);
+                            triggered = true;
+                        }
+                    });
+                });
+            });
+        }
+    });
+    if let Some(notification) = dismissed.and_then(|i| state.notifications.dismiss(i))
+        && triggered
+        && let Some(action) = notification.action
+    {
+        state.run_notification_action(action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::settings::Settings;
+    use clap::Parser;
+    use std::path::PathBuf;
+
+    #[test]
+    fn elapsed_time_is_short() {
+        use std::time::Duration;
+        assert_eq!(format_elapsed(Duration::from_millis(4500)), "4 s");
+        assert_eq!(format_elapsed(Duration::from_secs(150)), "2 min");
+        assert_eq!(format_elapsed(Duration::from_secs(7300)), "2 h");
+    }
+
+    #[test]
+    fn center_readout_wraps_longitude() {
+        let (lat, lon) = normalized_lat_lon(walkers::lat_lon(51.5, -0.12));
+        assert_eq!((lat, lon), (51.5, -0.12));
+
+        // Two full turns east of 10°E is still 10°E
+        let (_, lon) = normalized_lat_lon(walkers::lat_lon(0.0, 730.0));
+        assert!((lon - 10.0).abs() < 1e-9);
+        let (_, lon) = normalized_lat_lon(walkers::lat_lon(0.0, -190.0));
+        assert!((lon - 170.0).abs() < 1e-9);
+        let (_, lon) = normalized_lat_lon(walkers::lat_lon(0.0, 180.0));
+        assert_eq!(lon, -180.0);
+    }
+
+    #[test]
+    fn containing_folder_is_the_parent_directory() {
+        let path = PathBuf::from("/home/user/tracks/ride.gpx");
+        assert_eq!(
+            containing_folder(&path),
+            Some(Path::new("/home/user/tracks"))
+        );
+
+        // Files without a folder on disk
+        assert_eq!(containing_folder(Path::new("ride.gpx")), None);
+        assert_eq!(
+            containing_folder(Path::new("web://ride.gpx-1a2b3c4d")),
+            None
+        );
+        assert_eq!(
+            containing_folder(Path::new("recording://Recording 2024-05-01 10:00")),
+            None
+        );
+    }
+
+    #[test]
+    fn recording_prompt_stays_until_the_save_is_confirmed() {
+        use eframe_entrypoints::async_runtime::oneshot;
+        let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+        let path = PathBuf::from(format!(
+            "{}Morning ride",
+            crate::app::recorder::RECORDING_PATH_PREFIX
+        ));
+        let gpx = gpx::read(
+            br#"<gpx version="1.1" creator="test"><trk><trkseg>
+            <trkpt lat="45.0" lon="6.0"/><trkpt lat="45.1" lon="6.1"/>
+            </trkseg></trk></gpx>"#
+                .as_slice(),
+        )
+        .unwrap();
+        state.index_recording(path.clone(), gpx);
+        state.finished_recording = Some(path.clone());
+        let ctx = egui::Context::default();
+        let prompt = |state: &mut AppState| {
+            let mut done = false;
+            let _ = ctx.run(Default::default(), |ctx| {
+                done = recording_save_prompt(ctx, state);
+            });
+            done
+        };
+        let errors = |state: &AppState| {
+            state
+                .notifications
+                .iter()
+                .filter(|notification| notification.level == NotificationLevel::Error)
+                .count()
+        };
+
+        // A closed or failed dialog leaves the prompt up to try again
+        for (outcome, failed) in [
+            (SaveOutcome::Cancelled, 0),
+            (SaveOutcome::Failed("disk full".to_string()), 1),
+        ] {
+            let (sender, receiver) = oneshot::channel();
+            state.recording_save = Some(receiver);
+            assert!(!prompt(&mut state));
+            assert!(state.recording_save.is_some(), "the dialog is still open");
+            sender.send(outcome).unwrap();
+            assert!(!prompt(&mut state));
+            assert!(state.recording_save.is_none());
+            assert_eq!(state.finished_recording, Some(path.clone()));
+            assert_eq!(errors(&state), failed);
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        state.recording_save = Some(receiver);
+        sender.send(SaveOutcome::Saved).unwrap();
+        assert!(prompt(&mut state));
+        assert_eq!(state.finished_recording, None);
+        // The saved track stays on the map
+        assert_eq!(state.file_loader.loaded_files.len(), 1);
+    }
+
+    #[test]
+    fn settings_say_when_local_time_is_not_available() {
+        use egui_kittest::kittest::Queryable;
+        for (offset, shown) in [(None, true), (Some(time::UtcOffset::UTC), false)] {
+            let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+            state.ui_settings.time_zone = TimeZone::Local;
+            state.local_offset = offset;
+            let mut harness = egui_kittest::Harness::new_ui_state(render_settings_tab, state);
+            harness.run();
+            let note = harness.query_by_label_contains("Local time isn't available");
+            assert_eq!(note.is_some(), shown);
+        }
+    }
+
+    #[test]
+    fn presses_on_the_sidebar_toggle_never_reach_the_map() {
+        let size = egui::vec2(1200.0, 800.0);
+        // The button's center, 10 points in from the top right corner
+        let button = egui::pos2(size.x - 30.0, 30.0);
+        let press = |pressed| egui::Event::PointerButton {
+            pos: button,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: Default::default(),
+        };
+        let gestures = [
+            // A click toggles the sidebar
+            vec![press(true), press(false)],
+            // A press that moves before it is released is no click, and no pan either
+            vec![
+                press(true),
+                egui::Event::PointerMoved(button + egui::vec2(30.0, 30.0)),
+                egui::Event::PointerMoved(button + egui::vec2(60.0, 60.0)),
+                press(false),
+            ],
+        ];
+        for (gesture, toggles) in gestures.into_iter().zip([true, false]) {
+            let ctx = egui::Context::default();
+            let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+            state.ui_settings.sidebar_open = false;
+            let mut map_used = false;
+            // A first frame lays the map and the button out, then one event per frame
+            let frames = std::iter::once(egui::Event::PointerMoved(button)).chain(gesture);
+            for event in frames {
+                let input = egui::RawInput {
+                    screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size)),
+                    events: vec![event],
+                    ..Default::default()
+                };
+                let _ = ctx.run(input, |ctx| {
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::NONE)
+                        .show(ctx, |ui| {
+                            // Stands in for the map, which senses the same
+                            let map =
+                                ui.allocate_rect(ui.max_rect(), egui::Sense::click_and_drag());
+                            map_used |= map.clicked() || map.drag_started() || map.dragged();
+                            sidebar_toggle_button(ui, &mut state);
+                        });
+                });
+            }
+            assert_eq!(state.ui_settings.sidebar_open, toggles);
+            assert!(!map_used, "the map saw a press on the button");
+        }
+    }
+
+    #[test]
+    fn wheel_warning_stays_inside_the_map_in_both_sidebar_layouts() {
+        // Landscape puts the sidebar on the right, portrait at the bottom
+        for size in [egui::vec2(1200.0, 800.0), egui::vec2(600.0, 1000.0)] {
+            let ctx = egui::Context::default();
+            let mut state = AppState::new(&Settings::parse_from(["large-track-viewer"]));
+            state.ui_settings.sidebar_open = true;
+            state.show_wheel_zoom_warning("Ctrl");
+            // Areas are sized on their first frame and placed on the next
+            for _ in 0..3 {
+                let input = egui::RawInput {
+                    screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size)),
+                    ..Default::default()
+                };
+                let _ = ctx.run(input, |ctx| {
+                    render_sidebar(ctx, &mut state);
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::NONE)
+                        .show(ctx, |ui| state.map_rect = Some(ui.max_rect()));
+                    render_notifications(ctx, &mut state);
+                });
+            }
+
+            let map_rect = state.map_rect.unwrap();
+            assert!(map_rect.area() < size.x * size.y, "sidebar shown");
+            let warning = ctx
+                .memory(|memory| memory.area_rect(egui::Id::new("notifications")))
+                .expect("the warning is shown");
+            assert!(
+                map_rect.contains_rect(warning),
+                "{:?} outside the map {:?}",
+                warning,
+                map_rect
+            );
+        }
+    }
 }
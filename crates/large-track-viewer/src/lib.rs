@@ -5,7 +5,7 @@
 
 mod app;
 
-pub use app::LargeTrackViewerApp;
+pub use app::{LargeTrackViewerApp, before_start};
 
 // Define all platform entry points using the unified macro
 eframe_entrypoints::eframe_app_lib!("Large Track Viewer", |cc| Box::new(
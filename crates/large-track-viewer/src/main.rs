@@ -4,6 +4,8 @@ mod app;
 
 pub use app::LargeTrackViewerApp;
 
-eframe_entrypoints::eframe_app_main!("Large Track Viewer", |cc| Box::new(
-    LargeTrackViewerApp::new(cc)
-));
+eframe_entrypoints::eframe_app_main!(
+    "Large Track Viewer",
+    |cc| Box::new(LargeTrackViewerApp::new(cc)),
+    before_start: app::before_start
+);